@@ -0,0 +1,38 @@
+use std::{path::PathBuf, process::Command};
+
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+fn main() {
+    let describe = git(&["describe", "--always", "--dirty", "--tags"])
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_DESCRIBE={describe}");
+
+    // HEAD only changes on checkout; a commit rewrites the branch's ref file (or
+    // packed-refs after `git gc`), and the index covers --dirty.
+    let Some(git_dir) = git(&["rev-parse", "--git-dir"]).map(PathBuf::from) else {
+        return;
+    };
+    let head = git_dir.join("HEAD");
+    println!("cargo:rerun-if-changed={}", head.display());
+    println!("cargo:rerun-if-changed={}", git_dir.join("index").display());
+    if let Some(branch) = std::fs::read_to_string(&head)
+        .ok()
+        .and_then(|s| s.trim().strip_prefix("ref: ").map(str::to_string))
+    {
+        let ref_file = git_dir.join(branch);
+        if ref_file.exists() {
+            println!("cargo:rerun-if-changed={}", ref_file.display());
+        }
+    }
+    let packed = git_dir.join("packed-refs");
+    if packed.exists() {
+        println!("cargo:rerun-if-changed={}", packed.display());
+    }
+}
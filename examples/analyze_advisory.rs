@@ -0,0 +1,92 @@
+// Strict lags for one advisory through the library API, printed as JSON.
+//
+//   cargo run --example analyze_advisory -- <db-dump dir> <RUSTSEC-*.md or .toml>
+//
+// Without arguments it writes a three-crate dump to a temp directory and analyzes a
+// built-in advisory against it.
+
+use std::{env, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use time_to_fix_cve::advisory::{Advisory, parse_advisory};
+use time_to_fix_cve::dump::DumpStore;
+use time_to_fix_cve::pipeline::{AnalysisOptions, LagMode, StoreSource, analyze_advisory};
+
+const DEMO_ADVISORY: &str = r#"
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+aliases = ["CVE-0000-0001"]
+
+[versions]
+patched = [">= 0.2.0"]
+"#;
+
+const DEMO_CRATES: &str = "id,name,downloads
+1,vulnlib,5000
+2,app_a,300
+3,app_b,200
+";
+
+const DEMO_VERSIONS: &str = "id,crate_id,num,created_at
+10,1,0.1.0,2020-01-01 00:00:00
+11,1,0.1.1,2020-02-01 00:00:00
+12,1,0.2.0,2020-03-01 00:00:00
+20,2,1.0.0,2020-01-15 00:00:00
+21,2,1.1.0,2020-03-11 00:00:00
+30,3,0.1.0,2020-01-20 00:00:00
+31,3,0.2.0,2020-04-01 00:00:00
+";
+
+const DEMO_DEPENDENCIES: &str = "version_id,crate_id,req,kind
+20,1,^0.1,0
+21,1,^0.2,0
+30,1,=0.1.1,0
+31,1,^0.2.0,0
+";
+
+fn demo_dump() -> Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("rq2_demo_dump_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("crates.csv"), DEMO_CRATES)?;
+    std::fs::write(dir.join("versions.csv"), DEMO_VERSIONS)?;
+    std::fs::write(dir.join("dependencies.csv"), DEMO_DEPENDENCIES)?;
+    Ok(dir)
+}
+
+// Advisory files in the RustSec repository keep their TOML in a ```toml fence.
+fn load_advisory(text: &str) -> Result<Advisory> {
+    let toml_text = match text.split_once("```toml") {
+        Some((_, rest)) => rest.split("```").next().unwrap_or(rest),
+        None => text,
+    };
+    parse_advisory(&toml::from_str(toml_text)?)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (dump_dir, advisory) = match args.as_slice() {
+        [] => (demo_dump()?, load_advisory(DEMO_ADVISORY)?),
+        [dir, file] => {
+            let text = std::fs::read_to_string(file).with_context(|| format!("read {file}"))?;
+            (PathBuf::from(dir), load_advisory(&text)?)
+        }
+        _ => {
+            return Err(anyhow!(
+                "usage: analyze_advisory [<dump dir> <advisory file>]"
+            ));
+        }
+    };
+
+    let store = DumpStore::load(&dump_dir)?;
+    let opts = AnalysisOptions {
+        lag_mode: LagMode::Both,
+        ..AnalysisOptions::default()
+    };
+    let mut source = StoreSource::new(&store, opts.renames);
+    let result = analyze_advisory(&mut source, &advisory, &opts).await?;
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
@@ -0,0 +1,1178 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use reqwest::Client;
+use semver::{Op, Version, VersionReq};
+use zip::ZipArchive;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrereleasePolicy {
+    Include,
+    Exclude,
+    MatchSemver,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InformationalKind {
+    None,
+    Unmaintained,
+    Unsound,
+    Notice,
+}
+
+impl InformationalKind {
+    pub const ALL: [InformationalKind; 4] = [
+        InformationalKind::None,
+        InformationalKind::Unmaintained,
+        InformationalKind::Unsound,
+        InformationalKind::Notice,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InformationalKind::None => "none",
+            InformationalKind::Unmaintained => "unmaintained",
+            InformationalKind::Unsound => "unsound",
+            InformationalKind::Notice => "notice",
+        }
+    }
+}
+
+// Where an advisory's severity came from: its `severity` field, a CVSS vector, the
+// `informational` marker, a --severity-overrides row, or nothing at all.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SeveritySource {
+    Advisory,
+    Cvss,
+    Informational,
+    Override,
+    Unknown,
+}
+
+impl SeveritySource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SeveritySource::Advisory => "advisory",
+            SeveritySource::Cvss => "cvss",
+            SeveritySource::Informational => "informational",
+            SeveritySource::Override => "override",
+            SeveritySource::Unknown => "unknown",
+        }
+    }
+}
+
+// `Default` drops unmaintained crates and notices but keeps unsound advisories, which
+// describe real (if not always exploitable) bugs with fixes to adopt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InformationalPolicy {
+    Default,
+    Include,
+    Exclude,
+    Only,
+}
+
+impl InformationalPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InformationalPolicy::Default => "default",
+            InformationalPolicy::Include => "include",
+            InformationalPolicy::Exclude => "exclude",
+            InformationalPolicy::Only => "only",
+        }
+    }
+
+    pub fn keeps(self, kind: InformationalKind) -> bool {
+        match self {
+            InformationalPolicy::Default => {
+                matches!(kind, InformationalKind::None | InformationalKind::Unsound)
+            }
+            InformationalPolicy::Include => true,
+            InformationalPolicy::Exclude => kind == InformationalKind::None,
+            InformationalPolicy::Only => kind != InformationalKind::None,
+        }
+    }
+}
+
+pub struct Advisory {
+    pub rustsec_id: String,
+    pub cve_id: String,
+    // True when the advisory has no CVE alias and `cve_id` holds the RustSec id.
+    pub cve_is_fallback: bool,
+    pub aliases: Vec<String>,
+    pub related: Vec<String>,
+    // Smallest RustSec id among advisories linked to this one by aliases or `related`;
+    // equal to `rustsec_id` for advisories that stand alone.
+    pub group_id: String,
+    // Set by `dedupe_by_cve` under `KeepAll`: the earlier advisory this one repeats a CVE of.
+    pub duplicate_of: Option<String>,
+    pub affected_functions: Vec<String>,
+    // RustSec's `keywords` / `categories` as written; the taxonomy is not normalized.
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub informational: InformationalKind,
+    pub severity: String,
+    pub severity_source: SeveritySource,
+    pub cvss: Option<String>,
+    pub package: String,
+    pub date: Option<NaiveDate>,
+    pub withdrawn: bool,
+    pub withdrawn_date: Option<NaiveDate>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+}
+
+impl Advisory {
+    pub fn ghsa_id(&self) -> Option<&str> {
+        self.aliases
+            .iter()
+            .map(|s| s.as_str())
+            .find(|s| s.starts_with("GHSA-"))
+    }
+
+    pub fn function_scoped(&self) -> bool {
+        !self.affected_functions.is_empty()
+    }
+
+    // Every CVE alias, upper-cased; `cve_id` only keeps the first.
+    pub fn cve_ids(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .aliases
+            .iter()
+            .map(|s| normalize_cve_id(s))
+            .filter(|s| s.starts_with("CVE-"))
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+}
+
+pub fn normalize_cve_id(s: &str) -> String {
+    s.trim().to_ascii_uppercase()
+}
+
+// CVE -> RustSec ids of the advisories listing it as an alias, ids sorted.
+pub fn cve_index(advisories: &[Advisory]) -> BTreeMap<String, Vec<String>> {
+    let mut index: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for adv in advisories {
+        for cve in adv.cve_ids() {
+            index.entry(cve).or_default().push(adv.rustsec_id.clone());
+        }
+    }
+    for ids in index.values_mut() {
+        ids.sort();
+        ids.dedup();
+    }
+    index
+}
+
+// Which advisory survives when several RustSec entries carry the same CVE. `KeepAll`
+// keeps every one and only records the duplicates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CveDedupe {
+    PreferEarliest,
+    PreferLatest,
+    KeepAll,
+}
+
+impl CveDedupe {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CveDedupe::PreferEarliest => "prefer-earliest",
+            CveDedupe::PreferLatest => "prefer-latest",
+            CveDedupe::KeepAll => "keep-all",
+        }
+    }
+}
+
+pub struct CveDuplicate {
+    pub rustsec_id: String,
+    pub duplicate_of: String,
+    pub cve: String,
+}
+
+// Advisories are visited in RustSec id order (newest first for `PreferLatest`); the first
+// to name a CVE claims all of its CVEs and later ones naming any claimed CVE are
+// duplicates. Returns the duplicates by id, which are dropped unless `KeepAll` is set.
+pub fn dedupe_by_cve(advisories: &mut Vec<Advisory>, policy: CveDedupe) -> Vec<CveDuplicate> {
+    let mut order: Vec<usize> = (0..advisories.len()).collect();
+    order.sort_by(|&a, &b| advisories[a].rustsec_id.cmp(&advisories[b].rustsec_id));
+    if policy == CveDedupe::PreferLatest {
+        order.reverse();
+    }
+    let mut claimed: HashMap<String, String> = HashMap::new();
+    let mut duplicates: HashMap<usize, CveDuplicate> = HashMap::new();
+    for i in order {
+        let adv = &advisories[i];
+        let cves = adv.cve_ids();
+        match cves
+            .iter()
+            .find_map(|cve| claimed.get(cve).map(|kept| (cve, kept)))
+        {
+            Some((cve, kept)) => {
+                duplicates.insert(
+                    i,
+                    CveDuplicate {
+                        rustsec_id: adv.rustsec_id.clone(),
+                        duplicate_of: kept.clone(),
+                        cve: cve.clone(),
+                    },
+                );
+            }
+            None => {
+                for cve in cves {
+                    claimed.insert(cve, adv.rustsec_id.clone());
+                }
+            }
+        }
+    }
+
+    if policy == CveDedupe::KeepAll {
+        for (&i, d) in &duplicates {
+            advisories[i].duplicate_of = Some(d.duplicate_of.clone());
+        }
+    } else {
+        let mut i = 0;
+        advisories.retain(|_| {
+            i += 1;
+            !duplicates.contains_key(&(i - 1))
+        });
+    }
+    let mut out: Vec<CveDuplicate> = duplicates.into_values().collect();
+    out.sort_by(|a, b| a.rustsec_id.cmp(&b.rustsec_id));
+    out
+}
+
+const ADVISORY_DB_URL: &str = "https://github.com/RustSec/advisory-db/archive/refs/heads/main.zip";
+
+// Where a set of advisories came from. GitHub stores the archived commit in the zip
+// comment; the top-level directory only names the branch.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AdvisorySource {
+    pub url: String,
+    pub top_level_dir: Option<String>,
+    pub commit: Option<String>,
+    // Advisory files skipped because their front matter did not parse.
+    pub parse_error_count: usize,
+    pub parse_errors: Vec<AdvisoryParseError>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AdvisoryParseError {
+    pub file: String,
+    pub error: String,
+}
+
+pub async fn fetch_rustsec_advisories(client: &Client) -> Result<Vec<Advisory>> {
+    Ok(fetch_rustsec_advisories_with_source(client, false).await?.0)
+}
+
+// With `strict_parse` a malformed advisory file aborts the load; otherwise it is skipped
+// and listed in `AdvisorySource::parse_errors`.
+pub async fn fetch_rustsec_advisories_with_source(
+    client: &Client,
+    strict_parse: bool,
+) -> Result<(Vec<Advisory>, AdvisorySource)> {
+    let url = ADVISORY_DB_URL;
+    let bytes = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    parse_advisory_archive(&bytes, url, strict_parse)
+}
+
+pub fn parse_advisory_archive(
+    bytes: &[u8],
+    url: &str,
+    strict_parse: bool,
+) -> Result<(Vec<Advisory>, AdvisorySource)> {
+    let cursor = Cursor::new(bytes);
+    let mut zip = ZipArchive::new(cursor)?;
+    let commit = std::str::from_utf8(zip.comment())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let top_level_dir = zip
+        .file_names()
+        .filter_map(|n| n.split('/').next())
+        .find(|n| !n.is_empty())
+        .map(|n| n.to_string());
+    let mut source = AdvisorySource {
+        url: url.to_string(),
+        top_level_dir,
+        commit,
+        parse_error_count: 0,
+        parse_errors: Vec::new(),
+    };
+    let mut out = Vec::new();
+
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if !file.name().ends_with(".md") {
+            continue;
+        }
+        if !file.name().contains("/crates/") {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut s = String::new();
+        std::io::Read::read_to_string(&mut file, &mut s)?;
+        let Some(toml_str) = extract_toml_front_matter(&s) else {
+            continue;
+        };
+        let parsed = toml::from_str::<toml::Value>(toml_str)
+            .map_err(anyhow::Error::from)
+            .and_then(|val| parse_advisory(&val));
+        match parsed {
+            Ok(adv) => out.push(adv),
+            Err(e) if strict_parse => return Err(e.context(format!("cannot parse {name}"))),
+            Err(e) => source.parse_errors.push(AdvisoryParseError {
+                file: name,
+                error: format!("{e:#}")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            }),
+        }
+    }
+    source.parse_error_count = source.parse_errors.len();
+
+    out.sort_by(|a, b| a.rustsec_id.cmp(&b.rustsec_id));
+    assign_advisory_groups(&mut out);
+    Ok((out, source))
+}
+
+// Union-find over shared ids: two advisories end up in one group when one names the
+// other's RustSec id (as alias or related) or both name the same CVE/GHSA id.
+pub fn assign_advisory_groups(advisories: &mut [Advisory]) {
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent: Vec<usize> = (0..advisories.len()).collect();
+    let mut owner: HashMap<&str, usize> = HashMap::new();
+    for (i, adv) in advisories.iter().enumerate() {
+        let ids = std::iter::once(&adv.rustsec_id)
+            .chain(&adv.aliases)
+            .chain(&adv.related);
+        for id in ids {
+            match owner.get(id.as_str()) {
+                Some(&j) => {
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                    parent[a] = b;
+                }
+                None => {
+                    owner.insert(id.as_str(), i);
+                }
+            }
+        }
+    }
+
+    let mut group_ids: HashMap<usize, String> = HashMap::new();
+    for (i, adv) in advisories.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let id = group_ids
+            .entry(root)
+            .or_insert_with(|| adv.rustsec_id.clone());
+        if adv.rustsec_id < *id {
+            *id = adv.rustsec_id.clone();
+        }
+    }
+    for (i, adv) in advisories.iter_mut().enumerate() {
+        let root = find(&mut parent, i);
+        adv.group_id = group_ids[&root].clone();
+    }
+}
+
+// The opening fence may carry attributes after `toml` and lines may end in `\r\n`; the
+// block runs to the next line that starts with a fence.
+pub fn extract_toml_front_matter(md: &str) -> Option<&str> {
+    let start = md.find("```toml")?;
+    let rest = &md[start + "```toml".len()..];
+    let rest = &rest[rest.find('\n')? + 1..];
+    let end = rest
+        .match_indices("```")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || rest[..i].ends_with('\n'))?;
+    Some(rest[..end].trim_end_matches(['\r', '\n']))
+}
+
+pub fn parse_advisory(val: &toml::Value) -> Result<Advisory> {
+    let advisory = val
+        .get("advisory")
+        .and_then(|v| v.as_table())
+        .ok_or_else(|| anyhow!("missing [advisory]"))?;
+
+    let rustsec_id = advisory
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing advisory.id"))?
+        .to_string();
+
+    let package = advisory
+        .get("package")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing advisory.package"))?
+        .to_string();
+
+    let date = advisory.get("date").and_then(parse_advisory_date);
+
+    let withdrawn = advisory.get("withdrawn").is_some();
+    let withdrawn_date = advisory.get("withdrawn").and_then(parse_advisory_date);
+
+    let aliases = advisory
+        .get("aliases")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let related = advisory
+        .get("related")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let keywords = advisory
+        .get("keywords")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let categories = advisory
+        .get("categories")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // `[affected] functions = { "crate::path" = ["< 1.2"] }`; only the paths are kept.
+    let mut affected_functions = val
+        .get("affected")
+        .and_then(|v| v.as_table())
+        .and_then(|t| t.get("functions"))
+        .and_then(|v| v.as_table())
+        .map(|t| t.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    affected_functions.sort();
+
+    let cve_alias = aliases.iter().find(|s| s.starts_with("CVE-"));
+    let cve_is_fallback = cve_alias.is_none();
+    let cve_id = cve_alias.unwrap_or(&rustsec_id).clone();
+
+    let (severity, severity_source) = extract_severity_with_source(advisory);
+    let cvss = advisory
+        .get("cvss")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    // Any other informational value is treated as a notice.
+    let informational = match advisory.get("informational").and_then(|v| v.as_str()) {
+        None => InformationalKind::None,
+        Some("unmaintained") => InformationalKind::Unmaintained,
+        Some("unsound") => InformationalKind::Unsound,
+        Some(_) => InformationalKind::Notice,
+    };
+
+    let patched = val
+        .get("versions")
+        .and_then(|v| v.as_table())
+        .and_then(|t| t.get("patched"))
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let unaffected = val
+        .get("versions")
+        .and_then(|v| v.as_table())
+        .and_then(|t| t.get("unaffected"))
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let group_id = rustsec_id.clone();
+    Ok(Advisory {
+        rustsec_id,
+        cve_id,
+        cve_is_fallback,
+        aliases,
+        related,
+        group_id,
+        duplicate_of: None,
+        affected_functions,
+        keywords,
+        categories,
+        informational,
+        severity,
+        severity_source,
+        cvss,
+        package,
+        date,
+        withdrawn,
+        withdrawn_date,
+        patched,
+        unaffected,
+    })
+}
+
+fn parse_advisory_date(v: &toml::Value) -> Option<NaiveDate> {
+    let s = match v {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Datetime(dt) => dt.to_string(),
+        _ => return None,
+    };
+    let s = s.trim();
+    let day = s.get(..10).unwrap_or(s);
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()
+}
+
+pub fn severity_rank(s: &str) -> Option<u8> {
+    match s {
+        "INFO" => Some(0),
+        "LOW" => Some(1),
+        "MEDIUM" => Some(2),
+        "HIGH" => Some(3),
+        "CRITICAL" => Some(4),
+        _ => None,
+    }
+}
+
+pub fn normalize_severity(s: &str) -> String {
+    let u = s.trim().to_ascii_uppercase();
+    match u.as_str() {
+        "INFORMATIONAL" | "INFO" => "INFO".to_string(),
+        "LOW" => "LOW".to_string(),
+        "MEDIUM" | "MODERATE" => "MEDIUM".to_string(),
+        "HIGH" => "HIGH".to_string(),
+        "CRITICAL" => "CRITICAL".to_string(),
+        _ => {
+            if u.is_empty() {
+                "UNKNOWN".to_string()
+            } else {
+                u
+            }
+        }
+    }
+}
+
+pub fn extract_severity(advisory: &toml::value::Table) -> String {
+    extract_severity_with_source(advisory).0
+}
+
+pub fn extract_severity_with_source(advisory: &toml::value::Table) -> (String, SeveritySource) {
+    if let Some(s) = advisory.get("severity").and_then(|v| v.as_str()) {
+        return (normalize_severity(s), SeveritySource::Advisory);
+    }
+    // `cvss` is normally one vector; when several are given a v3 score wins over v2.
+    let vectors: Vec<&str> = match advisory.get("cvss") {
+        Some(toml::Value::String(s)) => vec![s.as_str()],
+        Some(toml::Value::Array(arr)) => arr.iter().filter_map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if let Some(score) = vectors.iter().find_map(|v| cvss3_base_score(v)) {
+        return (severity_from_cvss_score(score), SeveritySource::Cvss);
+    }
+    if let Some(score) = vectors.iter().find_map(|v| cvss2_base_score_from_vector(v)) {
+        return (severity_from_cvss2_score(score), SeveritySource::Cvss);
+    }
+    if advisory.get("informational").is_some() {
+        return ("INFO".to_string(), SeveritySource::Informational);
+    }
+    ("UNKNOWN".to_string(), SeveritySource::Unknown)
+}
+
+// Manual corrections from a `rustsec_id,severity` CSV, keyed by id. Severities go
+// through `normalize_severity` and must land on a known level (or UNKNOWN).
+pub fn parse_severity_overrides(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut r = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(text.as_bytes());
+    let headers = r.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| anyhow!("severity overrides have no `{name}` column"))
+    };
+    let (id_col, severity_col) = (column("rustsec_id")?, column("severity")?);
+    let mut overrides = BTreeMap::new();
+    for (i, record) in r.records().enumerate() {
+        let record = record?;
+        let line = i + 2;
+        let id = record.get(id_col).unwrap_or_default();
+        if id.is_empty() {
+            return Err(anyhow!("severity overrides line {line}: empty rustsec_id"));
+        }
+        let raw = record.get(severity_col).unwrap_or_default();
+        let severity = normalize_severity(raw);
+        if severity != "UNKNOWN" && severity_rank(&severity).is_none() {
+            return Err(anyhow!(
+                "severity overrides line {line}: unknown severity `{raw}` for {id}"
+            ));
+        }
+        if overrides.insert(id.to_string(), severity).is_some() {
+            return Err(anyhow!("severity overrides line {line}: {id} listed twice"));
+        }
+    }
+    Ok(overrides)
+}
+
+// Replaces the severity of every overridden advisory and marks it as such. Returns the
+// override ids that matched no advisory, sorted.
+pub fn apply_severity_overrides(
+    advisories: &mut [Advisory],
+    overrides: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut unused: BTreeSet<&String> = overrides.keys().collect();
+    for adv in advisories.iter_mut() {
+        if let Some(severity) = overrides.get(&adv.rustsec_id) {
+            adv.severity = severity.clone();
+            adv.severity_source = SeveritySource::Override;
+            unused.remove(&adv.rustsec_id);
+        }
+    }
+    unused.into_iter().cloned().collect()
+}
+
+// With `cvss-crate` the v3 score comes from the `cvss` crate, falling back to our own
+// arithmetic for vectors it rejects.
+#[cfg(feature = "cvss-crate")]
+fn cvss3_base_score(s: &str) -> Option<f64> {
+    use std::str::FromStr;
+    cvss::v3::Vector::from_str(s.trim())
+        .ok()
+        .map(|v| v.score().value())
+        .or_else(|| cvss31_base_score_from_vector(s))
+}
+
+#[cfg(not(feature = "cvss-crate"))]
+fn cvss3_base_score(s: &str) -> Option<f64> {
+    cvss31_base_score_from_vector(s)
+}
+
+// (ours, cvss crate) when both score the vector and differ by at least 0.1.
+#[cfg(feature = "cvss-crate")]
+pub fn cvss3_score_disagreement(s: &str) -> Option<(f64, f64)> {
+    use std::str::FromStr;
+    let ours = cvss31_base_score_from_vector(s)?;
+    let theirs = cvss::v3::Vector::from_str(s.trim()).ok()?.score().value();
+    ((ours - theirs).abs() >= 0.1 - 1e-9).then_some((ours, theirs))
+}
+
+// NVD bands for v2 scores, which have no CRITICAL.
+fn severity_from_cvss2_score(score: f64) -> String {
+    if !score.is_finite() || score <= 0.0 {
+        return "INFO".to_string();
+    }
+    if score < 4.0 {
+        "LOW".to_string()
+    } else if score < 7.0 {
+        "MEDIUM".to_string()
+    } else {
+        "HIGH".to_string()
+    }
+}
+
+// Splits `K:V/K:V` into pairs, skipping empty or malformed segments, so temporal and
+// environmental metrics after the base metrics do not affect the base score.
+fn cvss_metrics(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    s.split('/').filter_map(|part| {
+        let (k, v) = part.split_once(':')?;
+        let (k, v) = (k.trim(), v.trim());
+        (!k.is_empty() && !v.is_empty()).then_some((k, v))
+    })
+}
+
+// CVSS v2 base score; accepts the bare vector, a parenthesized one, or a `CVSS:2.0/` prefix.
+pub fn cvss2_base_score_from_vector(s: &str) -> Option<f64> {
+    let s = s.trim().trim_start_matches('(').trim_end_matches(')');
+    let s = s.strip_prefix("CVSS:2.0/").unwrap_or(s);
+    if s.starts_with("CVSS:") {
+        return None;
+    }
+    let mut av: Option<f64> = None;
+    let mut ac: Option<f64> = None;
+    let mut au: Option<f64> = None;
+    let mut c: Option<f64> = None;
+    let mut i: Option<f64> = None;
+    let mut a: Option<f64> = None;
+    let impact = |v: &str| match v {
+        "N" => Some(0.0),
+        "P" => Some(0.275),
+        "C" => Some(0.660),
+        _ => None,
+    };
+    for (k, v) in cvss_metrics(s) {
+        match k {
+            "AV" => {
+                av = match v {
+                    "L" => Some(0.395),
+                    "A" => Some(0.646),
+                    "N" => Some(1.0),
+                    _ => None,
+                };
+            }
+            "AC" => {
+                ac = match v {
+                    "H" => Some(0.35),
+                    "M" => Some(0.61),
+                    "L" => Some(0.71),
+                    _ => None,
+                };
+            }
+            "Au" => {
+                au = match v {
+                    "M" => Some(0.45),
+                    "S" => Some(0.56),
+                    "N" => Some(0.704),
+                    _ => None,
+                };
+            }
+            "C" => c = impact(v),
+            "I" => i = impact(v),
+            "A" => a = impact(v),
+            _ => {}
+        }
+    }
+
+    let impact = 10.41 * (1.0 - (1.0 - c?) * (1.0 - i?) * (1.0 - a?));
+    let exploitability = 20.0 * av? * ac? * au?;
+    let f_impact = if impact == 0.0 { 0.0 } else { 1.176 };
+    let raw = (0.6 * impact + 0.4 * exploitability - 1.5) * f_impact;
+    Some((raw.max(0.0) * 10.0).round() / 10.0)
+}
+
+// CVSS 3.1 Roundup: smallest one-decimal value >= x, robust to float noise such as
+// 4.000000000000001.
+fn cvss3_roundup(x: f64) -> f64 {
+    let int_input = (x * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+pub fn severity_from_cvss_score(score: f64) -> String {
+    if !score.is_finite() || score <= 0.0 {
+        return "INFO".to_string();
+    }
+    if score < 4.0 {
+        "LOW".to_string()
+    } else if score < 7.0 {
+        "MEDIUM".to_string()
+    } else if score < 9.0 {
+        "HIGH".to_string()
+    } else {
+        "CRITICAL".to_string()
+    }
+}
+
+pub fn cvss31_base_score_from_vector(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("CVSS:3.1/")
+        .or_else(|| s.strip_prefix("CVSS:3.0/"))?;
+    let mut av: Option<f64> = None;
+    let mut ac: Option<f64> = None;
+    let mut pr_u: Option<f64> = None;
+    let mut pr_c: Option<f64> = None;
+    let mut ui: Option<f64> = None;
+    let mut scope: Option<char> = None;
+    let mut c: Option<f64> = None;
+    let mut i: Option<f64> = None;
+    let mut a: Option<f64> = None;
+
+    for (k, v) in cvss_metrics(s) {
+        match k {
+            "AV" => {
+                av = match v {
+                    "N" => Some(0.85),
+                    "A" => Some(0.62),
+                    "L" => Some(0.55),
+                    "P" => Some(0.20),
+                    _ => None,
+                };
+            }
+            "AC" => {
+                ac = match v {
+                    "L" => Some(0.77),
+                    "H" => Some(0.44),
+                    _ => None,
+                };
+            }
+            "PR" => {
+                pr_u = match v {
+                    "N" => Some(0.85),
+                    "L" => Some(0.62),
+                    "H" => Some(0.27),
+                    _ => None,
+                };
+                pr_c = match v {
+                    "N" => Some(0.85),
+                    "L" => Some(0.68),
+                    "H" => Some(0.50),
+                    _ => None,
+                };
+            }
+            "UI" => {
+                ui = match v {
+                    "N" => Some(0.85),
+                    "R" => Some(0.62),
+                    _ => None,
+                };
+            }
+            "S" => {
+                scope = match v {
+                    "U" => Some('U'),
+                    "C" => Some('C'),
+                    _ => None,
+                };
+            }
+            "C" => {
+                c = match v {
+                    "H" => Some(0.56),
+                    "L" => Some(0.22),
+                    "N" => Some(0.0),
+                    _ => None,
+                };
+            }
+            "I" => {
+                i = match v {
+                    "H" => Some(0.56),
+                    "L" => Some(0.22),
+                    "N" => Some(0.0),
+                    _ => None,
+                };
+            }
+            "A" => {
+                a = match v {
+                    "H" => Some(0.56),
+                    "L" => Some(0.22),
+                    "N" => Some(0.0),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let av = av?;
+    let ac = ac?;
+    let ui = ui?;
+    let scope = scope?;
+    let pr = match scope {
+        'U' => pr_u?,
+        'C' => pr_c?,
+        _ => return None,
+    };
+    let c = c?;
+    let i = i?;
+    let a = a?;
+
+    let iss = 1.0 - (1.0 - c) * (1.0 - i) * (1.0 - a);
+    let impact = if scope == 'U' {
+        6.42 * iss
+    } else {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powf(15.0)
+    };
+    let exploitability = 8.22 * av * ac * pr * ui;
+
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+
+    let raw = if scope == 'U' {
+        (impact + exploitability).min(10.0)
+    } else {
+        (1.08 * (impact + exploitability)).min(10.0)
+    };
+
+    Some(cvss3_roundup(raw))
+}
+
+pub fn extract_all_fixed_versions(patched: &[String], policy: PrereleasePolicy) -> Vec<Version> {
+    let mut candidates = Vec::new();
+    for p in patched {
+        if let Ok(v) = Version::parse(p) {
+            candidates.push(v);
+            continue;
+        }
+        if let Ok(req) = VersionReq::parse(p) {
+            candidates.extend(extract_versions_from_req(&req));
+        }
+    }
+    if policy == PrereleasePolicy::Exclude {
+        candidates.retain(|v| v.pre.is_empty());
+    }
+    candidates.sort();
+    candidates
+}
+
+// `MatchSemver` keeps semver's rule that a pre-release only matches comparators on
+// the same major.minor.patch carrying a pre-release; `Include` orders pre-releases
+// like any other version and `Exclude` never matches them.
+pub fn req_matches(req: &VersionReq, v: &Version, policy: PrereleasePolicy) -> bool {
+    if v.pre.is_empty() {
+        return req.matches(v);
+    }
+    match policy {
+        PrereleasePolicy::MatchSemver => req.matches(v),
+        PrereleasePolicy::Exclude => false,
+        PrereleasePolicy::Include => {
+            // An exact comparator on `v` itself satisfies semver's pre-release
+            // compatibility check without changing the range being tested.
+            let mut comparators = req.comparators.clone();
+            comparators.push(semver::Comparator {
+                op: Op::Exact,
+                major: v.major,
+                minor: Some(v.minor),
+                patch: Some(v.patch),
+                pre: v.pre.clone(),
+            });
+            VersionReq { comparators }.matches(v)
+        }
+    }
+}
+
+pub fn same_version_ignoring_build(a: &Version, b: &Version) -> bool {
+    a.major == b.major && a.minor == b.minor && a.patch == b.patch && a.pre == b.pre
+}
+
+pub fn extract_versions_from_req(req: &VersionReq) -> Vec<Version> {
+    let mut out = Vec::new();
+    for c in &req.comparators {
+        // `>=1.4` and `2.*` name a release line rather than a version; use its
+        // first version. `>` excludes the whole line it names, so `>1.4` starts at
+        // 1.5.0 and `>1` at 2.0.0.
+        let v = match (c.op, c.minor, c.patch) {
+            (Op::Greater, Some(minor), Some(patch)) if c.pre.is_empty() => {
+                Version::new(c.major, minor, patch + 1)
+            }
+            (Op::Greater, Some(minor), None) => Version::new(c.major, minor + 1, 0),
+            (Op::Greater, None, _) => Version::new(c.major + 1, 0, 0),
+            _ => Version {
+                major: c.major,
+                minor: c.minor.unwrap_or(0),
+                patch: c.patch.unwrap_or(0),
+                pre: c.pre.clone(),
+                build: semver::BuildMetadata::EMPTY,
+            },
+        };
+        match c.op {
+            Op::Exact | Op::Greater | Op::GreaterEq | Op::Tilde | Op::Caret | Op::Wildcard => {
+                out.push(v)
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+pub fn identify_vuln_versions(
+    all_versions: &[String],
+    patched: &[String],
+    unaffected: &[String],
+    policy: PrereleasePolicy,
+) -> Vec<Version> {
+    let mut vuln = Vec::new();
+
+    if patched.is_empty() && unaffected.is_empty() {
+        for v_str in all_versions {
+            if let Ok(v) = Version::parse(v_str) {
+                if !v.pre.is_empty() && policy == PrereleasePolicy::Exclude {
+                    continue;
+                }
+                vuln.push(v);
+            }
+        }
+        vuln.sort();
+        vuln.dedup_by(|a, b| same_version_ignoring_build(a, b));
+        return vuln;
+    }
+
+    // Parse constraints
+    let patched_reqs: Vec<VersionReq> = patched
+        .iter()
+        .filter_map(|s| VersionReq::parse(s).ok())
+        .collect();
+    let unaffected_reqs: Vec<VersionReq> = unaffected
+        .iter()
+        .filter_map(|s| VersionReq::parse(s).ok())
+        .collect();
+
+    // Check each version
+    for v_str in all_versions {
+        if let Ok(v) = Version::parse(v_str) {
+            if !v.pre.is_empty() && policy == PrereleasePolicy::Exclude {
+                continue;
+            }
+            // Check if safe
+            let is_patched = patched_reqs.iter().any(|req| req_matches(req, &v, policy));
+            let is_unaffected = unaffected_reqs
+                .iter()
+                .any(|req| req_matches(req, &v, policy));
+
+            if !is_patched && !is_unaffected {
+                vuln.push(v);
+            }
+        }
+    }
+
+    // `1.2.3` and `1.2.3+build5` match exactly the same requirements, so keep one.
+    vuln.sort();
+    vuln.dedup_by(|a, b| same_version_ignoring_build(a, b));
+    vuln
+}
+
+// Shape problems in an advisory's patched/unaffected reqs; worst first when several apply.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdvisoryQuality {
+    Ok,
+    OverlappingPatched,
+    Conflicting,
+    EmptyVuln,
+}
+
+impl AdvisoryQuality {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdvisoryQuality::Ok => "ok",
+            AdvisoryQuality::OverlappingPatched => "overlapping_patched",
+            AdvisoryQuality::Conflicting => "conflicting",
+            AdvisoryQuality::EmptyVuln => "empty_vuln",
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AdvisoryQualityReport {
+    pub quality: AdvisoryQuality,
+    // One line per problem found, naming the reqs and a version they share.
+    pub details: Vec<String>,
+}
+
+// Checks the reqs against the published versions plus every version a comparator names,
+// so `>=0.8.0` / `>=0.8.2` overlap even before 0.8.2 is published. Does not change what
+// `identify_vuln_versions` returns.
+pub fn check_advisory_quality(
+    all_versions: &[String],
+    patched: &[String],
+    unaffected: &[String],
+    policy: PrereleasePolicy,
+) -> AdvisoryQualityReport {
+    let parse = |reqs: &[String]| -> Vec<(String, VersionReq)> {
+        reqs.iter()
+            .filter_map(|s| Some((s.clone(), VersionReq::parse(s).ok()?)))
+            .collect()
+    };
+    let patched_reqs = parse(patched);
+    let unaffected_reqs = parse(unaffected);
+    let published: Vec<Version> = all_versions
+        .iter()
+        .filter_map(|s| Version::parse(s).ok())
+        .filter(|v| v.pre.is_empty() || policy != PrereleasePolicy::Exclude)
+        .collect();
+    let mut candidates = published.clone();
+    for (_, req) in patched_reqs.iter().chain(&unaffected_reqs) {
+        candidates.extend(req.comparators.iter().map(|c| Version {
+            major: c.major,
+            minor: c.minor.unwrap_or(0),
+            patch: c.patch.unwrap_or(0),
+            pre: c.pre.clone(),
+            build: semver::BuildMetadata::EMPTY,
+        }));
+    }
+    candidates.sort();
+    candidates.dedup();
+    let shared = |a: &VersionReq, b: &VersionReq| {
+        candidates
+            .iter()
+            .find(|v| req_matches(a, v, policy) && req_matches(b, v, policy))
+    };
+
+    let mut overlapping = Vec::new();
+    for (i, (sa, a)) in patched_reqs.iter().enumerate() {
+        for (sb, b) in &patched_reqs[i + 1..] {
+            if let Some(v) = shared(a, b) {
+                overlapping.push(format!("patched `{sa}` overlaps patched `{sb}` at {v}"));
+            }
+        }
+    }
+    let mut conflicts = Vec::new();
+    for (sa, a) in &patched_reqs {
+        for (sb, b) in &unaffected_reqs {
+            if let Some(v) = shared(a, b) {
+                conflicts.push(format!(
+                    "patched `{sa}` and unaffected `{sb}` both match {v}"
+                ));
+            }
+        }
+    }
+    // Published versions that only the patched reqs keep out of the vulnerable set.
+    let exposed = published
+        .iter()
+        .filter(|v| {
+            !unaffected_reqs
+                .iter()
+                .any(|(_, req)| req_matches(req, v, policy))
+        })
+        .count();
+    let empty_vuln = !patched_reqs.is_empty()
+        && exposed > 0
+        && identify_vuln_versions(all_versions, patched, unaffected, policy).is_empty();
+
+    let quality = if !conflicts.is_empty() {
+        AdvisoryQuality::Conflicting
+    } else if empty_vuln {
+        AdvisoryQuality::EmptyVuln
+    } else if !overlapping.is_empty() {
+        AdvisoryQuality::OverlappingPatched
+    } else {
+        AdvisoryQuality::Ok
+    };
+    let mut details = conflicts;
+    if empty_vuln {
+        details.push(format!(
+            "patched reqs cover all {exposed} published versions not marked unaffected"
+        ));
+    }
+    details.extend(overlapping);
+    AdvisoryQualityReport { quality, details }
+}
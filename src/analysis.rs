@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use semver::{Version, VersionReq};
+
+use crate::pipeline::{AdoptionKind, estimate_min_version};
+
+// Cargo treats the leftmost non-zero component as the breaking one (`0.7` -> `0.8` is major).
+pub fn semver_compat_line(v: &Version) -> (u64, u64, u64) {
+    match (v.major, v.minor) {
+        (0, 0) => (0, 0, v.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+// Size of a version step in Cargo's terms: `Major` leaves the semver-compatible line,
+// `Minor` stays on it but changes major.minor, `Patch` is anything smaller.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VersionDelta {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl VersionDelta {
+    pub const ALL: [VersionDelta; 3] = [
+        VersionDelta::Patch,
+        VersionDelta::Minor,
+        VersionDelta::Major,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VersionDelta::Patch => "patch",
+            VersionDelta::Minor => "minor",
+            VersionDelta::Major => "major",
+        }
+    }
+}
+
+pub fn version_delta(from: &Version, to: &Version) -> VersionDelta {
+    if semver_compat_line(from) != semver_compat_line(to) {
+        VersionDelta::Major
+    } else if (from.major, from.minor) != (to.major, to.minor) {
+        VersionDelta::Minor
+    } else {
+        VersionDelta::Patch
+    }
+}
+
+// Whether moving from the last vulnerable req to the fixing req stayed within one
+// semver-compatible line. `compatible` means the old req already admitted the fix.
+pub fn classify_adoption(
+    original_req: &str,
+    fixed_req: &str,
+    fix_version: &Version,
+) -> AdoptionKind {
+    if let Ok(req) = VersionReq::parse(original_req.trim())
+        && req.matches(fix_version)
+    {
+        return AdoptionKind::Compatible;
+    }
+    let (Some(from), Some(to)) = (
+        estimate_min_version(original_req),
+        estimate_min_version(fixed_req),
+    ) else {
+        return AdoptionKind::Unknown;
+    };
+    match version_delta(&from, &to) {
+        VersionDelta::Major => AdoptionKind::MajorBump,
+        VersionDelta::Minor | VersionDelta::Patch => AdoptionKind::MinorBump,
+    }
+}
+
+// Whether `cargo update` alone can move some vulnerable release onto a fix: a stable fixed
+// version above it on the same caret line (same major; same minor for 0.x; same patch for
+// 0.0.x, so 0.0.x releases never qualify). Caret requirements on stable versions do not
+// pick up prereleases, so prerelease fixes do not count.
+pub fn fix_semver_compatible(vuln_versions: &[Version], fixed_versions: &[Version]) -> bool {
+    fixed_versions.iter().filter(|f| f.pre.is_empty()).any(|f| {
+        vuln_versions
+            .iter()
+            .any(|v| v < f && semver_compat_line(v) == semver_compat_line(f))
+    })
+}
+
+// Which of an advisory's fixed versions take part in matching and t0 selection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FixSelection {
+    EarliestOverall,
+    EarliestPerMajor,
+    All,
+}
+
+impl FixSelection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FixSelection::EarliestOverall => "earliest-overall",
+            FixSelection::EarliestPerMajor => "earliest-per-major",
+            FixSelection::All => "all",
+        }
+    }
+}
+
+// Drops the fixed versions `selection` leaves out. "Earliest" is by publish time, ties to
+// the lower version; per-major groups by `semver_compat_line`, so 0.7.x and 0.8.x are
+// separate lines.
+pub fn select_fix_versions(
+    fix_times: &mut BTreeMap<Version, DateTime<Utc>>,
+    selection: FixSelection,
+) {
+    let key = |(v, t): (&Version, &DateTime<Utc>)| (*t, v.clone());
+    let keep: Vec<Version> = match selection {
+        FixSelection::All => return,
+        FixSelection::EarliestOverall => fix_times
+            .iter()
+            .min_by_key(|e| key(*e))
+            .map(|(v, _)| v.clone())
+            .into_iter()
+            .collect(),
+        FixSelection::EarliestPerMajor => {
+            let mut first: HashMap<(u64, u64, u64), (DateTime<Utc>, Version)> = HashMap::new();
+            for e in fix_times.iter() {
+                let k = key(e);
+                first
+                    .entry(semver_compat_line(e.0))
+                    .and_modify(|cur| {
+                        if k < *cur {
+                            *cur = k.clone();
+                        }
+                    })
+                    .or_insert(k);
+            }
+            first.into_values().map(|(_, v)| v).collect()
+        }
+    };
+    fix_times.retain(|v, _| keep.contains(v));
+}
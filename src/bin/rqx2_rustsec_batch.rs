@@ -1,122 +1,582 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    io::Cursor,
-    path::Path,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
     time::{Duration, Instant},
 };
 
 use anyhow::{Result, anyhow};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use clap::Parser;
+use futures::{StreamExt, TryStreamExt, stream};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
 use reqwest::Client;
-use semver::{Op, Version, VersionReq};
-use time_to_fix_cve::database::{Database, DownstreamVersionInfo};
-use zip::ZipArchive;
+use semver::{Version, VersionReq};
+use time_to_fix_cve::advisory::{
+    Advisory, AdvisorySource, CveDedupe, InformationalKind, InformationalPolicy, PrereleasePolicy,
+    SeveritySource, apply_severity_overrides, cve_index, cvss31_base_score_from_vector,
+    dedupe_by_cve, fetch_rustsec_advisories_with_source, normalize_cve_id, normalize_severity,
+    parse_advisory_archive, parse_severity_overrides, severity_from_cvss_score, severity_rank,
+};
+use time_to_fix_cve::analysis::{FixSelection, VersionDelta};
+use time_to_fix_cve::charts::{
+    BarChart, BoxPlotChart, ChartStyle, GroupedBarChart, HistogramChart, StackedBarChart,
+    StepCurveChart,
+};
+use time_to_fix_cve::config;
+use time_to_fix_cve::database::{
+    CrateActivity, DataSource, Database, DownstreamVersionInfo, RowSource,
+};
+use time_to_fix_cve::dump::DumpStore;
+use time_to_fix_cve::extsort::{column_index, sort_csv_file};
+use time_to_fix_cve::output::{
+    ColumnKind, Compression, CsvSchema, OutputFile, create_csv, ensure_parent_dir,
+    serialize_opt_path, serialize_path,
+};
+#[cfg(feature = "parquet")]
+use time_to_fix_cve::parquet::ParquetTable;
+use time_to_fix_cve::pipeline::{
+    AdoptionEvidence, AdoptionKind, AdvisoryResult, AdvisorySkip, AnalysisOptions, AnalysisSource,
+    ConstraintTotals, CrateRenames, DuplicateReqs, ExplainSink, ExposureCounts, FirstOpportunity,
+    FixMatchPolicy, FixedVersionSource, HorizonBreak, LagMode, LagUnit, PackageAliases, ReqShape,
+    SkipReason, Stage, StrictLagRow, T0Kind, WithdrawnPolicy, analyze_advisory, as_of_cutoff,
+    collapse_earliest, downstream_histories, estimate_min_version, first_opportunity,
+    fractional_days, lookup_version_time, merge_renamed_histories, prefix_group, req_sample,
+    resolve_package, resolve_t0, static_fixed_versions,
+};
+use time_to_fix_cve::records::{
+    EdgeBudget, PendingRows, PropagationEvent, SummaryRow, propagation_event_record,
+    summary_record, write_constraint_breakdown, write_constraint_edges, write_constraint_horizons,
+    write_grouped_lags, write_negative_lags, write_propagation_reach, write_strict_lags,
+};
+use time_to_fix_cve::report::HtmlReport;
+use time_to_fix_cve::sampling::{EventSelector, Reservoir};
+use time_to_fix_cve::stats::{
+    LagStats, PROPAGATION_EVENT_SCHEMA, PROPAGATION_EVENT_SCHEMA_ID, STRICT_LAG_SCHEMA,
+    STRICT_LAG_SCHEMA_ID, SUMMARY_SCHEMA, SUMMARY_SCHEMA_ID, compute_lag_stats,
+    cumulative_fractions, format_float, percentile_sorted,
+};
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let p = Path::new(path);
-    let Some(parent) = p.parent() else {
-        return Ok(());
+// Removes the charts (and their data CSVs) an earlier run left in `dir` under one of
+// `prefixes`. Best effort: a file that cannot be removed is overwritten or left alone.
+fn remove_stale_charts(dir: &Path, prefixes: &[&str]) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
     };
-    if parent.as_os_str().is_empty() {
-        return Ok(());
+    for p in entries.flatten().map(|e| e.path()) {
+        let stale = p.is_file()
+            && p.extension()
+                .is_some_and(|ext| ext == "svg" || ext == "csv")
+            && p.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                prefixes.iter().any(|prefix| name.starts_with(prefix))
+            });
+        if stale {
+            let _ = std::fs::remove_file(p);
+        }
     }
-    std::fs::create_dir_all(parent)?;
-    Ok(())
 }
 
+// A file name built from outside data (e.g. a rustsec id) with path separators and
+// characters Windows rejects replaced, so it cannot escape or break its directory.
+fn file_name_component(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum LogLevel {
+    Info,
+    Debug,
+}
+
+// The log file is written in LOG_BUFFER_BYTES chunks and flushed at least every
+// LOG_FLUSH_INTERVAL, so a log on slow (network) storage costs one write per chunk
+// rather than one per line.
+const LOG_BUFFER_BYTES: usize = 64 * 1024;
+const LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
 struct Logger {
     file: Option<std::io::BufWriter<std::fs::File>>,
+    last_flush: Instant,
+    level: LogLevel,
+    // Interactive progress bar on stderr; log lines are printed above it.
+    bar: Option<ProgressBar>,
 }
 
 impl Logger {
-    fn new(path: Option<&str>) -> Result<Self> {
+    fn new(path: Option<&Path>, level: LogLevel) -> Result<Self> {
         let file = if let Some(p) = path {
             ensure_parent_dir(p)?;
-            Some(std::io::BufWriter::new(std::fs::File::create(p)?))
+            Some(std::io::BufWriter::with_capacity(
+                LOG_BUFFER_BYTES,
+                std::fs::File::create(p)?,
+            ))
         } else {
             None
         };
-        Ok(Self { file })
+        Ok(Self {
+            file,
+            last_flush: Instant::now(),
+            level,
+            bar: None,
+        })
     }
 
     fn println(&mut self, msg: impl AsRef<str>) -> Result<()> {
         let msg = msg.as_ref();
-        eprintln!("{msg}");
+        self.stderr(msg);
+        self.write_file(msg)
+    }
+
+    fn stderr(&self, msg: &str) {
+        match &self.bar {
+            Some(bar) => bar.suspend(|| eprintln!("{msg}")),
+            None => eprintln!("{msg}"),
+        }
+    }
+
+    // Periodic status lines repeat what the bar shows, so with a bar they only go to
+    // the log file.
+    fn status(&mut self, msg: impl AsRef<str>) -> Result<()> {
+        let msg = msg.as_ref();
+        if self.bar.is_none() {
+            eprintln!("{msg}");
+        }
+        self.write_file(msg)
+    }
+
+    // Per-row details for `--log-level debug`. They go to the log file only, or to
+    // stderr when there is none.
+    fn debug(&mut self, msg: impl AsRef<str>) -> Result<()> {
+        if self.level != LogLevel::Debug {
+            return Ok(());
+        }
+        let msg = msg.as_ref();
+        if self.file.is_none() {
+            self.stderr(msg);
+        }
+        self.write_file(msg)
+    }
+
+    // An info line whose `detail` tail is only added at debug level, and then only in
+    // the log file when there is one.
+    fn println_with_detail(&mut self, msg: &str, detail: &str) -> Result<()> {
+        if self.level == LogLevel::Info || detail.is_empty() {
+            return self.println(msg);
+        }
+        let full = format!("{msg} {detail}");
+        self.stderr(if self.file.is_some() { msg } else { &full });
+        self.write_file(&full)
+    }
+
+    fn write_file(&mut self, msg: &str) -> Result<()> {
         if let Some(w) = self.file.as_mut() {
-            use std::io::Write;
             writeln!(w, "{msg}")?;
+            if self.last_flush.elapsed() >= LOG_FLUSH_INTERVAL {
+                w.flush()?;
+                self.last_flush = Instant::now();
+            }
         }
         Ok(())
     }
 
+    fn start_progress(&mut self, len: usize) -> Result<()> {
+        let bar = ProgressBar::new(len as u64);
+        bar.set_style(ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] {bar:30} {pos}/{len} eta {eta} {wide_msg}",
+        )?);
+        bar.enable_steady_tick(Duration::from_millis(120));
+        self.bar = Some(bar);
+        Ok(())
+    }
+
+    fn progress(&self) -> Option<&ProgressBar> {
+        self.bar.as_ref()
+    }
+
+    fn finish_progress(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+
     fn flush(&mut self) -> Result<()> {
         if let Some(w) = self.file.as_mut() {
-            use std::io::Write;
             w.flush()?;
         }
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+// `--cve-list`: the requested CVEs, the advisories carrying them as aliases (before any
+// other filter) and what the run did with each of those advisories.
+struct CveCoverage {
+    cves: Vec<String>,
+    index: BTreeMap<String, Vec<String>>,
+    packages: HashMap<String, String>,
+    // Advisories left after all filters.
+    kept: HashSet<String>,
+    // rustsec_id -> (status, detail, strict lag rows).
+    outcomes: HashMap<String, (&'static str, String, usize)>,
+}
+
+impl CveCoverage {
+    fn load(path: &str, advisories: &[Advisory]) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read --cve-list {path}: {e}"))?;
+        let mut cves: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let cve = normalize_cve_id(line);
+            if !cve.is_empty() && !cve.starts_with('#') && !cves.contains(&cve) {
+                cves.push(cve);
+            }
+        }
+        let mut index = cve_index(advisories);
+        index.retain(|cve, _| cves.contains(cve));
+        let packages = advisories
+            .iter()
+            .filter(|a| index.values().flatten().any(|id| *id == a.rustsec_id))
+            .map(|a| (a.rustsec_id.clone(), a.package.clone()))
+            .collect();
+        Ok(Self {
+            cves,
+            index,
+            packages,
+            kept: HashSet::new(),
+            outcomes: HashMap::new(),
+        })
+    }
+
+    fn rustsec_ids(&self) -> HashSet<String> {
+        self.index.values().flatten().cloned().collect()
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let mut w = csv::Writer::from_writer(std::fs::File::create(path)?);
+        w.write_record([
+            "cve_id",
+            "in_advisory_db",
+            "rustsec_id",
+            "target_crate",
+            "status",
+            "detail",
+            "strict_lag_rows",
+        ])?;
+        for cve in &self.cves {
+            let Some(ids) = self.index.get(cve) else {
+                w.write_record([cve.as_str(), "false", "", "", "no_advisory", "", "0"])?;
+                continue;
+            };
+            for id in ids {
+                let (status, detail, rows) = match self.outcomes.get(id) {
+                    Some((status, detail, rows)) => (*status, detail.as_str(), *rows),
+                    None if self.kept.contains(id) => ("not_processed", "", 0),
+                    None => ("filtered", "", 0),
+                };
+                w.write_record([
+                    cve.as_str(),
+                    "true",
+                    id.as_str(),
+                    self.packages.get(id).map_or("", |p| p.as_str()),
+                    status,
+                    detail,
+                    &rows.to_string(),
+                ])?;
+            }
+        }
+        w.flush()?;
         Ok(())
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-enum SkipReason {
-    Withdrawn,
-    NoFixedVersions,
-    NoFixTimes,
-    NoSummaryT0,
-    NoVulnVersions,
+// `MatchedOnly` starts hop 1 from the strict-lag rows (each dependent's first adoption);
+// `AllFixed` starts one carrier per fixed version so every fixed release line is followed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum PropagationSeed {
+    MatchedOnly,
+    AllFixed,
+}
+
+impl PropagationSeed {
+    fn as_str(self) -> &'static str {
+        match self {
+            PropagationSeed::MatchedOnly => "matched-only",
+            PropagationSeed::AllFixed => "all-fixed",
+        }
+    }
+}
+
+// Where a propagation lag starts: the carrier's fix release, or the downstream crate's
+// first own release after it, so crates that simply had no reason to release are not
+// charged for the wait.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum PropagationLagBasis {
+    FixTime,
+    FirstOpportunity,
 }
 
-impl SkipReason {
+impl PropagationLagBasis {
     fn as_str(self) -> &'static str {
         match self {
-            SkipReason::Withdrawn => "withdrawn",
-            SkipReason::NoFixedVersions => "no_fixed_versions",
-            SkipReason::NoFixTimes => "no_fix_times",
-            SkipReason::NoSummaryT0 => "no_summary_t0",
-            SkipReason::NoVulnVersions => "no_vuln_versions",
+            PropagationLagBasis::FixTime => "fix-time",
+            PropagationLagBasis::FirstOpportunity => "first-opportunity",
+        }
+    }
+
+    fn lag_secs(
+        self,
+        fix_lag_secs: i64,
+        adopted_at: DateTime<Utc>,
+        opportunity: Option<FirstOpportunity>,
+    ) -> i64 {
+        match (self, opportunity) {
+            (PropagationLagBasis::FirstOpportunity, Some(o)) => o.lag_secs(adopted_at),
+            _ => fix_lag_secs,
+        }
+    }
+}
+
+// `Auto` draws the bar only when stderr is a terminal; redirected runs keep the plain
+// periodic progress lines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ProgressMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ProgressMode {
+    fn enabled(self) -> bool {
+        match self {
+            ProgressMode::Auto => std::io::stderr().is_terminal(),
+            ProgressMode::Always => true,
+            ProgressMode::Never => false,
         }
     }
 }
 
-#[derive(Parser)]
+#[derive(Parser, serde::Serialize)]
 struct Args {
     #[arg(long, default_value = "rustsec_rqx2_strict_lags.csv")]
-    output: String,
+    #[serde(serialize_with = "serialize_path")]
+    output: PathBuf,
 
     #[arg(long, default_value = "rustsec_rqx2_strict_summary.csv")]
-    summary_output: String,
+    #[serde(serialize_with = "serialize_path")]
+    summary_output: PathBuf,
 
     #[arg(long, value_delimiter = ',', num_args = 0..)]
     only: Vec<String>,
 
+    #[arg(long, value_name = "PATH")]
+    cve_list: Option<String>,
+
+    #[arg(long, default_value = "rustsec_cve_coverage.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    cve_coverage_output: PathBuf,
+
+    #[arg(long)]
+    min_severity: Option<String>,
+
+    #[arg(long, value_name = "CSV")]
+    severity_overrides: Option<String>,
+
+    #[arg(long)]
+    max_severity: Option<String>,
+
+    #[arg(long)]
+    published_after: Option<NaiveDate>,
+
+    #[arg(long)]
+    published_before: Option<NaiveDate>,
+
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    packages: Vec<String>,
+
+    #[arg(long, value_name = "FROM=TO")]
+    package_alias: Vec<String>,
+
+    // Built from --package-alias by `configure`.
+    #[arg(skip)]
+    #[serde(skip)]
+    package_aliases: PackageAliases,
+
+    #[arg(long, value_enum, default_value_t = T0Kind::FixRelease)]
+    t0: T0Kind,
+
+    #[arg(long, value_enum, default_value_t = PrereleasePolicy::MatchSemver)]
+    prerelease_policy: PrereleasePolicy,
+
+    #[arg(long, value_enum, default_value_t = InformationalPolicy::Default)]
+    informational: InformationalPolicy,
+
+    #[arg(long, value_enum, default_value_t = WithdrawnPolicy::Skip)]
+    withdrawn: WithdrawnPolicy,
+
+    #[arg(long, value_enum, default_value_t = CveDedupe::KeepAll)]
+    dedupe_by_cve: CveDedupe,
+
+    #[arg(long, value_enum, default_value_t = LagUnit::Days)]
+    lag_unit: LagUnit,
+
+    #[arg(long, default_value_t = false)]
+    include_negative_lags: bool,
+
+    #[arg(long, default_value = "rustsec_rqx2_negative_lags.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    negative_lags_output: PathBuf,
+
+    #[arg(long, default_value_t = false)]
+    all_adoptions: bool,
+
+    #[arg(long, value_enum, default_value_t = LagMode::Strict)]
+    lag_mode: LagMode,
+
+    #[arg(long, default_value_t = false)]
+    group_by_owner: bool,
+
+    #[arg(long, default_value = "rustsec_rqx2_strict_lags_by_owner.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    owner_grouped_output: PathBuf,
+
+    #[arg(long)]
+    collapse_prefix_regex: Option<String>,
+
+    #[arg(long, default_value = "rustsec_rqx2_strict_lags_collapsed.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    collapsed_output: PathBuf,
+
+    #[arg(long)]
+    crate_renames: Option<String>,
+
+    // Read from --crate-renames by `configure`.
+    #[arg(skip)]
+    #[serde(skip)]
+    renames: CrateRenames,
+
+    #[arg(long, default_value_t = false)]
+    crates_io_downstream_fallback: bool,
+
     #[arg(long, default_value_t = false)]
     propagation: bool,
 
     #[arg(long, default_value = "rustsec_rqx2_propagation_summary.txt")]
-    propagation_summary_output: String,
+    #[serde(serialize_with = "serialize_path")]
+    propagation_summary_output: PathBuf,
 
     #[arg(long, default_value = "rustsec_rqx2_propagation_svgs")]
-    propagation_output_dir: String,
+    #[serde(serialize_with = "serialize_path")]
+    propagation_output_dir: PathBuf,
 
     #[arg(long)]
     propagation_max_hops: Option<usize>,
 
+    #[arg(long, default_value = "rustsec_rqx2_propagation_coverage.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    propagation_coverage_output: PathBuf,
+
+    #[arg(long, default_value = "rustsec_rqx2_propagation_reach.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    propagation_reach_output: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = PropagationSeed::MatchedOnly)]
+    propagation_seed: PropagationSeed,
+
+    #[arg(long)]
+    propagation_max_queue: Option<usize>,
+
+    #[arg(long)]
+    propagation_min_downstream_releases: Option<usize>,
+
+    #[arg(long)]
+    propagation_within_days: Option<f64>,
+
+    #[arg(long, value_enum, default_value_t = PropagationLagBasis::FixTime)]
+    propagation_lag_basis: PropagationLagBasis,
+
     #[arg(long, default_value_t = 60)]
     propagation_bins: usize,
 
+    #[arg(long)]
+    propagation_x_max_days: Option<f64>,
+
+    #[arg(long)]
+    hist_clip_days: Option<f64>,
+
+    #[arg(long, default_value = "rustsec_rqx2_strict_svgs")]
+    #[serde(serialize_with = "serialize_path")]
+    strict_output_dir: PathBuf,
+
+    #[arg(long, value_name = "DIR")]
+    #[serde(serialize_with = "serialize_opt_path")]
+    per_advisory_svgs: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 20)]
+    per_advisory_svgs_min_rows: usize,
+
+    #[arg(long, default_value_t = 200)]
+    per_advisory_svgs_max: usize,
+
+    #[arg(long, default_value_t = 20)]
+    boxplot_min_samples: usize,
+
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    chart_data_csv: bool,
+
+    #[arg(long)]
+    chart_width: Option<u32>,
+
+    #[arg(long)]
+    chart_height: Option<u32>,
+
+    #[arg(long, default_value_t = 1.0)]
+    chart_font_scale: f64,
+
+    #[arg(long, default_value = "default")]
+    chart_palette: String,
+
+    // Built from the --chart-* flags by `configure`.
+    #[arg(skip)]
+    #[serde(skip)]
+    chart_style: ChartStyle,
+
     #[arg(long, default_value_t = false)]
     constraint: bool,
 
     #[arg(long, default_value = "rustsec_rqx2_constraint_breakdown.csv")]
-    constraint_breakdown_output: String,
+    #[serde(serialize_with = "serialize_path")]
+    constraint_breakdown_output: PathBuf,
 
     #[arg(long, default_value = "rustsec_rqx2_constraint_summary.txt")]
-    constraint_summary_output: String,
+    #[serde(serialize_with = "serialize_path")]
+    constraint_summary_output: PathBuf,
 
     #[arg(long, default_value = "rustsec_rqx2_constraint_svgs")]
-    constraint_output_dir: String,
+    #[serde(serialize_with = "serialize_path")]
+    constraint_output_dir: PathBuf,
 
     #[arg(long, default_value_t = 40)]
     constraint_bins: usize,
@@ -125,1482 +585,5907 @@ struct Args {
     constraint_min_age_days: i64,
 
     #[arg(long)]
-    propagation_events_output: Option<String>,
+    #[serde(serialize_with = "serialize_opt_path")]
+    constraint_edges_output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 0)]
+    constraint_edges_limit: usize,
+
+    #[arg(long, default_value_t = false)]
+    constraint_whatif: bool,
+
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    constraint_horizons: Vec<i64>,
+
+    #[arg(long, default_value = "rustsec_rqx2_constraint_horizons.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    constraint_horizons_output: PathBuf,
+
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    adoption_curve_output: Option<PathBuf>,
+
+    #[arg(long, value_delimiter = ',', default_value = "0,7,14,30,60,90,180,365")]
+    adoption_curve_days: Vec<i64>,
+
+    #[arg(long, value_delimiter = ',', default_value = "0,10,100")]
+    dependent_tiers: Vec<i64>,
+
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    category_summary_output: Option<PathBuf>,
+
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    propagation_events_output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false)]
+    sort_events: bool,
+
+    #[arg(long, default_value_t = 1_000_000)]
+    sort_events_chunk_rows: usize,
 
     #[arg(long, default_value_t = 0)]
     propagation_events_limit: usize,
 
+    #[arg(long, default_value_t = 0)]
+    propagation_events_total_limit: usize,
+
     #[arg(long, default_value_t = 5)]
     propagation_verify_samples: usize,
 
+    #[arg(long, default_value_t = 0)]
+    verify_seed: u64,
+
     #[arg(long, default_value_t = 50)]
     downstream_cache_crates: usize,
 
+    #[arg(long, default_value_t = 1_000_000)]
+    downstream_stream_threshold: usize,
+
+    #[arg(long)]
+    max_memory_mb: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = DataSource::Postgres)]
+    data_source: DataSource,
+
+    #[arg(long)]
+    dump_dir: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    prefetch: bool,
+
+    #[arg(long, default_value_t = 8)]
+    prefetch_concurrency: usize,
+
     #[arg(long)]
     max_advisories: Option<usize>,
 
+    #[arg(long, value_name = "N")]
+    max_runtime_minutes: Option<f64>,
+
     #[arg(long)]
-    log_output: Option<String>,
-}
+    #[serde(serialize_with = "serialize_opt_path")]
+    log_output: Option<PathBuf>,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-    ensure_parent_dir(&args.output)?;
-    ensure_parent_dir(&args.summary_output)?;
-    if args.propagation {
-        ensure_parent_dir(&args.propagation_summary_output)?;
-    }
-    if let Some(p) = args.propagation_events_output.as_deref() {
-        ensure_parent_dir(p)?;
-    }
-    if args.constraint {
-        ensure_parent_dir(&args.constraint_breakdown_output)?;
-        ensure_parent_dir(&args.constraint_summary_output)?;
-    }
-    let mut logger = Logger::new(args.log_output.as_deref())?;
+    #[arg(long, value_enum, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
 
-    logger.println("connecting to postgres...")?;
-    let db = Database::connect_from_env().await?;
-    let client = Client::builder()
-        .user_agent("time-to-fix-cve/0.1")
-        .build()?;
+    #[arg(long, value_enum, default_value_t = ProgressMode::Auto)]
+    progress: ProgressMode,
 
-    logger.println("downloading rustsec advisory-db...")?;
-    let mut advisories = fetch_rustsec_advisories(&client).await?;
-    if !args.only.is_empty() {
-        let allow: HashSet<String> = args
-            .only
-            .iter()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
-        advisories.retain(|a| allow.contains(&a.cve_id) || allow.contains(&a.rustsec_id));
-    }
-    let total_advisories = advisories.len();
-    logger.println(format!("rustsec advisories loaded: {total_advisories}"))?;
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    timings_output: Option<PathBuf>,
 
-    let file = std::fs::File::create(&args.output)?;
-    let mut w = csv::Writer::from_writer(file);
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    metadata_output: Option<PathBuf>,
 
-    let summary_file = std::fs::File::create(&args.summary_output)?;
-    let mut sw = csv::Writer::from_writer(summary_file);
+    #[arg(long, value_name = "PATH")]
+    #[serde(serialize_with = "serialize_opt_path")]
+    list_advisories: Option<PathBuf>,
 
-    let mut propagation_events_written = 0usize;
-    let mut propagation_events_writer = if let Some(path) = &args.propagation_events_output {
-        let file = std::fs::File::create(path)?;
-        let mut w = csv::Writer::from_writer(file);
-        w.write_record([
-            "root_rustsec_id",
-            "root_cve_id",
-            "root_target_crate",
-            "hop",
-            "upstream_crate",
-            "upstream_fix_version",
-            "upstream_fix_time",
-            "downstream_crate",
-            "downstream_version",
-            "downstream_time",
-            "lag_days",
-            "dep_req",
-        ])?;
-        Some(w)
-    } else {
-        None
-    };
-    let mut propagation_verify_samples: Vec<(
-        String,
-        String,
-        String,
-        chrono::DateTime<chrono::Utc>,
-        String,
-    )> = Vec::new();
-
-    let mut constraint_breakdown_writer = if args.constraint {
-        let file = std::fs::File::create(&args.constraint_breakdown_output)?;
-        let mut w = csv::Writer::from_writer(file);
-        w.write_record([
-            "rustsec_id",
-            "cve_id",
-            "severity",
-            "target_crate",
-            "fix_time",
-            "downstream_crates_with_history",
-            "affected_edges",
-            "locked_out_edges",
-            "break_rate_percent",
-            "affected_req_exact_pin",
-            "affected_req_has_upper_bound",
-            "affected_req_caret_0x",
-            "affected_req_other",
-            "unknown_req_unparseable",
-        ])?;
-        Some(w)
-    } else {
-        None
-    };
-    let mut constraint_break_rate_per_adv_percent: Vec<i64> = Vec::new();
-    let mut constraint_totals = ConstraintTotals::default();
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    html_report: Option<PathBuf>,
 
-    w.write_record([
-        "rustsec_id",
-        "cve_id",
-        "severity",
-        "target_crate",
-        "fixed_version",
-        "fix_time",
-        "downstream_crate",
-        "downstream_version",
-        "downstream_time",
-        "lag_days",
-        "original_req",
-        "fixed_req",
-    ])?;
+    #[arg(long, default_value_t = false)]
+    verify_deterministic: bool,
 
-    sw.write_record([
-        "rustsec_id",
-        "cve_id",
-        "severity",
-        "target_crate",
-        "fixed_version",
-        "fix_time",
-        "downstream_fixed_cnt",
-        "lag_days_min",
-        "lag_days_p50",
-        "lag_days_avg",
-        "lag_days_max",
-    ])?;
+    #[arg(long, default_value_t = false)]
+    fail_fast: bool,
 
-    let mut processed = 0usize;
-    let mut written_rows = 0usize;
-    let mut skipped = 0usize;
-    let mut skipped_by_reason: HashMap<SkipReason, usize> = HashMap::new();
-    let mut propagation_fallback_latest_seed = 0usize;
-    let mut crates_io_time_fallback_hits = 0usize;
-    let mut crates_io_time_fallback_misses = 0usize;
-    let mut crates_io_time_cache: HashMap<(String, String), Option<DateTime<Utc>>> = HashMap::new();
-    let mut crate_versions_cache: HashMap<String, Vec<String>> = HashMap::new();
-    let start = Instant::now();
-    let mut last_progress = Instant::now();
-    let now = Utc::now();
-
-    let mut cache = DownstreamCache::new(args.downstream_cache_crates);
-    let mut propagation_lags_by_hop: HashMap<usize, Vec<i64>> = HashMap::new();
+    #[arg(long, default_value = "rustsec_rqx2_errors.csv")]
+    #[serde(serialize_with = "serialize_path")]
+    errors_output: PathBuf,
 
-    for adv in advisories {
-        if let Some(limit) = args.max_advisories
-            && processed >= limit
-        {
-            break;
-        }
+    #[arg(long, value_enum, default_value_t = Compression::None)]
+    compress: Compression,
 
-        processed += 1;
-        if processed == 1 || last_progress.elapsed() >= Duration::from_secs(5) {
-            logger.println(format!(
-                "progress: {processed}/{total_advisories} advisories, written_rows={written_rows}, skipped={skipped}, elapsed={:.1}s",
-                start.elapsed().as_secs_f64()
-            ))?;
-            last_progress = Instant::now();
-        }
+    #[arg(long, default_value_t = false)]
+    csv_schema_comments: bool,
 
-        if adv.withdrawn {
-            record_skip(
-                &mut logger,
-                &mut skipped,
-                &mut skipped_by_reason,
-                &adv,
-                SkipReason::Withdrawn,
-                "advisory withdrawn".to_string(),
-            )?;
-            continue;
-        }
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    parquet_output_dir: Option<PathBuf>,
 
-        let pkg = normalize_crate_name(&adv.package);
-        if pkg != adv.package {
-            logger.println(format!(
-                "package alias: rustsec_pkg={} db_pkg={}",
-                adv.package, pkg
-            ))?;
-        }
+    #[arg(long, default_value_t = 65536)]
+    parquet_batch_size: usize,
 
-        let fixed_versions = extract_all_fixed_versions(&adv.patched);
-        let mut root_seed: Option<Carrier> = None;
-        if fixed_versions.is_empty() && args.propagation {
-            let patched_sample = adv
-                .patched
-                .iter()
-                .take(5)
-                .map(|s| s.as_str())
-                .collect::<Vec<_>>()
-                .join("|");
-            let unaffected_sample = adv
-                .unaffected
-                .iter()
-                .take(5)
-                .map(|s| s.as_str())
-                .collect::<Vec<_>>()
-                .join("|");
-            let all_versions =
-                query_all_version_numbers_cached(&db, &mut crate_versions_cache, pkg).await?;
-            let mut best: Option<Version> = None;
-            for v_str in &all_versions {
-                if let Ok(v) = Version::parse(v_str) {
-                    match &best {
-                        None => best = Some(v),
-                        Some(b) => {
-                            if v > *b {
-                                best = Some(v);
-                            }
-                        }
-                    }
-                }
-            }
-            let Some(latest_version) = best else {
-                record_skip(
-                    &mut logger,
-                    &mut skipped,
-                    &mut skipped_by_reason,
-                    &adv,
-                    SkipReason::NoFixedVersions,
-                    format!(
-                        "fallback_latest_seed_failed: all_versions_count={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
-                        all_versions.len(),
-                        adv.patched.len(),
-                        adv.unaffected.len(),
-                        patched_sample,
-                        unaffected_sample
-                    ),
-                )?;
-                continue;
-            };
-            let latest_version_str = latest_version.to_string();
-            let resolved_str = resolve_equivalent_version_string(&all_versions, &latest_version)
-                .unwrap_or_else(|| latest_version_str.clone());
-            let latest_time = match db.query_version_time(pkg, &latest_version_str).await? {
-                Some(t) => t,
-                None => match db.query_version_time(pkg, &resolved_str).await? {
-                    Some(t) => t,
-                    None => {
-                        let fetched = crates_io_query_version_time(
-                            &client,
-                            &mut crates_io_time_cache,
-                            pkg,
-                            &resolved_str,
-                        )
-                        .await?;
-                        match fetched {
-                            Some(t) => {
-                                crates_io_time_fallback_hits += 1;
-                                t
-                            }
-                            None => {
-                                crates_io_time_fallback_misses += 1;
-                                record_skip(
-                                    &mut logger,
-                                    &mut skipped,
-                                    &mut skipped_by_reason,
-                                    &adv,
-                                    SkipReason::NoFixTimes,
-                                    format!(
-                                        "fallback_latest_seed_failed: latest_version={} resolved_version={} all_versions_count={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
-                                        latest_version_str,
-                                        resolved_str,
-                                        all_versions.len(),
-                                        adv.patched.len(),
-                                        adv.unaffected.len(),
-                                        patched_sample,
-                                        unaffected_sample
-                                    ),
-                                )?;
-                                continue;
-                            }
-                        }
-                    }
-                },
-            };
-            propagation_fallback_latest_seed += 1;
-            logger.println(format!(
-                "propagation fallback: rustsec_id={} cve_id={} pkg={} reason=no_patched_using_latest_version latest_version={} latest_time={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
-                adv.rustsec_id,
-                adv.cve_id,
-                pkg,
-                latest_version,
-                latest_time,
-                adv.patched.len(),
-                adv.unaffected.len(),
-                patched_sample,
-                unaffected_sample
-            ))?;
-            root_seed = Some(Carrier {
-                crate_name: pkg.to_string(),
-                fix_version: latest_version,
-                fix_time: latest_time,
-                hop: 0,
-            });
-        }
+    #[arg(long)]
+    downstream_min_versions: Option<i64>,
 
-        if fixed_versions.is_empty() && root_seed.is_none() {
-            record_skip(
-                &mut logger,
-                &mut skipped,
-                &mut skipped_by_reason,
-                &adv,
-                SkipReason::NoFixedVersions,
-                format!(
-                    "patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
-                    adv.patched.len(),
-                    adv.unaffected.len(),
-                    adv.patched
-                        .iter()
-                        .take(5)
-                        .map(|s| s.as_str())
-                        .collect::<Vec<_>>()
-                        .join("|"),
-                    adv.unaffected
-                        .iter()
-                        .take(5)
-                        .map(|s| s.as_str())
-                        .collect::<Vec<_>>()
-                        .join("|")
-                ),
-            )?;
-            continue;
-        }
+    #[arg(long)]
+    downstream_active_within_days: Option<i64>,
 
-        // Query times for all fixed versions
-        let mut fix_times = HashMap::new();
-        if !fixed_versions.is_empty() {
-            let all_versions =
-                query_all_version_numbers_cached(&db, &mut crate_versions_cache, pkg).await?;
-            for fv in &fixed_versions {
-                let fv_str = fv.to_string();
-                if let Some(t) = db.query_version_time(pkg, &fv_str).await? {
-                    fix_times.insert(fv.clone(), t);
-                    continue;
-                }
-                let resolved_str = resolve_equivalent_version_string(&all_versions, fv)
-                    .unwrap_or_else(|| fv_str.clone());
-                if let Some(t) = db.query_version_time(pkg, &resolved_str).await? {
-                    fix_times.insert(fv.clone(), t);
-                    continue;
-                }
-                let fetched = crates_io_query_version_time(
-                    &client,
-                    &mut crates_io_time_cache,
-                    pkg,
-                    &resolved_str,
-                )
-                .await?;
-                match fetched {
-                    Some(t) => {
-                        crates_io_time_fallback_hits += 1;
-                        fix_times.insert(fv.clone(), t);
-                    }
-                    None => {
-                        crates_io_time_fallback_misses += 1;
-                    }
-                }
-            }
+    #[arg(long, value_enum, default_value_t = FixMatchPolicy::Either)]
+    fix_match_policy: FixMatchPolicy,
 
-            if fix_times.is_empty() {
-                let mut used_ge_min = false;
-                if adv.patched.iter().any(|s| VersionReq::parse(s).is_ok()) {
-                    let published = parse_published_versions(&all_versions);
-                    for req_str in &adv.patched {
-                        let Ok(req) = VersionReq::parse(req_str) else {
-                            continue;
-                        };
-                        let mut picked: Option<(&Version, &String)> = published
-                            .iter()
-                            .find(|(v, _)| req.matches(v))
-                            .map(|x| (&x.0, &x.1));
-
-                        if picked.is_none()
-                            && !req_str.contains('<')
-                            && let Some(min_v) = estimate_min_version(req_str)
-                        {
-                            picked = published
-                                .iter()
-                                .find(|(v, _)| *v >= min_v)
-                                .map(|x| (&x.0, &x.1));
-                            if picked.is_some() {
-                                used_ge_min = true;
-                            }
-                        }
-
-                        let Some((v, v_str)) = picked else {
-                            continue;
-                        };
-                        if fix_times.contains_key(v) {
-                            continue;
-                        }
-                        if let Some(t) = db.query_version_time(pkg, v_str).await? {
-                            fix_times.insert(v.clone(), t);
-                            continue;
-                        }
-                        let fetched = crates_io_query_version_time(
-                            &client,
-                            &mut crates_io_time_cache,
-                            pkg,
-                            v_str,
-                        )
-                        .await?;
-                        match fetched {
-                            Some(t) => {
-                                crates_io_time_fallback_hits += 1;
-                                fix_times.insert(v.clone(), t);
-                            }
-                            None => {
-                                crates_io_time_fallback_misses += 1;
-                            }
-                        }
-                    }
+    #[arg(long, value_enum, default_value_t = DuplicateReqs::LeastRestrictive)]
+    duplicate_dep_reqs: DuplicateReqs,
 
-                    if !fix_times.is_empty() {
-                        let mut xs: Vec<_> = fix_times.keys().cloned().collect();
-                        xs.sort();
-                        let reason = if used_ge_min {
-                            "patched_req_first_published_or_ge_min"
-                        } else {
-                            "patched_req_first_published"
-                        };
-                        logger.println(format!(
-                            "fixed_version fallback: rustsec_id={} cve_id={} pkg={} reason={} fixed_versions_sample={}",
-                            adv.rustsec_id,
-                            adv.cve_id,
-                            pkg,
-                            reason,
-                            xs.iter()
-                                .take(5)
-                                .map(|v| v.to_string())
-                                .collect::<Vec<_>>()
-                                .join("|")
-                        ))?;
-                    }
-                }
+    #[arg(long, default_value_t = false)]
+    exclude_target_specific_deps: bool,
 
-                if fix_times.is_empty() {
-                    record_skip(
-                        &mut logger,
-                        &mut skipped,
-                        &mut skipped_by_reason,
-                        &adv,
-                        SkipReason::NoFixTimes,
-                        format!(
-                            "fixed_versions_count={} fixed_versions_sample={} db_versions_count={}",
-                            fixed_versions.len(),
-                            fixed_versions
-                                .iter()
-                                .take(5)
-                                .map(|v| v.to_string())
-                                .collect::<Vec<_>>()
-                                .join("|"),
-                            all_versions.len()
-                        ),
-                    )?;
-                    continue;
-                }
-            }
-        }
+    #[arg(long, default_value_t = false)]
+    exclude_yanked_versions: bool,
 
-        let mut effective_fixed_versions: Vec<Version> = fix_times.keys().cloned().collect();
-        effective_fixed_versions.sort();
-        let min_fixed_version = effective_fixed_versions.first().cloned();
-        let min_fixed_version_str = min_fixed_version.as_ref().map(|v| v.to_string());
+    // Connect, report which optional columns the snapshot has, and exit.
+    #[arg(long, default_value_t = false)]
+    check_db: bool,
 
-        // If the smallest version has no time (unlikely if fix_times is not empty, but possible if partial failure),
-        // we try to find the earliest time among available ones for summary.
-        let mut rows: Vec<StrictLagRow> = Vec::new();
-        if !fix_times.is_empty() {
-            let summary_t0 = min_fixed_version
-                .as_ref()
-                .and_then(|v| fix_times.get(v).cloned())
-                .or_else(|| fix_times.values().min().cloned());
-
-            let Some(summary_t0) = summary_t0 else {
-                record_skip(
-                    &mut logger,
-                    &mut skipped,
-                    &mut skipped_by_reason,
-                    &adv,
-                    SkipReason::NoSummaryT0,
-                    format!(
-                        "fixed_versions_count={} fix_times_count={}",
-                        fixed_versions.len(),
-                        fix_times.len()
-                    ),
-                )?;
-                continue;
-            };
+    #[arg(long, value_enum, default_value_t = FixSelection::All)]
+    fix_selection: FixSelection,
 
-            let all_versions =
-                query_all_version_numbers_cached(&db, &mut crate_versions_cache, pkg).await?;
-            let vuln_versions =
-                identify_vuln_versions(&all_versions, &adv.patched, &adv.unaffected);
-
-            if vuln_versions.is_empty() {
-                record_skip(
-                    &mut logger,
-                    &mut skipped,
-                    &mut skipped_by_reason,
-                    &adv,
-                    SkipReason::NoVulnVersions,
-                    format!(
-                        "all_versions_count={} patched_versions_count={} unaffected_versions_count={}",
-                        all_versions.len(),
-                        adv.patched.len(),
-                        adv.unaffected.len()
-                    ),
-                )?;
-                continue;
-            }
+    #[arg(long, default_value_t = 3)]
+    timestamp_check_samples: usize,
 
-            let downstream = cache.get_or_fetch(&db, pkg).await?;
-            rows = compute_strict_lags_for_target(&fix_times, &vuln_versions, downstream);
+    #[arg(long, default_value_t = 24)]
+    timestamp_anomaly_hours: i64,
 
-            if args.constraint {
-                if args.constraint_min_age_days > 0
-                    && (now - summary_t0).num_days() < args.constraint_min_age_days
-                {
-                    continue;
-                }
-                let fixed_set: Vec<Version> = fix_times.keys().cloned().collect();
-                let c = compute_constraint_breakdown(
-                    summary_t0,
-                    &vuln_versions,
-                    &fixed_set,
-                    downstream,
-                );
-                constraint_totals.add(&c);
-
-                if let Some(w) = constraint_breakdown_writer.as_mut() {
-                    w.write_record([
-                        adv.rustsec_id.clone(),
-                        adv.cve_id.clone(),
-                        adv.severity.clone(),
-                        pkg.to_string(),
-                        summary_t0.to_string(),
-                        c.downstream_crates_with_history.to_string(),
-                        c.affected_edges.to_string(),
-                        c.locked_out_edges.to_string(),
-                        c.break_rate_percent.to_string(),
-                        c.affected_req_exact_pin.to_string(),
-                        c.affected_req_has_upper_bound.to_string(),
-                        c.affected_req_caret_0x.to_string(),
-                        c.affected_req_other.to_string(),
-                        c.unknown_req_unparseable.to_string(),
-                    ])?;
-                }
+    #[arg(long, default_value_t = false)]
+    prefer_crates_io_times: bool,
 
-                if c.affected_edges > 0 {
-                    constraint_break_rate_per_adv_percent.push(c.break_rate_percent as i64);
-                }
-            }
+    #[arg(long, default_value_t = false)]
+    strict_parse: bool,
 
-            let stats = compute_lag_stats(rows.iter().map(|r| r.lag_days));
-            if let Some(stats) = stats {
-                sw.write_record([
-                    adv.rustsec_id.clone(),
-                    adv.cve_id.clone(),
-                    adv.severity.clone(),
-                    pkg.to_string(),
-                    min_fixed_version_str
-                        .clone()
-                        .unwrap_or_else(|| "".to_string()),
-                    summary_t0.to_string(),
-                    stats.count.to_string(),
-                    stats.min.to_string(),
-                    format_float(stats.p50),
-                    format_float(stats.avg),
-                    stats.max.to_string(),
-                ])?;
-            }
-        }
+    #[arg(long)]
+    advisory_db_zip: Option<String>,
 
-        if args.propagation {
-            let mut best_seen: HashMap<String, (usize, chrono::DateTime<chrono::Utc>)> =
-                HashMap::new();
-            let mut queue: VecDeque<Carrier> = VecDeque::new();
-            let mut last_adv_progress = Instant::now();
-            let mut propagated_events = 0usize;
-
-            if let Some(seed) = root_seed {
-                let downstream = cache.get_or_fetch(&db, &seed.crate_name).await?;
-                let events = compute_adoption_events_for_target(
-                    &seed.fix_version,
-                    seed.fix_time,
-                    downstream,
-                );
-                for ev in events {
-                    let recomputed = (ev.downstream_time - seed.fix_time).num_days();
-                    if recomputed != ev.lag_days {
-                        return Err(anyhow!(
-                            "lag_days mismatch hop=1: {} {} -> {} {} csv={} recomputed={}",
-                            adv.package,
-                            seed.fix_time,
-                            ev.downstream_crate,
-                            ev.downstream_time,
-                            ev.lag_days,
-                            recomputed
-                        ));
-                    }
-                    propagated_events += 1;
-                    propagation_lags_by_hop
-                        .entry(1)
-                        .or_default()
-                        .push(ev.lag_days);
-                    if let Some(w) = propagation_events_writer.as_mut() {
-                        let can_write = args.propagation_events_limit == 0
-                            || propagation_events_written < args.propagation_events_limit;
-                        if can_write {
-                            w.write_record([
-                                adv.rustsec_id.clone(),
-                                adv.cve_id.clone(),
-                                pkg.to_string(),
-                                "1".to_string(),
-                                pkg.to_string(),
-                                seed.fix_version.to_string(),
-                                seed.fix_time.to_string(),
-                                ev.downstream_crate.clone(),
-                                ev.downstream_version.to_string(),
-                                ev.downstream_time.to_string(),
-                                ev.lag_days.to_string(),
-                                ev.dep_req.clone(),
-                            ])?;
-                            propagation_events_written += 1;
-                        }
-                    }
-                    if args.propagation_verify_samples > 0
-                        && propagation_verify_samples.len() < args.propagation_verify_samples
-                    {
-                        propagation_verify_samples.push((
-                            pkg.to_string(),
-                            ev.downstream_crate.clone(),
-                            ev.downstream_version.to_string(),
-                            ev.downstream_time,
-                            ev.dep_req.clone(),
-                        ));
-                    }
-                    let can_expand = match args.propagation_max_hops {
-                        None => true,
-                        Some(max_hops) => 1 < max_hops,
-                    };
-                    if can_expand {
-                        let key = ev.downstream_crate.clone();
-                        best_seen.insert(key.clone(), (1, ev.downstream_time));
-                        queue.push_back(Carrier {
-                            crate_name: key,
-                            fix_version: ev.downstream_version,
-                            fix_time: ev.downstream_time,
-                            hop: 1,
-                        });
-                    }
-                }
-            } else {
-                for r in &rows {
-                    let recomputed = (r.downstream_time - r.matched_fix_time).num_days();
-                    if recomputed != r.lag_days {
-                        return Err(anyhow!(
-                            "lag_days mismatch hop=1: {} {} -> {} {} csv={} recomputed={}",
-                            adv.package,
-                            r.matched_fix_time,
-                            r.downstream_crate,
-                            r.downstream_time,
-                            r.lag_days,
-                            recomputed
-                        ));
-                    }
+    #[arg(long, default_value_t = false)]
+    strict_advisories: bool,
 
-                    propagation_lags_by_hop
-                        .entry(1)
-                        .or_default()
-                        .push(r.lag_days);
-                    if let Some(w) = propagation_events_writer.as_mut() {
-                        let can_write = args.propagation_events_limit == 0
-                            || propagation_events_written < args.propagation_events_limit;
-                        if can_write {
-                            w.write_record([
-                                adv.rustsec_id.clone(),
-                                adv.cve_id.clone(),
-                                pkg.to_string(),
-                                "1".to_string(),
-                                pkg.to_string(),
-                                r.matched_fix_version.clone(),
-                                r.matched_fix_time.to_string(),
-                                r.downstream_crate.clone(),
-                                r.downstream_version.clone(),
-                                r.downstream_time.to_string(),
-                                r.lag_days.to_string(),
-                                r.fixed_req.clone(),
-                            ])?;
-                            propagation_events_written += 1;
-                        }
-                    }
-                    if args.propagation_verify_samples > 0
-                        && propagation_verify_samples.len() < args.propagation_verify_samples
-                    {
-                        propagation_verify_samples.push((
-                            pkg.to_string(),
-                            r.downstream_crate.clone(),
-                            r.downstream_version.clone(),
-                            r.downstream_time,
-                            r.fixed_req.clone(),
-                        ));
-                    }
+    #[arg(long, value_name = "RUSTSEC_ID")]
+    explain: Option<String>,
 
-                    let can_expand = match args.propagation_max_hops {
-                        None => true,
-                        Some(max_hops) => 1 < max_hops,
-                    };
-                    if can_expand && let Ok(v) = Version::parse(&r.downstream_version) {
-                        let key = r.downstream_crate.clone();
-                        best_seen.insert(key.clone(), (1, r.downstream_time));
-                        queue.push_back(Carrier {
-                            crate_name: key,
-                            fix_version: v,
-                            fix_time: r.downstream_time,
-                            hop: 1,
-                        });
-                    }
-                }
-            }
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_opt_path")]
+    explain_output: Option<PathBuf>,
 
-            while let Some(carrier) = queue.pop_front() {
-                if let Some(max_hops) = args.propagation_max_hops
-                    && carrier.hop >= max_hops
-                {
-                    continue;
-                }
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<String>,
 
-                let next_hop = carrier.hop + 1;
-                if let Some(max_hops) = args.propagation_max_hops
-                    && next_hop > max_hops
-                {
-                    continue;
-                }
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    print_config: bool,
+}
 
-                if last_adv_progress.elapsed() >= Duration::from_secs(5) {
-                    logger.println(format!(
-                        "propagation: adv={}/{} pkg={} queue={} seen={} events={} elapsed={:.1}s",
-                        processed,
-                        total_advisories,
-                        pkg,
-                        queue.len(),
-                        best_seen.len(),
-                        propagated_events,
-                        start.elapsed().as_secs_f64()
-                    ))?;
-                    last_adv_progress = Instant::now();
-                }
+// Exit codes besides 0 (done, no advisory errored) and 1 (the run failed after it started).
+const EXIT_ADVISORY_ERRORS: u8 = 2;
+const EXIT_CONFIG: u8 = 3;
+const EXIT_TRUNCATED: u8 = 4;
 
-                let downstream = cache.get_or_fetch(&db, &carrier.crate_name).await?;
-                let events = compute_adoption_events_for_target(
-                    &carrier.fix_version,
-                    carrier.fix_time,
-                    downstream,
-                );
-                for ev in events {
-                    let recomputed = (ev.downstream_time - carrier.fix_time).num_days();
-                    if recomputed != ev.lag_days {
-                        return Err(anyhow!(
-                            "lag_days mismatch hop={}: {} {} -> {} {} csv={} recomputed={}",
-                            next_hop,
-                            carrier.crate_name,
-                            carrier.fix_time,
-                            ev.downstream_crate,
-                            ev.downstream_time,
-                            ev.lag_days,
-                            recomputed
-                        ));
-                    }
+// A failure before the first advisory is touched: an unreadable input, an unreachable
+// database or an output that cannot be created. `main` exits with EXIT_CONFIG for these.
+#[derive(Debug)]
+struct SetupError(anyhow::Error);
 
-                    propagated_events += 1;
-                    propagation_lags_by_hop
-                        .entry(next_hop)
-                        .or_default()
-                        .push(ev.lag_days);
-
-                    if let Some(w) = propagation_events_writer.as_mut() {
-                        let can_write = args.propagation_events_limit == 0
-                            || propagation_events_written < args.propagation_events_limit;
-                        if can_write {
-                            w.write_record([
-                                adv.rustsec_id.clone(),
-                                adv.cve_id.clone(),
-                                pkg.to_string(),
-                                next_hop.to_string(),
-                                carrier.crate_name.clone(),
-                                carrier.fix_version.to_string(),
-                                carrier.fix_time.to_string(),
-                                ev.downstream_crate.clone(),
-                                ev.downstream_version.to_string(),
-                                ev.downstream_time.to_string(),
-                                ev.lag_days.to_string(),
-                                ev.dep_req.clone(),
-                            ])?;
-                            propagation_events_written += 1;
-                        }
-                    }
-                    if args.propagation_verify_samples > 0
-                        && propagation_verify_samples.len() < args.propagation_verify_samples
-                    {
-                        propagation_verify_samples.push((
-                            carrier.crate_name.clone(),
-                            ev.downstream_crate.clone(),
-                            ev.downstream_version.to_string(),
-                            ev.downstream_time,
-                            ev.dep_req.clone(),
-                        ));
-                    }
+impl std::fmt::Display for SetupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
-                    let can_expand = match args.propagation_max_hops {
-                        None => true,
-                        Some(max_hops) => next_hop < max_hops,
-                    };
-                    if !can_expand {
-                        continue;
-                    }
+impl std::error::Error for SetupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
 
-                    let should_push = match best_seen.get(&ev.downstream_crate) {
-                        None => true,
-                        Some((seen_hop, seen_time)) => {
-                            next_hop < *seen_hop
-                                || (next_hop == *seen_hop && ev.downstream_time < *seen_time)
-                        }
-                    };
-                    if should_push {
-                        let key = ev.downstream_crate.clone();
-                        best_seen.insert(key.clone(), (next_hop, ev.downstream_time));
-                        queue.push_back(Carrier {
-                            crate_name: key,
-                            fix_version: ev.downstream_version,
-                            fix_time: ev.downstream_time,
-                            hop: next_hop,
-                        });
-                    }
-                }
+fn setup_error(e: anyhow::Error) -> anyhow::Error {
+    SetupError(e).into()
+}
+
+// The last stdout line of a run that got through every advisory.
+#[derive(serde::Serialize)]
+struct RunOutcome {
+    processed: usize,
+    written_rows: usize,
+    skipped: usize,
+    errors: usize,
+    outputs: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let config_error = |e: anyhow::Error| {
+        eprintln!("Error: {e:?}");
+        ExitCode::from(EXIT_CONFIG)
+    };
+    let mut args: Args = match config::try_parse_args() {
+        Ok(args) => args,
+        Err(e) => return config_error(e),
+    };
+    if args.print_config {
+        return match config::to_toml(&args) {
+            Ok(text) => {
+                print!("{text}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => config_error(e),
+        };
+    }
+    let collapse_prefix = match configure(&mut args) {
+        Ok(re) => re,
+        Err(e) => return config_error(e),
+    };
+    match run(args, collapse_prefix).await {
+        Ok(outcome) => {
+            let line = serde_json::to_string(&outcome).expect("RunOutcome serializes");
+            println!("{line}");
+            if outcome.errors > 0 {
+                ExitCode::from(EXIT_ADVISORY_ERRORS)
+            } else if outcome.truncated {
+                ExitCode::from(EXIT_TRUNCATED)
+            } else {
+                ExitCode::SUCCESS
             }
         }
-
-        for row in rows {
-            w.write_record([
-                adv.rustsec_id.clone(),
-                adv.cve_id.clone(),
-                adv.severity.clone(),
-                pkg.to_string(),
-                row.matched_fix_version.clone(),
-                row.matched_fix_time.to_string(),
-                row.downstream_crate,
-                row.downstream_version,
-                row.downstream_time.to_string(),
-                row.lag_days.to_string(),
-                row.original_req,
-                row.fixed_req,
-            ])?;
-            written_rows += 1;
+        Err(e) if e.is::<SetupError>() => config_error(e),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::FAILURE
         }
     }
+}
 
-    w.flush()?;
-    sw.flush()?;
-    if let Some(w) = propagation_events_writer.as_mut() {
-        w.flush()?;
+// Normalizes the arguments and rejects bad combinations before any work is done.
+fn configure(args: &mut Args) -> Result<Option<Regex>> {
+    args.output = args.compress.apply_to(&args.output);
+    args.constraint_breakdown_output = args.compress.apply_to(&args.constraint_breakdown_output);
+    args.propagation_events_output = args
+        .propagation_events_output
+        .take()
+        .map(|p| args.compress.apply_to(&p));
+    args.chart_style = ChartStyle::new(
+        args.chart_width,
+        args.chart_height,
+        args.chart_font_scale,
+        &args.chart_palette,
+    )?;
+    args.package_aliases = PackageAliases::parse(&args.package_alias)?;
+    if let Some(path) = args.crate_renames.as_deref() {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read --crate-renames {path}: {e}"))?;
+        args.renames =
+            CrateRenames::parse(&text).map_err(|e| anyhow!("--crate-renames {path}: {e}"))?;
     }
-    if let Some(w) = constraint_breakdown_writer.as_mut() {
-        w.flush()?;
+    args.adoption_curve_days.sort_unstable();
+    args.adoption_curve_days.dedup();
+    args.dependent_tiers.sort_unstable();
+    args.dependent_tiers.dedup();
+    if let Some(m) = args.max_runtime_minutes
+        && !(m >= 0.0 && m.is_finite())
+    {
+        return Err(anyhow!(
+            "--max-runtime-minutes {m}: must be a non-negative number of minutes"
+        ));
+    }
+    if let Some(d) = args.propagation_within_days
+        && !(d >= 0.0 && d.is_finite())
+    {
+        return Err(anyhow!(
+            "--propagation-within-days {d}: must be a non-negative number of days"
+        ));
+    }
+    if args.sort_events && args.propagation_events_output.is_none() {
+        return Err(anyhow!("--sort-events needs --propagation-events-output"));
+    }
+    #[cfg(not(feature = "parquet"))]
+    if args.parquet_output_dir.is_some() {
+        return Err(anyhow!(
+            "--parquet-output-dir needs a build with `--features parquet`"
+        ));
+    }
+    let collapse_prefix = match args.collapse_prefix_regex.as_deref() {
+        Some(p) => {
+            let re = Regex::new(p).map_err(|e| anyhow!("--collapse-prefix-regex {p}: {e}"))?;
+            if re.captures_len() < 2 {
+                return Err(anyhow!(
+                    "--collapse-prefix-regex {p}: needs a capture group for the workspace prefix"
+                ));
+            }
+            Some(re)
+        }
+        None => None,
+    };
+    Ok(collapse_prefix)
+}
+
+async fn run(mut args: Args, collapse_prefix: Option<Regex>) -> Result<RunOutcome> {
+    let started_at = Utc::now();
+    let run_start = Instant::now();
+    ensure_output_dirs(&args).map_err(setup_error)?;
+    let mut logger =
+        Logger::new(args.log_output.as_deref(), args.log_level).map_err(setup_error)?;
+
+    if args.check_db {
+        let db = Database::connect(args.data_source, args.dump_dir.as_deref())
+            .await
+            .map_err(setup_error)?;
+        logger.println(format!("database: {}", db.name()))?;
+        for line in db.schema_report().lines() {
+            logger.println(line)?;
+        }
+        logger.flush()?;
+        return Ok(RunOutcome {
+            processed: 0,
+            written_rows: 0,
+            skipped: 0,
+            errors: 0,
+            outputs: Vec::new(),
+            truncated: false,
+        });
     }
-    logger.flush()?;
 
-    if args.propagation {
-        use std::io::Write;
+    let client = Client::builder()
+        .user_agent("time-to-fix-cve/0.1")
+        .build()
+        .map_err(|e| setup_error(e.into()))?;
+
+    let (advisories, advisory_source, cve_coverage) =
+        BatchRun::load_advisories(&mut args, &client, &mut logger)
+            .await
+            .map_err(setup_error)?;
+
+    if let Some(path) = &args.list_advisories {
+        ensure_parent_dir(path)?;
+        write_advisory_list(path, &advisories, args.prerelease_policy)?;
+        logger.println(format!("wrote advisory list: {}", path.display()))?;
+        logger.flush()?;
+        return Ok(RunOutcome {
+            processed: 0,
+            written_rows: 0,
+            skipped: 0,
+            errors: 0,
+            outputs: vec![path.to_string_lossy().into_owned()],
+            truncated: false,
+        });
+    }
 
-        let mut hops: Vec<_> = propagation_lags_by_hop.into_iter().collect();
-        hops.sort_by_key(|(h, _)| *h);
+    match (args.data_source, args.dump_dir.as_deref()) {
+        (DataSource::CsvDump, Some(dir)) => logger.println(format!(
+            "warning: loading the crates.io dump from {} into memory ({:.0} MiB of CSV); a full dump needs several GiB of RAM",
+            dir,
+            DumpStore::csv_bytes(dir) as f64 / (1024.0 * 1024.0)
+        ))?,
+        _ => logger.println("connecting to postgres...")?,
+    }
+    let mut db = Database::connect(args.data_source, args.dump_dir.as_deref())
+        .await
+        .map_err(setup_error)?;
+    db.exclude_target_specific_deps(args.exclude_target_specific_deps)
+        .map_err(setup_error)?;
+    db.exclude_yanked_versions(args.exclude_yanked_versions)
+        .map_err(setup_error)?;
+    let mut metadata = RunMetadata {
+        tool: "rqx2_rustsec_batch",
+        version: env!("CARGO_PKG_VERSION"),
+        git_describe: env!("GIT_DESCRIBE"),
+        args: &args,
+        advisory_db: advisory_source,
+        database: db.name().to_string(),
+        snapshot_freshness: db.snapshot_freshness().await.map_err(setup_error)?,
+        started_at,
+        finished_at: None,
+        truncated: None,
+        crates_io_version_time: BTreeMap::new(),
+    };
+    logger.println(format!(
+        "advisory-db commit: {}, crates.io snapshot freshness: {}",
+        metadata.advisory_db.commit.as_deref().unwrap_or("unknown"),
+        metadata
+            .snapshot_freshness
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    ))?;
 
-        let mut f = std::fs::File::create(&args.propagation_summary_output)?;
-        let max_hop = hops.iter().map(|(h, _)| *h).max().unwrap_or(0);
-        writeln!(f, "patch propagation analysis (hops=1..{})", max_hop)?;
-        if let Some(max_hops) = args.propagation_max_hops {
-            writeln!(f, "max_hops_limit = {}", max_hops)?;
+    let mut batch = BatchRun::new(
+        &args,
+        &db,
+        &client,
+        logger,
+        collapse_prefix,
+        advisories.len(),
+        cve_coverage,
+    )
+    .map_err(setup_error)?;
+    if args.prefetch {
+        batch.prefetch(&advisories).await?;
+    }
+    batch.last_progress = Instant::now();
+    if args.progress.enabled() {
+        batch.ctx.logger.start_progress(
+            advisories
+                .len()
+                .min(args.max_advisories.unwrap_or(usize::MAX)),
+        )?;
+    }
+    for (i, adv) in advisories.iter().enumerate() {
+        if let Some(limit) = args.max_advisories
+            && batch.ctx.processed >= limit
+        {
+            break;
         }
-        writeln!(f)?;
+        if let Some(minutes) = args.max_runtime_minutes
+            && run_start.elapsed().as_secs_f64() >= minutes * 60.0
+        {
+            let remaining: Vec<String> = advisories[i..]
+                .iter()
+                .take(
+                    args.max_advisories
+                        .map_or(usize::MAX, |m| m - batch.ctx.processed),
+                )
+                .map(|a| a.rustsec_id.clone())
+                .collect();
+            batch.ctx.logger.println(format!(
+                "stopping: --max-runtime-minutes {minutes} reached after {:.1}s; finalizing partial outputs",
+                run_start.elapsed().as_secs_f64()
+            ))?;
+            metadata.truncated = Some(RunTruncation {
+                max_runtime_minutes: minutes,
+                last_completed: i.checked_sub(1).map(|j| advisories[j].rustsec_id.clone()),
+                remaining,
+            });
+            break;
+        }
+        if let Err(e) = batch.process_advisory(adv).await {
+            batch.ctx.logger.finish_progress();
+            batch.writers.finish()?;
+            return Err(e);
+        }
+    }
+    batch.write_outputs()?;
+    batch.finalize(&mut metadata).await
+}
 
-        let mut all_lags: Vec<i64> = Vec::new();
-        for (_, lags) in &hops {
-            all_lags.extend_from_slice(lags);
+fn ensure_output_dirs(args: &Args) -> Result<()> {
+    ensure_parent_dir(&args.output)?;
+    ensure_parent_dir(&args.summary_output)?;
+    ensure_parent_dir(&args.errors_output)?;
+    if let Some(p) = args.timings_output.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if args.propagation {
+        ensure_parent_dir(&args.propagation_summary_output)?;
+        ensure_parent_dir(&args.propagation_coverage_output)?;
+        ensure_parent_dir(&args.propagation_reach_output)?;
+    }
+    if let Some(p) = args.propagation_events_output.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if args.constraint {
+        ensure_parent_dir(&args.constraint_breakdown_output)?;
+        ensure_parent_dir(&args.constraint_summary_output)?;
+        if let Some(p) = args.constraint_edges_output.as_deref() {
+            ensure_parent_dir(p)?;
         }
-        if let Some(stats) = compute_lag_stats(all_lags.iter().copied()) {
-            writeln!(f, "all hops")?;
-            writeln!(f, "  count = {}", stats.count)?;
-            writeln!(f, "  min   = {} days", stats.min)?;
-            writeln!(f, "  p50   = {:.4} days", stats.p50)?;
-            writeln!(f, "  avg   = {:.4} days", stats.avg)?;
-            writeln!(f, "  max   = {} days", stats.max)?;
-            writeln!(f)?;
+        if !args.constraint_horizons.is_empty() {
+            ensure_parent_dir(&args.constraint_horizons_output)?;
         }
+    }
+    if let Some(p) = args.html_report.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if let Some(p) = args.adoption_curve_output.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if let Some(p) = args.category_summary_output.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if let Some(p) = args.metadata_output.as_deref() {
+        ensure_parent_dir(p)?;
+    }
+    if args.cve_list.is_some() {
+        ensure_parent_dir(&args.cve_coverage_output)?;
+    }
+    Ok(())
+}
 
-        for (hop, lags) in &hops {
-            if lags.is_empty() {
-                continue;
+// One batch run. `run` drives the stages in order: load_advisories, new, prefetch,
+// process_advisory per advisory, write_outputs and finalize.
+struct BatchRun<'a> {
+    ctx: BatchContext<'a>,
+    writers: BatchWriters,
+    errors_writer: csv::Writer<std::fs::File>,
+    timings_writer: Option<csv::Writer<std::fs::File>>,
+    totals: RunTotals,
+    errored: usize,
+    advisory_durations: Vec<(Duration, String, String)>,
+    cve_coverage: Option<CveCoverage>,
+    last_progress: Instant,
+}
+
+impl<'a> BatchRun<'a> {
+    // Reads the advisory-db and applies --cve-list and the advisory filters.
+    async fn load_advisories(
+        args: &mut Args,
+        client: &Client,
+        logger: &mut Logger,
+    ) -> Result<(Vec<Advisory>, AdvisorySource, Option<CveCoverage>)> {
+        let (mut advisories, advisory_source) = match args.advisory_db_zip.as_deref() {
+            Some(path) => {
+                logger.println(format!("reading rustsec advisory-db from {path}..."))?;
+                let bytes = std::fs::read(path).map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+                parse_advisory_archive(&bytes, path, args.strict_parse)?
             }
-            if let Some(stats) = compute_lag_stats(lags.iter().copied()) {
-                writeln!(f, "hop {}", hop)?;
-                writeln!(f, "  count = {}", stats.count)?;
-                writeln!(f, "  min   = {} days", stats.min)?;
-                writeln!(f, "  p50   = {:.4} days", stats.p50)?;
-                writeln!(f, "  avg   = {:.4} days", stats.avg)?;
-                writeln!(f, "  max   = {} days", stats.max)?;
-                writeln!(f)?;
+            None => {
+                logger.println("downloading rustsec advisory-db...")?;
+                fetch_rustsec_advisories_with_source(client, args.strict_parse).await?
             }
+        };
+        if advisory_source.parse_error_count > 0 {
+            logger.println(format!(
+            "warning: {} advisory files failed to parse and were skipped (listed at the end of the run; --strict-parse aborts instead)",
+            advisory_source.parse_error_count
+        ))?;
         }
-
-        let out_dir = Path::new(&args.propagation_output_dir);
-        std::fs::create_dir_all(out_dir)?;
-        if let Ok(rd) = std::fs::read_dir(out_dir) {
-            for ent in rd.flatten() {
-                let p = ent.path();
-                if p.is_file()
-                    && let Some(name) = p.file_name().and_then(|s| s.to_str())
-                    && name.starts_with("propagation_lag_hist_")
-                    && name.ends_with(".svg")
-                {
-                    let _ = std::fs::remove_file(p);
-                }
+        if let Some(path) = args.severity_overrides.as_deref() {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+            let overrides = parse_severity_overrides(&text)
+                .map_err(|e| anyhow!("--severity-overrides {path}: {e}"))?;
+            let unknown = apply_severity_overrides(&mut advisories, &overrides);
+            for id in &unknown {
+                logger.println(format!(
+                    "warning: severity override for {id} matches no advisory in advisory-db"
+                ))?;
             }
+            logger.println(format!(
+                "severity overrides: {} applied from {path}, {} unknown ids",
+                overrides.len() - unknown.len(),
+                unknown.len()
+            ))?;
         }
-
-        if !all_lags.is_empty() {
-            let x_max = all_lags.iter().copied().max().unwrap_or(1).max(1);
-            let svg_path = out_dir.join("propagation_lag_hist_all.svg");
-            write_hist_svg(
-                &svg_path,
-                &all_lags,
-                args.propagation_bins,
-                x_max,
-                true,
-                &format!(
-                    "propagation lag_days histogram (all hops, n={})",
-                    all_lags.len()
-                ),
-                &format!(
-                    "bins={}, x_max={}, y_scale=log10",
-                    args.propagation_bins, x_max
-                ),
-            )?;
+        if let Some(id) = &args.explain {
+            args.only = vec![id.clone()];
         }
-
-        for (hop, lags) in &hops {
-            if lags.is_empty() {
-                continue;
+        let mut cve_coverage = match args.cve_list.as_deref() {
+            Some(path) => {
+                let coverage = CveCoverage::load(path, &advisories)?;
+                let wanted = coverage.rustsec_ids();
+                advisories.retain(|a| wanted.contains(&a.rustsec_id));
+                logger.println(format!(
+                    "cve list: {} CVEs, {} found in advisory-db, {} advisories",
+                    coverage.cves.len(),
+                    coverage.index.len(),
+                    advisories.len()
+                ))?;
+                Some(coverage)
             }
-            let x_max = lags.iter().copied().max().unwrap_or(1).max(1);
-            let svg_path = out_dir.join(format!("propagation_lag_hist_hop_{}.svg", hop));
-            write_hist_svg(
-                &svg_path,
-                lags,
-                args.propagation_bins,
-                x_max,
-                true,
-                &format!(
-                    "propagation lag_days histogram (hop={}, n={})",
-                    hop,
-                    lags.len()
-                ),
-                &format!(
-                    "bins={}, x_max={}, y_scale=log10",
-                    args.propagation_bins, x_max
-                ),
-            )?;
+            None => None,
+        };
+        apply_advisory_filters(args, &mut advisories, logger)?;
+        let duplicates = dedupe_by_cve(&mut advisories, args.dedupe_by_cve);
+        if args.dedupe_by_cve == CveDedupe::KeepAll {
+            if !duplicates.is_empty() {
+                logger.println(format!(
+                    "dedupe by cve: policy=keep-all advisories repeating an earlier CVE={} (summary column duplicate_of)",
+                    duplicates.len()
+                ))?;
+            }
+        } else {
+            for d in &duplicates {
+                logger.println(format!(
+                    "dedupe by cve: suppressed {} (duplicate of {} via {})",
+                    d.rustsec_id, d.duplicate_of, d.cve
+                ))?;
+            }
+            logger.println(format!(
+                "dedupe by cve: policy={} suppressed={}",
+                args.dedupe_by_cve.as_str(),
+                duplicates.len()
+            ))?;
         }
+        if let Some(c) = cve_coverage.as_mut() {
+            c.kept = advisories.iter().map(|a| a.rustsec_id.clone()).collect();
+        }
+        if let Some(id) = &args.explain
+            && advisories.is_empty()
+        {
+            return Err(anyhow!(
+                "--explain {id}: no such advisory left after the filters"
+            ));
+        }
+        let total_advisories = advisories.len();
+        logger.println(format!("rustsec advisories loaded: {total_advisories}"))?;
+        log_severity_reclassification(logger, &advisories)?;
+        #[cfg(feature = "cvss-crate")]
+        log_cvss_disagreements(logger, &advisories)?;
+        Ok((advisories, advisory_source, cve_coverage))
     }
 
-    if args.constraint {
-        use std::io::Write;
+    fn new(
+        args: &'a Args,
+        db: &'a Database,
+        client: &'a Client,
+        mut logger: Logger,
+        collapse_prefix: Option<Regex>,
+        total_advisories: usize,
+        cve_coverage: Option<CveCoverage>,
+    ) -> Result<Self> {
+        let writers = BatchWriters::open(args, collapse_prefix.is_some())?;
+        let mut errors_writer =
+            csv::Writer::from_writer(std::fs::File::create(&args.errors_output)?);
+        errors_writer.write_record(["rustsec_id", "cve_id", "package", "error"])?;
+        let timings_writer = match args.timings_output.as_deref() {
+            Some(path) => {
+                let mut w = csv::Writer::from_writer(std::fs::File::create(path)?);
+                w.write_record([
+                    "rustsec_id",
+                    "package",
+                    "status",
+                    "total_ms",
+                    "fetch_ms",
+                    "strict_ms",
+                    "constraint_ms",
+                    "propagation_ms",
+                    "downstream_rows",
+                    "propagation_carriers",
+                    "propagation_max_queue",
+                    "cycle_edges",
+                    "revisit_edges",
+                    "queue_capped",
+                ])?;
+                Some(w)
+            }
+            None => None,
+        };
 
-        let mut f = std::fs::File::create(&args.constraint_summary_output)?;
-        writeln!(
-            f,
-            "constraint break analysis (edge=downstream crate at fix_time)"
-        )?;
-        if args.constraint_min_age_days > 0 {
-            writeln!(f, "min_age_days = {}", args.constraint_min_age_days)?;
+        if args.crate_renames.is_some() {
+            logger.println(format!("crate renames loaded: {}", args.renames.len()))?;
         }
-        writeln!(f)?;
-        writeln!(f, "totals")?;
-        writeln!(
-            f,
-            "  downstream_crates_with_history = {}",
-            constraint_totals.downstream_crates_with_history
-        )?;
-        writeln!(
-            f,
-            "  affected_edges                = {}",
-            constraint_totals.affected_edges
-        )?;
-        writeln!(
-            f,
-            "  locked_out_edges              = {}",
-            constraint_totals.locked_out_edges
-        )?;
+        let analysis = AnalysisOptions {
+            package_aliases: &args.package_aliases,
+            renames: &args.renames,
+            prerelease_policy: args.prerelease_policy,
+            fix_selection: args.fix_selection,
+            fix_match_policy: args.fix_match_policy,
+            t0: args.t0,
+            lag_mode: args.lag_mode,
+            all_adoptions: args.all_adoptions,
+            withdrawn: args.withdrawn,
+            strict_advisories: args.strict_advisories,
+            duplicate_reqs: args.duplicate_dep_reqs,
+            constraint: args.constraint,
+            constraint_whatif: args.constraint_whatif,
+            constraint_edges: writers.constraint_edges.is_some(),
+            constraint_horizons: match writers.constraint_horizons {
+                Some(_) => &args.constraint_horizons,
+                None => &[],
+            },
+        };
+        let as_of_cutoff = args.as_of.map(as_of_cutoff);
+        let ctx = BatchContext {
+            args,
+            db,
+            client,
+            logger,
+            crates_io_times: CratesIoTimes::default(),
+            crate_versions_cache: HashMap::new(),
+            canonical_names: HashMap::new(),
+            crate_downloads_cache: HashMap::new(),
+            crate_activity_cache: HashMap::new(),
+            dependent_counts_cache: HashMap::new(),
+            owner_group_cache: HashMap::new(),
+            collapse_prefix,
+            analysis,
+            cache: DownstreamCache::new(
+                args.downstream_cache_crates,
+                as_of_cutoff,
+                args.downstream_stream_threshold,
+                args.crates_io_downstream_fallback
+                    .then(|| CratesIoFallback::new(client.clone())),
+            ),
+            propagation_verifier: VerifySampler::new(
+                args.propagation_verify_samples,
+                args.verify_seed,
+            ),
+            propagation_events_written: 0,
+            constraint_edges: EdgeBudget {
+                limit: args.constraint_edges_limit,
+                ..EdgeBudget::default()
+            },
+            processed: 0,
+            total_advisories,
+            start: Instant::now(),
+            now: as_of_cutoff.unwrap_or_else(Utc::now),
+            as_of_cutoff,
+            as_of_excluded_versions: 0,
+            group_crates: HashSet::new(),
+            explain: args.explain.as_ref().map(|_| ExplainSink::new()),
+            memory_degradations: 0,
+        };
+        Ok(Self {
+            ctx,
+            writers,
+            errors_writer,
+            timings_writer,
+            totals: RunTotals::new(args),
+            errored: 0,
+            advisory_durations: Vec::new(),
+            cve_coverage,
+            last_progress: Instant::now(),
+        })
+    }
+
+    // Resolves the target crates up front and loads their downstream histories into the
+    // cache, largest first, within --max-memory-mb.
+    async fn prefetch(&mut self, advisories: &[Advisory]) -> Result<()> {
+        let args = self.ctx.args;
+        let db = self.ctx.db;
+        let prefetch_start = Instant::now();
+        // Name resolution neither times stages nor counts downstream rows.
+        let mut timings = AdvisoryTimings::new();
+        let mut targets: Vec<String> = Vec::new();
+        for adv in advisories
+            .iter()
+            .take(args.max_advisories.unwrap_or(usize::MAX))
+            .filter(|a| !a.withdrawn || args.withdrawn != WithdrawnPolicy::Skip)
+        {
+            let mut source = BatchSource::new(&mut self.ctx, adv, &mut self.totals, &mut timings);
+            let (resolved, _) =
+                resolve_package(&mut source, &args.package_aliases, &adv.package).await?;
+            targets.extend(resolved);
+        }
+        // Renamed crates merge several histories, so they are fetched on demand.
+        targets.retain(|t| self.ctx.analysis.renames.chain(t).len() == 1);
+        targets.sort();
+        targets.dedup();
+        let target_cnt = targets.len();
+        let db_ref = db;
+        let mut counts: Vec<(String, i64)> = stream::iter(targets)
+            .map(|name| async move {
+                let n = db_ref.count_downstream_details(&name).await?;
+                Ok::<_, anyhow::Error>((name, n))
+            })
+            .buffer_unordered(args.prefetch_concurrency.max(1))
+            .try_collect()
+            .await?;
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.ctx.logger.println(format!(
+            "prefetch: largest downstream histories: {}",
+            counts
+                .iter()
+                .take(10)
+                .map(|(name, n)| format!("{name}={n}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))?;
+        self.ctx.cache.counts.extend(counts.iter().cloned());
+        // `insert_many` would evict all but the largest `max_crates` histories, so the
+        // rest are not fetched at all.
+        let cap = self.ctx.cache.max_crates;
+        if counts.len() > cap {
+            self.ctx.logger.println(format!(
+                "prefetch: --downstream-cache-crates {} leaves {} of {} target crates to be fetched on demand",
+                cap,
+                counts.len() - cap,
+                counts.len()
+            ))?;
+            counts.truncate(cap);
+        }
+        if let Some(limit) = args.max_memory_mb {
+            // Prefetch only what fits the budget, smallest histories first.
+            let budget = limit * 1024 * 1024;
+            let mut fits = Vec::new();
+            let mut used = 0usize;
+            for (name, n) in counts.iter().rev() {
+                let bytes = (*n).max(0) as usize * DOWNSTREAM_ROW_BYTES;
+                if used + bytes > budget {
+                    continue;
+                }
+                used += bytes;
+                fits.push((name.clone(), *n));
+            }
+            if fits.len() < counts.len() {
+                self.ctx.logger.println(format!(
+                    "prefetch: --max-memory-mb {} leaves {} of {} target crates to be fetched on demand",
+                    limit,
+                    counts.len() - fits.len(),
+                    counts.len()
+                ))?;
+            }
+            counts = fits;
+        }
+        let threshold = args.downstream_stream_threshold;
+        self.ctx.cache.streamed += counts
+            .iter()
+            .filter(|(_, n)| *n as usize > threshold)
+            .count();
+        let fetched: Vec<(String, Vec<DownstreamVersionInfo>)> = stream::iter(counts)
+            .map(|(name, n)| async move {
+                let rows = fetch_downstream(db_ref, &name, n, threshold).await?;
+                Ok::<_, anyhow::Error>((name, rows))
+            })
+            .buffer_unordered(args.prefetch_concurrency.max(1))
+            .try_collect()
+            .await?;
+        let mut fetched = fetched;
+        if self.ctx.cache.crates_io_fallback.is_some() {
+            // Left for the on-demand path, which falls back to crates.io.
+            fetched.retain(|(_, rows)| !rows.is_empty());
+        }
+        let fetched_rows: usize = fetched.iter().map(|(_, rows)| rows.len()).sum();
+        self.ctx.cache.insert_many(fetched);
+        self.ctx.logger.println(format!(
+            "prefetch: target_crates={} downstream_rows={} cached_crates={}/{} elapsed={:.1}s",
+            target_cnt,
+            fetched_rows,
+            self.ctx.cache.len(),
+            self.ctx.cache.max_crates,
+            prefetch_start.elapsed().as_secs_f64()
+        ))?;
+        Ok(())
+    }
+
+    // Runs one advisory and commits its rows, or isolates its error unless --fail-fast.
+    async fn process_advisory(&mut self, adv: &Advisory) -> Result<()> {
+        let args = self.ctx.args;
+        if let Some(bar) = self.ctx.logger.progress() {
+            bar.set_position(self.ctx.processed as u64);
+            bar.set_message(format!(
+                "rows={} cache_hit={:.0}% {} {}",
+                self.totals.written_rows,
+                self.ctx.cache.hit_rate() * 100.0,
+                adv.rustsec_id,
+                adv.package
+            ));
+        }
+        self.ctx.processed += 1;
+        if self.ctx.processed == 1 || self.last_progress.elapsed() >= Duration::from_secs(5) {
+            self.ctx.logger.status(format!(
+                "progress: {}/{} advisories, written_rows={}, skipped={}, errored={}, mem_est={:.0}MiB, elapsed={:.1}s",
+                self.ctx.processed,
+                self.ctx.total_advisories,
+                self.totals.written_rows,
+                self.totals.skipped,
+                self.errored,
+                mib(memory_estimate(&self.ctx)),
+                self.ctx.start.elapsed().as_secs_f64()
+            ))?;
+            self.last_progress = Instant::now();
+        }
+
+        let mut pending = PendingAdvisory::new(args, &self.writers);
+        let counters = (
+            self.ctx.propagation_events_written,
+            self.ctx.constraint_edges,
+        );
+        let mut timings = AdvisoryTimings::new();
+        let result = process_advisory(&mut self.ctx, adv, &mut pending, &mut timings).await;
+        timings.finish();
+        if let Some(c) = self.cve_coverage.as_mut() {
+            let outcome = match &result {
+                Ok(()) => match pending.totals.skipped_by_reason.keys().next() {
+                    Some(reason) => ("skipped", reason.as_str().to_string(), 0),
+                    None if pending.totals.written_rows > 0 => {
+                        ("lag_rows", String::new(), pending.totals.written_rows)
+                    }
+                    None => ("no_lag_rows", String::new(), 0),
+                },
+                Err(e) => ("error", format!("{e:#}"), 0),
+            };
+            c.outcomes.insert(adv.rustsec_id.clone(), outcome);
+        }
+        if let Some(x) = self.ctx.explain.as_mut() {
+            x.section("outcome");
+            x.line(match &result {
+                Ok(()) => match pending.totals.skipped_by_reason.keys().next() {
+                    Some(reason) => format!("skipped reason={}", reason.as_str()),
+                    None => format!("strict_lag_rows={}", pending.totals.written_rows),
+                },
+                Err(e) => format!("error {e:#}"),
+            });
+        }
+        if let Some(w) = self.timings_writer.as_mut() {
+            let ms = |d: Duration| d.as_millis().to_string();
+            w.write_record([
+                adv.rustsec_id.clone(),
+                adv.package.clone(),
+                if result.is_ok() { "ok" } else { "error" }.to_string(),
+                ms(timings.total()),
+                ms(timings.spent(Stage::Fetch)),
+                ms(timings.spent(Stage::Strict)),
+                ms(timings.spent(Stage::Constraint)),
+                ms(timings.spent(Stage::Propagation)),
+                timings.downstream_rows.to_string(),
+                timings.propagation_carriers.to_string(),
+                timings.propagation_max_queue.to_string(),
+                timings.propagation_cycle_edges.to_string(),
+                timings.propagation_revisit_edges.to_string(),
+                timings.propagation_queue_capped.to_string(),
+            ])?;
+        }
+        self.advisory_durations.push((
+            timings.total(),
+            adv.rustsec_id.clone(),
+            adv.package.clone(),
+        ));
+        match result {
+            Ok(()) => {
+                if let Some(key) = pending.group_crate.take() {
+                    self.ctx.group_crates.insert(key);
+                }
+                self.writers.write_pending(&pending)?;
+                self.totals.merge(pending.totals);
+            }
+            Err(e) if args.fail_fast => return Err(e),
+            Err(e) => {
+                (
+                    self.ctx.propagation_events_written,
+                    self.ctx.constraint_edges,
+                ) = counters;
+                self.errored += 1;
+                self.ctx.logger.println(format!(
+                    "error: rustsec_id={} cve_id={} pkg={} error={:#}",
+                    adv.rustsec_id, adv.cve_id, adv.package, e
+                ))?;
+                self.errors_writer.write_record([
+                    adv.rustsec_id.as_str(),
+                    adv.cve_id.as_str(),
+                    adv.package.as_str(),
+                    &format!("{e:#}"),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+
+    // Outputs that are complete once every advisory has run: the explain trace, the overall
+    // adoption curves, the error and timing CSVs and the CVE coverage report.
+    fn write_outputs(&mut self) -> Result<()> {
+        let args = self.ctx.args;
+        self.ctx.logger.finish_progress();
+        if let (Some(id), Some(x)) = (&args.explain, &self.ctx.explain) {
+            let path = explain_output_path(args, id);
+            ensure_parent_dir(&path)?;
+            x.write(&path)?;
+            self.ctx
+                .logger
+                .println(format!("wrote explain trace: {}", path.display()))?;
+        }
+        let adoption_curves = adoption_curve_groups(&self.totals.adoption_lag_days_by_severity);
+        if let Some(w) = self.writers.adoption_curve.as_mut() {
+            for (sev, lag_days) in &adoption_curves {
+                let mut record: Vec<String> = ["overall", "", "", "", sev.as_str(), ""]
+                    .map(String::from)
+                    .to_vec();
+                record.extend(adoption_curve_cells(lag_days, &args.adoption_curve_days));
+                w.write_record(&record)?;
+            }
+        }
+
+        self.errors_writer.flush()?;
+        if let Some(c) = &self.cve_coverage {
+            c.write(&args.cve_coverage_output)?;
+            self.ctx.logger.println(format!(
+                "wrote cve coverage: {}",
+                args.cve_coverage_output.display()
+            ))?;
+        }
+        if let Some(w) = self.timings_writer.as_mut() {
+            w.flush()?;
+        }
+        Ok(())
+    }
+
+    // Closes the streaming outputs, then writes the run-level summaries, charts, log lines
+    // and --metadata-output.
+    async fn finalize(self, metadata: &mut RunMetadata<'_>) -> Result<RunOutcome> {
+        let BatchRun {
+            ctx,
+            writers,
+            totals,
+            errored,
+            mut advisory_durations,
+            ..
+        } = self;
+        let args = ctx.args;
+        let per_advisory_svgs = writers
+            .per_advisory_svgs
+            .as_ref()
+            .map(|s| (s.written, s.capped));
+        writers.finish()?;
+        let final_memory_estimate = memory_estimate(&ctx);
+        let BatchContext {
+            db,
+            mut logger,
+            processed,
+            total_advisories,
+            cache,
+            as_of_excluded_versions,
+            propagation_verifier,
+            constraint_edges,
+            memory_degradations,
+            crates_io_times,
+            ..
+        } = ctx;
+        metadata.crates_io_version_time = crates_io_times.stats;
+        advisory_durations.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        logger.println("slowest advisories:")?;
+        for (d, rustsec_id, package) in advisory_durations.iter().take(10) {
+            logger.println(format!(
+                "  {:.1}s {} {}",
+                d.as_secs_f64(),
+                rustsec_id,
+                package
+            ))?;
+        }
+        if args.propagation && args.propagation_events_output.is_some() {
+            logger.println(format!(
+                "propagation events dropped: {} by --propagation-events-limit (per advisory), {} by --propagation-events-total-limit",
+                totals.propagation_events_truncated, totals.propagation_events_over_total_limit
+            ))?;
+        }
+        if let Some(path) = args
+            .propagation_events_output
+            .as_deref()
+            .filter(|_| args.propagation && args.sort_events)
+        {
+            let started = Instant::now();
+            let sorted = sort_csv_file(path, args.sort_events_chunk_rows, propagation_event_key)?;
+            logger.println(format!(
+                "sorted propagation events by (root_rustsec_id, hop, lag_days): rows={} spilled_runs={} in {:.1}s",
+                sorted.rows,
+                sorted.runs,
+                started.elapsed().as_secs_f64()
+            ))?;
+        }
+        if let Some((written, capped)) = per_advisory_svgs {
+            logger.println(format!(
+                "per-advisory lag histograms: written={written}, skipped over --per-advisory-svgs-max={capped}"
+            ))?;
+        }
+        if args.constraint_edges_output.is_some() {
+            logger.println(format!(
+                "constraint edges written: {}, truncated by --constraint-edges-limit: {}",
+                constraint_edges.written, constraint_edges.truncated
+            ))?;
+        }
+        logger.flush()?;
+
+        if args.adoption_curve_output.is_some() {
+            write_adoption_curve_charts(args, &totals)?;
+        }
+        log_grouped_lags(&mut logger, args, &totals)?;
+        write_lag_charts(args, &totals)?;
+
+        if args.propagation {
+            let mut f = std::fs::File::create(&args.propagation_summary_output)?;
+            write!(f, "{}", metadata.comment_lines()?)?;
+            let mut cw =
+                csv::Writer::from_writer(std::fs::File::create(&args.propagation_coverage_output)?);
+            write_propagation_summary(&mut f, &mut cw, args, &totals)?;
+            cw.flush()?;
+            write_propagation_charts(args, &totals)?;
+        }
+
+        if args.constraint {
+            let mut f = std::fs::File::create(&args.constraint_summary_output)?;
+            write!(f, "{}", metadata.comment_lines()?)?;
+            write_constraint_summary(&mut f, args, &totals)?;
+            write_constraint_charts(args, &totals)?;
+        }
+
+        if args.propagation {
+            verify_propagation_samples(db, &mut logger, args, propagation_verifier).await?;
+        }
+
+        log_advisory_counts(&mut logger, args, &totals, processed, errored)?;
+        if let Some(as_of) = args.as_of {
+            logger.println(format!(
+                "as_of {}: excluded downstream rows={} excluded versions={}",
+                as_of, cache.excluded_rows, as_of_excluded_versions
+            ))?;
+        }
+        logger.println(format!(
+            "crate id lookups hitting the database: {}",
+            db.crate_id_queries()
+        ))?;
+        logger.println(format!(
+            "downstream cache: hit_rate={:.1}% counted_crates={} streamed_crates={} (threshold {} rows)",
+            cache.hit_rate() * 100.0,
+            cache.counts.len(),
+            cache.streamed,
+            args.downstream_stream_threshold
+        ))?;
+        if let Some(fallback) = &cache.crates_io_fallback {
+            logger.println(format!(
+                "crates.io downstream fallback: synthesized_crates={} synthesized_rows={} api_requests={}",
+                fallback.crates, fallback.rows, fallback.requests
+            ))?;
+        }
+        if let Some(limit) = args.max_memory_mb {
+            logger.println(format!(
+                "memory: --max-memory-mb {limit} degradations={memory_degradations} final_estimate={:.0}MiB",
+                mib(final_memory_estimate)
+            ))?;
+        }
+        log_downstream_lookups(&mut logger, args, &totals, metadata)?;
+        log_lag_summary(&mut logger, args, &totals)?;
+        if let Some(path) = &args.category_summary_output {
+            let mut cw = csv::Writer::from_writer(std::fs::File::create(path)?);
+            write_category_summary(&mut cw, args, &totals)?;
+            cw.flush()?;
+            logger.println(format!("wrote category summary: {}", path.display()))?;
+            write_category_chart(args, &totals)?;
+        }
+        if totals.skipped > 0 {
+            logger.println("skipped advisories breakdown:")?;
+            for reason in SkipReason::ALL {
+                if let Some(n) = totals.skipped_by_reason.get(&reason) {
+                    logger.println(format!("  {}: {}", reason.as_str(), n))?;
+                }
+            }
+        }
+
+        if let Some(path) = &args.html_report {
+            let counts = [
+                ("advisories_loaded", total_advisories),
+                ("processed_advisories", processed),
+                ("written_rows", totals.written_rows),
+                ("skipped_advisories", totals.skipped),
+                ("errored_advisories", errored),
+                ("negative_lag_rows", totals.negative_lag_rows_total),
+                ("pre_disclosure_rows", totals.pre_disclosure_rows),
+            ];
+            write_html_report(path, args, &totals, &counts)?;
+            logger.println(format!("wrote html report: {}", path.display()))?;
+        }
+
+        if args.verify_deterministic {
+            let files = digest_outputs(args);
+            logger.println("deterministic digest (fnv1a64):")?;
+            let mut combined = Vec::new();
+            for path in &files {
+                let mut bytes = std::fs::read(path)?;
+                if path.extension().is_some_and(|ext| ext == "txt") {
+                    bytes = strip_comment_lines(&String::from_utf8_lossy(&bytes)).into_bytes();
+                }
+                let digest = fnv1a64(&bytes);
+                logger.println(format!("  {digest:016x}  {}", path.display()))?;
+                combined.extend_from_slice(path.to_string_lossy().as_bytes());
+                combined.extend_from_slice(&digest.to_be_bytes());
+            }
+            logger.println(format!(
+                "deterministic digest: {:016x} ({} files)",
+                fnv1a64(&combined),
+                files.len()
+            ))?;
+            logger.println(
+                "not covered by the digest: --log-output (timings, progress), --metadata-output and the `#` metadata lines of the text summaries, and crates.io time fallbacks, which depend on the network",
+            )?;
+        }
+
+        for e in &metadata.advisory_db.parse_errors {
+            logger.println(format!(
+                "advisory parse error: file={} error={}",
+                e.file, e.error
+            ))?;
+        }
+
+        if let Some(path) = &args.metadata_output {
+            metadata.finished_at = Some(Utc::now());
+            std::fs::write(path, serde_json::to_string_pretty(&metadata)?)?;
+            logger.println(format!("wrote run metadata: {}", path.display()))?;
+        }
+
+        if errored > 0 {
+            logger.println(format!(
+                "{errored} advisories failed; see {}",
+                args.errors_output.display()
+            ))?;
+        }
+        if let Some(t) = &metadata.truncated {
+            logger.println(format!(
+                "run truncated by --max-runtime-minutes {}: last completed rustsec_id={}, {} advisories not started (next: {}); outputs cover only the completed advisories",
+                t.max_runtime_minutes,
+                t.last_completed.as_deref().unwrap_or("-"),
+                t.remaining.len(),
+                t.remaining.first().map_or("-", String::as_str)
+            ))?;
+        }
+        Ok(RunOutcome {
+            processed,
+            written_rows: totals.written_rows,
+            skipped: totals.skipped,
+            errors: errored,
+            outputs: written_outputs(args)
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect(),
+            truncated: metadata.truncated.is_some(),
+        })
+    }
+}
+
+fn write_adoption_curve_charts(args: &Args, totals: &RunTotals) -> Result<()> {
+    let out_dir = args.strict_output_dir.as_path();
+    std::fs::create_dir_all(out_dir)?;
+    let adoption_curves = adoption_curve_groups(&totals.adoption_lag_days_by_severity);
+    let xs: Vec<f64> = args.adoption_curve_days.iter().map(|d| *d as f64).collect();
+    let curve = |(sev, lags): &(String, Vec<f64>)| {
+        let grid = cumulative_fractions(lags, &xs);
+        (format!("{sev} (n={})", lags.len()), grid)
+    };
+    let (overall, by_severity) = adoption_curves
+        .split_last()
+        .expect("`all` is always present");
+    StepCurveChart::new(&xs, &[curve(overall)])
+        .style(&args.chart_style)
+        .title("adoption over time (first adoptions, all advisories)")
+        .subtitle("share of eventual adopters whose lag is at most N days")
+        .x_label("days since t0")
+        .write_svg(out_dir.join("adoption_curve_overall.svg"))?;
+    let series: Vec<(String, Vec<f64>)> = by_severity.iter().map(curve).collect();
+    StepCurveChart::new(&xs, &series)
+        .style(&args.chart_style)
+        .title("adoption over time by severity (first adoptions)")
+        .subtitle("share of eventual adopters whose lag is at most N days")
+        .x_label("days since t0")
+        .write_svg(out_dir.join("adoption_curve_by_severity.svg"))?;
+    Ok(())
+}
+
+// --group-by-owner and --collapse-prefix-regex: how far the grouping moved the p50.
+fn log_grouped_lags(logger: &mut Logger, args: &Args, totals: &RunTotals) -> Result<()> {
+    let p50 = |s: Option<LagStats>| s.map(|s| format_float(s.p50)).unwrap_or("n/a".into());
+    let ungrouped_p50 = p50(compute_lag_stats(
+        totals.lags_by_severity.values().flatten().copied(),
+    ));
+    if args.group_by_owner {
+        logger.println(format!(
+            "owner grouping: {} first adoptions -> {} (advisory, owner) rows; p50 {ungrouped_p50} ungrouped vs {} grouped {} (written to {})",
+            totals.owner_grouped_input_rows,
+            totals.owner_grouped_lags.len(),
+            p50(compute_lag_stats(totals.owner_grouped_lags.iter().copied())),
+            args.lag_unit.label(),
+            args.owner_grouped_output.display()
+        ))?;
+    }
+    if args.collapse_prefix_regex.is_some() {
+        logger.println(format!(
+            "prefix collapsing: {} first adoptions -> {} (advisory, prefix) rows; p50 {ungrouped_p50} raw vs {} collapsed {} (written to {})",
+            totals.collapsed_input_rows,
+            totals.collapsed_lags.len(),
+            p50(compute_lag_stats(totals.collapsed_lags.iter().copied())),
+            args.lag_unit.label(),
+            args.collapsed_output.display()
+        ))?;
+    }
+    Ok(())
+}
+
+// First-adoption lags by severity, most severe first.
+fn severity_groups(totals: &RunTotals) -> Vec<(String, Vec<f64>)> {
+    let mut groups: Vec<(String, Vec<f64>)> = totals
+        .lags_by_severity
+        .iter()
+        .map(|(sev, lags)| (sev.clone(), lags.clone()))
+        .collect();
+    groups.sort_by_key(|(sev, _)| severity_rank(sev).unwrap_or(u8::MAX));
+    groups
+}
+
+fn cadence_groups(totals: &RunTotals) -> Vec<(String, Vec<f64>)> {
+    (0..=CADENCE_BINS.len())
+        .filter_map(|bin| {
+            let lags = totals.lags_by_cadence_bin.get(&bin)?;
+            Some((cadence_bin_label(bin).to_string(), lags.clone()))
+        })
+        .collect()
+}
+
+// (category, summarized advisories, first-adoption lags), named categories first.
+fn category_groups(totals: &RunTotals) -> Vec<(String, usize, Vec<f64>)> {
+    let mut groups: Vec<(String, usize, Vec<f64>)> = totals
+        .category_advisories
+        .iter()
+        .map(|(category, n)| {
+            let lags = totals
+                .lags_by_category
+                .get(category)
+                .cloned()
+                .unwrap_or_default();
+            (category.clone(), *n, lags)
+        })
+        .collect();
+    groups.sort_by(|a, b| (a.0 == UNCATEGORIZED, &a.0).cmp(&(b.0 == UNCATEGORIZED, &b.0)));
+    groups
+}
+
+fn fix_compat_groups(totals: &RunTotals) -> Vec<(bool, &'static str, LagStats)> {
+    [(true, "compatible"), (false, "incompatible")]
+        .into_iter()
+        .filter_map(|(compatible, label)| {
+            let lags = totals.lags_by_fix_compat.get(&compatible)?;
+            Some((compatible, label, compute_lag_stats(lags.iter().copied())?))
+        })
+        .collect()
+}
+
+fn dependent_tier_groups(args: &Args, totals: &RunTotals) -> Vec<(String, LagStats)> {
+    dependent_tier_labels(&args.dependent_tiers)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, label)| {
+            let lags = totals.lags_by_dependent_tier.get(&i)?;
+            Some((label, compute_lag_stats(lags.iter().copied())?))
+        })
+        .collect()
+}
+
+fn exposure_classes(exposure: &ExposureCounts) -> [(&'static str, usize); 3] {
+    [
+        ("exposed", exposure.exposed),
+        ("never exposed", exposure.never_exposed),
+        ("unparseable", exposure.unparseable),
+    ]
+}
+
+// severity, function-scoped count, not-function-scoped count.
+fn function_scoped_rows(totals: &RunTotals) -> Vec<Vec<String>> {
+    let mut severities: Vec<&String> = totals
+        .function_scoped_by_severity
+        .keys()
+        .map(|(sev, _)| sev)
+        .collect();
+    severities.sort_by_key(|sev| (severity_rank(sev).unwrap_or(u8::MAX), sev.as_str()));
+    severities.dedup();
+    severities
+        .iter()
+        .map(|sev| {
+            let n = |scoped: bool| {
+                totals
+                    .function_scoped_by_severity
+                    .get(&((*sev).clone(), scoped))
+                    .copied()
+                    .unwrap_or(0)
+            };
+            vec![sev.to_string(), n(true).to_string(), n(false).to_string()]
+        })
+        .collect()
+}
+
+// The strict-lag charts under --strict-output-dir, each only when it has data.
+fn write_lag_charts(args: &Args, totals: &RunTotals) -> Result<()> {
+    let out_dir = args.strict_output_dir.as_path();
+    let severity_groups = severity_groups(totals);
+    if !severity_groups.is_empty() {
+        std::fs::create_dir_all(out_dir)?;
+        BoxPlotChart::new(&severity_groups)
+            .style(&args.chart_style)
+            .min_samples(args.boxplot_min_samples)
+            .title("strict lag by severity (first adoption)")
+            .subtitle(format!(
+                "box=p25/p50/p75, whiskers=p5/p95, grey: n<{}",
+                args.boxplot_min_samples
+            ))
+            .y_label(args.lag_unit.column())
+            .write_svg(out_dir.join("lag_boxplot_by_severity.svg"))?;
+    }
+
+    let exposure_classes = exposure_classes(&totals.exposure);
+    let exposure_total: usize = exposure_classes.iter().map(|(_, n)| n).sum();
+    if exposure_total > 0 {
+        std::fs::create_dir_all(out_dir)?;
+        BarChart::new(&exposure_classes)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .title("downstream exposure to the vulnerable range")
+            .subtitle(format!(
+                "downstream crates with history before the fix, summed over advisories (n={exposure_total})"
+            ))
+            .write_svg(out_dir.join("downstream_exposure.svg"))?;
+    }
+    if let Some(s) = compute_lag_stats(totals.upstream_exposure_days.iter().copied()) {
+        std::fs::create_dir_all(out_dir)?;
+        HistogramChart::new(&totals.upstream_exposure_days)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .title(format!(
+                "upstream exposure histogram (first vulnerable release to fix, n={})",
+                s.count
+            ))
+            .subtitle(format!(
+                "bins=60, x_max={}, vulnerable since first release: {}",
+                format_float(s.max.max(1.0)),
+                totals.vulnerable_since_first_release
+            ))
+            .x_label("exposure_days")
+            .write_svg(out_dir.join("exposure_days_hist.svg"))?;
+    }
+
+    let fix_compat_groups = fix_compat_groups(totals);
+    if !fix_compat_groups.is_empty() {
+        std::fs::create_dir_all(out_dir)?;
+        let clip = args
+            .hist_clip_days
+            .map(|d| args.lag_unit.convert((d * 86_400.0) as i64));
+        for (compatible, label, s) in &fix_compat_groups {
+            let lags = &totals.lags_by_fix_compat[compatible];
+            let x_max = clip.unwrap_or_else(|| lags.iter().copied().fold(1.0, f64::max));
+            HistogramChart::new(lags)
+                .style(&args.chart_style)
+                .data_csv(args.chart_data_csv)
+                .x_max(x_max)
+                .overflow_bin(clip.is_some())
+                .title(format!(
+                    "strict lag histogram, semver-{label} fixes (first adoptions, n={})",
+                    s.count
+                ))
+                .subtitle(format!(
+                    "bins=60, x_max={}, p50={}",
+                    args.lag_unit.format(x_max),
+                    format_float(s.p50)
+                ))
+                .x_label(args.lag_unit.column())
+                .write_svg(out_dir.join(format!("lag_hist_fix_semver_{label}.svg")))?;
+        }
+    }
+
+    let cadence_groups = cadence_groups(totals);
+    if !cadence_groups.is_empty() {
+        std::fs::create_dir_all(out_dir)?;
+        BoxPlotChart::new(&cadence_groups)
+            .style(&args.chart_style)
+            .min_samples(args.boxplot_min_samples)
+            .title("strict lag by downstream release cadence (first adoption)")
+            .subtitle(format!(
+                "cadence = median days between releases in the year before the fix; box=p25/p50/p75, whiskers=p5/p95, grey: n<{}",
+                args.boxplot_min_samples
+            ))
+            .y_label(args.lag_unit.column())
+            .write_svg(out_dir.join("lag_boxplot_by_cadence.svg"))?;
+    }
+
+    let dependent_tier_groups = dependent_tier_groups(args, totals);
+    if !dependent_tier_groups.is_empty() {
+        std::fs::create_dir_all(out_dir)?;
+        let bars: Vec<(&str, Vec<usize>)> = dependent_tier_groups
+            .iter()
+            .map(|(label, s)| {
+                (
+                    label.as_str(),
+                    [s.p25, s.p50, s.p75, s.p95]
+                        .iter()
+                        .map(|v| v.max(0.0).round() as usize)
+                        .collect(),
+                )
+            })
+            .collect();
+        GroupedBarChart::new(&bars, &["p25", "p50", "p75", "p95"])
+            .style(&args.chart_style)
+            .title("strict lag by downstream dependents (first adoption)")
+            .subtitle(
+                dependent_tier_groups
+                    .iter()
+                    .map(|(label, s)| format!("{label}: n={}", s.count))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .y_label(args.lag_unit.column())
+            .write_svg(out_dir.join("lag_by_dependent_tier.svg"))?;
+    }
+    Ok(())
+}
+
+// Per-hop lags converted to --lag-unit and ordered by hop, then all of them together.
+fn lags_by_hop(
+    by_hop: &HashMap<usize, Vec<i64>>,
+    unit: LagUnit,
+) -> (Vec<(usize, Vec<f64>)>, Vec<f64>) {
+    let mut hops: Vec<(usize, Vec<f64>)> = by_hop
+        .iter()
+        .map(|(h, lags)| (*h, lags.iter().map(|&x| unit.convert(x)).collect()))
+        .collect();
+    hops.sort_by_key(|(h, _)| *h);
+    let all = hops
+        .iter()
+        .flat_map(|(_, lags)| lags.iter().copied())
+        .collect();
+    (hops, all)
+}
+
+fn write_lag_stats(f: &mut impl Write, label: &str, stats: &LagStats, unit: LagUnit) -> Result<()> {
+    writeln!(f, "{label}")?;
+    writeln!(f, "  count = {}", stats.count)?;
+    writeln!(f, "  min   = {} {}", unit.format(stats.min), unit.label())?;
+    writeln!(f, "  p50   = {:.4} {}", stats.p50, unit.label())?;
+    writeln!(f, "  avg   = {:.4} {}", stats.avg, unit.label())?;
+    writeln!(f, "  max   = {} {}", unit.format(stats.max), unit.label())?;
+    writeln!(f)?;
+    Ok(())
+}
+
+// The body of --propagation-summary-output below its metadata lines, with the per-hop
+// coverage also written to `coverage` as CSV.
+fn write_propagation_summary(
+    f: &mut impl Write,
+    coverage_csv: &mut csv::Writer<impl Write>,
+    args: &Args,
+    totals: &RunTotals,
+) -> Result<()> {
+    let unit = args.lag_unit;
+    let (hops, all_lags) = lags_by_hop(&totals.propagation_lags_by_hop, unit);
+    let max_hop = hops.iter().map(|(h, _)| *h).max().unwrap_or(0);
+    writeln!(f, "patch propagation analysis (hops=1..{})", max_hop)?;
+    if let Some(max_hops) = args.propagation_max_hops {
+        writeln!(f, "max_hops_limit = {}", max_hops)?;
+    }
+    writeln!(f, "seed = {}", args.propagation_seed.as_str())?;
+    if let Some(n) = args.propagation_min_downstream_releases {
+        writeln!(f, "min_downstream_releases = {}", n)?;
+    }
+    if let Some(d) = args.propagation_within_days {
+        writeln!(f, "within_days = {}", d)?;
+    }
+    if args.propagation_lag_basis != PropagationLagBasis::FixTime {
+        writeln!(f, "lag_basis = {}", args.propagation_lag_basis.as_str())?;
+    }
+    if args.propagation_seed == PropagationSeed::AllFixed {
+        writeln!(f, "seeds_used = {}", totals.propagation_seeds_used)?;
         writeln!(
             f,
-            "  break_rate_percent            = {}",
-            constraint_totals.break_rate_percent()
+            "seed_duplicates_suppressed = {}",
+            totals.propagation_seed_duplicates
         )?;
+    }
+    if let Some(as_of) = args.as_of {
+        writeln!(f, "as_of = {}", as_of)?;
+    }
+    if args.lag_unit != LagUnit::Days {
+        writeln!(f, "lag_unit = {}", args.lag_unit.as_str())?;
+    }
+    writeln!(
+        f,
+        "cycle_edges = {} (advisories with cycles: {})",
+        totals.propagation_cycle_edges, totals.propagation_cycle_advisories
+    )?;
+    writeln!(f, "revisit_edges = {}", totals.propagation_revisit_edges)?;
+    if let Some(cap) = args.propagation_max_queue {
+        let mut capped = totals.propagation_queue_capped.clone();
+        capped.sort();
+        writeln!(f, "max_queue_limit = {}", cap)?;
+        writeln!(f, "queue_capped_advisories = {}", capped.len())?;
+        for id in &capped {
+            writeln!(f, "  {}", id)?;
+        }
+    }
+    writeln!(f)?;
+
+    if let Some(stats) = compute_lag_stats(all_lags.iter().copied()) {
+        write_lag_stats(f, "all hops", &stats, unit)?;
+    }
+    for (hop, lags) in &hops {
+        if let Some(stats) = compute_lag_stats(lags.iter().copied()) {
+            write_lag_stats(f, &format!("hop {hop}"), &stats, unit)?;
+        }
+    }
+
+    for evidence in AdoptionEvidence::ALL {
+        let Some(lags) = totals.propagation_lags_by_evidence.get(&evidence) else {
+            continue;
+        };
+        if let Some(stats) = compute_lag_stats(lags.iter().map(|&x| unit.convert(x))) {
+            writeln!(f, "evidence {} (all hops)", evidence.as_str())?;
+            writeln!(f, "  count = {}", stats.count)?;
+            writeln!(f, "  p25   = {:.4} {}", stats.p25, unit.label())?;
+            writeln!(f, "  p50   = {:.4} {}", stats.p50, unit.label())?;
+            writeln!(f, "  p75   = {:.4} {}", stats.p75, unit.label())?;
+            writeln!(f, "  p95   = {:.4} {}", stats.p95, unit.label())?;
+            writeln!(f)?;
+        }
+    }
+
+    // Days since the original fix rather than since the immediate upstream's release.
+    let (cumulative, all_cumulative) =
+        lags_by_hop(&totals.propagation_cumulative_lags_by_hop, unit);
+    let cumulative_groups = std::iter::once(("all hops".to_string(), &all_cumulative)).chain(
+        cumulative
+            .iter()
+            .map(|(h, lags)| (format!("hop {h}"), lags)),
+    );
+    for (label, lags) in cumulative_groups {
+        if let Some(stats) = compute_lag_stats(lags.iter().copied()) {
+            write_lag_stats(
+                f,
+                &format!("cumulative since root fix, {label}"),
+                &stats,
+                unit,
+            )?;
+        }
+    }
+
+    // Lag from the downstream crate's first release after the upstream fix, and how many
+    // releases it shipped before adopting, whatever --propagation-lag-basis is.
+    let mut opportunity: Vec<(usize, &Vec<(i64, usize)>)> = totals
+        .propagation_opportunity_by_hop
+        .iter()
+        .map(|(h, xs)| (*h, xs))
+        .collect();
+    opportunity.sort_by_key(|(h, _)| *h);
+    let all_opportunity: Vec<(i64, usize)> = opportunity
+        .iter()
+        .flat_map(|(_, xs)| xs.iter().copied())
+        .collect();
+    let opportunity_groups = std::iter::once(("all hops".to_string(), &all_opportunity))
+        .chain(opportunity.iter().map(|(h, xs)| (format!("hop {h}"), *xs)));
+    for (label, xs) in opportunity_groups {
+        let lags = compute_lag_stats(xs.iter().map(|&(secs, _)| unit.convert(secs)));
+        let skipped = compute_lag_stats(xs.iter().map(|&(_, n)| n as f64));
+        let (Some(lags), Some(skipped)) = (lags, skipped) else {
+            continue;
+        };
+        writeln!(f, "since first post-fix release, {label}")?;
+        writeln!(f, "  count = {}", lags.count)?;
         writeln!(
             f,
-            "  unknown_req_unparseable       = {}",
-            constraint_totals.unknown_req_unparseable
+            "  lag p25/p50/p75/p95 = {:.4} / {:.4} / {:.4} / {:.4} {}",
+            lags.p25,
+            lags.p50,
+            lags.p75,
+            lags.p95,
+            unit.label()
         )?;
-        writeln!(f)?;
-        writeln!(f, "affected edges dep_req shape (at fix_time)")?;
         writeln!(
             f,
-            "  exact-pin (=...)              = {}",
-            constraint_totals.affected_req_exact_pin
+            "  skipped releases p25/p50/p75/p95 = {} / {} / {} / {}",
+            format_float(skipped.p25),
+            format_float(skipped.p50),
+            format_float(skipped.p75),
+            format_float(skipped.p95)
         )?;
+        writeln!(f)?;
+    }
+
+    writeln!(f, "coverage (affected dependents reached per hop)")?;
+    coverage_csv.write_record([
+        "hop",
+        "affected_cnt",
+        "adopted_cnt",
+        "not_adopted_cnt",
+        "adoption_rate_percent",
+        "censored_cnt",
+        "few_releases_cnt",
+    ])?;
+    for (hop, c) in propagation_coverage(totals) {
+        let mut line = format!(
+            "  hop {}: affected={} adopted={} adoption_rate={:.2}%",
+            hop,
+            c.affected,
+            c.adopted,
+            c.adoption_rate_percent()
+        );
+        if args.propagation_within_days.is_some() {
+            line.push_str(&format!(" censored={}", c.censored));
+        }
+        if args.propagation_min_downstream_releases.is_some() {
+            line.push_str(&format!(" few_releases={}", c.few_releases));
+        }
+        writeln!(f, "{line}")?;
+        coverage_csv.write_record([
+            hop.to_string(),
+            c.affected.to_string(),
+            c.adopted.to_string(),
+            c.not_adopted().to_string(),
+            format_float(c.adoption_rate_percent()),
+            c.censored.to_string(),
+            c.few_releases.to_string(),
+        ])?;
+    }
+
+    let reach = &totals.propagation_reach;
+    let max_reach_hop = reach.iter().map(|r| r.len()).max().unwrap_or(0);
+    if max_reach_hop > 0 {
+        writeln!(f)?;
         writeln!(
             f,
-            "  has upper bound (< or <=)     = {}",
-            constraint_totals.affected_req_has_upper_bound
+            "reach (unique dependents within N hops per advisory, deciles over {} advisories)",
+            reach.len()
         )?;
-        writeln!(
+        let deciles = |mut xs: Vec<f64>| -> String {
+            xs.sort_unstable_by(f64::total_cmp);
+            let mut parts: Vec<String> = (1..10)
+                .map(|d| format!("p{}={:.0}", d * 10, percentile_sorted(&xs, d as f64 / 10.0)))
+                .collect();
+            parts.push(format!("max={:.0}", xs.last().copied().unwrap_or(0.0)));
+            parts.join(" ")
+        };
+        for hop in 1..=max_reach_hop {
+            let cumulative = |pick: fn(&(usize, i64)) -> f64| -> Vec<f64> {
+                reach
+                    .iter()
+                    .map(|r| r.iter().take(hop).map(pick).sum())
+                    .collect()
+            };
+            writeln!(f, "  hops<={hop}")?;
+            writeln!(f, "    crates    {}", deciles(cumulative(|x| x.0 as f64)))?;
+            writeln!(f, "    downloads {}", deciles(cumulative(|x| x.1 as f64)))?;
+        }
+    }
+    Ok(())
+}
+
+fn propagation_coverage(totals: &RunTotals) -> Vec<(usize, &HopCoverage)> {
+    let mut coverage: Vec<(usize, &HopCoverage)> = totals
+        .propagation_coverage_by_hop
+        .iter()
+        .map(|(h, c)| (*h, c))
+        .collect();
+    coverage.sort_by_key(|(h, _)| *h);
+    coverage
+}
+
+fn write_propagation_charts(args: &Args, totals: &RunTotals) -> Result<()> {
+    let unit = args.lag_unit;
+    let out_dir = args.propagation_output_dir.as_path();
+    std::fs::create_dir_all(out_dir)?;
+    remove_stale_charts(
+        out_dir,
+        &["propagation_lag_hist_", "propagation_cumulative_lag_hist_"],
+    );
+
+    let propagation_clip = args
+        .propagation_x_max_days
+        .or(args.hist_clip_days)
+        .map(|d| unit.convert((d * 86_400.0) as i64));
+    let (hops, all_lags) = lags_by_hop(&totals.propagation_lags_by_hop, unit);
+    let (cumulative, all_cumulative) =
+        lags_by_hop(&totals.propagation_cumulative_lags_by_hop, unit);
+    propagation_histograms(
+        args,
+        out_dir,
+        "propagation_lag_hist",
+        "propagation",
+        &all_lags,
+        &hops,
+        propagation_clip,
+    )?;
+    propagation_histograms(
+        args,
+        out_dir,
+        "propagation_cumulative_lag_hist",
+        "propagation cumulative",
+        &all_cumulative,
+        &cumulative,
+        propagation_clip,
+    )?;
+
+    let hop_groups: Vec<(String, Vec<f64>)> = hops
+        .iter()
+        .filter(|(_, lags)| !lags.is_empty())
+        .map(|(hop, lags)| (format!("hop {hop}"), lags.clone()))
+        .collect();
+    if !hop_groups.is_empty() {
+        BoxPlotChart::new(&hop_groups)
+            .style(&args.chart_style)
+            .min_samples(args.boxplot_min_samples)
+            .title("propagation lag by hop")
+            .subtitle(format!(
+                "box=p25/p50/p75, whiskers=p5/p95, grey: n<{}",
+                args.boxplot_min_samples
+            ))
+            .y_label(unit.column())
+            .write_svg(out_dir.join("propagation_lag_boxplot_by_hop.svg"))?;
+    }
+
+    let coverage = propagation_coverage(totals);
+    if !coverage.is_empty() {
+        let labels: Vec<String> = coverage.iter().map(|(h, _)| format!("hop {h}")).collect();
+        let late = args
+            .propagation_within_days
+            .map(|d| format!("adopted after {d} days"));
+        let categories: Vec<(&str, Vec<usize>)> = labels
+            .iter()
+            .zip(&coverage)
+            .map(|(label, (_, c))| {
+                let mut counts = vec![c.adopted, c.not_adopted()];
+                if late.is_some() {
+                    counts.push(c.censored);
+                }
+                (label.as_str(), counts)
+            })
+            .collect();
+        let mut series = vec!["adopted", "not adopted"];
+        series.extend(late.as_deref());
+        StackedBarChart::new(&categories, &series)
+            .style(&args.chart_style)
+            .title("propagation coverage by hop")
+            .subtitle("affected dependents per hop, split by whether they adopted the fix")
+            .write_svg(out_dir.join("propagation_coverage_by_hop.svg"))?;
+    }
+    Ok(())
+}
+
+// The body of --constraint-summary-output below its metadata lines.
+fn write_constraint_summary(f: &mut impl Write, args: &Args, totals: &RunTotals) -> Result<()> {
+    let constraint_totals = &totals.constraint_totals;
+    writeln!(
+        f,
+        "constraint break analysis (edge=downstream crate at fix_time)"
+    )?;
+    if args.constraint_min_age_days > 0 {
+        writeln!(f, "min_age_days = {}", args.constraint_min_age_days)?;
+    }
+    if let Some(as_of) = args.as_of {
+        writeln!(f, "as_of = {}", as_of)?;
+    }
+    writeln!(f)?;
+    writeln!(f, "totals")?;
+    writeln!(
+        f,
+        "  downstream_crates_with_history = {}",
+        constraint_totals.downstream_crates_with_history
+    )?;
+    writeln!(
+        f,
+        "  affected_edges                = {}",
+        constraint_totals.affected_edges
+    )?;
+    writeln!(
+        f,
+        "  locked_out_edges              = {}",
+        constraint_totals.locked_out_edges
+    )?;
+    writeln!(
+        f,
+        "  break_rate_percent            = {}",
+        constraint_totals.break_rate_percent()
+    )?;
+    writeln!(
+        f,
+        "  unknown_req_unparseable       = {}",
+        constraint_totals.unknown_req_unparseable
+    )?;
+    writeln!(f)?;
+    writeln!(f, "by severity")?;
+    for (sev, t) in &ConstraintTotals::by_severity_rank(&totals.constraint_totals_by_severity) {
+        writeln!(
+            f,
+            "  {:<10} affected_edges={} locked_out_edges={} break_rate_percent={} advisories_with_edges={}",
+            sev,
+            t.affected_edges,
+            t.locked_out_edges,
+            t.break_rate_percent(),
+            totals
+                .constraint_break_rate_by_severity
+                .get(*sev)
+                .map_or(0, |rates| rates.len())
+        )?;
+    }
+    writeln!(f)?;
+    writeln!(f, "affected edges dep_req shape (at fix_time)")?;
+    for shape in ReqShape::ALL {
+        if shape != ReqShape::Unparseable {
+            writeln!(
+                f,
+                "  {:<30}= {}",
+                shape.as_str(),
+                constraint_totals.affected_req_shape[shape as usize]
+            )?;
+        }
+    }
+    if !totals.constraint_horizon_totals.is_empty() {
+        writeln!(f)?;
+        writeln!(
+            f,
+            "break rate by horizon (latest downstream version before fix_time + N days)"
+        )?;
+        for h in &totals.constraint_horizon_totals {
+            writeln!(
+                f,
+                "  +{:<5} affected_edges={} locked_out_edges={} break_rate_percent={}",
+                format!("{}d", h.horizon_days),
+                h.affected_edges,
+                h.locked_out_edges,
+                h.break_rate_percent()
+            )?;
+        }
+    }
+    if args.constraint_whatif {
+        writeln!(f)?;
+        writeln!(
+            f,
+            "what-if backport (locked-out edges, fix published as patch+1 of the resolved minor line)"
+        )?;
+        writeln!(
+            f,
+            "  resolved_locked_edges         = {}",
+            constraint_totals.whatif_resolved_locked_edges
+        )?;
+        writeln!(
+            f,
+            "  unlocked_backport_1_line      = {}",
+            constraint_totals.whatif_unlocked_1_line
+        )?;
+        writeln!(
+            f,
+            "  unlocked_backport_2_lines     = {}",
+            constraint_totals.whatif_unlocked_2_lines
+        )?;
+        writeln!(
             f,
-            "  caret 0.x (^0.)               = {}",
-            constraint_totals.affected_req_caret_0x
+            "  unlocked_backport_all_lines   = {}",
+            constraint_totals.whatif_unlocked_all_lines
         )?;
+    }
+    writeln!(f)?;
+    writeln!(f, "fix adoption kind (strict lag rows, first adoption)")?;
+    for kind in AdoptionKind::ALL {
         writeln!(
             f,
-            "  other                         = {}",
-            constraint_totals.affected_req_other
+            "  {:<30}= {}",
+            kind.as_str(),
+            totals.adoption_kind_totals.get(&kind).copied().unwrap_or(0)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_constraint_charts(args: &Args, totals: &RunTotals) -> Result<()> {
+    let constraint_totals = &totals.constraint_totals;
+    let severity_constraint_totals =
+        ConstraintTotals::by_severity_rank(&totals.constraint_totals_by_severity);
+    let out_dir = args.constraint_output_dir.as_path();
+    std::fs::create_dir_all(out_dir)?;
+
+    let per_advisory = &totals.constraint_break_rate_per_adv_percent;
+    if !per_advisory.is_empty() {
+        let x_max = 100.0;
+        HistogramChart::new(per_advisory)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .bins(args.constraint_bins)
+            .x_max(x_max)
+            .title(format!(
+                "constraint break_rate histogram (per advisory, n={})",
+                per_advisory.len()
+            ))
+            .subtitle(format!(
+                "bins={}, x_max={}, y_scale=linear",
+                args.constraint_bins, x_max
+            ))
+            .x_label("break_rate_percent")
+            .write_svg(out_dir.join("constraint_break_rate_hist_advisory.svg"))?;
+    }
+    for (sev, _) in &severity_constraint_totals {
+        let Some(rates) = totals.constraint_break_rate_by_severity.get(*sev) else {
+            continue;
+        };
+        let x_max = 100.0;
+        HistogramChart::new(rates)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .bins(args.constraint_bins)
+            .x_max(x_max)
+            .title(format!(
+                "constraint break_rate histogram (per advisory, severity={}, n={})",
+                sev,
+                rates.len()
+            ))
+            .subtitle(format!(
+                "bins={}, x_max={}, y_scale=linear",
+                args.constraint_bins, x_max
+            ))
+            .x_label("break_rate_percent")
+            .write_svg(out_dir.join(format!(
+                "constraint_break_rate_hist_advisory_{}.svg",
+                sev.to_lowercase()
+            )))?;
+    }
+
+    let horizons = &totals.constraint_horizon_totals;
+    if !horizons.is_empty() {
+        let labels: Vec<String> = horizons
+            .iter()
+            .map(|h| format!("+{}d", h.horizon_days))
+            .collect();
+        let categories: Vec<(&str, usize)> = labels
+            .iter()
+            .zip(horizons)
+            .map(|(label, h)| (label.as_str(), h.break_rate_percent()))
+            .collect();
+        BarChart::new(&categories)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .title("constraint break_rate_percent by horizon")
+            .subtitle("edges evaluated at the latest downstream version before fix_time + horizon")
+            .write_svg(out_dir.join("constraint_break_rate_by_horizon.svg"))?;
+    }
+
+    let shapes: Vec<(&str, usize)> = ReqShape::ALL
+        .into_iter()
+        .filter(|shape| *shape != ReqShape::Unparseable)
+        .map(|shape| {
+            (
+                shape.label(),
+                constraint_totals.affected_req_shape[shape as usize],
+            )
+        })
+        .collect();
+    BarChart::new(&shapes)
+        .style(&args.chart_style)
+        .data_csv(args.chart_data_csv)
+        .title("affected edges dep_req shape")
+        .subtitle(format!(
+            "affected_edges={}, locked_out_edges={}, break_rate_percent={}",
+            constraint_totals.affected_edges,
+            constraint_totals.locked_out_edges,
+            constraint_totals.break_rate_percent()
+        ))
+        .write_svg(out_dir.join("constraint_req_shape_bar.svg"))?;
+
+    if !severity_constraint_totals.is_empty() {
+        let series: Vec<&str> = severity_constraint_totals
+            .iter()
+            .map(|(sev, _)| sev.as_str())
+            .collect();
+        let shapes_by_severity: Vec<(&str, Vec<usize>)> = ReqShape::ALL
+            .into_iter()
+            .filter(|shape| *shape != ReqShape::Unparseable)
+            .map(|shape| {
+                (
+                    shape.label(),
+                    severity_constraint_totals
+                        .iter()
+                        .map(|(_, t)| t.affected_req_shape[shape as usize])
+                        .collect(),
+                )
+            })
+            .collect();
+        GroupedBarChart::new(&shapes_by_severity, &series)
+            .style(&args.chart_style)
+            .title("affected edges dep_req shape by severity")
+            .subtitle(
+                severity_constraint_totals
+                    .iter()
+                    .map(|(sev, t)| {
+                        format!("{}: break_rate_percent={}", sev, t.break_rate_percent())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .write_svg(out_dir.join("constraint_req_shape_by_severity.svg"))?;
+    }
+    Ok(())
+}
+
+// Re-reads each sampled propagation edge from the database; a sample that is not there
+// fails the run.
+async fn verify_propagation_samples(
+    db: &Database,
+    logger: &mut Logger,
+    args: &Args,
+    verifier: VerifySampler,
+) -> Result<()> {
+    let events_seen = verifier.seen();
+    let samples = verifier.into_samples();
+    if samples.is_empty() {
+        return Ok(());
+    }
+    let mut hop_counts: BTreeMap<usize, usize> = BTreeMap::new();
+    for s in &samples {
+        let rows = db.query_all_downstream_details(&s.upstream_crate).await?;
+        let ok = rows.iter().any(|r| {
+            r.crate_name == s.downstream_crate
+                && r.version == s.downstream_version
+                && r.created_at == s.downstream_time
+                && r.dep_req == s.dep_req
+        });
+        if !ok {
+            let same_crate = rows
+                .iter()
+                .filter(|r| r.crate_name == s.downstream_crate)
+                .count();
+            return Err(anyhow!(
+                "propagation edge verify failed: rustsec_id={} cve_id={} root_crate={} hop={} carrier={} carrier_fix_version={} carrier_fix_time={} downstream={} version={} time={} dep_req={} (carrier has {} downstream rows, {} for this downstream crate)",
+                s.rustsec_id,
+                s.cve_id,
+                s.root_crate,
+                s.hop,
+                s.upstream_crate,
+                s.upstream_fix_version,
+                s.upstream_fix_time,
+                s.downstream_crate,
+                s.downstream_version,
+                s.downstream_time,
+                s.dep_req,
+                rows.len(),
+                same_crate
+            ));
+        }
+        *hop_counts.entry(s.hop).or_default() += 1;
+    }
+    let by_hop: Vec<String> = hop_counts
+        .iter()
+        .map(|(hop, n)| format!("hop{hop}={n}"))
+        .collect();
+    logger.println(format!(
+        "verified propagation edges: {} samples of {} events (seed={}, {})",
+        samples.len(),
+        events_seen,
+        args.verify_seed,
+        by_hop.join(" ")
+    ))?;
+    Ok(())
+}
+
+fn log_advisory_counts(
+    logger: &mut Logger,
+    args: &Args,
+    totals: &RunTotals,
+    processed: usize,
+    errored: usize,
+) -> Result<()> {
+    logger.println(format!(
+        "processed advisories: {processed}, written rows: {}, skipped advisories: {}",
+        totals.written_rows, totals.skipped
+    ))?;
+    if errored > 0 {
+        logger.println(format!(
+            "errored advisories: {} (written to {})",
+            errored,
+            args.errors_output.display()
+        ))?;
+    }
+    if totals.propagation_fallback_latest_seed > 0 {
+        logger.println(format!(
+            "propagation fallback advisories (no patched using latest version): {}",
+            totals.propagation_fallback_latest_seed
+        ))?;
+    }
+    if totals.fixed_from_partial_req > 0 || totals.fixed_from_published_range > 0 {
+        logger.println(format!(
+            "fixed versions recovered (previously no_fixed_versions): partial_or_wildcard_req={} published_range={}",
+            totals.fixed_from_partial_req, totals.fixed_from_published_range
+        ))?;
+    }
+    if totals.min_version_changed_rows > 0 {
+        logger.println(format!(
+            "estimate_min_version reclassified downstream reqs: rows={} distinct_reqs={} upper_bound_only_rows={}",
+            totals.min_version_changed_rows,
+            totals.min_version_changed_reqs.len(),
+            totals.min_version_upper_only_rows
+        ))?;
+    }
+    if totals.negative_lag_rows_total > 0 {
+        logger.println(format!(
+            "negative strict lags (downstream req admitted a fix before it was published): {}{}",
+            totals.negative_lag_rows_total,
+            if args.include_negative_lags {
+                format!(" (written to {})", args.negative_lags_output.display())
+            } else {
+                " (excluded; use --include-negative-lags to export)".to_string()
+            }
+        ))?;
+    }
+    let fix_delta_totals = &totals.fix_delta_totals;
+    if !fix_delta_totals.is_empty() {
+        logger.println(format!(
+            "fix version delta (first adoptions, original_req min -> matched fix): {} unknown={}",
+            VersionDelta::ALL
+                .iter()
+                .map(|d| format!(
+                    "{}={}",
+                    d.as_str(),
+                    fix_delta_totals.get(&Some(*d)).copied().unwrap_or(0)
+                ))
+                .collect::<Vec<_>>()
+                .join(" "),
+            fix_delta_totals.get(&None).copied().unwrap_or(0)
+        ))?;
+    }
+    if totals.pre_disclosure_rows > 0 {
+        logger.println(format!(
+            "strict lag rows adopted before advisory disclosure (t0={}): {}",
+            args.t0.as_str(),
+            totals.pre_disclosure_rows
+        ))?;
+    }
+    Ok(())
+}
+
+// Downstream filtering, timestamp checks and crates.io lookups over the run.
+fn log_downstream_lookups(
+    logger: &mut Logger,
+    args: &Args,
+    totals: &RunTotals,
+    metadata: &RunMetadata<'_>,
+) -> Result<()> {
+    if args.downstream_min_versions.is_some() || args.downstream_active_within_days.is_some() {
+        logger.println(format!(
+            "downstream activity filter (crates excluded, summed over analysed targets): fewer than {} versions={} no release within {} days before the fix={}",
+            args.downstream_min_versions.unwrap_or(0),
+            totals.downstream_exclusions.few_versions,
+            args.downstream_active_within_days
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            totals.downstream_exclusions.inactive
+        ))?;
+    }
+    let timestamps = &totals.timestamps;
+    if timestamps.checked > 0 {
+        logger.println(format!(
+            "timestamp anomalies (negative-lag downstream versions vs crates.io, >{}h): checked={} unavailable={} disagreeing={} substituted_rows={}",
+            args.timestamp_anomaly_hours,
+            timestamps.checked,
+            timestamps.unavailable,
+            timestamps.anomalies,
+            timestamps.substituted_rows
+        ))?;
+    }
+    if totals.duplicate_dep_rows_merged > 0 || totals.propagation_duplicate_dep_rows_merged > 0 {
+        logger.println(format!(
+            "duplicate dependency rows for the same downstream version merged (--duplicate-dep-reqs {}): strict={} propagation={}",
+            args.duplicate_dep_reqs.as_str(),
+            totals.duplicate_dep_rows_merged,
+            totals.propagation_duplicate_dep_rows_merged
+        ))?;
+    }
+    if totals.crates_io_time_fallback_hits > 0 || totals.crates_io_time_fallback_misses > 0 {
+        logger.println(format!(
+            "crates.io version-time fallback: hits={} misses={}",
+            totals.crates_io_time_fallback_hits, totals.crates_io_time_fallback_misses
+        ))?;
+    }
+    if !metadata.crates_io_version_time.is_empty() {
+        logger.println("crates.io version-time lookups by outcome:")?;
+        logger.println(format!(
+            "  {:<14} {:>8} {:>8} {:>10} {:>9}",
+            "outcome", "lookups", "requests", "latency_s", "mean_ms"
+        ))?;
+        for category in CratesIoTimeOutcome::CATEGORIES {
+            let t = metadata
+                .crates_io_version_time
+                .get(category)
+                .copied()
+                .unwrap_or_default();
+            let mean_ms = if t.requests > 0 {
+                format!("{:.0}", t.latency_secs * 1000.0 / t.requests as f64)
+            } else {
+                "-".to_string()
+            };
+            logger.println(format!(
+                "  {category:<14} {:>8} {:>8} {:>10.2} {mean_ms:>9}",
+                t.lookups, t.requests, t.latency_secs
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+// Advisory-level counts and the first-adoption lag breakdowns.
+fn log_lag_summary(logger: &mut Logger, args: &Args, totals: &RunTotals) -> Result<()> {
+    let function_scoped_by_severity = &totals.function_scoped_by_severity;
+    let scoped_total: usize = function_scoped_by_severity
+        .iter()
+        .filter(|((_, scoped), _)| *scoped)
+        .map(|(_, n)| n)
+        .sum();
+    let summarized_total: usize = function_scoped_by_severity.values().sum();
+    logger.println(format!(
+        "function-scoped advisories: {scoped_total} of {summarized_total} summarized"
+    ))?;
+    logger.println(format!(
+        "advisories with a semver-compatible fix: {} of {summarized_total} summarized",
+        totals.fix_semver_compatible_advisories
+    ))?;
+    let zero_adopter_advisories = totals.zero_adopter_advisories;
+    let with_adopters = summarized_total - zero_adopter_advisories;
+    logger.println(format!(
+        "advisories with any adoption: {with_adopters} of {summarized_total} summarized ({}); zero adopters: {zero_adopter_advisories}; downstream affected={}",
+        if summarized_total > 0 {
+            format!(
+                "{:.1}%",
+                with_adopters as f64 * 100.0 / summarized_total as f64
+            )
+        } else {
+            "n/a".to_string()
+        },
+        totals.downstream_affected_total
+    ))?;
+    let exposure_classes = exposure_classes(&totals.exposure);
+    let exposure_total: usize = exposure_classes.iter().map(|(_, n)| n).sum();
+    logger.println(format!(
+        "downstream exposure (crates with history before the fix, summed over advisories): {}",
+        exposure_classes
+            .iter()
+            .map(|(label, n)| format!(
+                "{label}={n} ({})",
+                if exposure_total > 0 {
+                    format!("{:.1}%", *n as f64 * 100.0 / exposure_total as f64)
+                } else {
+                    "n/a".to_string()
+                }
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))?;
+    logger.println(format!(
+        "post-fix new dependents (first depended after the fix, never exposed; not in the classes above): {}",
+        totals.exposure.post_fix_new
+    ))?;
+    if let Some(s) = compute_lag_stats(totals.upstream_exposure_days.iter().copied()) {
+        logger.println(format!(
+            "upstream exposure (first vulnerable release -> fix, days): n={} p5={} p25={} p50={} p75={} p95={} max={}; vulnerable since first release: {}",
+            s.count,
+            format_float(s.p5),
+            format_float(s.p25),
+            format_float(s.p50),
+            format_float(s.p75),
+            format_float(s.p95),
+            format_float(s.max),
+            totals.vulnerable_since_first_release
+        ))?;
+    }
+    let function_scoped_rows = function_scoped_rows(totals);
+    if !function_scoped_rows.is_empty() {
+        logger.println("function_scoped by severity (scoped / not scoped):")?;
+        for row in &function_scoped_rows {
+            logger.println(format!("  {}: {} / {}", row[0], row[1], row[2]))?;
+        }
+    }
+    let evidence_lines: Vec<String> = AdoptionEvidence::ALL
+        .iter()
+        .filter_map(|e| {
+            let lags = totals.lags_by_evidence.get(e)?;
+            let s = compute_lag_stats(lags.iter().copied())?;
+            Some(format!(
+                "  {}: n={} p25={} p50={} p75={} p95={}",
+                e.as_str(),
+                s.count,
+                format_float(s.p25),
+                format_float(s.p50),
+                format_float(s.p75),
+                format_float(s.p95)
+            ))
+        })
+        .collect();
+    if !evidence_lines.is_empty() {
+        logger.println(format!(
+            "strict lag by adoption evidence (first adoption, {}):",
+            args.lag_unit.column()
+        ))?;
+        for line in &evidence_lines {
+            logger.println(line)?;
+        }
+    }
+    let fix_compat_groups = fix_compat_groups(totals);
+    if !fix_compat_groups.is_empty() {
+        logger.println(format!(
+            "strict lag by fix semver compatibility (first adoption, {}):",
+            args.lag_unit.column()
+        ))?;
+        for (_, label, s) in &fix_compat_groups {
+            logger.println(format!(
+                "  {label}: n={} p25={} p50={} p75={} p95={}",
+                s.count,
+                format_float(s.p25),
+                format_float(s.p50),
+                format_float(s.p75),
+                format_float(s.p95)
+            ))?;
+        }
+    }
+    if let Some(s) = compute_lag_stats(totals.lag_over_cadence.iter().copied()) {
+        let first_adoptions: usize = cadence_groups(totals).iter().map(|(_, l)| l.len()).sum();
+        logger.println(format!(
+            "strict lag over downstream release cadence (first adoptions with >=3 releases in the year before the fix, n={} of {first_adoptions}): p25={} p50={} p75={} p95={}",
+            s.count,
+            format_float(s.p25),
+            format_float(s.p50),
+            format_float(s.p75),
+            format_float(s.p95)
+        ))?;
+    }
+    let dependent_tier_groups = dependent_tier_groups(args, totals);
+    if !dependent_tier_groups.is_empty() {
+        logger.println(format!(
+            "strict lag by downstream dependents (first adoption, {}):",
+            args.lag_unit.column()
+        ))?;
+        for (label, s) in &dependent_tier_groups {
+            logger.println(format!(
+                "  {label}: n={} p25={} p50={} p75={} p95={}",
+                s.count,
+                format_float(s.p25),
+                format_float(s.p50),
+                format_float(s.p75),
+                format_float(s.p95)
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+// --category-summary-output: one row per advisory category with its first-adoption lag
+// stats; categories without adoptions keep their advisory count and empty stats.
+fn write_category_summary(
+    cw: &mut csv::Writer<impl Write>,
+    args: &Args,
+    totals: &RunTotals,
+) -> Result<()> {
+    cw.write_record([
+        "category",
+        "advisory_cnt",
+        "lag_unit",
+        "count",
+        "min",
+        "p25",
+        "p50",
+        "avg",
+        "p75",
+        "p95",
+        "max",
+    ])?;
+    for (category, advisories, lags) in &category_groups(totals) {
+        let mut record = vec![
+            category.clone(),
+            advisories.to_string(),
+            args.lag_unit.as_str().to_string(),
+        ];
+        match compute_lag_stats(lags.iter().copied()) {
+            Some(s) => {
+                record.push(s.count.to_string());
+                record.extend([s.min, s.p25, s.p50, s.avg, s.p75, s.p95, s.max].map(format_float));
+            }
+            None => {
+                record.push("0".to_string());
+                record.resize(record.len() + 7, String::new());
+            }
+        }
+        cw.write_record(&record)?;
+    }
+    Ok(())
+}
+
+fn write_category_chart(args: &Args, totals: &RunTotals) -> Result<()> {
+    let category_groups = category_groups(totals);
+    let bars: Vec<(&str, Vec<usize>)> = category_groups
+        .iter()
+        .filter_map(|(category, _, lags)| {
+            let s = compute_lag_stats(lags.iter().copied())?;
+            Some((
+                category.as_str(),
+                [s.p25, s.p50, s.p75]
+                    .iter()
+                    .map(|v| v.max(0.0).round() as usize)
+                    .collect(),
+            ))
+        })
+        .collect();
+    if bars.is_empty() {
+        return Ok(());
+    }
+    let out_dir = args.strict_output_dir.as_path();
+    std::fs::create_dir_all(out_dir)?;
+    GroupedBarChart::new(&bars, &["p25", "p50", "p75"])
+        .style(&args.chart_style)
+        .title("strict lag by advisory category (first adoption)")
+        .subtitle("an advisory with several categories counts in each; (none) = no categories")
+        .y_label(args.lag_unit.column())
+        .write_svg(out_dir.join("lag_by_category.svg"))?;
+    Ok(())
+}
+
+// --html-report: the run counts, the first-adoption lag tables, the text summaries and
+// every chart written under the output directories.
+fn write_html_report(
+    path: &Path,
+    args: &Args,
+    totals: &RunTotals,
+    run_counts: &[(&str, usize)],
+) -> Result<()> {
+    let mut report = HtmlReport::new("rustsec rqx2 strict lag report");
+    let command_line: Vec<String> = std::env::args().collect();
+    let mut counts = run_counts.to_vec();
+    for reason in SkipReason::ALL {
+        if let Some(n) = totals.skipped_by_reason.get(&reason) {
+            counts.push((reason.as_str(), *n));
+        }
+    }
+    let mut meta = format!("command: {}\n", command_line.join(" "));
+    if let Some(as_of) = args.as_of {
+        meta.push_str(&format!("as_of: {as_of}\n"));
+    }
+    meta.push('\n');
+    for (name, n) in counts {
+        meta.push_str(&format!("{name:<30}= {n}\n"));
+    }
+    report.pre("run", &meta);
+
+    let lag_header = [
+        "severity", "count", "min", "p25", "p50", "avg", "p75", "p95", "max",
+    ];
+    let lag_row = |label: &str, lags: &[f64]| {
+        compute_lag_stats(lags.iter().copied()).map(|s| {
+            vec![
+                label.to_string(),
+                s.count.to_string(),
+                format_float(s.min),
+                format_float(s.p25),
+                format_float(s.p50),
+                format_float(s.avg),
+                format_float(s.p75),
+                format_float(s.p95),
+                format_float(s.max),
+            ]
+        })
+    };
+    let severity_groups = severity_groups(totals);
+    let all_lags: Vec<f64> = severity_groups
+        .iter()
+        .flat_map(|(_, lags)| lags.iter().copied())
+        .collect();
+    let mut lag_rows: Vec<Vec<String>> = lag_row("all", &all_lags).into_iter().collect();
+    for (sev, lags) in &severity_groups {
+        lag_rows.extend(lag_row(sev, lags));
+    }
+    for (scoped, label) in [(true, "function_scoped"), (false, "not_function_scoped")] {
+        if let Some(lags) = totals.lags_by_function_scoped.get(&scoped) {
+            lag_rows.extend(lag_row(label, lags));
+        }
+    }
+    for (compatible, label) in [
+        (true, "fix_semver_compatible"),
+        (false, "fix_semver_incompatible"),
+    ] {
+        if let Some(lags) = totals.lags_by_fix_compat.get(&compatible) {
+            lag_rows.extend(lag_row(label, lags));
+        }
+    }
+    for evidence in AdoptionEvidence::ALL {
+        if let Some(lags) = totals.lags_by_evidence.get(&evidence) {
+            lag_rows.extend(lag_row(evidence.as_str(), lags));
+        }
+    }
+    for (i, label) in dependent_tier_labels(&args.dependent_tiers)
+        .iter()
+        .enumerate()
+    {
+        if let Some(lags) = totals.lags_by_dependent_tier.get(&i) {
+            lag_rows.extend(lag_row(&format!("dependents {label}"), lags));
+        }
+    }
+    for (label, lags) in &cadence_groups(totals) {
+        lag_rows.extend(lag_row(&format!("release cadence {label}"), lags));
+    }
+    for (category, _, lags) in &category_groups(totals) {
+        lag_rows.extend(lag_row(&format!("category {category}"), lags));
+    }
+    report.table(
+        format!("strict lag, first adoption ({})", args.lag_unit.column()),
+        &lag_header,
+        &lag_rows,
+    );
+    report.table(
+        "function-scoped advisories by severity",
+        &["severity", "function_scoped", "not_function_scoped"],
+        &function_scoped_rows(totals),
+    );
+
+    if args.propagation {
+        let text = std::fs::read_to_string(&args.propagation_summary_output)?;
+        report.pre("propagation summary", &strip_comment_lines(&text));
+    }
+    if args.constraint {
+        let text = std::fs::read_to_string(&args.constraint_summary_output)?;
+        report.pre("constraint summary", &strip_comment_lines(&text));
+    }
+
+    let mut svg_dirs = vec![&args.strict_output_dir];
+    if args.propagation {
+        svg_dirs.push(&args.propagation_output_dir);
+    }
+    if args.constraint {
+        svg_dirs.push(&args.constraint_output_dir);
+    }
+    for dir in svg_dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut svgs: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "svg"))
+            .collect();
+        svgs.sort();
+        for p in svgs {
+            let svg = std::fs::read_to_string(&p)?;
+            report.svg(p.display().to_string(), &svg);
+        }
+    }
+
+    report.write(path)
+}
+
+// Outputs covered by the --verify-deterministic digest, in a stable order.
+fn digest_outputs(args: &Args) -> Vec<PathBuf> {
+    let mut files = vec![
+        args.output.clone(),
+        args.summary_output.clone(),
+        args.errors_output.clone(),
+    ];
+    if args.include_negative_lags {
+        files.push(args.negative_lags_output.clone());
+    }
+    if args.group_by_owner {
+        files.push(args.owner_grouped_output.clone());
+    }
+    if args.collapse_prefix_regex.is_some() {
+        files.push(args.collapsed_output.clone());
+    }
+    let mut svg_dirs = vec![&args.strict_output_dir];
+    svg_dirs.extend(&args.per_advisory_svgs);
+    if args.propagation {
+        files.push(args.propagation_summary_output.clone());
+        files.push(args.propagation_coverage_output.clone());
+        files.push(args.propagation_reach_output.clone());
+        files.extend(args.propagation_events_output.clone());
+        svg_dirs.push(&args.propagation_output_dir);
+    }
+    if args.constraint {
+        files.push(args.constraint_breakdown_output.clone());
+        files.push(args.constraint_summary_output.clone());
+        files.extend(args.constraint_edges_output.clone());
+        if !args.constraint_horizons.is_empty() {
+            files.push(args.constraint_horizons_output.clone());
+        }
+        svg_dirs.push(&args.constraint_output_dir);
+    }
+    files.extend(args.adoption_curve_output.clone());
+    files.extend(args.category_summary_output.clone());
+    if args.cve_list.is_some() {
+        files.push(args.cve_coverage_output.clone());
+    }
+    files.extend(args.html_report.clone());
+    for dir in svg_dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        // Charts and their companion data CSVs.
+        let mut svgs: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|ext| ext == "svg" || ext == "csv")
+            })
+            .collect();
+        svgs.sort();
+        files.extend(svgs);
+    }
+    files
+}
+
+// --explain-output, or explain_<id>.txt in the working directory.
+fn explain_output_path(args: &Args, id: &str) -> PathBuf {
+    args.explain_output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(file_name_component(&format!("explain_{id}.txt"))))
+}
+
+// Everything the run wrote: the digested outputs plus the run-specific files the digest
+// leaves out.
+fn written_outputs(args: &Args) -> Vec<PathBuf> {
+    let mut files = digest_outputs(args);
+    if let Some(dir) = args.parquet_output_dir.as_deref() {
+        files.push(dir.join("lag_rows.parquet"));
+        files.push(dir.join("summary.parquet"));
+        if args.propagation {
+            files.push(dir.join("propagation_events.parquet"));
+        }
+    }
+    if let Some(id) = &args.explain {
+        files.push(explain_output_path(args, id));
+    }
+    files.extend(args.timings_output.clone());
+    files.extend(args.metadata_output.clone());
+    files.extend(args.log_output.clone());
+    files
+}
+
+// What produced a set of outputs: written as JSON by --metadata-output and as `#` lines
+// at the top of the text summaries.
+#[derive(serde::Serialize)]
+struct RunMetadata<'a> {
+    tool: &'static str,
+    version: &'static str,
+    git_describe: &'static str,
+    args: &'a Args,
+    advisory_db: AdvisorySource,
+    database: String,
+    snapshot_freshness: Option<DateTime<Utc>>,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    truncated: Option<RunTruncation>,
+    // crates.io version-time lookups by outcome category.
+    crates_io_version_time: BTreeMap<&'static str, CratesIoTimeTally>,
+}
+
+// Set when --max-runtime-minutes stopped the run: the advisories after `last_completed`
+// that were never started, in processing order.
+#[derive(serde::Serialize)]
+struct RunTruncation {
+    max_runtime_minutes: f64,
+    last_completed: Option<String>,
+    remaining: Vec<String>,
+}
+
+impl RunMetadata<'_> {
+    fn comment_lines(&self) -> Result<String> {
+        let unknown = || "unknown".to_string();
+        Ok(format!(
+            "# tool: {} {} (git {})\n# args: {}\n# advisory_db: {} dir={} commit={} parse_errors={}\n# database: {} snapshot_freshness={}\n# started_at: {}\n",
+            self.tool,
+            self.version,
+            self.git_describe,
+            serde_json::to_string(self.args)?,
+            self.advisory_db.url,
+            self.advisory_db
+                .top_level_dir
+                .clone()
+                .unwrap_or_else(unknown),
+            self.advisory_db.commit.clone().unwrap_or_else(unknown),
+            self.advisory_db.parse_error_count,
+            self.database,
+            self.snapshot_freshness
+                .map(|t| t.to_string())
+                .unwrap_or_else(unknown),
+            self.started_at
+        ))
+    }
+}
+
+fn strip_comment_lines(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(|l| format!("{l}\n"))
+        .collect()
+}
+
+// Stable across platforms and Rust versions, unlike DefaultHasher.
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+// Severity as computed before v2 vectors and vectors with empty or malformed segments
+// were scored, rebuilt from the parsed advisory; None for overridden severities.
+fn legacy_severity(adv: &Advisory) -> Option<String> {
+    let informational = adv.severity_source == SeveritySource::Informational
+        || adv.informational != InformationalKind::None;
+    match adv.severity_source {
+        SeveritySource::Override => None,
+        SeveritySource::Advisory => Some(adv.severity.clone()),
+        _ => Some(
+            match adv
+                .cvss
+                .as_deref()
+                .filter(|c| c.trim().split('/').all(|part| part.contains(':')))
+                .and_then(cvss31_base_score_from_vector)
+            {
+                Some(score) => severity_from_cvss_score(score),
+                None if informational => "INFO".to_string(),
+                None => "UNKNOWN".to_string(),
+            },
+        ),
+    }
+}
+
+// Severity counts under the previous CVSS handling (v3 only, strict segment parsing)
+// next to the current ones.
+fn log_severity_reclassification(logger: &mut Logger, advisories: &[Advisory]) -> Result<()> {
+    let mut before: BTreeMap<(u8, String), usize> = BTreeMap::new();
+    let mut after: BTreeMap<(u8, String), usize> = BTreeMap::new();
+    let mut moved = 0usize;
+    // Overrides are manual corrections, not a change in how severities are extracted.
+    for (a, legacy) in advisories
+        .iter()
+        .filter_map(|a| legacy_severity(a).map(|legacy| (a, legacy)))
+    {
+        if legacy != a.severity {
+            moved += 1;
+        }
+        *before
+            .entry((severity_rank(&legacy).unwrap_or(u8::MAX), legacy))
+            .or_default() += 1;
+        *after
+            .entry((
+                severity_rank(&a.severity).unwrap_or(u8::MAX),
+                a.severity.clone(),
+            ))
+            .or_default() += 1;
+    }
+    let mut keys: Vec<&(u8, String)> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let parts: Vec<String> = keys
+        .into_iter()
+        .map(|k| {
+            format!(
+                "{}={}->{}",
+                k.1,
+                before.get(k).copied().unwrap_or(0),
+                after.get(k).copied().unwrap_or(0)
+            )
+        })
+        .collect();
+    logger.println(format!(
+        "severity distribution (legacy cvss -> current): {} reclassified={moved}",
+        parts.join(" ")
+    ))?;
+    Ok(())
+}
+
+#[cfg(feature = "cvss-crate")]
+fn log_cvss_disagreements(logger: &mut Logger, advisories: &[Advisory]) -> Result<()> {
+    let mut checked = 0usize;
+    let mut disagreements = 0usize;
+    for a in advisories {
+        let Some(vector) = a.cvss.as_deref() else {
+            continue;
+        };
+        checked += 1;
+        if let Some((ours, theirs)) = time_to_fix_cve::advisory::cvss3_score_disagreement(vector) {
+            disagreements += 1;
+            logger.println(format!(
+                "cvss score mismatch: rustsec_id={} vector={} ours={:.1} cvss_crate={:.1}",
+                a.rustsec_id, vector, ours, theirs
+            ))?;
+        }
+    }
+    logger.println(format!(
+        "cvss crate cross-check: vectors={checked} disagreements={disagreements}"
+    ))?;
+    Ok(())
+}
+
+fn apply_advisory_filters(
+    args: &Args,
+    advisories: &mut Vec<Advisory>,
+    logger: &mut Logger,
+) -> Result<()> {
+    let only: HashSet<String> = args
+        .only
+        .iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let packages: HashSet<String> = args
+        .packages
+        .iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let min_rank = match args.min_severity.as_deref() {
+        Some(s) => Some(
+            severity_rank(&normalize_severity(s))
+                .ok_or_else(|| anyhow!("invalid --min-severity {s}"))?,
+        ),
+        None => None,
+    };
+    let max_rank = match args.max_severity.as_deref() {
+        Some(s) => Some(
+            severity_rank(&normalize_severity(s))
+                .ok_or_else(|| anyhow!("invalid --max-severity {s}"))?,
+        ),
+        None => None,
+    };
+
+    let mut excluded_only = 0usize;
+    let mut excluded_severity = 0usize;
+    let mut excluded_date = 0usize;
+    let mut excluded_packages = 0usize;
+    let mut informational_counts: HashMap<InformationalKind, usize> = HashMap::new();
+
+    advisories.retain(|a| {
+        if !only.is_empty() && !only.contains(&a.cve_id) && !only.contains(&a.rustsec_id) {
+            excluded_only += 1;
+            return false;
+        }
+
+        if min_rank.is_some() || max_rank.is_some() {
+            let in_range = match severity_rank(&a.severity) {
+                None => false,
+                Some(r) => min_rank.is_none_or(|m| r >= m) && max_rank.is_none_or(|m| r <= m),
+            };
+            if !in_range {
+                excluded_severity += 1;
+                return false;
+            }
+        }
+
+        if args.published_after.is_some() || args.published_before.is_some() {
+            let in_range = match a.date {
+                None => false,
+                Some(d) => {
+                    args.published_after.is_none_or(|after| d >= after)
+                        && args.published_before.is_none_or(|before| d <= before)
+                }
+            };
+            if !in_range {
+                excluded_date += 1;
+                return false;
+            }
+        }
+
+        if !packages.is_empty()
+            && !packages.contains(&a.package)
+            && !packages.contains(args.package_aliases.resolve(&a.package))
+        {
+            excluded_packages += 1;
+            return false;
+        }
+
+        *informational_counts.entry(a.informational).or_default() += 1;
+        args.informational.keeps(a.informational)
+    });
+
+    let mut informational_parts = Vec::new();
+    for kind in InformationalKind::ALL {
+        let n = informational_counts.get(&kind).copied().unwrap_or(0);
+        let verdict = if args.informational.keeps(kind) {
+            "included"
+        } else {
+            "excluded"
+        };
+        informational_parts.push(format!("{}={} {}", kind.as_str(), n, verdict));
+    }
+    logger.println(format!(
+        "advisory filter: informational policy={} {}",
+        args.informational.as_str(),
+        informational_parts.join(" ")
+    ))?;
+
+    if !only.is_empty() {
+        logger.println(format!("advisory filter: only excluded={excluded_only}"))?;
+    }
+    if min_rank.is_some() || max_rank.is_some() {
+        logger.println(format!(
+            "advisory filter: severity min={} max={} excluded={excluded_severity}",
+            args.min_severity.as_deref().unwrap_or("-"),
+            args.max_severity.as_deref().unwrap_or("-"),
+        ))?;
+    }
+    if args.published_after.is_some() || args.published_before.is_some() {
+        logger.println(format!(
+            "advisory filter: published after={} before={} excluded={excluded_date}",
+            args.published_after
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            args.published_before
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ))?;
+    }
+    if !packages.is_empty() {
+        logger.println(format!(
+            "advisory filter: packages excluded={excluded_packages}"
+        ))?;
+    }
+    Ok(())
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoCrateResponse {
+    #[serde(rename = "crate")]
+    krate: CratesIoCrate,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoCrate {
+    name: String,
+}
+
+async fn crates_io_query_canonical_name(
+    client: &Client,
+    crate_name: &str,
+) -> Result<Option<String>> {
+    let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+    let body: CratesIoCrateResponse = resp.json().await?;
+    Ok(Some(body.krate.name))
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoVersionResponse {
+    version: CratesIoVersion,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoVersion {
+    created_at: String,
+}
+
+// Why a crates.io version-time lookup did or did not produce a time. Everything but
+// network errors is cached, so a cached miss still says what caused it.
+#[derive(Clone, Debug)]
+enum CratesIoTimeOutcome {
+    Found(DateTime<Utc>),
+    NotFound,
+    Throttled,
+    HttpStatus(u16),
+    NetworkError(String),
+    DecodeError,
+}
+
+impl CratesIoTimeOutcome {
+    const CATEGORIES: [&'static str; 6] = [
+        "found",
+        "not_found",
+        "throttled",
+        "http_error",
+        "network_error",
+        "decode_error",
+    ];
+
+    fn category(&self) -> &'static str {
+        match self {
+            CratesIoTimeOutcome::Found(_) => "found",
+            CratesIoTimeOutcome::NotFound => "not_found",
+            CratesIoTimeOutcome::Throttled => "throttled",
+            CratesIoTimeOutcome::HttpStatus(_) => "http_error",
+            CratesIoTimeOutcome::NetworkError(_) => "network_error",
+            CratesIoTimeOutcome::DecodeError => "decode_error",
+        }
+    }
+
+    // The time if there is one; network errors stay errors, as before the taxonomy.
+    fn time(self) -> Result<Option<DateTime<Utc>>> {
+        match self {
+            CratesIoTimeOutcome::Found(t) => Ok(Some(t)),
+            CratesIoTimeOutcome::NetworkError(e) => Err(anyhow!(e)),
+            _ => Ok(None),
+        }
+    }
+}
+
+// Lookups per outcome category; requests and latency only count lookups that were not
+// answered from the cache.
+#[derive(Clone, Copy, Default, serde::Serialize)]
+struct CratesIoTimeTally {
+    lookups: usize,
+    requests: usize,
+    latency_secs: f64,
+}
+
+#[derive(Default)]
+struct CratesIoTimes {
+    cache: HashMap<(String, String), CratesIoTimeOutcome>,
+    stats: BTreeMap<&'static str, CratesIoTimeTally>,
+}
+
+impl CratesIoTimes {
+    async fn query(
+        &mut self,
+        client: &Client,
+        logger: &mut Logger,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<CratesIoTimeOutcome> {
+        let key = (crate_name.to_string(), version.to_string());
+        if let Some(outcome) = self.cache.get(&key) {
+            self.stats.entry(outcome.category()).or_default().lookups += 1;
+            return Ok(outcome.clone());
+        }
+
+        let started = Instant::now();
+        let outcome = fetch_crates_io_version_time(client, crate_name, version).await;
+        let tally = self.stats.entry(outcome.category()).or_default();
+        tally.lookups += 1;
+        tally.requests += 1;
+        tally.latency_secs += started.elapsed().as_secs_f64();
+        match &outcome {
+            CratesIoTimeOutcome::DecodeError => logger.debug(format!(
+                "crates.io version-time response for {crate_name} {version} did not decode"
+            ))?,
+            CratesIoTimeOutcome::HttpStatus(status) => logger.debug(format!(
+                "crates.io version-time lookup for {crate_name} {version}: HTTP {status}"
+            ))?,
+            _ => {}
+        }
+        if !matches!(outcome, CratesIoTimeOutcome::NetworkError(_)) {
+            self.cache.insert(key, outcome.clone());
+        }
+        Ok(outcome)
+    }
+}
+
+async fn fetch_crates_io_version_time(
+    client: &Client,
+    crate_name: &str,
+    version: &str,
+) -> CratesIoTimeOutcome {
+    let url = format!("https://crates.io/api/v1/crates/{}/{}", crate_name, version);
+    let resp = match client.get(&url).send().await {
+        Ok(r) => r,
+        Err(e) => return CratesIoTimeOutcome::NetworkError(format!("GET {url}: {e}")),
+    };
+    match resp.status() {
+        s if s.is_success() => {}
+        reqwest::StatusCode::NOT_FOUND => return CratesIoTimeOutcome::NotFound,
+        reqwest::StatusCode::TOO_MANY_REQUESTS => return CratesIoTimeOutcome::Throttled,
+        s => return CratesIoTimeOutcome::HttpStatus(s.as_u16()),
+    }
+    let body = match resp.bytes().await {
+        Ok(b) => b,
+        Err(e) => return CratesIoTimeOutcome::NetworkError(format!("GET {url}: {e}")),
+    };
+    serde_json::from_slice::<CratesIoVersionResponse>(&body)
+        .ok()
+        .and_then(|b| chrono::DateTime::parse_from_rfc3339(&b.version.created_at).ok())
+        .map_or(CratesIoTimeOutcome::DecodeError, |dt| {
+            CratesIoTimeOutcome::Found(dt.with_timezone(&Utc))
+        })
+}
+
+// crates.io asks crawlers for at most one request per second.
+const CRATES_IO_FALLBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(serde::Deserialize)]
+struct CratesIoReverseDependencies {
+    dependencies: Vec<serde::de::IgnoredAny>,
+    versions: Vec<CratesIoDependentVersion>,
+    meta: CratesIoMeta,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoDependentVersion {
+    #[serde(rename = "crate")]
+    krate: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoMeta {
+    total: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoVersionList {
+    versions: Vec<CratesIoVersionEntry>,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoVersionEntry {
+    num: String,
+    created_at: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoDependencyList {
+    dependencies: Vec<CratesIoDependency>,
+}
+
+#[derive(serde::Deserialize)]
+struct CratesIoDependency {
+    // The dependency's crate name, despite the field name.
+    crate_id: String,
+    req: String,
+    kind: String,
+}
+
+// --crates-io-downstream-fallback: rebuilds a crate's dependent history from the API when
+// the snapshot predates its dependents. Requests are sequential and spaced out.
+struct CratesIoFallback {
+    client: Client,
+    last_request: Option<Instant>,
+    requests: usize,
+    // Crates whose history came from the API, and the rows synthesized for them.
+    crates: usize,
+    rows: usize,
+}
+
+impl CratesIoFallback {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            last_request: None,
+            requests: 0,
+            crates: 0,
+            rows: 0,
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&mut self, url: String) -> Result<Option<T>> {
+        if let Some(last) = self.last_request {
+            let wait = CRATES_IO_FALLBACK_INTERVAL.saturating_sub(last.elapsed());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        self.last_request = Some(Instant::now());
+        self.requests += 1;
+        let resp = self.client.get(url).send().await?;
+        if !resp.status().is_success() {
+            return Ok(None);
+        }
+        Ok(Some(resp.json().await?))
+    }
+
+    // Every version of every crate listed as a reverse dependency, with its normal
+    // dependency req on `target_crate`, in the order the database returns them.
+    async fn downstream(&mut self, target_crate: &str) -> Result<Vec<DownstreamVersionInfo>> {
+        const PER_PAGE: usize = 100;
+        let mut dependents = BTreeSet::new();
+        for page in 1.. {
+            let url = format!(
+                "https://crates.io/api/v1/crates/{target_crate}/reverse_dependencies?page={page}&per_page={PER_PAGE}"
+            );
+            let Some(body) = self.get::<CratesIoReverseDependencies>(url).await? else {
+                break;
+            };
+            dependents.extend(body.versions.into_iter().map(|v| v.krate));
+            if body.dependencies.is_empty() || page * PER_PAGE >= body.meta.total {
+                break;
+            }
+        }
+
+        let mut rows = Vec::new();
+        for name in dependents {
+            let url = format!("https://crates.io/api/v1/crates/{name}/versions");
+            let Some(list) = self.get::<CratesIoVersionList>(url).await? else {
+                continue;
+            };
+            for v in list.versions {
+                let Ok(created_at) = DateTime::parse_from_rfc3339(&v.created_at) else {
+                    continue;
+                };
+                let url = format!(
+                    "https://crates.io/api/v1/crates/{name}/{}/dependencies",
+                    v.num
+                );
+                let Some(deps) = self.get::<CratesIoDependencyList>(url).await? else {
+                    continue;
+                };
+                rows.extend(
+                    deps.dependencies
+                        .into_iter()
+                        .filter(|d| d.crate_id == target_crate && d.kind == "normal")
+                        .map(|d| DownstreamVersionInfo {
+                            crate_name: name.clone(),
+                            version: v.num.clone(),
+                            created_at: created_at.with_timezone(&Utc),
+                            dep_req: d.req,
+                            source: RowSource::CratesIo,
+                        }),
+                );
+            }
+        }
+        rows.sort_by(|a, b| {
+            a.crate_name
+                .cmp(&b.crate_name)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        Ok(rows)
+    }
+}
+
+// Group of a downstream crate for --group-by-owner: its first owner in sort order, so a
+// team shared by a workspace wins over individual users, or the crate itself when the
+// dump has no ownership rows for it.
+async fn owner_group(
+    db: &Database,
+    cache: &mut HashMap<String, String>,
+    crate_name: &str,
+) -> Result<String> {
+    if let Some(group) = cache.get(crate_name) {
+        return Ok(group.clone());
+    }
+    let group = db
+        .query_crate_owners(crate_name)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| format!("crate:{crate_name}"));
+    cache.insert(crate_name.to_string(), group.clone());
+    Ok(group)
+}
+
+async fn group_rows_by_owner<'r>(
+    db: &Database,
+    cache: &mut HashMap<String, String>,
+    rows: impl Iterator<Item = &'r StrictLagRow>,
+) -> Result<Vec<(String, &'r StrictLagRow, usize)>> {
+    let mut keyed = Vec::new();
+    for r in rows {
+        keyed.push((owner_group(db, cache, &r.downstream_crate).await?, r));
+    }
+    Ok(collapse_earliest(keyed))
+}
+
+// Tier of a crate with `n` dependents given ascending inclusive upper bounds; counts above
+// the last bound fall in the extra top tier.
+// Upper bounds in days of the downstream release-cadence bins; rows whose crate has no
+// cadence (fewer than 3 releases in the year before the fix) go to one bin past the end.
+const CADENCE_BINS: [(f64, &str); 5] = [
+    (7.0, "<=7d"),
+    (30.0, "7-30d"),
+    (90.0, "30-90d"),
+    (180.0, "90-180d"),
+    (f64::INFINITY, ">180d"),
+];
+
+fn cadence_bin(cadence_days: Option<f64>) -> usize {
+    match cadence_days {
+        Some(c) => CADENCE_BINS
+            .iter()
+            .position(|(b, _)| c <= *b)
+            .unwrap_or(CADENCE_BINS.len() - 1),
+        None => CADENCE_BINS.len(),
+    }
+}
+
+fn cadence_bin_label(bin: usize) -> &'static str {
+    CADENCE_BINS
+        .get(bin)
+        .map_or("<3 releases", |(_, label)| label)
+}
+
+// Sort key for --sort-events: (root_rustsec_id, hop, lag_days).
+fn propagation_event_key(
+    headers: &csv::StringRecord,
+) -> Result<impl Fn(&csv::StringRecord) -> Result<(String, i64, i64)> + use<>> {
+    let id = column_index(headers, "root_rustsec_id")?;
+    let hop = column_index(headers, "hop")?;
+    let lag = column_index(headers, "lag_days")?;
+    Ok(move |r: &csv::StringRecord| Ok((r[id].to_string(), r[hop].parse()?, r[lag].parse()?)))
+}
+
+const UNCATEGORIZED: &str = "(none)";
+
+fn advisory_categories(adv: &Advisory) -> Vec<&str> {
+    if adv.categories.is_empty() {
+        vec![UNCATEGORIZED]
+    } else {
+        adv.categories.iter().map(|c| c.as_str()).collect()
+    }
+}
+
+fn dependent_tier(bounds: &[i64], n: i64) -> usize {
+    bounds.iter().position(|b| n <= *b).unwrap_or(bounds.len())
+}
+
+// "0", "1-10", "11-100", ">100" for bounds 0,10,100.
+fn dependent_tier_labels(bounds: &[i64]) -> Vec<String> {
+    let mut labels = Vec::with_capacity(bounds.len() + 1);
+    let mut lower = 0;
+    for b in bounds {
+        labels.push(if lower >= *b {
+            b.to_string()
+        } else {
+            format!("{lower}-{b}")
+        });
+        lower = b + 1;
+    }
+    labels.push(match bounds.last() {
+        Some(b) => format!(">{b}"),
+        None => "all".to_string(),
+    });
+    labels
+}
+
+async fn query_all_version_numbers_cached(
+    db: &Database,
+    cache: &mut HashMap<String, Vec<String>>,
+    renames: &CrateRenames,
+    crate_name: &str,
+    cutoff: Option<DateTime<Utc>>,
+    excluded: &mut usize,
+) -> Result<Vec<String>> {
+    if let Some(v) = cache.get(crate_name) {
+        return Ok(v.clone());
+    }
+    let rows = if cutoff.is_none() && renames.chain(crate_name).len() == 1 {
+        db.query_all_version_numbers(crate_name).await?
+    } else {
+        let all = renamed_version_times(db, renames, crate_name).await?;
+        let total = all.len();
+        let kept: Vec<String> = all
+            .into_iter()
+            .filter(|(_, t)| within_as_of(*t, cutoff))
+            .map(|(num, _)| num)
+            .collect();
+        *excluded += total - kept.len();
+        kept
+    };
+    cache.insert(crate_name.to_string(), rows.clone());
+    Ok(rows)
+}
+
+fn within_as_of(t: DateTime<Utc>, cutoff: Option<DateTime<Utc>>) -> bool {
+    cutoff.is_none_or(|c| t < c)
+}
+
+// Publish times of every name in the crate's --crate-renames chain, merged
+// chronologically with republished version numbers kept once.
+async fn renamed_version_times(
+    db: &Database,
+    renames: &CrateRenames,
+    crate_name: &str,
+) -> Result<Vec<(String, DateTime<Utc>)>> {
+    let names = renames.chain(crate_name);
+    if names.len() == 1 {
+        return db.query_all_version_times(crate_name).await;
+    }
+    let mut histories = Vec::new();
+    for name in names {
+        let times = db.query_all_version_times(&name).await?;
+        histories.push((name, times));
+    }
+    Ok(
+        merge_renamed_histories(histories, |(_, t)| *t, |(num, _)| num.clone())
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect(),
+    )
+}
+
+// Version numbers with their publish times, limited to the --as-of snapshot.
+async fn published_version_times(
+    db: &Database,
+    renames: &CrateRenames,
+    crate_name: &str,
+    crate_id: i64,
+    cutoff: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, DateTime<Utc>)>> {
+    let times = if renames.chain(crate_name).len() > 1 {
+        renamed_version_times(db, renames, crate_name).await?
+    } else {
+        db.query_all_version_times_by_id(crate_id).await?
+    };
+    Ok(times
+        .into_iter()
+        .filter(|(_, t)| within_as_of(*t, cutoff))
+        .collect())
+}
+
+// Wall-clock time per stage of one advisory: `enter` charges the time since the last
+// boundary to the stage being left.
+struct AdvisoryTimings {
+    started: Instant,
+    boundary: Instant,
+    current: Stage,
+    spent: [Duration; 4],
+    finished: Option<Duration>,
+    downstream_rows: usize,
+    propagation_carriers: usize,
+    propagation_max_queue: usize,
+    propagation_cycle_edges: usize,
+    propagation_revisit_edges: usize,
+    propagation_queue_capped: bool,
+}
+
+impl AdvisoryTimings {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            boundary: now,
+            current: Stage::Fetch,
+            spent: [Duration::ZERO; 4],
+            finished: None,
+            downstream_rows: 0,
+            propagation_carriers: 0,
+            propagation_max_queue: 0,
+            propagation_cycle_edges: 0,
+            propagation_revisit_edges: 0,
+            propagation_queue_capped: false,
+        }
+    }
+
+    fn enter(&mut self, stage: Stage) {
+        let now = Instant::now();
+        self.spent[self.current as usize] += now - self.boundary;
+        self.boundary = now;
+        self.current = stage;
+    }
+
+    fn finish(&mut self) {
+        let current = self.current;
+        self.enter(current);
+        self.finished = Some(self.boundary - self.started);
+    }
+
+    fn spent(&self, stage: Stage) -> Duration {
+        self.spent[stage as usize]
+    }
+
+    fn total(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+}
+
+// Shared across advisories: caches, the logger and counters that cap output sizes.
+struct BatchContext<'a> {
+    args: &'a Args,
+    db: &'a Database,
+    client: &'a Client,
+    logger: Logger,
+    crates_io_times: CratesIoTimes,
+    crate_versions_cache: HashMap<String, Vec<String>>,
+    // RustSec package -> its name on crates.io, for names the crates table lacks.
+    canonical_names: HashMap<String, Option<String>>,
+    crate_downloads_cache: HashMap<String, i64>,
+    // None for crates with no version before the --as-of cutoff.
+    crate_activity_cache: HashMap<String, Option<CrateActivity>>,
+    // Downstream crate -> number of its own dependents, for --dependent-tiers.
+    dependent_counts_cache: HashMap<String, i64>,
+    // Downstream crate -> its --group-by-owner group.
+    owner_group_cache: HashMap<String, String>,
+    collapse_prefix: Option<Regex>,
+    analysis: AnalysisOptions<'a>,
+    cache: DownstreamCache,
+    propagation_verifier: VerifySampler,
+    propagation_events_written: usize,
+    constraint_edges: EdgeBudget,
+    processed: usize,
+    total_advisories: usize,
+    start: Instant,
+    now: DateTime<Utc>,
+    as_of_cutoff: Option<DateTime<Utc>>,
+    as_of_excluded_versions: usize,
+    // (group_id, crate) pairs already analyzed, so a group's rows are written once per crate.
+    group_crates: HashSet<(String, String)>,
+    explain: Option<ExplainSink>,
+    memory_degradations: usize,
+}
+
+struct RunTotals {
+    written_rows: usize,
+    skipped: usize,
+    skipped_by_reason: HashMap<SkipReason, usize>,
+    negative_lag_rows_total: usize,
+    adoption_kind_totals: HashMap<AdoptionKind, usize>,
+    // First adoptions per fix_version_delta; None when original_req has no minimum.
+    fix_delta_totals: HashMap<Option<VersionDelta>, usize>,
+    // First-adoption lags in fractional days, kept only for --adoption-curve-output.
+    adoption_lag_days_by_severity: HashMap<String, Vec<f64>>,
+    lags_by_severity: HashMap<String, Vec<f64>>,
+    lags_by_function_scoped: HashMap<bool, Vec<f64>>,
+    lags_by_fix_compat: HashMap<bool, Vec<f64>>,
+    fix_semver_compatible_advisories: usize,
+    lags_by_evidence: HashMap<AdoptionEvidence, Vec<f64>>,
+    // Keyed by index into `dependent_tier_labels`.
+    lags_by_dependent_tier: HashMap<usize, Vec<f64>>,
+    // First-adoption lags keyed by `cadence_bin`, and lag / cadence where there is one.
+    lags_by_cadence_bin: HashMap<usize, Vec<f64>>,
+    // First-adoption lags and summarized advisories per advisory category (each category
+    // of an advisory counts; UNCATEGORIZED when it has none).
+    lags_by_category: HashMap<String, Vec<f64>>,
+    category_advisories: HashMap<String, usize>,
+    lag_over_cadence: Vec<f64>,
+    // Summarized advisories per (severity, function_scoped).
+    function_scoped_by_severity: HashMap<(String, bool), usize>,
+    constraint_break_rate_per_adv_percent: Vec<f64>,
+    constraint_totals: ConstraintTotals,
+    constraint_break_rate_by_severity: HashMap<String, Vec<f64>>,
+    constraint_totals_by_severity: HashMap<String, ConstraintTotals>,
+    constraint_horizon_totals: Vec<HorizonBreak>,
+    propagation_fallback_latest_seed: usize,
+    propagation_seeds_used: usize,
+    // Summarized advisories whose target had kept downstream rows but no adopter.
+    zero_adopter_advisories: usize,
+    exposure: ExposureCounts,
+    downstream_affected_total: usize,
+    // First-adoption lags after --group-by-owner collapsing, and the rows collapsed.
+    owner_grouped_lags: Vec<f64>,
+    owner_grouped_input_rows: usize,
+    // Same for --collapse-prefix-regex.
+    collapsed_lags: Vec<f64>,
+    collapsed_input_rows: usize,
+    propagation_events_truncated: usize,
+    propagation_events_over_total_limit: usize,
+    propagation_seed_duplicates: usize,
+    propagation_cycle_edges: usize,
+    propagation_revisit_edges: usize,
+    propagation_cycle_advisories: usize,
+    propagation_queue_capped: Vec<String>,
+    fixed_from_partial_req: usize,
+    fixed_from_published_range: usize,
+    min_version_changed_rows: usize,
+    min_version_upper_only_rows: usize,
+    min_version_changed_reqs: HashSet<String>,
+    pre_disclosure_rows: usize,
+    crates_io_time_fallback_hits: usize,
+    crates_io_time_fallback_misses: usize,
+    // History rows merged away by --duplicate-dep-reqs, in strict scans and propagation.
+    duplicate_dep_rows_merged: usize,
+    propagation_duplicate_dep_rows_merged: usize,
+    downstream_exclusions: DownstreamExclusions,
+    timestamps: TimestampChecks,
+    // Lags are kept in seconds and converted to --lag-unit when reported.
+    propagation_lags_by_hop: HashMap<usize, Vec<i64>>,
+    // Seconds from the root fix to each propagation event, by hop.
+    propagation_cumulative_lags_by_hop: HashMap<usize, Vec<i64>>,
+    propagation_lags_by_evidence: HashMap<AdoptionEvidence, Vec<i64>>,
+    // (seconds since the first post-fix release, releases skipped) per event, by hop.
+    propagation_opportunity_by_hop: HashMap<usize, Vec<(i64, usize)>>,
+    propagation_coverage_by_hop: HashMap<usize, HopCoverage>,
+    // Per advisory, (unique crates, their downloads) first reached at hop i+1.
+    propagation_reach: Vec<Vec<(usize, i64)>>,
+    // Days from the first vulnerable release to the fix, one per summarized advisory.
+    upstream_exposure_days: Vec<f64>,
+    vulnerable_since_first_release: usize,
+}
+
+impl RunTotals {
+    fn new(args: &Args) -> Self {
+        Self {
+            written_rows: 0,
+            skipped: 0,
+            skipped_by_reason: HashMap::new(),
+            negative_lag_rows_total: 0,
+            adoption_kind_totals: HashMap::new(),
+            fix_delta_totals: HashMap::new(),
+            adoption_lag_days_by_severity: HashMap::new(),
+            lags_by_severity: HashMap::new(),
+            lags_by_function_scoped: HashMap::new(),
+            lags_by_fix_compat: HashMap::new(),
+            fix_semver_compatible_advisories: 0,
+            lags_by_evidence: HashMap::new(),
+            lags_by_dependent_tier: HashMap::new(),
+            lags_by_cadence_bin: HashMap::new(),
+            lags_by_category: HashMap::new(),
+            category_advisories: HashMap::new(),
+            lag_over_cadence: Vec::new(),
+            function_scoped_by_severity: HashMap::new(),
+            constraint_break_rate_per_adv_percent: Vec::new(),
+            constraint_totals: ConstraintTotals::default(),
+            constraint_break_rate_by_severity: HashMap::new(),
+            constraint_totals_by_severity: HashMap::new(),
+            constraint_horizon_totals: args
+                .constraint_horizons
+                .iter()
+                .map(|&d| HorizonBreak::new(d))
+                .collect(),
+            propagation_fallback_latest_seed: 0,
+            propagation_seeds_used: 0,
+            zero_adopter_advisories: 0,
+            exposure: ExposureCounts::default(),
+            downstream_affected_total: 0,
+            owner_grouped_lags: Vec::new(),
+            owner_grouped_input_rows: 0,
+            collapsed_lags: Vec::new(),
+            collapsed_input_rows: 0,
+            propagation_events_truncated: 0,
+            propagation_events_over_total_limit: 0,
+            propagation_seed_duplicates: 0,
+            propagation_cycle_edges: 0,
+            propagation_revisit_edges: 0,
+            propagation_cycle_advisories: 0,
+            propagation_queue_capped: Vec::new(),
+            fixed_from_partial_req: 0,
+            fixed_from_published_range: 0,
+            min_version_changed_rows: 0,
+            min_version_upper_only_rows: 0,
+            min_version_changed_reqs: HashSet::new(),
+            pre_disclosure_rows: 0,
+            crates_io_time_fallback_hits: 0,
+            crates_io_time_fallback_misses: 0,
+            duplicate_dep_rows_merged: 0,
+            propagation_duplicate_dep_rows_merged: 0,
+            downstream_exclusions: DownstreamExclusions::default(),
+            timestamps: TimestampChecks::default(),
+            propagation_lags_by_hop: HashMap::new(),
+            propagation_cumulative_lags_by_hop: HashMap::new(),
+            propagation_lags_by_evidence: HashMap::new(),
+            propagation_opportunity_by_hop: HashMap::new(),
+            propagation_coverage_by_hop: HashMap::new(),
+            propagation_reach: Vec::new(),
+            upstream_exposure_days: Vec::new(),
+            vulnerable_since_first_release: 0,
+        }
+    }
+
+    fn add_strict_scan(&mut self, result: &AdvisoryResult) {
+        if let Some(days) = result.exposure_days {
+            self.upstream_exposure_days.push(days);
+        }
+        if result.vulnerable_since_first_release {
+            self.vulnerable_since_first_release += 1;
+        }
+        self.timestamps.substituted_rows += result.corrected_rows;
+        self.duplicate_dep_rows_merged += result.merged_duplicates;
+        self.exposure.add(result.exposure);
+        self.negative_lag_rows_total += result.negative_rows.len();
+    }
+
+    fn note_opportunity(
+        &mut self,
+        hop: usize,
+        opportunity: Option<FirstOpportunity>,
+        adopted_at: DateTime<Utc>,
+    ) {
+        if let Some(o) = opportunity {
+            self.propagation_opportunity_by_hop
+                .entry(hop)
+                .or_default()
+                .push((o.lag_secs(adopted_at), o.skipped_releases));
+        }
+    }
+
+    fn merge(&mut self, other: RunTotals) {
+        self.written_rows += other.written_rows;
+        self.skipped += other.skipped;
+        for (reason, n) in other.skipped_by_reason {
+            *self.skipped_by_reason.entry(reason).or_default() += n;
+        }
+        self.negative_lag_rows_total += other.negative_lag_rows_total;
+        for (kind, n) in other.adoption_kind_totals {
+            *self.adoption_kind_totals.entry(kind).or_default() += n;
+        }
+        for (delta, n) in other.fix_delta_totals {
+            *self.fix_delta_totals.entry(delta).or_default() += n;
+        }
+        for (sev, lags) in other.adoption_lag_days_by_severity {
+            self.adoption_lag_days_by_severity
+                .entry(sev)
+                .or_default()
+                .extend(lags);
+        }
+        for (sev, lags) in other.lags_by_severity {
+            self.lags_by_severity.entry(sev).or_default().extend(lags);
+        }
+        for (category, lags) in other.lags_by_category {
+            self.lags_by_category
+                .entry(category)
+                .or_default()
+                .extend(lags);
+        }
+        for (category, n) in other.category_advisories {
+            *self.category_advisories.entry(category).or_default() += n;
+        }
+        for (tier, lags) in other.lags_by_dependent_tier {
+            self.lags_by_dependent_tier
+                .entry(tier)
+                .or_default()
+                .extend(lags);
+        }
+        for (evidence, lags) in other.lags_by_evidence {
+            self.lags_by_evidence
+                .entry(evidence)
+                .or_default()
+                .extend(lags);
+        }
+        for (scoped, lags) in other.lags_by_function_scoped {
+            self.lags_by_function_scoped
+                .entry(scoped)
+                .or_default()
+                .extend(lags);
+        }
+        for (compatible, lags) in other.lags_by_fix_compat {
+            self.lags_by_fix_compat
+                .entry(compatible)
+                .or_default()
+                .extend(lags);
+        }
+        self.fix_semver_compatible_advisories += other.fix_semver_compatible_advisories;
+        for (bin, lags) in other.lags_by_cadence_bin {
+            self.lags_by_cadence_bin
+                .entry(bin)
+                .or_default()
+                .extend(lags);
+        }
+        self.lag_over_cadence.extend(other.lag_over_cadence);
+        for (key, n) in other.function_scoped_by_severity {
+            *self.function_scoped_by_severity.entry(key).or_default() += n;
+        }
+        self.constraint_break_rate_per_adv_percent
+            .extend(other.constraint_break_rate_per_adv_percent);
+        self.constraint_totals.merge(&other.constraint_totals);
+        for (sev, rates) in other.constraint_break_rate_by_severity {
+            self.constraint_break_rate_by_severity
+                .entry(sev)
+                .or_default()
+                .extend(rates);
+        }
+        for (sev, t) in other.constraint_totals_by_severity {
+            self.constraint_totals_by_severity
+                .entry(sev)
+                .or_default()
+                .merge(&t);
+        }
+        for (total, h) in self
+            .constraint_horizon_totals
+            .iter_mut()
+            .zip(other.constraint_horizon_totals)
+        {
+            total.affected_edges += h.affected_edges;
+            total.locked_out_edges += h.locked_out_edges;
+        }
+        self.propagation_fallback_latest_seed += other.propagation_fallback_latest_seed;
+        self.propagation_seeds_used += other.propagation_seeds_used;
+        self.zero_adopter_advisories += other.zero_adopter_advisories;
+        self.exposure.add(other.exposure);
+        self.downstream_affected_total += other.downstream_affected_total;
+        self.owner_grouped_lags.extend(other.owner_grouped_lags);
+        self.owner_grouped_input_rows += other.owner_grouped_input_rows;
+        self.collapsed_lags.extend(other.collapsed_lags);
+        self.collapsed_input_rows += other.collapsed_input_rows;
+        self.propagation_events_truncated += other.propagation_events_truncated;
+        self.propagation_events_over_total_limit += other.propagation_events_over_total_limit;
+        self.propagation_seed_duplicates += other.propagation_seed_duplicates;
+        self.propagation_cycle_edges += other.propagation_cycle_edges;
+        self.propagation_revisit_edges += other.propagation_revisit_edges;
+        self.propagation_cycle_advisories += other.propagation_cycle_advisories;
+        self.propagation_queue_capped
+            .extend(other.propagation_queue_capped);
+        self.fixed_from_partial_req += other.fixed_from_partial_req;
+        self.fixed_from_published_range += other.fixed_from_published_range;
+        self.min_version_changed_rows += other.min_version_changed_rows;
+        self.min_version_upper_only_rows += other.min_version_upper_only_rows;
+        self.min_version_changed_reqs
+            .extend(other.min_version_changed_reqs);
+        self.pre_disclosure_rows += other.pre_disclosure_rows;
+        self.crates_io_time_fallback_hits += other.crates_io_time_fallback_hits;
+        self.crates_io_time_fallback_misses += other.crates_io_time_fallback_misses;
+        self.duplicate_dep_rows_merged += other.duplicate_dep_rows_merged;
+        self.propagation_duplicate_dep_rows_merged += other.propagation_duplicate_dep_rows_merged;
+        self.downstream_exclusions.add(other.downstream_exclusions);
+        self.timestamps.add(other.timestamps);
+        for (hop, xs) in other.propagation_opportunity_by_hop {
+            self.propagation_opportunity_by_hop
+                .entry(hop)
+                .or_default()
+                .extend(xs);
+        }
+        for (evidence, lags) in other.propagation_lags_by_evidence {
+            self.propagation_lags_by_evidence
+                .entry(evidence)
+                .or_default()
+                .extend(lags);
+        }
+        for (hop, lags) in other.propagation_lags_by_hop {
+            self.propagation_lags_by_hop
+                .entry(hop)
+                .or_default()
+                .extend(lags);
+        }
+        for (hop, lags) in other.propagation_cumulative_lags_by_hop {
+            self.propagation_cumulative_lags_by_hop
+                .entry(hop)
+                .or_default()
+                .extend(lags);
+        }
+        for (hop, c) in other.propagation_coverage_by_hop {
+            let total = self.propagation_coverage_by_hop.entry(hop).or_default();
+            total.affected += c.affected;
+            total.adopted += c.adopted;
+            total.censored += c.censored;
+            total.few_releases += c.few_releases;
+        }
+        self.propagation_reach.extend(other.propagation_reach);
+        self.upstream_exposure_days
+            .extend(other.upstream_exposure_days);
+        self.vulnerable_since_first_release += other.vulnerable_since_first_release;
+    }
+}
+
+struct PendingAdvisory {
+    totals: RunTotals,
+    strict_lags: PendingRows,
+    summary: PendingRows,
+    negative_lags: Option<PendingRows>,
+    owner_grouped: Option<PendingRows>,
+    collapsed: Option<PendingRows>,
+    propagation_events: Option<PendingRows>,
+    propagation_reach: Option<PendingRows>,
+    constraint_breakdown: Option<PendingRows>,
+    constraint_horizons: Option<PendingRows>,
+    constraint_edges: Option<PendingRows>,
+    adoption_curve: Option<PendingRows>,
+    lag_hist: Option<AdvisoryLagHist>,
+    group_crate: Option<(String, String)>,
+}
+
+// One advisory's first-adoption lags for --per-advisory-svgs, in --lag-unit.
+struct AdvisoryLagHist {
+    rustsec_id: String,
+    lags: Vec<f64>,
+    subtitle: String,
+}
+
+impl PendingAdvisory {
+    fn new(args: &Args, writers: &BatchWriters) -> Self {
+        Self {
+            totals: RunTotals::new(args),
+            strict_lags: PendingRows::default(),
+            summary: PendingRows::default(),
+            negative_lags: writers
+                .negative_lags
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            owner_grouped: writers
+                .owner_grouped
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            collapsed: writers.collapsed.as_ref().map(|_| PendingRows::default()),
+            propagation_events: writers
+                .keeps_propagation_events()
+                .then(PendingRows::default),
+            propagation_reach: writers
+                .propagation_reach
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            constraint_breakdown: writers
+                .constraint_breakdown
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            constraint_horizons: writers
+                .constraint_horizons
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            constraint_edges: writers
+                .constraint_edges
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            adoption_curve: writers
+                .adoption_curve
+                .as_ref()
+                .map(|_| PendingRows::default()),
+            lag_hist: None,
+            group_crate: None,
+        }
+    }
+}
+
+struct BatchWriters {
+    strict_lags: csv::Writer<OutputFile>,
+    summary: csv::Writer<OutputFile>,
+    negative_lags: Option<csv::Writer<OutputFile>>,
+    owner_grouped: Option<csv::Writer<OutputFile>>,
+    collapsed: Option<csv::Writer<OutputFile>>,
+    propagation_events: Option<csv::Writer<OutputFile>>,
+    propagation_reach: Option<csv::Writer<OutputFile>>,
+    constraint_breakdown: Option<csv::Writer<OutputFile>>,
+    constraint_horizons: Option<csv::Writer<OutputFile>>,
+    constraint_edges: Option<csv::Writer<OutputFile>>,
+    adoption_curve: Option<csv::Writer<OutputFile>>,
+    per_advisory_svgs: Option<PerAdvisorySvgs>,
+    #[cfg(feature = "parquet")]
+    parquet: Option<ParquetWriters>,
+}
+
+// --per-advisory-svgs: `lag_hist_{rustsec_id}.svg` for the first --per-advisory-svgs-max
+// advisories that qualify; later ones are only counted.
+struct PerAdvisorySvgs {
+    dir: PathBuf,
+    max: usize,
+    clip: Option<f64>,
+    x_label: &'static str,
+    style: ChartStyle,
+    data_csv: bool,
+    written: usize,
+    capped: usize,
+}
+
+impl PerAdvisorySvgs {
+    // Stale histograms from an earlier run are removed, like the propagation charts.
+    fn open(args: &Args, dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        remove_stale_charts(dir, &["lag_hist_"]);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max: args.per_advisory_svgs_max,
+            clip: args
+                .hist_clip_days
+                .map(|d| args.lag_unit.convert((d * 86_400.0) as i64)),
+            x_label: args.lag_unit.column(),
+            style: args.chart_style.clone(),
+            data_csv: args.chart_data_csv,
+            written: 0,
+            capped: 0,
+        })
+    }
+
+    fn write(&mut self, h: &AdvisoryLagHist) -> Result<()> {
+        if self.written >= self.max {
+            self.capped += 1;
+            return Ok(());
+        }
+        let x_max = self
+            .clip
+            .unwrap_or_else(|| h.lags.iter().copied().fold(1.0, f64::max));
+        HistogramChart::new(&h.lags)
+            .style(&self.style)
+            .data_csv(self.data_csv)
+            .x_max(x_max)
+            .overflow_bin(self.clip.is_some())
+            .title(format!(
+                "{} strict lag histogram (first adoptions, n={})",
+                h.rustsec_id,
+                h.lags.len()
+            ))
+            .subtitle(&h.subtitle)
+            .x_label(self.x_label)
+            .write_svg(self.dir.join(file_name_component(&format!(
+                "lag_hist_{}.svg",
+                h.rustsec_id
+            ))))?;
+        self.written += 1;
+        Ok(())
+    }
+}
+
+// Typed copies of the lag, summary and propagation event rows; fed from the same records
+// as the CSV writers.
+#[cfg(feature = "parquet")]
+struct ParquetWriters {
+    strict_lags: ParquetTable,
+    summary: ParquetTable,
+    propagation_events: Option<ParquetTable>,
+}
+
+impl BatchWriters {
+    fn open(args: &Args, collapse: bool) -> Result<Self> {
+        let comments = args.csv_schema_comments;
+        let lag_rows = CsvSchema::new(STRICT_LAG_SCHEMA_ID, &STRICT_LAG_SCHEMA);
+        let w = create_csv(&args.output, args.compress, &lag_rows, comments)?;
+        let sw = create_csv(
+            &args.summary_output,
+            Compression::None,
+            &CsvSchema::new(SUMMARY_SCHEMA_ID, &SUMMARY_SCHEMA),
+            comments,
         )?;
 
-        let out_dir = Path::new(&args.constraint_output_dir);
-        std::fs::create_dir_all(out_dir)?;
+        let propagation_reach_writer = if args.propagation {
+            Some(create_csv(
+                &args.propagation_reach_output,
+                Compression::None,
+                &CsvSchema::new(PROPAGATION_REACH_SCHEMA_ID, &PROPAGATION_REACH_SCHEMA),
+                comments,
+            )?)
+        } else {
+            None
+        };
+
+        let propagation_events_writer = match &args.propagation_events_output {
+            Some(path) => Some(create_csv(
+                path,
+                args.compress,
+                &CsvSchema::new(PROPAGATION_EVENT_SCHEMA_ID, &PROPAGATION_EVENT_SCHEMA),
+                comments,
+            )?),
+            None => None,
+        };
+
+        let constraint_breakdown_writer = if args.constraint {
+            let mut schema = CsvSchema::new(
+                CONSTRAINT_BREAKDOWN_SCHEMA_ID,
+                &CONSTRAINT_BREAKDOWN_KEY_SCHEMA,
+            );
+            for name in [
+                "downstream_crates_with_history",
+                "affected_edges",
+                "locked_out_edges",
+                "break_rate_percent",
+            ] {
+                schema.push(name, ColumnKind::Int64);
+            }
+            for shape in ReqShape::ALL {
+                if shape != ReqShape::Unparseable {
+                    schema.push(
+                        format!("affected_req_{}", shape.as_str()),
+                        ColumnKind::Int64,
+                    );
+                }
+            }
+            for name in [
+                "unknown_req_unparseable",
+                "whatif_resolved_locked_edges",
+                "whatif_unlocked_1_line",
+                "whatif_unlocked_2_lines",
+                "whatif_unlocked_all_lines",
+            ] {
+                schema.push(name, ColumnKind::Int64);
+            }
+            Some(create_csv(
+                &args.constraint_breakdown_output,
+                args.compress,
+                &schema,
+                comments,
+            )?)
+        } else {
+            None
+        };
+        let constraint_edges_writer = match args.constraint_edges_output.as_deref() {
+            Some(path) if args.constraint => Some(create_csv(
+                path,
+                Compression::None,
+                &CsvSchema::new(CONSTRAINT_EDGES_SCHEMA_ID, &CONSTRAINT_EDGES_SCHEMA),
+                comments,
+            )?),
+            _ => None,
+        };
+        let constraint_horizons_writer = if args.constraint && !args.constraint_horizons.is_empty()
+        {
+            let mut schema = CsvSchema::new(
+                CONSTRAINT_HORIZONS_SCHEMA_ID,
+                &CONSTRAINT_BREAKDOWN_KEY_SCHEMA,
+            );
+            for d in &args.constraint_horizons {
+                schema.push(format!("h{d}d_affected_edges"), ColumnKind::Int64);
+                schema.push(format!("h{d}d_locked_out_edges"), ColumnKind::Int64);
+                schema.push(format!("h{d}d_break_rate_percent"), ColumnKind::Int64);
+            }
+            Some(create_csv(
+                &args.constraint_horizons_output,
+                Compression::None,
+                &schema,
+                comments,
+            )?)
+        } else {
+            None
+        };
+        let adoption_curve_writer = match &args.adoption_curve_output {
+            Some(path) => {
+                let key: Vec<_> = ADOPTION_CURVE_KEY_COLUMNS
+                    .iter()
+                    .map(|name| (*name, ColumnKind::Utf8))
+                    .collect();
+                let mut schema = CsvSchema::new(ADOPTION_CURVE_SCHEMA_ID, &key);
+                schema.push("adopters", ColumnKind::Int64);
+                for d in &args.adoption_curve_days {
+                    schema.push(format!("adopted_by_{d}d"), ColumnKind::Float64);
+                }
+                Some(create_csv(path, Compression::None, &schema, comments)?)
+            }
+            None => None,
+        };
+        let negative_lags_writer = if args.include_negative_lags {
+            ensure_parent_dir(&args.negative_lags_output)?;
+            Some(create_csv(
+                &args.negative_lags_output,
+                Compression::None,
+                &lag_rows,
+                comments,
+            )?)
+        } else {
+            None
+        };
+
+        let owner_grouped_writer = if args.group_by_owner {
+            ensure_parent_dir(&args.owner_grouped_output)?;
+            let mut schema = CsvSchema {
+                id: OWNER_GROUPED_SCHEMA_ID,
+                ..lag_rows.clone()
+            };
+            schema.push("owner_group", ColumnKind::Utf8);
+            schema.push("owner_group_crates", ColumnKind::Int64);
+            Some(create_csv(
+                &args.owner_grouped_output,
+                Compression::None,
+                &schema,
+                comments,
+            )?)
+        } else {
+            None
+        };
+        let collapsed_writer = if collapse {
+            ensure_parent_dir(&args.collapsed_output)?;
+            let mut schema = CsvSchema {
+                id: COLLAPSED_SCHEMA_ID,
+                ..lag_rows.clone()
+            };
+            schema.push("collapse_group", ColumnKind::Utf8);
+            schema.push("collapse_group_crates", ColumnKind::Int64);
+            Some(create_csv(
+                &args.collapsed_output,
+                Compression::None,
+                &schema,
+                comments,
+            )?)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "parquet")]
+        let parquet = match args.parquet_output_dir.as_deref() {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                let batch = args.parquet_batch_size;
+                Some(ParquetWriters {
+                    strict_lags: ParquetTable::create(
+                        dir.join("lag_rows.parquet"),
+                        &STRICT_LAG_SCHEMA,
+                        batch,
+                    )?,
+                    summary: ParquetTable::create(
+                        dir.join("summary.parquet"),
+                        &SUMMARY_SCHEMA,
+                        batch,
+                    )?,
+                    propagation_events: if args.propagation {
+                        Some(ParquetTable::create(
+                            dir.join("propagation_events.parquet"),
+                            &PROPAGATION_EVENT_SCHEMA,
+                            batch,
+                        )?)
+                    } else {
+                        None
+                    },
+                })
+            }
+            None => None,
+        };
+
+        Ok(BatchWriters {
+            strict_lags: w,
+            summary: sw,
+            negative_lags: negative_lags_writer,
+            owner_grouped: owner_grouped_writer,
+            collapsed: collapsed_writer,
+            propagation_events: propagation_events_writer,
+            propagation_reach: propagation_reach_writer,
+            constraint_breakdown: constraint_breakdown_writer,
+            constraint_horizons: constraint_horizons_writer,
+            constraint_edges: constraint_edges_writer,
+            adoption_curve: adoption_curve_writer,
+            per_advisory_svgs: args
+                .per_advisory_svgs
+                .as_deref()
+                .map(|dir| PerAdvisorySvgs::open(args, dir))
+                .transpose()?,
+            #[cfg(feature = "parquet")]
+            parquet,
+        })
+    }
+
+    fn write_pending(&mut self, p: &PendingAdvisory) -> Result<()> {
+        let pairs = [
+            (Some(&mut self.strict_lags), Some(&p.strict_lags)),
+            (Some(&mut self.summary), Some(&p.summary)),
+            (self.negative_lags.as_mut(), p.negative_lags.as_ref()),
+            (self.owner_grouped.as_mut(), p.owner_grouped.as_ref()),
+            (self.collapsed.as_mut(), p.collapsed.as_ref()),
+            (
+                self.propagation_events.as_mut(),
+                p.propagation_events.as_ref(),
+            ),
+            (
+                self.propagation_reach.as_mut(),
+                p.propagation_reach.as_ref(),
+            ),
+            (
+                self.constraint_breakdown.as_mut(),
+                p.constraint_breakdown.as_ref(),
+            ),
+            (
+                self.constraint_horizons.as_mut(),
+                p.constraint_horizons.as_ref(),
+            ),
+            (self.constraint_edges.as_mut(), p.constraint_edges.as_ref()),
+            (self.adoption_curve.as_mut(), p.adoption_curve.as_ref()),
+        ];
+        for (w, rows) in pairs {
+            if let (Some(w), Some(rows)) = (w, rows) {
+                for record in &rows.0 {
+                    w.write_record(record)?;
+                }
+            }
+        }
+        if let (Some(svgs), Some(h)) = (self.per_advisory_svgs.as_mut(), p.lag_hist.as_ref()) {
+            svgs.write(h)?;
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(pq) = self.parquet.as_mut() {
+            let tables = [
+                (Some(&mut pq.strict_lags), Some(&p.strict_lags)),
+                (Some(&mut pq.summary), Some(&p.summary)),
+                (
+                    pq.propagation_events.as_mut(),
+                    p.propagation_events.as_ref(),
+                ),
+            ];
+            for (t, rows) in tables {
+                if let (Some(t), Some(rows)) = (t, rows) {
+                    for record in &rows.0 {
+                        t.write_record(record)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 
-        if !constraint_break_rate_per_adv_percent.is_empty() {
-            let x_max = 100i64;
-            let svg_path = out_dir.join("constraint_break_rate_hist_advisory.svg");
-            write_hist_svg(
-                &svg_path,
-                &constraint_break_rate_per_adv_percent,
-                args.constraint_bins,
-                x_max,
-                false,
-                &format!(
-                    "constraint break_rate histogram (per advisory, n={})",
-                    constraint_break_rate_per_adv_percent.len()
+    fn keeps_propagation_events(&self) -> bool {
+        #[cfg(feature = "parquet")]
+        if self
+            .parquet
+            .as_ref()
+            .is_some_and(|pq| pq.propagation_events.is_some())
+        {
+            return true;
+        }
+        self.propagation_events.is_some()
+    }
+
+    // Flushes everything and ends the compressed streams, which cannot be appended to
+    // afterwards.
+    fn finish(self) -> Result<()> {
+        #[cfg(feature = "parquet")]
+        if let Some(pq) = self.parquet {
+            pq.strict_lags.close()?;
+            pq.summary.close()?;
+            if let Some(t) = pq.propagation_events {
+                t.close()?;
+            }
+        }
+        for w in [
+            Some(self.strict_lags),
+            Some(self.summary),
+            self.negative_lags,
+            self.owner_grouped,
+            self.collapsed,
+            self.propagation_events,
+            self.propagation_reach,
+            self.constraint_breakdown,
+            self.constraint_horizons,
+            self.constraint_edges,
+            self.adoption_curve,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            w.into_inner()
+                .map_err(|e| anyhow!("flushing output: {}", e.error()))?
+                .finish()?;
+        }
+        Ok(())
+    }
+}
+
+// The run's store as an `AnalysisSource` for one advisory: reads go through the shared
+// caches and the --as-of snapshot, crates.io fills in names and version times the
+// snapshot lacks, and counters land in the advisory's pending totals.
+struct BatchSource<'s, 'a> {
+    ctx: &'s mut BatchContext<'a>,
+    adv: &'s Advisory,
+    totals: &'s mut RunTotals,
+    timings: &'s mut AdvisoryTimings,
+    crate_name: String,
+    group_crate: Option<(String, String)>,
+    version_times: HashMap<String, Vec<(String, DateTime<Utc>)>>,
+    // What `downstream` saw, for the summary row.
+    history_rows: i64,
+    excluded: DownstreamExclusions,
+    downstream_source: RowSource,
+}
+
+impl<'s, 'a> BatchSource<'s, 'a> {
+    fn new(
+        ctx: &'s mut BatchContext<'a>,
+        adv: &'s Advisory,
+        totals: &'s mut RunTotals,
+        timings: &'s mut AdvisoryTimings,
+    ) -> Self {
+        Self {
+            ctx,
+            adv,
+            totals,
+            timings,
+            crate_name: adv.package.clone(),
+            group_crate: None,
+            version_times: HashMap::new(),
+            history_rows: 0,
+            excluded: DownstreamExclusions::default(),
+            downstream_source: RowSource::Database,
+        }
+    }
+
+    fn record_skip(&mut self, reason: SkipReason, detail: String) -> Result<()> {
+        record_skip(
+            &mut self.ctx.logger,
+            &mut self.totals.skipped,
+            &mut self.totals.skipped_by_reason,
+            self.adv,
+            reason,
+            detail,
+        )
+    }
+
+    // Names only reach the store once `crate_exists` found them, so this is served from
+    // the id cache.
+    async fn crate_id(&self, crate_name: &str) -> Result<i64> {
+        self.ctx
+            .db
+            .query_crate_id(crate_name)
+            .await?
+            .ok_or_else(|| anyhow!("crate {crate_name} resolved but has no id"))
+    }
+
+    // Without a fixed version, propagation seeds hop 1 from the crate's latest release.
+    // None once the advisory has been recorded as skipped.
+    async fn latest_version_seed(&mut self, pkg: &str) -> Result<Option<Carrier>> {
+        let adv = self.adv;
+        let patched_sample = req_sample(&adv.patched);
+        let unaffected_sample = req_sample(&adv.unaffected);
+        let all_versions = self.version_numbers(pkg).await?;
+        let Some(latest_version) = all_versions
+            .iter()
+            .filter_map(|v| Version::parse(v).ok())
+            .max()
+        else {
+            self.record_skip(
+                SkipReason::NoFixedVersions,
+                format!(
+                    "fallback_latest_seed_failed: all_versions_count={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
+                    all_versions.len(),
+                    adv.patched.len(),
+                    adv.unaffected.len(),
+                    patched_sample,
+                    unaffected_sample
                 ),
-                &format!(
-                    "bins={}, x_max={}, y_scale=linear",
-                    args.constraint_bins, x_max
+            )?;
+            return Ok(None);
+        };
+        let Some((latest_time, _)) = lookup_version_time(self, pkg, &latest_version).await? else {
+            self.record_skip(
+                SkipReason::NoFixTimes,
+                format!(
+                    "fallback_latest_seed_failed: latest_version={} all_versions_count={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
+                    latest_version,
+                    all_versions.len(),
+                    adv.patched.len(),
+                    adv.unaffected.len(),
+                    patched_sample,
+                    unaffected_sample
                 ),
             )?;
+            return Ok(None);
+        };
+        self.totals.propagation_fallback_latest_seed += 1;
+        if let Some(x) = self.ctx.explain.as_mut() {
+            x.line(format!(
+                "no fixed version; propagation seeds from latest_version={latest_version} time={latest_time}"
+            ));
         }
+        self.ctx.logger.debug(format!(
+            "propagation fallback: rustsec_id={} cve_id={} pkg={} reason=no_patched_using_latest_version latest_version={} latest_time={} patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
+            adv.rustsec_id,
+            adv.cve_id,
+            pkg,
+            latest_version,
+            latest_time,
+            adv.patched.len(),
+            adv.unaffected.len(),
+            patched_sample,
+            unaffected_sample
+        ))?;
+        Ok(Some(Carrier {
+            crate_name: pkg.to_string(),
+            fix_version: latest_version,
+            fix_time: latest_time,
+            hop: 0,
+            path: vec![pkg.to_string()],
+            root_fix_time: latest_time,
+            root_lag_secs: 0,
+        }))
+    }
+}
 
-        let svg_path = out_dir.join("constraint_req_shape_bar.svg");
-        write_category_bar_svg(
-            &svg_path,
-            &[
-                ("exact-pin (=...)", constraint_totals.affected_req_exact_pin),
-                (
-                    "upper bound (<,<=)",
-                    constraint_totals.affected_req_has_upper_bound,
-                ),
-                ("caret 0.x (^0.)", constraint_totals.affected_req_caret_0x),
-                ("other", constraint_totals.affected_req_other),
-            ],
-            "affected edges dep_req shape",
-            &format!(
-                "affected_edges={}, locked_out_edges={}, break_rate_percent={}",
-                constraint_totals.affected_edges,
-                constraint_totals.locked_out_edges,
-                constraint_totals.break_rate_percent()
-            ),
-        )?;
+impl AnalysisSource for BatchSource<'_, '_> {
+    async fn crate_exists(&mut self, crate_name: &str) -> Result<bool> {
+        self.ctx.db.crate_exists(crate_name).await
     }
 
-    if args.propagation
-        && args.propagation_verify_samples > 0
-        && !propagation_verify_samples.is_empty()
-    {
-        for (up, down, ver, t, req) in &propagation_verify_samples {
-            let rows = db.query_all_downstream_details(up).await?;
-            let ok = rows.iter().any(|r| {
-                r.crate_name == *down
-                    && r.version == *ver
-                    && r.created_at == *t
-                    && r.dep_req == *req
-            });
-            if !ok {
-                return Err(anyhow!(
-                    "propagation edge verify failed: upstream={} downstream={} version={} time={} dep_req={}",
-                    up,
-                    down,
-                    ver,
-                    t,
-                    req
+    // A failed crates.io request leaves the name unresolved.
+    async fn canonical_name(&mut self, package: &str) -> Result<Option<String>> {
+        if let Some(name) = self.ctx.canonical_names.get(package) {
+            return Ok(name.clone());
+        }
+        let name = match crates_io_query_canonical_name(self.ctx.client, package).await {
+            Ok(name) => name,
+            Err(e) => {
+                self.ctx.logger.println(format!(
+                    "warning: crates.io name lookup for {package} failed, leaving it unresolved: {e:#}"
+                ))?;
+                None
+            }
+        };
+        self.ctx
+            .canonical_names
+            .insert(package.to_string(), name.clone());
+        Ok(name)
+    }
+
+    async fn version_numbers(&mut self, crate_name: &str) -> Result<Vec<String>> {
+        let ctx = &mut *self.ctx;
+        query_all_version_numbers_cached(
+            ctx.db,
+            &mut ctx.crate_versions_cache,
+            ctx.analysis.renames,
+            crate_name,
+            ctx.as_of_cutoff,
+            &mut ctx.as_of_excluded_versions,
+        )
+        .await
+    }
+
+    async fn version_times(&mut self, crate_name: &str) -> Result<Vec<(String, DateTime<Utc>)>> {
+        if let Some(times) = self.version_times.get(crate_name) {
+            return Ok(times.clone());
+        }
+        let crate_id = self.crate_id(crate_name).await?;
+        let times = published_version_times(
+            self.ctx.db,
+            self.ctx.analysis.renames,
+            crate_name,
+            crate_id,
+            self.ctx.as_of_cutoff,
+        )
+        .await?;
+        self.version_times
+            .insert(crate_name.to_string(), times.clone());
+        Ok(times)
+    }
+
+    async fn version_time(
+        &mut self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let crate_id = self.crate_id(crate_name).await?;
+        let cutoff = self.ctx.as_of_cutoff;
+        Ok(self
+            .ctx
+            .db
+            .query_version_time_by_id(crate_id, version)
+            .await?
+            .filter(|t| within_as_of(*t, cutoff)))
+    }
+
+    async fn remote_version_time(
+        &mut self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let ctx = &mut *self.ctx;
+        let fetched = ctx
+            .crates_io_times
+            .query(ctx.client, &mut ctx.logger, crate_name, version)
+            .await?
+            .time()?
+            .filter(|t| within_as_of(*t, ctx.as_of_cutoff));
+        if fetched.is_some() {
+            self.totals.crates_io_time_fallback_hits += 1;
+        } else {
+            self.totals.crates_io_time_fallback_misses += 1;
+        }
+        Ok(fetched)
+    }
+
+    async fn downstream(
+        &mut self,
+        crate_name: &str,
+        t0: DateTime<Utc>,
+        history_cutoff: Option<DateTime<Utc>>,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        let args = self.ctx.args;
+        let renames = self.ctx.analysis.renames;
+        let history_rows = self
+            .ctx
+            .cache
+            .count(self.ctx.db, renames, crate_name)
+            .await?;
+        enforce_memory_limit(self.ctx, crate_name).await?;
+        let ctx = &mut *self.ctx;
+        ctx.cache.load(ctx.db, renames, crate_name).await?;
+        if let Some(sources) = ctx.cache.merged_sources.get(crate_name) {
+            let merged_names = renames.chain(crate_name).join("|");
+            let sources = sources
+                .iter()
+                .map(|(name, n)| format!("{name}={n}"))
+                .collect::<Vec<_>>()
+                .join("|");
+            ctx.logger.debug(format!(
+                "crate renames: rustsec_id={} pkg={} merged_names={} downstream_rows={}",
+                self.adv.rustsec_id, crate_name, merged_names, sources
+            ))?;
+            if let Some(x) = ctx.explain.as_mut() {
+                x.section("crate renames");
+                x.line(format!(
+                    "merged_names={merged_names} downstream_rows_by_name={sources}"
                 ));
             }
         }
-        logger.println(format!(
-            "verified propagation edges: {} samples",
-            propagation_verify_samples.len()
-        ))?;
+        let downstream = &ctx.cache.map[crate_name];
+        self.timings.downstream_rows = downstream.len();
+        let synthesized_rows = downstream
+            .iter()
+            .filter(|r| r.source == RowSource::CratesIo)
+            .count();
+        self.downstream_source = if synthesized_rows > 0 {
+            ctx.logger.println(format!(
+                "warning: downstream history synthesized from crates.io: rustsec_id={} pkg={} rows={}",
+                self.adv.rustsec_id, crate_name, synthesized_rows
+            ))?;
+            RowSource::CratesIo
+        } else {
+            RowSource::Database
+        };
+        let mut excluded = DownstreamExclusions::default();
+        let downstream = active_downstream(
+            ctx.db,
+            args,
+            &mut ctx.crate_activity_cache,
+            downstream,
+            t0,
+            ctx.as_of_cutoff,
+            &mut excluded,
+        )
+        .await?;
+        if let Some(x) = ctx.explain.as_mut() {
+            x.section("downstream");
+            x.line(format!(
+                "history_rows={history_rows} kept_rows={} withdrawn_cutoff={} source={}",
+                downstream.len(),
+                history_cutoff.map_or("-".to_string(), |t| t.to_string()),
+                self.downstream_source.as_str()
+            ));
+            x.line(format!(
+                "t0={:?} fix_match_policy={:?} all_adoptions={} lag_mode={}",
+                args.t0,
+                args.fix_match_policy,
+                args.all_adoptions,
+                args.lag_mode.as_str()
+            ));
+        }
+        self.history_rows = history_rows;
+        self.excluded = excluded;
+        self.totals.downstream_exclusions.add(excluded);
+        for r in downstream.iter() {
+            let new_min = estimate_min_version(&r.dep_req);
+            if new_min != legacy_estimate_min_version(&r.dep_req) {
+                self.totals.min_version_changed_rows += 1;
+                if new_min == Some(Version::new(0, 0, 0)) {
+                    self.totals.min_version_upper_only_rows += 1;
+                }
+                self.totals
+                    .min_version_changed_reqs
+                    .insert(r.dep_req.clone());
+            }
+        }
+        Ok(downstream.into_owned())
+    }
+
+    // Versions whose database time disagrees with crates.io are always reported; their
+    // crates.io times replace the database's only with --prefer-crates-io-times.
+    async fn corrected_times(
+        &mut self,
+        rows: &[StrictLagRow],
+    ) -> Result<HashMap<(String, String), DateTime<Utc>>> {
+        let ctx = &mut *self.ctx;
+        let args = ctx.args;
+        let anomalies = check_timestamp_anomalies(
+            args,
+            ctx.client,
+            &mut ctx.crates_io_times,
+            &mut ctx.logger,
+            rows,
+            &mut self.totals.timestamps,
+        )
+        .await?;
+        if !anomalies.is_empty() {
+            ctx.logger.println(format!(
+                "warning: {} ({}): {} downstream version times disagree with crates.io by more than {}h{}",
+                self.adv.rustsec_id,
+                self.crate_name,
+                anomalies.len(),
+                args.timestamp_anomaly_hours,
+                if args.prefer_crates_io_times {
+                    "; using the crates.io times"
+                } else {
+                    ""
+                }
+            ))?;
+        }
+        if !args.prefer_crates_io_times {
+            return Ok(HashMap::new());
+        }
+        Ok(anomalies)
+    }
+
+    fn crate_resolved(
+        &mut self,
+        advisory: &Advisory,
+        crate_name: &str,
+    ) -> Result<Option<AdvisorySkip>> {
+        if crate_name != advisory.package {
+            self.ctx.logger.debug(format!(
+                "package alias: rustsec_pkg={} db_pkg={}",
+                advisory.package, crate_name
+            ))?;
+        }
+        // Grouped advisories are distinct entries, so they are only collapsed when CVE
+        // dedupe asks for it; otherwise `group_id` just tags their rows.
+        let group_crate = (advisory.group_id.clone(), crate_name.to_string());
+        if self.ctx.args.dedupe_by_cve != CveDedupe::KeepAll
+            && self.ctx.group_crates.contains(&group_crate)
+        {
+            return Ok(Some(AdvisorySkip {
+                reason: SkipReason::DuplicateInGroup,
+                detail: format!("group_id={} crate={}", advisory.group_id, crate_name),
+            }));
+        }
+        self.crate_name = crate_name.to_string();
+        self.group_crate = Some(group_crate);
+        Ok(None)
+    }
+
+    fn debug(&mut self, message: String) -> Result<()> {
+        self.ctx.logger.debug(message)
+    }
+
+    fn warning(&mut self, message: String) -> Result<()> {
+        self.ctx.logger.println(format!("warning: {message}"))
+    }
+
+    fn enter(&mut self, stage: Stage) {
+        self.timings.enter(stage);
+    }
+
+    fn explain(&mut self) -> Option<&mut ExplainSink> {
+        self.ctx.explain.as_mut()
+    }
+}
+
+// Everything one advisory contributes is collected in `pending`, so an error part-way
+// through leaves the shared writers and run totals untouched.
+async fn process_advisory(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    pending: &mut PendingAdvisory,
+    timings: &mut AdvisoryTimings,
+) -> Result<()> {
+    let args = ctx.args;
+    let analysis = ctx.analysis;
+    if let Some(x) = ctx.explain.as_mut() {
+        explain_advisory(x, adv);
+    }
+
+    let mut source = BatchSource::new(ctx, adv, &mut pending.totals, timings);
+    let result = analyze_advisory(&mut source, adv, &analysis).await?;
+    pending.group_crate = source.group_crate.take();
+    let pkg = result.crate_name.as_str();
+    match result.fixed_version_source {
+        Some(FixedVersionSource::PublishedRange) if !result.fixed_versions.is_empty() => {
+            source.totals.fixed_from_published_range += 1;
+        }
+        Some(FixedVersionSource::PartialReq) => source.totals.fixed_from_partial_req += 1,
+        _ => {}
+    }
+    let mut root_seed: Option<Carrier> = None;
+    if let Some(skip) = result.skip.clone() {
+        if skip.reason == SkipReason::NoFixedVersions && args.propagation {
+            root_seed = source.latest_version_seed(pkg).await?;
+        } else {
+            source.record_skip(skip.reason, skip.detail)?;
+        }
+        if root_seed.is_none() {
+            return Ok(());
+        }
+    }
+    let BatchSource {
+        history_rows,
+        excluded,
+        downstream_source,
+        ..
+    } = source;
+    let scan = ScanInfo {
+        history_rows,
+        excluded,
+        downstream_source,
+    };
+
+    if let (None, Some(summary_t0)) = (&result.skip, result.summary_t0) {
+        pending.totals.add_strict_scan(&result);
+        if let Some(w) = pending.negative_lags.as_mut() {
+            write_negative_lags(w, adv, &result)?;
+        }
+        if result.constraint.is_some() {
+            if args.constraint_min_age_days > 0
+                && (ctx.now - summary_t0).num_days() < args.constraint_min_age_days
+            {
+                return Ok(());
+            }
+            record_constraint(ctx, adv, &result, pending)?;
+        }
+        write_summaries(ctx, adv, &result, &scan, pending).await?;
+    }
+
+    if args.propagation {
+        timings.enter(Stage::Propagation);
+        propagate(ctx, adv, &result, root_seed, pending, timings).await?;
+    }
+
+    pending.totals.written_rows += write_strict_lags(
+        &mut pending.strict_lags,
+        adv,
+        &result,
+        args.lag_mode.row_modes(),
+    )?;
+    Ok(())
+}
+
+// What the downstream scan of an advisory saw, for its summary rows.
+struct ScanInfo {
+    history_rows: i64,
+    excluded: DownstreamExclusions,
+    downstream_source: RowSource,
+}
+
+// Adds the constraint breakdown to the run totals and writes its edge, breakdown and
+// horizon rows.
+fn record_constraint(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    pending: &mut PendingAdvisory,
+) -> Result<()> {
+    let Some(c) = &result.constraint else {
+        return Ok(());
+    };
+    let totals = &mut pending.totals;
+    totals.constraint_totals.add(c);
+    totals
+        .constraint_totals_by_severity
+        .entry(adv.severity.clone())
+        .or_default()
+        .add(c);
+    if let Some(w) = pending.constraint_edges.as_mut() {
+        write_constraint_edges(w, adv, &result.constraint_edges, &mut ctx.constraint_edges)?;
+    }
+    if let Some(w) = pending.constraint_breakdown.as_mut() {
+        write_constraint_breakdown(w, adv, result, ctx.args.constraint_whatif)?;
+    }
+    if c.affected_edges > 0 {
+        totals
+            .constraint_break_rate_per_adv_percent
+            .push(c.break_rate_percent as f64);
+        totals
+            .constraint_break_rate_by_severity
+            .entry(adv.severity.clone())
+            .or_default()
+            .push(c.break_rate_percent as f64);
+    }
+    if let Some(w) = pending.constraint_horizons.as_mut() {
+        for (total, h) in totals
+            .constraint_horizon_totals
+            .iter_mut()
+            .zip(&result.constraint_horizons)
+        {
+            total.affected_edges += h.affected_edges;
+            total.locked_out_edges += h.locked_out_edges;
+        }
+        write_constraint_horizons(w, adv, result)?;
+    }
+    Ok(())
+}
+
+// One summary row per --lag-mode. Run totals follow the first mode; a second mode only
+// adds its summary row.
+async fn write_summaries(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    scan: &ScanInfo,
+    pending: &mut PendingAdvisory,
+) -> Result<()> {
+    let args = ctx.args;
+    let Some(summary_t0) = result.summary_t0 else {
+        return Ok(());
+    };
+    let pkg = result.crate_name.as_str();
+    let advisory_time = adv.date.map(|d| d.and_time(NaiveTime::MIN).and_utc());
+    let (lag_t0, lag_t0_kind) = resolve_t0(args.t0, summary_t0, advisory_time);
+    for (mode_index, &mode) in args.lag_mode.row_modes().iter().enumerate() {
+        let (mode_rows, mode_affected_cnt) = result.mode_rows(mode);
+        let first_adoptions: Vec<&StrictLagRow> =
+            mode_rows.iter().filter(|r| r.adoption_index == 1).collect();
+        let stats = compute_lag_stats(
+            first_adoptions
+                .iter()
+                .map(|r| args.lag_unit.convert(r.lag_secs)),
+        );
+        let owner_groups = if args.group_by_owner {
+            group_rows_by_owner(
+                ctx.db,
+                &mut ctx.owner_group_cache,
+                first_adoptions.iter().copied(),
+            )
+            .await?
+        } else {
+            Vec::new()
+        };
+        let collapsed = match ctx.collapse_prefix.as_ref() {
+            Some(re) => collapse_earliest(
+                first_adoptions
+                    .iter()
+                    .map(|r| (prefix_group(re, &r.downstream_crate), *r)),
+            ),
+            None => Vec::new(),
+        };
+        for (w, groups) in [
+            (pending.owner_grouped.as_mut(), &owner_groups),
+            (pending.collapsed.as_mut(), &collapsed),
+        ] {
+            if let Some(w) = w {
+                write_grouped_lags(w, adv, pkg, groups)?;
+            }
+        }
+        let has_summary = stats.is_some() || result.downstream_crates > 0;
+        if mode_index == 0 {
+            let totals = &mut pending.totals;
+            totals.pre_disclosure_rows += first_adoptions.iter().filter(|r| r.lag_secs < 0).count();
+            if args.per_advisory_svgs.is_some()
+                && stats
+                    .as_ref()
+                    .is_some_and(|s| s.count >= args.per_advisory_svgs_min_rows)
+            {
+                pending.lag_hist = Some(AdvisoryLagHist {
+                    rustsec_id: adv.rustsec_id.clone(),
+                    lags: first_adoptions
+                        .iter()
+                        .map(|r| args.lag_unit.convert(r.lag_secs))
+                        .collect(),
+                    subtitle: format!(
+                        "{pkg} | severity {} | fixed {}",
+                        adv.severity,
+                        summary_t0.date_naive()
+                    ),
+                });
+            }
+            if args.group_by_owner {
+                totals.owner_grouped_input_rows += first_adoptions.len();
+                totals.owner_grouped_lags.extend(
+                    owner_groups
+                        .iter()
+                        .map(|(_, r, _)| args.lag_unit.convert(r.lag_secs)),
+                );
+            }
+            if ctx.collapse_prefix.is_some() {
+                totals.collapsed_input_rows += first_adoptions.len();
+                totals.collapsed_lags.extend(
+                    collapsed
+                        .iter()
+                        .map(|(_, r, _)| args.lag_unit.convert(r.lag_secs)),
+                );
+            }
+            add_first_adoption_lags(ctx, adv, result, &first_adoptions, totals).await?;
+            if has_summary {
+                add_summarized_advisory(
+                    args,
+                    adv,
+                    result,
+                    &first_adoptions,
+                    mode_affected_cnt,
+                    stats.is_some(),
+                    pending,
+                )?;
+            }
+        }
+        if has_summary {
+            pending.summary.write_record(summary_record(&SummaryRow {
+                advisory: adv,
+                result,
+                mode,
+                lag_unit: args.lag_unit,
+                lag_t0,
+                lag_t0_kind,
+                as_of: args.as_of,
+                fix_selection: args.fix_selection,
+                history_rows: scan.history_rows,
+                excluded_few_versions: scan.excluded.few_versions,
+                excluded_inactive: scan.excluded.inactive,
+                downstream_source: scan.downstream_source,
+                owner_groups: args.group_by_owner.then(|| {
+                    (
+                        owner_groups.len(),
+                        compute_lag_stats(
+                            owner_groups
+                                .iter()
+                                .map(|(_, r, _)| args.lag_unit.convert(r.lag_secs)),
+                        ),
+                    )
+                }),
+                collapsed: ctx
+                    .collapse_prefix
+                    .is_some()
+                    .then_some((first_adoptions.len(), collapsed.len())),
+            }))?;
+        }
+    }
+    Ok(())
+}
+
+// Splits the first adoptions' lags across the run's breakdowns.
+async fn add_first_adoption_lags(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    first_adoptions: &[&StrictLagRow],
+    totals: &mut RunTotals,
+) -> Result<()> {
+    let args = ctx.args;
+    for r in first_adoptions {
+        let lag = args.lag_unit.convert(r.lag_secs);
+        totals
+            .lags_by_severity
+            .entry(adv.severity.clone())
+            .or_default()
+            .push(lag);
+        totals
+            .lags_by_function_scoped
+            .entry(adv.function_scoped())
+            .or_default()
+            .push(lag);
+        totals
+            .lags_by_fix_compat
+            .entry(result.fix_semver_compatible)
+            .or_default()
+            .push(lag);
+        totals
+            .lags_by_evidence
+            .entry(r.evidence)
+            .or_default()
+            .push(lag);
+        *totals
+            .adoption_kind_totals
+            .entry(r.adoption_kind)
+            .or_default() += 1;
+        *totals.fix_delta_totals.entry(r.fix_delta()).or_default() += 1;
+        totals
+            .lags_by_cadence_bin
+            .entry(cadence_bin(r.cadence_days))
+            .or_default()
+            .push(lag);
+        totals.lag_over_cadence.extend(r.lag_over_cadence());
+        for category in advisory_categories(adv) {
+            totals
+                .lags_by_category
+                .entry(category.to_string())
+                .or_default()
+                .push(lag);
+        }
+    }
+    for r in first_adoptions {
+        let dependents = match ctx.dependent_counts_cache.get(&r.downstream_crate) {
+            Some(n) => *n,
+            None => {
+                let n = ctx.db.count_dependents(&r.downstream_crate).await?;
+                ctx.dependent_counts_cache
+                    .insert(r.downstream_crate.clone(), n);
+                n
+            }
+        };
+        totals
+            .lags_by_dependent_tier
+            .entry(dependent_tier(&args.dependent_tiers, dependents))
+            .or_default()
+            .push(args.lag_unit.convert(r.lag_secs));
     }
+    Ok(())
+}
 
-    logger.println(format!(
-        "processed advisories: {processed}, written rows: {written_rows}, skipped advisories: {skipped}"
-    ))?;
-    if propagation_fallback_latest_seed > 0 {
-        logger.println(format!(
-            "propagation fallback advisories (no patched using latest version): {}",
-            propagation_fallback_latest_seed
-        ))?;
+// Per-advisory totals of an advisory that gets a summary row, and its adoption curve.
+fn add_summarized_advisory(
+    args: &Args,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    first_adoptions: &[&StrictLagRow],
+    affected: usize,
+    adopted: bool,
+    pending: &mut PendingAdvisory,
+) -> Result<()> {
+    let totals = &mut pending.totals;
+    if !adopted {
+        totals.zero_adopter_advisories += 1;
     }
-    if crates_io_time_fallback_hits > 0 || crates_io_time_fallback_misses > 0 {
-        logger.println(format!(
-            "crates.io version-time fallback: hits={} misses={}",
-            crates_io_time_fallback_hits, crates_io_time_fallback_misses
-        ))?;
+    totals.downstream_affected_total += affected;
+    for category in advisory_categories(adv) {
+        *totals
+            .category_advisories
+            .entry(category.to_string())
+            .or_default() += 1;
     }
-    if skipped > 0 {
-        logger.println("skipped advisories breakdown:")?;
-        let order = [
-            SkipReason::Withdrawn,
-            SkipReason::NoFixedVersions,
-            SkipReason::NoFixTimes,
-            SkipReason::NoSummaryT0,
-            SkipReason::NoVulnVersions,
+    if let Some(w) = pending.adoption_curve.as_mut().filter(|_| adopted) {
+        let mut lag_days: Vec<f64> = first_adoptions
+            .iter()
+            .map(|r| fractional_days(r.lag_secs))
+            .collect();
+        lag_days.sort_unstable_by(f64::total_cmp);
+        let mut record = vec![
+            adv.rustsec_id.clone(),
+            adv.cve_id.clone(),
+            adv.ghsa_id().unwrap_or_default().to_string(),
+            adv.group_id.clone(),
+            adv.severity.clone(),
+            result.crate_name.clone(),
         ];
-        for reason in order {
-            if let Some(n) = skipped_by_reason.get(&reason) {
-                logger.println(format!("  {}: {}", reason.as_str(), n))?;
-            }
-        }
+        record.extend(adoption_curve_cells(&lag_days, &args.adoption_curve_days));
+        w.write_record(&record)?;
+        totals
+            .adoption_lag_days_by_severity
+            .entry(adv.severity.clone())
+            .or_default()
+            .extend(lag_days);
+    }
+    *totals
+        .function_scoped_by_severity
+        .entry((adv.severity.clone(), adv.function_scoped()))
+        .or_default() += 1;
+    if result.fix_semver_compatible {
+        totals.fix_semver_compatible_advisories += 1;
     }
     Ok(())
 }
 
-#[derive(Clone, Copy, Default)]
-struct ConstraintBreakdown {
-    downstream_crates_with_history: usize,
-    affected_edges: usize,
-    locked_out_edges: usize,
-    break_rate_percent: usize,
-    affected_req_exact_pin: usize,
-    affected_req_has_upper_bound: usize,
-    affected_req_caret_0x: usize,
-    affected_req_other: usize,
-    unknown_req_unparseable: usize,
+// One advisory's propagation walk: the carriers still to expand and what they reached.
+struct PropagationWalk {
+    best_seen: HashMap<String, (usize, DateTime<Utc>)>,
+    // Minimum hop of every crate reached; unlike best_seen this includes crates at the
+    // last hop, which are never expanded.
+    reach: HashMap<String, usize>,
+    queue: VecDeque<Carrier>,
+    events: usize,
+    selected: Option<EventSelector<Vec<String>>>,
 }
 
-#[derive(Default)]
-struct ConstraintTotals {
-    downstream_crates_with_history: usize,
-    affected_edges: usize,
-    locked_out_edges: usize,
-    affected_req_exact_pin: usize,
-    affected_req_has_upper_bound: usize,
-    affected_req_caret_0x: usize,
-    affected_req_other: usize,
-    unknown_req_unparseable: usize,
-}
+// Walks the advisory's fix through its dependents hop by hop, then writes the selected
+// events and the reach rows.
+async fn propagate(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    root_seed: Option<Carrier>,
+    pending: &mut PendingAdvisory,
+    timings: &mut AdvisoryTimings,
+) -> Result<()> {
+    let args = ctx.args;
+    let pkg = result.crate_name.as_str();
+    let totals = &mut pending.totals;
+    let mut walk = PropagationWalk {
+        best_seen: HashMap::new(),
+        reach: HashMap::new(),
+        queue: VecDeque::new(),
+        events: 0,
+        selected: pending
+            .propagation_events
+            .is_some()
+            .then(|| EventSelector::new(args.propagation_events_limit)),
+    };
+    seed_propagation(ctx, adv, result, root_seed, totals, &mut walk).await?;
+    expand_propagation(ctx, adv, pkg, totals, &mut walk, timings).await?;
+
+    let mut events_truncated_by_hop: BTreeMap<usize, usize> = BTreeMap::new();
+    if let Some(sel) = walk.selected.take() {
+        let (records, dropped) = sel.finish();
+        totals.propagation_events_truncated += dropped.values().sum::<usize>();
+        events_truncated_by_hop = dropped;
+        if let Some(w) = pending.propagation_events.as_mut() {
+            for record in records {
+                if args.propagation_events_total_limit != 0
+                    && ctx.propagation_events_written >= args.propagation_events_total_limit
+                {
+                    totals.propagation_events_over_total_limit += 1;
+                    continue;
+                }
+                w.write_record(record)?;
+                ctx.propagation_events_written += 1;
+            }
+        }
+    }
 
-impl ConstraintTotals {
-    fn add(&mut self, c: &ConstraintBreakdown) {
-        self.downstream_crates_with_history += c.downstream_crates_with_history;
-        self.affected_edges += c.affected_edges;
-        self.locked_out_edges += c.locked_out_edges;
-        self.affected_req_exact_pin += c.affected_req_exact_pin;
-        self.affected_req_has_upper_bound += c.affected_req_has_upper_bound;
-        self.affected_req_caret_0x += c.affected_req_caret_0x;
-        self.affected_req_other += c.affected_req_other;
-        self.unknown_req_unparseable += c.unknown_req_unparseable;
+    totals.propagation_cycle_edges += timings.propagation_cycle_edges;
+    totals.propagation_revisit_edges += timings.propagation_revisit_edges;
+    if timings.propagation_cycle_edges > 0 {
+        totals.propagation_cycle_advisories += 1;
     }
 
-    fn break_rate_percent(&self) -> usize {
-        if self.affected_edges == 0 {
-            return 0;
-        }
-        (self.locked_out_edges * 100) / self.affected_edges
+    let per_hop = reach_by_hop(ctx, pkg, walk.reach).await?;
+    if let Some(w) = pending.propagation_reach.as_mut() {
+        write_propagation_reach(w, adv, pkg, &per_hop, &events_truncated_by_hop)?;
     }
+    totals.propagation_reach.push(per_hop);
+    Ok(())
 }
 
-fn compute_constraint_breakdown(
-    fix_time: chrono::DateTime<chrono::Utc>,
-    vuln_versions: &[Version],
-    fixed_versions: &[Version],
-    downstream: &[DownstreamVersionInfo],
-) -> ConstraintBreakdown {
-    fn classify_req_shape(s: &str) -> ReqShape {
-        let t = s.trim();
-        if t.starts_with('=') {
-            return ReqShape::ExactPin;
-        }
-        if t.starts_with("^0.") {
-            return ReqShape::Caret0x;
-        }
-        if t.contains('<') {
-            return ReqShape::HasUpperBound;
-        }
-        ReqShape::Other
+// Hop-1 events as (upstream fix version, fix time, event) when seeding from fixed versions
+// directly instead of from the strict-lag rows.
+async fn hop1_seeds(
+    ctx: &mut BatchContext<'_>,
+    result: &AdvisoryResult,
+    root_seed: Option<Carrier>,
+    totals: &mut RunTotals,
+) -> Result<Option<Vec<(Version, DateTime<Utc>, AdoptionEvent)>>> {
+    let (args, db, cutoff) = (ctx.args, ctx.db, ctx.as_of_cutoff);
+    let pkg = result.crate_name.as_str();
+    if let Some(seed) = root_seed {
+        enforce_memory_limit(ctx, &seed.crate_name).await?;
+        let downstream = ctx
+            .cache
+            .get_or_fetch(db, ctx.analysis.renames, &seed.crate_name)
+            .await?;
+        let downstream = active_downstream(
+            db,
+            args,
+            &mut ctx.crate_activity_cache,
+            downstream,
+            seed.fix_time,
+            cutoff,
+            &mut totals.downstream_exclusions,
+        )
+        .await?;
+        let scan = compute_adoption_events_for_target(
+            &seed.fix_version,
+            seed.fix_time,
+            &downstream,
+            AdoptionFilters::from_args(args),
+        );
+        totals
+            .propagation_coverage_by_hop
+            .entry(1)
+            .or_default()
+            .add(&scan);
+        totals.propagation_duplicate_dep_rows_merged += scan.merged_duplicates;
+        return Ok(Some(
+            scan.events
+                .into_iter()
+                .map(|ev| (seed.fix_version.clone(), seed.fix_time, ev))
+                .collect(),
+        ));
     }
-
-    #[derive(Clone, Copy)]
-    enum ReqShape {
-        ExactPin,
-        HasUpperBound,
-        Caret0x,
-        Other,
+    if args.propagation_seed != PropagationSeed::AllFixed {
+        return Ok(None);
     }
-
-    let mut c = ConstraintBreakdown::default();
-
-    let mut current: Option<&str> = None;
-    let mut last_before: Option<&DownstreamVersionInfo> = None;
-
-    let process = |row: Option<&DownstreamVersionInfo>, c: &mut ConstraintBreakdown| {
-        let Some(row) = row else {
-            return;
-        };
-        c.downstream_crates_with_history += 1;
-
-        let Ok(req) = VersionReq::parse(&row.dep_req) else {
-            c.unknown_req_unparseable += 1;
-            return;
-        };
-
-        let affected = vuln_versions.iter().any(|v| req.matches(v));
-        if !affected {
-            return;
-        }
-        c.affected_edges += 1;
-
-        match classify_req_shape(&row.dep_req) {
-            ReqShape::ExactPin => c.affected_req_exact_pin += 1,
-            ReqShape::HasUpperBound => c.affected_req_has_upper_bound += 1,
-            ReqShape::Caret0x => c.affected_req_caret_0x += 1,
-            ReqShape::Other => c.affected_req_other += 1,
+    enforce_memory_limit(ctx, pkg).await?;
+    let downstream = ctx
+        .cache
+        .get_or_fetch(db, ctx.analysis.renames, pkg)
+        .await?;
+    // fix_times iterates in version order, so ties keep the lowest fixed version.
+    let mut earliest: BTreeMap<String, (Version, DateTime<Utc>, AdoptionEvent)> = BTreeMap::new();
+    for (fv, ft) in &result.fix_times {
+        totals.propagation_seeds_used += 1;
+        let active = active_downstream(
+            db,
+            args,
+            &mut ctx.crate_activity_cache,
+            downstream,
+            *ft,
+            cutoff,
+            &mut totals.downstream_exclusions,
+        )
+        .await?;
+        let scan =
+            compute_adoption_events_for_target(fv, *ft, &active, AdoptionFilters::from_args(args));
+        totals.propagation_duplicate_dep_rows_merged += scan.merged_duplicates;
+        for ev in scan.events {
+            match earliest.get(&ev.downstream_crate) {
+                None => {
+                    earliest.insert(ev.downstream_crate.clone(), (fv.clone(), *ft, ev));
+                }
+                Some((_, _, kept)) => {
+                    totals.propagation_seed_duplicates += 1;
+                    if ev.downstream_time < kept.downstream_time {
+                        earliest.insert(ev.downstream_crate.clone(), (fv.clone(), *ft, ev));
+                    }
+                }
+            }
         }
+    }
+    let coverage = totals.propagation_coverage_by_hop.entry(1).or_default();
+    coverage.affected += result.affected;
+    coverage.adopted += earliest.len();
+    Ok(Some(earliest.into_values().collect()))
+}
 
-        let compatible = fixed_versions.iter().any(|v| req.matches(v));
-        if !compatible {
-            c.locked_out_edges += 1;
-        }
+// Hop 1 of the walk, from --propagation-seed or the strict-lag rows' first adoptions.
+async fn seed_propagation(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    root_seed: Option<Carrier>,
+    totals: &mut RunTotals,
+    walk: &mut PropagationWalk,
+) -> Result<()> {
+    let args = ctx.args;
+    let pkg = result.crate_name.as_str();
+    let can_expand = match args.propagation_max_hops {
+        None => true,
+        Some(max_hops) => 1 < max_hops,
     };
-
-    for row in downstream {
-        match current {
-            None => {
-                current = Some(row.crate_name.as_str());
-                if row.created_at < fix_time {
-                    last_before = Some(row);
-                }
-            }
-            Some(name) if name == row.crate_name.as_str() => {
-                if row.created_at < fix_time {
-                    last_before = Some(row);
-                }
+    if let Some(seeded) = hop1_seeds(ctx, result, root_seed, totals).await? {
+        for (fix_version, fix_time, ev) in seeded {
+            let recomputed = (ev.downstream_time - fix_time).num_seconds();
+            if recomputed != ev.lag_secs {
+                return Err(anyhow!(
+                    "lag mismatch hop=1: {} {} -> {} {} csv_secs={} recomputed_secs={}",
+                    adv.package,
+                    fix_time,
+                    ev.downstream_crate,
+                    ev.downstream_time,
+                    ev.lag_secs,
+                    recomputed
+                ));
             }
-            Some(_) => {
-                process(last_before.take(), &mut c);
-                current = Some(row.crate_name.as_str());
-                if row.created_at < fix_time {
-                    last_before = Some(row);
-                }
+            note_propagation_event(
+                ctx,
+                adv,
+                totals,
+                walk,
+                &PropagationEvent {
+                    root_crate: pkg,
+                    hop: 1,
+                    upstream_crate: pkg,
+                    upstream_fix_version: fix_version.to_string(),
+                    upstream_fix_time: fix_time,
+                    downstream_crate: &ev.downstream_crate,
+                    downstream_version: ev.downstream_version.to_string(),
+                    downstream_time: ev.downstream_time,
+                    dep_req: &ev.dep_req,
+                    lag_secs: ev.lag_secs,
+                    cumulative_secs: ev.lag_secs,
+                    evidence: ev.evidence,
+                    opportunity: ev.opportunity,
+                },
+            );
+            if can_expand {
+                let key = ev.downstream_crate.clone();
+                walk.best_seen.insert(key.clone(), (1, ev.downstream_time));
+                walk.queue.push_back(Carrier {
+                    path: vec![pkg.to_string(), key.clone()],
+                    crate_name: key,
+                    fix_version: ev.downstream_version,
+                    fix_time: ev.downstream_time,
+                    hop: 1,
+                    root_fix_time: fix_time,
+                    root_lag_secs: ev.lag_secs,
+                });
             }
         }
+        return Ok(());
     }
-    process(last_before.take(), &mut c);
 
-    if c.affected_edges > 0 {
-        c.break_rate_percent = (c.locked_out_edges * 100) / c.affected_edges;
+    let first_adoptions = || result.rows.iter().filter(|r| r.adoption_index == 1);
+    let coverage = totals.propagation_coverage_by_hop.entry(1).or_default();
+    coverage.affected += result.affected;
+    coverage.adopted += first_adoptions().count();
+    for r in first_adoptions() {
+        let recomputed = (r.downstream_time - r.t0).num_seconds();
+        if recomputed != r.lag_secs {
+            return Err(anyhow!(
+                "lag mismatch hop=1: {} {} -> {} {} csv_secs={} recomputed_secs={}",
+                adv.package,
+                r.t0,
+                r.downstream_crate,
+                r.downstream_time,
+                r.lag_secs,
+                recomputed
+            ));
+        }
+        let lag_secs = (r.downstream_time - r.matched_fix_time).num_seconds();
+        note_propagation_event(
+            ctx,
+            adv,
+            totals,
+            walk,
+            &PropagationEvent {
+                root_crate: pkg,
+                hop: 1,
+                upstream_crate: pkg,
+                upstream_fix_version: r.matched_fix_version.clone(),
+                upstream_fix_time: r.matched_fix_time,
+                downstream_crate: &r.downstream_crate,
+                downstream_version: r.downstream_version.clone(),
+                downstream_time: r.downstream_time,
+                dep_req: &r.fixed_req,
+                lag_secs,
+                cumulative_secs: lag_secs,
+                evidence: r.evidence,
+                opportunity: r.first_opportunity,
+            },
+        );
+        if can_expand && let Ok(v) = Version::parse(&r.downstream_version) {
+            let key = r.downstream_crate.clone();
+            walk.best_seen.insert(key.clone(), (1, r.downstream_time));
+            walk.queue.push_back(Carrier {
+                path: vec![pkg.to_string(), key.clone()],
+                crate_name: key,
+                fix_version: v,
+                fix_time: r.downstream_time,
+                hop: 1,
+                root_fix_time: r.matched_fix_time,
+                root_lag_secs: lag_secs,
+            });
+        }
     }
-    c
+    Ok(())
 }
 
-fn write_category_bar_svg(
-    path: &Path,
-    categories: &[(&str, usize)],
-    title: &str,
-    subtitle: &str,
+// Hops 2 and up: expands the queued carriers breadth-first.
+async fn expand_propagation(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    pkg: &str,
+    totals: &mut RunTotals,
+    walk: &mut PropagationWalk,
+    timings: &mut AdvisoryTimings,
 ) -> Result<()> {
-    let w = 960.0;
-    let h = 520.0;
-    let margin = 70.0;
-    let plot_w = w - margin * 2.0;
-    let plot_h = h - margin * 2.0;
-
-    let axis = "#222222";
-    let grid = "#E6E6E6";
-    let fill = "#4C78A8";
-    let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
-
-    let max_v = categories.iter().map(|(_, v)| *v).max().unwrap_or(1).max(1) as f64;
-    let bar_w = plot_w / categories.len().max(1) as f64;
-
-    let x0 = margin;
-    let y0 = margin;
-    let x1 = w - margin;
-    let y1 = h - margin;
-
-    let y_ticks = nice_ticks(max_v, 6);
-
-    let mut parts = Vec::new();
-    parts.push(format!(
-        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
-        w_i = w as i64,
-        h_i = h as i64
-    ));
-    parts.push(format!(
-        r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
-        w_i = w as i64,
-        h_i = h as i64
-    ));
+    let (args, db, cutoff) = (ctx.args, ctx.db, ctx.as_of_cutoff);
+    let mut last_adv_progress = Instant::now();
+    while let Some(carrier) = walk.queue.pop_front() {
+        timings.propagation_carriers += 1;
+        timings.propagation_max_queue = timings.propagation_max_queue.max(walk.queue.len() + 1);
+        if let Some(cap) = args.propagation_max_queue
+            && walk.queue.len() + 1 > cap
+        {
+            ctx.logger.println(format!(
+                "warning: propagation queue for {} ({}) exceeded --propagation-max-queue={} at hop={} (queued={} seen={}); expansion stopped",
+                adv.rustsec_id,
+                pkg,
+                cap,
+                carrier.hop,
+                walk.queue.len() + 1,
+                walk.best_seen.len()
+            ))?;
+            timings.propagation_queue_capped = true;
+            totals.propagation_queue_capped.push(adv.rustsec_id.clone());
+            break;
+        }
+        if let Some(max_hops) = args.propagation_max_hops
+            && carrier.hop >= max_hops
+        {
+            continue;
+        }
 
-    for t in y_ticks {
-        let y = y1 - (t / max_v) * plot_h;
-        parts.push(format!(
-            r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
-        ));
-        parts.push(format!(
-            r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="12" fill="{axis}">{label}</text>"#,
-            x = x0 - 10.0,
-            ytext = y + 4.0,
-            label = svg_escape(&format!("{t:.0}"))
-        ));
-    }
+        let next_hop = carrier.hop + 1;
+        if let Some(max_hops) = args.propagation_max_hops
+            && next_hop > max_hops
+        {
+            continue;
+        }
 
-    parts.push(format!(
-        r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
-    ));
-    parts.push(format!(
-        r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
-    ));
+        if let Some(bar) = ctx.logger.progress() {
+            bar.set_message(format!(
+                "{} propagation hop={} queue={} {}",
+                adv.rustsec_id,
+                next_hop,
+                walk.queue.len(),
+                carrier.crate_name
+            ));
+        }
+        if last_adv_progress.elapsed() >= Duration::from_secs(5) {
+            ctx.logger.status(format!(
+                "propagation: adv={}/{} pkg={} queue={} seen={} events={} elapsed={:.1}s",
+                ctx.processed,
+                ctx.total_advisories,
+                pkg,
+                walk.queue.len(),
+                walk.best_seen.len(),
+                walk.events,
+                ctx.start.elapsed().as_secs_f64()
+            ))?;
+            last_adv_progress = Instant::now();
+        }
 
-    for (i, (name, v)) in categories.iter().enumerate() {
-        let v = *v as f64;
-        let bh = (v / max_v) * plot_h;
-        let x = x0 + i as f64 * bar_w;
-        let y = y1 - bh;
-        parts.push(format!(
-            r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
-            bw = (bar_w - 8.0).max(0.0)
-        ));
-        parts.push(format!(
-            r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="12" fill="{axis}">{label}</text>"#,
-            x = x + bar_w / 2.0 - 4.0,
-            y = y1 + 22.0,
-            label = svg_escape(name)
-        ));
-    }
+        enforce_memory_limit(ctx, &carrier.crate_name).await?;
+        let downstream = ctx
+            .cache
+            .get_or_fetch(db, ctx.analysis.renames, &carrier.crate_name)
+            .await?;
+        let downstream = active_downstream(
+            db,
+            args,
+            &mut ctx.crate_activity_cache,
+            downstream,
+            carrier.fix_time,
+            cutoff,
+            &mut totals.downstream_exclusions,
+        )
+        .await?;
+        let scan = compute_adoption_events_for_target(
+            &carrier.fix_version,
+            carrier.fix_time,
+            &downstream,
+            AdoptionFilters::from_args(args),
+        );
+        totals
+            .propagation_coverage_by_hop
+            .entry(next_hop)
+            .or_default()
+            .add(&scan);
+        totals.propagation_duplicate_dep_rows_merged += scan.merged_duplicates;
+        for ev in scan.events {
+            let recomputed = (ev.downstream_time - carrier.fix_time).num_seconds();
+            if recomputed != ev.lag_secs {
+                return Err(anyhow!(
+                    "lag mismatch hop={}: {} {} -> {} {} csv_secs={} recomputed_secs={}",
+                    next_hop,
+                    carrier.crate_name,
+                    carrier.fix_time,
+                    ev.downstream_crate,
+                    ev.downstream_time,
+                    ev.lag_secs,
+                    recomputed
+                ));
+            }
+            // The hop lags summed along the path must land on the root fix.
+            let cumulative_secs = carrier.root_lag_secs + ev.lag_secs;
+            let since_root = (ev.downstream_time - carrier.root_fix_time).num_seconds();
+            if cumulative_secs != since_root {
+                return Err(anyhow!(
+                    "cumulative lag mismatch hop={}: {} {} -> {} {} path_secs={} recomputed_secs={}",
+                    next_hop,
+                    pkg,
+                    carrier.root_fix_time,
+                    ev.downstream_crate,
+                    ev.downstream_time,
+                    cumulative_secs,
+                    since_root
+                ));
+            }
 
-    parts.push(format!(
-        r#"<text x="{x:.2}" y="28" text-anchor="middle" font-family="{font}" font-size="18" fill="{axis}">{t}</text>"#,
-        x = w / 2.0,
-        t = svg_escape(title)
-    ));
-    parts.push(format!(
-        r#"<text x="{x:.2}" y="48" text-anchor="middle" font-family="{font}" font-size="12" fill="{axis}">{t}</text>"#,
-        x = w / 2.0,
-        t = svg_escape(subtitle)
-    ));
-    parts.push(format!(
-        r#"<text x="18" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="14" fill="{axis}" transform="rotate(-90 18 {y:.2})">count</text>"#,
-        y = h / 2.0
-    ));
-    parts.push("</svg>\n".to_string());
+            note_propagation_event(
+                ctx,
+                adv,
+                totals,
+                walk,
+                &PropagationEvent {
+                    root_crate: pkg,
+                    hop: next_hop,
+                    upstream_crate: &carrier.crate_name,
+                    upstream_fix_version: carrier.fix_version.to_string(),
+                    upstream_fix_time: carrier.fix_time,
+                    downstream_crate: &ev.downstream_crate,
+                    downstream_version: ev.downstream_version.to_string(),
+                    downstream_time: ev.downstream_time,
+                    dep_req: &ev.dep_req,
+                    lag_secs: ev.lag_secs,
+                    cumulative_secs,
+                    evidence: ev.evidence,
+                    opportunity: ev.opportunity,
+                },
+            );
+            let can_expand = match args.propagation_max_hops {
+                None => true,
+                Some(max_hops) => next_hop < max_hops,
+            };
+            if !can_expand {
+                continue;
+            }
+            if carrier.path.contains(&ev.downstream_crate) {
+                timings.propagation_cycle_edges += 1;
+                continue;
+            }
 
-    std::fs::write(path, parts.join("\n"))?;
+            let should_push = match walk.best_seen.get(&ev.downstream_crate) {
+                None => true,
+                Some((seen_hop, seen_time)) => {
+                    if *seen_hop < next_hop {
+                        timings.propagation_revisit_edges += 1;
+                    }
+                    next_hop < *seen_hop
+                        || (next_hop == *seen_hop && ev.downstream_time < *seen_time)
+                }
+            };
+            if should_push {
+                let key = ev.downstream_crate.clone();
+                walk.best_seen
+                    .insert(key.clone(), (next_hop, ev.downstream_time));
+                let mut path = carrier.path.clone();
+                path.push(key.clone());
+                walk.queue.push_back(Carrier {
+                    crate_name: key,
+                    fix_version: ev.downstream_version,
+                    fix_time: ev.downstream_time,
+                    hop: next_hop,
+                    path,
+                    root_fix_time: carrier.root_fix_time,
+                    root_lag_secs: cumulative_secs,
+                });
+            }
+        }
+    }
     Ok(())
 }
 
-fn normalize_crate_name(name: &str) -> &str {
-    match name {
-        "rustdecimal" | "rust_demical" => "rust_decimal",
-        _ => name,
+// Counts one adoption on the walk: its lags go to the run totals, its row to the event
+// selection, and it is offered to the verify sampler and noted as reached.
+fn note_propagation_event(
+    ctx: &mut BatchContext<'_>,
+    adv: &Advisory,
+    totals: &mut RunTotals,
+    walk: &mut PropagationWalk,
+    ev: &PropagationEvent<'_>,
+) {
+    walk.events += 1;
+    let basis_lag =
+        ctx.args
+            .propagation_lag_basis
+            .lag_secs(ev.lag_secs, ev.downstream_time, ev.opportunity);
+    totals
+        .propagation_lags_by_hop
+        .entry(ev.hop)
+        .or_default()
+        .push(basis_lag);
+    totals
+        .propagation_cumulative_lags_by_hop
+        .entry(ev.hop)
+        .or_default()
+        .push(ev.cumulative_secs);
+    totals
+        .propagation_lags_by_evidence
+        .entry(ev.evidence)
+        .or_default()
+        .push(basis_lag);
+    totals.note_opportunity(ev.hop, ev.opportunity, ev.downstream_time);
+    if let Some(sel) = walk.selected.as_mut() {
+        sel.push(
+            ev.hop,
+            ev.downstream_time,
+            propagation_event_record(adv, ev),
+        );
     }
+    ctx.propagation_verifier.offer(ev.hop, || VerifySample {
+        rustsec_id: adv.rustsec_id.clone(),
+        cve_id: adv.cve_id.clone(),
+        root_crate: ev.root_crate.to_string(),
+        hop: ev.hop,
+        upstream_crate: ev.upstream_crate.to_string(),
+        upstream_fix_version: ev.upstream_fix_version.clone(),
+        upstream_fix_time: ev.upstream_fix_time,
+        downstream_crate: ev.downstream_crate.to_string(),
+        downstream_version: ev.downstream_version.clone(),
+        downstream_time: ev.downstream_time,
+        dep_req: ev.dep_req.to_string(),
+    });
+    note_reach(&mut walk.reach, ev.downstream_crate, ev.hop);
 }
 
-#[derive(serde::Deserialize)]
-struct CratesIoVersionResponse {
-    version: CratesIoVersion,
-}
-
-#[derive(serde::Deserialize)]
-struct CratesIoVersion {
-    created_at: String,
-}
-
-async fn crates_io_query_version_time(
-    client: &Client,
-    cache: &mut HashMap<(String, String), Option<DateTime<Utc>>>,
-    crate_name: &str,
-    version: &str,
-) -> Result<Option<DateTime<Utc>>> {
-    let key = (crate_name.to_string(), version.to_string());
-    if let Some(v) = cache.get(&key) {
-        return Ok(*v);
+// (crates, downloads) first reached at each hop, the advisory's own crate aside.
+async fn reach_by_hop(
+    ctx: &mut BatchContext<'_>,
+    pkg: &str,
+    mut reach: HashMap<String, usize>,
+) -> Result<Vec<(usize, i64)>> {
+    reach.remove(pkg);
+    let mut missing: Vec<String> = reach
+        .keys()
+        .filter(|c| !ctx.crate_downloads_cache.contains_key(*c))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        missing.sort();
+        let found = ctx.db.query_crate_downloads(&missing).await?;
+        for name in missing {
+            let n = found.get(&name).copied().unwrap_or(0);
+            ctx.crate_downloads_cache.insert(name, n);
+        }
     }
-
-    let url = format!("https://crates.io/api/v1/crates/{}/{}", crate_name, version);
-    let resp = client.get(url).send().await?;
-    if !resp.status().is_success() {
-        cache.insert(key, None);
-        return Ok(None);
+    let max_reach_hop = reach.values().copied().max().unwrap_or(0);
+    let mut per_hop = vec![(0usize, 0i64); max_reach_hop];
+    for (name, hop) in &reach {
+        per_hop[hop - 1].0 += 1;
+        per_hop[hop - 1].1 += ctx.crate_downloads_cache[name];
     }
-
-    let body: CratesIoVersionResponse = resp.json().await?;
-    let parsed = chrono::DateTime::parse_from_rfc3339(&body.version.created_at)
-        .map(|dt| dt.with_timezone(&Utc))
-        .ok();
-    cache.insert(key, parsed);
-    Ok(parsed)
+    Ok(per_hop)
 }
 
-async fn query_all_version_numbers_cached(
-    db: &Database,
-    cache: &mut HashMap<String, Vec<String>>,
-    crate_name: &str,
-) -> Result<Vec<String>> {
-    if let Some(v) = cache.get(crate_name) {
-        return Ok(v.clone());
+// A crate reached through several carriers or fixed versions counts once, at its
+// smallest hop.
+fn note_reach(reach: &mut HashMap<String, usize>, crate_name: &str, hop: usize) {
+    match reach.get_mut(crate_name) {
+        Some(h) => *h = (*h).min(hop),
+        None => {
+            reach.insert(crate_name.to_string(), hop);
+        }
     }
-    let rows = db.query_all_version_numbers(crate_name).await?;
-    cache.insert(crate_name.to_string(), rows.clone());
-    Ok(rows)
 }
 
-fn resolve_equivalent_version_string(all_versions: &[String], wanted: &Version) -> Option<String> {
-    let mut best: Option<String> = None;
-    for s in all_versions {
-        if let Ok(v) = Version::parse(s)
-            && v.major == wanted.major
-            && v.minor == wanted.minor
-            && v.patch == wanted.patch
-            && v.pre == wanted.pre
-        {
-            match &best {
-                None => best = Some(s.clone()),
-                Some(b) => {
-                    if s > b {
-                        best = Some(s.clone());
-                    }
-                }
-            }
-        }
+fn write_advisory_list(
+    path: &Path,
+    advisories: &[Advisory],
+    policy: PrereleasePolicy,
+) -> Result<()> {
+    let mut w = csv::Writer::from_writer(std::fs::File::create(path)?);
+    w.write_record([
+        "rustsec_id",
+        "cve_id",
+        "severity",
+        "cvss",
+        "package",
+        "withdrawn",
+        "patched",
+        "unaffected",
+        "fixed_versions",
+        "fixed_version_source",
+    ])?;
+    for adv in advisories {
+        let (fixed, source) = static_fixed_versions(&adv.patched, policy);
+        w.write_record([
+            adv.rustsec_id.clone(),
+            adv.cve_id.clone(),
+            adv.severity.clone(),
+            adv.cvss.clone().unwrap_or_default(),
+            adv.package.clone(),
+            adv.withdrawn.to_string(),
+            adv.patched.join("|"),
+            adv.unaffected.join("|"),
+            fixed
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join("|"),
+            source.as_str().to_string(),
+        ])?;
     }
-    best
+    w.flush()?;
+    Ok(())
 }
 
-fn parse_published_versions(all_versions: &[String]) -> Vec<(Version, String)> {
-    let mut out: Vec<(Version, String)> = all_versions
+// Schema ids of the outputs whose columns live in this binary; the row tables shared
+// with other tools are in `stats`.
+const PROPAGATION_REACH_SCHEMA_ID: &str = "propagation_reach.v1";
+const CONSTRAINT_BREAKDOWN_SCHEMA_ID: &str = "constraint_breakdown.v1";
+const CONSTRAINT_EDGES_SCHEMA_ID: &str = "constraint_edges.v1";
+const CONSTRAINT_HORIZONS_SCHEMA_ID: &str = "constraint_horizons.v1";
+const ADOPTION_CURVE_SCHEMA_ID: &str = "adoption_curve.v1";
+const OWNER_GROUPED_SCHEMA_ID: &str = "owner_grouped_lag_rows.v2";
+const COLLAPSED_SCHEMA_ID: &str = "collapsed_lag_rows.v2";
+
+const PROPAGATION_REACH_SCHEMA: [(&str, ColumnKind); 9] = [
+    ("rustsec_id", ColumnKind::Utf8),
+    ("cve_id", ColumnKind::Utf8),
+    ("target_crate", ColumnKind::Utf8),
+    ("hop", ColumnKind::Int64),
+    ("reached_crates", ColumnKind::Int64),
+    ("reached_downloads", ColumnKind::Int64),
+    ("cumulative_crates", ColumnKind::Int64),
+    ("cumulative_downloads", ColumnKind::Int64),
+    ("events_truncated", ColumnKind::Int64),
+];
+
+// Leading columns of the constraint breakdown and horizons outputs.
+const CONSTRAINT_BREAKDOWN_KEY_SCHEMA: [(&str, ColumnKind); 9] = [
+    ("rustsec_id", ColumnKind::Utf8),
+    ("cve_id", ColumnKind::Utf8),
+    ("ghsa_id", ColumnKind::Utf8),
+    ("aliases", ColumnKind::Utf8),
+    ("cve_is_fallback", ColumnKind::Bool),
+    ("group_id", ColumnKind::Utf8),
+    ("severity", ColumnKind::Utf8),
+    ("target_crate", ColumnKind::Utf8),
+    ("fix_time", ColumnKind::Timestamp),
+];
+
+const CONSTRAINT_EDGES_SCHEMA: [(&str, ColumnKind); 7] = [
+    ("rustsec_id", ColumnKind::Utf8),
+    ("downstream_crate", ColumnKind::Utf8),
+    ("downstream_version", ColumnKind::Utf8),
+    ("dep_req", ColumnKind::Utf8),
+    ("req_shape", ColumnKind::Utf8),
+    ("locked_out", ColumnKind::Bool),
+    ("smallest_satisfying_fix", ColumnKind::Utf8),
+];
+
+const ADOPTION_CURVE_KEY_COLUMNS: [&str; 6] = [
+    "rustsec_id",
+    "cve_id",
+    "ghsa_id",
+    "group_id",
+    "severity",
+    "target_crate",
+];
+
+// Sorted first-adoption lags for every severity (by rank), then `all` over everything.
+fn adoption_curve_groups(by_severity: &HashMap<String, Vec<f64>>) -> Vec<(String, Vec<f64>)> {
+    let mut groups: Vec<(String, Vec<f64>)> = by_severity
         .iter()
-        .filter_map(|s| Version::parse(s).ok().map(|v| (v, s.clone())))
+        .map(|(sev, lags)| (sev.clone(), lags.clone()))
         .collect();
-    out.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
-    out
+    groups.sort_by(|a, b| {
+        (severity_rank(&a.0).unwrap_or(u8::MAX), &a.0)
+            .cmp(&(severity_rank(&b.0).unwrap_or(u8::MAX), &b.0))
+    });
+    let mut all: Vec<f64> = groups.iter().flat_map(|(_, l)| l.iter().copied()).collect();
+    for (_, lags) in &mut groups {
+        lags.sort_unstable_by(f64::total_cmp);
+    }
+    all.sort_unstable_by(f64::total_cmp);
+    groups.push(("all".to_string(), all));
+    groups
+}
+
+// `adopters` followed by the cumulative adopted fraction at each grid day.
+fn adoption_curve_cells(sorted_lag_days: &[f64], grid_days: &[i64]) -> Vec<String> {
+    let grid: Vec<f64> = grid_days.iter().map(|d| *d as f64).collect();
+    std::iter::once(sorted_lag_days.len().to_string())
+        .chain(
+            cumulative_fractions(sorted_lag_days, &grid)
+                .into_iter()
+                .map(format_float),
+        )
+        .collect()
+}
+
+fn explain_advisory(x: &mut ExplainSink, adv: &Advisory) {
+    x.section("advisory");
+    x.line(format!("rustsec_id={}", adv.rustsec_id));
+    x.line(format!(
+        "cve_id={} cve_is_fallback={}",
+        adv.cve_id, adv.cve_is_fallback
+    ));
+    x.line(format!("aliases={}", adv.aliases.join("|")));
+    x.line(format!("related={}", adv.related.join("|")));
+    x.line(format!("group_id={}", adv.group_id));
+    if let Some(id) = &adv.duplicate_of {
+        x.line(format!("duplicate_of={id}"));
+    }
+    x.line(format!("package={}", adv.package));
+    x.line(format!(
+        "date={}",
+        adv.date.map_or("-".to_string(), |d| d.to_string())
+    ));
+    x.line(format!(
+        "severity={} severity_source={} legacy_severity={} cvss={}",
+        adv.severity,
+        adv.severity_source.as_str(),
+        legacy_severity(adv).as_deref().unwrap_or("-"),
+        adv.cvss.as_deref().unwrap_or("-")
+    ));
+    x.line(format!("informational={}", adv.informational.as_str()));
+    x.line(format!(
+        "withdrawn={} withdrawn_date={}",
+        adv.withdrawn,
+        adv.withdrawn_date
+            .map_or("-".to_string(), |d| d.to_string())
+    ));
+    x.line(format!(
+        "affected_functions={}",
+        adv.affected_functions.join("|")
+    ));
+    x.line(format!(
+        "keywords={} categories={}",
+        adv.keywords.join("|"),
+        adv.categories.join("|")
+    ));
+    x.line(format!("patched={}", adv.patched.join("|")));
+    x.line(format!("unaffected={}", adv.unaffected.join("|")));
 }
 
 fn record_skip(
@@ -1613,391 +6498,356 @@ fn record_skip(
 ) -> Result<()> {
     *skipped += 1;
     *skipped_by_reason.entry(reason).or_insert(0) += 1;
-    logger.println(format!(
-        "skip: rustsec_id={} cve_id={} pkg={} reason={} detail={}",
-        adv.rustsec_id,
-        adv.cve_id,
-        adv.package,
-        reason.as_str(),
-        detail
-    ))?;
-    Ok(())
-}
-
-struct LagStats {
-    count: usize,
-    min: i64,
-    max: i64,
-    avg: f64,
-    p50: f64,
-}
-
-fn compute_lag_stats<I>(lags: I) -> Option<LagStats>
-where
-    I: IntoIterator<Item = i64>,
-{
-    let mut xs: Vec<i64> = lags.into_iter().collect();
-    if xs.is_empty() {
-        return None;
-    }
-    xs.sort_unstable();
-    let count = xs.len();
-    let min = *xs.first().unwrap();
-    let max = *xs.last().unwrap();
-    let sum: i128 = xs.iter().map(|&x| x as i128).sum();
-    let avg = sum as f64 / count as f64;
-    let p50 = if count % 2 == 1 {
-        xs[count / 2] as f64
-    } else {
-        (xs[count / 2 - 1] as f64 + xs[count / 2] as f64) / 2.0
-    };
-    Some(LagStats {
-        count,
-        min,
-        max,
-        avg,
-        p50,
-    })
-}
-
-fn format_float(v: f64) -> String {
-    if v.is_finite() {
-        format!("{v:.4}")
-    } else {
-        v.to_string()
-    }
-}
-
-fn svg_escape(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    for ch in s.chars() {
-        match ch {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&apos;"),
-            _ => out.push(ch),
-        }
-    }
-    out
+    logger.println_with_detail(
+        &format!(
+            "skip: rustsec_id={} cve_id={} pkg={} reason={}",
+            adv.rustsec_id,
+            adv.cve_id,
+            adv.package,
+            reason.as_str()
+        ),
+        &format!("detail={detail}"),
+    )
 }
 
-fn nice_ticks(max_value: f64, tick_count: usize) -> Vec<f64> {
-    if !max_value.is_finite() || max_value <= 0.0 {
-        return vec![0.0];
-    }
-    let tick_count = tick_count.max(2);
-    let raw_step = max_value / (tick_count as f64 - 1.0);
-    let exp = raw_step.log10().floor();
-    let base = 10f64.powf(exp);
-    let frac = raw_step / base;
-    let step = if frac <= 1.0 {
-        1.0 * base
-    } else if frac <= 2.0 {
-        2.0 * base
-    } else if frac <= 5.0 {
-        5.0 * base
-    } else {
-        10.0 * base
-    };
-    let top = (max_value / step).ceil() * step;
-    let mut ticks = Vec::new();
-    let mut v = 0.0;
-    while v <= top + 1e-9 {
-        ticks.push(v);
-        v += step;
-    }
-    ticks
-}
-
-fn histogram_counts(values: &[i64], bins: usize, x_max: i64) -> Vec<usize> {
-    let bins = bins.max(1);
-    let x_max = x_max.max(1) as f64;
-    let w = x_max / bins as f64;
-    let mut counts = vec![0usize; bins];
-    for &v in values {
-        if v < 0 {
-            continue;
-        }
-        let vf = v as f64;
-        let mut idx = (vf / w).floor() as isize;
-        if idx < 0 {
-            idx = 0;
-        }
-        if idx as usize >= bins {
-            idx = bins as isize - 1;
-        }
-        counts[idx as usize] += 1;
-    }
-    counts
+struct Carrier {
+    crate_name: String,
+    fix_version: Version,
+    fix_time: chrono::DateTime<chrono::Utc>,
+    hop: usize,
+    // Root crate first, ending with crate_name.
+    path: Vec<String>,
+    // The root advisory's fix, and the per-hop lags summed along `path` up to fix_time.
+    root_fix_time: chrono::DateTime<chrono::Utc>,
+    root_lag_secs: i64,
 }
 
-fn write_hist_svg(
-    path: &Path,
-    values: &[i64],
-    bins: usize,
-    x_max: i64,
-    log_y: bool,
-    title: &str,
-    subtitle: &str,
-) -> Result<()> {
-    let bins = bins.max(1);
-    let x_max = x_max.max(1);
-    let counts = histogram_counts(values, bins, x_max);
-
-    let y_values: Vec<f64> = if log_y {
-        counts
-            .iter()
-            .map(|&c| if c > 0 { (c as f64).log10() } else { 0.0 })
-            .collect()
-    } else {
-        counts.iter().map(|&c| c as f64).collect()
-    };
-
-    let y_max = y_values.iter().copied().fold(0.0, f64::max).max(1.0);
-
-    let w = 960.0;
-    let h = 540.0;
-    let ml = 70.0;
-    let mr = 20.0;
-    let mt = 20.0;
-    let mb = 60.0;
-    let plot_w = w - ml - mr;
-    let plot_h = h - mt - mb;
-    let x0 = ml;
-    let y0 = mt;
-    let x1 = x0 + plot_w;
-    let y1 = y0 + plot_h;
-    let bar_w = plot_w / bins as f64;
-
-    let axis = "#222222";
-    let grid = "#E6E6E6";
-    let fill = "#4C78A8";
-    let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
-
-    let y_ticks = nice_ticks(y_max, 6);
-    let x_ticks = nice_ticks(x_max as f64, 7);
-
-    let mut parts = Vec::new();
-    parts.push(format!(
-        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
-        w_i = w as i64,
-        h_i = h as i64
-    ));
-    parts.push(format!(
-        r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
-        w_i = w as i64,
-        h_i = h as i64
-    ));
-
-    for t in y_ticks {
-        let y = y1 - (t / y_max) * plot_h;
-        parts.push(format!(
-            r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
-        ));
-        let label_val = if log_y {
-            format!("{:.1}", t)
-        } else {
-            format!("{:.0}", t)
-        };
-        parts.push(format!(
-            r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="12" fill="{axis}">{label}</text>"#,
-            x = x0 - 10.0,
-            ytext = y + 4.0,
-            label = svg_escape(&label_val)
-        ));
-    }
-
-    for t in x_ticks {
-        let x = x0 + (t / x_max as f64) * plot_w;
-        parts.push(format!(
-            r#"<line x1="{x:.2}" y1="{y0:.2}" x2="{x:.2}" y2="{y1:.2}" stroke="{grid}" stroke-width="1"/>"#
-        ));
-        parts.push(format!(
-            r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="middle" font-family="{font}" font-size="12" fill="{axis}">{label}</text>"#,
-            ytext = y1 + 20.0,
-            label = svg_escape(&format!("{:.0}", t))
-        ));
-    }
-
-    parts.push(format!(
-        r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
-    ));
-    parts.push(format!(
-        r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
-    ));
-
-    for (i, &v) in y_values.iter().enumerate() {
-        let bh = (v / y_max) * plot_h;
-        let x = x0 + i as f64 * bar_w;
-        let y = y1 - bh;
-        parts.push(format!(
-            r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
-            bw = (bar_w - 1.0).max(0.0)
-        ));
-    }
+// Downstream crates whose last version before the carrier's fix was affected,
+// and how many of them later required the fix. Adoptions past
+// --propagation-within-days are censored rather than adopted; crates with fewer
+// than --propagation-min-downstream-releases releases are not counted as affected.
+#[derive(Clone, Copy, Default)]
+struct HopCoverage {
+    affected: usize,
+    adopted: usize,
+    censored: usize,
+    few_releases: usize,
+}
 
-    parts.push(format!(
-        r#"<text x="{x:.2}" y="28" text-anchor="middle" font-family="{font}" font-size="18" fill="{axis}">{t}</text>"#,
-        x = w / 2.0,
-        t = svg_escape(title)
-    ));
-    parts.push(format!(
-        r#"<text x="{x:.2}" y="48" text-anchor="middle" font-family="{font}" font-size="12" fill="{axis}">{t}</text>"#,
-        x = w / 2.0,
-        t = svg_escape(subtitle)
-    ));
-    parts.push(format!(
-        r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="14" fill="{axis}">lag_days</text>"#,
-        x = w / 2.0,
-        y = h - 20.0
-    ));
+impl HopCoverage {
+    fn add(&mut self, scan: &AdoptionScan) {
+        self.affected += scan.affected;
+        self.adopted += scan.events.len();
+        self.censored += scan.censored;
+        self.few_releases += scan.few_releases;
+    }
 
-    let y_label = if log_y { "count (log10)" } else { "count" };
-    parts.push(format!(
-        r#"<text x="18" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="14" fill="{axis}" transform="rotate(-90 18 {y:.2})">{lbl}</text>"#,
-        y = h / 2.0,
-        lbl = y_label
-    ));
-    parts.push("</svg>\n".to_string());
+    fn not_adopted(&self) -> usize {
+        self.affected.saturating_sub(self.adopted + self.censored)
+    }
 
-    std::fs::write(path, parts.join("\n"))?;
-    Ok(())
+    fn adoption_rate_percent(&self) -> f64 {
+        if self.affected == 0 {
+            return 0.0;
+        }
+        self.adopted as f64 * 100.0 / self.affected as f64
+    }
 }
 
-struct StrictLagRow {
+#[derive(Clone, PartialEq)]
+struct VerifySample {
+    rustsec_id: String,
+    cve_id: String,
+    root_crate: String,
+    hop: usize,
+    upstream_crate: String,
+    upstream_fix_version: String,
+    upstream_fix_time: chrono::DateTime<chrono::Utc>,
     downstream_crate: String,
     downstream_version: String,
     downstream_time: chrono::DateTime<chrono::Utc>,
-    lag_days: i64,
-    original_req: String,
-    fixed_req: String,
-    matched_fix_version: String,
-    matched_fix_time: chrono::DateTime<chrono::Utc>,
+    dep_req: String,
 }
 
-struct Carrier {
-    crate_name: String,
-    fix_version: Version,
-    fix_time: chrono::DateTime<chrono::Utc>,
-    hop: usize,
+// A uniform reservoir over all propagation events plus a one-slot reservoir per hop,
+// so every observed hop is verified even when hop-1 events dominate the stream.
+struct VerifySampler {
+    capacity: usize,
+    seed: u64,
+    all: Reservoir<VerifySample>,
+    by_hop: BTreeMap<usize, Reservoir<VerifySample>>,
+}
+
+impl VerifySampler {
+    fn new(capacity: usize, seed: u64) -> Self {
+        Self {
+            capacity,
+            seed,
+            all: Reservoir::new(capacity, seed),
+            by_hop: BTreeMap::new(),
+        }
+    }
+
+    fn offer(&mut self, hop: usize, make: impl Fn() -> VerifySample) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.all.offer_with(&make);
+        let seed = self.seed.wrapping_add(hop as u64);
+        self.by_hop
+            .entry(hop)
+            .or_insert_with(|| Reservoir::new(1, seed))
+            .offer_with(&make);
+    }
+
+    fn seen(&self) -> u64 {
+        self.all.seen()
+    }
+
+    fn into_samples(self) -> Vec<VerifySample> {
+        let mut out: Vec<VerifySample> = self
+            .by_hop
+            .into_values()
+            .flat_map(Reservoir::into_items)
+            .take(self.capacity)
+            .collect();
+        for s in self.all.into_items() {
+            if out.len() >= self.capacity {
+                break;
+            }
+            if !out.contains(&s) {
+                out.push(s);
+            }
+        }
+        out
+    }
 }
 
 struct AdoptionEvent {
     downstream_crate: String,
     downstream_version: Version,
     downstream_time: chrono::DateTime<chrono::Utc>,
-    lag_days: i64,
+    lag_secs: i64,
     dep_req: String,
+    evidence: AdoptionEvidence,
+    opportunity: Option<FirstOpportunity>,
 }
 
-fn compute_strict_lags_for_target(
-    fix_times: &HashMap<Version, chrono::DateTime<chrono::Utc>>,
-    vuln_versions: &[Version],
-    downstream: &[DownstreamVersionInfo],
-) -> Vec<StrictLagRow> {
-    let mut by_crate: HashMap<&str, Vec<&DownstreamVersionInfo>> = HashMap::new();
-    for row in downstream {
-        by_crate
-            .entry(row.crate_name.as_str())
-            .or_default()
-            .push(row);
-    }
-
-    let mut outputs = Vec::new();
-    let mut skipped_negative = 0usize;
-    for (downstream_crate, mut history) in by_crate {
-        history.sort_by(|a, b| {
-            a.created_at
-                .cmp(&b.created_at)
-                .then_with(|| a.version.cmp(&b.version))
-        });
-
-        let mut ever_affected = false;
-        let mut last_vuln_req: Option<String> = None;
+// --propagation-min-downstream-releases and --propagation-within-days.
+#[derive(Clone, Copy)]
+struct AdoptionFilters {
+    policy: FixMatchPolicy,
+    duplicate_reqs: DuplicateReqs,
+    min_releases: Option<usize>,
+    within_secs: Option<i64>,
+}
 
-        for item in history {
-            let req = match VersionReq::parse(&item.dep_req) {
-                Ok(r) => r,
-                Err(_) => continue,
-            };
+impl AdoptionFilters {
+    fn from_args(args: &Args) -> Self {
+        AdoptionFilters {
+            policy: args.fix_match_policy,
+            duplicate_reqs: args.duplicate_dep_reqs,
+            min_releases: args.propagation_min_downstream_releases,
+            within_secs: args.propagation_within_days.map(|d| (d * 86_400.0) as i64),
+        }
+    }
+}
 
-            let is_vuln = vuln_versions.iter().any(|v| req.matches(v));
+struct AdoptionScan {
+    events: Vec<AdoptionEvent>,
+    affected: usize,
+    // Affected crates that adopted only after the --propagation-within-days window.
+    censored: usize,
+    // Crates skipped for having fewer releases than --propagation-min-downstream-releases.
+    few_releases: usize,
+    merged_duplicates: usize,
+}
 
-            if is_vuln {
-                ever_affected = true;
-                last_vuln_req = Some(item.dep_req.clone());
-                continue;
-            }
+#[derive(Clone, Copy, Default)]
+struct DownstreamExclusions {
+    few_versions: usize,
+    inactive: usize,
+}
 
-            if ever_affected {
-                let mut best_match: Option<(&Version, &chrono::DateTime<chrono::Utc>)> = None;
+impl DownstreamExclusions {
+    fn add(&mut self, other: DownstreamExclusions) {
+        self.few_versions += other.few_versions;
+        self.inactive += other.inactive;
+    }
+}
 
-                for (fv, ftime) in fix_times {
-                    if *ftime > item.created_at {
-                        continue;
-                    }
-                    let mut is_match = req.matches(fv);
-                    if !is_match
-                        && let Some(min_v) = estimate_min_version(&item.dep_req)
-                        && min_v >= *fv
-                    {
-                        is_match = true;
-                    }
+// `<prefix>_all.svg` and one `<prefix>_hop_<K>.svg` per hop, clipped at `clip` when set.
+fn propagation_histograms(
+    args: &Args,
+    out_dir: &Path,
+    prefix: &str,
+    title: &str,
+    all_lags: &[f64],
+    hops: &[(usize, Vec<f64>)],
+    clip: Option<f64>,
+) -> Result<()> {
+    let unit = args.lag_unit;
+    let groups = std::iter::once(("all hops".to_string(), "all".to_string(), all_lags)).chain(
+        hops.iter()
+            .map(|(hop, lags)| (format!("hop={hop}"), format!("hop_{hop}"), lags.as_slice())),
+    );
+    for (label, suffix, lags) in groups {
+        if lags.is_empty() {
+            continue;
+        }
+        let x_max = clip.unwrap_or_else(|| lags.iter().copied().fold(1.0, f64::max));
+        HistogramChart::new(lags)
+            .style(&args.chart_style)
+            .data_csv(args.chart_data_csv)
+            .bins(args.propagation_bins)
+            .x_max(x_max)
+            .overflow_bin(clip.is_some())
+            .log_y(true)
+            .title(format!(
+                "{title} {} histogram ({label}, n={})",
+                unit.column(),
+                lags.len()
+            ))
+            .subtitle(format!(
+                "bins={}, x_max={}, y_scale=log10",
+                args.propagation_bins,
+                unit.format(x_max)
+            ))
+            .x_label(unit.column())
+            .write_svg(out_dir.join(file_name_component(&format!("{prefix}_{suffix}.svg"))))?;
+    }
+    Ok(())
+}
 
-                    if is_match {
-                        match best_match {
-                            None => best_match = Some((fv, ftime)),
-                            Some((_, best_time)) => {
-                                if ftime < best_time {
-                                    best_match = Some((fv, ftime));
-                                }
-                            }
-                        }
-                    }
-                }
+// Drops downstream crates that look abandoned relative to `fix_time`: fewer than
+// --downstream-min-versions releases in total, or none within
+// --downstream-active-within-days before the fix. Strict, constraint and propagation
+// all take their histories through here so they agree on the population.
+async fn active_downstream<'a>(
+    db: &Database,
+    args: &Args,
+    activity_cache: &mut HashMap<String, Option<CrateActivity>>,
+    downstream: &'a [DownstreamVersionInfo],
+    fix_time: DateTime<Utc>,
+    cutoff: Option<DateTime<Utc>>,
+    excluded: &mut DownstreamExclusions,
+) -> Result<Cow<'a, [DownstreamVersionInfo]>> {
+    if args.downstream_min_versions.is_none() && args.downstream_active_within_days.is_none() {
+        return Ok(Cow::Borrowed(downstream));
+    }
 
-                if let Some((matched_ver, matched_time)) = best_match
-                    && let Some(original_req) = last_vuln_req.take()
-                {
-                    let lag_days = (item.created_at - *matched_time).num_days();
-                    if lag_days < 0 {
-                        skipped_negative += 1;
-                        continue;
-                    }
+    let mut missing: Vec<String> = downstream
+        .iter()
+        .filter(|r| !activity_cache.contains_key(&r.crate_name))
+        .map(|r| r.crate_name.clone())
+        .collect();
+    missing.sort();
+    missing.dedup();
+    if !missing.is_empty() {
+        let mut found = db.query_crate_activity(&missing, cutoff).await?;
+        for name in missing {
+            let activity = found.remove(&name);
+            activity_cache.insert(name, activity);
+        }
+    }
 
-                    outputs.push(StrictLagRow {
-                        downstream_crate: downstream_crate.to_string(),
-                        downstream_version: item.version.clone(),
-                        downstream_time: item.created_at,
-                        lag_days,
-                        original_req,
-                        fixed_req: item.dep_req.clone(),
-                        matched_fix_version: matched_ver.to_string(),
-                        matched_fix_time: *matched_time,
-                    });
-                    break;
-                }
+    let active_since = args
+        .downstream_active_within_days
+        .map(|d| fix_time - chrono::Duration::days(d));
+    let mut keep: HashMap<&str, bool> = HashMap::new();
+    let mut kept = Vec::with_capacity(downstream.len());
+    for row in downstream {
+        let keep = *keep.entry(row.crate_name.as_str()).or_insert_with(|| {
+            let activity = activity_cache.get(&row.crate_name).copied().flatten();
+            if args
+                .downstream_min_versions
+                .is_some_and(|n| activity.map_or(0, |a| a.versions) < n)
+            {
+                excluded.few_versions += 1;
+                return false;
             }
+            if let Some(since) = active_since
+                && activity.is_none_or(|a| a.last_release < since)
+            {
+                excluded.inactive += 1;
+                return false;
+            }
+            true
+        });
+        if keep {
+            kept.push(row.clone());
         }
     }
+    Ok(Cow::Owned(kept))
+}
 
-    if skipped_negative > 0 {
-        eprintln!(
-            "warning: skipped negative strict lags: {}",
-            skipped_negative
-        );
+#[derive(Clone, Copy, Default)]
+struct TimestampChecks {
+    checked: usize,
+    unavailable: usize,
+    anomalies: usize,
+    substituted_rows: usize,
+}
+
+impl TimestampChecks {
+    fn add(&mut self, other: TimestampChecks) {
+        self.checked += other.checked;
+        self.unavailable += other.unavailable;
+        self.anomalies += other.anomalies;
+        self.substituted_rows += other.substituted_rows;
+    }
+}
+
+// Negative lags are mostly downstream versions whose created_at was backfilled in the
+// dump. Checks the most negative ones (up to --timestamp-check-samples per advisory)
+// against crates.io and returns those off by more than --timestamp-anomaly-hours, keyed
+// by (crate, version), with the crates.io time.
+async fn check_timestamp_anomalies(
+    args: &Args,
+    client: &Client,
+    times: &mut CratesIoTimes,
+    logger: &mut Logger,
+    rows: &[StrictLagRow],
+    checks: &mut TimestampChecks,
+) -> Result<HashMap<(String, String), DateTime<Utc>>> {
+    let mut suspicious: Vec<&StrictLagRow> = rows.iter().filter(|r| r.negative).collect();
+    suspicious.sort_by(|a, b| {
+        a.lag_secs
+            .cmp(&b.lag_secs)
+            .then_with(|| a.downstream_crate.cmp(&b.downstream_crate))
+    });
+    let threshold = chrono::Duration::hours(args.timestamp_anomaly_hours);
+    let mut out = HashMap::new();
+    for r in suspicious.into_iter().take(args.timestamp_check_samples) {
+        checks.checked += 1;
+        // A failed request leaves the row unchecked rather than failing the advisory.
+        let api_time = times
+            .query(client, logger, &r.downstream_crate, &r.downstream_version)
+            .await?
+            .time()
+            .ok()
+            .flatten();
+        let Some(api_time) = api_time else {
+            checks.unavailable += 1;
+            continue;
+        };
+        if (api_time - r.downstream_time).abs() > threshold {
+            checks.anomalies += 1;
+            out.insert(
+                (r.downstream_crate.clone(), r.downstream_version.clone()),
+                api_time,
+            );
+        }
     }
-    outputs.sort_by(|a, b| a.downstream_crate.cmp(&b.downstream_crate));
-    outputs
+    Ok(out)
 }
 
 fn compute_adoption_events_for_target(
     fix_version: &Version,
     fix_time: chrono::DateTime<chrono::Utc>,
     downstream: &[DownstreamVersionInfo],
-) -> Vec<AdoptionEvent> {
+    filters: AdoptionFilters,
+) -> AdoptionScan {
     fn min_allowed(dep_req: &str) -> Option<Version> {
         estimate_min_version(dep_req)
     }
@@ -2016,21 +6866,27 @@ fn compute_adoption_events_for_target(
         min_v >= *fix_version
     }
 
-    let mut by_crate: HashMap<&str, Vec<&DownstreamVersionInfo>> = HashMap::new();
-    for row in downstream {
-        by_crate
-            .entry(row.crate_name.as_str())
-            .or_default()
-            .push(row);
-    }
-
-    let mut outputs = Vec::new();
-    for (downstream_crate, mut history) in by_crate {
-        history.sort_by(|a, b| {
-            a.created_at
-                .cmp(&b.created_at)
-                .then_with(|| a.version.cmp(&b.version))
-        });
+    // Events come out sorted by downstream crate, which fixes the BFS expansion order.
+    let (histories, merged_duplicates) = downstream_histories(downstream, filters.duplicate_reqs);
+    let mut scan = AdoptionScan {
+        events: Vec::new(),
+        affected: 0,
+        censored: 0,
+        few_releases: 0,
+        merged_duplicates,
+    };
+    for (downstream_crate, history) in histories {
+        if let Some(n) = filters.min_releases
+            && history
+                .iter()
+                .map(|r| r.version.as_str())
+                .collect::<HashSet<_>>()
+                .len()
+                < n
+        {
+            scan.few_releases += 1;
+            continue;
+        }
 
         let mut last_before: Option<&DownstreamVersionInfo> = None;
         for item in &history {
@@ -2046,7 +6902,9 @@ fn compute_adoption_events_for_target(
         if !is_ever_affected(&last_before.dep_req, fix_version) {
             continue;
         }
+        scan.affected += 1;
 
+        let release_times: Vec<DateTime<Utc>> = history.iter().map(|r| r.created_at).collect();
         for item in history {
             if item.created_at < fix_time {
                 continue;
@@ -2056,23 +6914,43 @@ fn compute_adoption_events_for_target(
                 let Ok(v) = Version::parse(&item.version) else {
                     break;
                 };
-                let lag_days = (item.created_at - fix_time).num_days();
-                outputs.push(AdoptionEvent {
+                let req = VersionReq::parse(&item.dep_req).ok();
+                let evidence = AdoptionEvidence::classify(
+                    req.as_ref(),
+                    min_allowed(&item.dep_req).as_ref(),
+                    fix_version,
+                )
+                .unwrap_or(AdoptionEvidence::EstimatedMin);
+                // The minimum already reached the fix, so only strict-req can reject
+                // this, and a later release may still qualify.
+                if !filters.policy.accepts(evidence) {
+                    continue;
+                }
+                let lag_secs = (item.created_at - fix_time).num_seconds();
+                if filters.within_secs.is_some_and(|w| lag_secs > w) {
+                    scan.censored += 1;
+                    break;
+                }
+                scan.events.push(AdoptionEvent {
                     downstream_crate: downstream_crate.to_string(),
                     downstream_version: v,
                     downstream_time: item.created_at,
-                    lag_days,
+                    lag_secs,
                     dep_req: item.dep_req.clone(),
+                    evidence,
+                    opportunity: first_opportunity(&release_times, fix_time, item.created_at),
                 });
                 break;
             }
         }
     }
 
-    outputs
+    scan
 }
 
-fn estimate_min_version(req_str: &str) -> Option<Version> {
+// Prefix-stripping estimate used before the comparator-based rework; kept only to
+// report how many downstream reqs changed classification.
+fn legacy_estimate_min_version(req_str: &str) -> Option<Version> {
     let s = req_str.trim();
     let s = s.split(',').next().unwrap_or(s).trim();
     let s = s.trim_start_matches(|c| {
@@ -2096,413 +6974,248 @@ fn estimate_min_version(req_str: &str) -> Option<Version> {
     None
 }
 
-struct Advisory {
-    rustsec_id: String,
-    cve_id: String,
-    severity: String,
-    package: String,
-    withdrawn: bool,
-    patched: Vec<String>,
-    unaffected: Vec<String>,
-}
-
-async fn fetch_rustsec_advisories(client: &Client) -> Result<Vec<Advisory>> {
-    let url = "https://github.com/RustSec/advisory-db/archive/refs/heads/main.zip";
-    let bytes = client
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()?
-        .bytes()
-        .await?;
-
-    let cursor = Cursor::new(bytes);
-    let mut zip = ZipArchive::new(cursor)?;
-    let mut out = Vec::new();
-
-    for i in 0..zip.len() {
-        let mut file = zip.by_index(i)?;
-        if !file.name().ends_with(".md") {
-            continue;
-        }
-        if !file.name().contains("/crates/") {
-            continue;
-        }
-        let mut s = String::new();
-        std::io::Read::read_to_string(&mut file, &mut s)?;
-        let Some(toml_str) = extract_toml_front_matter(&s) else {
-            continue;
-        };
-        let val: toml::Value = toml::from_str(toml_str)?;
-        let adv = parse_advisory(&val)?;
-        out.push(adv);
-    }
+// Approximate heap footprint per cached row, used by --max-memory-mb: the struct itself
+// plus typical crate name, version and req strings with allocator overhead.
+const DOWNSTREAM_ROW_BYTES: usize = std::mem::size_of::<DownstreamVersionInfo>() + 64;
+const VERSION_NUMBER_BYTES: usize = std::mem::size_of::<String>() + 24;
+const CRATES_IO_TIME_ENTRY_BYTES: usize =
+    std::mem::size_of::<((String, String), CratesIoTimeOutcome)>() + 48;
 
-    out.sort_by(|a, b| a.rustsec_id.cmp(&b.rustsec_id));
-    Ok(out)
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
 }
 
-fn extract_toml_front_matter(md: &str) -> Option<&str> {
-    let start = md.find("```toml")?;
-    let rest = &md[start + "```toml".len()..];
-    let rest = rest.strip_prefix('\n').unwrap_or(rest);
-    let end = rest.find("\n```")?;
-    Some(&rest[..end])
-}
-
-fn parse_advisory(val: &toml::Value) -> Result<Advisory> {
-    let advisory = val
-        .get("advisory")
-        .and_then(|v| v.as_table())
-        .ok_or_else(|| anyhow!("missing [advisory]"))?;
-
-    let rustsec_id = advisory
-        .get("id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("missing advisory.id"))?
-        .to_string();
-
-    let package = advisory
-        .get("package")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("missing advisory.package"))?
-        .to_string();
-
-    let withdrawn = advisory.get("withdrawn").is_some();
-
-    let cve_id = advisory
-        .get("aliases")
-        .and_then(|v| v.as_array())
-        .and_then(|arr| {
-            arr.iter()
-                .filter_map(|x| x.as_str())
-                .find(|s| s.starts_with("CVE-"))
-        })
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| rustsec_id.clone());
-
-    let severity = extract_severity(advisory);
-
-    let patched = val
-        .get("versions")
-        .and_then(|v| v.as_table())
-        .and_then(|t| t.get("patched"))
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|x| x.as_str().map(|s| s.to_string()))
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default();
-
-    let unaffected = val
-        .get("versions")
-        .and_then(|v| v.as_table())
-        .and_then(|t| t.get("unaffected"))
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|x| x.as_str().map(|s| s.to_string()))
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default();
-
-    Ok(Advisory {
-        rustsec_id,
-        cve_id,
-        severity,
-        package,
-        withdrawn,
-        patched,
-        unaffected,
-    })
-}
-
-fn normalize_severity(s: &str) -> String {
-    let u = s.trim().to_ascii_uppercase();
-    match u.as_str() {
-        "INFORMATIONAL" | "INFO" => "INFO".to_string(),
-        "LOW" => "LOW".to_string(),
-        "MEDIUM" | "MODERATE" => "MEDIUM".to_string(),
-        "HIGH" => "HIGH".to_string(),
-        "CRITICAL" => "CRITICAL".to_string(),
-        _ => {
-            if u.is_empty() {
-                "UNKNOWN".to_string()
-            } else {
-                u
-            }
-        }
-    }
+fn versions_cache_bytes(cache: &HashMap<String, Vec<String>>) -> usize {
+    cache.values().map(|v| v.len()).sum::<usize>() * VERSION_NUMBER_BYTES
 }
 
-fn extract_severity(advisory: &toml::value::Table) -> String {
-    if let Some(s) = advisory.get("severity").and_then(|v| v.as_str()) {
-        return normalize_severity(s);
-    }
-    if let Some(cvss) = advisory.get("cvss").and_then(|v| v.as_str())
-        && let Some(score) = cvss31_base_score_from_vector(cvss)
-    {
-        return severity_from_cvss_score(score);
-    }
-    if advisory.get("informational").is_some() {
-        return "INFO".to_string();
-    }
-    "UNKNOWN".to_string()
+fn crates_io_time_cache_bytes(cache: &HashMap<(String, String), CratesIoTimeOutcome>) -> usize {
+    cache.len() * CRATES_IO_TIME_ENTRY_BYTES
 }
 
-fn severity_from_cvss_score(score: f64) -> String {
-    if !score.is_finite() || score <= 0.0 {
-        return "INFO".to_string();
-    }
-    if score < 4.0 {
-        "LOW".to_string()
-    } else if score < 7.0 {
-        "MEDIUM".to_string()
-    } else if score < 9.0 {
-        "HIGH".to_string()
-    } else {
-        "CRITICAL".to_string()
-    }
-}
-
-fn cvss31_base_score_from_vector(s: &str) -> Option<f64> {
-    let s = s.trim();
-    let s = s
-        .strip_prefix("CVSS:3.1/")
-        .or_else(|| s.strip_prefix("CVSS:3.0/"))?;
-    let mut av: Option<f64> = None;
-    let mut ac: Option<f64> = None;
-    let mut pr_u: Option<f64> = None;
-    let mut pr_c: Option<f64> = None;
-    let mut ui: Option<f64> = None;
-    let mut scope: Option<char> = None;
-    let mut c: Option<f64> = None;
-    let mut i: Option<f64> = None;
-    let mut a: Option<f64> = None;
-
-    for part in s.split('/') {
-        let mut it = part.splitn(2, ':');
-        let k = it.next()?.trim();
-        let v = it.next()?.trim();
-        match k {
-            "AV" => {
-                av = match v {
-                    "N" => Some(0.85),
-                    "A" => Some(0.62),
-                    "L" => Some(0.55),
-                    "P" => Some(0.20),
-                    _ => None,
-                };
-            }
-            "AC" => {
-                ac = match v {
-                    "L" => Some(0.77),
-                    "H" => Some(0.44),
-                    _ => None,
-                };
-            }
-            "PR" => {
-                pr_u = match v {
-                    "N" => Some(0.85),
-                    "L" => Some(0.62),
-                    "H" => Some(0.27),
-                    _ => None,
-                };
-                pr_c = match v {
-                    "N" => Some(0.85),
-                    "L" => Some(0.68),
-                    "H" => Some(0.50),
-                    _ => None,
-                };
-            }
-            "UI" => {
-                ui = match v {
-                    "N" => Some(0.85),
-                    "R" => Some(0.62),
-                    _ => None,
-                };
-            }
-            "S" => {
-                scope = match v {
-                    "U" => Some('U'),
-                    "C" => Some('C'),
-                    _ => None,
-                };
-            }
-            "C" => {
-                c = match v {
-                    "H" => Some(0.56),
-                    "L" => Some(0.22),
-                    "N" => Some(0.0),
-                    _ => None,
-                };
-            }
-            "I" => {
-                i = match v {
-                    "H" => Some(0.56),
-                    "L" => Some(0.22),
-                    "N" => Some(0.0),
-                    _ => None,
-                };
-            }
-            "A" => {
-                a = match v {
-                    "H" => Some(0.56),
-                    "L" => Some(0.22),
-                    "N" => Some(0.0),
-                    _ => None,
-                };
-            }
-            _ => {}
-        }
-    }
-
-    let av = av?;
-    let ac = ac?;
-    let ui = ui?;
-    let scope = scope?;
-    let pr = match scope {
-        'U' => pr_u?,
-        'C' => pr_c?,
-        _ => return None,
-    };
-    let c = c?;
-    let i = i?;
-    let a = a?;
+fn memory_estimate(ctx: &BatchContext<'_>) -> usize {
+    ctx.cache.approx_bytes()
+        + versions_cache_bytes(&ctx.crate_versions_cache)
+        + crates_io_time_cache_bytes(&ctx.crates_io_times.cache)
+}
 
-    let iss = 1.0 - (1.0 - c) * (1.0 - i) * (1.0 - a);
-    let impact = if scope == 'U' {
-        6.42 * iss
-    } else {
-        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powf(15.0)
+// Soft --max-memory-mb check before `target_crate`'s history is loaded. Over the limit,
+// the caches are emptied first; if the incoming history alone still does not fit, it is
+// fetched through the streaming path so at least the fetch buffer is not doubled.
+async fn enforce_memory_limit(ctx: &mut BatchContext<'_>, target_crate: &str) -> Result<()> {
+    let Some(limit_mb) = ctx.args.max_memory_mb else {
+        return Ok(());
     };
-    let exploitability = 8.22 * av * ac * pr * ui;
-
-    if impact <= 0.0 {
-        return Some(0.0);
-    }
-
-    let raw = if scope == 'U' {
-        (impact + exploitability).min(10.0)
+    let limit = limit_mb * 1024 * 1024;
+    let incoming = if ctx.cache.map.contains_key(target_crate) {
+        0
     } else {
-        (1.08 * (impact + exploitability)).min(10.0)
+        ctx.cache
+            .count(ctx.db, ctx.analysis.renames, target_crate)
+            .await?
+            .max(0) as usize
+            * DOWNSTREAM_ROW_BYTES
     };
-
-    Some((raw * 10.0).ceil() / 10.0)
-}
-
-fn extract_all_fixed_versions(patched: &[String]) -> Vec<Version> {
-    let mut candidates = Vec::new();
-    for p in patched {
-        if let Ok(v) = Version::parse(p) {
-            candidates.push(v);
-            continue;
-        }
-        if let Ok(req) = VersionReq::parse(p) {
-            candidates.extend(extract_versions_from_req(&req));
-        }
+    let estimate = memory_estimate(ctx) + incoming;
+    if estimate <= limit {
+        return Ok(());
     }
-    candidates.sort();
-    candidates
-}
-
-fn extract_versions_from_req(req: &VersionReq) -> Vec<Version> {
-    let mut out = Vec::new();
-    for c in &req.comparators {
-        let Some(minor) = c.minor else {
-            continue;
-        };
-        let Some(patch) = c.patch else {
-            continue;
-        };
-        let v = Version {
-            major: c.major,
-            minor,
-            patch,
-            pre: c.pre.clone(),
-            build: semver::BuildMetadata::EMPTY,
-        };
-        match c.op {
-            Op::Exact | Op::Greater | Op::GreaterEq | Op::Tilde | Op::Caret => out.push(v),
-            _ => {}
-        }
+    ctx.memory_degradations += 1;
+    let (crates, rows) = ctx.cache.evict_all_except(target_crate);
+    let versions = ctx.crate_versions_cache.len();
+    let times = ctx.crates_io_times.cache.len();
+    ctx.crate_versions_cache.clear();
+    ctx.crates_io_times.cache.clear();
+    ctx.logger.println(format!(
+        "memory: estimate {:.0} MiB over --max-memory-mb {} before {}; evicted {} downstream crates ({} rows), {} version lists, {} crates.io times",
+        mib(estimate),
+        limit_mb,
+        target_crate,
+        crates,
+        rows,
+        versions,
+        times
+    ))?;
+    if memory_estimate(ctx) + incoming > limit && incoming > 0 {
+        ctx.cache.force_stream = true;
+        ctx.logger.println(format!(
+            "memory: {} needs ~{:.0} MiB on its own; fetching it through the streaming path",
+            target_crate,
+            mib(incoming)
+        ))?;
     }
-    out
+    Ok(())
 }
 
-fn identify_vuln_versions(
-    all_versions: &[String],
-    patched: &[String],
-    unaffected: &[String],
-) -> Vec<Version> {
-    let mut vuln = Vec::new();
-
-    if patched.is_empty() && unaffected.is_empty() {
-        for v_str in all_versions {
-            if let Ok(v) = Version::parse(v_str) {
-                vuln.push(v);
-            }
-        }
-        vuln.sort();
-        return vuln;
-    }
-
-    // Parse constraints
-    let patched_reqs: Vec<VersionReq> = patched
-        .iter()
-        .filter_map(|s| VersionReq::parse(s).ok())
-        .collect();
-    let unaffected_reqs: Vec<VersionReq> = unaffected
-        .iter()
-        .filter_map(|s| VersionReq::parse(s).ok())
-        .collect();
-
-    // Check each version
-    for v_str in all_versions {
-        if let Ok(v) = Version::parse(v_str) {
-            // Check if safe
-            let is_patched = patched_reqs.iter().any(|req| req.matches(&v));
-            let is_unaffected = unaffected_reqs.iter().any(|req| req.matches(&v));
-
-            if !is_patched && !is_unaffected {
-                vuln.push(v);
-            }
-        }
+async fn fetch_downstream(
+    db: &Database,
+    target_crate: &str,
+    count: i64,
+    stream_threshold: usize,
+) -> Result<Vec<DownstreamVersionInfo>> {
+    let count = count.max(0) as usize;
+    if count > stream_threshold {
+        db.stream_all_downstream_details(target_crate, count).await
+    } else {
+        db.query_all_downstream_details(target_crate).await
     }
-
-    vuln.sort();
-    vuln
 }
 
 struct DownstreamCache {
     max_crates: usize,
     order: std::collections::VecDeque<String>,
     map: HashMap<String, Vec<DownstreamVersionInfo>>,
+    // Rows created at or after the --as-of cutoff are dropped on insert, so every
+    // analysis reading from the cache sees the same snapshot.
+    cutoff: Option<DateTime<Utc>>,
+    excluded_rows: usize,
+    hits: usize,
+    misses: usize,
+    // Unfiltered dependent-history sizes; kept for every crate ever counted, not evicted.
+    counts: HashMap<String, i64>,
+    stream_threshold: usize,
+    streamed: usize,
+    // Rows currently held in `map`, for the --max-memory-mb estimate.
+    rows: usize,
+    // Set by the memory limit: the next fetch takes the streaming path whatever its size.
+    force_stream: bool,
+    // Renamed target -> rows each of its names contributed to the merged history.
+    merged_sources: HashMap<String, Vec<(String, usize)>>,
+    crates_io_fallback: Option<CratesIoFallback>,
 }
 
 impl DownstreamCache {
-    fn new(max_crates: usize) -> Self {
+    fn new(
+        max_crates: usize,
+        cutoff: Option<DateTime<Utc>>,
+        stream_threshold: usize,
+        crates_io_fallback: Option<CratesIoFallback>,
+    ) -> Self {
         Self {
             max_crates: max_crates.max(1),
             order: std::collections::VecDeque::new(),
             map: HashMap::new(),
+            cutoff,
+            excluded_rows: 0,
+            hits: 0,
+            misses: 0,
+            counts: HashMap::new(),
+            stream_threshold,
+            streamed: 0,
+            rows: 0,
+            force_stream: false,
+            merged_sources: HashMap::new(),
+            crates_io_fallback,
+        }
+    }
+
+    async fn count(
+        &mut self,
+        db: &Database,
+        renames: &CrateRenames,
+        target_crate: &str,
+    ) -> Result<i64> {
+        if let Some(n) = self.counts.get(target_crate) {
+            return Ok(*n);
+        }
+        let mut n = 0;
+        for name in renames.chain(target_crate) {
+            n += db.count_downstream_details(&name).await?;
         }
+        self.counts.insert(target_crate.to_string(), n);
+        Ok(n)
     }
 
     async fn get_or_fetch(
         &mut self,
         db: &Database,
+        renames: &CrateRenames,
         target_crate: &str,
     ) -> Result<&Vec<DownstreamVersionInfo>> {
+        self.load(db, renames, target_crate).await?;
+        Ok(self.map.get(target_crate).unwrap())
+    }
+
+    // A crate with --crate-renames gets the histories of all its names merged, with a
+    // dependent version that required several of them kept once.
+    async fn load(
+        &mut self,
+        db: &Database,
+        renames: &CrateRenames,
+        target_crate: &str,
+    ) -> Result<()> {
         if self.map.contains_key(target_crate) {
+            self.hits += 1;
             self.touch(target_crate);
-            return Ok(self.map.get(target_crate).unwrap());
+            return Ok(());
         }
 
-        let rows = db.query_all_downstream_details(target_crate).await?;
+        self.misses += 1;
+        let threshold = if std::mem::take(&mut self.force_stream) {
+            0
+        } else {
+            self.stream_threshold
+        };
+        let names = renames.chain(target_crate);
+        let rows = if names.len() == 1 {
+            let count = self.count(db, renames, target_crate).await?;
+            self.fetch_name(db, target_crate, count, threshold).await?
+        } else {
+            let mut histories = Vec::new();
+            for name in names {
+                let count = db.count_downstream_details(&name).await?;
+                let rows = self.fetch_name(db, &name, count, threshold).await?;
+                histories.push((name, rows));
+            }
+            let mut sources: Vec<(String, usize)> = histories
+                .iter()
+                .map(|(name, _)| (name.clone(), 0))
+                .collect();
+            let merged = merge_renamed_histories(
+                histories,
+                |r| r.created_at,
+                |r| (r.crate_name.clone(), r.version.clone()),
+            );
+            let mut rows = Vec::with_capacity(merged.len());
+            for (name, r) in merged {
+                if let Some(s) = sources.iter_mut().find(|(n, _)| *n == name) {
+                    s.1 += 1;
+                }
+                rows.push(r);
+            }
+            self.merged_sources
+                .insert(target_crate.to_string(), sources);
+            rows
+        };
         self.insert(target_crate.to_string(), rows);
-        Ok(self.map.get(target_crate).unwrap())
+        Ok(())
+    }
+
+    // With --crates-io-downstream-fallback, a crate without dependents in the snapshot
+    // gets rows synthesized from the crates.io API instead.
+    async fn fetch_name(
+        &mut self,
+        db: &Database,
+        crate_name: &str,
+        count: i64,
+        threshold: usize,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        if count as usize > threshold {
+            self.streamed += 1;
+        }
+        let rows = fetch_downstream(db, crate_name, count, threshold).await?;
+        let Some(fallback) = self.crates_io_fallback.as_mut() else {
+            return Ok(rows);
+        };
+        if !rows.is_empty() {
+            return Ok(rows);
+        }
+        let rows = fallback.downstream(crate_name).await?;
+        if !rows.is_empty() {
+            fallback.crates += 1;
+            fallback.rows += rows.len();
+        }
+        Ok(rows)
     }
 
     fn touch(&mut self, key: &str) {
@@ -2512,13 +7225,63 @@ impl DownstreamCache {
         self.order.push_back(key.to_string());
     }
 
-    fn insert(&mut self, key: String, value: Vec<DownstreamVersionInfo>) {
-        self.map.insert(key.clone(), value);
+    // Smallest first, so under eviction pressure the largest (most expensive) crates stay.
+    fn insert_many(&mut self, mut entries: Vec<(String, Vec<DownstreamVersionInfo>)>) {
+        entries.sort_by(|a, b| a.1.len().cmp(&b.1.len()).then_with(|| a.0.cmp(&b.0)));
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn approx_bytes(&self) -> usize {
+        self.rows * DOWNSTREAM_ROW_BYTES
+    }
+
+    // Drops every cached crate except `keep`; returns (crates, rows) evicted.
+    fn evict_all_except(&mut self, keep: &str) -> (usize, usize) {
+        let before = (self.map.len(), self.rows);
+        let kept = self.map.remove_entry(keep);
+        self.map.clear();
+        self.order.clear();
+        self.rows = 0;
+        if let Some((key, value)) = kept {
+            self.rows = value.len();
+            self.order.push_back(key.clone());
+            self.map.insert(key, value);
+        }
+        (before.0 - self.map.len(), before.1 - self.rows)
+    }
+
+    fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+
+    fn insert(&mut self, key: String, mut value: Vec<DownstreamVersionInfo>) {
+        if let Some(cutoff) = self.cutoff {
+            let before = value.len();
+            value.retain(|r| r.created_at < cutoff);
+            self.excluded_rows += before - value.len();
+        }
+        self.rows += value.len();
+        if let Some(old) = self.map.insert(key.clone(), value) {
+            self.rows -= old.len();
+        }
         self.touch(&key);
 
         while self.order.len() > self.max_crates {
-            if let Some(oldest) = self.order.pop_front() {
-                self.map.remove(&oldest);
+            if let Some(oldest) = self.order.pop_front()
+                && let Some(old) = self.map.remove(&oldest)
+            {
+                self.rows -= old.len();
             }
         }
     }
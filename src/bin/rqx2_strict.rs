@@ -1,24 +1,85 @@
-use std::{collections::HashMap, fs::File};
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
 
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Parser;
+use reqwest::Client;
 use semver::{Version, VersionReq};
-use time_to_fix_cve::database;
-use time_to_fix_cve::database::Database;
+use time_to_fix_cve::advisory::{
+    InformationalKind, PrereleasePolicy, fetch_rustsec_advisories, parse_advisory_archive,
+};
+use time_to_fix_cve::config;
+use time_to_fix_cve::database::{DataSource, Database};
+use time_to_fix_cve::output::{
+    Compression, CsvSchema, create_csv, ensure_parent_dir, serialize_opt_path,
+};
+use time_to_fix_cve::pipeline::{
+    AnalysisOptions, DuplicateReqs, FixMatchPolicy, PackageAliases, StoreSource, StrictLagOptions,
+    StrictLagRow, T0Kind, WithdrawnPolicy, analyze_advisory, compute_strict_lags_for_target,
+    resolve_equivalent_version_string, whole_days,
+};
+use time_to_fix_cve::stats::{SUMMARY_SCHEMA, SUMMARY_SCHEMA_ID, compute_lag_stats, format_float};
 
-#[derive(Parser)]
+#[derive(Parser, serde::Serialize)]
 struct Args {
+    #[arg(long, conflicts_with_all = ["cve_id", "target_crate", "fixed_version", "vuln_version_sample", "vuln_req"])]
+    rustsec_id: Option<String>,
+
+    #[arg(long, required_unless_present = "rustsec_id")]
+    cve_id: Option<String>,
+
+    #[arg(long, required_unless_present = "rustsec_id")]
+    target_crate: Option<String>,
+
+    #[arg(long, required_unless_present = "rustsec_id")]
+    fixed_version: Vec<String>,
+
+    #[arg(long, required_unless_present_any = ["rustsec_id", "vuln_req"], conflicts_with = "vuln_req")]
+    vuln_version_sample: Option<String>,
+
     #[arg(long)]
-    cve_id: String,
+    vuln_req: Vec<String>,
+
+    // Read instead of downloading advisory-db, as with the batch's flag of the same name.
+    #[arg(long, requires = "rustsec_id")]
+    advisory_db_zip: Option<String>,
+
+    #[arg(long, value_name = "FROM=TO", requires = "rustsec_id")]
+    package_alias: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = PrereleasePolicy::MatchSemver)]
+    prerelease_policy: PrereleasePolicy,
+
+    #[arg(long, value_enum, default_value_t = FixMatchPolicy::Either)]
+    fix_match_policy: FixMatchPolicy,
 
     #[arg(long)]
-    target_crate: String,
+    #[serde(serialize_with = "serialize_opt_path")]
+    output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false)]
+    summary: bool,
+
+    #[arg(long, requires = "summary")]
+    #[serde(serialize_with = "serialize_opt_path")]
+    summary_output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false)]
+    csv_schema_comments: bool,
+
+    #[arg(long, value_enum, default_value_t = DataSource::Postgres)]
+    data_source: DataSource,
 
     #[arg(long)]
-    fixed_version: String,
+    dump_dir: Option<String>,
 
     #[arg(long)]
-    vuln_version_sample: String,
+    #[serde(skip)]
+    config: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    print_config: bool,
 }
 
 struct OutputRow {
@@ -28,104 +89,252 @@ struct OutputRow {
     lag_days: i64,
     original_req: String,
     fixed_req: String,
+    matched_fix_version: String,
+    matched_fix_time: String,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-
-    let fixed_version = Version::parse(&args.fixed_version)
-        .map_err(|e| anyhow!("invalid --fixed-version {}: {e}", args.fixed_version))?;
-    let vuln_sample = Version::parse(&args.vuln_version_sample).map_err(|e| {
-        anyhow!(
-            "invalid --vuln-version-sample {}: {e}",
-            args.vuln_version_sample
-        )
-    })?;
-
-    let db = Database::connect_from_env().await?;
-    let t0 = db
-        .query_version_time(&args.target_crate, &args.fixed_version)
-        .await?
-        .ok_or_else(|| {
-            anyhow!(
-                "cannot find published time for {} {}",
-                args.target_crate,
-                args.fixed_version
-            )
-        })?;
-
-    let downstream = db.query_all_downstream_details(&args.target_crate).await?;
-    let mut by_crate: HashMap<String, Vec<database::DownstreamVersionInfo>> = HashMap::new();
-    for row in downstream {
-        by_crate
-            .entry(row.crate_name.clone())
-            .or_default()
-            .push(row);
+// The advisory (or the manual flags standing in for one) with its fix times and the
+// strict rows measured against them.
+struct StrictTarget {
+    rustsec_id: String,
+    cve_id: String,
+    ghsa_id: String,
+    cve_is_fallback: bool,
+    aliases: Vec<String>,
+    group_id: String,
+    affected_functions: Vec<String>,
+    keywords: Vec<String>,
+    categories: Vec<String>,
+    informational: InformationalKind,
+    withdrawn_date: Option<NaiveDate>,
+    severity: String,
+    target_crate: String,
+    fix_times: BTreeMap<Version, DateTime<Utc>>,
+    rows: Vec<StrictLagRow>,
+    affected: usize,
+}
+
+// Everything is derived by `analyze_advisory` over the database, as in the batch: the
+// crate name through aliases and `-`/`_` variants, the fixed versions (first published
+// releases for pure ranges), their times and the vulnerable range.
+async fn target_from_advisory(
+    args: &Args,
+    db: &Database,
+    rustsec_id: &str,
+) -> Result<StrictTarget> {
+    let advisories = match args.advisory_db_zip.as_deref() {
+        Some(path) => {
+            let bytes = std::fs::read(path).map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+            parse_advisory_archive(&bytes, path, false)?.0
+        }
+        None => {
+            let client = Client::builder()
+                .user_agent("time-to-fix-cve/0.1")
+                .build()?;
+            fetch_rustsec_advisories(&client).await?
+        }
+    };
+    let adv = advisories
+        .into_iter()
+        .find(|a| a.rustsec_id == rustsec_id)
+        .ok_or_else(|| anyhow!("advisory {rustsec_id} not found in advisory-db"))?;
+    if adv.withdrawn {
+        eprintln!("warning: advisory {rustsec_id} is withdrawn");
     }
 
-    let mut outputs = Vec::new();
-
-    for (crate_name, mut history) in by_crate {
-        history.sort_by(|a, b| {
-            a.created_at
-                .cmp(&b.created_at)
-                .then_with(|| a.version.cmp(&b.version))
-        });
-
-        let mut ever_affected = false;
-        let mut last_vuln_req: Option<String> = None;
-        let mut found_fix: Option<OutputRow> = None;
-
-        for item in history {
-            let req = match VersionReq::parse(&item.dep_req) {
-                Ok(r) => r,
-                Err(e) => {
-                    eprintln!(
-                        "skip {} {}: cannot parse req {} ({e})",
-                        crate_name, item.version, item.dep_req
-                    );
-                    continue;
-                }
-            };
+    let package_aliases = PackageAliases::parse(&args.package_alias)?;
+    let opts = AnalysisOptions {
+        package_aliases: &package_aliases,
+        prerelease_policy: args.prerelease_policy,
+        fix_match_policy: args.fix_match_policy,
+        withdrawn: WithdrawnPolicy::Include,
+        constraint: false,
+        ..AnalysisOptions::default()
+    };
+    let mut source = StoreSource::new(db, opts.renames);
+    let result = analyze_advisory(&mut source, &adv, &opts).await?;
+    if let Some(skip) = &result.skip {
+        return Err(anyhow!(
+            "advisory {rustsec_id} not analyzed: {} ({})",
+            skip.reason.as_str(),
+            skip.detail
+        ));
+    }
+    eprintln!(
+        "{rustsec_id}: crate={} fixed_versions={} vuln_versions={}",
+        result.crate_name,
+        result.fix_times.len(),
+        result.vuln_versions.len()
+    );
 
-            let allows_vuln = req.matches(&vuln_sample);
-            if allows_vuln {
-                ever_affected = true;
-                last_vuln_req = Some(item.dep_req.clone());
-                continue;
-            }
+    Ok(StrictTarget {
+        ghsa_id: adv.ghsa_id().unwrap_or_default().to_string(),
+        rustsec_id: adv.rustsec_id,
+        cve_id: adv.cve_id,
+        cve_is_fallback: adv.cve_is_fallback,
+        aliases: adv.aliases,
+        group_id: adv.group_id,
+        affected_functions: adv.affected_functions,
+        keywords: adv.keywords,
+        categories: adv.categories,
+        informational: adv.informational,
+        withdrawn_date: adv.withdrawn_date,
+        severity: adv.severity,
+        target_crate: result.crate_name,
+        fix_times: result.fix_times,
+        rows: result.rows,
+        affected: result.affected,
+    })
+}
 
-            if ever_affected
-                && req.matches(&fixed_version)
-                && let Some(original_req) = last_vuln_req.take()
-            {
-                let lag_days = (item.created_at - t0).num_days();
-                found_fix = Some(OutputRow {
-                    crate_name: crate_name.clone(),
-                    fix_version: args.fixed_version.clone(),
-                    fix_time: t0.to_string(),
-                    lag_days,
-                    original_req,
-                    fixed_req: item.dep_req.clone(),
-                });
-                break;
-            }
+async fn target_from_flags(args: &Args, db: &Database) -> Result<StrictTarget> {
+    // clap guarantees the manual flags are present without --rustsec-id.
+    let target_crate = args.target_crate.clone().unwrap_or_default();
+    let mut fixed_versions = Vec::new();
+    for s in &args.fixed_version {
+        let v = Version::parse(s).map_err(|e| anyhow!("invalid --fixed-version {s}: {e}"))?;
+        fixed_versions.push(v);
+    }
+    fixed_versions.sort();
+    fixed_versions.dedup();
+
+    let vuln_versions = if let Some(sample) = &args.vuln_version_sample {
+        let v = Version::parse(sample)
+            .map_err(|e| anyhow!("invalid --vuln-version-sample {sample}: {e}"))?;
+        vec![v]
+    } else {
+        let mut reqs = Vec::new();
+        for s in &args.vuln_req {
+            let req = VersionReq::parse(s).map_err(|e| anyhow!("invalid --vuln-req {s}: {e}"))?;
+            reqs.push(req);
+        }
+        let mut out: Vec<Version> = db
+            .query_all_version_numbers(&target_crate)
+            .await?
+            .iter()
+            .filter_map(|s| Version::parse(s).ok())
+            .filter(|v| reqs.iter().any(|req| req.matches(v)))
+            .collect();
+        out.sort();
+        out.dedup();
+        if out.is_empty() {
+            return Err(anyhow!(
+                "--vuln-req matches no published version of {target_crate}"
+            ));
         }
+        eprintln!("{target_crate}: vuln_versions={}", out.len());
+        out
+    };
 
-        match found_fix {
-            Some(r) => outputs.push(r),
-            None => {
-                if ever_affected {
-                    eprintln!("crate {crate_name} was affected but never explicitly fixed");
+    // Fix times come from the published version list, so a fixed version given without
+    // build metadata still finds the release that carries it.
+    let version_times = db.query_all_version_times(&target_crate).await?;
+    let mut fix_times: BTreeMap<Version, DateTime<Utc>> = BTreeMap::new();
+    for v in &fixed_versions {
+        match resolve_equivalent_version_string(&version_times, v) {
+            Some(eq) => {
+                if !eq.exact {
+                    eprintln!("{target_crate} {v}: using {}", eq.describe());
                 }
+                fix_times.insert(v.clone(), eq.created_at);
             }
+            None => eprintln!("cannot find published time for {target_crate} {v}"),
         }
     }
+    if fix_times.is_empty() {
+        return Err(anyhow!(
+            "cannot find published time for any fixed version of {target_crate}"
+        ));
+    }
+
+    let downstream = db.query_all_downstream_details(&target_crate).await?;
+    // A downstream release counts only once its req carries a fix that was already
+    // published, by the evidence --fix-match-policy accepts, so rows are never negative.
+    let scan = compute_strict_lags_for_target(
+        &fix_times,
+        &vuln_versions,
+        &downstream,
+        StrictLagOptions {
+            t0_kind: T0Kind::FixRelease,
+            advisory_time: None,
+            all_adoptions: false,
+            history_cutoff: None,
+            policy: args.fix_match_policy,
+            duplicate_reqs: DuplicateReqs::default(),
+        },
+        None,
+    );
+
+    Ok(StrictTarget {
+        rustsec_id: String::new(),
+        cve_id: args.cve_id.clone().unwrap_or_default(),
+        ghsa_id: String::new(),
+        cve_is_fallback: false,
+        aliases: Vec::new(),
+        group_id: String::new(),
+        affected_functions: Vec::new(),
+        keywords: Vec::new(),
+        categories: Vec::new(),
+        informational: InformationalKind::None,
+        withdrawn_date: None,
+        severity: "UNKNOWN".to_string(),
+        target_crate,
+        fix_times,
+        rows: scan.rows.into_iter().filter(|r| !r.negative).collect(),
+        affected: scan.affected,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Args = config::parse_args()?;
+    if args.print_config {
+        print!("{}", config::to_toml(&args)?);
+        return Ok(());
+    }
+
+    let db = Database::connect(args.data_source, args.dump_dir.as_deref()).await?;
+    let target = match &args.rustsec_id {
+        Some(rustsec_id) => target_from_advisory(&args, &db, rustsec_id).await?,
+        None => target_from_flags(&args, &db).await?,
+    };
+    let Some((primary_fix_version, primary_fix_time)) = target
+        .fix_times
+        .first_key_value()
+        .map(|(v, t)| (v.to_string(), t.to_string()))
+    else {
+        return Err(anyhow!(
+            "cannot find published time for any fixed version of {}",
+            target.target_crate
+        ));
+    };
+
+    let mut outputs: Vec<OutputRow> = target
+        .rows
+        .iter()
+        .map(|r| OutputRow {
+            crate_name: r.downstream_crate.clone(),
+            fix_version: primary_fix_version.clone(),
+            fix_time: primary_fix_time.clone(),
+            lag_days: whole_days(r.lag_secs),
+            original_req: r.original_req.clone(),
+            fixed_req: r.fixed_req.clone(),
+            matched_fix_version: r.matched_fix_version.clone(),
+            matched_fix_time: r.matched_fix_time.to_string(),
+        })
+        .collect();
+    let never_fixed = target.affected - outputs.len();
+    if never_fixed > 0 {
+        eprintln!("{never_fixed} affected downstream crates were never explicitly fixed");
+    }
 
     outputs.sort_by(|a, b| a.crate_name.cmp(&b.crate_name));
 
-    let out_path = format!("rqx2_strict_lag_{}.csv", args.cve_id);
+    let out_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("rqx2_strict_lag_{}.csv", target.cve_id)));
+    ensure_parent_dir(&out_path)?;
     let file = File::create(&out_path)?;
     let mut w = csv::Writer::from_writer(file);
     w.write_record([
@@ -135,19 +344,86 @@ async fn main() -> Result<()> {
         "lag_days",
         "original_req",
         "fixed_req",
+        "matched_fix_version",
+        "matched_fix_time",
     ])?;
-    for row in outputs {
+    for row in &outputs {
         w.write_record([
-            row.crate_name,
-            row.fix_version,
-            row.fix_time,
-            row.lag_days.to_string(),
-            row.original_req,
-            row.fixed_req,
+            &row.crate_name,
+            &row.fix_version,
+            &row.fix_time,
+            &row.lag_days.to_string(),
+            &row.original_req,
+            &row.fixed_req,
+            &row.matched_fix_version,
+            &row.matched_fix_time,
         ])?;
     }
     w.flush()?;
 
-    println!("wrote {out_path}");
+    println!("wrote {}", out_path.display());
+
+    if args.summary {
+        let Some(stats) = compute_lag_stats(outputs.iter().map(|r| r.lag_days as f64)) else {
+            println!("no strict lag rows, nothing to summarize");
+            return Ok(());
+        };
+        println!(
+            "lag_days: count={} min={} p50={} avg={} max={}",
+            stats.count,
+            stats.min as i64,
+            format_float(stats.p50),
+            format_float(stats.avg),
+            stats.max as i64
+        );
+
+        if let Some(path) = &args.summary_output {
+            ensure_parent_dir(path)?;
+            let schema = CsvSchema::new(SUMMARY_SCHEMA_ID, &SUMMARY_SCHEMA);
+            let mut sw = create_csv(path, Compression::None, &schema, args.csv_schema_comments)?;
+            // Columns the strict tool does not compute stay empty.
+            let record = schema.record(&[
+                ("rustsec_id", target.rustsec_id.clone()),
+                ("cve_id", target.cve_id.clone()),
+                ("ghsa_id", target.ghsa_id.clone()),
+                ("aliases", target.aliases.join("|")),
+                ("cve_is_fallback", target.cve_is_fallback.to_string()),
+                ("group_id", target.group_id.clone()),
+                (
+                    "function_scoped",
+                    (!target.affected_functions.is_empty()).to_string(),
+                ),
+                ("affected_functions", target.affected_functions.join("|")),
+                (
+                    "informational_kind",
+                    target.informational.as_str().to_string(),
+                ),
+                (
+                    "withdrawn_date",
+                    target
+                        .withdrawn_date
+                        .map(|d| d.to_string())
+                        .unwrap_or_default(),
+                ),
+                ("severity", target.severity.clone()),
+                ("target_crate", target.target_crate.clone()),
+                ("fixed_version", primary_fix_version.clone()),
+                ("fix_time", primary_fix_time.clone()),
+                ("downstream_fixed_cnt", stats.count.to_string()),
+                ("lag_days_min", (stats.min as i64).to_string()),
+                ("lag_days_p50", format_float(stats.p50)),
+                ("lag_days_avg", format_float(stats.avg)),
+                ("lag_days_max", (stats.max as i64).to_string()),
+                ("t0_kind", "fix-release".to_string()),
+                ("pre_disclosure_cnt", "0".to_string()),
+                ("lag_unit", "days".to_string()),
+                ("keywords", target.keywords.join("|")),
+                ("categories", target.categories.join("|")),
+            ])?;
+            sw.write_record(&record)?;
+            sw.flush()?;
+            println!("wrote {}", path.display());
+        }
+    }
     Ok(())
 }
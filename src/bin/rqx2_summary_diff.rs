@@ -0,0 +1,244 @@
+use std::{collections::BTreeMap, fs::File, io::Write, path::PathBuf};
+
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use time_to_fix_cve::{output::ensure_parent_dir, stats::format_float};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    old: String,
+
+    #[arg(long)]
+    new: String,
+
+    #[arg(long, default_value = "summary_diff.csv")]
+    output: PathBuf,
+
+    #[arg(long, default_value = "summary_diff.txt")]
+    digest_output: PathBuf,
+
+    #[arg(long, default_value_t = 0.0)]
+    lag_tolerance: f64,
+
+    #[arg(long, default_value_t = 0)]
+    count_tolerance: u64,
+}
+
+const COUNT_COLUMN: &str = "downstream_fixed_cnt";
+const LAG_COLUMNS: [&str; 4] = [
+    "lag_days_min",
+    "lag_days_p50",
+    "lag_days_avg",
+    "lag_days_max",
+];
+
+// One summary CSV keyed by rustsec_id; columns are looked up by name so runs that
+// added columns in between still compare.
+struct SummaryTable {
+    rows: BTreeMap<String, BTreeMap<String, String>>,
+    duplicates: usize,
+}
+
+fn load_summary(path: &str) -> Result<SummaryTable> {
+    // Summaries written with --csv-schema-comments start with a `# schema=` line.
+    let mut r = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(path)
+        .map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+    let headers = r.headers()?.clone();
+    if !headers.iter().any(|h| h == "rustsec_id") {
+        return Err(anyhow!("{path} has no rustsec_id column"));
+    }
+    let mut rows = BTreeMap::new();
+    let mut duplicates = 0usize;
+    for record in r.records() {
+        let record = record?;
+        let row: BTreeMap<String, String> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(h, v)| (h.to_string(), v.to_string()))
+            .collect();
+        let id = row.get("rustsec_id").cloned().unwrap_or_default();
+        if rows.insert(id, row).is_some() {
+            duplicates += 1;
+        }
+    }
+    Ok(SummaryTable { rows, duplicates })
+}
+
+struct Change {
+    rustsec_id: String,
+    metric: &'static str,
+    old: f64,
+    new: f64,
+}
+
+impl Change {
+    fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+fn numeric(row: &BTreeMap<String, String>, column: &str) -> Option<f64> {
+    row.get(column)
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let old = load_summary(&args.old)?;
+    let new = load_summary(&args.new)?;
+
+    let added: Vec<&String> = new
+        .rows
+        .keys()
+        .filter(|id| !old.rows.contains_key(*id))
+        .collect();
+    let removed: Vec<&String> = old
+        .rows
+        .keys()
+        .filter(|id| !new.rows.contains_key(*id))
+        .collect();
+
+    let mut changes: Vec<Change> = Vec::new();
+    let mut unit_mismatches: Vec<&String> = Vec::new();
+    for (id, old_row) in &old.rows {
+        let Some(new_row) = new.rows.get(id) else {
+            continue;
+        };
+        if old_row.get("lag_unit") != new_row.get("lag_unit") {
+            unit_mismatches.push(id);
+        }
+        let metrics = std::iter::once((COUNT_COLUMN, args.count_tolerance as f64))
+            .chain(LAG_COLUMNS.iter().map(|c| (*c, args.lag_tolerance)));
+        for (metric, tolerance) in metrics {
+            let (Some(o), Some(n)) = (numeric(old_row, metric), numeric(new_row, metric)) else {
+                continue;
+            };
+            if (n - o).abs() > tolerance {
+                changes.push(Change {
+                    rustsec_id: id.clone(),
+                    metric,
+                    old: o,
+                    new: n,
+                });
+            }
+        }
+    }
+
+    ensure_parent_dir(&args.output)?;
+    let mut w = csv::Writer::from_writer(File::create(&args.output)?);
+    w.write_record(["rustsec_id", "status", "metric", "old", "new", "delta"])?;
+    for id in &added {
+        w.write_record([id.as_str(), "added", "", "", "", ""])?;
+    }
+    for id in &removed {
+        w.write_record([id.as_str(), "removed", "", "", "", ""])?;
+    }
+    for c in &changes {
+        w.write_record([
+            c.rustsec_id.clone(),
+            "changed".to_string(),
+            c.metric.to_string(),
+            format_float(c.old),
+            format_float(c.new),
+            format_float(c.delta()),
+        ])?;
+    }
+    w.flush()?;
+
+    let changed_advisories: BTreeMap<&str, usize> =
+        changes.iter().fold(BTreeMap::new(), |mut m, c| {
+            *m.entry(c.rustsec_id.as_str()).or_default() += 1;
+            m
+        });
+
+    ensure_parent_dir(&args.digest_output)?;
+    let mut f = File::create(&args.digest_output)?;
+    writeln!(f, "summary diff")?;
+    writeln!(f, "old = {} ({} advisories)", args.old, old.rows.len())?;
+    writeln!(f, "new = {} ({} advisories)", args.new, new.rows.len())?;
+    writeln!(
+        f,
+        "tolerance: {} = {}, lag = {}",
+        COUNT_COLUMN,
+        args.count_tolerance,
+        format_float(args.lag_tolerance)
+    )?;
+    if old.duplicates > 0 || new.duplicates > 0 {
+        writeln!(
+            f,
+            "duplicate rustsec_id rows (last one kept): old={} new={}",
+            old.duplicates, new.duplicates
+        )?;
+    }
+    writeln!(f)?;
+    writeln!(f, "added advisories = {}", added.len())?;
+    writeln!(f, "removed advisories = {}", removed.len())?;
+    writeln!(f, "changed advisories = {}", changed_advisories.len())?;
+    if !unit_mismatches.is_empty() {
+        writeln!(
+            f,
+            "lag_unit differs for {} advisories; their lag deltas mix units",
+            unit_mismatches.len()
+        )?;
+    }
+
+    let list = |f: &mut File, heading: &str, ids: &[&String]| -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "{heading}:")?;
+        for id in ids {
+            writeln!(f, "  {id}")?;
+        }
+        Ok(())
+    };
+    list(&mut f, "added", &added)?;
+    list(&mut f, "removed", &removed)?;
+
+    if !changes.is_empty() {
+        writeln!(f)?;
+        writeln!(f, "changed (largest |delta| first per metric):")?;
+        for metric in std::iter::once(COUNT_COLUMN).chain(LAG_COLUMNS) {
+            let mut of_metric: Vec<&Change> =
+                changes.iter().filter(|c| c.metric == metric).collect();
+            if of_metric.is_empty() {
+                continue;
+            }
+            of_metric.sort_by(|a, b| {
+                b.delta()
+                    .abs()
+                    .total_cmp(&a.delta().abs())
+                    .then_with(|| a.rustsec_id.cmp(&b.rustsec_id))
+            });
+            writeln!(f, "  {metric} ({} advisories)", of_metric.len())?;
+            for c in of_metric {
+                let sign = if c.delta() > 0.0 { "+" } else { "" };
+                writeln!(
+                    f,
+                    "    {} {} -> {} ({}{})",
+                    c.rustsec_id,
+                    format_float(c.old),
+                    format_float(c.new),
+                    sign,
+                    format_float(c.delta())
+                )?;
+            }
+        }
+    }
+
+    println!(
+        "added={} removed={} changed={} (wrote {} and {})",
+        added.len(),
+        removed.len(),
+        changed_advisories.len(),
+        args.output.display(),
+        args.digest_output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,1437 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::stats::compute_lag_stats;
+
+// Size, text scale and colors of a chart; a run builds one from the --chart-* flags and
+// hands it to every chart it draws.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartStyle {
+    // None keeps each chart's own default size.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub font_scale: f64,
+    // None keeps each chart's own colors.
+    pub palette: Option<Vec<String>>,
+}
+
+impl Default for ChartStyle {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            font_scale: 1.0,
+            palette: None,
+        }
+    }
+}
+
+// Named palettes for --chart-palette; `default` keeps the built-in colors.
+pub const PALETTES: [(&str, &[&str]); 3] = [
+    // Okabe-Ito, distinguishable with the common color vision deficiencies.
+    (
+        "colorblind",
+        &[
+            "#0072B2", "#E69F00", "#009E73", "#D55E00", "#CC79A7", "#56B4E9", "#F0E442",
+        ],
+    ),
+    (
+        "grayscale",
+        &[
+            "#252525", "#969696", "#525252", "#BDBDBD", "#737373", "#D9D9D9",
+        ],
+    ),
+    (
+        "viridis",
+        &["#440154", "#3B528B", "#21908C", "#5DC863", "#FDE725"],
+    ),
+];
+
+impl ChartStyle {
+    // `palette` is `default`, a name from PALETTES, or a comma-separated list of
+    // `#rgb` / `#rrggbb` colors.
+    pub fn new(
+        width: Option<u32>,
+        height: Option<u32>,
+        font_scale: f64,
+        palette: &str,
+    ) -> Result<Self> {
+        if !(0.5..=4.0).contains(&font_scale) {
+            return Err(anyhow!(
+                "--chart-font-scale {font_scale}: must be between 0.5 and 4"
+            ));
+        }
+        // The margins grow with the text, so small canvases only fit small text.
+        for (flag, value, min) in [
+            ("--chart-width", width, 240.0),
+            ("--chart-height", height, 180.0),
+        ] {
+            if let Some(v) = value
+                && (v as f64) < min * font_scale
+            {
+                return Err(anyhow!(
+                    "{flag} {v}: must be at least {} at --chart-font-scale {font_scale}",
+                    (min * font_scale).ceil()
+                ));
+            }
+        }
+        let palette = match palette {
+            "default" => None,
+            name if !name.contains('#') => {
+                let (_, colors) = PALETTES.iter().find(|(n, _)| *n == name).ok_or_else(|| {
+                    anyhow!(
+                        "--chart-palette {name}: expected default, {}, or a list of #rrggbb colors",
+                        PALETTES.map(|(n, _)| n).join(", ")
+                    )
+                })?;
+                Some(colors.iter().map(|c| c.to_string()).collect())
+            }
+            list => Some(
+                list.split(',')
+                    .map(|c| {
+                        let c = c.trim();
+                        let hex = c.strip_prefix('#').unwrap_or("");
+                        if matches!(hex.len(), 3 | 6) && hex.chars().all(|h| h.is_ascii_hexdigit())
+                        {
+                            Ok(c.to_string())
+                        } else {
+                            Err(anyhow!(
+                                "--chart-palette: {c:?} is not a #rgb or #rrggbb color"
+                            ))
+                        }
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        Ok(Self {
+            width,
+            height,
+            font_scale,
+            palette,
+        })
+    }
+
+    // The configured palette, or `default` (the chart's own colors) when there is none.
+    fn colors(&self, default: &[&str]) -> Vec<String> {
+        match &self.palette {
+            Some(p) => p.clone(),
+            None => default.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    // Canvas size and text metrics for a chart whose default size is `w` x `h`. Text
+    // sizes and the offsets of text from the plot scale together, as do the margins
+    // that hold text, so larger text pushes the plot inwards instead of being clipped.
+    fn layout(&self, w: f64, h: f64) -> Layout {
+        let s = self.font_scale;
+        // Font sizes are printed as is, so keep them to one decimal.
+        let size = |v: f64| (v * s * 10.0).round() / 10.0;
+        Layout {
+            s,
+            w: self.width.map_or(w, f64::from),
+            h: self.height.map_or(h, f64::from),
+            title_size: size(18.0),
+            text_size: size(12.0),
+            label_size: size(14.0),
+            small_size: size(11.0),
+            title_y: 28.0 * s,
+            subtitle_y: 48.0 * s,
+            label_x: 18.0 * s,
+            swatch: 12.0 * s,
+        }
+    }
+}
+
+struct Layout {
+    s: f64,
+    w: f64,
+    h: f64,
+    title_size: f64,
+    text_size: f64,
+    label_size: f64,
+    small_size: f64,
+    title_y: f64,
+    subtitle_y: f64,
+    label_x: f64,
+    swatch: f64,
+}
+
+// The chart's data next to the SVG, as the same file name with a `.csv` extension.
+fn write_data_csv(svg_path: &Path, header: &[&str], rows: &[Vec<String>]) -> Result<()> {
+    let mut w = csv::Writer::from_path(svg_path.with_extension("csv"))?;
+    w.write_record(header)?;
+    for row in rows {
+        w.write_record(row)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+pub struct HistogramChart<'a> {
+    values: &'a [f64],
+    bins: usize,
+    x_max: Option<f64>,
+    overflow_bin: bool,
+    log_y: bool,
+    title: String,
+    subtitle: String,
+    x_label: String,
+    data_csv: bool,
+    style: ChartStyle,
+}
+
+impl<'a> HistogramChart<'a> {
+    pub fn new(values: &'a [f64]) -> Self {
+        Self {
+            values,
+            bins: 60,
+            x_max: None,
+            overflow_bin: false,
+            log_y: false,
+            title: String::new(),
+            subtitle: String::new(),
+            x_label: String::new(),
+            data_csv: true,
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    // Also write the plotted data next to the SVG; on by default.
+    pub fn data_csv(mut self, data_csv: bool) -> Self {
+        self.data_csv = data_csv;
+        self
+    }
+
+    pub fn bins(mut self, bins: usize) -> Self {
+        self.bins = bins;
+        self
+    }
+
+    // Defaults to the largest value when not set.
+    pub fn x_max(mut self, x_max: f64) -> Self {
+        self.x_max = Some(x_max);
+        self
+    }
+
+    // Values above x_max get their own `>x_max` bar instead of piling into the last bin.
+    pub fn overflow_bin(mut self, overflow_bin: bool) -> Self {
+        self.overflow_bin = overflow_bin;
+        self
+    }
+
+    pub fn log_y(mut self, log_y: bool) -> Self {
+        self.log_y = log_y;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn x_label(mut self, x_label: impl Into<String>) -> Self {
+        self.x_label = x_label.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        let bins = self.bins.max(1);
+        let x_max = self
+            .x_max
+            .unwrap_or_else(|| self.values.iter().copied().fold(1.0, f64::max))
+            .max(1.0);
+        let log_y = self.log_y;
+        let (counts, overflow) = if self.overflow_bin {
+            histogram_counts_with_overflow(self.values, bins, x_max)
+        } else {
+            (histogram_counts(self.values, bins, x_max), 0)
+        };
+
+        let scale = |c: usize| {
+            if !log_y {
+                c as f64
+            } else if c > 0 {
+                (c as f64).log10()
+            } else {
+                0.0
+            }
+        };
+        let y_values: Vec<f64> = counts.iter().map(|&c| scale(c)).collect();
+        let y_overflow = scale(overflow);
+
+        // Bins are [start, end); the overflow bin has no end.
+        let bin_w = x_max / bins as f64;
+        let mut rows: Vec<Vec<String>> = counts
+            .iter()
+            .zip(&y_values)
+            .enumerate()
+            .map(|(i, (c, y))| {
+                let mut row = vec![
+                    (i as f64 * bin_w).to_string(),
+                    ((i + 1) as f64 * bin_w).to_string(),
+                    c.to_string(),
+                ];
+                if log_y {
+                    row.push(y.to_string());
+                }
+                row
+            })
+            .collect();
+        if self.overflow_bin {
+            let mut row = vec![x_max.to_string(), String::new(), overflow.to_string()];
+            if log_y {
+                row.push(y_overflow.to_string());
+            }
+            rows.push(row);
+        }
+        let header: &[&str] = if log_y {
+            &["bin_start", "bin_end", "count", "log10_count"]
+        } else {
+            &["bin_start", "bin_end", "count"]
+        };
+        if self.data_csv {
+            write_data_csv(path.as_ref(), header, &rows)?;
+        }
+
+        let y_max = y_values.iter().copied().fold(y_overflow, f64::max).max(1.0);
+
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            ..
+        } = style.layout(960.0, 540.0);
+        let ml = 70.0 * s;
+        let mr = 20.0;
+        let mt = 20.0 * s;
+        let mb = 60.0 * s;
+        let plot_w = (w - ml - mr).max(1.0);
+        let plot_h = (h - mt - mb).max(1.0);
+        let x0 = ml;
+        let y0 = mt;
+        let x1 = x0 + plot_w;
+        let y1 = y0 + plot_h;
+        let slots = if self.overflow_bin { bins + 1 } else { bins };
+        let bar_w = plot_w / slots as f64;
+        let data_w = bar_w * bins as f64;
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let fills = style.colors(&["#4C78A8", "#E45756"]);
+        let fill = &fills[0];
+        let overflow_fill = &fills[1 % fills.len()];
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let y_ticks = nice_ticks(y_max, 6);
+        let x_ticks = nice_ticks(x_max, 7);
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        for t in y_ticks {
+            let y = y1 - (t / y_max) * plot_h;
+            parts.push(format!(
+                r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            let label_val = if log_y {
+                format!("{:.1}", t)
+            } else {
+                format!("{:.0}", t)
+            };
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x0 - 10.0 * s,
+                ytext = y + 4.0 * s,
+                label = svg_escape(&label_val)
+            ));
+        }
+
+        for t in x_ticks {
+            if self.overflow_bin && t > x_max + 1e-9 {
+                continue;
+            }
+            let x = x0 + (t / x_max) * data_w;
+            parts.push(format!(
+                r#"<line x1="{x:.2}" y1="{y0:.2}" x2="{x:.2}" y2="{y1:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                ytext = y1 + 20.0 * s,
+                label = svg_escape(&format!("{:.0}", t))
+            ));
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (i, &v) in y_values.iter().enumerate() {
+            let bh = (v / y_max) * plot_h;
+            let x = x0 + i as f64 * bar_w;
+            let y = y1 - bh;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
+                bw = (bar_w - 1.0).max(0.0)
+            ));
+        }
+
+        if self.overflow_bin {
+            let bh = (y_overflow / y_max) * plot_h;
+            let x = x0 + data_w;
+            let y = y1 - bh;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{overflow_fill}"/>"#,
+                bw = (bar_w - 1.0).max(0.0)
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{overflow_fill}">{label}</text>"#,
+                x = x + bar_w / 2.0,
+                ytext = y1 + 36.0 * s,
+                label = svg_escape(&format!(">{x_max:.0}"))
+            ));
+        }
+
+        let subtitle = if self.overflow_bin {
+            format!("{}, clipped={overflow} (>{x_max:.0})", self.subtitle)
+        } else {
+            self.subtitle.clone()
+        };
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}">{lbl}</text>"#,
+            x = w / 2.0,
+            y = h - 20.0 * s,
+            lbl = svg_escape(&self.x_label)
+        ));
+
+        let y_label = if log_y { "count (log10)" } else { "count" };
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">{lbl}</text>"#,
+            y = h / 2.0,
+            lbl = y_label
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub struct BarChart<'a> {
+    categories: &'a [(&'a str, usize)],
+    title: String,
+    subtitle: String,
+    data_csv: bool,
+    style: ChartStyle,
+}
+
+impl<'a> BarChart<'a> {
+    pub fn new(categories: &'a [(&'a str, usize)]) -> Self {
+        Self {
+            categories,
+            title: String::new(),
+            subtitle: String::new(),
+            data_csv: true,
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    // Also write the plotted data next to the SVG; on by default.
+    pub fn data_csv(mut self, data_csv: bool) -> Self {
+        self.data_csv = data_csv;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        let rows: Vec<Vec<String>> = self
+            .categories
+            .iter()
+            .map(|(name, v)| vec![name.to_string(), v.to_string()])
+            .collect();
+        if self.data_csv {
+            write_data_csv(path.as_ref(), &["category", "count"], &rows)?;
+        }
+
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            ..
+        } = style.layout(960.0, 520.0);
+        let margin = 70.0 * s;
+        let plot_w = (w - margin * 2.0).max(1.0);
+        let plot_h = (h - margin * 2.0).max(1.0);
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let fills = style.colors(&["#4C78A8"]);
+        let fill = &fills[0];
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let categories = self.categories;
+        let max_v = categories.iter().map(|(_, v)| *v).max().unwrap_or(1).max(1) as f64;
+        let bar_w = plot_w / categories.len().max(1) as f64;
+
+        let x0 = margin;
+        let y0 = margin;
+        let x1 = w - margin;
+        let y1 = h - margin;
+
+        let y_ticks = nice_ticks(max_v, 6);
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        for t in y_ticks {
+            let y = y1 - (t / max_v) * plot_h;
+            parts.push(format!(
+                r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x0 - 10.0 * s,
+                ytext = y + 4.0 * s,
+                label = svg_escape(&format!("{t:.0}"))
+            ));
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (i, (name, v)) in categories.iter().enumerate() {
+            let v = *v as f64;
+            let bh = (v / max_v) * plot_h;
+            let x = x0 + i as f64 * bar_w;
+            let y = y1 - bh;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
+                bw = (bar_w - 8.0).max(0.0)
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x + bar_w / 2.0 - 4.0,
+                y = y1 + 22.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">count</text>"#,
+            y = h / 2.0
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub struct StackedBarChart<'a> {
+    categories: &'a [(&'a str, Vec<usize>)],
+    series: &'a [&'a str],
+    title: String,
+    subtitle: String,
+    style: ChartStyle,
+}
+
+impl<'a> StackedBarChart<'a> {
+    // Each category holds one value per series, stacked bottom-up in series order.
+    pub fn new(categories: &'a [(&'a str, Vec<usize>)], series: &'a [&'a str]) -> Self {
+        Self {
+            categories,
+            series,
+            title: String::new(),
+            subtitle: String::new(),
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            swatch,
+            ..
+        } = style.layout(960.0, 520.0);
+        let margin = 70.0 * s;
+        let plot_w = (w - margin * 2.0).max(1.0);
+        let plot_h = (h - margin * 2.0).max(1.0);
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let fills = style.colors(&["#4C78A8", "#BAB0AC", "#E45756", "#54A24B", "#F58518"]);
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let categories = self.categories;
+        let max_v = categories
+            .iter()
+            .map(|(_, vs)| vs.iter().sum::<usize>())
+            .max()
+            .unwrap_or(1)
+            .max(1) as f64;
+        let bar_w = plot_w / categories.len().max(1) as f64;
+
+        let x0 = margin;
+        let y0 = margin;
+        let x1 = w - margin;
+        let y1 = h - margin;
+
+        let y_ticks = nice_ticks(max_v, 6);
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        for t in y_ticks {
+            let y = y1 - (t / max_v) * plot_h;
+            parts.push(format!(
+                r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x0 - 10.0 * s,
+                ytext = y + 4.0 * s,
+                label = svg_escape(&format!("{t:.0}"))
+            ));
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (i, (name, values)) in categories.iter().enumerate() {
+            let x = x0 + i as f64 * bar_w;
+            let mut y = y1;
+            for (j, v) in values.iter().enumerate() {
+                let bh = (*v as f64 / max_v) * plot_h;
+                y -= bh;
+                parts.push(format!(
+                    r#"<rect x="{x:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
+                    bw = (bar_w - 8.0).max(0.0),
+                    fill = fills[j % fills.len()]
+                ));
+            }
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x + bar_w / 2.0 - 4.0,
+                y = y1 + 22.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        for (j, name) in self.series.iter().enumerate() {
+            let ly = y0 + (4.0 + j as f64 * 18.0) * s;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{ly:.2}" width="{swatch:.2}" height="{swatch:.2}" fill="{fill}"/>"#,
+                x = x1 - 140.0 * s,
+                fill = fills[j % fills.len()]
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x1 - 122.0 * s,
+                y = ly + 10.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">count</text>"#,
+            y = h / 2.0
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub struct GroupedBarChart<'a> {
+    categories: &'a [(&'a str, Vec<usize>)],
+    series: &'a [&'a str],
+    title: String,
+    subtitle: String,
+    y_label: String,
+    style: ChartStyle,
+}
+
+impl<'a> GroupedBarChart<'a> {
+    // Each category holds one value per series, drawn side by side in series order.
+    pub fn new(categories: &'a [(&'a str, Vec<usize>)], series: &'a [&'a str]) -> Self {
+        Self {
+            categories,
+            series,
+            title: String::new(),
+            subtitle: String::new(),
+            y_label: "count".to_string(),
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn y_label(mut self, y_label: impl Into<String>) -> Self {
+        self.y_label = y_label.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            swatch,
+            ..
+        } = style.layout(960.0, 520.0);
+        let margin = 70.0 * s;
+        let plot_w = (w - margin * 2.0).max(1.0);
+        let plot_h = (h - margin * 2.0).max(1.0);
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let fills = style.colors(&["#4C78A8", "#BAB0AC", "#E45756", "#54A24B", "#F58518"]);
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let categories = self.categories;
+        let max_v = categories
+            .iter()
+            .flat_map(|(_, vs)| vs.iter().copied())
+            .max()
+            .unwrap_or(1)
+            .max(1) as f64;
+        let bar_w = plot_w / categories.len().max(1) as f64;
+
+        let x0 = margin;
+        let y0 = margin;
+        let x1 = w - margin;
+        let y1 = h - margin;
+
+        let y_ticks = nice_ticks(max_v, 6);
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        for t in y_ticks {
+            let y = y1 - (t / max_v) * plot_h;
+            parts.push(format!(
+                r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x0 - 10.0 * s,
+                ytext = y + 4.0 * s,
+                label = svg_escape(&format!("{t:.0}"))
+            ));
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (i, (name, values)) in categories.iter().enumerate() {
+            let x = x0 + i as f64 * bar_w;
+            let sub_w = (bar_w - 8.0).max(0.0) / self.series.len().max(1) as f64;
+            for (j, v) in values.iter().enumerate() {
+                let bh = (*v as f64 / max_v) * plot_h;
+                parts.push(format!(
+                    r#"<rect x="{bx:.2}" y="{y:.2}" width="{bw:.2}" height="{bh:.2}" fill="{fill}"/>"#,
+                    bx = x + j as f64 * sub_w,
+                    y = y1 - bh,
+                    bw = (sub_w - 1.0).max(0.0),
+                    fill = fills[j % fills.len()]
+                ));
+            }
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x + bar_w / 2.0 - 4.0,
+                y = y1 + 22.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        for (j, name) in self.series.iter().enumerate() {
+            let ly = y0 + (4.0 + j as f64 * 18.0) * s;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{ly:.2}" width="{swatch:.2}" height="{swatch:.2}" fill="{fill}"/>"#,
+                x = x1 - 140.0 * s,
+                fill = fills[j % fills.len()]
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x1 - 122.0 * s,
+                y = ly + 10.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">{label}</text>"#,
+            y = h / 2.0,
+            label = svg_escape(&self.y_label)
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub struct StepCurveChart<'a> {
+    xs: &'a [f64],
+    series: &'a [(String, Vec<f64>)],
+    title: String,
+    subtitle: String,
+    x_label: String,
+    style: ChartStyle,
+}
+
+impl<'a> StepCurveChart<'a> {
+    // One right-continuous step curve per series, valued in [0, 1] at each of the
+    // ascending `xs`; series are overlaid in order.
+    pub fn new(xs: &'a [f64], series: &'a [(String, Vec<f64>)]) -> Self {
+        Self {
+            xs,
+            series,
+            title: String::new(),
+            subtitle: String::new(),
+            x_label: String::new(),
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn x_label(mut self, x_label: impl Into<String>) -> Self {
+        self.x_label = x_label.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            swatch,
+            ..
+        } = style.layout(960.0, 520.0);
+        let margin = 70.0 * s;
+        let plot_w = (w - margin * 2.0).max(1.0);
+        let plot_h = (h - margin * 2.0).max(1.0);
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let strokes = style.colors(&[
+            "#4C78A8", "#E45756", "#F58518", "#54A24B", "#B279A2", "#BAB0AC",
+        ]);
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let x_max = self.xs.iter().copied().fold(1.0, f64::max);
+        let x0 = margin;
+        let y0 = margin;
+        let x1 = w - margin;
+        let y1 = h - margin;
+        let to_x = |v: f64| x0 + (v.max(0.0) / x_max) * plot_w;
+        let to_y = |v: f64| y1 - v.clamp(0.0, 1.0) * plot_h;
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        for i in 0..=5 {
+            let t = i as f64 / 5.0;
+            let y = to_y(t);
+            parts.push(format!(
+                r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x0 - 10.0 * s,
+                ytext = y + 4.0 * s,
+                label = svg_escape(&format!("{:.0}%", t * 100.0))
+            ));
+        }
+        for &v in self.xs {
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = to_x(v),
+                y = y1 + 20.0 * s,
+                label = svg_escape(&format!("{v}"))
+            ));
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (j, (name, ys)) in self.series.iter().enumerate() {
+            let stroke = &strokes[j % strokes.len()];
+            // Flat from each grid point to the next, then up to the next value.
+            let mut points = Vec::new();
+            for (i, (&x, &y)) in self.xs.iter().zip(ys).enumerate() {
+                if !y.is_finite() {
+                    continue;
+                }
+                points.push(format!("{:.2},{:.2}", to_x(x), to_y(y)));
+                let next_x = self.xs.get(i + 1).copied().unwrap_or(x_max);
+                points.push(format!("{:.2},{:.2}", to_x(next_x), to_y(y)));
+            }
+            if !points.is_empty() {
+                parts.push(format!(
+                    r#"<polyline points="{pts}" fill="none" stroke="{stroke}" stroke-width="2"/>"#,
+                    pts = points.join(" ")
+                ));
+            }
+            let ly = y0 + (4.0 + j as f64 * 18.0) * s;
+            parts.push(format!(
+                r#"<rect x="{x:.2}" y="{ly:.2}" width="{swatch:.2}" height="{swatch:.2}" fill="{stroke}"/>"#,
+                x = x1 - 180.0 * s
+            ));
+            parts.push(format!(
+                r#"<text x="{x:.2}" y="{y:.2}" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                x = x1 - 162.0 * s,
+                y = ly + 10.0 * s,
+                label = svg_escape(name)
+            ));
+        }
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}">{lbl}</text>"#,
+            x = w / 2.0,
+            y = h - 20.0 * s,
+            lbl = svg_escape(&self.x_label)
+        ));
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">adopted</text>"#,
+            y = h / 2.0
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub struct BoxPlotChart<'a> {
+    groups: &'a [(String, Vec<f64>)],
+    min_samples: usize,
+    max_outliers: usize,
+    title: String,
+    subtitle: String,
+    y_label: String,
+    style: ChartStyle,
+}
+
+impl<'a> BoxPlotChart<'a> {
+    pub fn new(groups: &'a [(String, Vec<f64>)]) -> Self {
+        Self {
+            groups,
+            min_samples: 1,
+            max_outliers: 100,
+            title: String::new(),
+            subtitle: String::new(),
+            y_label: String::new(),
+            style: ChartStyle::default(),
+        }
+    }
+
+    pub fn style(mut self, style: &ChartStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    // Groups with fewer samples are still drawn, but greyed out.
+    pub fn min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    pub fn max_outliers(mut self, max_outliers: usize) -> Self {
+        self.max_outliers = max_outliers;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    pub fn y_label(mut self, y_label: impl Into<String>) -> Self {
+        self.y_label = y_label.into();
+        self
+    }
+
+    pub fn write_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        // Box at p25/p50/p75, whiskers at p5/p95, anything beyond drawn as dots.
+        let mut boxes = Vec::new();
+        for (name, values) in self.groups {
+            let Some(stats) = compute_lag_stats(values.iter().copied()) else {
+                continue;
+            };
+            let mut outliers: Vec<f64> = values
+                .iter()
+                .copied()
+                .filter(|&v| v < stats.p5 || v > stats.p95)
+                .collect();
+            outliers.sort_unstable_by(f64::total_cmp);
+            if outliers.len() > self.max_outliers {
+                let step = outliers.len() as f64 / self.max_outliers.max(1) as f64;
+                outliers = (0..self.max_outliers)
+                    .map(|i| outliers[(i as f64 * step) as usize])
+                    .collect();
+            }
+            boxes.push((name.as_str(), stats, outliers));
+        }
+
+        let y_min = boxes
+            .iter()
+            .flat_map(|(_, st, out)| out.iter().copied().chain([st.p5]))
+            .fold(0.0, f64::min);
+        let y_max = boxes
+            .iter()
+            .flat_map(|(_, st, out)| out.iter().copied().chain([st.p95]))
+            .fold(1.0, f64::max);
+        let y_span = (y_max - y_min).max(1.0);
+
+        let style = &self.style;
+        let Layout {
+            s,
+            w,
+            h,
+            title_size,
+            text_size,
+            label_size,
+            small_size,
+            title_y,
+            subtitle_y,
+            label_x,
+            ..
+        } = style.layout(960.0, 540.0);
+        let ml = 70.0 * s;
+        let mr = 20.0;
+        let mt = 60.0 * s;
+        let mb = 70.0 * s;
+        let plot_w = (w - ml - mr).max(1.0);
+        let plot_h = (h - mt - mb).max(1.0);
+        let x0 = ml;
+        let y0 = mt;
+        let x1 = x0 + plot_w;
+        let y1 = y0 + plot_h;
+        let slot_w = plot_w / boxes.len().max(1) as f64;
+        let box_w = (slot_w * 0.5).min(80.0);
+        let to_y = |v: f64| y1 - ((v - y_min) / y_span) * plot_h;
+
+        let axis = "#222222";
+        let grid = "#E6E6E6";
+        let fills = style.colors(&["#4C78A8"]);
+        let fill = fills[0].as_str();
+        let muted = "#BBBBBB";
+        let font = "system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif";
+
+        let mut parts = Vec::new();
+        parts.push(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w_i}" height="{h_i}" viewBox="0 0 {w_i} {h_i}">"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+        parts.push(format!(
+            r#"<rect x="0" y="0" width="{w_i}" height="{h_i}" fill="white"/>"#,
+            w_i = w as i64,
+            h_i = h as i64
+        ));
+
+        let tick_step = nice_ticks(y_span, 6).get(1).copied().unwrap_or(y_span);
+        let mut t = (y_min / tick_step).floor() * tick_step;
+        while t <= y_max + 1e-9 {
+            let y = to_y(t);
+            if y >= y0 - 1e-9 && y <= y1 + 1e-9 {
+                parts.push(format!(
+                    r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}" stroke="{grid}" stroke-width="1"/>"#
+                ));
+                parts.push(format!(
+                    r#"<text x="{x:.2}" y="{ytext:.2}" text-anchor="end" font-family="{font}" font-size="{text_size}" fill="{axis}">{label}</text>"#,
+                    x = x0 - 10.0 * s,
+                    ytext = y + 4.0 * s,
+                    label = svg_escape(&format!("{t:.0}"))
+                ));
+            }
+            t += tick_step;
+        }
+
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y1:.2}" x2="{x1:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+        parts.push(format!(
+            r#"<line x1="{x0:.2}" y1="{y0:.2}" x2="{x0:.2}" y2="{y1:.2}" stroke="{axis}" stroke-width="1.5"/>"#
+        ));
+
+        for (i, (name, st, outliers)) in boxes.iter().enumerate() {
+            let cx = x0 + (i as f64 + 0.5) * slot_w;
+            let bx = cx - box_w / 2.0;
+            let enough = st.count >= self.min_samples;
+            let (box_fill, stroke) = if enough { (fill, axis) } else { (muted, muted) };
+
+            parts.push(format!(
+                r#"<line x1="{cx:.2}" y1="{ya:.2}" x2="{cx:.2}" y2="{yb:.2}" stroke="{stroke}" stroke-width="1"/>"#,
+                ya = to_y(st.p95),
+                yb = to_y(st.p75)
+            ));
+            parts.push(format!(
+                r#"<line x1="{cx:.2}" y1="{ya:.2}" x2="{cx:.2}" y2="{yb:.2}" stroke="{stroke}" stroke-width="1"/>"#,
+                ya = to_y(st.p25),
+                yb = to_y(st.p5)
+            ));
+            for v in [st.p5, st.p95] {
+                parts.push(format!(
+                    r#"<line x1="{xa:.2}" y1="{y:.2}" x2="{xb:.2}" y2="{y:.2}" stroke="{stroke}" stroke-width="1"/>"#,
+                    xa = cx - box_w / 4.0,
+                    xb = cx + box_w / 4.0,
+                    y = to_y(v)
+                ));
+            }
+            parts.push(format!(
+                r#"<rect x="{bx:.2}" y="{y:.2}" width="{box_w:.2}" height="{bh:.2}" fill="{box_fill}" fill-opacity="0.6" stroke="{stroke}" stroke-width="1"/>"#,
+                y = to_y(st.p75),
+                bh = (to_y(st.p25) - to_y(st.p75)).max(0.5)
+            ));
+            parts.push(format!(
+                r#"<line x1="{bx:.2}" y1="{y:.2}" x2="{xb:.2}" y2="{y:.2}" stroke="{stroke}" stroke-width="2"/>"#,
+                xb = bx + box_w,
+                y = to_y(st.p50)
+            ));
+            for &v in outliers {
+                parts.push(format!(
+                    r#"<circle cx="{cx:.2}" cy="{y:.2}" r="2" fill="none" stroke="{stroke}" stroke-width="1"/>"#,
+                    y = to_y(v)
+                ));
+            }
+            parts.push(format!(
+                r#"<text x="{cx:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{label_fill}">{label}</text>"#,
+                y = y1 + 20.0 * s,
+                label_fill = if enough { axis } else { muted },
+                label = svg_escape(name)
+            ));
+            parts.push(format!(
+                r#"<text x="{cx:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{small_size}" fill="{label_fill}">n={n}</text>"#,
+                y = y1 + 36.0 * s,
+                label_fill = if enough { axis } else { muted },
+                n = st.count
+            ));
+        }
+
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{title_y:.2}" text-anchor="middle" font-family="{font}" font-size="{title_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.title)
+        ));
+        parts.push(format!(
+            r#"<text x="{x:.2}" y="{subtitle_y:.2}" text-anchor="middle" font-family="{font}" font-size="{text_size}" fill="{axis}">{t}</text>"#,
+            x = w / 2.0,
+            t = svg_escape(&self.subtitle)
+        ));
+        parts.push(format!(
+            r#"<text x="{label_x:.2}" y="{y:.2}" text-anchor="middle" font-family="{font}" font-size="{label_size}" fill="{axis}" transform="rotate(-90 {label_x:.2} {y:.2})">{lbl}</text>"#,
+            y = h / 2.0,
+            lbl = svg_escape(&self.y_label)
+        ));
+        parts.push("</svg>\n".to_string());
+
+        std::fs::write(path.as_ref(), parts.join("\n"))?;
+        Ok(())
+    }
+}
+
+pub fn svg_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+pub fn nice_ticks(max_value: f64, tick_count: usize) -> Vec<f64> {
+    if !max_value.is_finite() || max_value <= 0.0 {
+        return vec![0.0];
+    }
+    let tick_count = tick_count.max(2);
+    let raw_step = max_value / (tick_count as f64 - 1.0);
+    let exp = raw_step.log10().floor();
+    let base = 10f64.powf(exp);
+    let frac = raw_step / base;
+    let step = if frac <= 1.0 {
+        1.0 * base
+    } else if frac <= 2.0 {
+        2.0 * base
+    } else if frac <= 5.0 {
+        5.0 * base
+    } else {
+        10.0 * base
+    };
+    let top = (max_value / step).ceil() * step;
+    let mut ticks = Vec::new();
+    let mut v = 0.0;
+    while v <= top + 1e-9 {
+        ticks.push(v);
+        v += step;
+    }
+    ticks
+}
+
+pub fn histogram_counts(values: &[f64], bins: usize, x_max: f64) -> Vec<usize> {
+    let bins = bins.max(1);
+    let x_max = x_max.max(1.0);
+    let w = x_max / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for &v in values {
+        if v.is_nan() || v < 0.0 {
+            continue;
+        }
+        let mut idx = (v / w).floor() as isize;
+        if idx < 0 {
+            idx = 0;
+        }
+        if idx as usize >= bins {
+            idx = bins as isize - 1;
+        }
+        counts[idx as usize] += 1;
+    }
+    counts
+}
+
+// Like `histogram_counts`, but values above `x_max` are returned as a separate
+// overflow count instead of being folded into the last bin. NaN is dropped rather
+// than counted as overflow.
+pub fn histogram_counts_with_overflow(
+    values: &[f64],
+    bins: usize,
+    x_max: f64,
+) -> (Vec<usize>, usize) {
+    let x_max = x_max.max(1.0);
+    let (inside, outside): (Vec<f64>, Vec<f64>) = values
+        .iter()
+        .filter(|v| !v.is_nan())
+        .partition(|&&v| v <= x_max);
+    (histogram_counts(&inside, bins, x_max), outside.len())
+}
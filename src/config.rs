@@ -0,0 +1,114 @@
+use std::ffi::OsString;
+
+use anyhow::{Result, anyhow};
+use clap::{ArgAction, CommandFactory, FromArgMatches, parser::ValueSource};
+use toml::Value;
+
+// Keys that only make sense on the command line.
+const RESERVED_KEYS: [&str; 4] = ["config", "print_config", "help", "version"];
+
+// Parses the process arguments, taking defaults from the TOML file named by `--config`.
+// Keys are the `Args` field names (`summary_output`, dashes also accepted); flags given
+// on the command line win over the file, and unknown keys are an error.
+pub fn parse_args<T: CommandFactory + FromArgMatches>() -> Result<T> {
+    parse_args_from(std::env::args_os().collect())
+}
+
+pub fn parse_args_from<T: CommandFactory + FromArgMatches>(argv: Vec<OsString>) -> Result<T> {
+    let argv = with_config_defaults::<T>(argv)?;
+    let matches = T::command().get_matches_from(argv);
+    Ok(T::from_arg_matches(&matches)?)
+}
+
+// Like `parse_args`, but usage errors come back as errors instead of exiting the process;
+// --help and --version still print and exit 0.
+pub fn try_parse_args<T: CommandFactory + FromArgMatches>() -> Result<T> {
+    try_parse_args_from(std::env::args_os().collect())
+}
+
+pub fn try_parse_args_from<T: CommandFactory + FromArgMatches>(argv: Vec<OsString>) -> Result<T> {
+    let argv = with_config_defaults::<T>(argv)?;
+    let matches = match T::command().try_get_matches_from(argv) {
+        Ok(m) => m,
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => return Err(anyhow!("{}", e.render().to_string().trim_end())),
+    };
+    Ok(T::from_arg_matches(&matches)?)
+}
+
+// `argv` with the flags of the `--config` file inserted before the command-line ones.
+fn with_config_defaults<T: CommandFactory>(argv: Vec<OsString>) -> Result<Vec<OsString>> {
+    let command = T::command();
+    // A first lenient pass only finds the config path and which flags were typed;
+    // any real usage error is reported by the final parse below.
+    let first = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&argv)
+        .ok();
+    let config_path = first
+        .as_ref()
+        .and_then(|m| m.try_get_one::<String>("config").ok().flatten().cloned());
+
+    let mut merged = argv.clone();
+    if let (Some(path), Some(first)) = (config_path, first) {
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("cannot read config {path}: {e}"))?;
+        let table: toml::Table =
+            toml::from_str(&text).map_err(|e| anyhow!("cannot parse config {path}: {e}"))?;
+
+        let mut from_file: Vec<OsString> = Vec::new();
+        for (key, value) in &table {
+            let id = key.replace('-', "_");
+            let arg = command
+                .get_arguments()
+                .find(|a| a.get_id() == id.as_str() && !RESERVED_KEYS.contains(&id.as_str()))
+                .ok_or_else(|| anyhow!("unknown key `{key}` in config {path}"))?;
+            if first.value_source(&id) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+            let long = arg
+                .get_long()
+                .ok_or_else(|| anyhow!("key `{key}` in config {path} has no long flag"))?;
+            if matches!(arg.get_action(), ArgAction::SetTrue) {
+                match value {
+                    Value::Boolean(true) => from_file.push(format!("--{long}").into()),
+                    Value::Boolean(false) => {}
+                    _ => return Err(anyhow!("key `{key}` in config {path} must be a boolean")),
+                }
+                continue;
+            }
+            let items = match value {
+                Value::Array(items) => items.as_slice(),
+                other => std::slice::from_ref(other),
+            };
+            for item in items {
+                let text = match item {
+                    Value::String(s) => s.clone(),
+                    Value::Datetime(d) => d.to_string(),
+                    Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => item.to_string(),
+                    _ => {
+                        return Err(anyhow!(
+                            "key `{key}` in config {path} must be a string, number, boolean or array of those"
+                        ));
+                    }
+                };
+                from_file.push(format!("--{long}={text}").into());
+            }
+        }
+        let (program, rest) = argv.split_at(argv.len().min(1));
+        merged = program
+            .iter()
+            .cloned()
+            .chain(from_file)
+            .chain(rest.iter().cloned())
+            .collect();
+    }
+    Ok(merged)
+}
+
+// The effective arguments as a config file; `config` and `print_config` are expected to
+// be `#[serde(skip)]` so the output loads back unchanged.
+pub fn to_toml<T: serde::Serialize>(args: &T) -> Result<String> {
+    toml::to_string(args).map_err(|e| anyhow!("cannot serialize config: {e}"))
+}
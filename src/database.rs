@@ -1,12 +1,184 @@
-use std::{env, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use dotenvy::dotenv;
-use sqlx::{PgPool, Row, postgres::PgPoolOptions};
+use futures::TryStreamExt;
+use sqlx::{
+    PgPool, Row,
+    postgres::{PgPoolOptions, PgRow},
+};
+
+use crate::dump::DumpStore;
+use crate::store::VersionStore;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataSource {
+    Postgres,
+    CsvDump,
+}
+
+enum Backend {
+    Postgres(PgStore),
+    Dump(Box<DumpStore>),
+}
+
+macro_rules! with_store {
+    ($db:expr, $store:ident => $call:expr) => {
+        match &$db.backend {
+            Backend::Postgres($store) => $call,
+            Backend::Dump($store) => $call,
+        }
+    };
+}
 
 pub struct Database {
+    backend: Backend,
+    name: String,
+    // name -> crates.id, including misses, so each name hits the database once.
+    crate_ids: Mutex<HashMap<String, Option<i64>>>,
+    crate_id_queries: AtomicUsize,
+}
+
+pub struct PgStore {
     pool: PgPool,
+    schema: SchemaReport,
+    exclude_target_specific: bool,
+    exclude_yanked: bool,
+}
+
+// Columns every query reads; a database without them cannot be analyzed at all.
+const REQUIRED_COLUMNS: [(&str, &str); 10] = [
+    ("crates", "id"),
+    ("crates", "name"),
+    ("versions", "id"),
+    ("versions", "crate_id"),
+    ("versions", "num"),
+    ("versions", "created_at"),
+    ("dependencies", "version_id"),
+    ("dependencies", "crate_id"),
+    ("dependencies", "req"),
+    ("dependencies", "kind"),
+];
+
+// Where per-crate download totals live: older schemas keep them on `crates`, newer ones
+// in a separate `crate_downloads` table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DownloadsSource {
+    Crates,
+    CrateDownloads,
+}
+
+// The optional parts of the crates.io schema a snapshot has. Dumps of different vintages
+// add, drop or move columns, so this is read once at connect time and the queries only
+// name columns that exist.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SchemaReport {
+    // Required `table.column`s that are absent.
+    pub missing_required: Vec<String>,
+    pub yanked: bool,
+    pub license: bool,
+    pub dependency_target: bool,
+    pub downloads: Option<DownloadsSource>,
+    pub owners: bool,
+}
+
+impl SchemaReport {
+    // `has(table, column)` answers for the connected snapshot.
+    pub fn from_columns(has: impl Fn(&str, &str) -> bool) -> Self {
+        let downloads = if has("crates", "downloads") {
+            Some(DownloadsSource::Crates)
+        } else if has("crate_downloads", "crate_id") && has("crate_downloads", "downloads") {
+            Some(DownloadsSource::CrateDownloads)
+        } else {
+            None
+        };
+        Self {
+            missing_required: REQUIRED_COLUMNS
+                .iter()
+                .filter(|(t, c)| !has(t, c))
+                .map(|(t, c)| format!("{t}.{c}"))
+                .collect(),
+            yanked: has("versions", "yanked"),
+            license: has("versions", "license"),
+            dependency_target: has("dependencies", "target"),
+            downloads,
+            owners: has("crate_owners", "owner_kind"),
+        }
+    }
+
+    pub fn require(&self, name: &str) -> Result<()> {
+        if self.missing_required.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "{name} is missing required columns: {} (is it a crates.io snapshot?)",
+            self.missing_required.join(", ")
+        ))
+    }
+
+    // What each optional column enables, one line each, for --check-db.
+    pub fn lines(&self) -> Vec<String> {
+        let present =
+            |yes: bool, what: &str| format!("{} ({what})", if yes { "present" } else { "absent" });
+        vec![
+            format!(
+                "required columns: {}",
+                if self.missing_required.is_empty() {
+                    "ok".to_string()
+                } else {
+                    format!("missing {}", self.missing_required.join(", "))
+                }
+            ),
+            format!(
+                "versions.yanked: {}",
+                present(self.yanked, "--exclude-yanked-versions")
+            ),
+            format!(
+                "versions.license: {}",
+                present(self.license, "detected only; no analysis reads it")
+            ),
+            format!(
+                "dependencies.target: {}",
+                present(self.dependency_target, "--exclude-target-specific-deps")
+            ),
+            format!(
+                "downloads: {}",
+                match self.downloads {
+                    Some(DownloadsSource::Crates) => "crates.downloads (download-weighted reach)",
+                    Some(DownloadsSource::CrateDownloads) =>
+                        "crate_downloads.downloads (download-weighted reach)",
+                    None => "absent (reach downloads are reported as 0)",
+                }
+            ),
+            format!(
+                "ownership tables: {}",
+                present(
+                    self.owners,
+                    "--group-by-owner; without them owners are unknown"
+                )
+            ),
+        ]
+    }
+}
+
+fn downstream_info(row: &PgRow) -> Result<DownstreamVersionInfo> {
+    Ok(DownstreamVersionInfo {
+        crate_name: row.try_get("crate_name")?,
+        version: row.try_get("version")?,
+        created_at: row.try_get("created_at")?,
+        dep_req: row.try_get("dep_req")?,
+        source: RowSource::Database,
+    })
 }
 
 #[derive(Clone)]
@@ -15,9 +187,45 @@ pub struct DownstreamVersionInfo {
     pub version: String,
     pub created_at: DateTime<Utc>,
     pub dep_req: String,
+    pub source: RowSource,
+}
+
+// Where a downstream row came from: the snapshot, or synthesized from the crates.io API
+// when the snapshot has no dependents for the crate.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum RowSource {
+    #[default]
+    Database,
+    CratesIo,
+}
+
+impl RowSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RowSource::Database => "db",
+            RowSource::CratesIo => "crates_io",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct CrateActivity {
+    pub versions: i64,
+    pub last_release: DateTime<Utc>,
 }
 
 impl Database {
+    pub async fn connect(source: DataSource, dump_dir: Option<&str>) -> Result<Self> {
+        match source {
+            DataSource::Postgres => Self::connect_from_env().await,
+            DataSource::CsvDump => {
+                let dir = dump_dir
+                    .ok_or_else(|| anyhow!("--data-source csv-dump requires --dump-dir"))?;
+                Self::open_dump(dir)
+            }
+        }
+    }
+
     pub async fn connect_from_env() -> Result<Self> {
         dotenv().ok();
 
@@ -43,67 +251,453 @@ impl Database {
             .connect(&url)
             .await?;
 
-        Ok(Self { pool })
+        Self::from_pool(pool, database).await
+    }
+
+    // Wraps an existing pool, e.g. one built from a connection URL by a test harness.
+    // Fails when the schema lacks columns every query needs.
+    pub async fn from_pool(pool: PgPool, name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        let schema = PgStore::introspect(&pool).await?;
+        schema.require(&name)?;
+        Ok(Self::with_backend(
+            Backend::Postgres(PgStore {
+                pool,
+                schema,
+                exclude_target_specific: false,
+                exclude_yanked: false,
+            }),
+            name,
+        ))
+    }
+
+    // Loads the dump tables into memory; see `DumpStore`.
+    pub fn open_dump(dir: &str) -> Result<Self> {
+        let store = DumpStore::load(dir)?;
+        Ok(Self::with_backend(
+            Backend::Dump(Box::new(store)),
+            dir.to_string(),
+        ))
+    }
+
+    fn with_backend(backend: Backend, name: String) -> Self {
+        Self {
+            backend,
+            name,
+            crate_ids: Mutex::new(HashMap::new()),
+            crate_id_queries: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn query_crate_id(&self, crate_name: &str) -> Result<Option<i64>> {
+        if let Some(id) = self.crate_ids.lock().unwrap().get(crate_name) {
+            return Ok(*id);
+        }
+        self.crate_id_queries.fetch_add(1, Ordering::Relaxed);
+        let id = with_store!(self, s => s.crate_id(crate_name).await?);
+        self.crate_ids
+            .lock()
+            .unwrap()
+            .insert(crate_name.to_string(), id);
+        Ok(id)
+    }
+
+    // Which optional columns the snapshot has, as read when it was opened.
+    pub fn schema_report(&self) -> &SchemaReport {
+        match &self.backend {
+            Backend::Postgres(s) => &s.schema,
+            Backend::Dump(s) => s.schema_report(),
+        }
+    }
+
+    // Leaves out target-specific dependency rows (`target IS NULL` only) from the
+    // downstream queries.
+    pub fn exclude_target_specific_deps(&mut self, exclude: bool) -> Result<()> {
+        if exclude && !self.schema_report().dependency_target {
+            return Err(anyhow!(
+                "--exclude-target-specific-deps needs dependencies.target, which {} lacks",
+                self.name
+            ));
+        }
+        match &mut self.backend {
+            Backend::Postgres(s) => s.exclude_target_specific = exclude,
+            Backend::Dump(s) => s.set_exclude_target_specific(exclude),
+        }
+        Ok(())
+    }
+
+    // Leaves yanked downstream versions out of the downstream queries.
+    pub fn exclude_yanked_versions(&mut self, exclude: bool) -> Result<()> {
+        if exclude && !self.schema_report().yanked {
+            return Err(anyhow!(
+                "--exclude-yanked-versions needs versions.yanked, which {} lacks",
+                self.name
+            ));
+        }
+        match &mut self.backend {
+            Backend::Postgres(s) => s.exclude_yanked = exclude,
+            Backend::Dump(s) => s.set_exclude_yanked(exclude),
+        }
+        Ok(())
+    }
+
+    // Number of name -> id lookups that actually reached the database.
+    pub fn crate_id_queries(&self) -> usize {
+        self.crate_id_queries.load(Ordering::Relaxed)
     }
 
     pub async fn query_all_downstream_details(
         &self,
         target_crate: &str,
     ) -> Result<Vec<DownstreamVersionInfo>> {
+        match self.query_crate_id(target_crate).await? {
+            Some(id) => self.query_all_downstream_details_by_id(id).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn query_all_downstream_details_by_id(
+        &self,
+        target_crate_id: i64,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        with_store!(self, s => s.downstream_details(target_crate_id).await)
+    }
+
+    // Same rows as `query_all_downstream_details`, converted as they arrive instead of
+    // after the whole result set is buffered; for crates with very large histories.
+    pub async fn stream_all_downstream_details(
+        &self,
+        target_crate: &str,
+        expected_rows: usize,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        let Some(id) = self.query_crate_id(target_crate).await? else {
+            return Ok(Vec::new());
+        };
+        match &self.backend {
+            Backend::Postgres(s) => s.stream_downstream_details(id, expected_rows).await,
+            Backend::Dump(s) => s.downstream_details(id).await,
+        }
+    }
+
+    pub async fn count_downstream_details(&self, target_crate: &str) -> Result<i64> {
+        match self.query_crate_id(target_crate).await? {
+            Some(id) => with_store!(self, s => s.count_downstream_details(id).await),
+            None => Ok(0),
+        }
+    }
+
+    // Distinct crates depending on `crate_name` at kind = 0; 0 for unknown crates.
+    pub async fn count_dependents(&self, crate_name: &str) -> Result<i64> {
+        match self.query_crate_id(crate_name).await? {
+            Some(id) => with_store!(self, s => s.count_dependents(id).await),
+            None => Ok(0),
+        }
+    }
+
+    // Sorted owner keys of `crate_name`; empty for unknown crates and crates without
+    // ownership rows.
+    pub async fn query_crate_owners(&self, crate_name: &str) -> Result<Vec<String>> {
+        match self.query_crate_id(crate_name).await? {
+            Some(id) => with_store!(self, s => s.crate_owners(id).await),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn snapshot_freshness(&self) -> Result<Option<DateTime<Utc>>> {
+        with_store!(self, s => s.snapshot_freshness().await)
+    }
+
+    // Total downloads per crate name; names missing from the dump are left out.
+    pub async fn query_crate_downloads(&self, names: &[String]) -> Result<HashMap<String, i64>> {
+        with_store!(self, s => s.crate_downloads(names).await)
+    }
+
+    // Version count and newest release per crate, counting only versions created before
+    // `before` when given; names without any such version are left out.
+    pub async fn query_crate_activity(
+        &self,
+        names: &[String],
+        before: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<String, CrateActivity>> {
+        with_store!(self, s => s.crate_activity(names, before).await)
+    }
+
+    pub async fn crate_exists(&self, crate_name: &str) -> Result<bool> {
+        Ok(self.query_crate_id(crate_name).await?.is_some())
+    }
+
+    pub async fn query_version_time(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        match self.query_crate_id(crate_name).await? {
+            Some(id) => self.query_version_time_by_id(id, version).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn query_version_time_by_id(
+        &self,
+        crate_id: i64,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        with_store!(self, s => s.version_time(crate_id, version).await)
+    }
+
+    pub async fn query_all_version_numbers(&self, crate_name: &str) -> Result<Vec<String>> {
+        match self.query_crate_id(crate_name).await? {
+            Some(id) => self.query_all_version_numbers_by_id(id).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn query_all_version_numbers_by_id(&self, crate_id: i64) -> Result<Vec<String>> {
+        with_store!(self, s => s.version_numbers(crate_id).await)
+    }
+
+    pub async fn query_all_version_times(
+        &self,
+        crate_name: &str,
+    ) -> Result<Vec<(String, DateTime<Utc>)>> {
+        match self.query_crate_id(crate_name).await? {
+            Some(id) => self.query_all_version_times_by_id(id).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn query_all_version_times_by_id(
+        &self,
+        crate_id: i64,
+    ) -> Result<Vec<(String, DateTime<Utc>)>> {
+        with_store!(self, s => s.version_times(crate_id).await)
+    }
+}
+
+// Whichever backend was opened, with the name -> id cache in front of it, so library
+// code written against `VersionStore` (e.g. `StoreSource`) runs on a `Database`.
+impl VersionStore for Database {
+    async fn crate_id(&self, crate_name: &str) -> Result<Option<i64>> {
+        self.query_crate_id(crate_name).await
+    }
+
+    async fn downstream_details(&self, crate_id: i64) -> Result<Vec<DownstreamVersionInfo>> {
+        self.query_all_downstream_details_by_id(crate_id).await
+    }
+
+    async fn count_downstream_details(&self, crate_id: i64) -> Result<i64> {
+        with_store!(self, s => s.count_downstream_details(crate_id).await)
+    }
+
+    async fn count_dependents(&self, crate_id: i64) -> Result<i64> {
+        with_store!(self, s => s.count_dependents(crate_id).await)
+    }
+
+    async fn crate_owners(&self, crate_id: i64) -> Result<Vec<String>> {
+        with_store!(self, s => s.crate_owners(crate_id).await)
+    }
+
+    async fn version_time(&self, crate_id: i64, version: &str) -> Result<Option<DateTime<Utc>>> {
+        self.query_version_time_by_id(crate_id, version).await
+    }
+
+    async fn version_numbers(&self, crate_id: i64) -> Result<Vec<String>> {
+        self.query_all_version_numbers_by_id(crate_id).await
+    }
+
+    async fn version_times(&self, crate_id: i64) -> Result<Vec<(String, DateTime<Utc>)>> {
+        self.query_all_version_times_by_id(crate_id).await
+    }
+
+    async fn crate_downloads(&self, names: &[String]) -> Result<HashMap<String, i64>> {
+        self.query_crate_downloads(names).await
+    }
+
+    async fn crate_activity(
+        &self,
+        names: &[String],
+        before: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<String, CrateActivity>> {
+        self.query_crate_activity(names, before).await
+    }
+
+    async fn snapshot_freshness(&self) -> Result<Option<DateTime<Utc>>> {
+        Database::snapshot_freshness(self).await
+    }
+}
+
+impl PgStore {
+    async fn introspect(pool: &PgPool) -> Result<SchemaReport> {
         let rows = sqlx::query(
             r#"
-            SELECT
-                downstream_crates.name AS crate_name,
-                downstream_versions.num AS version,
-                downstream_versions.created_at AS created_at,
-                dependencies.req AS dep_req
+            SELECT table_name::TEXT AS table_name, column_name::TEXT AS column_name
+            FROM information_schema.columns
+            WHERE table_schema = ANY(current_schemas(false))
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut columns = HashSet::with_capacity(rows.len());
+        for row in rows {
+            let table: String = row.try_get("table_name")?;
+            let column: String = row.try_get("column_name")?;
+            columns.insert((table, column));
+        }
+        Ok(SchemaReport::from_columns(|t, c| {
+            columns.contains(&(t.to_string(), c.to_string()))
+        }))
+    }
+
+    // The direct dependents of `$1`, with the optional filters; these only name columns
+    // the setters checked for.
+    fn dependents_from(&self) -> String {
+        let mut sql = r#"
             FROM dependencies
             JOIN versions AS downstream_versions
                 ON dependencies.version_id = downstream_versions.id
             JOIN crates AS downstream_crates
                 ON downstream_versions.crate_id = downstream_crates.id
             WHERE
-                dependencies.crate_id = (
-                    SELECT id FROM crates WHERE name = $1
-                )
+                dependencies.crate_id = $1
                 AND dependencies.kind = 0
+        "#
+        .to_string();
+        if self.exclude_target_specific {
+            sql.push_str("    AND dependencies.target IS NULL\n");
+        }
+        if self.exclude_yanked {
+            sql.push_str("    AND NOT downstream_versions.yanked\n");
+        }
+        sql
+    }
+
+    fn downstream_details_sql(&self) -> String {
+        format!(
+            r#"
+            SELECT
+                downstream_crates.name AS crate_name,
+                downstream_versions.num AS version,
+                downstream_versions.created_at AS created_at,
+                dependencies.req AS dep_req
+            {}
             ORDER BY downstream_crates.name ASC, downstream_versions.created_at ASC, downstream_versions.num ASC
             "#,
+            self.dependents_from()
         )
-        .bind(target_crate)
-        .fetch_all(&self.pool)
+    }
+
+    async fn stream_downstream_details(
+        &self,
+        crate_id: i64,
+        expected_rows: usize,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        let sql = self.downstream_details_sql();
+        let mut rows = sqlx::query(&sql).bind(crate_id).fetch(&self.pool);
+        let mut out = Vec::with_capacity(expected_rows);
+        while let Some(row) = rows.try_next().await? {
+            out.push(downstream_info(&row)?);
+        }
+        Ok(out)
+    }
+}
+
+impl VersionStore for PgStore {
+    async fn crate_id(&self, crate_name: &str) -> Result<Option<i64>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id::BIGINT AS id
+            FROM crates
+            WHERE name = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(crate_name)
+        .fetch_optional(&self.pool)
         .await?;
 
+        Ok(row.map(|r| r.try_get("id")).transpose()?)
+    }
+
+    async fn downstream_details(&self, crate_id: i64) -> Result<Vec<DownstreamVersionInfo>> {
+        let rows = sqlx::query(&self.downstream_details_sql())
+            .bind(crate_id)
+            .fetch_all(&self.pool)
+            .await?;
+
         let mut out = Vec::with_capacity(rows.len());
         for row in rows {
-            out.push(DownstreamVersionInfo {
-                crate_name: row.try_get("crate_name")?,
-                version: row.try_get("version")?,
-                created_at: row.try_get("created_at")?,
-                dep_req: row.try_get("dep_req")?,
-            });
+            out.push(downstream_info(&row)?);
         }
 
         Ok(out)
     }
 
-    pub async fn query_version_time(
-        &self,
-        crate_name: &str,
-        version: &str,
-    ) -> Result<Option<DateTime<Utc>>> {
+    async fn count_downstream_details(&self, crate_id: i64) -> Result<i64> {
+        let sql = format!("SELECT COUNT(*) AS cnt {}", self.dependents_from());
+        let row = sqlx::query(&sql)
+            .bind(crate_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.try_get("cnt")?)
+    }
+
+    async fn count_dependents(&self, crate_id: i64) -> Result<i64> {
+        let sql = format!(
+            "SELECT COUNT(DISTINCT downstream_versions.crate_id) AS cnt {}",
+            self.dependents_from()
+        );
+        let row = sqlx::query(&sql)
+            .bind(crate_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.try_get("cnt")?)
+    }
+
+    // owner_kind 0 is a user, 1 a team.
+    async fn crate_owners(&self, crate_id: i64) -> Result<Vec<String>> {
+        if !self.schema.owners {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query(
+            r#"
+            SELECT 'user:' || users.gh_login AS owner
+            FROM crate_owners
+            JOIN users ON users.id = crate_owners.owner_id
+            WHERE crate_owners.crate_id = $1 AND crate_owners.owner_kind = 0
+            UNION
+            SELECT 'team:' || teams.login AS owner
+            FROM crate_owners
+            JOIN teams ON teams.id = crate_owners.owner_id
+            WHERE crate_owners.crate_id = $1 AND crate_owners.owner_kind = 1
+            ORDER BY owner
+            "#,
+        )
+        .bind(crate_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows {
+            out.push(row.try_get("owner")?);
+        }
+        Ok(out)
+    }
+
+    async fn version_time(&self, crate_id: i64, version: &str) -> Result<Option<DateTime<Utc>>> {
         let row = sqlx::query(
             r#"
-            SELECT v.created_at AS created_at
-            FROM versions v
-            JOIN crates c
-                ON v.crate_id = c.id
-            WHERE c.name = $1
-              AND v.num = $2
+            SELECT created_at
+            FROM versions
+            WHERE crate_id = $1
+              AND num = $2
             LIMIT 1
             "#,
         )
-        .bind(crate_name)
+        .bind(crate_id)
         .bind(version)
         .fetch_optional(&self.pool)
         .await?;
@@ -111,17 +705,15 @@ impl Database {
         Ok(row.map(|r| r.try_get("created_at")).transpose()?)
     }
 
-    pub async fn query_all_version_numbers(&self, crate_name: &str) -> Result<Vec<String>> {
+    async fn version_numbers(&self, crate_id: i64) -> Result<Vec<String>> {
         let rows = sqlx::query(
             r#"
-            SELECT v.num AS num
-            FROM versions v
-            JOIN crates c
-                ON v.crate_id = c.id
-            WHERE c.name = $1
+            SELECT num
+            FROM versions
+            WHERE crate_id = $1
             "#,
         )
-        .bind(crate_name)
+        .bind(crate_id)
         .fetch_all(&self.pool)
         .await?;
 
@@ -131,4 +723,96 @@ impl Database {
         }
         Ok(out)
     }
+
+    async fn version_times(&self, crate_id: i64) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT num, created_at
+            FROM versions
+            WHERE crate_id = $1
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(crate_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows {
+            out.push((row.try_get("num")?, row.try_get("created_at")?));
+        }
+        Ok(out)
+    }
+
+    async fn crate_downloads(&self, names: &[String]) -> Result<HashMap<String, i64>> {
+        let sql = match self.schema.downloads {
+            Some(DownloadsSource::Crates) => {
+                r#"
+                SELECT name, downloads::BIGINT AS downloads
+                FROM crates
+                WHERE name = ANY($1)
+                "#
+            }
+            Some(DownloadsSource::CrateDownloads) => {
+                r#"
+                SELECT crates.name AS name, crate_downloads.downloads::BIGINT AS downloads
+                FROM crates
+                JOIN crate_downloads ON crate_downloads.crate_id = crates.id
+                WHERE crates.name = ANY($1)
+                "#
+            }
+            None => return Ok(HashMap::new()),
+        };
+        let rows = sqlx::query(sql).bind(names).fetch_all(&self.pool).await?;
+
+        let mut out = HashMap::with_capacity(rows.len());
+        for row in rows {
+            out.insert(row.try_get("name")?, row.try_get("downloads")?);
+        }
+        Ok(out)
+    }
+
+    async fn crate_activity(
+        &self,
+        names: &[String],
+        before: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<String, CrateActivity>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                crates.name AS name,
+                count(versions.id) AS versions,
+                max(versions.created_at) AS last_release
+            FROM crates
+            JOIN versions ON versions.crate_id = crates.id
+            WHERE
+                crates.name = ANY($1)
+                AND ($2::timestamptz IS NULL OR versions.created_at < $2)
+            GROUP BY crates.name
+            "#,
+        )
+        .bind(names)
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut out = HashMap::with_capacity(rows.len());
+        for row in rows {
+            out.insert(
+                row.try_get("name")?,
+                CrateActivity {
+                    versions: row.try_get("versions")?,
+                    last_release: row.try_get("last_release")?,
+                },
+            );
+        }
+        Ok(out)
+    }
+
+    async fn snapshot_freshness(&self) -> Result<Option<DateTime<Utc>>> {
+        let row = sqlx::query("SELECT max(created_at) AS latest FROM versions")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.try_get("latest")?)
+    }
 }
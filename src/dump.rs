@@ -0,0 +1,459 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use csv::StringRecord;
+
+use crate::database::{
+    CrateActivity, DownloadsSource, DownstreamVersionInfo, RowSource, SchemaReport,
+};
+use crate::store::VersionStore;
+
+struct DumpVersion {
+    crate_id: i64,
+    num: String,
+    created_at: DateTime<Utc>,
+    yanked: bool,
+}
+
+// The official crates.io db-dump (`crates.csv`, `versions.csv`, `dependencies.csv`) held
+// in memory and indexed by crate id. Answers the same queries as the Postgres backend,
+// including the kind = 0 filter and the downstream ordering; a full dump needs several
+// GiB of memory.
+pub struct DumpStore {
+    crate_ids: HashMap<String, i64>,
+    crate_names: HashMap<i64, String>,
+    downloads: HashMap<i64, i64>,
+    versions: HashMap<i64, DumpVersion>,
+    // crate id -> its version ids in file order.
+    crate_versions: HashMap<i64, Vec<i64>>,
+    // dependency crate id -> (dependent version id, req) of its kind = 0 dependents.
+    dependents: HashMap<i64, Vec<(i64, String)>>,
+    // The same for rows with a non-empty `target` (e.g. `cfg(windows)`), kept apart so
+    // they can be left out like the SQL's `target IS NULL`.
+    target_dependents: HashMap<i64, Vec<(i64, String)>>,
+    exclude_target_specific: bool,
+    exclude_yanked: bool,
+    // crate id -> sorted owner keys; empty when the dump has no ownership tables.
+    owners: HashMap<i64, Vec<String>>,
+    schema: SchemaReport,
+}
+
+impl DumpStore {
+    // The extracted dump keeps its tables under `data/`; either directory is accepted.
+    pub fn data_dir(dir: impl AsRef<Path>) -> PathBuf {
+        let dir = dir.as_ref();
+        let nested = dir.join("data");
+        if nested.join("crates.csv").exists() {
+            nested
+        } else {
+            dir.to_path_buf()
+        }
+    }
+
+    // Combined size of the tables `load` reads, for warning about memory up front.
+    pub fn csv_bytes(dir: impl AsRef<Path>) -> u64 {
+        let dir = Self::data_dir(dir);
+        ["crates.csv", "versions.csv", "dependencies.csv"]
+            .iter()
+            .filter_map(|f| std::fs::metadata(dir.join(f)).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+
+    // The same column checks as the Postgres introspection, from the table headers;
+    // missing optional tables count as having no columns.
+    fn schema(dir: &Path) -> Result<SchemaReport> {
+        let mut tables = HashMap::new();
+        for table in [
+            "crates",
+            "versions",
+            "dependencies",
+            "crate_downloads",
+            "crate_owners",
+        ] {
+            let file = format!("{table}.csv");
+            if dir.join(&file).exists() {
+                tables.insert(table, table_headers(dir, &file)?);
+            }
+        }
+        Ok(SchemaReport::from_columns(|t, c| {
+            tables.get(t).is_some_and(|h| h.iter().any(|h| h == c))
+        }))
+    }
+
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = Self::data_dir(dir);
+        let schema = Self::schema(&dir)?;
+        schema.require(&dir.display().to_string())?;
+
+        let mut crate_ids = HashMap::new();
+        let mut crate_names = HashMap::new();
+        let mut downloads = HashMap::new();
+        // Newer dumps moved the download counter out of crates.csv.
+        let has_downloads = schema.downloads == Some(DownloadsSource::Crates);
+        let columns: &[&str] = if has_downloads {
+            &["id", "name", "downloads"]
+        } else {
+            &["id", "name"]
+        };
+        for_each_row(&dir, "crates.csv", columns, |row| {
+            let id = row.i64(0)?;
+            crate_ids.insert(row.text(1).to_string(), id);
+            crate_names.insert(id, row.text(1).to_string());
+            if has_downloads {
+                downloads.insert(id, row.i64(2)?);
+            }
+            Ok(())
+        })?;
+        if schema.downloads == Some(DownloadsSource::CrateDownloads) {
+            for_each_row(
+                &dir,
+                "crate_downloads.csv",
+                &["crate_id", "downloads"],
+                |row| {
+                    downloads.insert(row.i64(0)?, row.i64(1)?);
+                    Ok(())
+                },
+            )?;
+        }
+
+        let mut versions = HashMap::new();
+        let mut crate_versions: HashMap<i64, Vec<i64>> = HashMap::new();
+        let columns: &[&str] = if schema.yanked {
+            &["id", "crate_id", "num", "created_at", "yanked"]
+        } else {
+            &["id", "crate_id", "num", "created_at"]
+        };
+        for_each_row(&dir, "versions.csv", columns, |row| {
+            let id = row.i64(0)?;
+            let crate_id = row.i64(1)?;
+            versions.insert(
+                id,
+                DumpVersion {
+                    crate_id,
+                    num: row.text(2).to_string(),
+                    created_at: row.timestamp(3)?,
+                    // Postgres exports booleans as t/f.
+                    yanked: schema.yanked && matches!(row.text(4), "t" | "true"),
+                },
+            );
+            crate_versions.entry(crate_id).or_default().push(id);
+            Ok(())
+        })?;
+
+        let mut dependents: HashMap<i64, Vec<(i64, String)>> = HashMap::new();
+        let mut target_dependents: HashMap<i64, Vec<(i64, String)>> = HashMap::new();
+        // Hand-made dumps may leave out the target column.
+        let has_target = schema.dependency_target;
+        let columns: &[&str] = if has_target {
+            &["version_id", "crate_id", "req", "kind", "target"]
+        } else {
+            &["version_id", "crate_id", "req", "kind"]
+        };
+        for_each_row(&dir, "dependencies.csv", columns, |row| {
+            if row.i64(3)? == 0 {
+                let into = if has_target && !row.text(4).is_empty() {
+                    &mut target_dependents
+                } else {
+                    &mut dependents
+                };
+                into.entry(row.i64(1)?)
+                    .or_default()
+                    .push((row.i64(0)?, row.text(2).to_string()));
+            }
+            Ok(())
+        })?;
+
+        let owners = load_owners(&dir)?;
+
+        Ok(Self {
+            crate_ids,
+            crate_names,
+            downloads,
+            versions,
+            crate_versions,
+            dependents,
+            target_dependents,
+            exclude_target_specific: false,
+            exclude_yanked: false,
+            owners,
+            schema,
+        })
+    }
+
+    pub fn schema_report(&self) -> &SchemaReport {
+        &self.schema
+    }
+
+    pub fn set_exclude_target_specific(&mut self, exclude: bool) {
+        self.exclude_target_specific = exclude;
+    }
+
+    pub fn set_exclude_yanked(&mut self, exclude: bool) {
+        self.exclude_yanked = exclude;
+    }
+
+    // Dependents whose version and crate rows exist, like the inner joins of the SQL.
+    fn joined_dependents(
+        &self,
+        crate_id: i64,
+    ) -> impl Iterator<Item = (&str, &DumpVersion, &str)> + '_ {
+        let target = (!self.exclude_target_specific)
+            .then(|| self.target_dependents.get(&crate_id))
+            .flatten();
+        self.dependents
+            .get(&crate_id)
+            .into_iter()
+            .chain(target)
+            .flatten()
+            .filter_map(|(version_id, req)| {
+                let v = self.versions.get(version_id)?;
+                if self.exclude_yanked && v.yanked {
+                    return None;
+                }
+                let name = self.crate_names.get(&v.crate_id)?;
+                Some((name.as_str(), v, req.as_str()))
+            })
+    }
+
+    fn versions_of(&self, crate_id: i64) -> impl Iterator<Item = &DumpVersion> + '_ {
+        self.crate_versions
+            .get(&crate_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.versions.get(id))
+    }
+}
+
+impl VersionStore for DumpStore {
+    async fn crate_id(&self, crate_name: &str) -> Result<Option<i64>> {
+        Ok(self.crate_ids.get(crate_name).copied())
+    }
+
+    async fn downstream_details(&self, crate_id: i64) -> Result<Vec<DownstreamVersionInfo>> {
+        let mut out: Vec<DownstreamVersionInfo> = self
+            .joined_dependents(crate_id)
+            .map(|(name, v, req)| DownstreamVersionInfo {
+                crate_name: name.to_string(),
+                version: v.num.clone(),
+                created_at: v.created_at,
+                dep_req: req.to_string(),
+                source: RowSource::Database,
+            })
+            .collect();
+        out.sort_by(|a, b| {
+            a.crate_name
+                .cmp(&b.crate_name)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        Ok(out)
+    }
+
+    async fn count_downstream_details(&self, crate_id: i64) -> Result<i64> {
+        Ok(self.joined_dependents(crate_id).count() as i64)
+    }
+
+    async fn count_dependents(&self, crate_id: i64) -> Result<i64> {
+        let names: HashSet<&str> = self
+            .joined_dependents(crate_id)
+            .map(|(name, _, _)| name)
+            .collect();
+        Ok(names.len() as i64)
+    }
+
+    async fn crate_owners(&self, crate_id: i64) -> Result<Vec<String>> {
+        Ok(self.owners.get(&crate_id).cloned().unwrap_or_default())
+    }
+
+    async fn version_time(&self, crate_id: i64, version: &str) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .versions_of(crate_id)
+            .find(|v| v.num == version)
+            .map(|v| v.created_at))
+    }
+
+    async fn version_numbers(&self, crate_id: i64) -> Result<Vec<String>> {
+        Ok(self.versions_of(crate_id).map(|v| v.num.clone()).collect())
+    }
+
+    async fn version_times(&self, crate_id: i64) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let mut out: Vec<(String, DateTime<Utc>)> = self
+            .versions_of(crate_id)
+            .map(|v| (v.num.clone(), v.created_at))
+            .collect();
+        out.sort_by_key(|(_, t)| *t);
+        Ok(out)
+    }
+
+    async fn crate_downloads(&self, names: &[String]) -> Result<HashMap<String, i64>> {
+        Ok(names
+            .iter()
+            .filter_map(|name| {
+                let id = self.crate_ids.get(name)?;
+                Some((name.clone(), *self.downloads.get(id)?))
+            })
+            .collect())
+    }
+
+    async fn crate_activity(
+        &self,
+        names: &[String],
+        before: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<String, CrateActivity>> {
+        let mut out = HashMap::new();
+        for name in names {
+            let Some(&id) = self.crate_ids.get(name) else {
+                continue;
+            };
+            let times = self
+                .versions_of(id)
+                .map(|v| v.created_at)
+                .filter(|t| before.is_none_or(|b| *t < b));
+            let (versions, last_release) = times.fold((0i64, None), |(n, last), t| {
+                (n + 1, Some(last.map_or(t, |l: DateTime<Utc>| l.max(t))))
+            });
+            if let Some(last_release) = last_release {
+                out.insert(
+                    name.clone(),
+                    CrateActivity {
+                        versions,
+                        last_release,
+                    },
+                );
+            }
+        }
+        Ok(out)
+    }
+
+    async fn snapshot_freshness(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(self.versions.values().map(|v| v.created_at).max())
+    }
+}
+
+// `crate_owners.csv` with user logins from `users.csv` and team logins from `teams.csv`.
+// Older or trimmed dumps may lack these tables; ownership is then simply unknown.
+fn load_owners(dir: &Path) -> Result<HashMap<i64, Vec<String>>> {
+    let mut owners: HashMap<i64, Vec<String>> = HashMap::new();
+    if !dir.join("crate_owners.csv").exists() {
+        return Ok(owners);
+    }
+    let mut logins: [HashMap<i64, String>; 2] = [HashMap::new(), HashMap::new()];
+    for (kind, file, column) in [(0, "users.csv", "gh_login"), (1, "teams.csv", "login")] {
+        if dir.join(file).exists() {
+            for_each_row(dir, file, &["id", column], |row| {
+                logins[kind].insert(row.i64(0)?, row.text(1).to_string());
+                Ok(())
+            })?;
+        }
+    }
+    for_each_row(
+        dir,
+        "crate_owners.csv",
+        &["crate_id", "owner_id", "owner_kind"],
+        |row| {
+            let (prefix, names) = match row.i64(2)? {
+                0 => ("user", &logins[0]),
+                1 => ("team", &logins[1]),
+                _ => return Ok(()),
+            };
+            if let Some(login) = names.get(&row.i64(1)?) {
+                owners
+                    .entry(row.i64(0)?)
+                    .or_default()
+                    .push(format!("{prefix}:{login}"));
+            }
+            Ok(())
+        },
+    )?;
+    for list in owners.values_mut() {
+        list.sort();
+        list.dedup();
+    }
+    Ok(owners)
+}
+
+fn table_headers(dir: &Path, file: &str) -> Result<StringRecord> {
+    let path = dir.join(file);
+    let mut r = csv::Reader::from_path(&path)
+        .map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+    Ok(r.headers()?.clone())
+}
+
+// One row of a dump table with the requested columns, in request order.
+struct DumpRow<'a> {
+    record: &'a StringRecord,
+    columns: &'a [usize],
+    names: &'a [&'a str],
+    path: &'a Path,
+}
+
+impl DumpRow<'_> {
+    fn text(&self, i: usize) -> &str {
+        &self.record[self.columns[i]]
+    }
+
+    fn i64(&self, i: usize) -> Result<i64> {
+        self.text(i).parse().map_err(|e| self.error(i, e))
+    }
+
+    // Postgres exports timestamps as `2015-03-06 04:17:38.599521`, with an offset when the
+    // column has a time zone.
+    fn timestamp(&self, i: usize) -> Result<DateTime<Utc>> {
+        let s = self.text(i);
+        DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%#z")
+            .map(|t| t.with_timezone(&Utc))
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").map(|t| t.and_utc())
+            })
+            .or_else(|_| DateTime::parse_from_rfc3339(s).map(|t| t.with_timezone(&Utc)))
+            .map_err(|e| self.error(i, e))
+    }
+
+    fn error(&self, i: usize, e: impl std::fmt::Display) -> anyhow::Error {
+        let line = self.record.position().map(|p| p.line()).unwrap_or(0);
+        anyhow!(
+            "{} line {}: bad {} {:?}: {e}",
+            self.path.display(),
+            line,
+            self.names[i],
+            self.text(i)
+        )
+    }
+}
+
+fn for_each_row(
+    dir: &Path,
+    file: &str,
+    names: &[&str],
+    mut f: impl FnMut(&DumpRow) -> Result<()>,
+) -> Result<()> {
+    let path = dir.join(file);
+    let mut r: csv::Reader<File> = csv::Reader::from_path(&path)
+        .map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+    let headers = r.headers()?.clone();
+    let columns = names
+        .iter()
+        .map(|name| {
+            headers
+                .iter()
+                .position(|h| h == *name)
+                .ok_or_else(|| anyhow!("{} has no `{name}` column", path.display()))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    let mut record = StringRecord::new();
+    while r.read_record(&mut record)? {
+        f(&DumpRow {
+            record: &record,
+            columns: &columns,
+            names,
+            path: &path,
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    ffi::OsString,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use csv::StringRecord;
+
+use crate::output::{Compression, OutputFile, open_reader};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SortSummary {
+    pub rows: usize,
+    // Sorted runs spilled to disk; 0 when the whole file fit in one chunk.
+    pub runs: usize,
+}
+
+// Temporary files next to the target, removed however the sort ends.
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn spill<K: Ord>(chunk: &mut Vec<(K, StringRecord)>, path: &Path) -> Result<()> {
+    chunk.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut w = csv::Writer::from_writer(File::create(path)?);
+    for (_, record) in chunk.drain(..) {
+        w.write_record(&record)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+// External merge sort of a CSV file's data rows, in place. At most `chunk_rows` rows are
+// held in memory: sorted chunks are spilled next to the file and merged into a temporary
+// copy that then replaces it. `key_for` sees the header and returns the key extractor.
+// Leading `#` lines (schema comments) and the header are kept, compression follows the
+// extension, and rows with equal keys keep their input order.
+pub fn sort_csv_file<K, F>(
+    path: &Path,
+    chunk_rows: usize,
+    key_for: impl FnOnce(&StringRecord) -> Result<F>,
+) -> Result<SortSummary>
+where
+    K: Ord,
+    F: Fn(&StringRecord) -> Result<K>,
+{
+    let chunk_rows = chunk_rows.max(1);
+    let mut input = BufReader::new(open_reader(path)?);
+    let mut preamble = String::new();
+    while input.fill_buf()?.first() == Some(&b'#') {
+        input.read_line(&mut preamble)?;
+    }
+    let mut reader = csv::Reader::from_reader(input);
+    let headers = reader.headers()?.clone();
+    let key = key_for(&headers)?;
+
+    let mut temp = TempFiles(Vec::new());
+    let mut chunk: Vec<(K, StringRecord)> = Vec::new();
+    let mut rows = 0;
+    for record in reader.into_records() {
+        let record = record?;
+        chunk.push((key(&record)?, record));
+        rows += 1;
+        if chunk.len() >= chunk_rows {
+            let run = sibling(path, &format!(".run{}", temp.0.len()));
+            temp.0.push(run.clone());
+            spill(&mut chunk, &run)?;
+        }
+    }
+    if !temp.0.is_empty() && !chunk.is_empty() {
+        let run = sibling(path, &format!(".run{}", temp.0.len()));
+        temp.0.push(run.clone());
+        spill(&mut chunk, &run)?;
+    }
+    let runs = temp.0.len();
+    let mut readers = Vec::with_capacity(runs);
+    for run in &temp.0 {
+        readers.push(
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(BufReader::new(File::open(run)?))
+                .into_records(),
+        );
+    }
+
+    let sorted = sibling(path, ".sorting");
+    temp.0.push(sorted.clone());
+    let mut file = OutputFile::create(&sorted, Compression::from_path(path))?;
+    file.write_all(preamble.as_bytes())?;
+    let mut w = csv::Writer::from_writer(file);
+    w.write_record(&headers)?;
+    if runs == 0 {
+        chunk.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, record) in &chunk {
+            w.write_record(record)?;
+        }
+    } else {
+        // The run index breaks ties, so equal keys come out in input order.
+        let mut heads: Vec<Option<StringRecord>> = Vec::with_capacity(readers.len());
+        let mut heap = BinaryHeap::new();
+        for (i, r) in readers.iter_mut().enumerate() {
+            let head = r.next().transpose()?;
+            if let Some(record) = &head {
+                heap.push(Reverse((key(record)?, i)));
+            }
+            heads.push(head);
+        }
+        while let Some(Reverse((_, i))) = heap.pop() {
+            let record = heads[i].take().expect("heap entries have a head");
+            w.write_record(&record)?;
+            if let Some(next) = readers[i].next().transpose()? {
+                heap.push(Reverse((key(&next)?, i)));
+                heads[i] = Some(next);
+            }
+        }
+    }
+    w.into_inner()
+        .map_err(|e| anyhow!("flushing sorted {}: {}", path.display(), e.error()))?
+        .finish()?;
+    std::fs::rename(&sorted, path)?;
+    Ok(SortSummary { rows, runs })
+}
+
+// Position of `name` in a CSV header, for building sort keys.
+pub fn column_index(headers: &StringRecord, name: &str) -> Result<usize> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| anyhow!("no {name} column to sort by"))
+}
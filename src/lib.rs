@@ -1 +1,16 @@
+pub mod advisory;
+pub mod analysis;
+pub mod charts;
+pub mod config;
 pub mod database;
+pub mod dump;
+pub mod extsort;
+pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod pipeline;
+pub mod records;
+pub mod report;
+pub mod sampling;
+pub mod stats;
+pub mod store;
@@ -0,0 +1,261 @@
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use flate2::{Compression as GzLevel, read::MultiGzDecoder, write::GzEncoder};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+        }
+    }
+
+    // Appends the extension unless the caller already spelled it out.
+    pub fn apply_to(self, path: &Path) -> PathBuf {
+        match self.extension() {
+            Some(ext) if path.extension() != Some(OsStr::new(ext)) => {
+                let mut with_ext = path.as_os_str().to_owned();
+                with_ext.push(".");
+                with_ext.push(ext);
+                with_ext.into()
+            }
+            _ => path.to_path_buf(),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Compression {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+// A file that is optionally compressed as it is written. The compressed stream is
+// finished on drop as well, so outputs stay readable when a run bails out early;
+// call `finish` to see the error instead.
+pub enum OutputFile {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl OutputFile {
+    pub fn create(path: impl AsRef<Path>, compression: Compression) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(match compression {
+            Compression::None => OutputFile::Plain(file),
+            Compression::Gzip => OutputFile::Gzip(GzEncoder::new(file, GzLevel::default())),
+            Compression::Zstd => OutputFile::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(f) => f.flush(),
+            OutputFile::Gzip(e) => e.try_finish(),
+            OutputFile::Zstd(e) => e.do_finish(),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Plain(f) => f.write(buf),
+            OutputFile::Gzip(e) => e.write(buf),
+            OutputFile::Zstd(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(f) => f.flush(),
+            OutputFile::Gzip(e) => e.flush(),
+            OutputFile::Zstd(e) => e.flush(),
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+// Reads a plain, .gz or .zst file, chosen by extension.
+pub fn open_reader(path: impl AsRef<Path>) -> Result<Box<dyn Read>> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path)?);
+    Ok(match Compression::from_path(path) {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+    })
+}
+
+// Type of a column in the tabular outputs. The CSV header and the Parquet schema are
+// both taken from one `(name, kind)` table per output so the two cannot drift.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColumnKind {
+    Utf8,
+    Bool,
+    Int64,
+    Float64,
+    Timestamp,
+    Date,
+}
+
+impl ColumnKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColumnKind::Utf8 => "utf8",
+            ColumnKind::Bool => "bool",
+            ColumnKind::Int64 => "int64",
+            ColumnKind::Float64 => "float64",
+            ColumnKind::Timestamp => "timestamp",
+            ColumnKind::Date => "date",
+        }
+    }
+}
+
+pub const fn column_names<const N: usize>(
+    schema: &[(&'static str, ColumnKind); N],
+) -> [&'static str; N] {
+    let mut names = [""; N];
+    let mut i = 0;
+    while i < N {
+        names[i] = schema[i].0;
+        i += 1;
+    }
+    names
+}
+
+// The versioned column layout of one CSV output, e.g. `lag_rows.v1`. Bump the version in
+// the id whenever the columns change, so downstream parsers can refuse what they do not
+// know instead of misreading shifted columns.
+#[derive(Clone, Debug)]
+pub struct CsvSchema {
+    pub id: &'static str,
+    pub columns: Vec<(String, ColumnKind)>,
+}
+
+impl CsvSchema {
+    pub fn new(id: &'static str, columns: &[(&str, ColumnKind)]) -> Self {
+        Self {
+            id,
+            columns: columns
+                .iter()
+                .map(|(name, kind)| (name.to_string(), *kind))
+                .collect(),
+        }
+    }
+
+    // Appends a column whose presence depends on the run's flags.
+    pub fn push(&mut self, name: impl Into<String>, kind: ColumnKind) {
+        self.columns.push((name.into(), kind));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.columns.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    // A row with `values` placed by column name and every other column empty.
+    pub fn record(&self, values: &[(&str, String)]) -> Result<Vec<String>> {
+        let mut record = vec![String::new(); self.columns.len()];
+        for (name, value) in values {
+            let i = self
+                .columns
+                .iter()
+                .position(|(n, _)| n == name)
+                .ok_or_else(|| anyhow!("schema {} has no column {name}", self.id))?;
+            record[i] = value.clone();
+        }
+        Ok(record)
+    }
+
+    pub fn comment_line(&self) -> String {
+        format!("# schema={}\n", self.id)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "schema": self.id,
+            "columns": self
+                .columns
+                .iter()
+                .map(|(name, kind)| serde_json::json!({"name": name, "type": kind.as_str()}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn sidecar_path(csv_path: &Path) -> PathBuf {
+        let mut path = csv_path.as_os_str().to_owned();
+        path.push(".schema.json");
+        path.into()
+    }
+}
+
+// Creates a CSV output with its header row and `<path>.schema.json` sidecar; with
+// `comment` the file starts with a `# schema=<id>` line (read it back with
+// `csv::ReaderBuilder::comment(Some(b'#'))`).
+pub fn create_csv(
+    path: impl AsRef<Path>,
+    compression: Compression,
+    schema: &CsvSchema,
+    comment: bool,
+) -> Result<csv::Writer<OutputFile>> {
+    let path = path.as_ref();
+    let mut file = OutputFile::create(path, compression)?;
+    if comment {
+        file.write_all(schema.comment_line().as_bytes())?;
+    }
+    let mut w = csv::Writer::from_writer(file);
+    w.write_record(schema.names())?;
+    std::fs::write(
+        CsvSchema::sidecar_path(path),
+        serde_json::to_string_pretty(&schema.to_json())? + "\n",
+    )?;
+    Ok(w)
+}
+
+// Creates the missing parent directories of an output path, at any depth.
+pub fn ensure_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+// Serializes a path in args and run metadata. serde's own impl fails on paths that are
+// not valid UTF-8; those are written lossily instead.
+pub fn serialize_path<S: serde::Serializer>(path: &Path, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&path.to_string_lossy())
+}
+
+pub fn serialize_opt_path<S: serde::Serializer>(
+    path: &Option<PathBuf>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(p) => s.serialize_some(&*p.to_string_lossy()),
+        None => s.serialize_none(),
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use arrow_array::{
+    ArrayRef, RecordBatch,
+    builder::{
+        BooleanBuilder, Date32Builder, Float64Builder, Int64Builder, StringBuilder,
+        TimestampMicrosecondBuilder,
+    },
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, Utc};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+use crate::output::ColumnKind;
+
+fn data_type(kind: ColumnKind) -> DataType {
+    match kind {
+        ColumnKind::Utf8 => DataType::Utf8,
+        ColumnKind::Bool => DataType::Boolean,
+        ColumnKind::Int64 => DataType::Int64,
+        ColumnKind::Float64 => DataType::Float64,
+        ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ColumnKind::Date => DataType::Date32,
+    }
+}
+
+pub fn arrow_schema(columns: &[(&str, ColumnKind)]) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|&(name, kind)| Field::new(name, data_type(kind), kind != ColumnKind::Utf8))
+            .collect::<Vec<_>>(),
+    )
+}
+
+// Takes the same string records as the CSV writers and buffers them into typed record
+// batches of `batch_size` rows. Empty cells become nulls except in text columns.
+pub struct ParquetTable {
+    columns: Vec<(&'static str, ColumnKind)>,
+    schema: Arc<Schema>,
+    rows: Vec<Vec<String>>,
+    batch_size: usize,
+    writer: ArrowWriter<File>,
+}
+
+impl ParquetTable {
+    pub fn create(
+        path: impl AsRef<Path>,
+        columns: &[(&'static str, ColumnKind)],
+        batch_size: usize,
+    ) -> Result<Self> {
+        let schema = Arc::new(arrow_schema(columns));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(props))?;
+        Ok(Self {
+            columns: columns.to_vec(),
+            schema,
+            rows: Vec::new(),
+            batch_size: batch_size.max(1),
+            writer,
+        })
+    }
+
+    pub fn write_record(&mut self, record: &[String]) -> Result<()> {
+        if record.len() != self.columns.len() {
+            return Err(anyhow!(
+                "parquet row has {} cells, schema has {} columns",
+                record.len(),
+                self.columns.len()
+            ));
+        }
+        self.rows.push(record.to_vec());
+        if self.rows.len() >= self.batch_size {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let arrays = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, &(name, kind))| {
+                build_column(kind, rows.iter().map(|r| r[i].as_str()))
+                    .map_err(|e| anyhow!("parquet column {name}: {e}"))
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+
+    pub fn close(mut self) -> Result<()> {
+        self.write_batch()?;
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+fn build_column<'a>(kind: ColumnKind, cells: impl Iterator<Item = &'a str>) -> Result<ArrayRef> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+    Ok(match kind {
+        ColumnKind::Utf8 => {
+            let mut b = StringBuilder::new();
+            for c in cells {
+                b.append_value(c);
+            }
+            Arc::new(b.finish())
+        }
+        ColumnKind::Bool => {
+            let mut b = BooleanBuilder::new();
+            for c in cells {
+                b.append_option(parse_cell::<bool>(c)?);
+            }
+            Arc::new(b.finish())
+        }
+        ColumnKind::Int64 => {
+            let mut b = Int64Builder::new();
+            for c in cells {
+                b.append_option(parse_cell::<i64>(c)?);
+            }
+            Arc::new(b.finish())
+        }
+        ColumnKind::Float64 => {
+            let mut b = Float64Builder::new();
+            for c in cells {
+                b.append_option(parse_cell::<f64>(c)?);
+            }
+            Arc::new(b.finish())
+        }
+        ColumnKind::Timestamp => {
+            let mut b = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+            for c in cells {
+                let t = parse_cell::<DateTime<Utc>>(c)?;
+                b.append_option(t.map(|t| t.timestamp_micros()));
+            }
+            Arc::new(b.finish())
+        }
+        ColumnKind::Date => {
+            let mut b = Date32Builder::new();
+            for c in cells {
+                let d = parse_cell::<NaiveDate>(c)?;
+                b.append_option(d.map(|d| (d - epoch).num_days() as i32));
+            }
+            Arc::new(b.finish())
+        }
+    })
+}
+
+fn parse_cell<T>(cell: &str) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    if cell.is_empty() {
+        return Ok(None);
+    }
+    cell.parse()
+        .map(Some)
+        .map_err(|e| anyhow!("cannot parse {cell:?}: {e}"))
+}
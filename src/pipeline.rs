@@ -0,0 +1,2454 @@
+// Strict-lag analysis of one advisory, shared by the batch binary and library users. The
+// batch layers its caches, crates.io time fallbacks, `--as-of` snapshots and optional
+// outputs on top of these pieces; `analyze_advisory` runs them against any `VersionStore`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+use semver::{Op, Version, VersionReq};
+
+use crate::advisory::{
+    Advisory, AdvisoryQuality, AdvisoryQualityReport, PrereleasePolicy, check_advisory_quality,
+    extract_all_fixed_versions, identify_vuln_versions, req_matches, same_version_ignoring_build,
+    severity_rank,
+};
+use crate::analysis::{
+    FixSelection, VersionDelta, classify_adoption, fix_semver_compatible, select_fix_versions,
+    version_delta,
+};
+use crate::database::DownstreamVersionInfo;
+use crate::stats::{LagStats, compute_lag_stats, format_float};
+use crate::store::VersionStore;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Withdrawn,
+    CrateNotFound,
+    NoFixedVersions,
+    NoFixTimes,
+    NoSummaryT0,
+    NoVulnVersions,
+    DuplicateInGroup,
+    ConflictingAdvisory,
+}
+
+impl SkipReason {
+    pub const ALL: [SkipReason; 8] = [
+        SkipReason::Withdrawn,
+        SkipReason::CrateNotFound,
+        SkipReason::NoFixedVersions,
+        SkipReason::NoFixTimes,
+        SkipReason::NoSummaryT0,
+        SkipReason::NoVulnVersions,
+        SkipReason::DuplicateInGroup,
+        SkipReason::ConflictingAdvisory,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::Withdrawn => "withdrawn",
+            SkipReason::CrateNotFound => "crate_not_found",
+            SkipReason::NoFixedVersions => "no_fixed_versions",
+            SkipReason::NoFixTimes => "no_fix_times",
+            SkipReason::NoSummaryT0 => "no_summary_t0",
+            SkipReason::NoVulnVersions => "no_vuln_versions",
+            SkipReason::DuplicateInGroup => "duplicate_in_group",
+            SkipReason::ConflictingAdvisory => "conflicting_advisory",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdoptionKind {
+    Compatible,
+    MinorBump,
+    MajorBump,
+    Unknown,
+}
+
+impl AdoptionKind {
+    pub const ALL: [AdoptionKind; 4] = [
+        AdoptionKind::Compatible,
+        AdoptionKind::MinorBump,
+        AdoptionKind::MajorBump,
+        AdoptionKind::Unknown,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdoptionKind::Compatible => "compatible",
+            AdoptionKind::MinorBump => "minor_bump",
+            AdoptionKind::MajorBump => "major_bump",
+            AdoptionKind::Unknown => "unknown",
+        }
+    }
+}
+
+// Which matching branch accepted a dependency req as carrying the fix: the req admits
+// the fix but its minimum is still below it (a loosened req), the req admits the fix
+// and its minimum is at or past it (an explicit bump), or only the estimated minimum
+// of a req that does not admit the fix is at or past it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+pub enum AdoptionEvidence {
+    #[serde(rename = "req_matches_fix")]
+    ReqMatches,
+    #[serde(rename = "min_bumped_to_fix")]
+    MinBumped,
+    #[serde(rename = "estimated_min_ge_fix")]
+    EstimatedMin,
+}
+
+impl AdoptionEvidence {
+    pub const ALL: [AdoptionEvidence; 3] = [
+        AdoptionEvidence::ReqMatches,
+        AdoptionEvidence::MinBumped,
+        AdoptionEvidence::EstimatedMin,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdoptionEvidence::ReqMatches => "req_matches_fix",
+            AdoptionEvidence::MinBumped => "min_bumped_to_fix",
+            AdoptionEvidence::EstimatedMin => "estimated_min_ge_fix",
+        }
+    }
+
+    pub fn classify(
+        req: Option<&VersionReq>,
+        estimated_min: Option<&Version>,
+        fix: &Version,
+    ) -> Option<AdoptionEvidence> {
+        let matches = req.is_some_and(|r| r.matches(fix));
+        let min_ge_fix = estimated_min.is_some_and(|m| m >= fix);
+        match (matches, min_ge_fix) {
+            (true, true) => Some(AdoptionEvidence::MinBumped),
+            (true, false) => Some(AdoptionEvidence::ReqMatches),
+            (false, true) => Some(AdoptionEvidence::EstimatedMin),
+            (false, false) => None,
+        }
+    }
+}
+
+// Which evidence counts as a downstream release having adopted the fix: the req admits
+// the fixed version, the req's minimum bound reaches it, or either one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FixMatchPolicy {
+    StrictReq,
+    MinBump,
+    Either,
+}
+
+impl FixMatchPolicy {
+    pub fn accepts(self, evidence: AdoptionEvidence) -> bool {
+        match self {
+            FixMatchPolicy::StrictReq => evidence != AdoptionEvidence::EstimatedMin,
+            FixMatchPolicy::MinBump => evidence != AdoptionEvidence::ReqMatches,
+            FixMatchPolicy::Either => true,
+        }
+    }
+}
+
+// What a history does with several dependency rows for the same downstream version, e.g.
+// target-specific dependencies: keep only the least restrictive req, or one entry per
+// distinct req. Identical rows always collapse.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateReqs {
+    #[default]
+    LeastRestrictive,
+    PerReq,
+}
+
+impl DuplicateReqs {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DuplicateReqs::LeastRestrictive => "least-restrictive",
+            DuplicateReqs::PerReq => "per-req",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum T0Kind {
+    FixRelease,
+    AdvisoryDate,
+    MaxOfBoth,
+}
+
+impl T0Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            T0Kind::FixRelease => "fix-release",
+            T0Kind::AdvisoryDate => "advisory-date",
+            T0Kind::MaxOfBoth => "max-of-both",
+        }
+    }
+}
+
+// `Strict` needs the req to stop admitting every vulnerable version; `FirstResolvable`
+// takes the first release after a fix whose req admits it, so a range that picks the fix
+// up through semver compatibility counts too. `Both` emits the two row sets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LagMode {
+    Strict,
+    FirstResolvable,
+    Both,
+}
+
+impl LagMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LagMode::Strict => "strict",
+            LagMode::FirstResolvable => "first-resolvable",
+            LagMode::Both => "both",
+        }
+    }
+
+    // The modes rows are emitted for, the one run totals follow first.
+    pub fn row_modes(self) -> &'static [LagMode] {
+        match self {
+            LagMode::Strict => &[LagMode::Strict],
+            LagMode::FirstResolvable => &[LagMode::FirstResolvable],
+            LagMode::Both => &[LagMode::Strict, LagMode::FirstResolvable],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LagUnit {
+    Days,
+    Hours,
+    FractionalDays,
+}
+
+impl LagUnit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LagUnit::Days => "days",
+            LagUnit::Hours => "hours",
+            LagUnit::FractionalDays => "fractional-days",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LagUnit::Days | LagUnit::FractionalDays => "days",
+            LagUnit::Hours => "hours",
+        }
+    }
+
+    pub fn column(self) -> &'static str {
+        match self {
+            LagUnit::Days => "lag_days",
+            LagUnit::Hours => "lag_hours",
+            LagUnit::FractionalDays => "lag_days_frac",
+        }
+    }
+
+    pub fn convert(self, lag_secs: i64) -> f64 {
+        match self {
+            LagUnit::Days => whole_days(lag_secs) as f64,
+            LagUnit::Hours => whole_hours(lag_secs) as f64,
+            LagUnit::FractionalDays => fractional_days(lag_secs),
+        }
+    }
+
+    pub fn format(self, v: f64) -> String {
+        match self {
+            LagUnit::Days | LagUnit::Hours => format!("{}", v as i64),
+            LagUnit::FractionalDays => format_float(v),
+        }
+    }
+}
+
+// Whole units truncate toward zero like `TimeDelta::num_days`, so 23h is 0 days
+// and -23h is also 0 days; the sign of a lag is always taken from the seconds.
+pub fn whole_days(lag_secs: i64) -> i64 {
+    lag_secs / 86_400
+}
+
+pub fn whole_hours(lag_secs: i64) -> i64 {
+    lag_secs / 3_600
+}
+
+pub fn fractional_days(lag_secs: i64) -> f64 {
+    lag_secs as f64 / 86_400.0
+}
+
+// Decision trace for `--explain`. Lines carry no wall-clock times or cache state, so two
+// traces of the same advisory against the same snapshot diff cleanly across code changes.
+#[derive(Default)]
+pub struct ExplainSink {
+    lines: Vec<String>,
+}
+
+impl ExplainSink {
+    pub fn new() -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    pub fn line(&mut self, msg: impl Into<String>) {
+        self.lines.push(msg.into());
+    }
+
+    pub fn section(&mut self, title: &str) {
+        if !self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.lines.push(format!("== {title}"));
+    }
+
+    // The caller creates the parent directory.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+// A published version string equal to the wanted version up to build metadata.
+pub struct EquivalentVersion {
+    pub num: String,
+    pub created_at: DateTime<Utc>,
+    pub exact: bool,
+    // Published variants of the version, the chosen one included.
+    pub variants: usize,
+}
+
+impl EquivalentVersion {
+    pub fn describe(&self) -> String {
+        if self.exact {
+            format!("{} exact", self.num)
+        } else {
+            format!("{} earliest of {} variants", self.num, self.variants)
+        }
+    }
+}
+
+// `1.2.3` and `1.2.3+build5` are one release to semver but separate rows on crates.io.
+// The exact string wins when it was published; otherwise the earliest-published variant,
+// ties broken by semver order and then the raw string.
+pub fn resolve_equivalent_version_string(
+    version_times: &[(String, DateTime<Utc>)],
+    wanted: &Version,
+) -> Option<EquivalentVersion> {
+    let wanted_str = wanted.to_string();
+    let candidates: Vec<(Version, &String, DateTime<Utc>)> = version_times
+        .iter()
+        .filter_map(|(s, t)| {
+            let v = Version::parse(s).ok()?;
+            same_version_ignoring_build(&v, wanted).then_some((v, s, *t))
+        })
+        .collect();
+    let variants = candidates.len();
+    let exact = candidates.iter().find(|(_, s, _)| **s == wanted_str);
+    let (pick, is_exact) = match exact {
+        Some(c) => (c, true),
+        None => (
+            candidates.iter().min_by(|a, b| {
+                a.2.cmp(&b.2)
+                    .then_with(|| a.0.cmp(&b.0))
+                    .then_with(|| a.1.cmp(b.1))
+            })?,
+            false,
+        ),
+    };
+    Some(EquivalentVersion {
+        num: pick.1.clone(),
+        created_at: pick.2,
+        exact: is_exact,
+        variants,
+    })
+}
+
+// When the flaw first shipped: the earliest publication of any vulnerable version.
+pub fn first_vulnerable_release(
+    version_times: &[(String, DateTime<Utc>)],
+    vuln_versions: &[Version],
+) -> Option<DateTime<Utc>> {
+    version_times
+        .iter()
+        .filter(|(s, _)| {
+            Version::parse(s).is_ok_and(|v| {
+                vuln_versions
+                    .iter()
+                    .any(|w| same_version_ignoring_build(&v, w))
+            })
+        })
+        .map(|(_, t)| *t)
+        .min()
+}
+
+pub fn parse_published_versions(all_versions: &[String]) -> Vec<(Version, String)> {
+    let mut out: Vec<(Version, String)> = all_versions
+        .iter()
+        .filter_map(|s| Version::parse(s).ok().map(|v| (v, s.clone())))
+        .collect();
+    out.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    out
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixedVersionSource {
+    Exact,
+    PartialReq,
+    PublishedRange,
+    None,
+}
+
+impl FixedVersionSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FixedVersionSource::Exact => "exact",
+            FixedVersionSource::PartialReq => "partial_req",
+            FixedVersionSource::PublishedRange => "published_range",
+            FixedVersionSource::None => "none",
+        }
+    }
+}
+
+// Fixed versions readable from the advisory alone. `PublishedRange` means the patched
+// reqs only bound a range, so the crate's published versions are needed to pick one.
+pub fn static_fixed_versions(
+    patched: &[String],
+    policy: PrereleasePolicy,
+) -> (Vec<Version>, FixedVersionSource) {
+    let fixed = extract_all_fixed_versions(patched, policy);
+    let source = if fixed.is_empty() {
+        if patched.iter().any(|s| VersionReq::parse(s).is_ok()) {
+            FixedVersionSource::PublishedRange
+        } else {
+            FixedVersionSource::None
+        }
+    } else if legacy_has_fixed_versions(patched) {
+        FixedVersionSource::Exact
+    } else {
+        FixedVersionSource::PartialReq
+    };
+    (fixed, source)
+}
+
+fn legacy_has_fixed_versions(patched: &[String]) -> bool {
+    patched.iter().any(|p| {
+        Version::parse(p).is_ok()
+            || VersionReq::parse(p).is_ok_and(|req| {
+                req.comparators.iter().any(|c| {
+                    c.minor.is_some()
+                        && c.patch.is_some()
+                        && matches!(
+                            c.op,
+                            Op::Exact | Op::Greater | Op::GreaterEq | Op::Tilde | Op::Caret
+                        )
+                })
+            })
+    })
+}
+
+// First published version admitted by a patched req, falling back to the first
+// release at or above the req's lower bound when none matches exactly. The flag
+// reports whether the lower-bound fallback was used.
+pub fn first_published_matching<'a>(
+    req_str: &str,
+    published: &'a [(Version, String)],
+    policy: PrereleasePolicy,
+) -> Option<(&'a Version, &'a String, bool)> {
+    let req = VersionReq::parse(req_str).ok()?;
+    if let Some((v, s)) = published.iter().find(|(v, _)| req_matches(&req, v, policy)) {
+        return Some((v, s, false));
+    }
+    if req_str.contains('<') {
+        return None;
+    }
+    let min_v = estimate_min_version(req_str)?;
+    published
+        .iter()
+        .filter(|(v, _)| v.pre.is_empty() || policy != PrereleasePolicy::Exclude)
+        .find(|(v, _)| *v >= min_v)
+        .map(|(v, s)| (v, s, true))
+}
+
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct ConstraintBreakdown {
+    pub downstream_crates_with_history: usize,
+    pub affected_edges: usize,
+    pub locked_out_edges: usize,
+    pub break_rate_percent: usize,
+    pub affected_req_shape: [usize; ReqShape::ALL.len()],
+    pub unknown_req_unparseable: usize,
+    pub whatif_resolved_locked_edges: usize,
+    pub whatif_unlocked_1_line: usize,
+    pub whatif_unlocked_2_lines: usize,
+    pub whatif_unlocked_all_lines: usize,
+    // Downstream crates whose rows were split or out of time order in the input and had
+    // to be regrouped; anything but zero means a backend lost the SQL ordering.
+    pub regrouped_crates: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReqShape {
+    Exact,
+    Tilde,
+    Caret00x,
+    Caret0x,
+    CaretGe1,
+    Wildcard,
+    RangeWithUpper,
+    GeOnly,
+    MultiComparator,
+    Unparseable,
+}
+
+impl ReqShape {
+    pub const ALL: [ReqShape; 10] = [
+        ReqShape::Exact,
+        ReqShape::Tilde,
+        ReqShape::Caret00x,
+        ReqShape::Caret0x,
+        ReqShape::CaretGe1,
+        ReqShape::Wildcard,
+        ReqShape::RangeWithUpper,
+        ReqShape::GeOnly,
+        ReqShape::MultiComparator,
+        ReqShape::Unparseable,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReqShape::Exact => "exact",
+            ReqShape::Tilde => "tilde",
+            ReqShape::Caret00x => "caret_0_0_x",
+            ReqShape::Caret0x => "caret_0_x",
+            ReqShape::CaretGe1 => "caret_ge1",
+            ReqShape::Wildcard => "wildcard",
+            ReqShape::RangeWithUpper => "range_with_upper",
+            ReqShape::GeOnly => "ge_only",
+            ReqShape::MultiComparator => "multi_comparator",
+            ReqShape::Unparseable => "unparseable",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReqShape::Exact => "=x.y.z",
+            ReqShape::Tilde => "~x.y",
+            ReqShape::Caret00x => "^0.0.x",
+            ReqShape::Caret0x => "^0.x",
+            ReqShape::CaretGe1 => "^1+",
+            ReqShape::Wildcard => "* / x.*",
+            ReqShape::RangeWithUpper => ">=a, <b",
+            ReqShape::GeOnly => ">=a",
+            ReqShape::MultiComparator => "multi",
+            ReqShape::Unparseable => "unparseable",
+        }
+    }
+}
+
+// Bare `1.2` parses as a caret req, `*` as no comparators at all.
+pub fn classify_req_shape(s: &str) -> ReqShape {
+    let Ok(req) = VersionReq::parse(s.trim()) else {
+        return ReqShape::Unparseable;
+    };
+    let is_range_op = |op: Op| matches!(op, Op::Greater | Op::GreaterEq | Op::Less | Op::LessEq);
+    let is_upper = |op: Op| matches!(op, Op::Less | Op::LessEq);
+    match req.comparators.as_slice() {
+        [] => ReqShape::Wildcard,
+        [c] => match c.op {
+            Op::Exact => ReqShape::Exact,
+            Op::Tilde => ReqShape::Tilde,
+            Op::Wildcard => ReqShape::Wildcard,
+            Op::Caret if c.major > 0 => ReqShape::CaretGe1,
+            Op::Caret if c.minor == Some(0) && c.patch.is_some() => ReqShape::Caret00x,
+            Op::Caret => ReqShape::Caret0x,
+            Op::Greater | Op::GreaterEq => ReqShape::GeOnly,
+            Op::Less | Op::LessEq => ReqShape::RangeWithUpper,
+            _ => ReqShape::MultiComparator,
+        },
+        cs if cs.iter().all(|c| is_range_op(c.op)) && cs.iter().any(|c| is_upper(c.op)) => {
+            ReqShape::RangeWithUpper
+        }
+        _ => ReqShape::MultiComparator,
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ConstraintEdge {
+    pub downstream_crate: String,
+    pub downstream_version: String,
+    pub dep_req: String,
+    pub req_shape: ReqShape,
+    pub locked_out: bool,
+    pub smallest_satisfying_fix: Option<Version>,
+}
+
+pub fn compute_constraint_breakdown(
+    fix_time: DateTime<Utc>,
+    vuln_versions: &[Version],
+    fixed_versions: &[Version],
+    downstream: &[DownstreamVersionInfo],
+    mut edges: Option<&mut Vec<ConstraintEdge>>,
+    published: Option<&[(Version, DateTime<Utc>)]>,
+) -> ConstraintBreakdown {
+    let mut c = ConstraintBreakdown::default();
+    // (major, minor) of each locked-out edge's resolution and whether patch+1 on that line would match.
+    let mut whatif: Vec<((u64, u64), bool)> = Vec::new();
+
+    let (rows, regrouped) = group_by_crate(downstream);
+    c.regrouped_crates = regrouped;
+
+    let mut current: Option<&str> = None;
+    let mut last_before: Option<&DownstreamVersionInfo> = None;
+
+    let mut process = |row: Option<&DownstreamVersionInfo>, c: &mut ConstraintBreakdown| {
+        let Some(row) = row else {
+            return;
+        };
+        c.downstream_crates_with_history += 1;
+
+        let Ok(req) = VersionReq::parse(&row.dep_req) else {
+            c.unknown_req_unparseable += 1;
+            return;
+        };
+
+        let affected = vuln_versions.iter().any(|v| req.matches(v));
+        if !affected {
+            return;
+        }
+        c.affected_edges += 1;
+
+        let shape = classify_req_shape(&row.dep_req);
+        c.affected_req_shape[shape as usize] += 1;
+
+        let smallest_fix = fixed_versions.iter().filter(|v| req.matches(v)).min();
+        if smallest_fix.is_none() {
+            c.locked_out_edges += 1;
+            if let Some(resolved) = published.and_then(|published| {
+                published
+                    .iter()
+                    .filter(|(v, t)| *t < fix_time && req.matches(v))
+                    .map(|(v, _)| v)
+                    .max()
+            }) {
+                let backport = Version::new(resolved.major, resolved.minor, resolved.patch + 1);
+                whatif.push(((resolved.major, resolved.minor), req.matches(&backport)));
+            }
+        }
+
+        if let Some(edges) = edges.as_mut() {
+            edges.push(ConstraintEdge {
+                downstream_crate: row.crate_name.clone(),
+                downstream_version: row.version.clone(),
+                dep_req: row.dep_req.clone(),
+                req_shape: shape,
+                locked_out: smallest_fix.is_none(),
+                smallest_satisfying_fix: smallest_fix.cloned(),
+            });
+        }
+    };
+
+    for row in rows {
+        match current {
+            None => {
+                current = Some(row.crate_name.as_str());
+                if row.created_at < fix_time {
+                    last_before = Some(row);
+                }
+            }
+            Some(name) if name == row.crate_name.as_str() => {
+                if row.created_at < fix_time {
+                    last_before = Some(row);
+                }
+            }
+            Some(_) => {
+                process(last_before.take(), &mut c);
+                current = Some(row.crate_name.as_str());
+                if row.created_at < fix_time {
+                    last_before = Some(row);
+                }
+            }
+        }
+    }
+    process(last_before.take(), &mut c);
+
+    // Backporting to k lines means the k affected minor lines closest to the fix.
+    let mut lines: Vec<(u64, u64)> = whatif.iter().map(|(line, _)| *line).collect();
+    lines.sort_unstable_by(|a, b| b.cmp(a));
+    lines.dedup();
+    let unlocked_within = |k: usize| {
+        whatif
+            .iter()
+            .filter(|(line, unlocked)| *unlocked && lines.iter().take(k).any(|l| l == line))
+            .count()
+    };
+    c.whatif_resolved_locked_edges = whatif.len();
+    c.whatif_unlocked_1_line = unlocked_within(1);
+    c.whatif_unlocked_2_lines = unlocked_within(2);
+    c.whatif_unlocked_all_lines = unlocked_within(lines.len());
+
+    if c.affected_edges == 0 {
+        return c;
+    }
+    c.break_rate_percent = (c.locked_out_edges * 100) / c.affected_edges;
+    c
+}
+
+// Constraint breakdowns summed over advisories, overall or per severity bucket.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConstraintTotals {
+    pub downstream_crates_with_history: usize,
+    pub affected_edges: usize,
+    pub locked_out_edges: usize,
+    pub affected_req_shape: [usize; ReqShape::ALL.len()],
+    pub unknown_req_unparseable: usize,
+    pub whatif_resolved_locked_edges: usize,
+    pub whatif_unlocked_1_line: usize,
+    pub whatif_unlocked_2_lines: usize,
+    pub whatif_unlocked_all_lines: usize,
+}
+
+impl ConstraintTotals {
+    pub fn add(&mut self, c: &ConstraintBreakdown) {
+        self.downstream_crates_with_history += c.downstream_crates_with_history;
+        self.affected_edges += c.affected_edges;
+        self.locked_out_edges += c.locked_out_edges;
+        for (total, n) in self.affected_req_shape.iter_mut().zip(c.affected_req_shape) {
+            *total += n;
+        }
+        self.unknown_req_unparseable += c.unknown_req_unparseable;
+        self.whatif_resolved_locked_edges += c.whatif_resolved_locked_edges;
+        self.whatif_unlocked_1_line += c.whatif_unlocked_1_line;
+        self.whatif_unlocked_2_lines += c.whatif_unlocked_2_lines;
+        self.whatif_unlocked_all_lines += c.whatif_unlocked_all_lines;
+    }
+
+    pub fn merge(&mut self, other: &ConstraintTotals) {
+        self.downstream_crates_with_history += other.downstream_crates_with_history;
+        self.affected_edges += other.affected_edges;
+        self.locked_out_edges += other.locked_out_edges;
+        for (total, n) in self
+            .affected_req_shape
+            .iter_mut()
+            .zip(other.affected_req_shape)
+        {
+            *total += n;
+        }
+        self.unknown_req_unparseable += other.unknown_req_unparseable;
+        self.whatif_resolved_locked_edges += other.whatif_resolved_locked_edges;
+        self.whatif_unlocked_1_line += other.whatif_unlocked_1_line;
+        self.whatif_unlocked_2_lines += other.whatif_unlocked_2_lines;
+        self.whatif_unlocked_all_lines += other.whatif_unlocked_all_lines;
+    }
+
+    pub fn break_rate_percent(&self) -> usize {
+        if self.affected_edges == 0 {
+            return 0;
+        }
+        (self.locked_out_edges * 100) / self.affected_edges
+    }
+
+    // Severity buckets from INFO up to CRITICAL, then unrecognized labels by name.
+    pub fn by_severity_rank(
+        totals: &HashMap<String, ConstraintTotals>,
+    ) -> Vec<(&String, &ConstraintTotals)> {
+        let mut rows: Vec<_> = totals.iter().collect();
+        rows.sort_by_key(|(sev, _)| (severity_rank(sev).unwrap_or(u8::MAX), *sev));
+        rows
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct HorizonBreak {
+    pub horizon_days: i64,
+    pub affected_edges: usize,
+    pub locked_out_edges: usize,
+}
+
+impl HorizonBreak {
+    pub fn new(horizon_days: i64) -> Self {
+        Self {
+            horizon_days,
+            affected_edges: 0,
+            locked_out_edges: 0,
+        }
+    }
+
+    pub fn break_rate_percent(&self) -> usize {
+        (self.locked_out_edges * 100)
+            .checked_div(self.affected_edges)
+            .unwrap_or(0)
+    }
+}
+
+// Same grouping as compute_constraint_breakdown, but each downstream crate keeps one
+// "latest version before cutoff" slot per horizon in a single pass over the grouped rows.
+pub fn compute_constraint_horizons(
+    fix_time: DateTime<Utc>,
+    horizons_days: &[i64],
+    vuln_versions: &[Version],
+    fixed_versions: &[Version],
+    downstream: &[DownstreamVersionInfo],
+) -> Vec<HorizonBreak> {
+    let cutoffs: Vec<_> = horizons_days
+        .iter()
+        .map(|d| fix_time + chrono::Duration::days(*d))
+        .collect();
+    let mut out: Vec<HorizonBreak> = horizons_days
+        .iter()
+        .map(|&d| HorizonBreak::new(d))
+        .collect();
+    let mut latest: Vec<Option<&DownstreamVersionInfo>> = vec![None; cutoffs.len()];
+
+    let flush = |latest: &mut [Option<&DownstreamVersionInfo>], out: &mut [HorizonBreak]| {
+        for (slot, h) in latest.iter_mut().zip(out.iter_mut()) {
+            let Some(row) = slot.take() else {
+                continue;
+            };
+            let Ok(req) = VersionReq::parse(&row.dep_req) else {
+                continue;
+            };
+            if !vuln_versions.iter().any(|v| req.matches(v)) {
+                continue;
+            }
+            h.affected_edges += 1;
+            if !fixed_versions.iter().any(|v| req.matches(v)) {
+                h.locked_out_edges += 1;
+            }
+        }
+    };
+
+    let (rows, _) = group_by_crate(downstream);
+    let mut current: Option<&str> = None;
+    for row in rows {
+        if current != Some(row.crate_name.as_str()) {
+            flush(&mut latest, &mut out);
+            current = Some(row.crate_name.as_str());
+        }
+        for (slot, cutoff) in latest.iter_mut().zip(&cutoffs) {
+            if row.created_at < *cutoff {
+                *slot = Some(row);
+            }
+        }
+    }
+    flush(&mut latest, &mut out);
+    out
+}
+
+// Rows are read as runs per crate in time order, which the SQL ORDER BY guarantees but
+// other backends, and histories merged across renames, need not. Regroup rather than
+// count a crate once per run; the second value is how many crates needed it.
+fn group_by_crate(downstream: &[DownstreamVersionInfo]) -> (Vec<&DownstreamVersionInfo>, usize) {
+    let mut rows: Vec<&DownstreamVersionInfo> = downstream.iter().collect();
+    let misordered = misordered_crates(&rows);
+    if misordered > 0 {
+        rows.sort_by(|a, b| {
+            a.crate_name
+                .cmp(&b.crate_name)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+        });
+    }
+    debug_assert_eq!(
+        misordered_crates(&rows),
+        0,
+        "downstream rows must form one time-ordered run per crate"
+    );
+    (rows, misordered)
+}
+
+// Crates whose rows do not form one contiguous, time-ordered run.
+fn misordered_crates(rows: &[&DownstreamVersionInfo]) -> usize {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut bad: HashSet<&str> = HashSet::new();
+    let mut prev: Option<&DownstreamVersionInfo> = None;
+    for row in rows {
+        let name = row.crate_name.as_str();
+        match prev {
+            Some(p) if p.crate_name == row.crate_name => {
+                if row.created_at < p.created_at {
+                    bad.insert(name);
+                }
+            }
+            _ => {
+                if !seen.insert(name) {
+                    bad.insert(name);
+                }
+            }
+        }
+        prev = Some(row);
+    }
+    bad.len()
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct StrictLagRow {
+    pub downstream_crate: String,
+    pub downstream_version: String,
+    pub downstream_time: DateTime<Utc>,
+    pub lag_secs: i64,
+    pub original_req: String,
+    pub fixed_req: String,
+    pub matched_fix_version: String,
+    pub matched_fix_time: DateTime<Utc>,
+    pub t0: DateTime<Utc>,
+    pub t0_kind: T0Kind,
+    pub negative: bool,
+    pub adoption_index: usize,
+    pub adoption_kind: AdoptionKind,
+    pub evidence: AdoptionEvidence,
+    pub lag_mode: LagMode,
+    // Median days between the downstream crate's releases in the year before the fix.
+    pub cadence_days: Option<f64>,
+    // First downstream release after the matched fix; None for negative rows.
+    pub first_opportunity: Option<FirstOpportunity>,
+}
+
+impl StrictLagRow {
+    // Lag in units of the downstream crate's usual gap between releases.
+    pub fn lag_over_cadence(&self) -> Option<f64> {
+        self.cadence_days
+            .map(|c| self.lag_secs as f64 / 86_400.0 / c)
+    }
+
+    // Step from the minimum of the last vulnerable req up to the matched fix version.
+    pub fn fix_delta(&self) -> Option<VersionDelta> {
+        let from = estimate_min_version(&self.original_req)?;
+        let to = Version::parse(&self.matched_fix_version).ok()?;
+        Some(version_delta(&from, &to))
+    }
+}
+
+// The downstream crate's first release at or after a fix, and how many of its releases
+// before the adopting one came out after the fix, i.e. shipped still on a vulnerable req.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+pub struct FirstOpportunity {
+    pub time: DateTime<Utc>,
+    pub skipped_releases: usize,
+}
+
+impl FirstOpportunity {
+    pub fn lag_secs(&self, adopted_at: DateTime<Utc>) -> i64 {
+        (adopted_at - self.time).num_seconds()
+    }
+}
+
+// Releases at the same instant count once; None when the adoption predates the fix.
+pub fn first_opportunity(
+    release_times: &[DateTime<Utc>],
+    fix_time: DateTime<Utc>,
+    adopted_at: DateTime<Utc>,
+) -> Option<FirstOpportunity> {
+    if adopted_at < fix_time {
+        return None;
+    }
+    let mut times: Vec<DateTime<Utc>> = release_times
+        .iter()
+        .copied()
+        .filter(|t| *t >= fix_time && *t < adopted_at)
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+    Some(FirstOpportunity {
+        time: times.first().copied().unwrap_or(adopted_at),
+        skipped_releases: times.len(),
+    })
+}
+
+// Median gap in days between consecutive releases in the year before `before`. Releases
+// at the same instant count once; with fewer than 3 releases in the window there is no
+// cadence to speak of.
+pub fn release_cadence_days(release_times: &[DateTime<Utc>], before: DateTime<Utc>) -> Option<f64> {
+    let since = before - chrono::Duration::days(365);
+    let mut times: Vec<DateTime<Utc>> = release_times
+        .iter()
+        .copied()
+        .filter(|t| *t >= since && *t < before)
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+    if times.len() < 3 {
+        return None;
+    }
+    let mut gaps: Vec<i64> = times
+        .windows(2)
+        .map(|w| (w[1] - w[0]).num_seconds())
+        .collect();
+    gaps.sort_unstable();
+    let mid = gaps.len() / 2;
+    let median = if gaps.len().is_multiple_of(2) {
+        (gaps[mid - 1] + gaps[mid]) as f64 / 2.0
+    } else {
+        gaps[mid] as f64
+    };
+    Some(median / 86_400.0)
+}
+
+pub fn resolve_t0(
+    kind: T0Kind,
+    fix_time: DateTime<Utc>,
+    advisory_time: Option<DateTime<Utc>>,
+) -> (DateTime<Utc>, T0Kind) {
+    match (kind, advisory_time) {
+        (T0Kind::FixRelease, _) | (_, None) => (fix_time, T0Kind::FixRelease),
+        (T0Kind::AdvisoryDate, Some(at)) => (at, T0Kind::AdvisoryDate),
+        (T0Kind::MaxOfBoth, Some(at)) => (fix_time.max(at), T0Kind::MaxOfBoth),
+    }
+}
+
+// How lags are measured for one advisory; everything except the histories themselves.
+#[derive(Clone, Copy)]
+pub struct StrictLagOptions {
+    pub t0_kind: T0Kind,
+    pub advisory_time: Option<DateTime<Utc>>,
+    pub all_adoptions: bool,
+    pub history_cutoff: Option<DateTime<Utc>>,
+    pub policy: FixMatchPolicy,
+    pub duplicate_reqs: DuplicateReqs,
+}
+
+// Downstream crates with a release before the earliest fix, split by whether any req in
+// their history admits a vulnerable version. `unparseable` crates have no parseable req.
+// `post_fix_new` counts the others: crates first seen at or after the earliest fix whose
+// first req already excludes every vulnerable version, so they never had a window.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Serialize)]
+pub struct ExposureCounts {
+    pub exposed: usize,
+    pub never_exposed: usize,
+    pub unparseable: usize,
+    pub post_fix_new: usize,
+}
+
+impl ExposureCounts {
+    pub fn add(&mut self, other: ExposureCounts) {
+        self.exposed += other.exposed;
+        self.never_exposed += other.never_exposed;
+        self.unparseable += other.unparseable;
+        self.post_fix_new += other.post_fix_new;
+    }
+}
+
+// Strict lag rows of one advisory, with `regressions` counting fixed -> vulnerable req
+// moves and `affected` the crates that ever had a vulnerable req.
+pub struct StrictLagScan {
+    pub rows: Vec<StrictLagRow>,
+    pub regressions: usize,
+    pub affected: usize,
+    pub exposure: ExposureCounts,
+    // History entries dropped as duplicates of another row for the same version.
+    pub merged_duplicates: usize,
+}
+
+// Sort key putting the req that admits the oldest versions first; unparseable reqs last.
+fn restrictiveness(req: &str) -> (bool, Option<Version>) {
+    let min = estimate_min_version(req);
+    (min.is_none(), min)
+}
+
+// Each downstream crate's releases by created_at, ties by version number, with duplicate
+// rows for one version merged per `duplicates`. Also returns how many rows were merged.
+pub fn downstream_histories(
+    downstream: &[DownstreamVersionInfo],
+    duplicates: DuplicateReqs,
+) -> (BTreeMap<&str, Vec<&DownstreamVersionInfo>>, usize) {
+    let mut by_crate: BTreeMap<&str, Vec<&DownstreamVersionInfo>> = BTreeMap::new();
+    for row in downstream {
+        by_crate
+            .entry(row.crate_name.as_str())
+            .or_default()
+            .push(row);
+    }
+    let mut merged = 0usize;
+    for history in by_crate.values_mut() {
+        history.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.version.cmp(&b.version))
+                .then_with(|| restrictiveness(&a.dep_req).cmp(&restrictiveness(&b.dep_req)))
+                .then_with(|| a.dep_req.cmp(&b.dep_req))
+        });
+        let before = history.len();
+        match duplicates {
+            DuplicateReqs::LeastRestrictive => history.dedup_by(|a, b| a.version == b.version),
+            DuplicateReqs::PerReq => {
+                history.dedup_by(|a, b| a.version == b.version && a.dep_req == b.dep_req)
+            }
+        }
+        merged += before - history.len();
+    }
+    (by_crate, merged)
+}
+
+pub fn compute_strict_lags_for_target(
+    fix_times: &BTreeMap<Version, DateTime<Utc>>,
+    vuln_versions: &[Version],
+    downstream: &[DownstreamVersionInfo],
+    opts: StrictLagOptions,
+    mut explain: Option<&mut ExplainSink>,
+) -> StrictLagScan {
+    let StrictLagOptions {
+        t0_kind,
+        advisory_time,
+        all_adoptions,
+        history_cutoff,
+        policy,
+        duplicate_reqs,
+    } = opts;
+    let earliest_fix = fix_times.values().min().copied();
+    let mut outputs = Vec::new();
+    let mut regressions = 0usize;
+    let mut affected = 0usize;
+    let mut exposure = ExposureCounts::default();
+    let (histories, merged_duplicates) = downstream_histories(downstream, duplicate_reqs);
+    for (downstream_crate, history) in histories {
+        if let Some(x) = explain.as_deref_mut() {
+            x.section(&format!("downstream {downstream_crate}"));
+        }
+        // Appends the decision for the current history row to the trace.
+        let mut note = |item: &DownstreamVersionInfo, decision: String| {
+            if let Some(x) = explain.as_deref_mut() {
+                x.line(format!(
+                    "{} {} req={:?}: {decision}",
+                    item.version, item.created_at, item.dep_req
+                ));
+            }
+        };
+
+        let exposure_counted =
+            earliest_fix.is_some_and(|t| history.first().is_some_and(|r| r.created_at < t));
+        let post_fix_new = earliest_fix.is_some_and(|t| {
+            history.first().is_some_and(|r| {
+                r.created_at >= t
+                    && VersionReq::parse(&r.dep_req)
+                        .is_ok_and(|req| !vuln_versions.iter().any(|v| req.matches(v)))
+            })
+        });
+        let release_times: Vec<DateTime<Utc>> = history.iter().map(|r| r.created_at).collect();
+        let mut any_parseable = false;
+        let mut ever_affected = false;
+        let mut last_vuln_req: Option<String> = None;
+        let mut negative_recorded = false;
+        let mut adoptions = 0usize;
+        let mut currently_fixed = false;
+
+        for item in history {
+            if history_cutoff.is_some_and(|c| item.created_at >= c) {
+                note(item, "after withdrawn cutoff; history ends".to_string());
+                break;
+            }
+            let req = match VersionReq::parse(&item.dep_req) {
+                Ok(r) => r,
+                Err(e) => {
+                    note(item, format!("unparseable req ({e}); ignored"));
+                    continue;
+                }
+            };
+            any_parseable = true;
+
+            let first_vuln = vuln_versions.iter().find(|v| req.matches(v));
+
+            if let Some(v) = first_vuln {
+                let regression = currently_fixed;
+                if currently_fixed {
+                    regressions += 1;
+                    currently_fixed = false;
+                }
+                ever_affected = true;
+                last_vuln_req = Some(item.dep_req.clone());
+                note(
+                    item,
+                    format!(
+                        "vulnerable (admits {v}){}; no row",
+                        if regression { ", regression" } else { "" }
+                    ),
+                );
+                continue;
+            }
+
+            if !ever_affected {
+                note(
+                    item,
+                    "not vulnerable, never affected before; no row".to_string(),
+                );
+            } else {
+                type Match<'m> = (&'m Version, &'m DateTime<Utc>, AdoptionEvidence);
+                let mut best_match: Option<Match> = None;
+                // A req that already admits a fix published only later is a negative lag:
+                // usually a timestamp problem or an equivalent-version mismatch upstream.
+                let mut early_match: Option<Match> = None;
+                let estimated_min = estimate_min_version(&item.dep_req);
+
+                for (fv, ftime) in fix_times {
+                    let Some(evidence) =
+                        AdoptionEvidence::classify(Some(&req), estimated_min.as_ref(), fv)
+                            .filter(|e| policy.accepts(*e))
+                    else {
+                        continue;
+                    };
+
+                    let slot = if *ftime > item.created_at {
+                        &mut early_match
+                    } else {
+                        &mut best_match
+                    };
+                    match slot {
+                        None => *slot = Some((fv, ftime, evidence)),
+                        Some((_, best_time, _)) => {
+                            if ftime < *best_time {
+                                *slot = Some((fv, ftime, evidence));
+                            }
+                        }
+                    }
+                }
+
+                if let Some((matched_ver, matched_time, evidence)) = best_match
+                    && let Some(original_req) = last_vuln_req.take()
+                {
+                    // Under an advisory-date baseline a negative lag means the downstream
+                    // adopted the fix before disclosure, which is kept rather than dropped.
+                    let (t0, row_t0_kind) = resolve_t0(t0_kind, *matched_time, advisory_time);
+                    let lag_secs = (item.created_at - t0).num_seconds();
+                    let negative = lag_secs < 0 && row_t0_kind == T0Kind::FixRelease;
+                    if !negative {
+                        adoptions += 1;
+                        currently_fixed = true;
+                        if adoptions > 1 && !all_adoptions {
+                            note(
+                                item,
+                                format!(
+                                    "fixed by {matched_ver} ({}); adoption #{adoptions} not emitted without --all-adoptions",
+                                    evidence.as_str()
+                                ),
+                            );
+                            continue;
+                        }
+                    }
+
+                    let adoption_kind =
+                        classify_adoption(&original_req, &item.dep_req, matched_ver);
+                    note(
+                        item,
+                        format!(
+                            "fixed by {matched_ver} ({}); row emitted: adoption #{adoptions} original_req={original_req:?} t0={t0} ({}) lag_secs={lag_secs}{}",
+                            evidence.as_str(),
+                            row_t0_kind.as_str(),
+                            if negative { " negative" } else { "" }
+                        ),
+                    );
+                    outputs.push(StrictLagRow {
+                        downstream_crate: downstream_crate.to_string(),
+                        downstream_version: item.version.clone(),
+                        downstream_time: item.created_at,
+                        lag_secs,
+                        original_req,
+                        fixed_req: item.dep_req.clone(),
+                        matched_fix_version: matched_ver.to_string(),
+                        matched_fix_time: *matched_time,
+                        t0,
+                        t0_kind: row_t0_kind,
+                        negative,
+                        adoption_index: adoptions,
+                        adoption_kind,
+                        evidence,
+                        lag_mode: LagMode::Strict,
+                        cadence_days: release_cadence_days(&release_times, *matched_time),
+                        first_opportunity: first_opportunity(
+                            &release_times,
+                            *matched_time,
+                            item.created_at,
+                        ),
+                    });
+                    continue;
+                }
+
+                if best_match.is_none()
+                    && !negative_recorded
+                    && let Some((matched_ver, matched_time, evidence)) = early_match
+                    && let Some(original_req) = last_vuln_req.clone()
+                {
+                    let (t0, row_t0_kind) = resolve_t0(t0_kind, *matched_time, advisory_time);
+                    let adoption_kind =
+                        classify_adoption(&original_req, &item.dep_req, matched_ver);
+                    note(
+                        item,
+                        format!(
+                            "admits {matched_ver} ({}) published later at {matched_time}; negative row emitted",
+                            evidence.as_str()
+                        ),
+                    );
+                    outputs.push(StrictLagRow {
+                        downstream_crate: downstream_crate.to_string(),
+                        downstream_version: item.version.clone(),
+                        downstream_time: item.created_at,
+                        lag_secs: (item.created_at - t0).num_seconds(),
+                        original_req,
+                        fixed_req: item.dep_req.clone(),
+                        matched_fix_version: matched_ver.to_string(),
+                        matched_fix_time: *matched_time,
+                        t0,
+                        t0_kind: row_t0_kind,
+                        negative: true,
+                        adoption_index: 0,
+                        adoption_kind,
+                        evidence,
+                        lag_mode: LagMode::Strict,
+                        cadence_days: release_cadence_days(&release_times, *matched_time),
+                        first_opportunity: None,
+                    });
+                    negative_recorded = true;
+                    continue;
+                }
+
+                note(
+                    item,
+                    match (best_match, early_match) {
+                        (Some((v, _, _)), _) => {
+                            format!("fixed by {v}; already adopted, no row")
+                        }
+                        (None, Some((v, _, _))) if negative_recorded => format!(
+                            "admits {v} only before its release; negative row already recorded"
+                        ),
+                        (None, Some((v, _, _))) => {
+                            format!("admits {v} only before its release; already adopted, no row")
+                        }
+                        (None, None) => {
+                            "not vulnerable but admits no fixed version under the policy; no row"
+                                .to_string()
+                        }
+                    },
+                );
+            }
+        }
+        if ever_affected {
+            affected += 1;
+        }
+        if exposure_counted {
+            if ever_affected {
+                exposure.exposed += 1;
+            } else if any_parseable {
+                exposure.never_exposed += 1;
+            } else {
+                exposure.unparseable += 1;
+            }
+        } else if post_fix_new {
+            exposure.post_fix_new += 1;
+        }
+    }
+
+    outputs.sort_by(|a, b| {
+        a.downstream_crate
+            .cmp(&b.downstream_crate)
+            .then_with(|| a.adoption_index.cmp(&b.adoption_index))
+    });
+    StrictLagScan {
+        rows: outputs,
+        regressions,
+        affected,
+        exposure,
+        merged_duplicates,
+    }
+}
+
+// One row per downstream crate that had a vulnerable req: its first later release whose
+// req admits a fix already published, even if that req still admits vulnerable versions
+// too. The fix match policy does not apply; only the req decides what resolves. Returns
+// the rows and the number of crates that ever had a vulnerable req.
+pub fn compute_first_resolvable_lags_for_target(
+    fix_times: &BTreeMap<Version, DateTime<Utc>>,
+    vuln_versions: &[Version],
+    downstream: &[DownstreamVersionInfo],
+    opts: StrictLagOptions,
+) -> (Vec<StrictLagRow>, usize) {
+    let mut outputs = Vec::new();
+    let mut affected = 0usize;
+    let (histories, _) = downstream_histories(downstream, opts.duplicate_reqs);
+    for (downstream_crate, history) in histories {
+        let mut last_vuln_req: Option<&str> = None;
+        let release_times: Vec<DateTime<Utc>> = history.iter().map(|r| r.created_at).collect();
+        for item in history {
+            if opts.history_cutoff.is_some_and(|c| item.created_at >= c) {
+                break;
+            }
+            let Ok(req) = VersionReq::parse(&item.dep_req) else {
+                continue;
+            };
+            if let Some(original_req) = last_vuln_req
+                && let Some((fv, ftime)) = fix_times
+                    .iter()
+                    .filter(|(fv, ftime)| **ftime <= item.created_at && req.matches(fv))
+                    .min_by_key(|(_, ftime)| **ftime)
+            {
+                let (t0, t0_kind) = resolve_t0(opts.t0_kind, *ftime, opts.advisory_time);
+                let estimated_min = estimate_min_version(&item.dep_req);
+                outputs.push(StrictLagRow {
+                    downstream_crate: downstream_crate.to_string(),
+                    downstream_version: item.version.clone(),
+                    downstream_time: item.created_at,
+                    lag_secs: (item.created_at - t0).num_seconds(),
+                    original_req: original_req.to_string(),
+                    fixed_req: item.dep_req.clone(),
+                    matched_fix_version: fv.to_string(),
+                    matched_fix_time: *ftime,
+                    t0,
+                    t0_kind,
+                    negative: false,
+                    adoption_index: 1,
+                    adoption_kind: classify_adoption(original_req, &item.dep_req, fv),
+                    evidence: AdoptionEvidence::classify(Some(&req), estimated_min.as_ref(), fv)
+                        .unwrap_or(AdoptionEvidence::ReqMatches),
+                    lag_mode: LagMode::FirstResolvable,
+                    cadence_days: release_cadence_days(&release_times, *ftime),
+                    first_opportunity: first_opportunity(&release_times, *ftime, item.created_at),
+                });
+                break;
+            }
+            if vuln_versions.iter().any(|v| req.matches(v)) {
+                if last_vuln_req.is_none() {
+                    affected += 1;
+                }
+                last_vuln_req = Some(&item.dep_req);
+            }
+        }
+    }
+    (outputs, affected)
+}
+
+// Lower bound of the versions a requirement admits: the max over comparators
+// (`>=0.3, <0.5` -> 0.3.0), partial versions filled with zeros (`~1.2`, `^0.4`,
+// `1.*`), and 0.0.0 when the requirement only has upper bounds (`<0.2.0`).
+pub fn estimate_min_version(req_str: &str) -> Option<Version> {
+    let req = VersionReq::parse(req_str.trim()).ok()?;
+    let mut lower = Version::new(0, 0, 0);
+    for c in &req.comparators {
+        let bound = match c.op {
+            Op::Less | Op::LessEq => continue,
+            Op::Greater => match (c.minor, c.patch) {
+                (Some(minor), Some(patch)) => Version {
+                    major: c.major,
+                    minor,
+                    patch: patch + 1,
+                    pre: semver::Prerelease::EMPTY,
+                    build: semver::BuildMetadata::EMPTY,
+                },
+                (Some(minor), None) => Version::new(c.major, minor + 1, 0),
+                (None, _) => Version::new(c.major + 1, 0, 0),
+            },
+            _ => Version {
+                major: c.major,
+                minor: c.minor.unwrap_or(0),
+                patch: c.patch.unwrap_or(0),
+                pre: c.pre.clone(),
+                build: semver::BuildMetadata::EMPTY,
+            },
+        };
+        if bound > lower {
+            lower = bound;
+        }
+    }
+    Some(lower)
+}
+
+// Workspace key of a crate for --collapse-prefix-regex: the pattern's first capture group,
+// or the whole name when it does not match (`^([a-z0-9_]+)-` maps tokio-util to tokio and
+// leaves serde_json alone).
+pub fn prefix_group(re: &Regex, crate_name: &str) -> String {
+    re.captures(crate_name)
+        .and_then(|c| c.get(1))
+        .map_or(crate_name, |m| m.as_str())
+        .to_string()
+}
+
+// One (group, earliest row, rows in the group) entry per group, ordered by group; ties on
+// adoption time go to the smaller crate name.
+pub fn collapse_earliest<'r>(
+    rows: impl IntoIterator<Item = (String, &'r StrictLagRow)>,
+) -> Vec<(String, &'r StrictLagRow, usize)> {
+    let mut groups: BTreeMap<String, (&StrictLagRow, usize)> = BTreeMap::new();
+    for (group, r) in rows {
+        let entry = groups.entry(group).or_insert((r, 0));
+        if (r.downstream_time, &r.downstream_crate)
+            < (entry.0.downstream_time, &entry.0.downstream_crate)
+        {
+            entry.0 = r;
+        }
+        entry.1 += 1;
+    }
+    groups.into_iter().map(|(g, (r, n))| (g, r, n)).collect()
+}
+
+// The crate name and its `-`/`_` swapped forms, in the order they are tried against the
+// crates table.
+pub fn crate_name_variants(name: &str) -> Vec<String> {
+    let mut out = vec![name.to_string()];
+    for v in [name.replace('-', "_"), name.replace('_', "-")] {
+        if !out.contains(&v) {
+            out.push(v);
+        }
+    }
+    out
+}
+
+// --package-alias `from=to` pairs: RustSec package names (typos such as `rustdecimal`)
+// looked up under another name before any other resolution.
+#[derive(Clone, Debug, Default)]
+pub struct PackageAliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl PackageAliases {
+    pub fn parse(pairs: &[String]) -> Result<Self> {
+        let mut aliases = BTreeMap::new();
+        for pair in pairs {
+            let (from, to) = pair
+                .split_once('=')
+                .map(|(f, t)| (f.trim(), t.trim()))
+                .filter(|(f, t)| !f.is_empty() && !t.is_empty())
+                .ok_or_else(|| anyhow!("--package-alias {pair:?}: expected FROM=TO"))?;
+            if let Some(old) = aliases.insert(from.to_string(), to.to_string())
+                && old != to
+            {
+                return Err(anyhow!(
+                    "--package-alias {from}: mapped to both {old} and {to}"
+                ));
+            }
+        }
+        Ok(Self { aliases })
+    }
+
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+}
+
+// One `old_name,new_name,effective_date` row of --crate-renames.
+#[derive(Clone, Debug)]
+pub struct CrateRename {
+    pub old_name: String,
+    pub new_name: String,
+    pub effective: NaiveDate,
+}
+
+// Crates republished under a new name on crates.io. Names linked by renames form a chain
+// (`a -> b -> c`) whose histories are analysed as one crate.
+#[derive(Clone, Debug, Default)]
+pub struct CrateRenames {
+    // Sorted by effective date, so a name renamed twice follows its earlier rename.
+    renames: Vec<CrateRename>,
+}
+
+impl CrateRenames {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut r = csv::Reader::from_reader(text.as_bytes());
+        let headers = r.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| anyhow!("crate renames have no `{name}` column"))
+        };
+        let (old, new, date) = (
+            column("old_name")?,
+            column("new_name")?,
+            column("effective_date")?,
+        );
+        let mut renames = Vec::new();
+        for record in r.records() {
+            let record = record?;
+            let line = record.position().map(|p| p.line()).unwrap_or(0);
+            let (old_name, new_name) = (record[old].trim(), record[new].trim());
+            if old_name.is_empty() || new_name.is_empty() || old_name == new_name {
+                return Err(anyhow!(
+                    "crate renames line {line}: bad rename {old_name:?} -> {new_name:?}"
+                ));
+            }
+            let effective =
+                NaiveDate::parse_from_str(record[date].trim(), "%Y-%m-%d").map_err(|e| {
+                    anyhow!(
+                        "crate renames line {line}: bad effective_date {:?}: {e}",
+                        &record[date]
+                    )
+                })?;
+            renames.push(CrateRename {
+                old_name: old_name.to_string(),
+                new_name: new_name.to_string(),
+                effective,
+            });
+        }
+        renames.sort_by(|a, b| {
+            (a.effective, &a.old_name, &a.new_name).cmp(&(b.effective, &b.old_name, &b.new_name))
+        });
+        Ok(Self { renames })
+    }
+
+    pub fn len(&self) -> usize {
+        self.renames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    // Every name of the crate, oldest first; just the name itself when it was never renamed.
+    pub fn chain(&self, crate_name: &str) -> Vec<String> {
+        let mut first = crate_name;
+        let mut seen = vec![crate_name];
+        while let Some(r) = self
+            .renames
+            .iter()
+            .find(|r| r.new_name == first && !seen.contains(&r.old_name.as_str()))
+        {
+            first = &r.old_name;
+            seen.push(first);
+        }
+        let mut names = vec![first.to_string()];
+        while let Some(r) = self.renames.iter().find(|r| {
+            names.last().is_some_and(|n| *n == r.old_name) && !names.contains(&r.new_name)
+        }) {
+            names.push(r.new_name.clone());
+        }
+        names
+    }
+}
+
+// Merges the histories of the names in a rename chain (oldest name first) into one
+// chronological list, each row tagged with the name it came from. A key found under
+// several names, such as a version number republished under the new name, keeps its
+// earliest row; ties go to the older name.
+pub fn merge_renamed_histories<T, K: Eq + Hash>(
+    histories: Vec<(String, Vec<T>)>,
+    time: impl Fn(&T) -> DateTime<Utc>,
+    key: impl Fn(&T) -> K,
+) -> Vec<(String, T)> {
+    let mut rows: Vec<(String, T)> = histories
+        .into_iter()
+        .flat_map(|(name, rows)| rows.into_iter().map(move |r| (name.clone(), r)))
+        .collect();
+    rows.sort_by_key(|(_, r)| time(r));
+    let mut seen = HashSet::new();
+    rows.retain(|(_, r)| seen.insert(key(r)));
+    rows
+}
+
+// `--as-of D` keeps everything published on D (UTC) and earlier.
+pub fn as_of_cutoff(as_of: NaiveDate) -> DateTime<Utc> {
+    as_of
+        .succ_opt()
+        .unwrap_or(as_of)
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+}
+
+pub fn join_versions(versions: &[Version]) -> String {
+    versions
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+// The first five reqs, for skip details.
+pub fn req_sample(reqs: &[String]) -> String {
+    reqs.iter()
+        .take(5)
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+// `Truncate` analyzes a withdrawn advisory as usual but ignores downstream versions
+// published after the withdrawal date.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WithdrawnPolicy {
+    Skip,
+    Include,
+    Truncate,
+}
+
+// Parts of one advisory's analysis, for per-stage timings.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stage {
+    Fetch,
+    Strict,
+    Constraint,
+    Propagation,
+}
+
+// Where `analyze_advisory` reads crate names, versions and downstream history. A source
+// answers for a renamed crate's whole --crate-renames chain under its current name.
+// `StoreSource` reads a `VersionStore`; the batch layers its caches, `--as-of` snapshot,
+// crates.io fallbacks, logger and `--explain` trace on top of its store.
+pub trait AnalysisSource {
+    fn crate_exists(&mut self, crate_name: &str) -> impl Future<Output = Result<bool>>;
+
+    fn version_numbers(&mut self, crate_name: &str) -> impl Future<Output = Result<Vec<String>>>;
+
+    fn version_times(
+        &mut self,
+        crate_name: &str,
+    ) -> impl Future<Output = Result<Vec<(String, DateTime<Utc>)>>>;
+
+    // The publish time of exactly this version string.
+    fn version_time(
+        &mut self,
+        crate_name: &str,
+        version: &str,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>>>;
+
+    // Ordered like `VersionStore::downstream_details`. `t0` is the advisory's earliest fix
+    // and `history_cutoff` the withdrawal cutoff, for sources that filter or report on them.
+    fn downstream(
+        &mut self,
+        crate_name: &str,
+        t0: DateTime<Utc>,
+        history_cutoff: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<Vec<DownstreamVersionInfo>>>;
+
+    // The package's name from outside the store, asked when none of its spellings exist.
+    fn canonical_name(&mut self, _package: &str) -> impl Future<Output = Result<Option<String>>> {
+        std::future::ready(Ok(None))
+    }
+
+    // A publish time from outside the store, asked for versions the store lacks.
+    fn remote_version_time(
+        &mut self,
+        _crate_name: &str,
+        _version: &str,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>>> {
+        std::future::ready(Ok(None))
+    }
+
+    // Replacement times for downstream versions of the first strict scan, keyed by
+    // (crate, version); the lags are computed again with them when any come back.
+    fn corrected_times(
+        &mut self,
+        _rows: &[StrictLagRow],
+    ) -> impl Future<Output = Result<HashMap<(String, String), DateTime<Utc>>>> {
+        std::future::ready(Ok(HashMap::new()))
+    }
+
+    // Called once the package has a crate; a skip returned here ends the analysis.
+    fn crate_resolved(
+        &mut self,
+        _advisory: &Advisory,
+        _crate_name: &str,
+    ) -> Result<Option<AdvisorySkip>> {
+        Ok(None)
+    }
+
+    fn debug(&mut self, _message: String) -> Result<()> {
+        Ok(())
+    }
+
+    fn warning(&mut self, _message: String) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter(&mut self, _stage: Stage) {}
+
+    fn explain(&mut self) -> Option<&mut ExplainSink> {
+        None
+    }
+}
+
+// A `VersionStore` as an `AnalysisSource`, with the histories of renamed crates merged.
+pub struct StoreSource<'a, S> {
+    store: &'a S,
+    renames: &'a CrateRenames,
+    ids: HashMap<String, Option<i64>>,
+    version_times: HashMap<String, Vec<(String, DateTime<Utc>)>>,
+}
+
+impl<'a, S: VersionStore> StoreSource<'a, S> {
+    pub fn new(store: &'a S, renames: &'a CrateRenames) -> Self {
+        Self {
+            store,
+            renames,
+            ids: HashMap::new(),
+            version_times: HashMap::new(),
+        }
+    }
+
+    async fn crate_id(&mut self, crate_name: &str) -> Result<Option<i64>> {
+        if let Some(id) = self.ids.get(crate_name) {
+            return Ok(*id);
+        }
+        let id = self.store.crate_id(crate_name).await?;
+        self.ids.insert(crate_name.to_string(), id);
+        Ok(id)
+    }
+}
+
+impl<S: VersionStore> AnalysisSource for StoreSource<'_, S> {
+    async fn crate_exists(&mut self, crate_name: &str) -> Result<bool> {
+        Ok(self.crate_id(crate_name).await?.is_some())
+    }
+
+    async fn version_numbers(&mut self, crate_name: &str) -> Result<Vec<String>> {
+        let times = self.version_times(crate_name).await?;
+        Ok(times.into_iter().map(|(num, _)| num).collect())
+    }
+
+    async fn version_times(&mut self, crate_name: &str) -> Result<Vec<(String, DateTime<Utc>)>> {
+        if let Some(times) = self.version_times.get(crate_name) {
+            return Ok(times.clone());
+        }
+        let mut histories = Vec::new();
+        for name in self.renames.chain(crate_name) {
+            if let Some(id) = self.crate_id(&name).await? {
+                histories.push((name, self.store.version_times(id).await?));
+            }
+        }
+        let times: Vec<(String, DateTime<Utc>)> = if histories.len() == 1 {
+            histories.pop().map(|(_, times)| times).unwrap_or_default()
+        } else {
+            merge_renamed_histories(histories, |(_, t)| *t, |(num, _)| num.clone())
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect()
+        };
+        self.version_times
+            .insert(crate_name.to_string(), times.clone());
+        Ok(times)
+    }
+
+    async fn version_time(
+        &mut self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let times = self.version_times(crate_name).await?;
+        Ok(times
+            .into_iter()
+            .find(|(num, _)| num == version)
+            .map(|(_, t)| t))
+    }
+
+    async fn downstream(
+        &mut self,
+        crate_name: &str,
+        _t0: DateTime<Utc>,
+        _history_cutoff: Option<DateTime<Utc>>,
+    ) -> Result<Vec<DownstreamVersionInfo>> {
+        let mut histories = Vec::new();
+        for name in self.renames.chain(crate_name) {
+            if let Some(id) = self.crate_id(&name).await? {
+                histories.push((name, self.store.downstream_details(id).await?));
+            }
+        }
+        if histories.len() == 1 {
+            return Ok(histories.pop().map(|(_, rows)| rows).unwrap_or_default());
+        }
+        Ok(merge_renamed_histories(
+            histories,
+            |r| r.created_at,
+            |r| (r.crate_name.clone(), r.version.clone()),
+        )
+        .into_iter()
+        .map(|(_, r)| r)
+        .collect())
+    }
+}
+
+// The crates table name of a RustSec package: its --package-alias target first, then that
+// name and its `-`/`_` swapped forms, then the canonical name the source reports. Returns
+// the name found, if any, and every name tried.
+pub async fn resolve_package(
+    source: &mut impl AnalysisSource,
+    aliases: &PackageAliases,
+    package: &str,
+) -> Result<(Option<String>, Vec<String>)> {
+    let mut attempted = Vec::new();
+    for candidate in crate_name_variants(aliases.resolve(package)) {
+        let exists = source.crate_exists(&candidate).await?;
+        attempted.push(candidate.clone());
+        if exists {
+            return Ok((Some(candidate), attempted));
+        }
+    }
+    if let Some(canonical) = source.canonical_name(package).await?
+        && !attempted.contains(&canonical)
+    {
+        let exists = source.crate_exists(&canonical).await?;
+        attempted.push(canonical.clone());
+        if exists {
+            return Ok((Some(canonical), attempted));
+        }
+    }
+    Ok((None, attempted))
+}
+
+// Where a version's publish time came from.
+pub enum VersionTimeVia {
+    Store,
+    Equivalent(EquivalentVersion),
+    Remote,
+}
+
+// The publish time of `version`: its exact string in the store, then a build-metadata
+// variant of it, then the source's remote lookup.
+pub async fn lookup_version_time(
+    source: &mut impl AnalysisSource,
+    crate_name: &str,
+    version: &Version,
+) -> Result<Option<(DateTime<Utc>, VersionTimeVia)>> {
+    let version_str = version.to_string();
+    if let Some(t) = source.version_time(crate_name, &version_str).await? {
+        return Ok(Some((t, VersionTimeVia::Store)));
+    }
+    let version_times = source.version_times(crate_name).await?;
+    if let Some(eq) = resolve_equivalent_version_string(&version_times, version) {
+        return Ok(Some((eq.created_at, VersionTimeVia::Equivalent(eq))));
+    }
+    let fetched = source.remote_version_time(crate_name, &version_str).await?;
+    Ok(fetched.map(|t| (t, VersionTimeVia::Remote)))
+}
+
+// What `analyze_advisory` does; the fields mirror the batch flags of the same names.
+// `constraint_edges` keeps the per-edge rows of the breakdown.
+#[derive(Clone, Copy, Debug)]
+pub struct AnalysisOptions<'a> {
+    pub package_aliases: &'a PackageAliases,
+    pub renames: &'a CrateRenames,
+    pub prerelease_policy: PrereleasePolicy,
+    pub fix_selection: FixSelection,
+    pub fix_match_policy: FixMatchPolicy,
+    pub t0: T0Kind,
+    pub lag_mode: LagMode,
+    pub all_adoptions: bool,
+    pub withdrawn: WithdrawnPolicy,
+    pub strict_advisories: bool,
+    pub duplicate_reqs: DuplicateReqs,
+    pub constraint: bool,
+    pub constraint_whatif: bool,
+    pub constraint_edges: bool,
+    pub constraint_horizons: &'a [i64],
+}
+
+static NO_PACKAGE_ALIASES: PackageAliases = PackageAliases {
+    aliases: BTreeMap::new(),
+};
+static NO_CRATE_RENAMES: CrateRenames = CrateRenames {
+    renames: Vec::new(),
+};
+
+impl Default for AnalysisOptions<'_> {
+    fn default() -> Self {
+        Self {
+            package_aliases: &NO_PACKAGE_ALIASES,
+            renames: &NO_CRATE_RENAMES,
+            prerelease_policy: PrereleasePolicy::MatchSemver,
+            fix_selection: FixSelection::All,
+            fix_match_policy: FixMatchPolicy::Either,
+            t0: T0Kind::FixRelease,
+            lag_mode: LagMode::Strict,
+            all_adoptions: false,
+            withdrawn: WithdrawnPolicy::Skip,
+            strict_advisories: false,
+            duplicate_reqs: DuplicateReqs::default(),
+            constraint: true,
+            constraint_whatif: false,
+            constraint_edges: false,
+            constraint_horizons: &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AdvisorySkip {
+    pub reason: SkipReason,
+    pub detail: String,
+}
+
+// First-adoption lags of one lag mode, in fractional days.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ModeSummary {
+    pub lag_mode: LagMode,
+    pub affected: usize,
+    pub stats: Option<LagStats>,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct AdvisoryResult {
+    pub rustsec_id: String,
+    pub cve_id: String,
+    // The crates table name once resolved, the RustSec package name before.
+    pub crate_name: String,
+    // All names of a renamed crate, oldest first and joined by `|`; empty otherwise.
+    pub merged_names: String,
+    // Set when the advisory was not analyzed; the fields filled before the skip stay.
+    pub skip: Option<AdvisorySkip>,
+    pub history_cutoff: Option<DateTime<Utc>>,
+    pub fixed_version_source: Option<FixedVersionSource>,
+    pub fixed_versions: Vec<Version>,
+    pub fix_times: BTreeMap<Version, DateTime<Utc>>,
+    pub summary_t0: Option<DateTime<Utc>>,
+    pub vuln_versions: Vec<Version>,
+    pub quality: Option<AdvisoryQualityReport>,
+    // Days from the first vulnerable release to the earliest fix.
+    pub exposure_days: Option<f64>,
+    pub vulnerable_since_first_release: bool,
+    pub fix_semver_compatible: bool,
+    // Distinct downstream crates in the history the lags were computed from.
+    pub downstream_crates: usize,
+    // Downstream rows whose time `corrected_times` replaced.
+    pub corrected_rows: usize,
+    // Strict rows by downstream crate and adoption index, negative lags kept apart. The
+    // first-resolvable rows are only computed when the lag mode asks for them.
+    pub rows: Vec<StrictLagRow>,
+    pub negative_rows: Vec<StrictLagRow>,
+    pub affected: usize,
+    pub regressions: usize,
+    pub merged_duplicates: usize,
+    pub exposure: ExposureCounts,
+    pub resolvable_rows: Vec<StrictLagRow>,
+    pub resolvable_affected: usize,
+    pub summaries: Vec<ModeSummary>,
+    pub constraint: Option<ConstraintBreakdown>,
+    pub constraint_edges: Vec<ConstraintEdge>,
+    pub constraint_horizons: Vec<HorizonBreak>,
+}
+
+impl AdvisoryResult {
+    fn skipped(mut self, reason: SkipReason, detail: impl Into<String>) -> Self {
+        self.skip = Some(AdvisorySkip {
+            reason,
+            detail: detail.into(),
+        });
+        self
+    }
+
+    // The rows of one lag mode and the crates it counts as affected.
+    pub fn mode_rows(&self, mode: LagMode) -> (&[StrictLagRow], usize) {
+        match mode {
+            LagMode::FirstResolvable => (&self.resolvable_rows, self.resolvable_affected),
+            _ => (&self.rows, self.affected),
+        }
+    }
+}
+
+// Resolves the crate, fixed versions and their times and the vulnerable range from
+// `source`, then computes strict lags, their summary and the constraint breakdown.
+// Advisories that cannot be analyzed come back with `skip` set; only source errors are
+// returned as `Err`.
+pub async fn analyze_advisory(
+    source: &mut impl AnalysisSource,
+    advisory: &Advisory,
+    opts: &AnalysisOptions<'_>,
+) -> Result<AdvisoryResult> {
+    let mut out = AdvisoryResult {
+        rustsec_id: advisory.rustsec_id.clone(),
+        cve_id: advisory.cve_id.clone(),
+        crate_name: advisory.package.clone(),
+        ..AdvisoryResult::default()
+    };
+    if advisory.withdrawn {
+        let detail = match (opts.withdrawn, advisory.withdrawn_date) {
+            (WithdrawnPolicy::Skip, _) => Some("advisory withdrawn"),
+            (WithdrawnPolicy::Include, _) => None,
+            (WithdrawnPolicy::Truncate, Some(d)) => {
+                out.history_cutoff = Some(as_of_cutoff(d));
+                None
+            }
+            (WithdrawnPolicy::Truncate, None) => {
+                Some("advisory withdrawn without a parseable date")
+            }
+        };
+        if let Some(detail) = detail {
+            return Ok(out.skipped(SkipReason::Withdrawn, detail));
+        }
+    }
+
+    let (resolved, attempted) =
+        resolve_package(source, opts.package_aliases, &advisory.package).await?;
+    if let Some(x) = source.explain() {
+        x.section("crate");
+        x.line(format!(
+            "resolved={} attempted_names={}",
+            resolved.as_deref().unwrap_or("-"),
+            attempted.join("|")
+        ));
+    }
+    let Some(pkg) = resolved else {
+        return Ok(out.skipped(
+            SkipReason::CrateNotFound,
+            format!("attempted_names={}", attempted.join("|")),
+        ));
+    };
+    out.crate_name = pkg.clone();
+    if let Some(skip) = source.crate_resolved(advisory, &pkg)? {
+        out.skip = Some(skip);
+        return Ok(out);
+    }
+    let pkg = pkg.as_str();
+    out.merged_names = match opts.renames.chain(pkg) {
+        names if names.len() > 1 => names.join("|"),
+        _ => String::new(),
+    };
+
+    let (mut fixed_versions, fixed_source) =
+        static_fixed_versions(&advisory.patched, opts.prerelease_policy);
+    if fixed_source == FixedVersionSource::PublishedRange {
+        // Pure ranges such as `<0.1.0` name no version, so take the first
+        // published release each patched req admits.
+        let all_versions = source.version_numbers(pkg).await?;
+        let published = parse_published_versions(&all_versions);
+        for req_str in &advisory.patched {
+            if let Some((v, _, _)) =
+                first_published_matching(req_str, &published, opts.prerelease_policy)
+            {
+                fixed_versions.push(v.clone());
+            }
+        }
+        fixed_versions.sort();
+        fixed_versions.dedup();
+    }
+    out.fixed_version_source = Some(fixed_source);
+    if let Some(x) = source.explain() {
+        x.section("fixed versions");
+        x.line(format!("source={}", fixed_source.as_str()));
+        x.line(format!("fixed_versions={}", join_versions(&fixed_versions)));
+    }
+    out.fixed_versions = fixed_versions;
+    if out.fixed_versions.is_empty() {
+        let detail = format!(
+            "patched_versions_count={} unaffected_versions_count={} patched_sample={} unaffected_sample={}",
+            advisory.patched.len(),
+            advisory.unaffected.len(),
+            req_sample(&advisory.patched),
+            req_sample(&advisory.unaffected)
+        );
+        return Ok(out.skipped(SkipReason::NoFixedVersions, detail));
+    }
+
+    // BTreeMap so ties on publish time resolve to the lowest version on every run.
+    let mut fix_times = BTreeMap::new();
+    let all_versions = source.version_numbers(pkg).await?;
+    if let Some(x) = source.explain() {
+        x.section("fix times");
+    }
+    for fv in &out.fixed_versions {
+        let found = lookup_version_time(source, pkg, fv).await?;
+        if let Some(x) = source.explain() {
+            x.line(match &found {
+                Some((t, VersionTimeVia::Store)) => format!("{fv}: time={t} via=db"),
+                Some((t, VersionTimeVia::Equivalent(eq))) => {
+                    format!("{fv}: time={t} via=db_equivalent({})", eq.describe())
+                }
+                Some((t, VersionTimeVia::Remote)) => format!("{fv}: time={t} via=crates_io"),
+                None => format!("{fv}: no time (db, no equivalent variant, crates.io)"),
+            });
+        }
+        if let Some((t, _)) = found {
+            fix_times.insert(fv.clone(), t);
+        }
+    }
+
+    if fix_times.is_empty()
+        && advisory
+            .patched
+            .iter()
+            .any(|s| VersionReq::parse(s).is_ok())
+    {
+        let mut used_ge_min = false;
+        let published = parse_published_versions(&all_versions);
+        for req_str in &advisory.patched {
+            let Some((v, v_str, via_ge_min)) =
+                first_published_matching(req_str, &published, opts.prerelease_policy)
+            else {
+                continue;
+            };
+            if via_ge_min {
+                used_ge_min = true;
+            }
+            if fix_times.contains_key(v) {
+                continue;
+            }
+            let via = if via_ge_min {
+                "first_published_ge_min"
+            } else {
+                "first_published"
+            };
+            if let Some(t) = source.version_time(pkg, v_str).await? {
+                if let Some(x) = source.explain() {
+                    x.line(format!("{v}: time={t} via={via}({req_str}) db"));
+                }
+                fix_times.insert(v.clone(), t);
+                continue;
+            }
+            let fetched = source.remote_version_time(pkg, v_str).await?;
+            if let Some(x) = source.explain() {
+                x.line(match fetched {
+                    Some(t) => format!("{v}: time={t} via={via}({req_str}) crates_io"),
+                    None => format!("{v}: no time via={via}({req_str})"),
+                });
+            }
+            if let Some(t) = fetched {
+                fix_times.insert(v.clone(), t);
+            }
+        }
+
+        if !fix_times.is_empty() {
+            let reason = if used_ge_min {
+                "patched_req_first_published_or_ge_min"
+            } else {
+                "patched_req_first_published"
+            };
+            source.debug(format!(
+                "fixed_version fallback: rustsec_id={} cve_id={} pkg={} reason={} fixed_versions_sample={}",
+                advisory.rustsec_id,
+                advisory.cve_id,
+                pkg,
+                reason,
+                fix_times
+                    .keys()
+                    .take(5)
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            ))?;
+        }
+    }
+    if fix_times.is_empty() {
+        let detail = format!(
+            "fixed_versions_count={} fixed_versions_sample={} db_versions_count={}",
+            out.fixed_versions.len(),
+            join_versions(&out.fixed_versions[..out.fixed_versions.len().min(5)]),
+            all_versions.len()
+        );
+        return Ok(out.skipped(SkipReason::NoFixTimes, detail));
+    }
+
+    if opts.fix_selection != FixSelection::All {
+        let before = fix_times.len();
+        select_fix_versions(&mut fix_times, opts.fix_selection);
+        if let Some(x) = source.explain() {
+            x.line(format!(
+                "fix_selection={} kept {} of {before}: {}",
+                opts.fix_selection.as_str(),
+                fix_times.len(),
+                fix_times
+                    .keys()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+    }
+    let Some((min_fixed_version, &summary_t0)) = fix_times.first_key_value() else {
+        let detail = format!(
+            "fixed_versions_count={} fix_times_count=0",
+            out.fixed_versions.len()
+        );
+        return Ok(out.skipped(SkipReason::NoSummaryT0, detail));
+    };
+    let min_fixed_version = min_fixed_version.clone();
+    out.fix_times = fix_times;
+    out.summary_t0 = Some(summary_t0);
+
+    let vuln_versions = identify_vuln_versions(
+        &all_versions,
+        &advisory.patched,
+        &advisory.unaffected,
+        opts.prerelease_policy,
+    );
+    let quality = check_advisory_quality(
+        &all_versions,
+        &advisory.patched,
+        &advisory.unaffected,
+        opts.prerelease_policy,
+    );
+    if quality.quality != AdvisoryQuality::Ok {
+        source.warning(format!(
+            "advisory quality: rustsec_id={} pkg={} quality={} details={}",
+            advisory.rustsec_id,
+            pkg,
+            quality.quality.as_str(),
+            quality.details.join("; ")
+        ))?;
+    }
+    if let Some(x) = source.explain() {
+        x.section("vulnerable versions");
+        x.line(format!("advisory_quality={}", quality.quality.as_str()));
+        for d in &quality.details {
+            x.line(format!("  {d}"));
+        }
+        x.line(format!(
+            "summary_t0={summary_t0} min_fixed_version={min_fixed_version}"
+        ));
+        x.line(format!(
+            "published={} vulnerable={}",
+            all_versions.len(),
+            vuln_versions.len()
+        ));
+        x.line(format!(
+            "vulnerable_versions={}",
+            join_versions(&vuln_versions)
+        ));
+    }
+    let conflicting = quality.quality == AdvisoryQuality::Conflicting;
+    let quality_details = quality.details.join("; ");
+    out.quality = Some(quality);
+    out.vuln_versions = vuln_versions;
+    if opts.strict_advisories && conflicting {
+        return Ok(out.skipped(SkipReason::ConflictingAdvisory, quality_details));
+    }
+    if out.vuln_versions.is_empty() {
+        let detail = format!(
+            "all_versions_count={} patched_versions_count={} unaffected_versions_count={}",
+            all_versions.len(),
+            advisory.patched.len(),
+            advisory.unaffected.len()
+        );
+        return Ok(out.skipped(SkipReason::NoVulnVersions, detail));
+    }
+
+    let version_times = source.version_times(pkg).await?;
+    let first_vuln_time = first_vulnerable_release(&version_times, &out.vuln_versions);
+    out.exposure_days = first_vuln_time.map(|t| (summary_t0 - t).num_seconds() as f64 / 86400.0);
+    // The flaw may predate the crate; all we can say is that it shipped from day one.
+    out.vulnerable_since_first_release =
+        first_vuln_time.is_some() && first_vuln_time == version_times.iter().map(|(_, t)| *t).min();
+    let fixed_set: Vec<Version> = out.fix_times.keys().cloned().collect();
+    out.fix_semver_compatible = fix_semver_compatible(&out.vuln_versions, &fixed_set);
+    if let Some(x) = source.explain() {
+        x.line(format!(
+            "first_vulnerable_release={} exposure_days={} vulnerable_since_first_release={}",
+            first_vuln_time.map_or("-".to_string(), |t| t.to_string()),
+            out.exposure_days.map(format_float).unwrap_or_default(),
+            out.vulnerable_since_first_release
+        ));
+        x.line(format!(
+            "fix_semver_compatible={}",
+            out.fix_semver_compatible
+        ));
+    }
+
+    let mut downstream = source
+        .downstream(pkg, summary_t0, out.history_cutoff)
+        .await?;
+    out.downstream_crates = downstream
+        .iter()
+        .map(|r| r.crate_name.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+    source.enter(Stage::Strict);
+    let strict_opts = StrictLagOptions {
+        t0_kind: opts.t0,
+        advisory_time: advisory.date.map(|d| d.and_time(NaiveTime::MIN).and_utc()),
+        all_adoptions: opts.all_adoptions,
+        history_cutoff: out.history_cutoff,
+        policy: opts.fix_match_policy,
+        duplicate_reqs: opts.duplicate_reqs,
+    };
+    let mut scan = compute_strict_lags_for_target(
+        &out.fix_times,
+        &out.vuln_versions,
+        &downstream,
+        strict_opts,
+        source.explain(),
+    );
+    let corrected = source.corrected_times(&scan.rows).await?;
+    if !corrected.is_empty() {
+        for r in &mut downstream {
+            if let Some(t) = corrected.get(&(r.crate_name.clone(), r.version.clone())) {
+                r.created_at = *t;
+                out.corrected_rows += 1;
+            }
+        }
+        if let Some(x) = source.explain() {
+            x.section("strict lags recomputed with crates.io times");
+        }
+        scan = compute_strict_lags_for_target(
+            &out.fix_times,
+            &out.vuln_versions,
+            &downstream,
+            strict_opts,
+            source.explain(),
+        );
+    }
+    out.affected = scan.affected;
+    out.regressions = scan.regressions;
+    out.merged_duplicates = scan.merged_duplicates;
+    out.exposure = scan.exposure;
+    (out.negative_rows, out.rows) = scan.rows.into_iter().partition(|r| r.negative);
+
+    if opts.constraint {
+        source.enter(Stage::Constraint);
+        let published: Option<Vec<(Version, DateTime<Utc>)>> = opts.constraint_whatif.then(|| {
+            version_times
+                .iter()
+                .filter_map(|(num, t)| Some((Version::parse(num).ok()?, *t)))
+                .collect()
+        });
+        let mut edges = Vec::new();
+        let c = compute_constraint_breakdown(
+            summary_t0,
+            &out.vuln_versions,
+            &fixed_set,
+            &downstream,
+            opts.constraint_edges.then_some(&mut edges),
+            published.as_deref(),
+        );
+        if c.regrouped_crates > 0 {
+            source.warning(format!(
+                "constraint rows for {} ({}) were not grouped by crate; regrouped {} downstream crates",
+                advisory.rustsec_id, pkg, c.regrouped_crates
+            ))?;
+        }
+        out.constraint = Some(c);
+        out.constraint_edges = edges;
+        out.constraint_horizons = compute_constraint_horizons(
+            summary_t0,
+            opts.constraint_horizons,
+            &out.vuln_versions,
+            &fixed_set,
+            &downstream,
+        );
+        source.enter(Stage::Strict);
+    }
+
+    if opts.lag_mode != LagMode::Strict {
+        (out.resolvable_rows, out.resolvable_affected) = compute_first_resolvable_lags_for_target(
+            &out.fix_times,
+            &out.vuln_versions,
+            &downstream,
+            strict_opts,
+        );
+    }
+    out.summaries = opts
+        .lag_mode
+        .row_modes()
+        .iter()
+        .map(|&mode| {
+            let (rows, affected) = out.mode_rows(mode);
+            ModeSummary {
+                lag_mode: mode,
+                affected,
+                stats: compute_lag_stats(
+                    rows.iter()
+                        .filter(|r| r.adoption_index == 1)
+                        .map(|r| fractional_days(r.lag_secs)),
+                ),
+            }
+        })
+        .collect();
+    Ok(out)
+}
@@ -0,0 +1,468 @@
+// Rows of rqx2_rustsec_batch's per-advisory outputs, built from an `AdvisoryResult`. Each
+// stage writes to the buffers it is handed, so the batch can hold an advisory's rows until
+// the advisory finishes and tests can read them back.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::advisory::{Advisory, AdvisoryQuality};
+use crate::analysis::{FixSelection, VersionDelta};
+use crate::database::RowSource;
+use crate::pipeline::{
+    AdoptionEvidence, AdoptionKind, AdvisoryResult, ConstraintEdge, FirstOpportunity, LagMode,
+    LagUnit, ReqShape, StrictLagRow, T0Kind, estimate_min_version, fractional_days, whole_days,
+    whole_hours,
+};
+use crate::stats::{LagStats, compute_lag_stats, format_float};
+
+// CSV records buffered for one advisory; same call shape as csv::Writer.
+#[derive(Clone, Debug, Default)]
+pub struct PendingRows(pub Vec<Vec<String>>);
+
+impl PendingRows {
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.0
+            .push(record.into_iter().map(|f| f.as_ref().to_string()).collect());
+        Ok(())
+    }
+}
+
+// The advisory columns that lead most per-advisory outputs.
+fn advisory_cells(adv: &Advisory) -> [String; 6] {
+    [
+        adv.rustsec_id.clone(),
+        adv.cve_id.clone(),
+        adv.ghsa_id().unwrap_or_default().to_string(),
+        adv.aliases.join("|"),
+        adv.cve_is_fallback.to_string(),
+        adv.group_id.clone(),
+    ]
+}
+
+pub fn strict_lag_record(adv: &Advisory, pkg: &str, row: &StrictLagRow) -> Vec<String> {
+    let mut record = advisory_cells(adv).to_vec();
+    record.extend([
+        adv.severity.clone(),
+        pkg.to_string(),
+        row.matched_fix_version.clone(),
+        row.matched_fix_time.to_string(),
+        row.downstream_crate.clone(),
+        row.downstream_version.clone(),
+        row.downstream_time.to_string(),
+        whole_days(row.lag_secs).to_string(),
+        row.original_req.clone(),
+        row.fixed_req.clone(),
+        row.t0_kind.as_str().to_string(),
+        whole_hours(row.lag_secs).to_string(),
+        format_float(fractional_days(row.lag_secs)),
+        row.adoption_index.to_string(),
+        row.adoption_kind.as_str().to_string(),
+        row.evidence.as_str().to_string(),
+        estimate_min_version(&row.original_req)
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        estimate_min_version(&row.fixed_req)
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        row.fix_delta()
+            .map(|d| d.as_str().to_string())
+            .unwrap_or_default(),
+        row.lag_mode.as_str().to_string(),
+        row.cadence_days.map(format_float).unwrap_or_default(),
+        row.lag_over_cadence().map(format_float).unwrap_or_default(),
+    ]);
+    record
+}
+
+// The lag rows of each of `modes` in turn; returns how many were written.
+pub fn write_strict_lags(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    modes: &[LagMode],
+) -> Result<usize> {
+    let mut written = 0;
+    for &mode in modes {
+        for row in result.mode_rows(mode).0 {
+            w.write_record(strict_lag_record(adv, &result.crate_name, row))?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+pub fn write_negative_lags(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+) -> Result<()> {
+    for row in &result.negative_rows {
+        w.write_record(strict_lag_record(adv, &result.crate_name, row))?;
+    }
+    Ok(())
+}
+
+// --group-by-owner and --collapse-prefix-regex rows: each group's earliest lag row, then
+// the group and how many crates fell in it.
+pub fn write_grouped_lags(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    pkg: &str,
+    groups: &[(String, &StrictLagRow, usize)],
+) -> Result<()> {
+    for (group, row, crates) in groups {
+        let mut record = strict_lag_record(adv, pkg, row);
+        record.push(group.clone());
+        record.push(crates.to_string());
+        w.write_record(&record)?;
+    }
+    Ok(())
+}
+
+// Constraint edges written across the run; past a non-zero `limit` the rest are counted
+// as truncated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EdgeBudget {
+    pub limit: usize,
+    pub written: usize,
+    pub truncated: usize,
+}
+
+pub fn write_constraint_edges(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    edges: &[ConstraintEdge],
+    budget: &mut EdgeBudget,
+) -> Result<()> {
+    for edge in edges {
+        if budget.limit > 0 && budget.written >= budget.limit {
+            budget.truncated += 1;
+            continue;
+        }
+        w.write_record([
+            adv.rustsec_id.clone(),
+            edge.downstream_crate.clone(),
+            edge.downstream_version.clone(),
+            edge.dep_req.clone(),
+            edge.req_shape.as_str().to_string(),
+            edge.locked_out.to_string(),
+            edge.smallest_satisfying_fix
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+        ])?;
+        budget.written += 1;
+    }
+    Ok(())
+}
+
+// The what-if columns stay empty unless --constraint-whatif computed them.
+fn whatif_cell(enabled: bool, count: usize) -> String {
+    if enabled {
+        count.to_string()
+    } else {
+        String::new()
+    }
+}
+
+// Nothing is written for an advisory without a constraint breakdown.
+pub fn write_constraint_breakdown(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+    whatif: bool,
+) -> Result<()> {
+    let (Some(c), Some(t0)) = (&result.constraint, result.summary_t0) else {
+        return Ok(());
+    };
+    let mut record = advisory_cells(adv).to_vec();
+    record.extend([
+        adv.severity.clone(),
+        result.crate_name.clone(),
+        t0.to_string(),
+        c.downstream_crates_with_history.to_string(),
+        c.affected_edges.to_string(),
+        c.locked_out_edges.to_string(),
+        c.break_rate_percent.to_string(),
+    ]);
+    for shape in ReqShape::ALL {
+        if shape != ReqShape::Unparseable {
+            record.push(c.affected_req_shape[shape as usize].to_string());
+        }
+    }
+    record.extend([
+        c.unknown_req_unparseable.to_string(),
+        whatif_cell(whatif, c.whatif_resolved_locked_edges),
+        whatif_cell(whatif, c.whatif_unlocked_1_line),
+        whatif_cell(whatif, c.whatif_unlocked_2_lines),
+        whatif_cell(whatif, c.whatif_unlocked_all_lines),
+    ]);
+    w.write_record(&record)
+}
+
+// One row with three columns per horizon of `result.constraint_horizons`.
+pub fn write_constraint_horizons(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    result: &AdvisoryResult,
+) -> Result<()> {
+    let Some(t0) = result.summary_t0.filter(|_| result.constraint.is_some()) else {
+        return Ok(());
+    };
+    let mut record = advisory_cells(adv).to_vec();
+    record.extend([
+        adv.severity.clone(),
+        result.crate_name.clone(),
+        t0.to_string(),
+    ]);
+    for h in &result.constraint_horizons {
+        record.push(h.affected_edges.to_string());
+        record.push(h.locked_out_edges.to_string());
+        record.push(h.break_rate_percent().to_string());
+    }
+    w.write_record(&record)
+}
+
+// What a summary row needs beyond the analysis result: the lag unit and reference time,
+// the batch's downstream filtering, and the optional owner and prefix groupings.
+pub struct SummaryRow<'a> {
+    pub advisory: &'a Advisory,
+    pub result: &'a AdvisoryResult,
+    pub mode: LagMode,
+    pub lag_unit: LagUnit,
+    pub lag_t0: DateTime<Utc>,
+    pub lag_t0_kind: T0Kind,
+    pub as_of: Option<NaiveDate>,
+    pub fix_selection: FixSelection,
+    pub history_rows: i64,
+    pub excluded_few_versions: usize,
+    pub excluded_inactive: usize,
+    pub downstream_source: RowSource,
+    // --group-by-owner: the (advisory, owner) groups and their lag stats in `lag_unit`.
+    pub owner_groups: Option<(usize, Option<LagStats>)>,
+    // --collapse-prefix-regex: first adoptions and the (advisory, prefix) groups they fall in.
+    pub collapsed: Option<(usize, usize)>,
+}
+
+// The summary row of one lag mode, laid out as SUMMARY_SCHEMA.
+pub fn summary_record(row: &SummaryRow<'_>) -> Vec<String> {
+    let (adv, result, unit) = (row.advisory, row.result, row.lag_unit);
+    let (mode_rows, mode_affected) = result.mode_rows(row.mode);
+    let (mode_negative, mode_regressions) = match row.mode {
+        LagMode::FirstResolvable => (0, 0),
+        _ => (result.negative_rows.len(), result.regressions),
+    };
+    let first_adoptions = || mode_rows.iter().filter(|r| r.adoption_index == 1);
+    let stats = compute_lag_stats(first_adoptions().map(|r| unit.convert(r.lag_secs)));
+    let pre_disclosure = first_adoptions().filter(|r| r.lag_secs < 0).count();
+    let mut adoption_kinds: HashMap<AdoptionKind, usize> = HashMap::new();
+    let mut fix_deltas: HashMap<Option<VersionDelta>, usize> = HashMap::new();
+    for r in first_adoptions() {
+        *adoption_kinds.entry(r.adoption_kind).or_default() += 1;
+        *fix_deltas.entry(r.fix_delta()).or_default() += 1;
+    }
+    let quality = result
+        .quality
+        .as_ref()
+        .map_or(AdvisoryQuality::Ok, |q| q.quality);
+    let exposure = result.exposure;
+    let owner_stats = row.owner_groups.as_ref().and_then(|(_, s)| s.as_ref());
+
+    let mut record = advisory_cells(adv).to_vec();
+    record.extend([
+        adv.function_scoped().to_string(),
+        adv.affected_functions.join("|"),
+        adv.informational.as_str().to_string(),
+        adv.withdrawn_date
+            .map(|d| d.to_string())
+            .unwrap_or_default(),
+        adv.severity.clone(),
+        result.crate_name.clone(),
+        result
+            .fix_times
+            .keys()
+            .next()
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        row.lag_t0.to_string(),
+        stats.as_ref().map_or(0, |s| s.count).to_string(),
+        stats
+            .as_ref()
+            .map(|s| unit.format(s.min))
+            .unwrap_or_default(),
+        stats
+            .as_ref()
+            .map(|s| format_float(s.p50))
+            .unwrap_or_default(),
+        stats
+            .as_ref()
+            .map(|s| format_float(s.avg))
+            .unwrap_or_default(),
+        stats
+            .as_ref()
+            .map(|s| unit.format(s.max))
+            .unwrap_or_default(),
+        row.lag_t0_kind.as_str().to_string(),
+        pre_disclosure.to_string(),
+        unit.as_str().to_string(),
+        mode_negative.to_string(),
+        mode_regressions.to_string(),
+    ]);
+    for kind in AdoptionKind::ALL {
+        record.push(adoption_kinds.get(&kind).copied().unwrap_or(0).to_string());
+    }
+    record.push(row.as_of.map(|d| d.to_string()).unwrap_or_default());
+    record.push(row.excluded_few_versions.to_string());
+    record.push(row.excluded_inactive.to_string());
+    record.push(row.history_rows.to_string());
+    for delta in VersionDelta::ALL {
+        record.push(
+            fix_deltas
+                .get(&Some(delta))
+                .copied()
+                .unwrap_or(0)
+                .to_string(),
+        );
+    }
+    record.push(result.downstream_crates.to_string());
+    record.push(mode_affected.to_string());
+    record.push(row.fix_selection.as_str().to_string());
+    record.push(
+        result
+            .fix_times
+            .keys()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join("|"),
+    );
+    record.push(quality.as_str().to_string());
+    record.push(row.mode.as_str().to_string());
+    record.push(exposure.exposed.to_string());
+    record.push(exposure.never_exposed.to_string());
+    record.push(exposure.unparseable.to_string());
+    record.push(
+        row.owner_groups
+            .as_ref()
+            .map(|(n, _)| n.to_string())
+            .unwrap_or_default(),
+    );
+    record.push(owner_stats.map(|s| unit.format(s.min)).unwrap_or_default());
+    record.push(owner_stats.map(|s| format_float(s.p50)).unwrap_or_default());
+    record.push(owner_stats.map(|s| format_float(s.avg)).unwrap_or_default());
+    record.push(owner_stats.map(|s| unit.format(s.max)).unwrap_or_default());
+    match row.collapsed {
+        Some((input, groups)) => record.extend([input.to_string(), groups.to_string()]),
+        None => record.extend([String::new(), String::new()]),
+    }
+    record.push(result.merged_names.clone());
+    record.push(row.downstream_source.as_str().to_string());
+    record.push(result.exposure_days.map(format_float).unwrap_or_default());
+    record.push(result.vulnerable_since_first_release.to_string());
+    record.push(adv.duplicate_of.clone().unwrap_or_default());
+    record.push(result.fix_semver_compatible.to_string());
+    record.push(adv.severity_source.as_str().to_string());
+    let cadence_stats = compute_lag_stats(first_adoptions().filter_map(|r| r.lag_over_cadence()));
+    record.push(cadence_stats.as_ref().map_or(0, |s| s.count).to_string());
+    match &cadence_stats {
+        Some(s) => record.extend([s.p25, s.p50, s.p75].map(format_float)),
+        None => record.extend([String::new(), String::new(), String::new()]),
+    }
+    record.push(result.merged_duplicates.to_string());
+    record.push(adv.keywords.join("|"));
+    record.push(adv.categories.join("|"));
+    record.push(exposure.post_fix_new.to_string());
+    record
+}
+
+// One adoption on a propagation path, `hop` steps from the advisory's crate.
+pub struct PropagationEvent<'a> {
+    pub root_crate: &'a str,
+    pub hop: usize,
+    pub upstream_crate: &'a str,
+    pub upstream_fix_version: String,
+    pub upstream_fix_time: DateTime<Utc>,
+    pub downstream_crate: &'a str,
+    pub downstream_version: String,
+    pub downstream_time: DateTime<Utc>,
+    pub dep_req: &'a str,
+    pub lag_secs: i64,
+    // Summed over the path back to the advisory's own fix.
+    pub cumulative_secs: i64,
+    pub evidence: AdoptionEvidence,
+    pub opportunity: Option<FirstOpportunity>,
+}
+
+// Laid out as PROPAGATION_EVENT_SCHEMA.
+pub fn propagation_event_record(adv: &Advisory, ev: &PropagationEvent<'_>) -> Vec<String> {
+    let mut record = advisory_cells(adv).to_vec();
+    record.extend([
+        ev.root_crate.to_string(),
+        ev.hop.to_string(),
+        ev.upstream_crate.to_string(),
+        ev.upstream_fix_version.clone(),
+        ev.upstream_fix_time.to_string(),
+        ev.downstream_crate.to_string(),
+        ev.downstream_version.clone(),
+        ev.downstream_time.to_string(),
+        whole_days(ev.lag_secs).to_string(),
+        ev.dep_req.to_string(),
+        whole_hours(ev.lag_secs).to_string(),
+        format_float(fractional_days(ev.lag_secs)),
+        ev.evidence.as_str().to_string(),
+        whole_days(ev.cumulative_secs).to_string(),
+    ]);
+    match ev.opportunity {
+        Some(o) => record.extend([
+            o.time.to_string(),
+            format_float(fractional_days(o.lag_secs(ev.downstream_time))),
+            o.skipped_releases.to_string(),
+        ]),
+        None => record.extend([String::new(), String::new(), String::new()]),
+    }
+    record
+}
+
+// One row per hop up to the last that reached a crate or dropped an event: the crates
+// first reached at that hop and their downloads, then the running totals.
+pub fn write_propagation_reach(
+    w: &mut PendingRows,
+    adv: &Advisory,
+    pkg: &str,
+    per_hop: &[(usize, i64)],
+    truncated_by_hop: &BTreeMap<usize, usize>,
+) -> Result<()> {
+    let max_row_hop = truncated_by_hop
+        .keys()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(per_hop.len());
+    let (mut cumulative_crates, mut cumulative_downloads) = (0usize, 0i64);
+    for i in 0..max_row_hop {
+        let (crates, downloads) = per_hop.get(i).copied().unwrap_or((0, 0));
+        cumulative_crates += crates;
+        cumulative_downloads += downloads;
+        w.write_record([
+            adv.rustsec_id.clone(),
+            adv.cve_id.clone(),
+            pkg.to_string(),
+            (i + 1).to_string(),
+            crates.to_string(),
+            downloads.to_string(),
+            cumulative_crates.to_string(),
+            cumulative_downloads.to_string(),
+            truncated_by_hop
+                .get(&(i + 1))
+                .copied()
+                .unwrap_or(0)
+                .to_string(),
+        ])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::charts::svg_escape;
+
+struct Section {
+    anchor: String,
+    heading: String,
+    body: String,
+}
+
+// Self-contained HTML page: inline CSS, inline SVG, no scripts.
+pub struct HtmlReport {
+    title: String,
+    sections: Vec<Section>,
+}
+
+impl HtmlReport {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn pre(&mut self, heading: impl Into<String>, text: &str) {
+        let body = format!("<pre>{}</pre>", svg_escape(text));
+        self.push(heading.into(), body);
+    }
+
+    pub fn table(&mut self, heading: impl Into<String>, header: &[&str], rows: &[Vec<String>]) {
+        let mut body = String::from("<table>\n<tr>");
+        for h in header {
+            body.push_str(&format!("<th>{}</th>", svg_escape(h)));
+        }
+        body.push_str("</tr>\n");
+        for row in rows {
+            body.push_str("<tr>");
+            for cell in row {
+                body.push_str(&format!("<td>{}</td>", svg_escape(cell)));
+            }
+            body.push_str("</tr>\n");
+        }
+        body.push_str("</table>");
+        self.push(heading.into(), body);
+    }
+
+    // `svg` is literal markup as written by the charts module.
+    pub fn svg(&mut self, heading: impl Into<String>, svg: &str) {
+        let start = svg.find("<svg").unwrap_or(0);
+        let body = format!("<div class=\"chart\">{}</div>", &svg[start..]);
+        self.push(heading.into(), body);
+    }
+
+    fn push(&mut self, heading: String, body: String) {
+        let anchor = format!("s{}-{}", self.sections.len() + 1, slug(&heading));
+        self.sections.push(Section {
+            anchor,
+            heading,
+            body,
+        });
+    }
+
+    pub fn render(&self) -> String {
+        let mut nav = String::new();
+        let mut body = String::new();
+        for s in &self.sections {
+            nav.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a></li>\n",
+                s.anchor,
+                svg_escape(&s.heading)
+            ));
+            body.push_str(&format!(
+                "<section id=\"{}\">\n<h2>{}</h2>\n{}\n<p><a href=\"#top\">top</a></p>\n</section>\n",
+                s.anchor,
+                svg_escape(&s.heading),
+                s.body
+            ));
+        }
+        render_page(&svg_escape(&self.title), &nav, &body)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.render())?;
+        Ok(())
+    }
+}
+
+fn render_page(title: &str, nav: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial, sans-serif; margin: 24px; color: #222222; }}
+h1 {{ font-size: 22px; }}
+h2 {{ font-size: 18px; border-bottom: 1px solid #E6E6E6; padding-bottom: 4px; }}
+table {{ border-collapse: collapse; font-size: 13px; }}
+th, td {{ border: 1px solid #E6E6E6; padding: 4px 8px; text-align: right; }}
+th:first-child, td:first-child {{ text-align: left; }}
+pre {{ background: #F7F7F7; padding: 12px; overflow-x: auto; font-size: 12px; }}
+.chart svg {{ max-width: 100%; height: auto; }}
+</style>
+</head>
+<body>
+<h1 id="top">{title}</h1>
+<nav>
+<ul>
+{nav}</ul>
+</nav>
+{body}</body>
+</html>
+"#
+    )
+}
+
+fn slug(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        if ch.is_ascii_alphanumeric() {
+            out.push(ch.to_ascii_lowercase());
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_matches('-').to_string()
+}
@@ -0,0 +1,157 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use chrono::{DateTime, Utc};
+
+// SplitMix64: tiny, seedable and good enough for picking samples; not for anything
+// security-related.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..n; n must be non-zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // Rejection sampling avoids the modulo bias of a plain `% n`.
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+// Algorithm R: after `seen` offers every item has had a capacity/seen chance to be kept.
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: u64,
+    items: Vec<T>,
+    rng: SplitMix64,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            items: Vec::with_capacity(capacity),
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    pub fn offer(&mut self, item: T) {
+        self.offer_with(|| item);
+    }
+
+    // Only builds the item when it is kept, so callers can skip clones for rejected offers.
+    pub fn offer_with(&mut self, make: impl FnOnce() -> T) {
+        self.seen += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.items.len() < self.capacity {
+            self.items.push(make());
+            return;
+        }
+        let j = self.rng.below(self.seen) as usize;
+        if j < self.capacity {
+            self.items[j] = make();
+        }
+    }
+
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+// Per-advisory cap on propagation events: keeps the `limit` events with the lowest
+// (hop, downstream time), ties to the earlier one found, and hands them back in the
+// order they were found. 0 keeps everything.
+pub struct EventSelector<T> {
+    limit: usize,
+    found: usize,
+    heap: BinaryHeap<SelectedEvent<T>>,
+    dropped_by_hop: BTreeMap<usize, usize>,
+}
+
+struct SelectedEvent<T> {
+    key: (usize, DateTime<Utc>, usize),
+    record: T,
+}
+
+impl<T> PartialEq for SelectedEvent<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for SelectedEvent<T> {}
+
+impl<T> PartialOrd for SelectedEvent<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for SelectedEvent<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<T> EventSelector<T> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            found: 0,
+            heap: BinaryHeap::new(),
+            dropped_by_hop: BTreeMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, hop: usize, downstream_time: DateTime<Utc>, record: T) {
+        self.heap.push(SelectedEvent {
+            key: (hop, downstream_time, self.found),
+            record,
+        });
+        self.found += 1;
+        if self.limit > 0
+            && self.heap.len() > self.limit
+            && let Some(worst) = self.heap.pop()
+        {
+            *self.dropped_by_hop.entry(worst.key.0).or_default() += 1;
+        }
+    }
+
+    // Kept records in discovery order, and the number dropped at each hop.
+    pub fn finish(self) -> (Vec<T>, BTreeMap<usize, usize>) {
+        let mut kept = self.heap.into_vec();
+        kept.sort_by_key(|e| e.key.2);
+        (
+            kept.into_iter().map(|e| e.record).collect(),
+            self.dropped_by_hop,
+        )
+    }
+}
@@ -0,0 +1,211 @@
+use crate::output::{ColumnKind, column_names};
+
+// Shared by rqx2_rustsec_batch and rqx2_strict so summaries can be concatenated.
+pub const SUMMARY_SCHEMA_ID: &str = "summary.v7";
+pub const SUMMARY_SCHEMA: [(&str, ColumnKind); 66] = [
+    ("rustsec_id", ColumnKind::Utf8),
+    ("cve_id", ColumnKind::Utf8),
+    ("ghsa_id", ColumnKind::Utf8),
+    ("aliases", ColumnKind::Utf8),
+    ("cve_is_fallback", ColumnKind::Bool),
+    ("group_id", ColumnKind::Utf8),
+    ("function_scoped", ColumnKind::Bool),
+    ("affected_functions", ColumnKind::Utf8),
+    ("informational_kind", ColumnKind::Utf8),
+    ("withdrawn_date", ColumnKind::Date),
+    ("severity", ColumnKind::Utf8),
+    ("target_crate", ColumnKind::Utf8),
+    ("fixed_version", ColumnKind::Utf8),
+    ("fix_time", ColumnKind::Timestamp),
+    ("downstream_fixed_cnt", ColumnKind::Int64),
+    ("lag_days_min", ColumnKind::Float64),
+    ("lag_days_p50", ColumnKind::Float64),
+    ("lag_days_avg", ColumnKind::Float64),
+    ("lag_days_max", ColumnKind::Float64),
+    ("t0_kind", ColumnKind::Utf8),
+    ("pre_disclosure_cnt", ColumnKind::Int64),
+    ("lag_unit", ColumnKind::Utf8),
+    ("negative_lag_rows", ColumnKind::Int64),
+    ("regression_cnt", ColumnKind::Int64),
+    ("adoption_compatible_cnt", ColumnKind::Int64),
+    ("adoption_minor_bump_cnt", ColumnKind::Int64),
+    ("adoption_major_bump_cnt", ColumnKind::Int64),
+    ("adoption_unknown_cnt", ColumnKind::Int64),
+    ("as_of", ColumnKind::Date),
+    ("downstream_excluded_few_versions", ColumnKind::Int64),
+    ("downstream_excluded_inactive", ColumnKind::Int64),
+    ("downstream_history_rows", ColumnKind::Int64),
+    ("fix_delta_patch_cnt", ColumnKind::Int64),
+    ("fix_delta_minor_cnt", ColumnKind::Int64),
+    ("fix_delta_major_cnt", ColumnKind::Int64),
+    ("downstream_total_cnt", ColumnKind::Int64),
+    ("downstream_affected_cnt", ColumnKind::Int64),
+    ("fix_selection", ColumnKind::Utf8),
+    ("fixed_versions_used", ColumnKind::Utf8),
+    ("advisory_quality", ColumnKind::Utf8),
+    ("lag_mode", ColumnKind::Utf8),
+    ("downstream_exposed_cnt", ColumnKind::Int64),
+    ("downstream_never_exposed_cnt", ColumnKind::Int64),
+    ("downstream_unparseable_cnt", ColumnKind::Int64),
+    ("owner_group_cnt", ColumnKind::Int64),
+    ("owner_lag_days_min", ColumnKind::Float64),
+    ("owner_lag_days_p50", ColumnKind::Float64),
+    ("owner_lag_days_avg", ColumnKind::Float64),
+    ("owner_lag_days_max", ColumnKind::Float64),
+    ("collapse_rows_before", ColumnKind::Int64),
+    ("collapse_rows_after", ColumnKind::Int64),
+    ("merged_names", ColumnKind::Utf8),
+    ("downstream_source", ColumnKind::Utf8),
+    ("exposure_days", ColumnKind::Float64),
+    ("vulnerable_since_first_release", ColumnKind::Bool),
+    ("duplicate_of", ColumnKind::Utf8),
+    ("fix_semver_compatible", ColumnKind::Bool),
+    ("severity_source", ColumnKind::Utf8),
+    ("lag_over_cadence_rows", ColumnKind::Int64),
+    ("lag_over_cadence_p25", ColumnKind::Float64),
+    ("lag_over_cadence_p50", ColumnKind::Float64),
+    ("lag_over_cadence_p75", ColumnKind::Float64),
+    ("duplicate_dep_rows_merged", ColumnKind::Int64),
+    ("keywords", ColumnKind::Utf8),
+    ("categories", ColumnKind::Utf8),
+    ("post_fix_new_dependents", ColumnKind::Int64),
+];
+
+pub const SUMMARY_COLUMNS: [&str; 66] = column_names(&SUMMARY_SCHEMA);
+
+// One row per downstream adoption; also the leading columns of the owner-grouped and
+// collapsed outputs.
+pub const STRICT_LAG_SCHEMA_ID: &str = "lag_rows.v2";
+pub const STRICT_LAG_SCHEMA: [(&str, ColumnKind); 28] = [
+    ("rustsec_id", ColumnKind::Utf8),
+    ("cve_id", ColumnKind::Utf8),
+    ("ghsa_id", ColumnKind::Utf8),
+    ("aliases", ColumnKind::Utf8),
+    ("cve_is_fallback", ColumnKind::Bool),
+    ("group_id", ColumnKind::Utf8),
+    ("severity", ColumnKind::Utf8),
+    ("target_crate", ColumnKind::Utf8),
+    ("fixed_version", ColumnKind::Utf8),
+    ("fix_time", ColumnKind::Timestamp),
+    ("downstream_crate", ColumnKind::Utf8),
+    ("downstream_version", ColumnKind::Utf8),
+    ("downstream_time", ColumnKind::Timestamp),
+    ("lag_days", ColumnKind::Int64),
+    ("original_req", ColumnKind::Utf8),
+    ("fixed_req", ColumnKind::Utf8),
+    ("t0_kind", ColumnKind::Utf8),
+    ("lag_hours", ColumnKind::Int64),
+    ("lag_days_frac", ColumnKind::Float64),
+    ("adoption_index", ColumnKind::Int64),
+    ("adoption_kind", ColumnKind::Utf8),
+    ("adoption_evidence", ColumnKind::Utf8),
+    ("original_req_min", ColumnKind::Utf8),
+    ("fixed_req_min", ColumnKind::Utf8),
+    ("fix_version_delta", ColumnKind::Utf8),
+    ("lag_mode", ColumnKind::Utf8),
+    ("cadence_days", ColumnKind::Float64),
+    ("lag_over_cadence", ColumnKind::Float64),
+];
+
+pub const STRICT_LAG_COLUMNS: [&str; 28] = column_names(&STRICT_LAG_SCHEMA);
+
+pub const PROPAGATION_EVENT_SCHEMA_ID: &str = "propagation_events.v2";
+pub const PROPAGATION_EVENT_SCHEMA: [(&str, ColumnKind); 23] = [
+    ("root_rustsec_id", ColumnKind::Utf8),
+    ("root_cve_id", ColumnKind::Utf8),
+    ("root_ghsa_id", ColumnKind::Utf8),
+    ("root_aliases", ColumnKind::Utf8),
+    ("root_cve_is_fallback", ColumnKind::Bool),
+    ("root_group_id", ColumnKind::Utf8),
+    ("root_target_crate", ColumnKind::Utf8),
+    ("hop", ColumnKind::Int64),
+    ("upstream_crate", ColumnKind::Utf8),
+    ("upstream_fix_version", ColumnKind::Utf8),
+    ("upstream_fix_time", ColumnKind::Timestamp),
+    ("downstream_crate", ColumnKind::Utf8),
+    ("downstream_version", ColumnKind::Utf8),
+    ("downstream_time", ColumnKind::Timestamp),
+    ("lag_days", ColumnKind::Int64),
+    ("dep_req", ColumnKind::Utf8),
+    ("lag_hours", ColumnKind::Int64),
+    ("lag_days_frac", ColumnKind::Float64),
+    ("adoption_evidence", ColumnKind::Utf8),
+    ("cumulative_lag_days", ColumnKind::Int64),
+    ("first_opportunity_time", ColumnKind::Timestamp),
+    ("first_opportunity_lag_days", ColumnKind::Float64),
+    ("skipped_releases", ColumnKind::Int64),
+];
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct LagStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+pub fn compute_lag_stats<I>(lags: I) -> Option<LagStats>
+where
+    I: IntoIterator<Item = f64>,
+{
+    let mut xs: Vec<f64> = lags.into_iter().collect();
+    if xs.is_empty() {
+        return None;
+    }
+    xs.sort_unstable_by(f64::total_cmp);
+    let count = xs.len();
+    let min = *xs.first().unwrap();
+    let max = *xs.last().unwrap();
+    let sum: f64 = xs.iter().sum();
+    let avg = sum / count as f64;
+    Some(LagStats {
+        count,
+        min,
+        max,
+        avg,
+        p5: percentile_sorted(&xs, 0.05),
+        p25: percentile_sorted(&xs, 0.25),
+        p50: percentile_sorted(&xs, 0.50),
+        p75: percentile_sorted(&xs, 0.75),
+        p95: percentile_sorted(&xs, 0.95),
+    })
+}
+
+// Linear interpolation between closest ranks; q=0.5 gives the usual median
+// (mean of the two middle values for even counts). `xs` must be sorted.
+pub fn percentile_sorted(xs: &[f64], q: f64) -> f64 {
+    if xs.is_empty() {
+        return f64::NAN;
+    }
+    let pos = q.clamp(0.0, 1.0) * (xs.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    xs[lo] + (xs[hi] - xs[lo]) * (pos - lo as f64)
+}
+
+// Fraction of `sorted` at or below each point of the ascending `grid`, in one scan;
+// NaN everywhere when there are no values.
+pub fn cumulative_fractions(sorted: &[f64], grid: &[f64]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(grid.len());
+    let mut below = 0usize;
+    for &d in grid {
+        while below < sorted.len() && sorted[below] <= d {
+            below += 1;
+        }
+        out.push(below as f64 / sorted.len() as f64);
+    }
+    out
+}
+
+pub fn format_float(v: f64) -> String {
+    if v.is_finite() {
+        format!("{v:.4}")
+    } else {
+        v.to_string()
+    }
+}
@@ -0,0 +1,59 @@
+use std::{collections::HashMap, future::Future};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::database::{CrateActivity, DownstreamVersionInfo};
+
+// Read access to the crates.io tables the analyses use, keyed by `crates.id`. Implemented
+// by the Postgres backend and by the in-memory CSV dump; `Database` picks one at startup
+// and keeps its name -> id cache on top.
+pub trait VersionStore {
+    fn crate_id(&self, crate_name: &str) -> impl Future<Output = Result<Option<i64>>> + Send;
+
+    // Normal (kind = 0) dependents of the crate, ordered by downstream crate name, then
+    // created_at, then version number.
+    fn downstream_details(
+        &self,
+        crate_id: i64,
+    ) -> impl Future<Output = Result<Vec<DownstreamVersionInfo>>> + Send;
+
+    fn count_downstream_details(&self, crate_id: i64) -> impl Future<Output = Result<i64>> + Send;
+
+    // Distinct crates with a normal (kind = 0) dependency on the crate in any version.
+    fn count_dependents(&self, crate_id: i64) -> impl Future<Output = Result<i64>> + Send;
+
+    // Owners as `user:<gh_login>` / `team:<login>`, sorted; empty when the ownership
+    // tables have no rows for the crate.
+    fn crate_owners(&self, crate_id: i64) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    fn version_time(
+        &self,
+        crate_id: i64,
+        version: &str,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>>> + Send;
+
+    fn version_numbers(&self, crate_id: i64) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    // Ordered by created_at.
+    fn version_times(
+        &self,
+        crate_id: i64,
+    ) -> impl Future<Output = Result<Vec<(String, DateTime<Utc>)>>> + Send;
+
+    // Names missing from the store are left out.
+    fn crate_downloads(
+        &self,
+        names: &[String],
+    ) -> impl Future<Output = Result<HashMap<String, i64>>> + Send;
+
+    // Only versions created before `before` count; names without any are left out.
+    fn crate_activity(
+        &self,
+        names: &[String],
+        before: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<HashMap<String, CrateActivity>>> + Send;
+
+    // Newest version timestamp; identifies which crates.io snapshot a run used.
+    fn snapshot_freshness(&self) -> impl Future<Output = Result<Option<DateTime<Utc>>>> + Send;
+}
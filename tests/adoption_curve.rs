@@ -0,0 +1,39 @@
+// The adoption-curve grid: the fraction of lags at or below each grid day, evaluated in one
+// scan over sorted lags.
+
+use time_to_fix_cve::stats::cumulative_fractions;
+
+const GRID: [f64; 8] = [0.0, 7.0, 14.0, 30.0, 60.0, 90.0, 180.0, 365.0];
+
+#[test]
+fn grid_points_include_lags_on_the_boundary() {
+    let lags = [0.0, 3.5, 7.0, 7.0, 20.0, 90.0, 200.0, 400.0];
+    assert_eq!(
+        cumulative_fractions(&lags, &GRID),
+        [0.125, 0.5, 0.5, 0.625, 0.625, 0.75, 0.75, 0.875]
+    );
+}
+
+#[test]
+fn the_curve_is_monotone_and_reaches_one_past_the_largest_lag() {
+    let lags: Vec<f64> = (0..50).map(|i| (i * i) as f64 / 7.0).collect();
+    let curve = cumulative_fractions(&lags, &GRID);
+    assert!(curve.windows(2).all(|w| w[0] <= w[1]), "{curve:?}");
+    assert_eq!(cumulative_fractions(&lags, &[1e9]), [1.0]);
+    // Every point agrees with counting directly.
+    for (d, frac) in GRID.iter().zip(&curve) {
+        let direct = lags.iter().filter(|l| *l <= d).count() as f64 / lags.len() as f64;
+        assert_eq!(*frac, direct, "day {d}");
+    }
+}
+
+#[test]
+fn edge_cases() {
+    // Lags before the first grid day, i.e. adoptions before t0.
+    assert_eq!(cumulative_fractions(&[-2.0, -1.0], &[0.0, 7.0]), [1.0, 1.0]);
+    assert_eq!(cumulative_fractions(&[500.0], &GRID), [0.0; 8]);
+    assert_eq!(cumulative_fractions(&[1.0, 2.0], &[]), Vec::<f64>::new());
+    let empty = cumulative_fractions(&[], &[0.0, 30.0]);
+    assert_eq!(empty.len(), 2);
+    assert!(empty.iter().all(|f| f.is_nan()), "{empty:?}");
+}
@@ -0,0 +1,37 @@
+// `classify_adoption`: how far a downstream moved its req to adopt a fix.
+
+use semver::Version;
+use time_to_fix_cve::analysis::classify_adoption;
+use time_to_fix_cve::pipeline::AdoptionKind;
+
+#[test]
+fn adoption_kinds_over_caret_tilde_and_exact_reqs() {
+    use AdoptionKind::{Compatible, MajorBump, MinorBump, Unknown};
+    for (original, fixed, fix, expected) in [
+        // The old req already admitted the fix.
+        ("^1.2", "^1.2.5", "1.2.5", Compatible),
+        ("~1", "~1.2.5", "1.2.5", Compatible),
+        ("~0.3.0", "~0.3.1", "0.3.1", Compatible),
+        ("*", "^1.2.5", "1.2.5", Compatible),
+        // Same semver-compatible line: a minor or patch move counts as minor_bump.
+        ("~1.1.0", "~1.2.5", "1.2.5", MinorBump),
+        ("=1.2.4", "=1.2.5", "1.2.5", MinorBump),
+        ("=0.3.0", "^0.3.1", "0.3.1", MinorBump),
+        // Crossing a compatible line, which is the minor for 0.x.
+        ("^0.2", "^0.3.1", "0.3.1", MajorBump),
+        ("~0.2.9", "=0.3.1", "0.3.1", MajorBump),
+        ("^1", "^2.0.1", "2.0.1", MajorBump),
+        ("=1.9.9", ">=2.0.1, <3", "2.0.1", MajorBump),
+        // An upper-bound-only req starts at 0.0.0.
+        ("<1.2.5", "^1.2.5", "1.2.5", MajorBump),
+        // Either req unreadable.
+        ("not a req", "^1.2.5", "1.2.5", Unknown),
+        ("=1.2.4", "", "1.2.5", Unknown),
+    ] {
+        assert_eq!(
+            classify_adoption(original, fixed, &Version::parse(fix).unwrap()),
+            expected,
+            "{original} -> {fixed} ({fix})"
+        );
+    }
+}
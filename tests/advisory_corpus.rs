@@ -0,0 +1,463 @@
+// Front-matter parsing over tests/fixtures/advisories, a corpus modelled on published
+// RustSec advisories (prose trimmed) plus a few files exercising formatting quirks: CRLF
+// line endings, fence attributes, bare TOML dates, odd `patched` entries and cvss lists.
+
+use std::{io::Write, path::PathBuf};
+
+use chrono::NaiveDate;
+use time_to_fix_cve::advisory::{
+    InformationalKind, PrereleasePolicy, extract_all_fixed_versions, extract_severity,
+    extract_toml_front_matter, parse_advisory, parse_advisory_archive,
+};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+struct Expected {
+    id: &'static str,
+    package: &'static str,
+    cve: &'static str,
+    severity: &'static str,
+    fixed: &'static [&'static str],
+    informational: InformationalKind,
+    withdrawn: Option<&'static str>,
+    functions: usize,
+}
+
+const fn vuln(
+    id: &'static str,
+    package: &'static str,
+    cve: &'static str,
+    severity: &'static str,
+    fixed: &'static [&'static str],
+) -> Expected {
+    Expected {
+        id,
+        package,
+        cve,
+        severity,
+        fixed,
+        informational: InformationalKind::None,
+        withdrawn: None,
+        functions: 0,
+    }
+}
+
+const fn info(id: &'static str, package: &'static str, kind: InformationalKind) -> Expected {
+    Expected {
+        id,
+        package,
+        cve: id,
+        severity: "INFO",
+        fixed: &[],
+        informational: kind,
+        withdrawn: None,
+        functions: 0,
+    }
+}
+
+const fn functions(e: Expected, functions: usize) -> Expected {
+    Expected { functions, ..e }
+}
+
+const fn withdrawn(e: Expected, date: &'static str) -> Expected {
+    Expected {
+        withdrawn: Some(date),
+        ..e
+    }
+}
+
+const CORPUS: &[Expected] = &[
+    info(
+        "RUSTSEC-2016-0005",
+        "rust-crypto",
+        InformationalKind::Unmaintained,
+    ),
+    vuln(
+        "RUSTSEC-2017-0001",
+        "sodiumoxide",
+        "CVE-2017-1000168",
+        "HIGH",
+        &["0.0.14"],
+    ),
+    vuln(
+        "RUSTSEC-2017-0004",
+        "base64",
+        "CVE-2017-1000430",
+        "UNKNOWN",
+        &["0.5.2"],
+    ),
+    info(
+        "RUSTSEC-2017-0007",
+        "lz4-compress",
+        InformationalKind::Unmaintained,
+    ),
+    withdrawn(
+        vuln(
+            "RUSTSEC-2018-0002",
+            "tar",
+            "CVE-2018-20990",
+            "UNKNOWN",
+            &["0.4.16"],
+        ),
+        "2021-04-13",
+    ),
+    functions(
+        vuln(
+            "RUSTSEC-2018-0003",
+            "smallvec",
+            "CVE-2018-20991",
+            "UNKNOWN",
+            &["0.6.3"],
+        ),
+        1,
+    ),
+    vuln(
+        "RUSTSEC-2018-0010",
+        "openssl",
+        "CVE-2018-20997",
+        "UNKNOWN",
+        &["0.10.9"],
+    ),
+    vuln(
+        "RUSTSEC-2018-0013",
+        "safe-transmute",
+        "CVE-2018-21000",
+        "CRITICAL",
+        &["0.10.1"],
+    ),
+    vuln(
+        "RUSTSEC-2019-0009",
+        "smallvec",
+        "CVE-2019-15551",
+        "UNKNOWN",
+        &["0.6.10"],
+    ),
+    vuln(
+        "RUSTSEC-2019-0027",
+        "libsecp256k1",
+        "CVE-2019-25003",
+        "UNKNOWN",
+        &["0.3.1"],
+    ),
+    functions(
+        vuln(
+            "RUSTSEC-2019-0033",
+            "http",
+            "CVE-2019-25008",
+            "HIGH",
+            &["0.1.20"],
+        ),
+        1,
+    ),
+    functions(
+        vuln(
+            "RUSTSEC-2019-0034",
+            "http",
+            "CVE-2019-25009",
+            "CRITICAL",
+            &["0.1.20"],
+        ),
+        1,
+    ),
+    vuln(
+        "RUSTSEC-2019-0036",
+        "failure",
+        "CVE-2019-25010",
+        "CRITICAL",
+        &[],
+    ),
+    vuln(
+        "RUSTSEC-2020-0028",
+        "rocket",
+        "CVE-2020-35882",
+        "MEDIUM",
+        &["0.4.5"],
+    ),
+    info(
+        "RUSTSEC-2020-0036",
+        "failure",
+        InformationalKind::Unmaintained,
+    ),
+    // A yank notice that still names the release to move to.
+    Expected {
+        fixed: &["0.7.3"],
+        ..info("RUSTSEC-2020-0052", "crossbeam", InformationalKind::Notice)
+    },
+    functions(
+        vuln(
+            "RUSTSEC-2020-0071",
+            "time",
+            "CVE-2020-26235",
+            "MEDIUM",
+            &["0.2.23"],
+        ),
+        6,
+    ),
+    vuln(
+        "RUSTSEC-2020-0159",
+        "chrono",
+        "RUSTSEC-2020-0159",
+        "UNKNOWN",
+        &["0.4.20"],
+    ),
+    functions(
+        vuln(
+            "RUSTSEC-2021-0003",
+            "smallvec",
+            "CVE-2021-25900",
+            "CRITICAL",
+            &["0.6.14", "1.6.1"],
+        ),
+        1,
+    ),
+    withdrawn(
+        vuln(
+            "RUSTSEC-2021-0067",
+            "cranelift-codegen",
+            "CVE-2021-32629",
+            "UNKNOWN",
+            &["0.73.1"],
+        ),
+        "2021-06-01",
+    ),
+    vuln(
+        "RUSTSEC-2021-0078",
+        "hyper",
+        "CVE-2021-32715",
+        "MEDIUM",
+        &["0.14.10"],
+    ),
+    vuln(
+        "RUSTSEC-2021-0079",
+        "hyper",
+        "CVE-2021-32714",
+        "CRITICAL",
+        &["0.14.10"],
+    ),
+    vuln(
+        "RUSTSEC-2021-0124",
+        "tokio",
+        "CVE-2021-45710",
+        "UNKNOWN",
+        &["1.8.4", "1.13.1"],
+    ),
+    info(
+        "RUSTSEC-2021-0139",
+        "ansi_term",
+        InformationalKind::Unmaintained,
+    ),
+    info("RUSTSEC-2021-0145", "atty", InformationalKind::Unsound),
+    vuln(
+        "RUSTSEC-2022-0013",
+        "regex",
+        "CVE-2022-24713",
+        "HIGH",
+        &["1.5.5"],
+    ),
+    info(
+        "RUSTSEC-2022-0040",
+        "owning_ref",
+        InformationalKind::Unsound,
+    ),
+    vuln(
+        "RUSTSEC-2023-0018",
+        "remove_dir_all",
+        "RUSTSEC-2023-0018",
+        "LOW",
+        &["0.8.0"],
+    ),
+    vuln(
+        "RUSTSEC-2023-0044",
+        "openssl",
+        "RUSTSEC-2023-0044",
+        "UNKNOWN",
+        &["0.10.55"],
+    ),
+    vuln(
+        "RUSTSEC-2023-0065",
+        "tungstenite",
+        "CVE-2023-43669",
+        "MEDIUM",
+        &["0.19.0", "0.20.1", "0.21.0-alpha.1"],
+    ),
+    vuln(
+        "RUSTSEC-2024-0003",
+        "h2",
+        "RUSTSEC-2024-0003",
+        "UNKNOWN",
+        &["0.3.24", "0.4.2"],
+    ),
+];
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/advisories")
+}
+
+// (path relative to the fixture dir, contents), sorted by path.
+fn fixtures() -> Vec<(String, String)> {
+    let root = fixture_dir();
+    let mut out = Vec::new();
+    for krate in std::fs::read_dir(&root).unwrap() {
+        for file in std::fs::read_dir(krate.unwrap().path()).unwrap() {
+            let path = file.unwrap().path();
+            let rel = path
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            out.push((rel, std::fs::read_to_string(&path).unwrap()));
+        }
+    }
+    out.sort();
+    out
+}
+
+#[test]
+fn every_fixture_has_an_expectation() {
+    let mut ids: Vec<String> = fixtures()
+        .iter()
+        .map(|(rel, _)| {
+            rel.rsplit('/')
+                .next()
+                .unwrap()
+                .trim_end_matches(".md")
+                .to_string()
+        })
+        .collect();
+    ids.sort();
+    let expected: Vec<&str> = CORPUS.iter().map(|e| e.id).collect();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn corpus_front_matters_parse_to_expected_values() {
+    let by_id: std::collections::HashMap<&str, &Expected> =
+        CORPUS.iter().map(|e| (e.id, e)).collect();
+    for (rel, md) in fixtures() {
+        let front =
+            extract_toml_front_matter(&md).unwrap_or_else(|| panic!("{rel}: no front matter"));
+        assert!(!front.contains("```"), "{rel}");
+        let val: toml::Value = toml::from_str(front).unwrap_or_else(|e| panic!("{rel}: {e}"));
+        let adv = parse_advisory(&val).unwrap_or_else(|e| panic!("{rel}: {e:#}"));
+        let exp = by_id[adv.rustsec_id.as_str()];
+
+        assert!(rel.starts_with(&format!("{}/", exp.package)), "{rel}");
+        assert_eq!(adv.package, exp.package, "{rel}");
+        assert_eq!(adv.cve_id, exp.cve, "{rel}");
+        assert_eq!(adv.cve_is_fallback, exp.cve == exp.id, "{rel}");
+        assert_eq!(adv.informational, exp.informational, "{rel}");
+        assert_eq!(adv.affected_functions.len(), exp.functions, "{rel}");
+        assert_eq!(adv.withdrawn, exp.withdrawn.is_some(), "{rel}");
+        assert_eq!(
+            adv.withdrawn_date,
+            exp.withdrawn
+                .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()),
+            "{rel}"
+        );
+
+        let severity = extract_severity(val["advisory"].as_table().unwrap());
+        assert_eq!(severity, exp.severity, "{rel}");
+        assert_eq!(adv.severity, exp.severity, "{rel}");
+
+        let fixed: Vec<String> =
+            extract_all_fixed_versions(&adv.patched, PrereleasePolicy::Include)
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+        assert_eq!(fixed, exp.fixed, "{rel}");
+    }
+}
+
+#[test]
+fn keywords_and_categories_are_kept_verbatim() {
+    let parsed: std::collections::HashMap<String, _> = fixtures()
+        .iter()
+        .map(|(_, md)| {
+            let val: toml::Value = toml::from_str(extract_toml_front_matter(md).unwrap()).unwrap();
+            let adv = parse_advisory(&val).unwrap();
+            (adv.rustsec_id.clone(), adv)
+        })
+        .collect();
+    let smallvec = &parsed["RUSTSEC-2019-0009"];
+    assert_eq!(smallvec.categories, ["memory-corruption"]);
+    // Free-form keywords keep their spaces and case.
+    assert_eq!(
+        smallvec.keywords,
+        ["double free", "use after free", "arbitrary code execution"]
+    );
+    assert_eq!(
+        parsed["RUSTSEC-2020-0071"].categories,
+        ["code-execution", "memory-corruption"]
+    );
+    assert_eq!(parsed["RUSTSEC-2018-0002"].categories, ["file-disclosure"]);
+    // Informational advisories usually carry neither array.
+    assert!(parsed["RUSTSEC-2021-0145"].categories.is_empty());
+    assert!(parsed["RUSTSEC-2021-0145"].keywords.is_empty());
+}
+
+#[test]
+fn excluding_prereleases_drops_only_prerelease_fixes() {
+    for (rel, md) in fixtures() {
+        let val: toml::Value = toml::from_str(extract_toml_front_matter(&md).unwrap()).unwrap();
+        let adv = parse_advisory(&val).unwrap();
+        let all = extract_all_fixed_versions(&adv.patched, PrereleasePolicy::Include);
+        let stable = extract_all_fixed_versions(&adv.patched, PrereleasePolicy::Exclude);
+        let expected: Vec<_> = all.into_iter().filter(|v| v.pre.is_empty()).collect();
+        assert_eq!(stable, expected, "{rel}");
+    }
+}
+
+#[test]
+fn corpus_archive_loads_without_parse_errors() {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (rel, md) in fixtures() {
+        zip.start_file(
+            format!("advisory-db-main/crates/{rel}"),
+            SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(md.as_bytes()).unwrap();
+    }
+    let bytes = zip.finish().unwrap().into_inner();
+
+    let (advisories, source) = parse_advisory_archive(&bytes, "fixtures", true).unwrap();
+    assert_eq!(source.parse_error_count, 0);
+    assert_eq!(source.top_level_dir.as_deref(), Some("advisory-db-main"));
+    let ids: Vec<&str> = advisories.iter().map(|a| a.rustsec_id.as_str()).collect();
+    let expected: Vec<&str> = CORPUS.iter().map(|e| e.id).collect();
+    assert_eq!(ids, expected);
+
+    let group = |id: &str| {
+        advisories
+            .iter()
+            .find(|a| a.rustsec_id == id)
+            .unwrap()
+            .group_id
+            .clone()
+    };
+    // `related` links the deprecation notice to the soundness advisory.
+    assert_eq!(group("RUSTSEC-2020-0036"), "RUSTSEC-2019-0036");
+    // time and chrono share the localtime_r GHSA id.
+    assert_eq!(group("RUSTSEC-2020-0159"), "RUSTSEC-2020-0071");
+    assert_eq!(group("RUSTSEC-2021-0079"), "RUSTSEC-2021-0079");
+}
+
+#[test]
+fn advisory_dates_take_the_calendar_day() {
+    let date = |line: &str| {
+        let toml =
+            format!("[advisory]\nid = \"RUSTSEC-2020-0001\"\npackage = \"vulnlib\"\n{line}\n");
+        parse_advisory(&toml::from_str(&toml).unwrap())
+            .unwrap()
+            .date
+    };
+    let day = NaiveDate::from_ymd_opt(2020, 3, 5);
+    assert_eq!(date("date = \"2020-03-05\""), day);
+    assert_eq!(date("date = \" 2020-03-05 \""), day);
+    assert_eq!(date("date = 2020-03-05"), day);
+    assert_eq!(date("date = \"2020-03-05T23:59:59Z\""), day);
+    assert_eq!(date("date = 2020-03-05T10:00:00Z"), day);
+    assert_eq!(date(""), None);
+    assert_eq!(date("date = \"March 2020\""), None);
+    assert_eq!(date("date = \"2020-13-01\""), None);
+    assert_eq!(date("date = 20200305"), None);
+}
@@ -0,0 +1,166 @@
+// Advisory groups: union-find over RustSec ids, aliases and `related` entries, and how the
+// batch treats a group's advisories with and without `--dedupe-by-cve`.
+
+mod common;
+
+use common::{Workdir, batch, read_columns};
+use time_to_fix_cve::advisory::{Advisory, assign_advisory_groups, parse_advisory};
+
+fn advisory(id: &str, aliases: &[&str], related: &[&str]) -> Advisory {
+    let toml = format!(
+        "[advisory]\nid = \"{id}\"\npackage = \"lib\"\ndate = \"2021-01-01\"\naliases = {aliases:?}\nrelated = {related:?}\n\n[versions]\npatched = [\">= 1.0.0\"]\n"
+    );
+    parse_advisory(&toml::from_str(&toml).unwrap()).unwrap()
+}
+
+// (rustsec_id, group_id), sorted by id.
+fn groups(advisories: &mut [Advisory]) -> Vec<(String, String)> {
+    assign_advisory_groups(advisories);
+    let mut out: Vec<(String, String)> = advisories
+        .iter()
+        .map(|a| (a.rustsec_id.clone(), a.group_id.clone()))
+        .collect();
+    out.sort();
+    out
+}
+
+// 0004 -> 0002 by `related`, 0002 and 0003 share a GHSA id, 0005 names 0004 as an alias:
+// one chain of four. 0001 and 0006 stand alone.
+fn corpus() -> Vec<Advisory> {
+    vec![
+        advisory("RUSTSEC-2021-0004", &[], &["RUSTSEC-2021-0002"]),
+        advisory("RUSTSEC-2021-0001", &["CVE-2021-1"], &[]),
+        advisory("RUSTSEC-2021-0003", &["GHSA-aaaa-bbbb-cccc"], &[]),
+        advisory(
+            "RUSTSEC-2021-0002",
+            &["CVE-2021-2", "GHSA-aaaa-bbbb-cccc"],
+            &[],
+        ),
+        advisory("RUSTSEC-2021-0005", &["RUSTSEC-2021-0004"], &[]),
+        advisory("RUSTSEC-2021-0006", &[], &["RUSTSEC-2099-0001"]),
+    ]
+}
+
+#[test]
+fn linked_advisories_share_the_smallest_id() {
+    let id = |n: &str| format!("RUSTSEC-2021-{n}");
+    let expected: Vec<(String, String)> = [
+        ("0001", "0001"),
+        ("0002", "0002"),
+        ("0003", "0002"),
+        ("0004", "0002"),
+        ("0005", "0002"),
+        ("0006", "0006"),
+    ]
+    .into_iter()
+    .map(|(n, group)| (id(n), id(group)))
+    .collect();
+    assert_eq!(groups(&mut corpus()), expected);
+}
+
+#[test]
+fn groups_do_not_depend_on_input_order() {
+    let expected = groups(&mut corpus());
+    for rotate in 1..6 {
+        let mut advisories = corpus();
+        advisories.rotate_left(rotate);
+        assert_eq!(groups(&mut advisories), expected, "rotate {rotate}");
+        let mut advisories = corpus();
+        advisories.reverse();
+        advisories.rotate_left(rotate);
+        assert_eq!(
+            groups(&mut advisories),
+            expected,
+            "reverse, rotate {rotate}"
+        );
+    }
+}
+
+#[test]
+fn a_link_between_two_groups_merges_them() {
+    let mut advisories = vec![
+        advisory("RUSTSEC-2021-0003", &["CVE-2021-3"], &[]),
+        advisory("RUSTSEC-2021-0004", &["CVE-2021-3"], &[]),
+        advisory("RUSTSEC-2021-0001", &["CVE-2021-1"], &[]),
+        advisory("RUSTSEC-2021-0002", &["CVE-2021-1"], &[]),
+    ];
+    let before: Vec<String> = groups(&mut advisories)
+        .into_iter()
+        .map(|(_, g)| g)
+        .collect();
+    assert_eq!(
+        before,
+        [
+            "RUSTSEC-2021-0001",
+            "RUSTSEC-2021-0001",
+            "RUSTSEC-2021-0003",
+            "RUSTSEC-2021-0003"
+        ]
+    );
+    advisories.push(advisory(
+        "RUSTSEC-2021-0005",
+        &["CVE-2021-3"],
+        &["RUSTSEC-2021-0002"],
+    ));
+    assert!(
+        groups(&mut advisories)
+            .iter()
+            .all(|(_, g)| g == "RUSTSEC-2021-0001")
+    );
+}
+
+// A second vulnlib advisory that only points at RUSTSEC-2020-0001 through `related`, so
+// CVE dedupe alone would keep both.
+const RELATED: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0009"
+package = "vulnlib"
+date = "2020-03-05"
+related = ["RUSTSEC-2020-0001"]
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# Follow-up to RUSTSEC-2020-0001
+"#;
+
+// (rustsec_id, group_id) of the vulnlib strict lag rows.
+fn vulnlib_rows(extra: &[&str], name: &str) -> Vec<[String; 2]> {
+    let dir = Workdir::golden()
+        .advisory("vulnlib/RUSTSEC-2020-0009.md", RELATED)
+        .create(name);
+    let out = batch(&dir, extra);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let mut rows: Vec<[String; 2]> = read_columns(
+        &dir.join("rustsec_rqx2_strict_lags.csv"),
+        ["rustsec_id", "group_id", "target_crate"],
+    )
+    .into_iter()
+    .filter(|[_, _, krate]| krate == "vulnlib")
+    .map(|[id, group, _]| [id, group])
+    .collect();
+    std::fs::remove_dir_all(&dir).ok();
+    rows.sort();
+    rows.dedup();
+    rows
+}
+
+#[test]
+fn grouped_advisories_are_all_analyzed_by_default() {
+    assert_eq!(
+        vulnlib_rows(&[], "groups_keep_all"),
+        [
+            ["RUSTSEC-2020-0001", "RUSTSEC-2020-0001"],
+            ["RUSTSEC-2020-0009", "RUSTSEC-2020-0001"],
+        ]
+    );
+}
+
+#[test]
+fn dedupe_analyzes_one_advisory_per_group_and_crate() {
+    assert_eq!(
+        vulnlib_rows(&["--dedupe-by-cve", "prefer-earliest"], "groups_dedupe"),
+        [["RUSTSEC-2020-0001", "RUSTSEC-2020-0001"]]
+    );
+}
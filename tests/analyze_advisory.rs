@@ -0,0 +1,235 @@
+// `analyze_advisory` against an in-memory `VersionStore` that records which queries ran.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use time_to_fix_cve::{
+    advisory::{Advisory, parse_advisory},
+    database::{CrateActivity, DownstreamVersionInfo, RowSource},
+    pipeline::{
+        AnalysisOptions, CrateRenames, PackageAliases, SkipReason, StoreSource, analyze_advisory,
+    },
+    store::VersionStore,
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+#[derive(Default)]
+struct MockStore {
+    ids: HashMap<String, i64>,
+    versions: HashMap<i64, Vec<(String, DateTime<Utc>)>>,
+    downstream: HashMap<i64, Vec<DownstreamVersionInfo>>,
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl MockStore {
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl VersionStore for MockStore {
+    async fn crate_id(&self, crate_name: &str) -> Result<Option<i64>> {
+        self.record("crate_id");
+        Ok(self.ids.get(crate_name).copied())
+    }
+
+    async fn downstream_details(&self, crate_id: i64) -> Result<Vec<DownstreamVersionInfo>> {
+        self.record("downstream_details");
+        Ok(self.downstream.get(&crate_id).cloned().unwrap_or_default())
+    }
+
+    async fn count_downstream_details(&self, crate_id: i64) -> Result<i64> {
+        self.record("count_downstream_details");
+        Ok(self.downstream.get(&crate_id).map_or(0, |d| d.len() as i64))
+    }
+
+    async fn count_dependents(&self, _crate_id: i64) -> Result<i64> {
+        self.record("count_dependents");
+        Ok(0)
+    }
+
+    async fn crate_owners(&self, _crate_id: i64) -> Result<Vec<String>> {
+        self.record("crate_owners");
+        Ok(Vec::new())
+    }
+
+    async fn version_time(&self, crate_id: i64, version: &str) -> Result<Option<DateTime<Utc>>> {
+        self.record("version_time");
+        Ok(self
+            .versions
+            .get(&crate_id)
+            .and_then(|vs| vs.iter().find(|(v, _)| v == version))
+            .map(|(_, t)| *t))
+    }
+
+    async fn version_numbers(&self, crate_id: i64) -> Result<Vec<String>> {
+        self.record("version_numbers");
+        Ok(self
+            .versions
+            .get(&crate_id)
+            .into_iter()
+            .flatten()
+            .map(|(v, _)| v.clone())
+            .collect())
+    }
+
+    async fn version_times(&self, crate_id: i64) -> Result<Vec<(String, DateTime<Utc>)>> {
+        self.record("version_times");
+        Ok(self.versions.get(&crate_id).cloned().unwrap_or_default())
+    }
+
+    async fn crate_downloads(&self, _names: &[String]) -> Result<HashMap<String, i64>> {
+        self.record("crate_downloads");
+        Ok(HashMap::new())
+    }
+
+    async fn crate_activity(
+        &self,
+        _names: &[String],
+        _before: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<String, CrateActivity>> {
+        self.record("crate_activity");
+        Ok(HashMap::new())
+    }
+
+    async fn snapshot_freshness(&self) -> Result<Option<DateTime<Utc>>> {
+        self.record("snapshot_freshness");
+        Ok(None)
+    }
+}
+
+// vulnlib 0.1.0 is vulnerable and 0.1.1 fixes it; app_a moves to ^0.1.1 on 03-11.
+fn store() -> MockStore {
+    let dep = |version: &str, created_at, req: &str| DownstreamVersionInfo {
+        crate_name: "app_a".to_string(),
+        version: version.to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    };
+    MockStore {
+        ids: HashMap::from([("vulnlib".to_string(), 1)]),
+        versions: HashMap::from([(
+            1,
+            vec![
+                ("0.1.0".to_string(), day(1, 1)),
+                ("0.1.1".to_string(), day(3, 1)),
+            ],
+        )]),
+        downstream: HashMap::from([(
+            1,
+            vec![
+                dep("1.0.0", day(1, 15), "^0.1.0"),
+                dep("1.0.1", day(3, 11), "^0.1.1"),
+            ],
+        )]),
+        ..MockStore::default()
+    }
+}
+
+fn advisory(package: &str) -> Advisory {
+    let toml = format!(
+        r#"
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "{package}"
+date = "2020-02-01"
+aliases = ["CVE-2020-0001"]
+
+[versions]
+patched = [">= 0.1.1"]
+"#
+    );
+    parse_advisory(&toml.parse().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn unknown_crates_skip_before_any_version_query() {
+    let store = store();
+    let opts = AnalysisOptions::default();
+    let mut source = StoreSource::new(&store, opts.renames);
+    let out = analyze_advisory(&mut source, &advisory("no_such_crate"), &opts)
+        .await
+        .unwrap();
+    let skip = out.skip.unwrap();
+    assert_eq!(skip.reason, SkipReason::CrateNotFound);
+    assert_eq!(skip.detail, "attempted_names=no_such_crate|no-such-crate");
+    assert!(out.fix_times.is_empty() && out.rows.is_empty());
+    assert_eq!(store.calls(), ["crate_id", "crate_id"]);
+}
+
+#[tokio::test]
+async fn known_crates_go_on_to_the_lag_scan() {
+    let store = store();
+    let opts = AnalysisOptions::default();
+    let mut source = StoreSource::new(&store, opts.renames);
+    let out = analyze_advisory(&mut source, &advisory("vulnlib"), &opts)
+        .await
+        .unwrap();
+    assert!(out.skip.is_none(), "{:?}", out.skip);
+    assert_eq!(out.vuln_versions.len(), 1);
+    assert_eq!(out.vuln_versions[0].to_string(), "0.1.0");
+    let rows: Vec<_> = out
+        .rows
+        .iter()
+        .map(|r| (r.downstream_version.as_str(), r.lag_secs / 86_400))
+        .collect();
+    assert_eq!(rows, [("1.0.1", 10)]);
+    assert_eq!(
+        store.calls(),
+        ["crate_id", "version_times", "downstream_details"]
+    );
+}
+
+// RustSec calls the crate vuln-lib-rs; it was published as oldlib before its rename, and
+// app_b still depends on the old name.
+#[tokio::test]
+async fn aliases_and_renames_are_resolved_before_the_scan() {
+    let mut store = store();
+    store.ids.insert("oldlib".to_string(), 2);
+    store.downstream.insert(
+        2,
+        [
+            ("1.9.0", day(2, 1), "^0.1.0"),
+            ("2.0.0", day(3, 5), "^0.1.1"),
+        ]
+        .map(|(version, created_at, req)| DownstreamVersionInfo {
+            crate_name: "app_b".to_string(),
+            version: version.to_string(),
+            created_at,
+            dep_req: req.to_string(),
+            source: RowSource::Database,
+        })
+        .to_vec(),
+    );
+    let aliases = PackageAliases::parse(&["vuln-lib-rs=vulnlib".to_string()]).unwrap();
+    let renames =
+        CrateRenames::parse("old_name,new_name,effective_date\noldlib,vulnlib,2019-06-01\n")
+            .unwrap();
+    let opts = AnalysisOptions {
+        package_aliases: &aliases,
+        renames: &renames,
+        ..AnalysisOptions::default()
+    };
+    let mut source = StoreSource::new(&store, opts.renames);
+    let out = analyze_advisory(&mut source, &advisory("vuln-lib-rs"), &opts)
+        .await
+        .unwrap();
+    assert!(out.skip.is_none(), "{:?}", out.skip);
+    assert_eq!(out.crate_name, "vulnlib");
+    assert_eq!(out.merged_names, "oldlib|vulnlib");
+    let rows: Vec<_> = out
+        .rows
+        .iter()
+        .map(|r| (r.downstream_crate.as_str(), r.lag_secs / 86_400))
+        .collect();
+    assert_eq!(rows, [("app_a", 10), ("app_b", 4)]);
+}
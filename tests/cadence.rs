@@ -0,0 +1,121 @@
+// Downstream release cadence: the median gap between a crate's releases in the year
+// before the fix, and strict lag expressed in units of that gap.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::database::{DownstreamVersionInfo, RowSource};
+use time_to_fix_cve::pipeline::{
+    DuplicateReqs, FixMatchPolicy, StrictLagOptions, T0Kind, compute_strict_lags_for_target,
+    release_cadence_days,
+};
+
+fn day(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn median_gap_over_the_year_before() {
+    let fix = day(2020, 3, 1);
+    // Gaps of 10, 20 and 60 days.
+    let times = [
+        day(2019, 11, 2),
+        day(2019, 11, 12),
+        day(2019, 12, 2),
+        day(2020, 1, 31),
+    ];
+    assert_eq!(release_cadence_days(&times, fix), Some(20.0));
+    // An even number of gaps averages the middle two; input order does not matter.
+    let times = [
+        day(2019, 12, 21),
+        day(2019, 12, 1),
+        day(2020, 1, 31),
+        day(2020, 1, 1),
+        day(2019, 12, 11),
+    ];
+    assert_eq!(release_cadence_days(&times, fix), Some(10.5));
+}
+
+#[test]
+fn releases_outside_the_window_are_ignored() {
+    let fix = day(2020, 3, 1);
+    let times = [
+        day(2018, 1, 1),
+        day(2019, 1, 1),
+        day(2019, 6, 1),
+        day(2019, 6, 11),
+        // At or after the fix: not part of the crate's pace before it.
+        day(2020, 3, 1),
+        day(2020, 3, 5),
+    ];
+    assert_eq!(release_cadence_days(&times, fix), None);
+}
+
+#[test]
+fn fewer_than_three_releases_have_no_cadence() {
+    let fix = day(2020, 3, 1);
+    assert_eq!(release_cadence_days(&[], fix), None);
+    assert_eq!(release_cadence_days(&[day(2020, 1, 1)], fix), None);
+    assert_eq!(
+        release_cadence_days(&[day(2020, 1, 1), day(2020, 2, 1)], fix),
+        None
+    );
+    // Releases at the same instant count once.
+    assert_eq!(
+        release_cadence_days(&[day(2020, 1, 1), day(2020, 1, 1), day(2020, 2, 1)], fix),
+        None
+    );
+}
+
+fn dep(
+    crate_name: &str,
+    version: &str,
+    created_at: DateTime<Utc>,
+    req: &str,
+) -> DownstreamVersionInfo {
+    DownstreamVersionInfo {
+        crate_name: crate_name.to_string(),
+        version: version.to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    }
+}
+
+#[test]
+fn strict_rows_carry_cadence_and_normalized_lag() {
+    let fix_times = BTreeMap::from([(Version::new(0, 2, 0), day(2020, 3, 1))]);
+    let vuln = [Version::new(0, 1, 0)];
+    let downstream = vec![
+        // Releases every 10 days, adopts 30 days after the fix.
+        dep("busy", "1.0.0", day(2020, 2, 1), "^0.1"),
+        dep("busy", "1.0.1", day(2020, 2, 11), "^0.1"),
+        dep("busy", "1.0.2", day(2020, 2, 21), "^0.1"),
+        dep("busy", "1.1.0", day(2020, 3, 31), "^0.2"),
+        // One release before the fix: no cadence.
+        dep("rare", "1.0.0", day(2019, 6, 1), "^0.1"),
+        dep("rare", "2.0.0", day(2020, 6, 1), "^0.2"),
+    ];
+    let opts = StrictLagOptions {
+        t0_kind: T0Kind::FixRelease,
+        advisory_time: None,
+        all_adoptions: false,
+        history_cutoff: None,
+        policy: FixMatchPolicy::Either,
+        duplicate_reqs: DuplicateReqs::LeastRestrictive,
+    };
+    let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts, None);
+    let got: Vec<(&str, Option<f64>, Option<f64>)> = scan
+        .rows
+        .iter()
+        .map(|r| {
+            (
+                r.downstream_crate.as_str(),
+                r.cadence_days,
+                r.lag_over_cadence(),
+            )
+        })
+        .collect();
+    assert_eq!(got, [("busy", Some(10.0), Some(3.0)), ("rare", None, None)]);
+}
@@ -0,0 +1,192 @@
+// --chart-width/--chart-height/--chart-font-scale/--chart-palette: every chart type takes
+// its canvas size, text size and colors from the style it is built with, and text stays
+// inside the canvas when it grows.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use time_to_fix_cve::charts::{
+    BarChart, BoxPlotChart, ChartStyle, GroupedBarChart, HistogramChart, StackedBarChart,
+    StepCurveChart,
+};
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rq2_chart_style_{name}_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// One chart of each type, as (name, svg text).
+fn draw_all(dir: &Path, style: &ChartStyle) -> Vec<(&'static str, String)> {
+    let values: Vec<f64> = (0..50).map(|i| (i * 7 % 90) as f64).collect();
+    let grouped = [("a", vec![1, 2]), ("b", vec![3, 4])];
+    let series = ["p50", "p75"];
+    let path = |name: &str| dir.join(format!("{name}.svg"));
+    HistogramChart::new(&values)
+        .style(style)
+        .data_csv(false)
+        .overflow_bin(true)
+        .x_max(60.0)
+        .title("lags")
+        .x_label("days")
+        .write_svg(path("histogram"))
+        .unwrap();
+    BarChart::new(&[("a", 3), ("b", 5)])
+        .style(style)
+        .data_csv(false)
+        .title("bars")
+        .write_svg(path("bar"))
+        .unwrap();
+    StackedBarChart::new(&grouped, &series)
+        .style(style)
+        .write_svg(path("stacked"))
+        .unwrap();
+    GroupedBarChart::new(&grouped, &series)
+        .style(style)
+        .write_svg(path("grouped"))
+        .unwrap();
+    StepCurveChart::new(
+        &[30.0, 90.0, 365.0],
+        &[("all".to_string(), vec![0.2, 0.5, 0.9])],
+    )
+    .style(style)
+    .x_label("days")
+    .write_svg(path("step"))
+    .unwrap();
+    BoxPlotChart::new(&[("x".to_string(), values.clone())])
+        .style(style)
+        .y_label("days")
+        .write_svg(path("box"))
+        .unwrap();
+    ["histogram", "bar", "stacked", "grouped", "step", "box"]
+        .into_iter()
+        .map(|name| (name, std::fs::read_to_string(path(name)).unwrap()))
+        .collect()
+}
+
+// Every `name="number"` attribute value in `svg`.
+fn attr_values(svg: &str, name: &str) -> Vec<f64> {
+    let needle = format!(" {name}=\"");
+    svg.match_indices(&needle)
+        .filter_map(|(i, _)| {
+            let rest = &svg[i + needle.len()..];
+            rest[..rest.find('"')?].parse().ok()
+        })
+        .collect()
+}
+
+#[test]
+fn style_applies_to_every_chart_type() {
+    let dir = scratch("all");
+
+    let style = ChartStyle::new(Some(480), Some(360), 1.5, "#112233, #445566").unwrap();
+    for (name, svg) in draw_all(&dir, &style) {
+        assert!(
+            svg.starts_with(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="480" height="360" viewBox="0 0 480 360">"#
+            ),
+            "{name}: {svg}"
+        );
+        assert!(svg.contains(r#"font-size="18""#), "{name}: {svg}");
+        assert!(svg.contains("#112233"), "{name}: {svg}");
+        assert!(!svg.contains("#4C78A8"), "{name}: {svg}");
+        // Text anchors stay on the canvas.
+        for line in svg.lines().filter(|l| l.starts_with("<text")) {
+            for x in attr_values(line, "x") {
+                assert!((0.0..=480.0).contains(&x), "{name}: {line}");
+            }
+            for y in attr_values(line, "y") {
+                assert!((0.0..=360.0).contains(&y), "{name}: {line}");
+            }
+        }
+    }
+
+    for (name, svg) in draw_all(&dir, &ChartStyle::default()) {
+        let size = if matches!(name, "histogram" | "box") {
+            r#"width="960" height="540""#
+        } else {
+            r#"width="960" height="520""#
+        };
+        assert!(svg.contains(size), "{name}: {svg}");
+        assert!(svg.contains(r#"font-size="12""#), "{name}: {svg}");
+        assert!(svg.contains("#4C78A8"), "{name}: {svg}");
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn data_csv_is_written_only_when_asked() {
+    let dir = scratch("data_csv");
+    for (data_csv, name) in [(true, "with"), (false, "without")] {
+        HistogramChart::new(&[1.0, 2.0, 2.5])
+            .bins(2)
+            .x_max(4.0)
+            .data_csv(data_csv)
+            .write_svg(dir.join(format!("hist_{name}.svg")))
+            .unwrap();
+        BarChart::new(&[("a", 3)])
+            .data_csv(data_csv)
+            .write_svg(dir.join(format!("bar_{name}.svg")))
+            .unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(dir.join("hist_with.csv")).unwrap(),
+        "bin_start,bin_end,count\n0,2,1\n2,4,2\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("bar_with.csv")).unwrap(),
+        "category,count\na,3\n"
+    );
+    assert!(!dir.join("hist_without.csv").exists());
+    assert!(!dir.join("bar_without.csv").exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn palettes_and_limits() {
+    let named = ChartStyle::new(None, None, 1.0, "grayscale").unwrap();
+    assert_eq!(named.palette.unwrap()[0], "#252525");
+    assert_eq!(
+        ChartStyle::new(None, None, 1.0, "default").unwrap(),
+        ChartStyle::default()
+    );
+    assert_eq!(
+        ChartStyle::new(None, None, 1.0, "#abc,#A0B1C2")
+            .unwrap()
+            .palette,
+        Some(vec!["#abc".to_string(), "#A0B1C2".to_string()])
+    );
+    for (width, scale, palette, message) in [
+        (
+            None,
+            1.0,
+            "neon",
+            "expected default, colorblind, grayscale, viridis",
+        ),
+        (
+            None,
+            1.0,
+            "#12345",
+            "\"#12345\" is not a #rgb or #rrggbb color",
+        ),
+        (None, 1.0, "#123456,", "\"\" is not a #rgb or #rrggbb color"),
+        (
+            None,
+            0.0,
+            "default",
+            "--chart-font-scale 0: must be between",
+        ),
+        (
+            Some(300),
+            2.0,
+            "default",
+            "--chart-width 300: must be at least 480",
+        ),
+    ] {
+        let err = ChartStyle::new(width, None, scale, palette).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
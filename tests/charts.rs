@@ -0,0 +1,79 @@
+// Axis ticks, binning and text escaping shared by every chart.
+
+use time_to_fix_cve::charts::{
+    histogram_counts, histogram_counts_with_overflow, nice_ticks, svg_escape,
+};
+
+#[test]
+fn ticks_for_zero_tiny_and_huge_maxima() {
+    assert_eq!(nice_ticks(0.0, 5), [0.0]);
+    assert_eq!(nice_ticks(-3.0, 5), [0.0]);
+    assert_eq!(nice_ticks(f64::NAN, 5), [0.0]);
+    assert_eq!(nice_ticks(f64::INFINITY, 5), [0.0]);
+
+    let tiny = nice_ticks(0.003, 5);
+    assert_eq!(tiny.len(), 4);
+    assert!((tiny[1] - 0.001).abs() < 1e-12, "{tiny:?}");
+    assert!(*tiny.last().unwrap() >= 0.003, "{tiny:?}");
+
+    let huge = nice_ticks(3.7e9, 5);
+    assert_eq!(huge, [0.0, 1e9, 2e9, 3e9, 4e9]);
+
+    assert_eq!(nice_ticks(100.0, 6), [0.0, 20.0, 40.0, 60.0, 80.0, 100.0]);
+    assert_eq!(nice_ticks(7.0, 0), [0.0, 10.0]);
+}
+
+#[test]
+fn ticks_always_cover_the_maximum() {
+    for max in [1.0, 9.0, 11.0, 99.5, 1234.0, 0.42] {
+        let ticks = nice_ticks(max, 5);
+        assert_eq!(ticks[0], 0.0);
+        assert!(*ticks.last().unwrap() >= max, "{max}: {ticks:?}");
+        assert!(ticks.windows(2).all(|w| w[1] > w[0]), "{max}: {ticks:?}");
+    }
+}
+
+#[test]
+fn bins_are_half_open_with_the_maximum_in_the_last_bin() {
+    // Width 10: [0,10) [10,20) [20,30) [30,40].
+    let values = [0.0, 9.999, 10.0, 19.0, 20.0, 30.0, 40.0];
+    assert_eq!(histogram_counts(&values, 4, 40.0), [2, 2, 1, 2]);
+    // Values past x_max fold into the last bin; negatives and NaN are dropped.
+    assert_eq!(
+        histogram_counts(&[-1.0, 55.0, f64::NAN, 5.0], 4, 40.0),
+        [1, 0, 0, 1]
+    );
+    // Zero bins and sub-unit maxima are clamped.
+    assert_eq!(histogram_counts(&[0.5, 3.0], 0, 0.2), [2]);
+    assert_eq!(histogram_counts(&[], 3, 10.0), [0, 0, 0]);
+}
+
+#[test]
+fn overflow_bucket_holds_values_past_the_clip() {
+    let values = [0.0, 10.0, 40.0, 40.5, 400.0, 3000.0];
+    let (counts, overflow) = histogram_counts_with_overflow(&values, 4, 40.0);
+    assert_eq!(counts, [1, 1, 0, 1]);
+    assert_eq!(overflow, 3);
+
+    let (counts, overflow) = histogram_counts_with_overflow(&[1.0, 2.0], 2, 10.0);
+    assert_eq!((counts, overflow), (vec![2, 0], 0));
+}
+
+#[test]
+fn overflow_bucket_skips_nan() {
+    let values = [f64::NAN, 4.0, f64::NAN, 50.0];
+    let (counts, overflow) = histogram_counts_with_overflow(&values, 2, 10.0);
+    assert_eq!(counts, [1, 0]);
+    assert_eq!(overflow, 1);
+}
+
+#[test]
+fn escapes_markup_characters() {
+    assert_eq!(
+        svg_escape(r#"a & b < c > d "e" 'f'"#),
+        "a &amp; b &lt; c &gt; d &quot;e&quot; &apos;f&apos;"
+    );
+    assert_eq!(svg_escape("&amp;"), "&amp;amp;");
+    assert_eq!(svg_escape("plain ≥ text"), "plain ≥ text");
+    assert_eq!(svg_escape(""), "");
+}
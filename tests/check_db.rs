@@ -0,0 +1,77 @@
+// Schema detection across dump vintages: --check-db reports the optional columns, and
+// missing required columns or flags that need an absent column fail up front.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{Workdir, batch};
+
+// The golden dump with `versions.csv` and `dependencies.csv` replaced.
+fn trimmed_dump(name: &str, versions: &str, dependencies: &str) -> PathBuf {
+    Workdir::golden()
+        .file("versions.csv", versions)
+        .file("dependencies.csv", dependencies)
+        .create(&format!("check_db_{name}"))
+}
+
+#[test]
+fn reports_the_optional_columns() {
+    let dir = Workdir::golden().create("check_db_golden");
+    let out = batch(&dir, &["--check-db"]);
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    for line in [
+        "required columns: ok",
+        "versions.yanked: present (--exclude-yanked-versions)",
+        "versions.license: absent",
+        "dependencies.target: absent (--exclude-target-specific-deps)",
+        "downloads: crates.downloads (download-weighted reach)",
+        "ownership tables: absent",
+    ] {
+        assert!(stderr.contains(line), "{line}: {stderr}");
+    }
+}
+
+#[test]
+fn missing_required_columns_are_listed() {
+    let dir = trimmed_dump(
+        "required",
+        "id,crate_id,num\n100,1,0.1.0\n",
+        "version_id,crate_id,kind\n",
+    );
+    let out = batch(&dir, &["--check-db"]);
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    assert_ne!(out.status.code(), Some(0), "{stderr}");
+    assert!(
+        stderr.contains("missing required columns: versions.created_at, dependencies.req"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn filters_need_their_column() {
+    let dir = trimmed_dump(
+        "filters",
+        "id,crate_id,num,created_at\n100,1,0.1.0,2020-01-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n",
+    );
+    let out = batch(&dir, &["--check-db"]);
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("versions.yanked: absent"), "{stderr}");
+    for (flag, column) in [
+        ("--exclude-yanked-versions", "versions.yanked"),
+        ("--exclude-target-specific-deps", "dependencies.target"),
+    ] {
+        let out = batch(&dir, &[flag]);
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert_ne!(out.status.code(), Some(0), "{stderr}");
+        assert!(
+            stderr.contains(&format!("{flag} needs {column}")),
+            "{stderr}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
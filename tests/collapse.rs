@@ -0,0 +1,89 @@
+// Workspace collapsing of strict-lag rows for --collapse-prefix-regex.
+
+use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
+use time_to_fix_cve::pipeline::{
+    AdoptionEvidence, AdoptionKind, LagMode, StrictLagRow, T0Kind, collapse_earliest, prefix_group,
+};
+
+fn day(d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, 3, d, 0, 0, 0).unwrap()
+}
+
+fn row(crate_name: &str, downstream_time: DateTime<Utc>) -> StrictLagRow {
+    StrictLagRow {
+        downstream_crate: crate_name.to_string(),
+        downstream_version: "1.0.0".to_string(),
+        downstream_time,
+        lag_secs: (downstream_time - day(1)).num_seconds(),
+        original_req: "^0.1".to_string(),
+        fixed_req: "^0.2".to_string(),
+        matched_fix_version: "0.2.0".to_string(),
+        matched_fix_time: day(1),
+        t0: day(1),
+        t0_kind: T0Kind::FixRelease,
+        adoption_index: 1,
+        adoption_kind: AdoptionKind::MinorBump,
+        evidence: AdoptionEvidence::ReqMatches,
+        negative: false,
+        lag_mode: LagMode::Strict,
+        cadence_days: None,
+        first_opportunity: None,
+    }
+}
+
+#[test]
+fn prefix_groups_of_common_crate_names() {
+    let dash = Regex::new("^([a-z0-9_]+)-").unwrap();
+    let names = [
+        "rusoto_core",
+        "rusoto_s3",
+        "tokio-util",
+        "tokio",
+        "serde_json",
+    ];
+    let groups: Vec<String> = names.iter().map(|n| prefix_group(&dash, n)).collect();
+    // Only dashes split: tokio-util joins tokio, underscore names stay apart.
+    assert_eq!(
+        groups,
+        ["rusoto_core", "rusoto_s3", "tokio", "tokio", "serde_json"]
+    );
+
+    let dash_or_underscore = Regex::new("^([a-z0-9]+)[-_]").unwrap();
+    let groups: Vec<String> = names
+        .iter()
+        .map(|n| prefix_group(&dash_or_underscore, n))
+        .collect();
+    assert_eq!(groups, ["rusoto", "rusoto", "tokio", "tokio", "serde"]);
+}
+
+#[test]
+fn collapsing_keeps_the_earliest_adoption_per_group() {
+    let dash = Regex::new("^([a-z0-9_]+)-").unwrap();
+    let rows = [
+        row("tokio-util", day(20)),
+        row("tokio", day(9)),
+        row("tokio-stream", day(9)),
+        row("serde_json", day(5)),
+    ];
+    let collapsed = collapse_earliest(
+        rows.iter()
+            .map(|r| (prefix_group(&dash, &r.downstream_crate), r)),
+    );
+    let summary: Vec<(&str, &str, i64, usize)> = collapsed
+        .iter()
+        .map(|(g, r, n)| {
+            (
+                g.as_str(),
+                r.downstream_crate.as_str(),
+                r.lag_secs / 86_400,
+                *n,
+            )
+        })
+        .collect();
+    // tokio and tokio-stream tie on day 9; the smaller crate name wins.
+    assert_eq!(
+        summary,
+        [("serde_json", "serde_json", 4, 1), ("tokio", "tokio", 8, 3)]
+    );
+}
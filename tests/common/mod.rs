@@ -0,0 +1,138 @@
+// Shared by the tests that run rqx2_rustsec_batch end to end: a scratch directory holding
+// a csv dump in `dump/` and a local `advisory-db.zip`, so nothing touches the network or
+// Postgres. Each test binary uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::{
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+pub fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden")
+}
+
+// Dump files and advisories for one scratch directory. Advisory paths are relative to the
+// archive's `crates/` directory, e.g. `vulnlib/RUSTSEC-0000-0001.md`.
+#[derive(Default)]
+pub struct Workdir {
+    dump: Vec<(String, String)>,
+    advisories: Vec<(String, String)>,
+}
+
+impl Workdir {
+    pub fn dump(crates: &str, versions: &str, dependencies: &str) -> Self {
+        Self::default()
+            .file("crates.csv", crates)
+            .file("versions.csv", versions)
+            .file("dependencies.csv", dependencies)
+    }
+
+    // The pinned dump and advisories under tests/fixtures/golden.
+    pub fn golden() -> Self {
+        let mut w = Self::default();
+        for file in sorted_files(&golden_dir().join("dump")) {
+            let name = file.file_name().unwrap().to_string_lossy().to_string();
+            w = w.file(&name, &std::fs::read_to_string(&file).unwrap());
+        }
+        let root = golden_dir().join("advisories");
+        for krate in sorted_files(&root) {
+            for file in sorted_files(&krate) {
+                let rel = file
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                w = w.advisory(&rel, &std::fs::read_to_string(&file).unwrap());
+            }
+        }
+        w
+    }
+
+    // Adds a dump file, replacing any earlier one with the same name.
+    pub fn file(mut self, name: &str, contents: &str) -> Self {
+        self.dump.retain(|(n, _)| n != name);
+        self.dump.push((name.to_string(), contents.to_string()));
+        self
+    }
+
+    pub fn advisory(mut self, path: &str, contents: &str) -> Self {
+        self.advisories
+            .push((path.to_string(), contents.to_string()));
+        self
+    }
+
+    // A fresh `$TMPDIR/rq2_<name>_<pid>`; outputs of `batch` land next to the inputs.
+    pub fn create(&self, name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rq2_{name}_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let dump = dir.join("dump");
+        std::fs::create_dir_all(&dump).unwrap();
+        for (file, contents) in &self.dump {
+            std::fs::write(dump.join(file), contents).unwrap();
+        }
+        let mut zip = ZipWriter::new(std::fs::File::create(dir.join("advisory-db.zip")).unwrap());
+        for (path, contents) in &self.advisories {
+            zip.start_file(
+                format!("advisory-db-main/crates/{path}"),
+                SimpleFileOptions::default(),
+            )
+            .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        dir
+    }
+}
+
+fn sorted_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    files.sort();
+    files
+}
+
+// The batch binary pointed at `dir`'s dump and archive, before any test-specific flags.
+pub fn batch_command(dir: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rqx2_rustsec_batch"));
+    cmd.current_dir(dir).args([
+        "--data-source",
+        "csv-dump",
+        "--dump-dir",
+        "dump",
+        "--advisory-db-zip",
+        "advisory-db.zip",
+        "--progress",
+        "never",
+    ]);
+    cmd
+}
+
+pub fn batch(dir: &Path, extra: &[&str]) -> Output {
+    batch_command(dir).args(extra).output().unwrap()
+}
+
+// The named columns of every row of a CSV output.
+pub fn read_columns<const N: usize>(path: &Path, names: [&str; N]) -> Vec<[String; N]> {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let cols = names.map(|name| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .unwrap_or_else(|| panic!("{}: no column {name}", path.display()))
+    });
+    reader
+        .records()
+        .map(|r| {
+            let r = r.unwrap();
+            cols.map(|i| r[i].to_string())
+        })
+        .collect()
+}
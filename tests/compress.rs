@@ -0,0 +1,144 @@
+// --compress: the lag, constraint breakdown and propagation events CSVs are written
+// through a streaming encoder and decompress to the same rows as an uncompressed run.
+
+mod common;
+
+use std::{io::Read, path::Path};
+
+use common::{Workdir, batch};
+use time_to_fix_cve::output::{Compression, OutputFile, open_reader};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+const OUTPUTS: [&str; 3] = [
+    "rustsec_rqx2_strict_lags.csv",
+    "rustsec_rqx2_constraint_breakdown.csv",
+    "events.csv",
+];
+
+fn run(compress: &str) -> std::path::PathBuf {
+    let dir = Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n3,app_b,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n\
+         30,3,0.1.0,2020-01-20 00:00:00\n\
+         31,3,0.2.0,2020-04-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n\
+         20,1,^0.1,0\n21,1,^0.2,0\n30,2,^1.0,0\n31,2,^1.1,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("compress_{compress}"));
+    let out = batch(
+        &dir,
+        &[
+            "--compress",
+            compress,
+            "--constraint",
+            "--propagation",
+            "--propagation-events-output",
+            "events.csv",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    dir
+}
+
+// Decompresses with the codec's own decoder rather than `open_reader`, then parses.
+fn decompress(path: &Path) -> String {
+    let bytes = std::fs::read(path).unwrap();
+    let mut text = String::new();
+    match Compression::from_path(path) {
+        Compression::Gzip => {
+            assert_eq!(bytes[..2], [0x1f, 0x8b], "{}", path.display());
+            flate2::read::GzDecoder::new(&bytes[..])
+                .read_to_string(&mut text)
+                .unwrap();
+        }
+        Compression::Zstd => {
+            text = String::from_utf8(zstd::decode_all(&bytes[..]).unwrap()).unwrap();
+        }
+        Compression::None => text = String::from_utf8(bytes).unwrap(),
+    };
+    text
+}
+
+fn records(text: &str) -> Vec<Vec<String>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let mut rows = vec![
+        reader
+            .headers()
+            .unwrap()
+            .iter()
+            .map(str::to_string)
+            .collect(),
+    ];
+    rows.extend(
+        reader
+            .records()
+            .map(|r| r.unwrap().iter().map(str::to_string).collect()),
+    );
+    rows
+}
+
+#[test]
+fn compressed_outputs_decompress_to_the_plain_rows() {
+    let plain = run("none");
+    for (compress, ext) in [("gzip", "gz"), ("zstd", "zst")] {
+        let dir = run(compress);
+        for output in OUTPUTS {
+            let expected = records(&decompress(&plain.join(output)));
+            assert!(expected.len() > 1, "{output} has no rows");
+            assert!(
+                !dir.join(output).exists(),
+                "{compress}: plain {output} written"
+            );
+            let path = dir.join(format!("{output}.{ext}"));
+            let actual = records(&decompress(&path));
+            assert_eq!(actual, expected, "{}", path.display());
+            // The library reader picks the codec from the extension.
+            let mut via_reader = String::new();
+            open_reader(&path)
+                .unwrap()
+                .read_to_string(&mut via_reader)
+                .unwrap();
+            assert_eq!(records(&via_reader), expected);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    std::fs::remove_dir_all(&plain).ok();
+}
+
+#[test]
+fn dropped_writers_still_end_the_stream() {
+    let dir = std::env::temp_dir().join(format!("rq2_compress_drop_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let path = compression.apply_to(&dir.join("rows.csv"));
+        {
+            let mut w = csv::Writer::from_writer(OutputFile::create(&path, compression).unwrap());
+            w.write_record(["a", "b"]).unwrap();
+            w.write_record(["1", "x,y"]).unwrap();
+            w.flush().unwrap();
+            // No finish(): dropping the writer has to complete the compressed stream.
+        }
+        assert_eq!(
+            records(&decompress(&path)),
+            [vec!["a", "b"], vec!["1", "x,y"]]
+        );
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,109 @@
+// compute_constraint_breakdown must not depend on the backend handing rows over grouped
+// by crate and in time order.
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::database::{DownstreamVersionInfo, RowSource};
+use time_to_fix_cve::pipeline::{
+    ConstraintBreakdown, HorizonBreak, compute_constraint_breakdown, compute_constraint_horizons,
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+fn row(
+    crate_name: &str,
+    version: &str,
+    created_at: DateTime<Utc>,
+    req: &str,
+) -> DownstreamVersionInfo {
+    DownstreamVersionInfo {
+        crate_name: crate_name.to_string(),
+        version: version.to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    }
+}
+
+// vulnlib 0.1.0..=0.1.4 is vulnerable and 0.1.5 (2020-03-01) fixes it. At the fix:
+//   app_a  last released on ^0.1: affected, not locked out.
+//   app_b  last released on =0.1.2: locked out (its earlier ^0.1 release does not count).
+//   app_c  already moved to ^0.2: unaffected.
+fn grouped() -> Vec<DownstreamVersionInfo> {
+    vec![
+        row("app_a", "1.0.0", day(1, 1), "=0.1.0"),
+        row("app_a", "1.1.0", day(2, 1), "^0.1"),
+        row("app_b", "0.1.0", day(1, 5), "^0.1"),
+        row("app_b", "0.2.0", day(2, 5), "=0.1.2"),
+        row("app_b", "0.3.0", day(4, 1), "^0.1.5"),
+        row("app_c", "2.0.0", day(1, 9), "^0.2"),
+    ]
+}
+
+fn breakdown(rows: &[DownstreamVersionInfo]) -> ConstraintBreakdown {
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    compute_constraint_breakdown(day(3, 1), &vuln, &[Version::new(0, 1, 5)], rows, None, None)
+}
+
+fn counts(c: &ConstraintBreakdown) -> (usize, usize, usize) {
+    (
+        c.downstream_crates_with_history,
+        c.affected_edges,
+        c.locked_out_edges,
+    )
+}
+
+#[test]
+fn grouped_rows_need_no_regrouping() {
+    let c = breakdown(&grouped());
+    assert_eq!(c.regrouped_crates, 0);
+    assert_eq!(counts(&c), (3, 2, 1));
+}
+
+#[test]
+fn interleaved_rows_are_detected_and_regrouped() {
+    // app_a, app_b, app_a, app_b, app_b, app_c: both split into two runs, which a
+    // run-by-run scan would count as five crates with history.
+    let sorted = grouped();
+    let rows: Vec<DownstreamVersionInfo> = [0, 2, 1, 3, 4, 5]
+        .iter()
+        .map(|&i| sorted[i].clone())
+        .collect();
+
+    let c = breakdown(&rows);
+    assert_eq!(c.regrouped_crates, 2);
+    assert_eq!(counts(&c), counts(&breakdown(&grouped())));
+}
+
+#[test]
+fn rows_out_of_time_order_are_regrouped() {
+    let mut rows = grouped();
+    rows.swap(2, 3);
+    let c = breakdown(&rows);
+    assert_eq!(c.regrouped_crates, 1);
+    assert_eq!(counts(&c), (3, 2, 1));
+}
+
+fn horizons(rows: &[DownstreamVersionInfo]) -> Vec<(i64, usize, usize)> {
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    compute_constraint_horizons(day(3, 1), &[0, 60], &vuln, &[Version::new(0, 1, 5)], rows)
+        .iter()
+        .map(|h: &HorizonBreak| (h.horizon_days, h.affected_edges, h.locked_out_edges))
+        .collect()
+}
+
+#[test]
+fn horizons_regroup_rows_sorted_by_time_across_crates() {
+    // At the fix app_b is still on =0.1.2; by day 60 it has moved to ^0.1.5.
+    let expected = [(0, 2, 1), (60, 1, 0)];
+    assert_eq!(horizons(&grouped()), expected);
+
+    // Renamed histories are merged in time order, interleaving the crates.
+    let mut rows = grouped();
+    rows.sort_by_key(|r| r.created_at);
+    assert_eq!(horizons(&rows), expected);
+    rows.reverse();
+    assert_eq!(horizons(&rows), expected);
+}
@@ -0,0 +1,92 @@
+// Constraint breakdowns summed per severity bucket, as the constraint summary and the
+// per-severity bars read them, including shards of a parallel run merged afterwards.
+
+use std::collections::HashMap;
+
+use time_to_fix_cve::pipeline::{ConstraintBreakdown, ConstraintTotals, ReqShape};
+
+fn breakdown(affected: usize, locked: usize, caret: usize) -> ConstraintBreakdown {
+    let mut c = ConstraintBreakdown {
+        downstream_crates_with_history: affected + 1,
+        affected_edges: affected,
+        locked_out_edges: locked,
+        ..ConstraintBreakdown::default()
+    };
+    let shape = ReqShape::ALL
+        .iter()
+        .position(|s| *s == ReqShape::CaretGe1)
+        .unwrap();
+    c.affected_req_shape[shape] = caret;
+    c
+}
+
+fn accumulate(rows: &[(&str, ConstraintBreakdown)]) -> HashMap<String, ConstraintTotals> {
+    let mut by_severity: HashMap<String, ConstraintTotals> = HashMap::new();
+    for (severity, c) in rows {
+        by_severity.entry(severity.to_string()).or_default().add(c);
+    }
+    by_severity
+}
+
+#[test]
+fn totals_accumulate_per_severity() {
+    let rows = [
+        ("CRITICAL", breakdown(10, 6, 4)),
+        ("LOW", breakdown(8, 1, 0)),
+        ("CRITICAL", breakdown(5, 3, 5)),
+        ("none", breakdown(0, 0, 0)),
+        ("HIGH", breakdown(3, 0, 1)),
+    ];
+    let by_severity = accumulate(&rows);
+    let summary: Vec<_> = ConstraintTotals::by_severity_rank(&by_severity)
+        .into_iter()
+        .map(|(sev, t)| {
+            (
+                sev.as_str(),
+                t.affected_edges,
+                t.locked_out_edges,
+                t.break_rate_percent(),
+                t.affected_req_shape.iter().sum::<usize>(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("LOW", 8, 1, 12, 0),
+            ("HIGH", 3, 0, 0, 1),
+            ("CRITICAL", 15, 9, 60, 9),
+            ("none", 0, 0, 0, 0),
+        ]
+    );
+
+    // The buckets add up to the overall totals.
+    let mut overall = ConstraintTotals::default();
+    for (_, c) in &rows {
+        overall.add(c);
+    }
+    let mut summed = ConstraintTotals::default();
+    for t in by_severity.values() {
+        summed.merge(t);
+    }
+    assert_eq!(summed, overall);
+    assert_eq!(overall.downstream_crates_with_history, 31);
+}
+
+#[test]
+fn merged_shards_match_one_pass() {
+    let rows = [
+        ("HIGH", breakdown(4, 2, 1)),
+        ("MEDIUM", breakdown(6, 3, 2)),
+        ("HIGH", breakdown(2, 2, 0)),
+        ("MEDIUM", breakdown(1, 0, 1)),
+        ("CRITICAL", breakdown(7, 7, 3)),
+    ];
+    let (left, right) = rows.split_at(2);
+    let mut merged = accumulate(left);
+    for (sev, t) in accumulate(right) {
+        merged.entry(sev).or_default().merge(&t);
+    }
+    assert_eq!(merged, accumulate(&rows));
+    assert_eq!(merged["HIGH"].break_rate_percent(), 66);
+}
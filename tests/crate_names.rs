@@ -0,0 +1,107 @@
+// RustSec package names that differ from the crates table: `-`/`_` spellings and
+// --package-alias, resolved before any crates.io request.
+
+mod common;
+
+use common::{Workdir, batch};
+use time_to_fix_cve::pipeline::{PackageAliases, crate_name_variants};
+
+#[test]
+fn dash_and_underscore_variants() {
+    for (name, expected) in [
+        ("serde", &["serde"][..]),
+        ("rust-decimal", &["rust-decimal", "rust_decimal"]),
+        ("rust_decimal", &["rust_decimal", "rust-decimal"]),
+        ("a-b_c", &["a-b_c", "a_b_c", "a-b-c"]),
+        ("-", &["-", "_"]),
+    ] {
+        assert_eq!(crate_name_variants(name), expected, "{name}");
+    }
+}
+
+#[test]
+fn package_aliases() {
+    let aliases = PackageAliases::parse(&[
+        "rustdecimal=rust_decimal".to_string(),
+        " rust_demical = rust_decimal ".to_string(),
+        "rustdecimal=rust_decimal".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(aliases.len(), 2);
+    assert_eq!(aliases.resolve("rustdecimal"), "rust_decimal");
+    assert_eq!(aliases.resolve("rust_demical"), "rust_decimal");
+    assert_eq!(aliases.resolve("rust_decimal"), "rust_decimal");
+    assert!(PackageAliases::parse(&[]).unwrap().is_empty());
+
+    for (pairs, message) in [
+        (
+            &["rustdecimal"][..],
+            "--package-alias \"rustdecimal\": expected FROM=TO",
+        ),
+        (&["=rust_decimal"], "expected FROM=TO"),
+        (&["a=b", "a=c"], "--package-alias a: mapped to both b and c"),
+    ] {
+        let pairs: Vec<String> = pairs.iter().map(|p| p.to_string()).collect();
+        let err = PackageAliases::parse(&pairs).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
+
+fn advisory(id: &str, package: &str, patched: &str) -> String {
+    format!(
+        "```toml\n[advisory]\nid = \"{id}\"\npackage = \"{package}\"\ndate = \"2020-03-05\"\n\n[versions]\npatched = [\"{patched}\"]\n```\n\n# Misspelled package\n"
+    )
+}
+
+#[test]
+fn misspelled_packages_resolve_from_the_dump() {
+    let dir = Workdir::golden()
+        .advisory(
+            "app-a/RUSTSEC-2020-0008.md",
+            &advisory("RUSTSEC-2020-0008", "app-a", ">= 1.0.1"),
+        )
+        .advisory(
+            "vulnlibb/RUSTSEC-2020-0009.md",
+            &advisory("RUSTSEC-2020-0009", "vulnlibb", ">= 0.2.0"),
+        )
+        .create("crate_names");
+    let out = batch(
+        &dir,
+        &[
+            "--log-level",
+            "debug",
+            "--package-alias",
+            "vulnlibb=vulnlib",
+        ],
+    );
+    std::fs::remove_dir_all(&dir).ok();
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    assert_eq!(out.status.code(), Some(0), "{stderr}");
+    for line in [
+        "package alias: rustsec_pkg=app-a db_pkg=app_a",
+        "package alias: rustsec_pkg=vulnlibb db_pkg=vulnlib",
+    ] {
+        assert!(stderr.contains(line), "{line}: {stderr}");
+    }
+    assert!(!stderr.contains("crate_not_found"), "{stderr}");
+}
+
+// Whether crates.io answers 404 or cannot be reached, the name stays unresolved and the
+// run goes on.
+#[test]
+fn unknown_packages_are_skipped_not_fatal() {
+    let dir = Workdir::golden()
+        .advisory(
+            "no-such-crate/RUSTSEC-2020-0010.md",
+            &advisory("RUSTSEC-2020-0010", "no-such-crate", ">= 1.0.0"),
+        )
+        .create("crate_names_unknown");
+    let out = batch(&dir, &[]);
+    std::fs::remove_dir_all(&dir).ok();
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    assert_eq!(out.status.code(), Some(0), "{stderr}");
+    assert!(
+        stderr.contains("skip: rustsec_id=RUSTSEC-2020-0010 cve_id=RUSTSEC-2020-0010 pkg=no-such-crate reason=crate_not_found"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,63 @@
+// The crates.io version-time fallback reports why lookups failed. The fix release below is
+// missing from the dump, so its time has to come from crates.io; an unreachable proxy
+// makes that a network error whether or not the machine running the test is online.
+
+mod common;
+
+use common::{Workdir, batch_command};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.3.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+#[test]
+fn network_errors_are_tallied_and_still_fail_the_advisory() {
+    let dir = Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n",
+        "version_id,crate_id,req,kind\n20,1,^0.1,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create("cratesio");
+    let out = batch_command(&dir)
+        .env("HTTPS_PROXY", "http://127.0.0.1:1")
+        .env("https_proxy", "http://127.0.0.1:1")
+        .env_remove("NO_PROXY")
+        .env_remove("no_proxy")
+        .args(["--metadata-output", "meta.json"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2), "{out:?}");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains("crates.io version-time lookups by outcome:"),
+        "{stderr}"
+    );
+    let row = stderr
+        .lines()
+        .find(|l| l.trim_start().starts_with("network_error"))
+        .unwrap_or_else(|| panic!("{stderr}"));
+    let cells: Vec<&str> = row.split_whitespace().collect();
+    assert_eq!(cells[..3], ["network_error", "1", "1"], "{row}");
+
+    let meta: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("meta.json")).unwrap()).unwrap();
+    let tally = &meta["crates_io_version_time"]["network_error"];
+    assert_eq!(tally["lookups"], 1, "{meta}");
+    assert_eq!(tally["requests"], 1, "{meta}");
+    assert!(tally["latency_secs"].as_f64().unwrap() >= 0.0);
+    assert!(meta["crates_io_version_time"].get("found").is_none());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,192 @@
+// Every CSV output gets a `<path>.schema.json` sidecar that names its columns; with
+// `--csv-schema-comments` the file also starts with a `# schema=<id>` line.
+
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use common::{Workdir, batch_command};
+
+use time_to_fix_cve::output::{ColumnKind, CsvSchema};
+use time_to_fix_cve::stats::{
+    PROPAGATION_EVENT_SCHEMA, PROPAGATION_EVENT_SCHEMA_ID, STRICT_LAG_SCHEMA, STRICT_LAG_SCHEMA_ID,
+    SUMMARY_SCHEMA, SUMMARY_SCHEMA_ID,
+};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n3,app_b,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n\
+         30,3,0.1.0,2020-01-20 00:00:00\n\
+         31,3,0.2.0,2020-04-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n\
+         20,1,^0.1,0\n21,1,^0.2,0\n30,1,^0.1,0\n31,1,^0.2,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("schema_{name}"))
+}
+
+fn batch(dir: &Path, extra: &[&str]) -> Output {
+    batch_command(dir)
+        .args([
+            "--constraint",
+            "--constraint-horizons",
+            "30,90",
+            "--constraint-edges-output",
+            "edges.csv",
+            "--adoption-curve-output",
+            "curve.csv",
+            "--propagation",
+            "--propagation-events-output",
+            "events.csv",
+            "--include-negative-lags",
+            "--group-by-owner",
+        ])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+// (csv path, schema id, column names) for every sidecar in the directory.
+fn sidecars(dir: &Path) -> Vec<(PathBuf, String, Vec<String>)> {
+    let mut out = Vec::new();
+    for ent in std::fs::read_dir(dir).unwrap() {
+        let path = ent.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let Some(csv_name) = name.strip_suffix(".schema.json") else {
+            continue;
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let columns = json["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap().to_string())
+            .collect();
+        out.push((
+            dir.join(csv_name),
+            json["schema"].as_str().unwrap().to_string(),
+            columns,
+        ));
+    }
+    out.sort();
+    out
+}
+
+fn expected_columns(schema: &[(&str, ColumnKind)]) -> Vec<String> {
+    schema.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+#[test]
+fn sidecars_match_the_header_rows() {
+    for comments in [false, true] {
+        let dir = workdir(if comments { "comments" } else { "plain" });
+        let extra: &[&str] = if comments {
+            &["--csv-schema-comments"]
+        } else {
+            &[]
+        };
+        let out = batch(&dir, extra);
+        assert_eq!(out.status.code(), Some(0), "{out:?}");
+
+        let found = sidecars(&dir);
+        assert_eq!(found.len(), 10, "{found:?}");
+        for (path, id, columns) in &found {
+            let text = std::fs::read_to_string(path).unwrap();
+            let mut lines = text.lines();
+            if comments {
+                assert_eq!(lines.next(), Some(format!("# schema={id}").as_str()));
+            }
+            let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+            assert_eq!(header, *columns, "{}", path.display());
+
+            // Readers that skip `#` lines see the same header either way.
+            let mut r = csv::ReaderBuilder::new()
+                .comment(Some(b'#'))
+                .from_path(path)
+                .unwrap();
+            assert_eq!(r.headers().unwrap(), columns, "{}", path.display());
+        }
+
+        let by_id = |id: &str| {
+            found
+                .iter()
+                .find(|(_, found_id, _)| found_id == id)
+                .unwrap_or_else(|| panic!("no {id} sidecar"))
+                .2
+                .clone()
+        };
+        assert_eq!(
+            by_id(STRICT_LAG_SCHEMA_ID),
+            expected_columns(&STRICT_LAG_SCHEMA)
+        );
+        assert_eq!(by_id(SUMMARY_SCHEMA_ID), expected_columns(&SUMMARY_SCHEMA));
+        assert_eq!(
+            by_id(PROPAGATION_EVENT_SCHEMA_ID),
+            expected_columns(&PROPAGATION_EVENT_SCHEMA)
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+#[test]
+fn summary_diff_reads_commented_summaries() {
+    let dir = workdir("diff");
+    let out = batch(&dir, &["--csv-schema-comments"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let out = Command::new(env!("CARGO_BIN_EXE_rqx2_summary_diff"))
+        .current_dir(&dir)
+        .args([
+            "--old",
+            "rustsec_rqx2_strict_summary.csv",
+            "--new",
+            "rustsec_rqx2_strict_summary.csv",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let diff = std::fs::read_to_string(dir.join("summary_diff.csv")).unwrap();
+    assert_eq!(diff, "rustsec_id,status,metric,old,new,delta\n");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn records_are_filled_by_column_name() {
+    let schema = CsvSchema::new(
+        "test.v1",
+        &[
+            ("a", ColumnKind::Utf8),
+            ("b", ColumnKind::Int64),
+            ("c", ColumnKind::Utf8),
+        ],
+    );
+    assert_eq!(
+        schema
+            .record(&[("c", "z".to_string()), ("a", "x".to_string())])
+            .unwrap(),
+        ["x", "", "z"]
+    );
+    let err = schema.record(&[("d", String::new())]).unwrap_err();
+    assert_eq!(err.to_string(), "schema test.v1 has no column d");
+}
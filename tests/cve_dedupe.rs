@@ -0,0 +1,100 @@
+// `--dedupe-by-cve`: one advisory per CVE, chosen by RustSec id order.
+
+use time_to_fix_cve::advisory::{Advisory, CveDedupe, dedupe_by_cve, parse_advisory};
+
+fn advisory(id: &str, aliases: &[&str]) -> Advisory {
+    let toml = format!(
+        "[advisory]\nid = \"{id}\"\npackage = \"lib\"\ndate = \"2021-01-01\"\naliases = {aliases:?}\n\n[versions]\npatched = [\">= 1.0.0\"]\n"
+    );
+    parse_advisory(&toml::from_str(&toml).unwrap()).unwrap()
+}
+
+// B repeats A's CVE and brings a second one that C repeats; D stands alone.
+fn corpus() -> Vec<Advisory> {
+    vec![
+        advisory("RUSTSEC-2021-0003", &["CVE-2021-2", "CVE-2021-3"]),
+        advisory("RUSTSEC-2021-0001", &["CVE-2021-1"]),
+        advisory("RUSTSEC-2021-0004", &["CVE-2021-3"]),
+        advisory("RUSTSEC-2021-0002", &["cve-2021-1", "CVE-2021-2"]),
+        advisory("RUSTSEC-2021-0005", &["GHSA-xxxx-yyyy-zzzz"]),
+    ]
+}
+
+fn ids(advisories: &[Advisory]) -> Vec<&str> {
+    let mut ids: Vec<&str> = advisories.iter().map(|a| a.rustsec_id.as_str()).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn prefer_earliest_keeps_the_first_claimant() {
+    let mut advisories = corpus();
+    let dups = dedupe_by_cve(&mut advisories, CveDedupe::PreferEarliest);
+    // 0002 loses CVE-2021-1 to 0001, so its CVE-2021-2 is still free for 0003.
+    assert_eq!(
+        ids(&advisories),
+        [
+            "RUSTSEC-2021-0001",
+            "RUSTSEC-2021-0003",
+            "RUSTSEC-2021-0005"
+        ]
+    );
+    let summary: Vec<(&str, &str, &str)> = dups
+        .iter()
+        .map(|d| {
+            (
+                d.rustsec_id.as_str(),
+                d.duplicate_of.as_str(),
+                d.cve.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("RUSTSEC-2021-0002", "RUSTSEC-2021-0001", "CVE-2021-1"),
+            ("RUSTSEC-2021-0004", "RUSTSEC-2021-0003", "CVE-2021-3"),
+        ]
+    );
+    assert!(advisories.iter().all(|a| a.duplicate_of.is_none()));
+}
+
+#[test]
+fn prefer_latest_walks_ids_newest_first() {
+    let mut advisories = corpus();
+    let dups = dedupe_by_cve(&mut advisories, CveDedupe::PreferLatest);
+    assert_eq!(
+        ids(&advisories),
+        [
+            "RUSTSEC-2021-0002",
+            "RUSTSEC-2021-0004",
+            "RUSTSEC-2021-0005"
+        ]
+    );
+    assert_eq!(dups.len(), 2);
+}
+
+#[test]
+fn keep_all_only_records_duplicates() {
+    let mut advisories = corpus();
+    let dups = dedupe_by_cve(&mut advisories, CveDedupe::KeepAll);
+    assert_eq!(advisories.len(), 5);
+    assert_eq!(dups.len(), 2);
+    let duplicate_of = |id: &str| {
+        advisories
+            .iter()
+            .find(|a| a.rustsec_id == id)
+            .unwrap()
+            .duplicate_of
+            .clone()
+    };
+    assert_eq!(
+        duplicate_of("RUSTSEC-2021-0002").as_deref(),
+        Some("RUSTSEC-2021-0001")
+    );
+    assert_eq!(
+        duplicate_of("RUSTSEC-2021-0004").as_deref(),
+        Some("RUSTSEC-2021-0003")
+    );
+    assert_eq!(duplicate_of("RUSTSEC-2021-0001"), None);
+}
@@ -0,0 +1,130 @@
+// Base scores for published vectors, checked against the scores NVD lists for them.
+
+use time_to_fix_cve::advisory::{cvss2_base_score_from_vector, cvss31_base_score_from_vector};
+
+// (vector, NVD base score). Several carry temporal metrics after the base ones, which must
+// not change the base score.
+const CVSS3: &[(&str, f64)] = &[
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 9.8),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H", 10.0),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H", 7.5),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N", 7.5),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N", 6.1),
+    ("CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H", 7.8),
+    ("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:H/A:H", 8.1),
+    ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H", 8.8),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:U/C:H/I:H/A:H", 8.8),
+    ("CVSS:3.1/AV:L/AC:L/PR:N/UI:R/S:U/C:H/I:H/A:H", 7.8),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:L/I:N/A:N", 5.3),
+    ("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:N/I:N/A:H", 5.9),
+    ("CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:N/I:N/A:H", 5.5),
+    ("CVSS:3.1/AV:P/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 6.8),
+    ("CVSS:3.1/AV:N/AC:L/PR:H/UI:N/S:U/C:H/I:H/A:H", 7.2),
+    ("CVSS:3.1/AV:N/AC:L/PR:L/UI:N/S:C/C:L/I:L/A:N", 6.4),
+    ("CVSS:3.1/AV:A/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 8.8),
+    ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N", 0.0),
+    ("CVSS:3.0/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 9.8),
+    ("CVSS:3.0/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N", 5.9),
+    (
+        "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H/E:P/RL:O/RC:C",
+        9.8,
+    ),
+    (
+        "CVSS:3.0/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H/E:U/RL:O/RC:R",
+        7.5,
+    ),
+    (
+        "CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H/E:F/RL:W/RC:C/CR:H/MAV:N",
+        7.8,
+    ),
+];
+
+const CVSS2: &[(&str, f64)] = &[
+    ("AV:N/AC:L/Au:N/C:P/I:P/A:P", 7.5),
+    ("AV:N/AC:L/Au:N/C:C/I:C/A:C", 10.0),
+    ("AV:N/AC:M/Au:N/C:P/I:N/A:N", 4.3),
+    ("AV:N/AC:L/Au:N/C:N/I:N/A:P", 5.0),
+    ("AV:L/AC:L/Au:N/C:C/I:C/A:C", 7.2),
+    ("AV:N/AC:M/Au:N/C:P/I:P/A:P", 6.8),
+    ("AV:N/AC:L/Au:N/C:P/I:N/A:N", 5.0),
+    ("AV:N/AC:M/Au:N/C:N/I:P/A:N", 4.3),
+    ("AV:L/AC:L/Au:N/C:P/I:N/A:N", 2.1),
+    ("AV:N/AC:H/Au:N/C:P/I:P/A:P", 5.1),
+    ("AV:N/AC:L/Au:S/C:P/I:P/A:P", 6.5),
+    ("AV:N/AC:M/Au:N/C:C/I:C/A:C", 9.3),
+    ("AV:A/AC:L/Au:N/C:C/I:C/A:C", 8.3),
+    ("AV:L/AC:M/Au:N/C:C/I:C/A:C", 6.9),
+    ("AV:N/AC:L/Au:N/C:N/I:N/A:C", 7.8),
+    ("AV:L/AC:L/Au:N/C:N/I:N/A:C", 4.9),
+    ("AV:N/AC:L/Au:N/C:N/I:N/A:N", 0.0),
+    ("(AV:N/AC:L/Au:N/C:P/I:P/A:P)", 7.5),
+    ("CVSS:2.0/AV:N/AC:M/Au:N/C:P/I:N/A:N", 4.3),
+    ("AV:N/AC:L/Au:N/C:P/I:P/A:P/E:POC/RL:OF/RC:C", 7.5),
+];
+
+#[test]
+fn cvss3_vectors_match_nvd() {
+    for &(vector, score) in CVSS3 {
+        assert_eq!(
+            cvss31_base_score_from_vector(vector),
+            Some(score),
+            "{vector}"
+        );
+    }
+}
+
+#[test]
+fn cvss2_vectors_match_nvd() {
+    for &(vector, score) in CVSS2 {
+        assert_eq!(
+            cvss2_base_score_from_vector(vector),
+            Some(score),
+            "{vector}"
+        );
+    }
+}
+
+#[test]
+fn incomplete_or_foreign_vectors_have_no_score() {
+    for vector in [
+        "",
+        "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H",
+        "CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H",
+        "CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:H/VA:H/SC:N/SI:N/SA:N",
+        "AV:N/AC:L/Au:N/C:P/I:P/A:P",
+    ] {
+        assert_eq!(cvss31_base_score_from_vector(vector), None, "{vector}");
+    }
+    for vector in [
+        "",
+        "AV:N/AC:L/Au:N/C:P/I:P",
+        "AV:N/AC:L/Au:N/C:X/I:P/A:P",
+        "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H",
+    ] {
+        assert_eq!(cvss2_base_score_from_vector(vector), None, "{vector}");
+    }
+}
+
+#[cfg(feature = "cvss-crate")]
+#[test]
+fn built_in_scorer_agrees_with_the_cvss_crate() {
+    use std::str::FromStr;
+    use time_to_fix_cve::advisory::cvss3_score_disagreement;
+
+    let mut compared = 0;
+    for &(vector, _) in CVSS3 {
+        assert_eq!(cvss3_score_disagreement(vector), None, "{vector}");
+        if let Ok(parsed) = cvss::v3::Vector::from_str(vector) {
+            assert_eq!(
+                cvss31_base_score_from_vector(vector),
+                Some(parsed.score().value()),
+                "{vector}"
+            );
+            compared += 1;
+        }
+    }
+    assert!(
+        compared >= 15,
+        "only {compared} vectors parsed by the cvss crate"
+    );
+}
@@ -0,0 +1,167 @@
+// The csv-dump backend against tests/fixtures/scenario.sql written out as db-dump tables:
+// the same answers the ignored Postgres tests in tests/database.rs expect, including the
+// kind = 0 filter and the downstream ordering. Columns come in dump order with extras the
+// store ignores.
+
+use std::{env, path::PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+use time_to_fix_cve::database::Database;
+
+const CRATES: &str = "\
+created_at,description,downloads,id,name
+2019-12-01 00:00:00+00,,5000,1,vulnlib
+2019-12-01 00:00:00+00,,300,2,app_a
+2019-12-01 00:00:00+00,,200,3,app_b
+2019-12-01 00:00:00+00,,100,4,app_c
+2019-12-01 00:00:00+00,,50,5,app_d
+2019-12-01 00:00:00+00,,10,6,otherlib
+";
+
+const VERSIONS: &str = "\
+checksum,crate_id,created_at,id,num,yanked
+x,1,2020-01-01 00:00:00+00,10,0.1.0,f
+x,1,2020-02-01 00:00:00+00,11,0.1.1,f
+x,1,2020-03-01 00:00:00+00,12,0.2.0,f
+x,1,2020-05-01 00:00:00+00,13,0.2.1,f
+x,2,2020-01-15 00:00:00+00,20,1.0.1,f
+x,2,2020-01-15 00:00:00+00,21,1.0.0,f
+x,2,2020-03-11 00:00:00+00,22,1.1.0,f
+x,3,2020-01-20 00:00:00+00,30,0.1.0,f
+x,3,2020-04-01 00:00:00+00,31,0.2.0,f
+x,3,2020-05-01 00:00:00+00,32,0.3.0,f
+x,4,2020-01-25 00:00:00+00,40,0.1.0,f
+x,5,2020-04-10 00:00:00+00,50,1.0.0,f
+x,6,2020-01-01 00:00:00+00,60,1.0.0,f
+";
+
+// Listed out of id order so the store has to sort, not rely on file order.
+const DEPENDENCIES: &str = "\
+crate_id,default_features,features,id,kind,optional,req,target,version_id
+1,t,{},107,0,f,^0.2,,50
+1,t,{},104,0,f,^0.2.0,,31
+1,t,{},100,0,f,^0.1,,20
+1,t,{},101,0,f,^0.1,,21
+1,t,{},102,0,f,^0.2,,22
+1,t,{},103,0,f,=0.1.1,,30
+1,t,{},105,2,f,^0.1,,32
+1,t,{},106,2,f,^0.1,,40
+6,t,{},108,0,f,^1,,22
+";
+
+const USERS: &str = "gh_avatar,gh_id,gh_login,id,name\n,1,alice,1,\n,2,bob,2,\n";
+const TEAMS: &str = "avatar,github_id,id,login,name,org_id\n,1,1,github:org:core,,1\n";
+const CRATE_OWNERS: &str = "\
+crate_id,created_at,created_by,owner_id,owner_kind
+2,2019-12-01 00:00:00+00,,1,0
+2,2019-12-01 00:00:00+00,,1,1
+3,2019-12-01 00:00:00+00,,1,1
+4,2019-12-01 00:00:00+00,,2,0
+";
+
+fn day(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+}
+
+fn scenario(name: &str) -> (PathBuf, Database) {
+    let dir = env::temp_dir().join(format!("rq2_dump_store_{name}_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    // Extracted dumps keep the tables under `data/`.
+    let data = dir.join("data");
+    std::fs::create_dir_all(&data).unwrap();
+    for (file, contents) in [
+        ("crates.csv", CRATES),
+        ("versions.csv", VERSIONS),
+        ("dependencies.csv", DEPENDENCIES),
+        ("users.csv", USERS),
+        ("teams.csv", TEAMS),
+        ("crate_owners.csv", CRATE_OWNERS),
+    ] {
+        std::fs::write(data.join(file), contents).unwrap();
+    }
+    let db = Database::open_dump(dir.to_str().unwrap()).unwrap();
+    (dir, db)
+}
+
+#[tokio::test]
+async fn downstream_details_keep_normal_dependencies_in_order() {
+    let (dir, db) = scenario("downstream");
+    let rows: Vec<(String, String, DateTime<Utc>, String)> = db
+        .query_all_downstream_details("vulnlib")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.crate_name, r.version, r.created_at, r.dep_req))
+        .collect();
+    let row = |c: &str, v: &str, t, req: &str| (c.to_string(), v.to_string(), t, req.to_string());
+    assert_eq!(
+        rows,
+        vec![
+            row("app_a", "1.0.0", day(2020, 1, 15), "^0.1"),
+            row("app_a", "1.0.1", day(2020, 1, 15), "^0.1"),
+            row("app_a", "1.1.0", day(2020, 3, 11), "^0.2"),
+            row("app_b", "0.1.0", day(2020, 1, 20), "=0.1.1"),
+            row("app_b", "0.2.0", day(2020, 4, 1), "^0.2.0"),
+            row("app_d", "1.0.0", day(2020, 4, 10), "^0.2"),
+        ]
+    );
+    assert_eq!(db.count_downstream_details("vulnlib").await.unwrap(), 6);
+    // app_a, app_b and app_d; app_c only dev-depends.
+    assert_eq!(db.count_dependents("vulnlib").await.unwrap(), 3);
+    assert_eq!(db.count_dependents("no_such_crate").await.unwrap(), 0);
+    assert!(
+        db.query_all_downstream_details("no_such_crate")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn crate_owners_are_sorted_and_fall_back_to_empty() {
+    let (dir, db) = scenario("owners");
+    assert_eq!(
+        db.query_crate_owners("app_a").await.unwrap(),
+        ["team:github:org:core", "user:alice"]
+    );
+    assert_eq!(
+        db.query_crate_owners("app_b").await.unwrap(),
+        ["team:github:org:core"]
+    );
+    assert!(db.query_crate_owners("app_d").await.unwrap().is_empty());
+    assert!(
+        db.query_crate_owners("no_such_crate")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn version_times_and_numbers() {
+    let (dir, db) = scenario("versions");
+    assert_eq!(
+        db.query_version_time("vulnlib", "0.2.0").await.unwrap(),
+        Some(day(2020, 3, 1))
+    );
+    assert_eq!(
+        db.query_version_time("vulnlib", "0.3.0").await.unwrap(),
+        None
+    );
+    assert_eq!(
+        db.query_version_time("no_such_crate", "0.1.0")
+            .await
+            .unwrap(),
+        None
+    );
+    let mut numbers = db.query_all_version_numbers("vulnlib").await.unwrap();
+    numbers.sort();
+    assert_eq!(numbers, ["0.1.0", "0.1.1", "0.2.0", "0.2.1"]);
+    assert_eq!(
+        db.snapshot_freshness().await.unwrap(),
+        Some(day(2020, 5, 1))
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
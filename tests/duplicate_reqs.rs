@@ -0,0 +1,188 @@
+// Several dependency rows for one downstream version (target-specific dependencies) are
+// merged into a single history entry unless --duplicate-dep-reqs per-req asks otherwise.
+
+mod common;
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use common::{Workdir, read_columns};
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::database::{DownstreamVersionInfo, RowSource};
+use time_to_fix_cve::pipeline::{
+    DuplicateReqs, FixMatchPolicy, StrictLagOptions, T0Kind, compute_strict_lags_for_target,
+    downstream_histories,
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+fn dep(version: &str, created_at: DateTime<Utc>, req: &str) -> DownstreamVersionInfo {
+    DownstreamVersionInfo {
+        crate_name: "app".to_string(),
+        version: version.to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    }
+}
+
+// vulnlib 0.2.0 (2020-03-01) fixes 0.1.x. app 1.1.0 lists vulnlib twice: ^0.2 for most
+// targets and ^0.1 for one platform. The fixed row comes first, as a query may return it.
+fn history() -> Vec<DownstreamVersionInfo> {
+    vec![
+        dep("1.0.0", day(1, 10), "^0.1"),
+        dep("1.1.0", day(3, 10), "^0.2"),
+        dep("1.1.0", day(3, 10), "^0.1"),
+        dep("1.2.0", day(4, 10), "^0.2"),
+    ]
+}
+
+fn opts(duplicate_reqs: DuplicateReqs) -> StrictLagOptions {
+    StrictLagOptions {
+        t0_kind: T0Kind::FixRelease,
+        advisory_time: None,
+        all_adoptions: true,
+        history_cutoff: None,
+        policy: FixMatchPolicy::Either,
+        duplicate_reqs,
+    }
+}
+
+fn scan(duplicate_reqs: DuplicateReqs) -> (Vec<(String, usize)>, usize) {
+    let fix_times = BTreeMap::from([(Version::new(0, 2, 0), day(3, 1))]);
+    let vuln = [Version::new(0, 1, 0)];
+    let scan =
+        compute_strict_lags_for_target(&fix_times, &vuln, &history(), opts(duplicate_reqs), None);
+    let rows = scan
+        .rows
+        .iter()
+        .map(|r| (r.downstream_version.clone(), r.adoption_index))
+        .collect();
+    (rows, scan.merged_duplicates)
+}
+
+#[test]
+fn least_restrictive_req_wins() {
+    let (rows, merged) = scan(DuplicateReqs::LeastRestrictive);
+    // 1.1.0 still admits a vulnerable version on one target, so 1.2.0 adopts.
+    assert_eq!(rows, [("1.2.0".to_string(), 1)]);
+    assert_eq!(merged, 1);
+}
+
+#[test]
+fn per_req_keeps_each_distinct_req() {
+    let (rows, merged) = scan(DuplicateReqs::PerReq);
+    // Least restrictive first within a version: 1.1.0 goes ^0.1 -> ^0.2, which counts as
+    // the adoption; 1.2.0 is not a second one because nothing regressed in between.
+    assert_eq!(rows, [("1.1.0".to_string(), 1)]);
+    assert_eq!(merged, 0);
+}
+
+#[test]
+fn identical_rows_always_collapse() {
+    let mut rows = history();
+    rows.push(dep("1.2.0", day(4, 10), "^0.2"));
+    for mode in [DuplicateReqs::LeastRestrictive, DuplicateReqs::PerReq] {
+        let (histories, merged) = downstream_histories(&rows, mode);
+        let versions: Vec<(&str, &str)> = histories["app"]
+            .iter()
+            .map(|r| (r.version.as_str(), r.dep_req.as_str()))
+            .collect();
+        match mode {
+            DuplicateReqs::LeastRestrictive => {
+                assert_eq!(merged, 2);
+                assert_eq!(
+                    versions,
+                    [("1.0.0", "^0.1"), ("1.1.0", "^0.1"), ("1.2.0", "^0.2")]
+                );
+            }
+            DuplicateReqs::PerReq => {
+                assert_eq!(merged, 1);
+                assert_eq!(
+                    versions,
+                    [
+                        ("1.0.0", "^0.1"),
+                        ("1.1.0", "^0.1"),
+                        ("1.1.0", "^0.2"),
+                        ("1.2.0", "^0.2")
+                    ]
+                );
+            }
+        }
+    }
+}
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+// The same history as a dump, with the ^0.1 row of app 1.1.0 marked target-specific.
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-10 00:00:00\n\
+         21,2,1.1.0,2020-03-10 00:00:00\n\
+         22,2,1.2.0,2020-04-10 00:00:00\n",
+        "version_id,crate_id,req,kind,target\n\
+         20,1,^0.1,0,\n\
+         21,1,^0.2,0,\n\
+         21,1,^0.1,0,cfg(windows)\n\
+         22,1,^0.2,0,\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("dupreqs_{name}"))
+}
+
+fn batch(name: &str, extra: &[&str]) -> (Vec<String>, String, String) {
+    let dir = workdir(name);
+    let out = common::batch(&dir, extra);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let versions = read_columns(
+        &dir.join("rustsec_rqx2_strict_lags.csv"),
+        ["downstream_version"],
+    )
+    .into_iter()
+    .map(|[v]| v)
+    .collect();
+    let [merged] = read_columns(
+        &dir.join("rustsec_rqx2_strict_summary.csv"),
+        ["duplicate_dep_rows_merged"],
+    )
+    .remove(0);
+    std::fs::remove_dir_all(&dir).ok();
+    (versions, merged, String::from_utf8(out.stderr).unwrap())
+}
+
+#[test]
+fn batch_merges_and_reports_duplicates() {
+    let (versions, merged, stderr) = batch("merge", &[]);
+    assert_eq!(versions, ["1.2.0"]);
+    assert_eq!(merged, "1");
+    assert!(
+        stderr.contains("(--duplicate-dep-reqs least-restrictive): strict=1 propagation=0"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn target_specific_rows_can_be_excluded() {
+    let (versions, merged, stderr) = batch("exclude", &["--exclude-target-specific-deps"]);
+    assert_eq!(versions, ["1.1.0"]);
+    assert_eq!(merged, "0");
+    assert!(!stderr.contains("duplicate dependency rows"), "{stderr}");
+}
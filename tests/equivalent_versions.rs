@@ -0,0 +1,78 @@
+// Fixed versions published under several `+build` strings resolve to one crates.io row.
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::pipeline::resolve_equivalent_version_string;
+
+fn day(d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 5, d, 0, 0, 0).unwrap()
+}
+
+fn times(rows: &[(&str, u32)]) -> Vec<(String, DateTime<Utc>)> {
+    rows.iter().map(|(v, d)| (v.to_string(), day(*d))).collect()
+}
+
+// (chosen string, its publish time, exact, variants).
+fn resolve(rows: &[(&str, u32)], wanted: &str) -> Option<(String, DateTime<Utc>, bool, usize)> {
+    let eq = resolve_equivalent_version_string(&times(rows), &Version::parse(wanted).unwrap())?;
+    Some((eq.num, eq.created_at, eq.exact, eq.variants))
+}
+
+#[test]
+fn exact_string_wins_over_earlier_build_variants() {
+    let rows = [
+        ("1.2.3+build9", 2),
+        ("1.2.3", 10),
+        ("1.2.3+build5", 4),
+        ("1.2.4", 1),
+    ];
+    assert_eq!(
+        resolve(&rows, "1.2.3"),
+        Some(("1.2.3".to_string(), day(10), true, 3))
+    );
+    // Asking for a build variant finds that exact row too.
+    assert_eq!(
+        resolve(&rows, "1.2.3+build5"),
+        Some(("1.2.3+build5".to_string(), day(4), true, 3))
+    );
+}
+
+#[test]
+fn without_the_plain_version_the_earliest_variant_is_chosen() {
+    let rows = [("1.2.3+zeta", 7), ("1.2.3+build5", 3), ("1.2.3+build10", 5)];
+    let eq = resolve_equivalent_version_string(&times(&rows), &Version::new(1, 2, 3)).unwrap();
+    assert_eq!(eq.num, "1.2.3+build5");
+    assert_eq!(eq.created_at, day(3));
+    assert!(!eq.exact);
+    assert_eq!(eq.describe(), "1.2.3+build5 earliest of 3 variants");
+}
+
+#[test]
+fn same_day_variants_break_ties_by_semver_then_string() {
+    // Numeric build identifiers compare as numbers, so 9 sorts before 10 where a plain
+    // string comparison would put "10" first.
+    let rows = [("1.2.3+10", 3), ("1.2.3+9", 3), ("1.2.3+a", 3)];
+    let pick = |rows: &[(&str, u32)]| {
+        resolve_equivalent_version_string(&times(rows), &Version::new(1, 2, 3))
+            .unwrap()
+            .num
+    };
+    assert_eq!(pick(&rows), "1.2.3+9");
+    // The choice does not depend on the order rows arrive in.
+    let mut reversed = rows;
+    reversed.reverse();
+    assert_eq!(pick(&reversed), "1.2.3+9");
+}
+
+#[test]
+fn prereleases_and_missing_versions_do_not_match() {
+    let rows = [("1.2.3-rc.1", 1), ("1.2.3-rc.1+b", 2), ("not-a-version", 1)];
+    assert!(resolve_equivalent_version_string(&times(&rows), &Version::new(1, 2, 3)).is_none());
+    let eq =
+        resolve_equivalent_version_string(&times(&rows), &Version::parse("1.2.3-rc.1").unwrap())
+            .unwrap();
+    assert_eq!(
+        (eq.num.as_str(), eq.exact, eq.variants),
+        ("1.2.3-rc.1", true, 2)
+    );
+}
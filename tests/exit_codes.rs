@@ -0,0 +1,193 @@
+// The exit-status and stdout contract of rqx2_rustsec_batch, run against a csv dump and a
+// local advisory-db archive so nothing touches the network or Postgres.
+
+mod common;
+
+use std::{path::PathBuf, process::Output};
+
+use common::{Workdir, batch};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+aliases = ["CVE-0000-0001"]
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+const CRATES: &str = "id,name,downloads
+1,vulnlib,5000
+2,app_a,300
+3,app_b,200
+";
+
+const VERSIONS: &str = "id,crate_id,num,created_at
+10,1,0.1.0,2020-01-01 00:00:00
+11,1,0.1.1,2020-02-01 00:00:00
+12,1,0.2.0,2020-03-01 00:00:00
+20,2,1.0.0,2020-01-15 00:00:00
+21,2,1.1.0,2020-03-11 00:00:00
+30,3,0.1.0,2020-01-20 00:00:00
+31,3,0.2.0,2020-04-01 00:00:00
+";
+
+const DEPENDENCIES: &str = "version_id,crate_id,req,kind
+20,1,^0.1,0
+21,1,^0.2,0
+30,1,=0.1.1,0
+31,1,^0.2.0,0
+";
+
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(CRATES, VERSIONS, DEPENDENCIES)
+        .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+        .create(&format!("exit_{name}"))
+}
+
+// The single JSON line on stdout.
+fn outcome(out: &Output) -> serde_json::Value {
+    let stdout = String::from_utf8(out.stdout.clone()).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "stdout: {stdout}");
+    serde_json::from_str(lines[0]).unwrap()
+}
+
+#[test]
+fn no_op_run_exits_zero_with_a_json_line() {
+    let dir = workdir("noop");
+    let out = batch(&dir, &["--max-advisories", "0"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let v = outcome(&out);
+    assert_eq!(v["processed"], 0);
+    assert_eq!(v["written_rows"], 0);
+    assert_eq!(v["errors"], 0);
+    let outputs: Vec<&str> = v["outputs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p.as_str().unwrap())
+        .collect();
+    assert!(outputs.contains(&"rustsec_rqx2_strict_lags.csv"));
+    assert!(outputs.iter().all(|p| dir.join(p).exists()), "{outputs:?}");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn full_run_reports_its_counters() {
+    let dir = workdir("full");
+    let out = batch(&dir, &[]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let v = outcome(&out);
+    assert_eq!(v["processed"], 1);
+    // app_a moves to ^0.2 and app_b to ^0.2.0 after the 0.2.0 fix.
+    assert_eq!(v["written_rows"], 2);
+    assert_eq!(v["skipped"], 0);
+    assert_eq!(v["errors"], 0);
+    assert!(v.get("truncated").is_none(), "{v}");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn time_boxed_run_finalizes_partial_outputs_and_exits_four() {
+    let dir = workdir("timebox");
+    let out = batch(
+        &dir,
+        &[
+            "--max-runtime-minutes",
+            "0",
+            "--metadata-output",
+            "meta.json",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(4), "{out:?}");
+    let v = outcome(&out);
+    assert_eq!(v["processed"], 0);
+    assert_eq!(v["truncated"], true);
+    // The writers were flushed: every output exists with its header.
+    let lags = std::fs::read_to_string(dir.join("rustsec_rqx2_strict_lags.csv")).unwrap();
+    assert!(lags.starts_with("rustsec_id,"), "{lags}");
+    let summary = std::fs::read_to_string(dir.join("rustsec_rqx2_strict_summary.csv")).unwrap();
+    assert_eq!(summary.lines().count(), 1, "{summary}");
+
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "run truncated by --max-runtime-minutes 0: last completed rustsec_id=-, 1 advisories not started (next: RUSTSEC-0000-0001)"
+        ),
+        "{stderr}"
+    );
+    let meta: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("meta.json")).unwrap()).unwrap();
+    assert_eq!(meta["truncated"]["remaining"][0], "RUSTSEC-0000-0001");
+    assert!(meta["truncated"]["last_completed"].is_null());
+
+    // A generous limit leaves the run untouched.
+    let out = batch(&dir, &["--max-runtime-minutes", "60"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    assert_eq!(outcome(&out)["processed"], 1);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn configuration_errors_exit_three_before_any_work() {
+    let dir = workdir("config");
+    for extra in [
+        &["--collapse-prefix-regex", "no-capture-group"][..],
+        &["--collapse-prefix-regex", "("][..],
+        &["--max-runtime-minutes", "NaN"][..],
+        &["--no-such-flag"][..],
+    ] {
+        let out = batch(&dir, extra);
+        assert_eq!(out.status.code(), Some(3), "{extra:?}: {out:?}");
+        assert!(out.stdout.is_empty(), "{extra:?}");
+    }
+    // Nothing was written.
+    assert!(!dir.join("rustsec_rqx2_strict_lags.csv").exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+// Inputs, the database and the output paths are all opened before the first advisory, so
+// failing on them is a configuration error too.
+#[test]
+fn setup_failures_exit_three() {
+    let dir = workdir("inputs");
+    std::fs::remove_file(dir.join("advisory-db.zip")).unwrap();
+    let out = batch(&dir, &[]);
+    assert_eq!(out.status.code(), Some(3), "{out:?}");
+    assert!(out.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("cannot read advisory-db.zip"), "{stderr}");
+    std::fs::remove_dir_all(&dir).ok();
+
+    let dir = workdir("dump_dir");
+    std::fs::rename(dir.join("dump"), dir.join("moved")).unwrap();
+    let out = batch(&dir, &[]);
+    assert_eq!(out.status.code(), Some(3), "{out:?}");
+    assert!(out.stdout.is_empty());
+    assert!(!dir.join("rustsec_rqx2_strict_lags.csv").exists());
+    std::fs::remove_dir_all(&dir).ok();
+
+    let dir = workdir("output_dir");
+    std::fs::write(dir.join("blocker"), "").unwrap();
+    let out = batch(&dir, &["--output", "blocker/lags.csv"]);
+    assert_eq!(out.status.code(), Some(3), "{out:?}");
+    assert!(out.stdout.is_empty());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn failures_after_the_start_exit_one() {
+    let dir = workdir("failure");
+    // The chart directory is only created once the advisories are done.
+    std::fs::write(dir.join("charts"), "").unwrap();
+    let out = batch(&dir, &["--strict-output-dir", "charts"]);
+    assert_eq!(out.status.code(), Some(1), "{out:?}");
+    assert!(out.stdout.is_empty());
+    std::fs::remove_dir_all(&dir).ok();
+}
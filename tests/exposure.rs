@@ -0,0 +1,84 @@
+// The first vulnerable release behind the `exposure_days` summary column, and the
+// downstream exposure classes of the strict scan.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::database::{DownstreamVersionInfo, RowSource};
+use time_to_fix_cve::pipeline::{
+    DuplicateReqs, ExposureCounts, FixMatchPolicy, StrictLagOptions, T0Kind,
+    compute_strict_lags_for_target, first_vulnerable_release,
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn earliest_vulnerable_publication_wins() {
+    let times = vec![
+        ("0.1.0".to_string(), day(1, 1)),
+        ("0.2.0+build.1".to_string(), day(2, 1)),
+        ("0.1.1".to_string(), day(3, 1)),
+        ("0.3.0".to_string(), day(4, 1)),
+        ("not-a-version".to_string(), day(1, 1)),
+    ];
+    let vuln = |vs: &[&str]| -> Vec<Version> { vs.iter().map(|v| v.parse().unwrap()).collect() };
+
+    // 0.2.0 matches its build-metadata row, which shipped before the 0.1.1 backport.
+    assert_eq!(
+        first_vulnerable_release(&times, &vuln(&["0.1.1", "0.2.0"])),
+        Some(day(2, 1))
+    );
+    assert_eq!(
+        first_vulnerable_release(&times, &vuln(&["0.1.0", "0.1.1"])),
+        Some(day(1, 1))
+    );
+    assert_eq!(first_vulnerable_release(&times, &vuln(&["9.9.9"])), None);
+}
+
+fn dep(crate_name: &str, created_at: DateTime<Utc>, req: &str) -> DownstreamVersionInfo {
+    DownstreamVersionInfo {
+        crate_name: crate_name.to_string(),
+        version: "1.0.0".to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    }
+}
+
+// vulnlib 0.1.0..=0.1.4 is vulnerable; 0.1.5 fixes it on 2020-03-01.
+#[test]
+fn crates_first_seen_after_the_fix_are_counted_apart() {
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(3, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream = vec![
+        dep("old_exposed", day(1, 10), "^0.1"),
+        dep("old_never", day(1, 10), "^0.2"),
+        // First release on the day of the fix, already on a fixed req.
+        dep("new_on_fix_day", day(3, 1), "^0.1.5"),
+        dep("new_fixed", day(5, 1), ">=0.1.5, <0.2"),
+        // Released after the fix but still admitting a vulnerable version.
+        dep("new_vulnerable", day(5, 1), "^0.1"),
+        dep("new_unparseable", day(5, 1), "not a req"),
+    ];
+    let opts = StrictLagOptions {
+        t0_kind: T0Kind::FixRelease,
+        advisory_time: None,
+        all_adoptions: false,
+        history_cutoff: None,
+        policy: FixMatchPolicy::Either,
+        duplicate_reqs: DuplicateReqs::LeastRestrictive,
+    };
+    let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts, None);
+    assert_eq!(
+        scan.exposure,
+        ExposureCounts {
+            exposed: 1,
+            never_exposed: 1,
+            unparseable: 0,
+            post_fix_new: 2,
+        }
+    );
+}
@@ -0,0 +1,152 @@
+// External merge sort of CSV files (`--sort-events`), checked on small hand-written files
+// and on random ones with tiny chunks so that most runs spill and merge.
+
+use std::{env, io::Read, path::PathBuf};
+
+use anyhow::Result;
+use csv::StringRecord;
+use time_to_fix_cve::extsort::{SortSummary, column_index, sort_csv_file};
+use time_to_fix_cve::output::open_reader;
+use time_to_fix_cve::sampling::SplitMix64;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rq2_extsort_{name}_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Sorts by (id, hop as a number); `tag` is left alone so ties show their order.
+fn id_hop_key(
+    headers: &StringRecord,
+) -> Result<impl Fn(&StringRecord) -> Result<(String, i64)> + use<>> {
+    let id = column_index(headers, "id")?;
+    let hop = column_index(headers, "hop")?;
+    Ok(move |r: &StringRecord| Ok((r[id].to_string(), r[hop].parse()?)))
+}
+
+const INPUT: &str = "\
+# schema: test.v1
+id,hop,tag
+B,2,first
+A,10,\"quoted, with comma\"
+B,1,x
+A,2,y
+B,2,second
+A,2,z
+";
+
+const SORTED: &str = "\
+# schema: test.v1
+id,hop,tag
+A,2,y
+A,2,z
+A,10,\"quoted, with comma\"
+B,1,x
+B,2,first
+B,2,second
+";
+
+#[test]
+fn sorts_in_memory_and_through_spilled_runs() {
+    let dir = scratch("small");
+    for (chunk_rows, runs) in [(100, 0), (2, 3), (1, 6)] {
+        let path = dir.join("events.csv");
+        std::fs::write(&path, INPUT).unwrap();
+        let summary = sort_csv_file(&path, chunk_rows, id_hop_key).unwrap();
+        assert_eq!(summary, SortSummary { rows: 6, runs });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), SORTED);
+        // Only the sorted file is left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn keeps_compression_and_handles_header_only_files() {
+    let dir = scratch("gz");
+    let path = dir.join("events.csv.gz");
+    let mut gz = flate2::write::GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        flate2::Compression::default(),
+    );
+    std::io::Write::write_all(&mut gz, INPUT.as_bytes()).unwrap();
+    gz.finish().unwrap();
+    sort_csv_file(&path, 2, id_hop_key).unwrap();
+    let mut text = String::new();
+    open_reader(&path)
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, SORTED);
+
+    let empty = dir.join("empty.csv");
+    std::fs::write(&empty, "id,hop,tag\n").unwrap();
+    let summary = sort_csv_file(&empty, 2, id_hop_key).unwrap();
+    assert_eq!(summary, SortSummary { rows: 0, runs: 0 });
+    assert_eq!(std::fs::read_to_string(&empty).unwrap(), "id,hop,tag\n");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn bad_keys_leave_the_file_untouched() {
+    let dir = scratch("bad");
+    let path = dir.join("events.csv");
+    let input = "id,hop,tag\nB,1,x\nA,not-a-number,y\n";
+    std::fs::write(&path, input).unwrap();
+    assert!(sort_csv_file(&path, 1, id_hop_key).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), input);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    let err = sort_csv_file(&path, 1, |h: &StringRecord| {
+        let lag = column_index(h, "lag_days")?;
+        Ok(move |r: &StringRecord| Ok(r[lag].to_string()))
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("lag_days"), "{err}");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+// Random files over a small key space (so ties are common), sorted with random chunk sizes:
+// the output is the stable sort of the input, hence also a permutation of it.
+#[test]
+fn output_is_a_stable_permutation_of_the_input() {
+    let dir = scratch("prop");
+    let path = dir.join("events.csv");
+    let mut rng = SplitMix64::new(7);
+    for case in 0..200 {
+        let rows = rng.below(40) as usize;
+        let mut records: Vec<[String; 3]> = (0..rows)
+            .map(|i| {
+                [
+                    format!("RUSTSEC-{}", rng.below(4)),
+                    rng.below(5).to_string(),
+                    format!("row{i}"),
+                ]
+            })
+            .collect();
+        let mut w = csv::Writer::from_path(&path).unwrap();
+        w.write_record(["id", "hop", "tag"]).unwrap();
+        for r in &records {
+            w.write_record(r).unwrap();
+        }
+        w.flush().unwrap();
+        drop(w);
+
+        let chunk_rows = 1 + rng.below(8) as usize;
+        let summary = sort_csv_file(&path, chunk_rows, id_hop_key).unwrap();
+        assert_eq!(summary.rows, rows, "case {case}");
+
+        let got: Vec<[String; 3]> = csv::Reader::from_path(&path)
+            .unwrap()
+            .records()
+            .map(|r| {
+                let r = r.unwrap();
+                [r[0].to_string(), r[1].to_string(), r[2].to_string()]
+            })
+            .collect();
+        records.sort_by_key(|r| (r[0].clone(), r[1].parse::<i64>().unwrap()));
+        assert_eq!(got, records, "case {case}, chunk_rows {chunk_rows}");
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,174 @@
+// Propagation lag measured from the downstream crate's first release after the upstream
+// fix, and the number of releases it shipped before adopting.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{Workdir, batch_command, read_columns};
+
+use chrono::{DateTime, TimeZone, Utc};
+use time_to_fix_cve::pipeline::{FirstOpportunity, first_opportunity};
+
+fn day(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn counts_releases_between_fix_and_adoption() {
+    let fix = day(2020, 3, 1);
+    let times = [
+        day(2020, 1, 1),
+        day(2020, 3, 5),
+        // Same instant as the previous release: counts once.
+        day(2020, 3, 5),
+        day(2020, 4, 1),
+        day(2020, 5, 1),
+    ];
+    let got = first_opportunity(&times, fix, day(2020, 5, 1)).unwrap();
+    assert_eq!(
+        got,
+        FirstOpportunity {
+            time: day(2020, 3, 5),
+            skipped_releases: 2
+        }
+    );
+    assert_eq!(got.lag_secs(day(2020, 5, 1)), 57 * 86_400);
+    // Adopting in the first release after the fix: nothing skipped, no wait.
+    let got = first_opportunity(&times, fix, day(2020, 3, 5)).unwrap();
+    assert_eq!(got.skipped_releases, 0);
+    assert_eq!(got.lag_secs(day(2020, 3, 5)), 0);
+    assert_eq!(first_opportunity(&times, fix, day(2020, 2, 1)), None);
+}
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+// vulnlib 0.2.0 (2020-03-01) is the fix. app_a releases once more on the vulnerable req
+// before adopting on 2020-03-11; app_b picks up app_a's fix a year after its own first
+// post-fix release.
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n3,app_b,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.0.1,2020-03-05 00:00:00\n\
+         22,2,1.1.0,2020-03-11 00:00:00\n\
+         30,3,1.0.0,2020-01-20 00:00:00\n\
+         31,3,1.0.2,2020-06-01 00:00:00\n\
+         32,3,1.1.0,2021-06-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n\
+         20,1,^0.1,0\n\
+         21,1,^0.1,0\n\
+         22,1,^0.2,0\n\
+         30,2,^1.0,0\n\
+         31,2,^1.0,0\n\
+         32,2,^1.1,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("opportunity_{name}"))
+}
+
+fn run(name: &str, extra: &[&str]) -> (Vec<[String; 5]>, String) {
+    let dir = workdir(name);
+    let out = batch_command(&dir)
+        .args([
+            "--propagation",
+            "--propagation-max-hops",
+            "2",
+            "--propagation-events-output",
+            "events.csv",
+        ])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let events = read_columns(
+        &dir.join("events.csv"),
+        [
+            "hop",
+            "downstream_crate",
+            "lag_days",
+            "first_opportunity_lag_days",
+            "skipped_releases",
+        ],
+    );
+    let summary =
+        std::fs::read_to_string(dir.join("rustsec_rqx2_propagation_summary.txt")).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    (events, summary)
+}
+
+fn section<'a>(summary: &'a str, title: &str) -> &'a str {
+    let start = summary
+        .find(&format!("{title}\n"))
+        .unwrap_or_else(|| panic!("{title}: {summary}"));
+    let rest = &summary[start..];
+    &rest[..rest.find("\n\n").unwrap_or(rest.len())]
+}
+
+#[test]
+fn events_carry_both_metrics() {
+    let (events, summary) = run("fix_time", &[]);
+    let expected = [
+        ["1", "app_a", "10", "6.0000", "1"],
+        ["2", "app_b", "447", "365.0000", "1"],
+    ]
+    .map(|r| r.map(str::to_string));
+    assert_eq!(events, expected);
+    assert!(!summary.contains("lag_basis ="), "{summary}");
+    // The hop sections stay on the fix-time basis.
+    assert!(
+        section(&summary, "hop 2").contains("p50   = 447.0000 days"),
+        "{summary}"
+    );
+    let s = section(&summary, "since first post-fix release, hop 2");
+    assert!(s.contains("count = 1"), "{s}");
+    assert!(
+        s.contains("lag p25/p50/p75/p95 = 365.0000 / 365.0000"),
+        "{s}"
+    );
+    assert!(
+        s.contains("skipped releases p25/p50/p75/p95 = 1.0000 / 1.0000 / 1.0000 / 1.0000"),
+        "{s}"
+    );
+}
+
+#[test]
+fn first_opportunity_basis_moves_the_hop_lags() {
+    let (events, summary) = run(
+        "opportunity",
+        &["--propagation-lag-basis", "first-opportunity"],
+    );
+    // Event rows keep both measurements whatever the basis.
+    assert_eq!(events[1][2..], ["447", "365.0000", "1"]);
+    assert!(
+        summary.contains("lag_basis = first-opportunity"),
+        "{summary}"
+    );
+    assert!(
+        section(&summary, "hop 1").contains("p50   = 6.0000 days"),
+        "{summary}"
+    );
+    assert!(
+        section(&summary, "hop 2").contains("p50   = 365.0000 days"),
+        "{summary}"
+    );
+    // Cumulative lags still add up from the root fix.
+    assert!(
+        section(&summary, "cumulative since root fix, hop 2").contains("p50   = 457.0000 days"),
+        "{summary}"
+    );
+}
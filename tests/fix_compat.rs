@@ -0,0 +1,55 @@
+// `fix_semver_compatible`: can `cargo update` alone reach a fix from a vulnerable release?
+
+use semver::Version;
+use time_to_fix_cve::analysis::fix_semver_compatible;
+
+fn versions(vs: &[&str]) -> Vec<Version> {
+    vs.iter().map(|v| v.parse().unwrap()).collect()
+}
+
+fn compatible(vuln: &[&str], fixed: &[&str]) -> bool {
+    fix_semver_compatible(&versions(vuln), &versions(fixed))
+}
+
+#[test]
+fn same_major_is_compatible_from_1_0() {
+    assert!(compatible(&["1.2.0", "1.2.1"], &["1.2.2"]));
+    assert!(compatible(&["1.0.0"], &["1.9.0"]));
+    assert!(!compatible(&["1.4.0"], &["2.0.0"]));
+    // One compatible pair is enough.
+    assert!(compatible(&["1.4.0", "2.0.0"], &["2.0.1"]));
+}
+
+#[test]
+fn zero_x_lines_are_split_by_minor() {
+    assert!(compatible(&["0.3.1"], &["0.3.4"]));
+    assert!(!compatible(&["0.3.9"], &["0.4.0"]));
+    assert!(compatible(&["0.3.9", "0.4.0"], &["0.4.1"]));
+    // 0.x and 1.x are different lines even at the boundary.
+    assert!(!compatible(&["0.9.9"], &["1.0.0"]));
+}
+
+#[test]
+fn zero_zero_x_releases_are_never_compatible() {
+    assert!(!compatible(&["0.0.1"], &["0.0.2"]));
+    assert!(!compatible(&["0.0.3"], &["0.1.0"]));
+    // `^0.0.3-alpha.1` does admit 0.0.3: the line is the patch, as in `semver_compat_line`.
+    assert!(compatible(&["0.0.3-alpha.1"], &["0.0.3"]));
+}
+
+#[test]
+fn fixes_must_be_newer_and_stable() {
+    // A backport below a vulnerable release is not an update target.
+    assert!(!compatible(&["1.5.0"], &["1.4.3"]));
+    assert!(!compatible(&["1.2.0"], &["1.2.0"]));
+    // Caret requirements on stable releases do not select prereleases.
+    assert!(!compatible(&["0.21.0-alpha.0"], &["0.21.0-alpha.1"]));
+    assert!(!compatible(&["1.2.0"], &["1.3.0-rc.1"]));
+    assert!(compatible(&["1.3.0-rc.1"], &["1.3.0"]));
+}
+
+#[test]
+fn empty_sets_are_incompatible() {
+    assert!(!compatible(&[], &["1.0.1"]));
+    assert!(!compatible(&["1.0.0"], &[]));
+}
@@ -0,0 +1,85 @@
+// --fix-selection: which of an advisory's fixed versions are matched against.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::analysis::{FixSelection, select_fix_versions};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, m, d, 0, 0, 0).unwrap()
+}
+
+// 0.7.4 and 0.8.2 are separate 0.x lines; 1.0.1 and 1.3.0 share the 1.x line, as do
+// 2.0.0 and 2.1.5. Within 0.7, 0.8 and 1.x the higher version was published first.
+fn fixes() -> BTreeMap<Version, DateTime<Utc>> {
+    [
+        ("0.7.4", day(3, 1)),
+        ("0.7.5", day(2, 1)),
+        ("0.8.2", day(3, 10)),
+        ("0.8.3", day(3, 5)),
+        ("1.0.1", day(3, 2)),
+        ("1.3.0", day(1, 20)),
+        ("2.0.0", day(4, 1)),
+        ("2.1.5", day(4, 1)),
+    ]
+    .into_iter()
+    .map(|(v, t)| (v.parse().unwrap(), t))
+    .collect()
+}
+
+fn select(mut fix_times: BTreeMap<Version, DateTime<Utc>>, selection: FixSelection) -> Vec<String> {
+    select_fix_versions(&mut fix_times, selection);
+    fix_times.keys().map(Version::to_string).collect()
+}
+
+#[test]
+fn all_keeps_every_fix() {
+    assert_eq!(select(fixes(), FixSelection::All).len(), 8);
+}
+
+#[test]
+fn earliest_per_major_keeps_one_fix_per_compatible_line() {
+    assert_eq!(
+        select(fixes(), FixSelection::EarliestPerMajor),
+        ["0.7.5", "0.8.3", "1.3.0", "2.0.0"]
+    );
+}
+
+#[test]
+fn zero_zero_x_releases_are_lines_of_their_own() {
+    let fix_times = [
+        ("0.0.3", day(2, 1)),
+        ("0.0.4", day(1, 1)),
+        ("0.1.0", day(3, 1)),
+        ("0.1.9", day(2, 15)),
+    ]
+    .into_iter()
+    .map(|(v, t)| (v.parse().unwrap(), t))
+    .collect();
+    assert_eq!(
+        select(fix_times, FixSelection::EarliestPerMajor),
+        ["0.0.3", "0.0.4", "0.1.9"]
+    );
+}
+
+#[test]
+fn earliest_overall_keeps_the_first_published_fix() {
+    assert_eq!(select(fixes(), FixSelection::EarliestOverall), ["1.3.0"]);
+
+    // Ties on publish time go to the lower version.
+    let mut tied = fixes();
+    tied.retain(|v, _| v.major == 2);
+    assert_eq!(select(tied, FixSelection::EarliestOverall), ["2.0.0"]);
+}
+
+#[test]
+fn empty_maps_stay_empty() {
+    for selection in [
+        FixSelection::All,
+        FixSelection::EarliestPerMajor,
+        FixSelection::EarliestOverall,
+    ] {
+        assert!(select(BTreeMap::new(), selection).is_empty());
+    }
+}
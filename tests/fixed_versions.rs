@@ -0,0 +1,51 @@
+// Fixed versions taken from `patched` entries that are ranges rather than versions.
+
+use semver::{Version, VersionReq};
+use time_to_fix_cve::advisory::{
+    PrereleasePolicy, extract_all_fixed_versions, extract_versions_from_req,
+};
+
+fn lower_bounds(req: &str) -> Vec<String> {
+    extract_versions_from_req(&VersionReq::parse(req).unwrap())
+        .iter()
+        .map(Version::to_string)
+        .collect()
+}
+
+#[test]
+fn greater_than_starts_after_the_named_line() {
+    for (req, expected) in [
+        (">1.4", "1.5.0"),
+        (">1", "2.0.0"),
+        (">0.3", "0.4.0"),
+        (">0", "1.0.0"),
+        (">1.4.2", "1.4.3"),
+        (">1.0.0-rc.1", "1.0.0-rc.1"),
+        (">=1.4", "1.4.0"),
+        (">=1", "1.0.0"),
+        ("^0.8.7", "0.8.7"),
+        ("~1.2", "1.2.0"),
+        ("2.*", "2.0.0"),
+        ("=1.4.2", "1.4.2"),
+    ] {
+        assert_eq!(lower_bounds(req), [expected], "{req}");
+    }
+    assert!(lower_bounds("<1.4").is_empty());
+    assert_eq!(lower_bounds(">1.4, <2"), ["1.5.0"]);
+}
+
+#[test]
+fn greater_than_patched_entries_yield_the_next_release() {
+    let fixed = extract_all_fixed_versions(
+        &[">1.4".to_string(), ">0".to_string(), "0.2.5".to_string()],
+        PrereleasePolicy::Include,
+    );
+    assert_eq!(
+        fixed,
+        [
+            Version::new(0, 2, 5),
+            Version::new(1, 0, 0),
+            Version::new(1, 5, 0)
+        ]
+    );
+}
@@ -0,0 +1,56 @@
+-- One advisory scenario: `vulnlib` < 0.2.0 is vulnerable and 0.2.0 (2020-03-01) fixes it.
+--   app_a  affected by ^0.1, moves to ^0.2 ten days after the fix; 1.0.0 and 1.0.1 share
+--          a timestamp so the version-number tie-break is visible.
+--   app_b  pins =0.1.1, moves to ^0.2.0 31 days after the fix; a later version only has
+--          a dev-dependency (kind = 2) on vulnlib.
+--   app_c  only ever dev-depends on vulnlib.
+--   app_d  starts on ^0.2 and is never affected.
+-- `otherlib` and its dependent are noise that must not show up for vulnlib.
+-- Ownership: app_a and app_b share the `org:core` team (app_a also has a user owner),
+-- app_c has a user owner and app_d has none.
+INSERT INTO crates (id, name, downloads) VALUES
+    (1, 'vulnlib', 5000),
+    (2, 'app_a', 300),
+    (3, 'app_b', 200),
+    (4, 'app_c', 100),
+    (5, 'app_d', 50),
+    (6, 'otherlib', 10);
+
+INSERT INTO versions (id, crate_id, num, created_at) VALUES
+    (10, 1, '0.1.0', '2020-01-01 00:00:00+00'),
+    (11, 1, '0.1.1', '2020-02-01 00:00:00+00'),
+    (12, 1, '0.2.0', '2020-03-01 00:00:00+00'),
+    (13, 1, '0.2.1', '2020-05-01 00:00:00+00'),
+    (20, 2, '1.0.1', '2020-01-15 00:00:00+00'),
+    (21, 2, '1.0.0', '2020-01-15 00:00:00+00'),
+    (22, 2, '1.1.0', '2020-03-11 00:00:00+00'),
+    (30, 3, '0.1.0', '2020-01-20 00:00:00+00'),
+    (31, 3, '0.2.0', '2020-04-01 00:00:00+00'),
+    (32, 3, '0.3.0', '2020-05-01 00:00:00+00'),
+    (40, 4, '0.1.0', '2020-01-25 00:00:00+00'),
+    (50, 5, '1.0.0', '2020-04-10 00:00:00+00'),
+    (60, 6, '1.0.0', '2020-01-01 00:00:00+00');
+
+INSERT INTO dependencies (id, version_id, crate_id, req, kind) VALUES
+    (100, 20, 1, '^0.1', 0),
+    (101, 21, 1, '^0.1', 0),
+    (102, 22, 1, '^0.2', 0),
+    (103, 30, 1, '=0.1.1', 0),
+    (104, 31, 1, '^0.2.0', 0),
+    (105, 32, 1, '^0.1', 2),
+    (106, 40, 1, '^0.1', 2),
+    (107, 50, 1, '^0.2', 0),
+    (108, 22, 6, '^1', 0);
+
+INSERT INTO users (id, gh_login) VALUES
+    (1, 'alice'),
+    (2, 'bob');
+
+INSERT INTO teams (id, login) VALUES
+    (1, 'github:org:core');
+
+INSERT INTO crate_owners (crate_id, owner_id, owner_kind) VALUES
+    (2, 1, 0),
+    (2, 1, 1),
+    (3, 1, 1),
+    (4, 2, 0);
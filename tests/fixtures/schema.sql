@@ -0,0 +1,42 @@
+-- The subset of the crates.io schema the queries in src/database.rs read.
+CREATE TABLE IF NOT EXISTS crates (
+    id INTEGER PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    downloads INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS versions (
+    id INTEGER PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    num VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    yanked BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (crate_id, num)
+);
+
+CREATE TABLE IF NOT EXISTS dependencies (
+    id INTEGER PRIMARY KEY,
+    version_id INTEGER NOT NULL REFERENCES versions (id),
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    req VARCHAR NOT NULL,
+    kind INTEGER NOT NULL DEFAULT 0,
+    target VARCHAR
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY,
+    gh_login VARCHAR NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    id INTEGER PRIMARY KEY,
+    login VARCHAR NOT NULL
+);
+
+-- owner_kind 0 points owner_id at users, 1 at teams.
+CREATE TABLE IF NOT EXISTS crate_owners (
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    owner_id INTEGER NOT NULL,
+    owner_kind INTEGER NOT NULL,
+    PRIMARY KEY (crate_id, owner_id, owner_kind)
+);
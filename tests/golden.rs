@@ -0,0 +1,111 @@
+// End-to-end regression test: the batch binary runs over the pinned CSV dump and advisories
+// in tests/fixtures/golden, and its lag, summary, constraint, propagation and category CSVs
+// must match the checked-in copies byte for byte. After an intended change in the numbers,
+// regenerate them with
+//   UPDATE_GOLDEN=1 cargo test --test golden
+// and review the diff. A new behavioral flag gets its own entry in CASES.
+
+mod common;
+
+use std::{env, path::PathBuf};
+
+use common::{Workdir, batch_command, golden_dir};
+
+// (case directory under tests/fixtures/golden, extra arguments).
+const CASES: &[(&str, &[&str])] = &[
+    ("default", &[]),
+    (
+        "both_all_adoptions",
+        &["--lag-mode", "both", "--all-adoptions"],
+    ),
+    (
+        "sorted_events",
+        &["--sort-events", "--sort-events-chunk-rows", "2"],
+    ),
+    ("exclude_yanked", &["--exclude-yanked-versions"]),
+];
+
+// (output written by the run, golden file name).
+const OUTPUTS: [(&str, &str); 5] = [
+    ("rustsec_rqx2_strict_lags.csv", "strict_lags.csv"),
+    ("rustsec_rqx2_strict_summary.csv", "strict_summary.csv"),
+    (
+        "rustsec_rqx2_constraint_breakdown.csv",
+        "constraint_breakdown.csv",
+    ),
+    ("propagation_events.csv", "propagation_events.csv"),
+    ("category_summary.csv", "category_summary.csv"),
+];
+
+fn run_case(name: &str, extra: &[&str]) -> PathBuf {
+    let dir = Workdir::golden().create(&format!("golden_{name}"));
+    let out = batch_command(&dir)
+        .args([
+            "--as-of",
+            "2022-12-31",
+            "--constraint",
+            "--propagation",
+            "--propagation-events-output",
+            "propagation_events.csv",
+            "--category-summary-output",
+            "category_summary.csv",
+        ])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0), "{name}: {out:?}");
+    dir
+}
+
+// The first differing line, so a failure points at the change instead of two whole files.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => {}
+            (e, a) => {
+                return format!(
+                    "line {line}\n  expected: {}\n  actual:   {}",
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                );
+            }
+        }
+    }
+    "line endings or trailing newline".to_string()
+}
+
+#[test]
+fn outputs_match_golden_files() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some_and(|v| v == "1");
+    let mut failures = Vec::new();
+    for (name, extra) in CASES {
+        let dir = run_case(name, extra);
+        let golden = golden_dir().join(name);
+        for (output, file) in OUTPUTS {
+            let actual = std::fs::read_to_string(dir.join(output)).unwrap();
+            let path = golden.join(file);
+            if update {
+                std::fs::create_dir_all(&golden).unwrap();
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("{}: {e} (run with UPDATE_GOLDEN=1)", path.display()));
+            if expected != actual {
+                failures.push(format!(
+                    "{name}/{file}: {}",
+                    first_difference(&expected, &actual)
+                ));
+            }
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    assert!(
+        failures.is_empty(),
+        "outputs differ from tests/fixtures/golden (UPDATE_GOLDEN=1 regenerates):\n{}",
+        failures.join("\n")
+    );
+}
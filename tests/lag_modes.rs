@@ -0,0 +1,356 @@
+// The strict and first-resolvable matchers, the t0 baselines, --all-adoptions and
+// --fix-match-policy over synthetic downstream histories.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::database::{DownstreamVersionInfo, RowSource};
+use time_to_fix_cve::pipeline::{
+    AdoptionEvidence, AdoptionKind, DuplicateReqs, ExposureCounts, FixMatchPolicy, LagMode,
+    StrictLagOptions, StrictLagRow, T0Kind, compute_first_resolvable_lags_for_target,
+    compute_strict_lags_for_target,
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+fn dep(
+    crate_name: &str,
+    version: &str,
+    created_at: DateTime<Utc>,
+    req: &str,
+) -> DownstreamVersionInfo {
+    DownstreamVersionInfo {
+        crate_name: crate_name.to_string(),
+        version: version.to_string(),
+        created_at,
+        dep_req: req.to_string(),
+        source: RowSource::Database,
+    }
+}
+
+// vulnlib 0.1.0..=0.1.4 is vulnerable and 0.1.5, published on 2020-03-01, fixes it.
+//   app_a  keeps ^0.1, which resolves to 0.1.5 once it is out: passive adoption only.
+//   app_b  raises its req to ^0.1.5.
+//   app_c  releases once more on ^0.1 before the fix, then pins =0.1.5.
+//   app_d  only ever requires ^0.2.
+//   app_e  has no parseable req.
+fn history() -> Vec<DownstreamVersionInfo> {
+    vec![
+        dep("app_a", "1.0.0", day(1, 10), "^0.1"),
+        dep("app_a", "1.0.1", day(3, 10), "^0.1"),
+        dep("app_a", "1.0.2", day(4, 10), "^0.1"),
+        dep("app_b", "0.1.0", day(1, 20), "^0.1"),
+        dep("app_b", "0.2.0", day(3, 20), "^0.1.5"),
+        dep("app_c", "0.1.0", day(1, 25), "^0.1"),
+        dep("app_c", "0.1.1", day(2, 15), "^0.1"),
+        dep("app_c", "0.2.0", day(4, 1), "=0.1.5"),
+        dep("app_d", "1.0.0", day(1, 5), "^0.2"),
+        dep("app_e", "0.1.0", day(1, 2), "not a req"),
+    ]
+}
+
+fn opts() -> StrictLagOptions {
+    StrictLagOptions {
+        t0_kind: T0Kind::FixRelease,
+        advisory_time: None,
+        all_adoptions: false,
+        history_cutoff: None,
+        policy: FixMatchPolicy::Either,
+        duplicate_reqs: DuplicateReqs::LeastRestrictive,
+    }
+}
+
+fn summary(rows: &[StrictLagRow]) -> Vec<(&str, &str, i64, AdoptionKind)> {
+    rows.iter()
+        .map(|r| {
+            (
+                r.downstream_crate.as_str(),
+                r.downstream_version.as_str(),
+                r.lag_secs / 86_400,
+                r.adoption_kind,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn strict_and_first_resolvable_on_the_same_history() {
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(3, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream = history();
+    let compatible = AdoptionKind::Compatible;
+
+    // ^0.1 already admits 0.1.5, so both strict adoptions count as compatible.
+    let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts(), None);
+    assert_eq!(
+        summary(&scan.rows),
+        [
+            ("app_b", "0.2.0", 19, compatible),
+            ("app_c", "0.2.0", 31, compatible),
+        ]
+    );
+    assert!(scan.rows.iter().all(|r| r.lag_mode == LagMode::Strict));
+    assert_eq!((scan.regressions, scan.affected), (0, 3));
+    assert_eq!(
+        scan.exposure,
+        ExposureCounts {
+            exposed: 3,
+            never_exposed: 1,
+            unparseable: 1,
+            post_fix_new: 0,
+        }
+    );
+
+    let (resolvable, resolvable_affected) =
+        compute_first_resolvable_lags_for_target(&fix_times, &vuln, &downstream, opts());
+    assert_eq!(
+        summary(&resolvable),
+        [
+            ("app_a", "1.0.1", 9, compatible),
+            ("app_b", "0.2.0", 19, compatible),
+            ("app_c", "0.2.0", 31, compatible),
+        ]
+    );
+    assert!(
+        resolvable
+            .iter()
+            .all(|r| r.lag_mode == LagMode::FirstResolvable && r.original_req == "^0.1")
+    );
+    assert_eq!(resolvable_affected, 3);
+}
+
+#[test]
+fn first_resolvable_waits_for_the_fix_to_be_published() {
+    // The fix is published after every app_a release, so nothing resolves to it.
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(6, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream: Vec<_> = history()
+        .into_iter()
+        .filter(|r| r.crate_name == "app_a")
+        .collect();
+
+    let (rows, affected) =
+        compute_first_resolvable_lags_for_target(&fix_times, &vuln, &downstream, opts());
+    assert!(rows.is_empty());
+    assert_eq!(affected, 1);
+}
+
+#[test]
+fn t0_modes_move_the_baseline() {
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(3, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream = history();
+    // (t0 kind, advisory date, expected rows as (crate, lag days, t0 kind)); the fix is
+    // out on 03-01 and app_b/app_c adopt it on 03-20 and 04-01.
+    let cases = [
+        (
+            T0Kind::FixRelease,
+            Some(day(2, 1)),
+            [
+                ("app_b", 19, T0Kind::FixRelease),
+                ("app_c", 31, T0Kind::FixRelease),
+            ],
+        ),
+        (
+            T0Kind::AdvisoryDate,
+            Some(day(2, 1)),
+            [
+                ("app_b", 48, T0Kind::AdvisoryDate),
+                ("app_c", 60, T0Kind::AdvisoryDate),
+            ],
+        ),
+        (
+            T0Kind::MaxOfBoth,
+            Some(day(2, 1)),
+            [
+                ("app_b", 19, T0Kind::MaxOfBoth),
+                ("app_c", 31, T0Kind::MaxOfBoth),
+            ],
+        ),
+        (
+            T0Kind::MaxOfBoth,
+            Some(day(3, 15)),
+            [
+                ("app_b", 5, T0Kind::MaxOfBoth),
+                ("app_c", 17, T0Kind::MaxOfBoth),
+            ],
+        ),
+        // app_b adopted the fix before disclosure; the row is kept with a negative lag.
+        (
+            T0Kind::AdvisoryDate,
+            Some(day(3, 25)),
+            [
+                ("app_b", -5, T0Kind::AdvisoryDate),
+                ("app_c", 7, T0Kind::AdvisoryDate),
+            ],
+        ),
+        // Without an advisory date every mode falls back to the fix release.
+        (
+            T0Kind::AdvisoryDate,
+            None,
+            [
+                ("app_b", 19, T0Kind::FixRelease),
+                ("app_c", 31, T0Kind::FixRelease),
+            ],
+        ),
+    ];
+    for (t0_kind, advisory_time, expected) in cases {
+        let opts = StrictLagOptions {
+            t0_kind,
+            advisory_time,
+            ..opts()
+        };
+        let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts, None);
+        let got: Vec<_> = scan
+            .rows
+            .iter()
+            .map(|r| {
+                assert!(!r.negative, "{r:?}");
+                assert_eq!(
+                    r.t0,
+                    r.downstream_time - chrono::Duration::seconds(r.lag_secs)
+                );
+                (r.downstream_crate.as_str(), r.lag_secs / 86_400, r.t0_kind)
+            })
+            .collect();
+        assert_eq!(got, expected, "{t0_kind:?} {advisory_time:?}");
+    }
+}
+
+// app_f fixes, regresses to ^0.1.0 twice and re-fixes each time; app_g regresses and
+// stays vulnerable.
+fn flip_flop() -> Vec<DownstreamVersionInfo> {
+    vec![
+        dep("app_f", "1.0.0", day(1, 10), "^0.1.0"),
+        dep("app_f", "1.1.0", day(3, 5), "^0.1.5"),
+        dep("app_f", "1.2.0", day(3, 20), "^0.1.0"),
+        dep("app_f", "1.2.1", day(3, 25), "^0.1.0"),
+        dep("app_f", "1.3.0", day(4, 5), "=0.1.5"),
+        dep("app_f", "1.4.0", day(4, 20), "~0.1.2"),
+        dep("app_f", "1.5.0", day(5, 1), "^0.1.5"),
+        dep("app_g", "0.1.0", day(1, 10), "^0.1.0"),
+        dep("app_g", "0.2.0", day(3, 10), "^0.1.5"),
+        dep("app_g", "0.3.0", day(4, 10), "^0.1.0"),
+    ]
+}
+
+#[test]
+fn all_adoptions_follow_fixes_and_regressions() {
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(3, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream = flip_flop();
+    let adoptions = |all_adoptions| {
+        let opts = StrictLagOptions {
+            all_adoptions,
+            ..opts()
+        };
+        let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts, None);
+        let rows: Vec<_> = scan
+            .rows
+            .iter()
+            .map(|r| {
+                (
+                    r.downstream_crate.clone(),
+                    r.downstream_version.clone(),
+                    r.adoption_index,
+                    r.original_req.clone(),
+                )
+            })
+            .collect();
+        (rows, scan.regressions)
+    };
+    let row = |c: &str, v: &str, i, req: &str| (c.to_string(), v.to_string(), i, req.to_string());
+
+    // Each re-fix takes the req of the release just before it as its original req.
+    let (rows, regressions) = adoptions(true);
+    assert_eq!(
+        rows,
+        [
+            row("app_f", "1.1.0", 1, "^0.1.0"),
+            row("app_f", "1.3.0", 2, "^0.1.0"),
+            row("app_f", "1.5.0", 3, "~0.1.2"),
+            row("app_g", "0.2.0", 1, "^0.1.0"),
+        ]
+    );
+    assert_eq!(regressions, 3);
+
+    // Without --all-adoptions only first adoptions are rows; regressions still count.
+    let (rows, regressions) = adoptions(false);
+    assert_eq!(
+        rows,
+        [
+            row("app_f", "1.1.0", 1, "^0.1.0"),
+            row("app_g", "0.2.0", 1, "^0.1.0"),
+        ]
+    );
+    assert_eq!(regressions, 3);
+}
+
+// Fix 0.1.5 again; each app leaves the vulnerable range with a different kind of evidence.
+//   app_h  ^0.1.5-rc.1 admits the fix below it (req only), later ^0.2 (min only).
+//   app_i  ^0.2 and ^0.2.1 never admit the fix (min only).
+//   app_j  ^0.1.5 admits it and starts at it (both).
+fn evidence_history() -> Vec<DownstreamVersionInfo> {
+    vec![
+        dep("app_h", "1.0.0", day(1, 10), "^0.1.0"),
+        dep("app_h", "1.1.0", day(3, 10), "^0.1.5-rc.1"),
+        dep("app_h", "1.2.0", day(3, 20), "^0.2"),
+        dep("app_i", "0.1.0", day(1, 10), "^0.1"),
+        dep("app_i", "0.2.0", day(3, 15), "^0.2"),
+        dep("app_i", "0.3.0", day(4, 1), "^0.2.1"),
+        dep("app_j", "0.1.0", day(1, 10), "^0.1.2"),
+        dep("app_j", "0.2.0", day(3, 5), "^0.1.5"),
+    ]
+}
+
+#[test]
+fn fix_match_policies_pick_different_rows() {
+    let fix_times = BTreeMap::from([(Version::new(0, 1, 5), day(3, 1))]);
+    let vuln: Vec<Version> = (0..5).map(|p| Version::new(0, 1, p)).collect();
+    let downstream = evidence_history();
+    use AdoptionEvidence::{EstimatedMin, MinBumped, ReqMatches};
+    for (policy, expected) in [
+        (
+            FixMatchPolicy::Either,
+            vec![
+                ("app_h", "1.1.0", ReqMatches),
+                ("app_i", "0.2.0", EstimatedMin),
+                ("app_j", "0.2.0", MinBumped),
+            ],
+        ),
+        (
+            FixMatchPolicy::StrictReq,
+            vec![
+                ("app_h", "1.1.0", ReqMatches),
+                ("app_j", "0.2.0", MinBumped),
+            ],
+        ),
+        (
+            FixMatchPolicy::MinBump,
+            vec![
+                ("app_h", "1.2.0", EstimatedMin),
+                ("app_i", "0.2.0", EstimatedMin),
+                ("app_j", "0.2.0", MinBumped),
+            ],
+        ),
+    ] {
+        let opts = StrictLagOptions { policy, ..opts() };
+        let scan = compute_strict_lags_for_target(&fix_times, &vuln, &downstream, opts, None);
+        let got: Vec<_> = scan
+            .rows
+            .iter()
+            .map(|r| {
+                (
+                    r.downstream_crate.as_str(),
+                    r.downstream_version.as_str(),
+                    r.evidence,
+                )
+            })
+            .collect();
+        assert_eq!(got, expected, "{policy:?}");
+        assert_eq!(scan.affected, 3, "{policy:?}");
+    }
+}
@@ -0,0 +1,64 @@
+// --lag-unit conversions around the day and hour boundaries. Whole units truncate toward
+// zero, so a lag a second short of a day is 0 days on either side of the fix.
+
+use time_to_fix_cve::pipeline::{LagUnit, fractional_days, whole_days, whole_hours};
+
+const DAY: i64 = 86_400;
+const HOUR: i64 = 3_600;
+
+#[test]
+fn whole_units_truncate_toward_zero() {
+    for (lag_secs, days, hours) in [
+        (0, 0, 0),
+        (DAY - 1, 0, 23),
+        (DAY, 1, 24),
+        (DAY + 1, 1, 24),
+        (23 * HOUR, 0, 23),
+        (HOUR - 1, 0, 0),
+        (-(DAY - 1), 0, -23),
+        (-DAY, -1, -24),
+        (-(DAY + 1), -1, -24),
+        (-(HOUR - 1), 0, 0),
+        (2 * DAY + 12 * HOUR, 2, 60),
+    ] {
+        assert_eq!(whole_days(lag_secs), days, "{lag_secs}s");
+        assert_eq!(whole_hours(lag_secs), hours, "{lag_secs}s");
+    }
+}
+
+#[test]
+fn fractional_days_keep_the_sign_and_remainder() {
+    for (lag_secs, frac) in [
+        (0, 0.0),
+        (DAY, 1.0),
+        (23 * HOUR, 23.0 / 24.0),
+        (-HOUR * 6, -0.25),
+        (DAY + 12 * HOUR, 1.5),
+    ] {
+        assert_eq!(fractional_days(lag_secs), frac, "{lag_secs}s");
+    }
+}
+
+#[test]
+fn units_convert_and_format() {
+    let lag = DAY + 12 * HOUR - 1;
+    for (unit, column, label, value, formatted) in [
+        (LagUnit::Days, "lag_days", "days", 1.0, "1"),
+        (LagUnit::Hours, "lag_hours", "hours", 35.0, "35"),
+        (
+            LagUnit::FractionalDays,
+            "lag_days_frac",
+            "days",
+            fractional_days(lag),
+            "1.5000",
+        ),
+    ] {
+        assert_eq!(unit.column(), column);
+        assert_eq!(unit.label(), label);
+        assert_eq!(unit.convert(lag), value, "{unit:?}");
+        assert_eq!(unit.format(unit.convert(lag)), formatted, "{unit:?}");
+    }
+    // 23h after the fix is 0 whole days but 23 hours.
+    assert_eq!(LagUnit::Days.convert(23 * HOUR), 0.0);
+    assert_eq!(LagUnit::Hours.convert(23 * HOUR), 23.0);
+}
@@ -0,0 +1,75 @@
+// `--log-level`: skip details reach the log file only at debug level, and stderr keeps
+// the concise line either way.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{Workdir, batch};
+
+// A withdrawn advisory, skipped before any lookup with a detail string.
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0002"
+package = "otherlib"
+date = "2020-03-05"
+withdrawn = "2020-04-01"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# otherlib is vulnerable
+"#;
+
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,otherlib,10\n",
+        "id,crate_id,num,created_at\n10,1,0.1.0,2020-01-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n",
+    )
+    .advisory("otherlib/RUSTSEC-0000-0002.md", ADVISORY)
+    .create(&format!("log_{name}"))
+}
+
+fn skip_lines(text: &str) -> Vec<&str> {
+    text.lines().filter(|l| l.starts_with("skip: ")).collect()
+}
+
+#[test]
+fn skip_details_only_reach_the_file_at_debug() {
+    let dir = workdir("levels");
+    let concise =
+        "skip: rustsec_id=RUSTSEC-0000-0002 cve_id=RUSTSEC-0000-0002 pkg=otherlib reason=withdrawn";
+    for (level, file_has_detail) in [("info", false), ("debug", true)] {
+        let log = format!("{level}.log");
+        let out = batch(&dir, &["--log-level", level, "--log-output", &log]);
+        assert_eq!(out.status.code(), Some(0), "{out:?}");
+
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert_eq!(skip_lines(&stderr), [concise], "{level}");
+
+        let file = std::fs::read_to_string(dir.join(&log)).unwrap();
+        let lines = skip_lines(&file);
+        assert_eq!(lines.len(), 1, "{level}: {file}");
+        assert!(lines[0].starts_with(concise), "{level}");
+        assert_eq!(
+            lines[0].contains(" detail=advisory withdrawn"),
+            file_has_detail,
+            "{level}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn debug_without_a_log_file_prints_details_to_stderr() {
+    let dir = workdir("stderr");
+    let out = batch(&dir, &["--log-level", "debug"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    let lines = skip_lines(&stderr);
+    assert_eq!(lines.len(), 1, "{stderr}");
+    assert!(lines[0].contains(" detail=advisory withdrawn"), "{stderr}");
+    std::fs::remove_dir_all(&dir).ok();
+}
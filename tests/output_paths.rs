@@ -0,0 +1,127 @@
+// Output paths are handled as paths, not strings: missing parent directories are created
+// at any depth, and names with spaces, non-ASCII or (on Unix) non-UTF-8 bytes work.
+
+mod common;
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Output,
+};
+
+use common::{Workdir, batch_command};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n",
+        "version_id,crate_id,req,kind\n20,1,^0.1,0\n21,1,^0.2,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("paths_{name}"))
+}
+
+fn batch(dir: &Path, extra: &[OsString]) -> Output {
+    batch_command(dir).args(extra).output().unwrap()
+}
+
+fn flag(name: &str, value: impl Into<OsString>) -> [OsString; 2] {
+    [name.into(), value.into()]
+}
+
+#[test]
+fn nested_directories_with_spaces_and_unicode_are_created() {
+    let dir = workdir("unicode");
+    let base = Path::new("out dir").join("résultats").join("深い");
+    let extra: Vec<OsString> = [
+        flag("--output", base.join("lags.csv.gz")),
+        flag("--summary-output", base.join("summary.csv")),
+        flag("--errors-output", base.join("errors").join("errors.csv")),
+        flag("--strict-output-dir", base.join("svgs ü")),
+        flag("--metadata-output", base.join("meta").join("run.json")),
+        flag("--html-report", base.join("report.html")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let out = batch(&dir, &extra);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    for rel in [
+        "lags.csv.gz",
+        "lags.csv.gz.schema.json",
+        "summary.csv",
+        "errors/errors.csv",
+        "svgs ü/lag_boxplot_by_severity.svg",
+        "meta/run.json",
+        "report.html",
+    ] {
+        assert!(dir.join(&base).join(rel).is_file(), "{rel}");
+    }
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let outcome: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let outputs: Vec<&str> = outcome["outputs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p.as_str().unwrap())
+        .collect();
+    assert!(
+        outputs.contains(&base.join("summary.csv").to_str().unwrap()),
+        "{outputs:?}"
+    );
+    let meta: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(dir.join(&base).join("meta/run.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        meta["args"]["output"],
+        base.join("lags.csv.gz").to_str().unwrap()
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_output_directories_work() {
+    use std::os::unix::ffi::OsStringExt;
+
+    let dir = workdir("non_utf8");
+    let bad = PathBuf::from(OsString::from_vec(b"out-\xff-dir".to_vec()));
+    let extra: Vec<OsString> = [
+        flag("--output", bad.join("lags.csv")),
+        flag("--strict-output-dir", bad.join("svgs")),
+        flag("--metadata-output", bad.join("run.json")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let out = batch(&dir, &extra);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let lags = std::fs::read_to_string(dir.join(&bad).join("lags.csv")).unwrap();
+    assert_eq!(lags.lines().count(), 2, "{lags}");
+    assert!(dir.join(&bad).join("svgs").is_dir());
+
+    // Serialized paths fall back to a lossy rendering instead of failing the run.
+    let meta: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.join(&bad).join("run.json")).unwrap()).unwrap();
+    assert_eq!(meta["args"]["output"], "out-\u{fffd}-dir/lags.csv");
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,190 @@
+// --parquet-output-dir: rows written through ParquetTable read back with the arrow reader
+// as typed columns.
+#![cfg(feature = "parquet")]
+
+use std::{env, path::PathBuf};
+
+use arrow_array::{
+    BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, TimeUnit};
+use chrono::{TimeZone, Utc};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use time_to_fix_cve::output::ColumnKind;
+use time_to_fix_cve::parquet::ParquetTable;
+use time_to_fix_cve::stats::STRICT_LAG_SCHEMA;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rq2_parquet_{name}_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read_back(path: &std::path::Path, batch_size: usize) -> Vec<RecordBatch> {
+    ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+        .unwrap()
+        .with_batch_size(batch_size)
+        .build()
+        .unwrap()
+        .map(|b| b.unwrap())
+        .collect()
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+    batch
+        .column_by_name(name)
+        .unwrap()
+        .as_any()
+        .downcast_ref::<T>()
+        .unwrap()
+}
+
+const COLUMNS: [(&str, ColumnKind); 6] = [
+    ("name", ColumnKind::Utf8),
+    ("flag", ColumnKind::Bool),
+    ("count", ColumnKind::Int64),
+    ("ratio", ColumnKind::Float64),
+    ("at", ColumnKind::Timestamp),
+    ("day", ColumnKind::Date),
+];
+
+fn row(cells: [&str; 6]) -> Vec<String> {
+    cells.map(str::to_string).to_vec()
+}
+
+#[test]
+fn typed_columns_round_trip_across_batches() {
+    let dir = scratch("typed");
+    let path = dir.join("rows.parquet");
+    // A batch size of 2 splits the three rows over two record batches.
+    let mut table = ParquetTable::create(&path, &COLUMNS, 2).unwrap();
+    table
+        .write_record(&row([
+            "a",
+            "true",
+            "61",
+            "0.5",
+            "2020-03-01 00:00:00 UTC",
+            "2020-03-05",
+        ]))
+        .unwrap();
+    table.write_record(&row(["", "", "", "", "", ""])).unwrap();
+    table
+        .write_record(&row([
+            "c,\"quoted\"",
+            "false",
+            "-3",
+            "1e3",
+            "2021-01-02T03:04:05Z",
+            "1970-01-01",
+        ]))
+        .unwrap();
+    table.close().unwrap();
+
+    // One row per batch on the way back, so each row can be checked on its own.
+    let rows = read_back(&path, 1);
+    assert_eq!(rows.len(), 3);
+    let (first, empty, last) = (&rows[0], &rows[1], &rows[2]);
+
+    let schema = first.schema();
+    let field = |name: &str| schema.field_with_name(name).unwrap().clone();
+    assert_eq!(field("name").data_type(), &DataType::Utf8);
+    assert!(!field("name").is_nullable());
+    assert_eq!(field("count").data_type(), &DataType::Int64);
+    assert_eq!(
+        field("at").data_type(),
+        &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+    );
+    assert_eq!(field("day").data_type(), &DataType::Date32);
+
+    // Text keeps empty strings; every other kind turns them into nulls.
+    assert_eq!(column::<StringArray>(first, "name").value(0), "a");
+    assert_eq!(column::<StringArray>(empty, "name").value(0), "");
+    assert_eq!(column::<StringArray>(last, "name").value(0), "c,\"quoted\"");
+    assert!(column::<BooleanArray>(first, "flag").value(0));
+    assert!(!column::<BooleanArray>(last, "flag").value(0));
+    assert_eq!(column::<Int64Array>(first, "count").value(0), 61);
+    assert_eq!(column::<Int64Array>(last, "count").value(0), -3);
+    assert_eq!(column::<Float64Array>(first, "ratio").value(0), 0.5);
+    assert_eq!(column::<Float64Array>(last, "ratio").value(0), 1000.0);
+    assert_eq!(
+        column::<TimestampMicrosecondArray>(first, "at").value(0),
+        Utc.with_ymd_and_hms(2020, 3, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp_micros()
+    );
+    assert_eq!(
+        column::<TimestampMicrosecondArray>(last, "at").value(0),
+        Utc.with_ymd_and_hms(2021, 1, 2, 3, 4, 5)
+            .unwrap()
+            .timestamp_micros()
+    );
+    assert_eq!(column::<Date32Array>(first, "day").value(0), 18326);
+    assert_eq!(column::<Date32Array>(last, "day").value(0), 0);
+    for name in ["flag", "count", "ratio", "at", "day"] {
+        assert!(empty.column_by_name(name).unwrap().is_null(0), "{name}");
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn strict_lag_rows_keep_their_schema() {
+    let dir = scratch("strict");
+    let path = dir.join("lag_rows.parquet");
+    let csv = std::fs::read_to_string(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/golden/default/strict_lags.csv"),
+    )
+    .unwrap();
+    let mut reader = csv::Reader::from_reader(csv.as_bytes());
+    let records: Vec<Vec<String>> = reader
+        .records()
+        .map(|r| r.unwrap().iter().map(str::to_string).collect())
+        .collect();
+    let mut table = ParquetTable::create(&path, &STRICT_LAG_SCHEMA, 1024).unwrap();
+    for record in &records {
+        table.write_record(record).unwrap();
+    }
+    table.close().unwrap();
+
+    let batches = read_back(&path, 1024);
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), records.len());
+    let names: Vec<String> = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect();
+    assert_eq!(names, STRICT_LAG_SCHEMA.map(|(n, _)| n.to_string()));
+    let lag_col = STRICT_LAG_SCHEMA
+        .iter()
+        .position(|(n, _)| *n == "lag_days")
+        .unwrap();
+    let lags = column::<Int64Array>(batch, "lag_days");
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(lags.value(i).to_string(), record[lag_col]);
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn malformed_rows_are_rejected() {
+    let dir = scratch("bad");
+    let mut table = ParquetTable::create(dir.join("bad.parquet"), &COLUMNS, 1).unwrap();
+    let err = table
+        .write_record(&row(["a", "true", "1", "1", "", ""])[..5])
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("5 cells, schema has 6 columns"),
+        "{err}"
+    );
+    let err = table
+        .write_record(&row(["a", "yes", "1", "1", "", ""]))
+        .unwrap_err();
+    assert!(err.to_string().contains("parquet column flag"), "{err}");
+    std::fs::remove_dir_all(&dir).ok();
+}
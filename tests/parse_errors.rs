@@ -0,0 +1,128 @@
+// Advisory files with broken front matter (tests/fixtures/broken_advisories) are skipped
+// and listed, and the rest of the archive still loads; --strict-parse aborts instead.
+
+mod common;
+
+use std::{io::Write, path::Path};
+
+use common::{Workdir, batch, golden_dir};
+use time_to_fix_cve::advisory::parse_advisory_archive;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+// (path under crates/, contents) of the broken fixtures, sorted by path.
+fn broken() -> Vec<(String, String)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/broken_advisories");
+    let mut out = Vec::new();
+    for krate in std::fs::read_dir(&root).unwrap() {
+        for file in std::fs::read_dir(krate.unwrap().path()).unwrap() {
+            let path = file.unwrap().path();
+            let rel = path.strip_prefix(&root).unwrap().to_string_lossy();
+            out.push((rel.into_owned(), std::fs::read_to_string(&path).unwrap()));
+        }
+    }
+    out.sort();
+    out
+}
+
+fn archive(files: &[(String, String)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (rel, md) in files {
+        zip.start_file(
+            format!("advisory-db-main/crates/{rel}"),
+            SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(md.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+const VALID: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "vulnlib"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+"#;
+
+#[test]
+fn broken_files_are_listed_and_skipped() {
+    let mut files = broken();
+    files.push((
+        "vulnlib/RUSTSEC-2020-0001.md".to_string(),
+        VALID.to_string(),
+    ));
+    let bytes = archive(&files);
+
+    let (advisories, source) = parse_advisory_archive(&bytes, "fixtures", false).unwrap();
+    let ids: Vec<&str> = advisories.iter().map(|a| a.rustsec_id.as_str()).collect();
+    assert_eq!(ids, ["RUSTSEC-2020-0001"]);
+    // The prose-only file has no front matter and is not an error.
+    assert_eq!(source.parse_error_count, 3);
+    let errors: Vec<(&str, &str)> = source
+        .parse_errors
+        .iter()
+        .map(|e| (e.file.as_str(), e.error.as_str()))
+        .collect();
+    for ((file, error), (expected_file, expected_error)) in errors.iter().zip([
+        ("brokenlib/RUSTSEC-2099-0001.md", "TOML parse error"),
+        ("brokenlib/RUSTSEC-2099-0002.md", "missing advisory.package"),
+        ("brokenlib/RUSTSEC-2099-0003.md", "missing [advisory]"),
+    ]) {
+        assert_eq!(
+            *file,
+            format!("advisory-db-main/crates/{expected_file}"),
+            "{error}"
+        );
+        assert!(error.contains(expected_error), "{file}: {error}");
+        assert!(!error.contains('\n'), "{file}: {error}");
+    }
+
+    let Err(err) = parse_advisory_archive(&bytes, "fixtures", true) else {
+        panic!("--strict-parse accepted a broken file");
+    };
+    assert!(
+        format!("{err:#}")
+            .contains("cannot parse advisory-db-main/crates/brokenlib/RUSTSEC-2099-0001.md"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn the_batch_run_continues_past_broken_files() {
+    let mut workdir = Workdir::golden();
+    for (rel, md) in broken() {
+        workdir = workdir.advisory(&rel, &md);
+    }
+    let dir = workdir.create("parse_errors");
+
+    let out = batch(&dir, &["--as-of", "2022-12-31"]);
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    assert_eq!(out.status.code(), Some(0), "{stderr}");
+    assert!(
+        stderr.contains("warning: 3 advisory files failed to parse and were skipped"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(
+            "advisory parse error: file=advisory-db-main/crates/brokenlib/RUSTSEC-2099-0002.md error=missing advisory.package"
+        ),
+        "{stderr}"
+    );
+    // The valid advisories give the same lags as a run without the broken files.
+    assert_eq!(
+        std::fs::read_to_string(dir.join("rustsec_rqx2_strict_lags.csv")).unwrap(),
+        std::fs::read_to_string(golden_dir().join("default/strict_lags.csv")).unwrap()
+    );
+
+    let out = batch(&dir, &["--as-of", "2022-12-31", "--strict-parse"]);
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    std::fs::remove_dir_all(&dir).ok();
+    assert_ne!(out.status.code(), Some(0), "{stderr}");
+    assert!(
+        stderr.contains("cannot parse advisory-db-main/crates/brokenlib/RUSTSEC-2099-0001.md"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,130 @@
+// `--per-advisory-svgs`: one lag histogram per advisory with enough first adoptions,
+// capped by `--per-advisory-svgs-max`, with stale files from earlier runs removed.
+
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    process::Output,
+};
+
+use common::{Workdir, batch_command};
+
+// Two advisories against the same crate, so both see the same two adopters.
+fn advisory(id: &str) -> String {
+    format!(
+        r#"```toml
+[advisory]
+id = "{id}"
+package = "vulnlib"
+date = "2020-03-05"
+cvss = "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#
+    )
+}
+
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n3,app_b,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n\
+         30,3,0.1.0,2020-01-20 00:00:00\n\
+         31,3,0.2.0,2020-04-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n\
+         20,1,^0.1,0\n21,1,^0.2,0\n30,1,^0.1,0\n31,1,^0.2,0\n",
+    )
+    .advisory(
+        "vulnlib/RUSTSEC-0000-0001.md",
+        &advisory("RUSTSEC-0000-0001"),
+    )
+    .advisory(
+        "vulnlib/RUSTSEC-0000-0003.md",
+        &advisory("RUSTSEC-0000-0003"),
+    )
+    .create(&format!("pasvg_{name}"))
+}
+
+fn batch(dir: &Path, extra: &[&str]) -> Output {
+    batch_command(dir)
+        .args(["--per-advisory-svgs", "per_adv"])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+fn svgs(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir.join("per_adv"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.ends_with(".svg"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn writes_one_histogram_per_qualifying_advisory() {
+    let dir = workdir("write");
+    std::fs::create_dir_all(dir.join("per_adv")).unwrap();
+    std::fs::write(dir.join("per_adv/lag_hist_RUSTSEC-1999-0001.svg"), "stale").unwrap();
+    std::fs::write(dir.join("per_adv/notes.svg"), "kept").unwrap();
+
+    let out = batch(&dir, &["--per-advisory-svgs-min-rows", "2"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    assert_eq!(
+        svgs(&dir),
+        [
+            "lag_hist_RUSTSEC-0000-0001.svg",
+            "lag_hist_RUSTSEC-0000-0003.svg",
+            "notes.svg"
+        ]
+    );
+    let svg = std::fs::read_to_string(dir.join("per_adv/lag_hist_RUSTSEC-0000-0001.svg")).unwrap();
+    assert!(svg.contains("RUSTSEC-0000-0001 strict lag histogram (first adoptions, n=2)"));
+    assert!(svg.contains("vulnlib | severity CRITICAL | fixed 2020-03-01"));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "per-advisory lag histograms: written=2, skipped over --per-advisory-svgs-max=0"
+        ),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn min_rows_and_max_limit_the_files() {
+    let dir = workdir("limits");
+    let out = batch(&dir, &["--per-advisory-svgs-min-rows", "3"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    assert!(svgs(&dir).is_empty());
+
+    let out = batch(
+        &dir,
+        &[
+            "--per-advisory-svgs-min-rows",
+            "2",
+            "--per-advisory-svgs-max",
+            "1",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    assert_eq!(svgs(&dir), ["lag_hist_RUSTSEC-0000-0001.svg"]);
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "per-advisory lag histograms: written=1, skipped over --per-advisory-svgs-max=1"
+        ),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
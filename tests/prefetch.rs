@@ -0,0 +1,44 @@
+// --prefetch warms the downstream cache with at most --downstream-cache-crates histories,
+// largest first, and leaves the rest to the on-demand path without changing the lags.
+
+mod common;
+
+use common::{Workdir, batch, golden_dir};
+
+#[test]
+fn prefetch_stops_at_the_cache_size() {
+    let dir = Workdir::golden().create("prefetch_cap");
+    let golden = std::fs::read_to_string(golden_dir().join("default/strict_lags.csv")).unwrap();
+    for (cache_crates, lines) in [
+        (
+            "1",
+            [
+                "prefetch: --downstream-cache-crates 1 leaves 2 of 3 target crates to be fetched on demand",
+                "prefetch: target_crates=3 downstream_rows=9 cached_crates=1/1",
+            ],
+        ),
+        (
+            "8",
+            [
+                "prefetch: largest downstream histories: vulnlib=9 parsekit=6 netio=5",
+                "prefetch: target_crates=3 downstream_rows=20 cached_crates=3/8",
+            ],
+        ),
+    ] {
+        let out = batch(
+            &dir,
+            &["--prefetch", "--downstream-cache-crates", cache_crates],
+        );
+        let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+        assert_eq!(out.status.code(), Some(0), "{stderr}");
+        for line in lines {
+            assert!(stderr.contains(line), "{line}: {stderr}");
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.join("rustsec_rqx2_strict_lags.csv")).unwrap(),
+            golden,
+            "--downstream-cache-crates {cache_crates}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,92 @@
+// --prerelease-policy on advisories whose only patched release is a pre-release, which
+// the real advisory-db has (e.g. a fix shipped in `1.0.0-rc.1` before 1.0.0 went out).
+
+use semver::Version;
+use time_to_fix_cve::advisory::{PrereleasePolicy, identify_vuln_versions};
+use time_to_fix_cve::pipeline::{
+    FixedVersionSource, first_published_matching, static_fixed_versions,
+};
+
+const PUBLISHED: [&str; 5] = ["0.9.0", "0.9.1-rc.1", "0.9.1-rc.2", "1.0.0-beta.1", "0.8.0"];
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+fn vuln(patched: &[&str], policy: PrereleasePolicy) -> Vec<String> {
+    identify_vuln_versions(&strings(&PUBLISHED), &strings(patched), &[], policy)
+        .iter()
+        .map(Version::to_string)
+        .collect()
+}
+
+fn first_fix(patched: &str, policy: PrereleasePolicy) -> Option<String> {
+    let mut published: Vec<(Version, String)> = PUBLISHED
+        .iter()
+        .map(|s| (Version::parse(s).unwrap(), s.to_string()))
+        .collect();
+    published.sort();
+    first_published_matching(patched, &published, policy).map(|(_, s, _)| s.clone())
+}
+
+#[test]
+fn prerelease_only_fix_under_each_policy() {
+    // semver only lets `>=0.9.1-rc.1` match pre-releases of 0.9.1, so 1.0.0-beta.1 stays
+    // vulnerable; `include` orders it after the fix like any other version.
+    assert_eq!(
+        vuln(&[">=0.9.1-rc.1"], PrereleasePolicy::MatchSemver),
+        ["0.8.0", "0.9.0", "1.0.0-beta.1"]
+    );
+    assert_eq!(
+        vuln(&[">=0.9.1-rc.1"], PrereleasePolicy::Include),
+        ["0.8.0", "0.9.0"]
+    );
+    assert_eq!(
+        vuln(&[">=0.9.1-rc.1"], PrereleasePolicy::Exclude),
+        ["0.8.0", "0.9.0"]
+    );
+
+    for policy in [PrereleasePolicy::MatchSemver, PrereleasePolicy::Include] {
+        assert_eq!(
+            static_fixed_versions(&strings(&[">=0.9.1-rc.1"]), policy),
+            (
+                vec![Version::parse("0.9.1-rc.1").unwrap()],
+                FixedVersionSource::Exact
+            ),
+            "{policy:?}"
+        );
+        assert_eq!(
+            first_fix(">=0.9.1-rc.1", policy).as_deref(),
+            Some("0.9.1-rc.1"),
+            "{policy:?}"
+        );
+    }
+    // With pre-releases excluded there is no fixed release to measure from.
+    assert_eq!(
+        static_fixed_versions(&strings(&[">=0.9.1-rc.1"]), PrereleasePolicy::Exclude),
+        (vec![], FixedVersionSource::PublishedRange)
+    );
+    assert_eq!(first_fix(">=0.9.1-rc.1", PrereleasePolicy::Exclude), None);
+}
+
+#[test]
+fn stable_patched_ranges_ignore_the_policy_for_stable_versions() {
+    for policy in [
+        PrereleasePolicy::MatchSemver,
+        PrereleasePolicy::Include,
+        PrereleasePolicy::Exclude,
+    ] {
+        let got = vuln(&[">=0.9.0"], policy);
+        assert!(
+            got.starts_with(&["0.8.0".to_string()]),
+            "{policy:?}: {got:?}"
+        );
+        assert!(!got.contains(&"0.9.0".to_string()), "{policy:?}: {got:?}");
+    }
+    // Only `match-semver` keeps pre-releases of a later line out of `>=0.9.0`.
+    assert_eq!(
+        vuln(&[">=0.9.0"], PrereleasePolicy::MatchSemver),
+        ["0.8.0", "0.9.1-rc.1", "0.9.1-rc.2", "1.0.0-beta.1"]
+    );
+    assert_eq!(vuln(&[">=0.9.0"], PrereleasePolicy::Include), ["0.8.0"]);
+}
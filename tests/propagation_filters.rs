@@ -0,0 +1,131 @@
+// --propagation-min-downstream-releases drops thinly released crates from the graph and
+// --propagation-within-days censors adoptions that came too late; both show up per hop in
+// the coverage CSV and the propagation summary.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{Workdir, batch, batch_command, read_columns};
+
+const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-0000-0001"
+package = "vulnlib"
+date = "2020-03-05"
+
+[versions]
+patched = [">= 0.2.0"]
+```
+
+# vulnlib is vulnerable
+"#;
+
+// vulnlib 0.2.0 is the fix; app_a adopts it (hop 1). At hop 2, app_b (three releases)
+// picks up app_a's fix after well over a year and app_c (two releases) after three weeks.
+fn workdir(name: &str) -> PathBuf {
+    Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n3,app_b,5\n4,app_c,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n\
+         30,3,1.0.0,2020-01-20 00:00:00\n\
+         31,3,1.0.1,2020-02-01 00:00:00\n\
+         32,3,1.1.0,2021-06-01 00:00:00\n\
+         40,4,1.0.0,2020-02-01 00:00:00\n\
+         41,4,1.0.1,2020-04-01 00:00:00\n",
+        "version_id,crate_id,req,kind\n\
+         20,1,^0.1,0\n\
+         21,1,^0.2,0\n\
+         30,2,^1.0,0\n\
+         31,2,^1.0,0\n\
+         32,2,^1.1,0\n\
+         40,2,^1.0,0\n\
+         41,2,^1.1,0\n",
+    )
+    .advisory("vulnlib/RUSTSEC-0000-0001.md", ADVISORY)
+    .create(&format!("propfilter_{name}"))
+}
+
+fn run(name: &str, extra: &[&str]) -> (Vec<[String; 6]>, String) {
+    let dir = workdir(name);
+    let out = batch_command(&dir)
+        .args(["--propagation"])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let rows = read_columns(
+        &dir.join("rustsec_rqx2_propagation_coverage.csv"),
+        [
+            "hop",
+            "affected_cnt",
+            "adopted_cnt",
+            "not_adopted_cnt",
+            "censored_cnt",
+            "few_releases_cnt",
+        ],
+    );
+    let summary =
+        std::fs::read_to_string(dir.join("rustsec_rqx2_propagation_summary.txt")).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    (rows, summary)
+}
+
+fn row(cells: [&str; 6]) -> [String; 6] {
+    cells.map(str::to_string)
+}
+
+#[test]
+fn unfiltered_counts_every_adoption() {
+    let (rows, summary) = run("off", &[]);
+    assert_eq!(
+        rows,
+        [
+            row(["1", "1", "1", "0", "0", "0"]),
+            row(["2", "2", "2", "0", "0", "0"]),
+            // app_b and app_c are expanded but nothing depends on them.
+            row(["3", "0", "0", "0", "0", "0"]),
+        ]
+    );
+    assert!(!summary.contains("censored="), "{summary}");
+    assert!(!summary.contains("within_days ="), "{summary}");
+}
+
+#[test]
+fn late_adoptions_are_censored_and_sparse_crates_dropped() {
+    let (rows, summary) = run(
+        "on",
+        &[
+            "--propagation-min-downstream-releases",
+            "3",
+            "--propagation-within-days",
+            "365",
+        ],
+    );
+    // Hop 1 follows the strict-lag rows, which these filters do not touch.
+    assert_eq!(
+        rows,
+        [
+            row(["1", "1", "1", "0", "0", "0"]),
+            row(["2", "1", "0", "0", "1", "1"]),
+        ]
+    );
+    assert!(summary.contains("min_downstream_releases = 3"), "{summary}");
+    assert!(summary.contains("within_days = 365"), "{summary}");
+    assert!(
+        summary
+            .contains("hop 2: affected=1 adopted=0 adoption_rate=0.00% censored=1 few_releases=1"),
+        "{summary}"
+    );
+}
+
+#[test]
+fn negative_window_is_rejected() {
+    let dir = workdir("bad");
+    let out = batch(&dir, &["--propagation", "--propagation-within-days", "-1"]);
+    assert_eq!(out.status.code(), Some(3), "{out:?}");
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,320 @@
+// The per-advisory row writers of rqx2_rustsec_batch, read back from in-memory buffers.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use semver::Version;
+use time_to_fix_cve::{
+    advisory::{Advisory, parse_advisory},
+    analysis::FixSelection,
+    database::RowSource,
+    pipeline::{
+        AdoptionEvidence, AdoptionKind, AdvisoryResult, ConstraintBreakdown, ConstraintEdge,
+        FirstOpportunity, HorizonBreak, LagMode, LagUnit, ReqShape, StrictLagRow, T0Kind,
+    },
+    records::{
+        EdgeBudget, PendingRows, PropagationEvent, SummaryRow, propagation_event_record,
+        summary_record, write_constraint_breakdown, write_constraint_edges,
+        write_constraint_horizons, write_grouped_lags, write_negative_lags,
+        write_propagation_reach, write_strict_lags,
+    },
+    stats::{PROPAGATION_EVENT_SCHEMA, STRICT_LAG_COLUMNS, SUMMARY_COLUMNS},
+};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+fn advisory() -> Advisory {
+    let toml = r#"
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "vulnlib"
+date = "2020-02-01"
+aliases = ["CVE-2020-0001"]
+
+[versions]
+patched = [">= 0.1.1"]
+"#;
+    parse_advisory(&toml.parse().unwrap()).unwrap()
+}
+
+fn row(downstream_crate: &str, adopted_at: DateTime<Utc>, adoption_index: usize) -> StrictLagRow {
+    StrictLagRow {
+        downstream_crate: downstream_crate.to_string(),
+        downstream_version: "1.0.1".to_string(),
+        downstream_time: adopted_at,
+        lag_secs: (adopted_at - day(3, 1)).num_seconds(),
+        original_req: "^0.1.0".to_string(),
+        fixed_req: "^0.1.1".to_string(),
+        matched_fix_version: "0.1.1".to_string(),
+        matched_fix_time: day(3, 1),
+        t0: day(3, 1),
+        t0_kind: T0Kind::FixRelease,
+        negative: false,
+        adoption_index,
+        adoption_kind: AdoptionKind::Compatible,
+        evidence: AdoptionEvidence::MinBumped,
+        lag_mode: LagMode::Strict,
+        cadence_days: None,
+        first_opportunity: None,
+    }
+}
+
+// vulnlib 0.1.1 fixed on 03-01; app_a adopts it 10 days later and again on 04-01, app_b
+// after 20 days.
+fn result() -> AdvisoryResult {
+    let mut resolvable = row("app_a", day(3, 6), 1);
+    resolvable.lag_mode = LagMode::FirstResolvable;
+    AdvisoryResult {
+        crate_name: "vulnlib".to_string(),
+        fix_times: BTreeMap::from([(Version::new(0, 1, 1), day(3, 1))]),
+        summary_t0: Some(day(3, 1)),
+        downstream_crates: 3,
+        rows: vec![
+            row("app_a", day(3, 11), 1),
+            row("app_a", day(4, 1), 2),
+            row("app_b", day(3, 21), 1),
+        ],
+        negative_rows: vec![row("app_c", day(2, 20), 1)],
+        affected: 2,
+        resolvable_rows: vec![resolvable],
+        resolvable_affected: 1,
+        ..AdvisoryResult::default()
+    }
+}
+
+fn summary<'a>(adv: &'a Advisory, result: &'a AdvisoryResult, mode: LagMode) -> SummaryRow<'a> {
+    SummaryRow {
+        advisory: adv,
+        result,
+        mode,
+        lag_unit: LagUnit::Days,
+        lag_t0: day(3, 1),
+        lag_t0_kind: T0Kind::FixRelease,
+        as_of: None,
+        fix_selection: FixSelection::EarliestOverall,
+        history_rows: 7,
+        excluded_few_versions: 0,
+        excluded_inactive: 0,
+        downstream_source: RowSource::Database,
+        owner_groups: None,
+        collapsed: None,
+    }
+}
+
+fn cell<'a>(columns: &[&str], record: &'a [String], name: &str) -> &'a str {
+    let i = columns.iter().position(|c| *c == name).unwrap();
+    &record[i]
+}
+
+#[test]
+fn strict_lags_follow_the_lag_modes_in_order() {
+    let (adv, result) = (advisory(), result());
+    let mut w = PendingRows::default();
+    let written = write_strict_lags(
+        &mut w,
+        &adv,
+        &result,
+        &[LagMode::Strict, LagMode::FirstResolvable],
+    )
+    .unwrap();
+    assert_eq!(written, 4);
+    assert!(w.0.iter().all(|r| r.len() == STRICT_LAG_COLUMNS.len()));
+    let rows: Vec<_> =
+        w.0.iter()
+            .map(|r| {
+                (
+                    cell(&STRICT_LAG_COLUMNS, r, "downstream_crate"),
+                    cell(&STRICT_LAG_COLUMNS, r, "lag_days"),
+                    cell(&STRICT_LAG_COLUMNS, r, "lag_mode"),
+                )
+            })
+            .collect();
+    assert_eq!(
+        rows,
+        [
+            ("app_a", "10", "strict"),
+            ("app_a", "31", "strict"),
+            ("app_b", "20", "strict"),
+            ("app_a", "5", "first-resolvable"),
+        ]
+    );
+
+    let mut negative = PendingRows::default();
+    write_negative_lags(&mut negative, &adv, &result).unwrap();
+    assert_eq!(negative.0.len(), 1);
+    assert_eq!(
+        cell(&STRICT_LAG_COLUMNS, &negative.0[0], "downstream_crate"),
+        "app_c"
+    );
+}
+
+#[test]
+fn grouped_lags_append_the_group_and_its_size() {
+    let (adv, result) = (advisory(), result());
+    let mut w = PendingRows::default();
+    let groups = [("acme".to_string(), &result.rows[0], 2)];
+    write_grouped_lags(&mut w, &adv, "vulnlib", &groups).unwrap();
+    assert_eq!(w.0[0].len(), STRICT_LAG_COLUMNS.len() + 2);
+    assert_eq!(w.0[0][STRICT_LAG_COLUMNS.len()..], ["acme", "2"]);
+}
+
+#[test]
+fn summary_rows_count_first_adoptions_of_their_mode() {
+    let (adv, result) = (advisory(), result());
+    let strict = summary_record(&summary(&adv, &result, LagMode::Strict));
+    assert_eq!(strict.len(), SUMMARY_COLUMNS.len());
+    let get = |name| cell(&SUMMARY_COLUMNS, &strict, name);
+    assert_eq!(get("rustsec_id"), "RUSTSEC-2020-0001");
+    assert_eq!(get("target_crate"), "vulnlib");
+    assert_eq!(get("downstream_fixed_cnt"), "2");
+    assert_eq!((get("lag_days_min"), get("lag_days_max")), ("10", "20"));
+    assert_eq!(get("downstream_affected_cnt"), "2");
+    assert_eq!(get("lag_mode"), "strict");
+    assert_eq!(get("lag_unit"), "days");
+    assert_eq!(get("downstream_total_cnt"), "3");
+    assert_eq!(get("downstream_history_rows"), "7");
+    assert_eq!(get("negative_lag_rows"), "1");
+    // Owner and prefix grouping were off, so their columns stay empty.
+    assert_eq!(get("owner_group_cnt"), "");
+
+    let resolvable = summary_record(&summary(&adv, &result, LagMode::FirstResolvable));
+    assert_eq!(
+        cell(&SUMMARY_COLUMNS, &resolvable, "lag_mode"),
+        "first-resolvable"
+    );
+    assert_eq!(
+        cell(&SUMMARY_COLUMNS, &resolvable, "negative_lag_rows"),
+        "0"
+    );
+    assert_eq!(
+        cell(&SUMMARY_COLUMNS, &resolvable, "downstream_affected_cnt"),
+        "1"
+    );
+}
+
+#[test]
+fn summary_rows_fill_the_grouping_columns_when_asked() {
+    let (adv, result) = (advisory(), result());
+    let mut row = summary(&adv, &result, LagMode::Strict);
+    row.collapsed = Some((2, 1));
+    let record = summary_record(&row);
+    assert_eq!(cell(&SUMMARY_COLUMNS, &record, "collapse_rows_before"), "2");
+    assert_eq!(cell(&SUMMARY_COLUMNS, &record, "collapse_rows_after"), "1");
+}
+
+#[test]
+fn constraint_edges_stop_at_the_run_budget() {
+    let adv = advisory();
+    let edge = |downstream_crate: &str| ConstraintEdge {
+        downstream_crate: downstream_crate.to_string(),
+        downstream_version: "1.0.0".to_string(),
+        dep_req: "=0.1.0".to_string(),
+        req_shape: ReqShape::Exact,
+        locked_out: true,
+        smallest_satisfying_fix: None,
+    };
+    let mut budget = EdgeBudget {
+        limit: 3,
+        ..EdgeBudget::default()
+    };
+    let mut w = PendingRows::default();
+    write_constraint_edges(&mut w, &adv, &[edge("app_a"), edge("app_b")], &mut budget).unwrap();
+    write_constraint_edges(&mut w, &adv, &[edge("app_c"), edge("app_d")], &mut budget).unwrap();
+    let crates: Vec<_> = w.0.iter().map(|r| r[1].as_str()).collect();
+    assert_eq!(crates, ["app_a", "app_b", "app_c"]);
+    assert_eq!((budget.written, budget.truncated), (3, 1));
+}
+
+#[test]
+fn constraint_rows_need_a_breakdown() {
+    let adv = advisory();
+    let mut result = result();
+    let (mut breakdown, mut horizons) = (PendingRows::default(), PendingRows::default());
+    write_constraint_breakdown(&mut breakdown, &adv, &result, false).unwrap();
+    write_constraint_horizons(&mut horizons, &adv, &result).unwrap();
+    assert!(breakdown.0.is_empty() && horizons.0.is_empty());
+
+    result.constraint = Some(ConstraintBreakdown {
+        affected_edges: 4,
+        locked_out_edges: 1,
+        break_rate_percent: 25,
+        ..ConstraintBreakdown::default()
+    });
+    result.constraint_horizons = vec![HorizonBreak {
+        horizon_days: 30,
+        affected_edges: 4,
+        locked_out_edges: 2,
+    }];
+    write_constraint_breakdown(&mut breakdown, &adv, &result, false).unwrap();
+    write_constraint_breakdown(&mut breakdown, &adv, &result, true).unwrap();
+    // The what-if columns are left empty unless they were computed.
+    let (without, with) = (&breakdown.0[0], &breakdown.0[1]);
+    assert_eq!(without.len(), with.len());
+    assert_eq!(without[9..13], ["0", "4", "1", "25"]);
+    assert_eq!(without[without.len() - 4..], ["", "", "", ""]);
+    assert_eq!(with[with.len() - 4..], ["0", "0", "0", "0"]);
+
+    write_constraint_horizons(&mut horizons, &adv, &result).unwrap();
+    assert_eq!(horizons.0[0][9..], ["4", "2", "50"]);
+}
+
+#[test]
+fn propagation_events_carry_the_path_lag_and_first_opportunity() {
+    let adv = advisory();
+    let event = PropagationEvent {
+        root_crate: "vulnlib",
+        hop: 2,
+        upstream_crate: "app_a",
+        upstream_fix_version: "1.0.1".to_string(),
+        upstream_fix_time: day(3, 11),
+        downstream_crate: "app_d",
+        downstream_version: "0.3.0".to_string(),
+        downstream_time: day(3, 16),
+        dep_req: "^1.0.1",
+        lag_secs: 5 * 86_400,
+        cumulative_secs: 15 * 86_400,
+        evidence: AdoptionEvidence::ReqMatches,
+        opportunity: Some(FirstOpportunity {
+            time: day(3, 13),
+            skipped_releases: 1,
+        }),
+    };
+    let record = propagation_event_record(&adv, &event);
+    let columns = PROPAGATION_EVENT_SCHEMA.map(|(name, _)| name);
+    assert_eq!(record.len(), columns.len());
+    let get = |name| cell(&columns, &record, name);
+    assert_eq!(get("root_target_crate"), "vulnlib");
+    assert_eq!(get("hop"), "2");
+    assert_eq!(get("upstream_crate"), "app_a");
+    assert_eq!(get("lag_days"), "5");
+    assert_eq!(get("cumulative_lag_days"), "15");
+    assert_eq!(get("first_opportunity_lag_days"), "3.0000");
+    assert_eq!(get("skipped_releases"), "1");
+
+    let record = propagation_event_record(
+        &adv,
+        &PropagationEvent {
+            opportunity: None,
+            ..event
+        },
+    );
+    assert_eq!(record[record.len() - 3..], ["", "", ""]);
+}
+
+#[test]
+fn reach_rows_run_past_the_last_reached_hop_for_truncated_events() {
+    let adv = advisory();
+    let mut w = PendingRows::default();
+    write_propagation_reach(
+        &mut w,
+        &adv,
+        "vulnlib",
+        &[(2, 100), (1, 5)],
+        &BTreeMap::from([(3, 4)]),
+    )
+    .unwrap();
+    let rows: Vec<_> = w.0.iter().map(|r| r[3..].join(",")).collect();
+    assert_eq!(rows, ["1,2,100,2,100,0", "2,1,5,3,105,0", "3,0,0,3,105,4"]);
+}
@@ -0,0 +1,61 @@
+// Rename chains and history merging for --crate-renames.
+
+use chrono::{DateTime, TimeZone, Utc};
+use time_to_fix_cve::pipeline::{CrateRenames, merge_renamed_histories};
+
+fn day(m: u32, d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn chains_link_every_name_oldest_first() {
+    let renames = CrateRenames::parse(
+        "old_name,new_name,effective_date
+libb,libc,2021-06-01
+liba,libb,2020-01-01
+",
+    )
+    .unwrap();
+    assert_eq!(renames.len(), 2);
+    for name in ["liba", "libb", "libc"] {
+        assert_eq!(renames.chain(name), ["liba", "libb", "libc"], "{name}");
+    }
+    assert_eq!(renames.chain("serde"), ["serde"]);
+}
+
+#[test]
+fn malformed_renames_are_rejected() {
+    for text in [
+        "old,new,date\na,b,2020-01-01\n",
+        "old_name,new_name,effective_date\na,b,June 2020\n",
+        "old_name,new_name,effective_date\na,a,2020-01-01\n",
+    ] {
+        assert!(CrateRenames::parse(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn merged_versions_are_chronological_and_unique() {
+    let old = vec![
+        ("0.1.0".to_string(), day(1, 1)),
+        ("0.2.0".to_string(), day(3, 1)),
+    ];
+    // The new name republishes 0.2.0 and continues from there.
+    let new = vec![
+        ("0.3.0".to_string(), day(6, 1)),
+        ("0.2.0".to_string(), day(5, 1)),
+    ];
+    let merged = merge_renamed_histories(
+        vec![("liba".to_string(), old), ("libb".to_string(), new)],
+        |(_, t)| *t,
+        |(num, _)| num.clone(),
+    );
+    let summary: Vec<(&str, &str)> = merged
+        .iter()
+        .map(|(name, (num, _))| (name.as_str(), num.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [("liba", "0.1.0"), ("liba", "0.2.0"), ("libb", "0.3.0")]
+    );
+}
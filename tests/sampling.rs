@@ -0,0 +1,149 @@
+// The seeded reservoir sampler behind --verify-seed, and the hop/time-ordered cap behind
+// --propagation-events-limit.
+
+use chrono::{DateTime, TimeZone, Utc};
+use time_to_fix_cve::sampling::{EventSelector, Reservoir, SplitMix64};
+
+fn sample(capacity: usize, seed: u64, n: u32) -> Vec<u32> {
+    let mut r = Reservoir::new(capacity, seed);
+    for i in 0..n {
+        r.offer(i);
+    }
+    assert_eq!(r.seen(), u64::from(n));
+    r.into_items()
+}
+
+#[test]
+fn same_seed_same_stream_and_sample() {
+    let mut a = SplitMix64::new(42);
+    let mut b = SplitMix64::new(42);
+    let mut c = SplitMix64::new(43);
+    let xs: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+    let ys: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+    let zs: Vec<u64> = (0..8).map(|_| c.next_u64()).collect();
+    assert_eq!(xs, ys);
+    assert_ne!(xs, zs);
+    // Reference output of SplitMix64 seeded with 0.
+    assert_eq!(SplitMix64::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+
+    assert_eq!(sample(5, 7, 1000), sample(5, 7, 1000));
+    assert_ne!(sample(5, 7, 1000), sample(5, 8, 1000));
+}
+
+#[test]
+fn below_stays_in_range() {
+    let mut rng = SplitMix64::new(1);
+    for n in [1, 2, 3, 10, 1 << 40] {
+        for _ in 0..200 {
+            assert!(rng.below(n) < n);
+        }
+    }
+    assert_eq!(rng.below(1), 0);
+}
+
+#[test]
+fn never_holds_more_than_its_capacity() {
+    for capacity in [1, 3, 16] {
+        let items = sample(capacity, 9, 500);
+        assert_eq!(items.len(), capacity);
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), capacity, "{items:?}");
+        assert!(items.iter().all(|&i| i < 500));
+    }
+    assert!(sample(0, 9, 500).is_empty());
+}
+
+#[test]
+fn fewer_items_than_capacity_keeps_them_all_in_order() {
+    assert_eq!(sample(10, 3, 4), [0, 1, 2, 3]);
+    assert_eq!(sample(4, 3, 4), [0, 1, 2, 3]);
+    assert!(sample(4, 3, 0).is_empty());
+}
+
+#[test]
+fn rejected_offers_are_never_built() {
+    let mut r = Reservoir::new(2, 11);
+    let mut built = 0;
+    for i in 0..1000 {
+        r.offer_with(|| {
+            built += 1;
+            i
+        });
+    }
+    assert_eq!(r.items().len(), 2);
+    assert!(built < 100, "{built}");
+}
+
+#[test]
+fn every_position_is_equally_likely() {
+    // 10 items into a 2-slot reservoir: each item should be kept about 20% of the time.
+    let mut kept = [0u32; 10];
+    for seed in 0..5000 {
+        for i in sample(2, seed, 10) {
+            kept[i as usize] += 1;
+        }
+    }
+    for (i, &k) in kept.iter().enumerate() {
+        assert!((800..1200).contains(&k), "item {i}: {k} of 5000 runs");
+    }
+}
+
+fn day(d: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 1, d, 0, 0, 0).unwrap()
+}
+
+// (hop, day, name) events pushed in order.
+fn select(
+    limit: usize,
+    events: &[(usize, u32, &'static str)],
+) -> (Vec<&'static str>, Vec<(usize, usize)>) {
+    let mut selector = EventSelector::new(limit);
+    for &(hop, d, name) in events {
+        selector.push(hop, day(d), name);
+    }
+    let (kept, dropped) = selector.finish();
+    (kept, dropped.into_iter().collect())
+}
+
+const EVENTS: [(usize, u32, &str); 6] = [
+    (2, 1, "a"),
+    (1, 9, "b"),
+    (1, 3, "c"),
+    (3, 1, "d"),
+    (2, 2, "e"),
+    (1, 5, "f"),
+];
+
+#[test]
+fn keeps_the_lowest_hop_then_earliest_in_discovery_order() {
+    assert_eq!(
+        select(3, &EVENTS),
+        (vec!["b", "c", "f"], vec![(2, 2), (3, 1)])
+    );
+    assert_eq!(
+        select(4, &EVENTS),
+        (vec!["a", "b", "c", "f"], vec![(2, 1), (3, 1)])
+    );
+    assert_eq!(
+        select(1, &EVENTS),
+        (vec!["c"], vec![(1, 2), (2, 2), (3, 1)])
+    );
+}
+
+#[test]
+fn ties_go_to_the_event_found_first() {
+    let tied = [(1, 4, "x"), (1, 4, "y"), (1, 4, "z"), (0, 9, "w")];
+    assert_eq!(select(2, &tied), (vec!["x", "w"], vec![(1, 2)]));
+    assert_eq!(select(3, &tied), (vec!["x", "y", "w"], vec![(1, 1)]));
+}
+
+#[test]
+fn a_limit_of_zero_or_above_the_input_keeps_everything() {
+    let all = vec!["a", "b", "c", "d", "e", "f"];
+    assert_eq!(select(0, &EVENTS), (all.clone(), vec![]));
+    assert_eq!(select(6, &EVENTS), (all.clone(), vec![]));
+    assert_eq!(select(100, &EVENTS), (all, vec![]));
+    assert_eq!(select(3, &[]), (vec![], vec![]));
+}
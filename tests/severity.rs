@@ -0,0 +1,243 @@
+// Severity normalization, where each severity comes from, and --severity-overrides.
+
+mod common;
+
+use common::{Workdir, batch};
+use time_to_fix_cve::advisory::{
+    Advisory, SeveritySource, apply_severity_overrides, extract_severity_with_source,
+    normalize_severity, parse_advisory, parse_severity_overrides,
+};
+
+fn front_matter(id: &str, extra: &str) -> String {
+    format!(
+        "[advisory]\nid = \"{id}\"\npackage = \"vulnlib\"\ndate = \"2020-03-05\"\n{extra}\n\n[versions]\npatched = [\">= 0.2.0\"]\n"
+    )
+}
+
+fn advisory(id: &str, extra: &str) -> Advisory {
+    parse_advisory(&toml::from_str(&front_matter(id, extra)).unwrap()).unwrap()
+}
+
+#[test]
+fn normalize_maps_aliases_and_keeps_unrecognized_values() {
+    for (raw, want) in [
+        ("informational", "INFO"),
+        (" info ", "INFO"),
+        ("low", "LOW"),
+        ("Moderate", "MEDIUM"),
+        ("medium", "MEDIUM"),
+        ("HIGH", "HIGH"),
+        ("critical", "CRITICAL"),
+        ("", "UNKNOWN"),
+        ("  ", "UNKNOWN"),
+        ("severe", "SEVERE"),
+    ] {
+        assert_eq!(normalize_severity(raw), want, "{raw:?}");
+    }
+}
+
+#[test]
+fn extraction_reports_its_source() {
+    let cases = [
+        (
+            "severity = \"moderate\"",
+            "MEDIUM",
+            SeveritySource::Advisory,
+        ),
+        (
+            "cvss = \"CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H\"",
+            "CRITICAL",
+            SeveritySource::Cvss,
+        ),
+        (
+            "cvss = \"AV:N/AC:L/Au:N/C:P/I:P/A:P\"",
+            "HIGH",
+            SeveritySource::Cvss,
+        ),
+        (
+            "informational = \"unmaintained\"",
+            "INFO",
+            SeveritySource::Informational,
+        ),
+        ("", "UNKNOWN", SeveritySource::Unknown),
+    ];
+    for (extra, severity, source) in cases {
+        let val: toml::Value = toml::from_str(&front_matter("RUSTSEC-2020-0001", extra)).unwrap();
+        let table = val["advisory"].as_table().unwrap();
+        assert_eq!(
+            extract_severity_with_source(table),
+            (severity.to_string(), source),
+            "{extra}"
+        );
+        let adv = parse_advisory(&val).unwrap();
+        assert_eq!(
+            (adv.severity.as_str(), adv.severity_source),
+            (severity, source)
+        );
+    }
+}
+
+#[test]
+fn overrides_are_normalized_and_validated() {
+    let parsed = parse_severity_overrides(
+        "# manual corrections\nseverity,rustsec_id\n moderate , RUSTSEC-2020-0001\nINFO,RUSTSEC-2020-0002\n,RUSTSEC-2020-0003\n",
+    )
+    .unwrap();
+    let pairs: Vec<(&str, &str)> = parsed
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ("RUSTSEC-2020-0001", "MEDIUM"),
+            ("RUSTSEC-2020-0002", "INFO"),
+            ("RUSTSEC-2020-0003", "UNKNOWN"),
+        ]
+    );
+
+    for (text, needle) in [
+        ("rustsec_id\nRUSTSEC-2020-0001\n", "no `severity` column"),
+        ("rustsec_id,severity\nRUSTSEC-2020-0001,severe\n", "line 2"),
+        (
+            "rustsec_id,severity\nRUSTSEC-2020-0001,low\nRUSTSEC-2020-0001,high\n",
+            "listed twice",
+        ),
+        ("rustsec_id,severity\n,high\n", "empty rustsec_id"),
+    ] {
+        let err = parse_severity_overrides(text).unwrap_err().to_string();
+        assert!(err.contains(needle), "{text:?}: {err}");
+    }
+}
+
+#[test]
+fn applying_overrides_marks_the_source_and_reports_unknown_ids() {
+    let mut advisories = vec![
+        advisory("RUSTSEC-2020-0001", "severity = \"low\""),
+        advisory("RUSTSEC-2020-0002", ""),
+    ];
+    let overrides = parse_severity_overrides(
+        "rustsec_id,severity\nRUSTSEC-2020-0002,critical\nRUSTSEC-1999-0001,low\n",
+    )
+    .unwrap();
+    let unknown = apply_severity_overrides(&mut advisories, &overrides);
+    assert_eq!(unknown, ["RUSTSEC-1999-0001"]);
+    assert_eq!(advisories[0].severity, "LOW");
+    assert_eq!(advisories[0].severity_source, SeveritySource::Advisory);
+    assert_eq!(advisories[1].severity, "CRITICAL");
+    assert_eq!(advisories[1].severity_source, SeveritySource::Override);
+}
+
+#[test]
+fn batch_applies_overrides_before_the_severity_filter() {
+    let mut workdir = Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n\
+         21,2,1.1.0,2020-03-11 00:00:00\n",
+        "version_id,crate_id,req,kind\n20,1,^0.1,0\n21,1,^0.2,0\n",
+    );
+    for (id, extra) in [
+        ("RUSTSEC-2020-0001", "severity = \"low\""),
+        ("RUSTSEC-2020-0002", ""),
+    ] {
+        workdir = workdir.advisory(
+            &format!("vulnlib/{id}.md"),
+            &format!("```toml\n{}```\n\n# vulnlib\n", front_matter(id, extra)),
+        );
+    }
+    let dir = workdir.create("severity");
+    std::fs::write(
+        dir.join("overrides.csv"),
+        "rustsec_id,severity\nRUSTSEC-2020-0002,high\nRUSTSEC-1999-0001,low\n",
+    )
+    .unwrap();
+
+    let out = batch(
+        &dir,
+        &[
+            "--severity-overrides",
+            "overrides.csv",
+            "--min-severity",
+            "medium",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "warning: severity override for RUSTSEC-1999-0001 matches no advisory in advisory-db"
+        ),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("severity overrides: 1 applied from overrides.csv, 1 unknown ids"),
+        "{stderr}"
+    );
+
+    let mut r = csv::Reader::from_path(dir.join("rustsec_rqx2_strict_summary.csv")).unwrap();
+    let headers = r.headers().unwrap().clone();
+    let col = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<(String, String, String)> = r
+        .records()
+        .map(|rec| {
+            let rec = rec.unwrap();
+            (
+                rec[col("rustsec_id")].to_string(),
+                rec[col("severity")].to_string(),
+                rec[col("severity_source")].to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [(
+            "RUSTSEC-2020-0002".to_string(),
+            "HIGH".to_string(),
+            "override".to_string()
+        )]
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn batch_logs_severities_the_previous_cvss_handling_missed() {
+    let mut workdir = Workdir::dump(
+        "id,name,downloads\n1,vulnlib,10\n2,app_a,5\n",
+        "id,crate_id,num,created_at\n\
+         10,1,0.1.0,2020-01-01 00:00:00\n\
+         11,1,0.2.0,2020-03-01 00:00:00\n\
+         20,2,1.0.0,2020-01-15 00:00:00\n",
+        "version_id,crate_id,req,kind\n20,1,^0.1,0\n",
+    );
+    for (id, extra) in [
+        ("RUSTSEC-2020-0001", "severity = \"low\""),
+        ("RUSTSEC-2020-0002", "cvss = \"AV:N/AC:L/Au:N/C:P/I:P/A:P\""),
+        ("RUSTSEC-2020-0003", ""),
+    ] {
+        workdir = workdir.advisory(
+            &format!("vulnlib/{id}.md"),
+            &format!("```toml\n{}```\n\n# vulnlib\n", front_matter(id, extra)),
+        );
+    }
+    let dir = workdir.create("severity-reclassified");
+    std::fs::write(
+        dir.join("overrides.csv"),
+        "rustsec_id,severity\nRUSTSEC-2020-0003,critical\n",
+    )
+    .unwrap();
+
+    let out = batch(&dir, &["--severity-overrides", "overrides.csv"]);
+    assert_eq!(out.status.code(), Some(0), "{out:?}");
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    // The v2 vector was unscored before; the overridden advisory is left out.
+    assert!(
+        stderr.contains(
+            "severity distribution (legacy cvss -> current): LOW=1->1 HIGH=0->1 UNKNOWN=1->0 reclassified=1"
+        ),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,261 @@
+// rqx2_strict over the golden csv dump: the same strict matcher and fix-time lookup as the
+// batch, with fixed versions given on the command line or derived from an advisory.
+
+mod common;
+
+use std::{path::Path, process::Command};
+
+use common::{Workdir, batch, read_columns};
+use time_to_fix_cve::stats::SUMMARY_COLUMNS;
+
+fn run_strict(dir: &Path, args: &[&str]) -> (String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_rqx2_strict"))
+        .current_dir(dir)
+        .args([
+            "--data-source",
+            "csv-dump",
+            "--dump-dir",
+            "dump",
+            "--output",
+            "strict.csv",
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    assert!(out.status.success(), "{stderr}");
+    (
+        std::fs::read_to_string(dir.join("strict.csv")).unwrap(),
+        stderr,
+    )
+}
+
+fn strict(dir: &Path, fixed: &str, extra: &[&str]) -> (String, String) {
+    let mut args = vec![
+        "--cve-id",
+        "CVE-TEST-0001",
+        "--target-crate",
+        "vulnlib",
+        "--fixed-version",
+        fixed,
+        "--vuln-req",
+        "<0.2.0",
+    ];
+    args.extend(extra);
+    run_strict(dir, &args)
+}
+
+fn advisory(id: &str, package: &str, patched: &str) -> String {
+    format!(
+        "```toml\n[advisory]\nid = \"{id}\"\npackage = \"{package}\"\ndate = \"2020-03-05\"\n\n[versions]\npatched = [\"{patched}\"]\n```\n\n# {package}\n"
+    )
+}
+
+fn strict_rustsec_id(dir: &Path, id: &str, extra: &[&str]) -> (String, String) {
+    let mut args = vec!["--rustsec-id", id, "--advisory-db-zip", "advisory-db.zip"];
+    args.extend(extra);
+    run_strict(dir, &args)
+}
+
+#[test]
+fn strict_rows_from_the_dump() {
+    let dir = Workdir::golden().create("strict_bin");
+    let (text, _) = strict(&dir, "0.2.0", &[]);
+    let fix = "2020-03-01 00:00:00 UTC";
+    assert_eq!(
+        text.lines().collect::<Vec<_>>(),
+        [
+            "crate,fix_version,fix_time,lag_days,original_req,fixed_req,matched_fix_version,matched_fix_time".to_string(),
+            format!("aa_tool,0.2.0,{fix},61,^0.1,^0.2,0.2.0,{fix}"),
+            format!("app_a,0.2.0,{fix},10,^0.1,^0.2,0.2.0,{fix}"),
+            format!("app_c,0.2.0,{fix},184,\">=0.1, <0.3\",^0.2.0,0.2.0,{fix}"),
+        ]
+    );
+
+    // Build metadata is ignored when looking up the fix's publish time.
+    let (text, stderr) = strict(&dir, "0.2.0+build.1", &[]);
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(
+        stderr.contains("vulnlib 0.2.0+build.1: using 0.2.0 earliest of 1 variants"),
+        "{stderr}"
+    );
+    assert_eq!(text.lines().count(), 4, "{text}");
+    assert!(
+        text.contains(&format!("app_a,0.2.0+build.1,{fix},10,")),
+        "{text}"
+    );
+}
+
+// The one-row summary lines up with the batch summary header, filled by column name.
+#[test]
+fn summary_row_uses_the_batch_columns() {
+    let dir = Workdir::golden().create("strict_bin_summary");
+    strict(
+        &dir,
+        "0.2.0",
+        &["--summary", "--summary-output", "summary.csv"],
+    );
+    let path = dir.join("summary.csv");
+    let header = std::fs::read_to_string(&path).unwrap();
+    let rows = read_columns(
+        &path,
+        [
+            "cve_id",
+            "target_crate",
+            "fixed_version",
+            "downstream_fixed_cnt",
+            "lag_days_p50",
+            "lag_unit",
+            "keywords",
+            "post_fix_new_dependents",
+        ],
+    );
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(header.lines().next().unwrap(), SUMMARY_COLUMNS.join(","));
+    assert_eq!(
+        rows,
+        [[
+            "CVE-TEST-0001",
+            "vulnlib",
+            "0.2.0",
+            "3",
+            "61.0000",
+            "days",
+            "",
+            ""
+        ]
+        .map(String::from)]
+    );
+}
+
+#[test]
+fn nested_output_directories_with_spaces_and_unicode_are_created() {
+    let dir = Workdir::golden().create("strict_bin_unicode");
+    let base = Path::new("out dir").join("résultats").join("深い");
+    let out = Command::new(env!("CARGO_BIN_EXE_rqx2_strict"))
+        .current_dir(&dir)
+        .args([
+            "--data-source",
+            "csv-dump",
+            "--dump-dir",
+            "dump",
+            "--cve-id",
+            "CVE-TEST-0001",
+            "--target-crate",
+            "vulnlib",
+            "--fixed-version",
+            "0.2.0",
+            "--vuln-req",
+            "<0.2.0",
+            "--summary",
+        ])
+        .arg("--output")
+        .arg(base.join("lags.csv"))
+        .arg("--summary-output")
+        .arg(base.join("summary ü").join("summary.csv"))
+        .output()
+        .unwrap();
+    let created =
+        ["lags.csv", "summary ü/summary.csv"].map(|rel| dir.join(&base).join(rel).is_file());
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(created, [true, true]);
+}
+
+// --rustsec-id goes through the batch's analysis: with pre-releases excluded the patched
+// req names no fixed version, so the first stable release it admits is used.
+#[test]
+fn rustsec_id_falls_back_to_the_first_published_fix() {
+    let dir = Workdir::golden()
+        .advisory(
+            "vulnlib/RUSTSEC-2020-0009.md",
+            &advisory("RUSTSEC-2020-0009", "vulnlib", ">= 0.2.0-rc.1"),
+        )
+        .create("strict_bin_rustsec_id");
+    let (text, stderr) = strict_rustsec_id(
+        &dir,
+        "RUSTSEC-2020-0009",
+        &["--prerelease-policy", "exclude"],
+    );
+    let (manual, _) = strict(&dir, "0.2.0", &[]);
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(
+        stderr.contains("RUSTSEC-2020-0009: crate=vulnlib fixed_versions=1 vuln_versions=2"),
+        "{stderr}"
+    );
+    assert_eq!(text, manual);
+}
+
+#[test]
+fn rustsec_id_resolves_the_crate_like_the_batch() {
+    let dir = Workdir::golden()
+        .advisory(
+            "aa-tool/RUSTSEC-2020-0010.md",
+            &advisory("RUSTSEC-2020-0010", "aa-tool", ">= 0.2.0"),
+        )
+        .advisory(
+            "tool-z/RUSTSEC-2020-0011.md",
+            &advisory("RUSTSEC-2020-0011", "tool-z", ">= 0.2.0"),
+        )
+        .create("strict_bin_resolve");
+    let (_, stderr) = strict_rustsec_id(&dir, "RUSTSEC-2020-0010", &[]);
+    assert!(
+        stderr.contains("RUSTSEC-2020-0010: crate=aa_tool fixed_versions=1 vuln_versions=1"),
+        "{stderr}"
+    );
+    let (_, stderr) = strict_rustsec_id(
+        &dir,
+        "RUSTSEC-2020-0011",
+        &["--package-alias", "tool-z=tool_x"],
+    );
+    assert!(
+        stderr.contains("RUSTSEC-2020-0011: crate=tool_x fixed_versions=1 vuln_versions=1"),
+        "{stderr}"
+    );
+
+    let out = Command::new(env!("CARGO_BIN_EXE_rqx2_strict"))
+        .current_dir(&dir)
+        .args([
+            "--data-source",
+            "csv-dump",
+            "--dump-dir",
+            "dump",
+            "--advisory-db-zip",
+            "advisory-db.zip",
+            "--rustsec-id",
+            "RUSTSEC-2020-0011",
+        ])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("advisory RUSTSEC-2020-0011 not analyzed: crate_not_found"),
+        "{stderr}"
+    );
+}
+
+// Both binaries match adoptions under the same default --fix-match-policy: svc_b's bump to
+// ^0.9.3 reaches the earlier 0.8.7 fix by its minimum, so both report 0.8.7 and 42 days.
+#[test]
+fn rustsec_id_rows_match_the_batch() {
+    let dir = Workdir::golden().create("strict_bin_vs_batch");
+    let out = batch(&dir, &["--only", "RUSTSEC-2022-0003"]);
+    assert!(out.status.success(), "{out:?}");
+    let batch_rows = read_columns(
+        &dir.join("rustsec_rqx2_strict_lags.csv"),
+        ["downstream_crate", "fixed_version", "lag_days"],
+    );
+    strict_rustsec_id(&dir, "RUSTSEC-2022-0003", &[]);
+    let strict_rows = read_columns(
+        &dir.join("strict.csv"),
+        ["crate", "matched_fix_version", "lag_days"],
+    );
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(strict_rows, batch_rows);
+    assert!(
+        strict_rows.contains(&["svc_b", "0.8.7", "42"].map(String::from)),
+        "{strict_rows:?}"
+    );
+}
@@ -0,0 +1,93 @@
+// rqx2_summary_diff over two summaries in tests/fixtures/summary_diff: one advisory added,
+// one removed, two with moved numbers, and a newer run that has an extra column and a
+// schema comment line.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/summary_diff")
+        .join(name)
+}
+
+// (diff CSV, digest) of one run with `extra` flags.
+fn diff(name: &str, extra: &[&str]) -> (String, String) {
+    let dir = env::temp_dir().join(format!("rq2_summary_diff_{name}_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_rqx2_summary_diff"))
+        .current_dir(&dir)
+        .arg("--old")
+        .arg(fixture("old.csv"))
+        .arg("--new")
+        .arg(fixture("new.csv"))
+        .args([
+            "--output",
+            "out/diff.csv",
+            "--digest-output",
+            "out/digest.txt",
+        ])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{out:?}");
+    let read = |file: &str| std::fs::read_to_string(dir.join("out").join(file)).unwrap();
+    let outputs = (read("diff.csv"), read("digest.txt"));
+    std::fs::remove_dir_all(&dir).ok();
+    outputs
+}
+
+#[test]
+fn reports_added_removed_and_changed_advisories() {
+    let (csv, digest) = diff("exact", &[]);
+    assert_eq!(
+        csv,
+        "\
+rustsec_id,status,metric,old,new,delta
+RUSTSEC-2021-0010,added,,,,
+RUSTSEC-2020-0003,removed,,,,
+RUSTSEC-2020-0001,changed,downstream_fixed_cnt,10.0000,13.0000,3.0000
+RUSTSEC-2020-0001,changed,lag_days_p50,12.0000,15.0000,3.0000
+RUSTSEC-2020-0001,changed,lag_days_avg,20.5000,22.0000,1.5000
+RUSTSEC-2020-0002,changed,lag_days_p50,3.0000,3.5000,0.5000
+"
+    );
+    for line in [
+        "(4 advisories)",
+        "added advisories = 1",
+        "removed advisories = 1",
+        "changed advisories = 2",
+        "added:\n  RUSTSEC-2021-0010\n",
+        "removed:\n  RUSTSEC-2020-0003\n",
+        "  lag_days_p50 (2 advisories)\n    RUSTSEC-2020-0001 12.0000 -> 15.0000 (+3.0000)\n    RUSTSEC-2020-0002 3.0000 -> 3.5000 (+0.5000)\n",
+    ] {
+        assert!(digest.contains(line), "{line}: {digest}");
+    }
+    assert!(!digest.contains("lag_unit differs"), "{digest}");
+    assert!(!digest.contains("duplicate rustsec_id"), "{digest}");
+}
+
+#[test]
+fn tolerances_hide_small_moves() {
+    let (csv, digest) = diff(
+        "tolerant",
+        &["--lag-tolerance", "1", "--count-tolerance", "3"],
+    );
+    let changed: Vec<&str> = csv.lines().filter(|l| l.contains(",changed,")).collect();
+    assert_eq!(
+        changed,
+        [
+            "RUSTSEC-2020-0001,changed,lag_days_p50,12.0000,15.0000,3.0000",
+            "RUSTSEC-2020-0001,changed,lag_days_avg,20.5000,22.0000,1.5000",
+        ]
+    );
+    assert!(digest.contains("changed advisories = 1"), "{digest}");
+    assert!(
+        digest.contains("tolerance: downstream_fixed_cnt = 3, lag = 1.0000"),
+        "{digest}"
+    );
+}
@@ -0,0 +1,94 @@
+// Reading dependency requirements: the lowest version a req admits and the shape of the
+// constraint it places on upgrades.
+
+use time_to_fix_cve::pipeline::{ReqShape, classify_req_shape, estimate_min_version};
+
+fn min(req: &str) -> Option<String> {
+    estimate_min_version(req).map(|v| v.to_string())
+}
+
+#[test]
+fn lower_bound_of_each_req_form() {
+    for (req, expected) in [
+        // caret
+        ("^1.2.3", "1.2.3"),
+        ("^0.4", "0.4.0"),
+        ("^0.0.3", "0.0.3"),
+        ("1.2", "1.2.0"),
+        ("0", "0.0.0"),
+        // tilde
+        ("~1.2.3", "1.2.3"),
+        ("~1.2", "1.2.0"),
+        ("~1", "1.0.0"),
+        // wildcard
+        ("*", "0.0.0"),
+        ("1.*", "1.0.0"),
+        ("1.2.*", "1.2.0"),
+        // comparison
+        ("=1.2.3", "1.2.3"),
+        (">=0.3", "0.3.0"),
+        (">0.3", "0.4.0"),
+        (">0.3.1", "0.3.2"),
+        (">1", "2.0.0"),
+        ("<0.2.0", "0.0.0"),
+        ("<=1.5", "0.0.0"),
+        // compound: the tightest lower bound wins, upper bounds are ignored
+        (">=0.3, <0.5", "0.3.0"),
+        (">=0.3, >=0.4.2", "0.4.2"),
+        ("^1.1, >1.2.5", "1.2.6"),
+        ("<2, >=1.0.7", "1.0.7"),
+        // prerelease
+        ("^1.0.0-beta.2", "1.0.0-beta.2"),
+        (">=0.5.0-rc.1, <0.6", "0.5.0-rc.1"),
+        ("=2.0.0-alpha", "2.0.0-alpha"),
+        // surrounding whitespace
+        ("  ^0.8  ", "0.8.0"),
+    ] {
+        assert_eq!(min(req).as_deref(), Some(expected), "{req}");
+    }
+}
+
+#[test]
+fn unparseable_reqs_have_no_lower_bound() {
+    for req in ["", "latest", "^1.2.3.4", ">= x", "1.2.3 || 2"] {
+        assert_eq!(min(req), None, "{req:?}");
+    }
+}
+
+#[test]
+fn req_shapes() {
+    use ReqShape::*;
+    for (req, shape) in [
+        ("=1.2.3", Exact),
+        ("=0.1", Exact),
+        ("~1.2.3", Tilde),
+        ("~0.4", Tilde),
+        ("^0.0.3", Caret00x),
+        ("0.0.7", Caret00x),
+        ("^0.4", Caret0x),
+        ("^0.4.1", Caret0x),
+        ("0.9", Caret0x),
+        ("^0", Caret0x),
+        ("^1.2.3", CaretGe1),
+        ("1", CaretGe1),
+        ("2.0", CaretGe1),
+        ("*", Wildcard),
+        ("1.*", Wildcard),
+        ("0.3.*", Wildcard),
+        (">=0.3, <0.5", RangeWithUpper),
+        ("<0.2.0", RangeWithUpper),
+        ("<=1.5", RangeWithUpper),
+        (">1, <=2.4.1", RangeWithUpper),
+        (">=0.3", GeOnly),
+        (">1.4.2", GeOnly),
+        ("^1.1, <1.4", MultiComparator),
+        (">=0.3, >=0.4", MultiComparator),
+        ("~1.2, =1.2.5", MultiComparator),
+        ("", Unparseable),
+        ("latest", Unparseable),
+        ("1.2.3 || 2", Unparseable),
+        ("  ^0.8  ", Caret0x),
+    ] {
+        assert_eq!(classify_req_shape(req), shape, "{req:?}");
+    }
+}